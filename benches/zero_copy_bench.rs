@@ -1,10 +1,12 @@
 use bytemuck::bytes_of;
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
 use mev_zerocopy_node::payload::DexSwapTx;
+use mev_zerocopy_node::pool_registry::{PoolEntry, PoolTable};
 use mev_zerocopy_node::processor;
-use mev_zerocopy_node::validator::{PoolStateUpdate, validate_pool_update};
+use mev_zerocopy_node::recip::Reciprocal;
+use mev_zerocopy_node::validator::{validate_pool_update, PoolStateUpdate};
 use serde::{Deserialize, Serialize};
-use zerocopy::AsBytes;
+use zerocopy::IntoBytes;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct DexSwapTxSerde {
@@ -56,25 +58,16 @@ fn bench_deserialization(c: &mut Criterion) {
     group.finish();
 }
 
-/// Benchmark 2: zerocopy::ref_from (PoolStateUpdate) vs serde_json for pool updates.
+/// Benchmark 2: zerocopy::ref_from_bytes (PoolStateUpdate) vs serde_json for pool updates.
 fn bench_pool_update_parsing(c: &mut Criterion) {
     let mut group = c.benchmark_group("pool_state_update_parsing");
 
     // zerocopy path: build wire bytes once
-    let update = PoolStateUpdate {
-        pool_address: [0xAB; 20],
-        reserve0_le: 1_000_000_000u64.to_le_bytes(),
-        reserve1_le: 500_000_000u64.to_le_bytes(),
-        slot_le: 12_345_678u64.to_le_bytes(),
-        seq_le: 1u32.to_le_bytes(),
-        _pad: [0u8; 16],
-    };
+    let update = PoolStateUpdate::new([0xAB; 20], 1_000_000_000, 500_000_000, 12_345_678, 1);
     let wire_bytes: Vec<u8> = update.as_bytes().to_vec();
 
     // serde_json path: build JSON bytes once
-    let json_bytes = format!(
-        r#"{{"pool":"0xabababababababababababababababababababababab","reserve0":1000000000,"reserve1":500000000,"slot":12345678,"seq":1}}"#
-    );
+    let json_bytes = r#"{"pool":"0xabababababababababababababababababababababab","reserve0":1000000000,"reserve1":500000000,"slot":12345678,"seq":1}"#.to_string();
 
     group.bench_function("zerocopy_ref_from", |b| {
         b.iter(|| {
@@ -111,5 +104,132 @@ fn bench_full_hot_path(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_deserialization, bench_pool_update_parsing, bench_full_hot_path);
+/// Benchmark 4: per-record `try_from_bytes` loop vs `cast_slice` batch with prefetch.
+fn bench_batch_parse(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch_parse");
+
+    let txs: Vec<DexSwapTx> = (0..64u64)
+        .map(|i| DexSwapTx::from_parts(i, [0xAB; 20], 20_000_000 + i * 1_000_000, 1, (i & 1) as u8))
+        .collect();
+    let wire: &[u8] = bytemuck::cast_slice(&txs);
+
+    group.bench_function("per_record_try_from_bytes", |b| {
+        b.iter(|| {
+            let mut total = 0u64;
+            for chunk in black_box(wire).chunks_exact(DexSwapTx::WIRE_SIZE) {
+                total = total.wrapping_add(processor::process_packet(chunk).unwrap_or(0));
+            }
+            black_box(total);
+        })
+    });
+
+    group.bench_function("cast_slice_prefetch_batch", |b| {
+        b.iter(|| {
+            let mut total = 0u64;
+            processor::process_batch(black_box(wire), |_, profit| {
+                total = total.wrapping_add(profit);
+            });
+            black_box(total);
+        })
+    });
+
+    group.finish();
+}
+
+/// Evict `len` bytes at `ptr` from every cache level.
+fn flush_lines(ptr: *const u8, len: usize) {
+    #[cfg(target_arch = "x86_64")]
+    for off in (0..len).step_by(64) {
+        unsafe { std::arch::x86_64::_mm_clflush(ptr.add(off)) };
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = (ptr, len);
+}
+
+/// Benchmark 5: batch evaluation against a pool table whose slots are cold
+/// (flushed before every batch). Run with and without `--features
+/// pool_prefetch` to compare.
+fn bench_pool_table_batch(c: &mut Criterion) {
+    static POOLS: PoolTable = PoolTable::new();
+    let mut group = c.benchmark_group("pool_table_batch");
+
+    let txs: Vec<DexSwapTx> = (0..32u64)
+        .map(|i| {
+            let mut pool = [0u8; 20];
+            pool[..8].copy_from_slice(&(i + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15).to_le_bytes());
+            DexSwapTx::from_parts(i, pool, 20_000_000 + i * 1_000_000, 1, 0)
+        })
+        .collect();
+    for tx in &txs {
+        POOLS
+            .upsert(processor::mock_pool_entry(tx.pool_address))
+            .expect("non-zero pool address");
+    }
+    let evaluator = processor::DEFAULT_EVALUATOR.with_pools(&POOLS);
+    let wire: &[u8] = bytemuck::cast_slice(&txs);
+
+    let name = if cfg!(feature = "pool_prefetch") {
+        "cold_pools_prefetch"
+    } else {
+        "cold_pools"
+    };
+    group.bench_function(name, |b| {
+        b.iter_batched(
+            || {
+                for tx in &txs {
+                    flush_lines(
+                        POOLS.slot_hint(&tx.pool_address) as *const u8,
+                        size_of::<PoolEntry>(),
+                    );
+                }
+            },
+            |()| {
+                let mut total = 0u64;
+                evaluator.process_batch(black_box(wire), |_, profit| {
+                    total = total.wrapping_add(profit);
+                });
+                black_box(total)
+            },
+            BatchSize::SmallInput,
+        )
+    });
+
+    group.finish();
+}
+
+/// Benchmark 6: the constant-product output quotient, `u128` division vs
+/// a cached reciprocal (what `--features recip_math` uses).
+fn bench_amm_division(c: &mut Criterion) {
+    let mut group = c.benchmark_group("amm_division");
+
+    let (reserve_in, reserve_out, amount_in_with_fee) =
+        (10_000_000_000u64, 5_000_000_000u64, 9_970_000_000u64);
+    let numerator = reserve_out as u128 * amount_in_with_fee as u128;
+    let denominator = reserve_in * 1_000 + amount_in_with_fee;
+    let recip = Reciprocal::new(denominator);
+
+    group.bench_function("u128_div", |b| {
+        b.iter(|| black_box(black_box(numerator) / black_box(denominator) as u128));
+    });
+
+    group.bench_function("cached_reciprocal", |b| {
+        b.iter(|| black_box(black_box(&recip).div(black_box(numerator))));
+    });
+
+    group.bench_function("fresh_reciprocal", |b| {
+        b.iter(|| black_box(Reciprocal::new(black_box(denominator)).div(black_box(numerator))));
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_deserialization,
+    bench_pool_update_parsing,
+    bench_full_hot_path,
+    bench_batch_parse,
+    bench_pool_table_batch,
+    bench_amm_division
+);
 criterion_main!(benches);
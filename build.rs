@@ -0,0 +1,62 @@
+//! Builds the DPDK fast-path shim (`src/dpdk_shim.c`) when the `dpdk`
+//! feature is enabled; a no-op otherwise. Compiler flags and libraries come
+//! from `pkg-config libdpdk`.
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn pkg_config(arg: &str) -> Option<Vec<String>> {
+    let out = Command::new("pkg-config")
+        .args([arg, "libdpdk"])
+        .output()
+        .ok()?;
+    out.status.success().then(|| {
+        String::from_utf8_lossy(&out.stdout)
+            .split_whitespace()
+            .map(String::from)
+            .collect()
+    })
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/dpdk_shim.c");
+    if env::var_os("CARGO_FEATURE_DPDK").is_none() {
+        return;
+    }
+    let (Some(cflags), Some(libs)) = (pkg_config("--cflags"), pkg_config("--libs")) else {
+        // Type-checking (`cargo check`/`clippy --all-features`) still works;
+        // only linking needs DPDK installed.
+        println!("cargo:warning=libdpdk not found by pkg-config; the `dpdk` feature will not link");
+        return;
+    };
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR"));
+    let object = out_dir.join("dpdk_shim.o");
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".into());
+    let status = Command::new(cc)
+        .args(["-O3", "-fPIC", "-c", "src/dpdk_shim.c", "-o"])
+        .arg(&object)
+        .args(&cflags)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "compiling src/dpdk_shim.c failed");
+    let status = Command::new("ar")
+        .arg("crs")
+        .arg(out_dir.join("libmev_dpdk_shim.a"))
+        .arg(&object)
+        .status()
+        .expect("failed to run ar");
+    assert!(status.success(), "archiving the DPDK shim failed");
+
+    println!("cargo:rustc-link-search=native={}", out_dir.display());
+    println!("cargo:rustc-link-lib=static=mev_dpdk_shim");
+    for flag in libs {
+        if let Some(dir) = flag.strip_prefix("-L") {
+            println!("cargo:rustc-link-search=native={dir}");
+        } else if let Some(lib) = flag.strip_prefix("-l") {
+            println!("cargo:rustc-link-lib={lib}");
+        } else {
+            println!("cargo:rustc-link-arg={flag}");
+        }
+    }
+}
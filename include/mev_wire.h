@@ -0,0 +1,253 @@
+#ifndef MEV_WIRE_H
+#define MEV_WIRE_H
+
+/* Generated by cbindgen from src/ffi.rs; do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+/**
+ * A required pointer argument was null.
+ */
+#define MEV_ERR_NULL -1
+
+/**
+ * The output buffer is too small for the message.
+ */
+#define MEV_ERR_BUFFER_TOO_SMALL -2
+
+/**
+ * The pool address is all zero.
+ */
+#define MEV_ERR_ZERO_POOL_ADDRESS -3
+
+/**
+ * A swap with no input amount.
+ */
+#define MEV_ERR_ZERO_AMOUNT -4
+
+/**
+ * Both pool reserves are zero.
+ */
+#define MEV_ERR_ZERO_RESERVES -5
+
+/**
+ * Batch records cannot carry deadline, priority fee or EVM fields.
+ */
+#define MEV_ERR_EXTENDED_IN_BATCH -6
+
+/**
+ * The bytes are not a message of the expected type.
+ */
+#define MEV_ERR_MALFORMED -7
+
+/**
+ * The pool update does not follow `last_seq`.
+ */
+#define MEV_ERR_SEQUENCE_GAP -8
+
+/**
+ * A pool update with a send time but no fee.
+ */
+#define MEV_ERR_SENT_WITHOUT_FEE -9
+
+/**
+ * A victim swap being built; see [`SwapEncoder`].
+ */
+typedef struct MevSwapEncoder MevSwapEncoder;
+
+/**
+ * A decoded `DexSwapTx`, `DexSwapTxExt` or `DexSwapTxEvm`. Fields the
+ * message does not carry are zero.
+ */
+typedef struct MevSwap {
+  uint64_t nonce;
+  uint8_t pool[20];
+  uint64_t amount_in;
+  uint64_t min_amount_out;
+  /**
+   * 0: token0 in, token1 out; 1: the reverse.
+   */
+  uint8_t direction;
+  uint64_t deadline_ms;
+  uint8_t sender[20];
+  uint64_t gas_price;
+  uint64_t priority_fee;
+} MevSwap;
+
+/**
+ * A decoded `PoolStateUpdate`.
+ */
+typedef struct MevPoolUpdate {
+  uint8_t pool[20];
+  uint64_t reserve0;
+  uint64_t reserve1;
+  uint64_t slot;
+  uint32_t seq;
+} MevPoolUpdate;
+
+/**
+ * A decoded `OpportunityReply`.
+ */
+typedef struct MevOpportunityReply {
+  uint64_t nonce;
+  uint64_t profit;
+  uint64_t rx_ts_ns;
+  uint64_t tx_ts_ns;
+  /**
+   * Confidence in `profit`, in basis points.
+   */
+  uint16_t confidence_bps;
+} MevOpportunityReply;
+
+/**
+ * A decoded `OpportunityCancel`.
+ */
+typedef struct MevOpportunityCancel {
+  uint8_t sender[20];
+  uint64_t nonce;
+  /**
+   * 1 replaced, 2 pool moved, 3 expired.
+   */
+  uint8_t reason;
+  uint64_t tx_ts_ns;
+} MevOpportunityCancel;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * A new swap encoder, or null if `pool` is null or `direction` is not 0
+ * or 1. Release it with [`mev_swap_encoder_free`].
+ *
+ * # Safety
+ * `pool` must point to 20 readable bytes.
+ */
+MevSwapEncoder *mev_swap_encoder_new(uint64_t nonce, const uint8_t *pool, uint8_t direction);
+
+/**
+ * # Safety
+ * `encoder` must come from [`mev_swap_encoder_new`] and not be used
+ * afterwards; null is ignored.
+ */
+void mev_swap_encoder_free(MevSwapEncoder *encoder);
+
+/**
+ * # Safety
+ * `encoder` must be a live handle from [`mev_swap_encoder_new`].
+ */
+int32_t mev_swap_encoder_set_amount_in(MevSwapEncoder *encoder, uint64_t amount_in);
+
+/**
+ * # Safety
+ * `encoder` must be a live handle from [`mev_swap_encoder_new`].
+ */
+int32_t mev_swap_encoder_set_min_amount_out(MevSwapEncoder *encoder, uint64_t min_amount_out);
+
+/**
+ * Makes the swap a 64-byte `DexSwapTxExt`.
+ *
+ * # Safety
+ * `encoder` must be a live handle from [`mev_swap_encoder_new`].
+ */
+int32_t mev_swap_encoder_set_deadline_ms(MevSwapEncoder *encoder, uint64_t deadline_ms);
+
+/**
+ * Makes the swap a 64-byte `DexSwapTxExt`.
+ *
+ * # Safety
+ * `encoder` must be a live handle from [`mev_swap_encoder_new`].
+ */
+int32_t mev_swap_encoder_set_priority_fee(MevSwapEncoder *encoder, uint64_t priority_fee);
+
+/**
+ * Makes the swap a 96-byte `DexSwapTxEvm`.
+ *
+ * # Safety
+ * `encoder` must be a live handle from [`mev_swap_encoder_new`] and
+ * `sender` must point to 20 readable bytes.
+ */
+int32_t mev_swap_encoder_set_evm(MevSwapEncoder *encoder, const uint8_t *sender, uint64_t gas_price);
+
+/**
+ * Bytes [`mev_swap_encoder_encode`] writes: 48, 64 or 96.
+ *
+ * # Safety
+ * `encoder` must be a live handle from [`mev_swap_encoder_new`].
+ */
+int32_t mev_swap_encoder_wire_size(const MevSwapEncoder *encoder);
+
+/**
+ * Write the swap to `out`; returns its length.
+ *
+ * # Safety
+ * `encoder` must be a live handle and `out..out+out_len` writable.
+ */
+int32_t mev_swap_encoder_encode(const MevSwapEncoder *encoder, uint8_t *out, size_t out_len);
+
+/**
+ * Append the swap as a batch record to the `*used` bytes already in
+ * `out`, advancing `*used`; returns the records now in the batch.
+ *
+ * # Safety
+ * `encoder` must be a live handle, `out..out+out_len` writable and `used`
+ * a valid pointer.
+ */
+int32_t mev_tx_batch_push(const MevSwapEncoder *encoder, uint8_t *out, size_t out_len, size_t *used);
+
+/**
+ * Write a `PoolStateUpdate` to `out`; returns its length.
+ *
+ * # Safety
+ * `pool` must point to 20 readable bytes and `out..out+out_len` be
+ * writable.
+ */
+int32_t mev_pool_update_encode(const uint8_t *pool,
+                               uint32_t seq,
+                               uint64_t reserve0,
+                               uint64_t reserve1,
+                               uint64_t slot,
+                               uint8_t *out,
+                               size_t out_len);
+
+/**
+ * Decode a swap, telling the variants apart by length: exactly 48, 64 or
+ * 96 bytes. Returns the length.
+ *
+ * # Safety
+ * `data..data+len` must be readable and `out` a valid pointer.
+ */
+int32_t mev_decode_swap(const uint8_t *data, size_t len, MevSwap *out);
+
+/**
+ * Decode and validate a `PoolStateUpdate` as the node does; `last_seq` 0
+ * skips the sequence check. Returns the length.
+ *
+ * # Safety
+ * `data..data+len` must be readable and `out` a valid pointer.
+ */
+int32_t mev_decode_pool_update(const uint8_t *data, size_t len, uint32_t last_seq, MevPoolUpdate *out);
+
+/**
+ * Decode an `OpportunityReply` (exactly 40 bytes). Returns the length.
+ *
+ * # Safety
+ * `data..data+len` must be readable and `out` a valid pointer.
+ */
+int32_t mev_decode_opportunity_reply(const uint8_t *data, size_t len, MevOpportunityReply *out);
+
+/**
+ * Decode an `OpportunityCancel` (exactly 48 bytes, "MVCX" magic).
+ * Returns the length.
+ *
+ * # Safety
+ * `data..data+len` must be readable and `out` a valid pointer.
+ */
+int32_t mev_decode_opportunity_cancel(const uint8_t *data, size_t len, MevOpportunityCancel *out);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* MEV_WIRE_H */
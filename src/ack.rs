@@ -0,0 +1,242 @@
+//! Executor acknowledgements of replies.
+//!
+//! Replies go out over UDP and nothing comes back, so a reply lost on the
+//! way to the executor is lost silently. With acknowledgements on
+//! (`MEV_REPLY_ACK_TTL_MS`), executors answer every reply with a
+//! [`ReplyAck`] naming the opportunity by the victim nonce the reply
+//! carries, and the node keeps each reply it has not seen acknowledged in an
+//! [`AckTracker`]. A reply still unacknowledged after the TTL is counted as
+//! lost. With a secondary endpoint configured it is first sent once more,
+//! there, and counted only if that copy goes unacknowledged too.
+//!
+//! The tracker is a fixed ring. A reply overwritten before its TTL is
+//! counted as unacknowledged, so size it above the replies sent per TTL.
+use crate::processor::Opportunity;
+use bytemuck::{Pod, Zeroable};
+
+/// Magic prefix identifying a `ReplyAck` datagram ("MVAK").
+pub const REPLY_ACK_MAGIC: [u8; 4] = *b"MVAK";
+
+/// Executor acknowledgement of one `OpportunityReply`.
+///
+/// Layout (16 bytes, little-endian):
+///   [0..4]   magic    "MVAK"
+///   [4..12]  nonce    u64 — `nonce` of the reply acknowledged
+///   [12..16] _reserved
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ReplyAck {
+    pub magic: [u8; 4],
+    pub nonce_le: [u8; 8],
+    pub _reserved: [u8; 4],
+}
+
+const _: () = assert!(core::mem::size_of::<ReplyAck>() == 16);
+
+impl ReplyAck {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<ReplyAck>();
+
+    pub fn new(nonce: u64) -> Self {
+        Self {
+            magic: REPLY_ACK_MAGIC,
+            nonce_le: nonce.to_le_bytes(),
+            _reserved: [0; 4],
+        }
+    }
+
+    #[inline(always)]
+    pub fn nonce(&self) -> u64 {
+        u64::from_le_bytes(self.nonce_le)
+    }
+
+    /// Zero-copy parse; returns `None` unless the payload is exactly one
+    /// `ReplyAck` carrying the expected magic.
+    pub fn parse(data: &[u8]) -> Option<&ReplyAck> {
+        if data.len() != Self::WIRE_SIZE {
+            return None;
+        }
+        let ack = bytemuck::try_from_bytes::<ReplyAck>(data).ok()?;
+        (ack.magic == REPLY_ACK_MAGIC).then_some(ack)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AckPolicy {
+    /// How long a reply may go unacknowledged, in milliseconds.
+    pub ttl_ms: u64,
+    /// Where an unacknowledged reply is sent once more, if anywhere.
+    pub retransmit_to: Option<([u8; 4], u16)>,
+}
+
+/// A reply waiting for its acknowledgement.
+#[derive(Clone, Copy, Debug)]
+pub struct Unacked {
+    pub nonce: u64,
+    /// As replied, for a retransmission.
+    pub opportunity: Opportunity,
+    /// RX stamp of the triggering packet.
+    pub rx_ts_ns: u64,
+    /// When the last copy went out.
+    sent_ms: u64,
+    retransmitted: bool,
+}
+
+/// Replies acknowledged, timed out, retransmitted, and acknowledgements
+/// that matched no reply (late, duplicated, or for an overwritten one).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AckCounts {
+    pub acked: u64,
+    pub unacked: u64,
+    pub retransmitted: u64,
+    pub unknown: u64,
+}
+
+pub struct AckTracker<const N: usize> {
+    entries: [Option<Unacked>; N],
+    head: usize,
+    policy: AckPolicy,
+    pub counts: AckCounts,
+}
+
+impl<const N: usize> AckTracker<N> {
+    pub fn new(policy: AckPolicy) -> Self {
+        Self {
+            entries: [None; N],
+            head: 0,
+            policy,
+            counts: AckCounts::default(),
+        }
+    }
+
+    pub fn policy(&self) -> &AckPolicy {
+        &self.policy
+    }
+
+    /// Track a reply sent at unix time `now_ms`.
+    pub fn sent(&mut self, nonce: u64, opportunity: Opportunity, rx_ts_ns: u64, now_ms: u64) {
+        let entry = Unacked {
+            nonce,
+            opportunity,
+            rx_ts_ns,
+            sent_ms: now_ms,
+            retransmitted: false,
+        };
+        if self.entries[self.head].replace(entry).is_some() {
+            self.counts.unacked += 1;
+        }
+        self.head = (self.head + 1) % N;
+    }
+
+    /// An executor acknowledged the reply against `nonce`. `false` if no
+    /// tracked reply carries it.
+    pub fn ack(&mut self, nonce: u64) -> bool {
+        match self
+            .entries
+            .iter_mut()
+            .find(|e| e.is_some_and(|e| e.nonce == nonce))
+        {
+            Some(entry) => {
+                *entry = None;
+                self.counts.acked += 1;
+                true
+            }
+            None => {
+                self.counts.unknown += 1;
+                false
+            }
+        }
+    }
+
+    /// At unix time `now_ms`, hand each reply past its TTL to `retransmit`
+    /// if a secondary endpoint is configured and it was not retransmitted
+    /// yet; it then gets another TTL. The others, and those `retransmit`
+    /// returns `false` for (TX buffer full), are counted unacknowledged and
+    /// dropped. Returns the number dropped.
+    pub fn sweep(&mut self, now_ms: u64, mut retransmit: impl FnMut(&Unacked) -> bool) -> usize {
+        let mut dropped = 0;
+        for entry in &mut self.entries {
+            let Some(unacked) = entry else { continue };
+            if now_ms.saturating_sub(unacked.sent_ms) < self.policy.ttl_ms {
+                continue;
+            }
+            if !unacked.retransmitted && self.policy.retransmit_to.is_some() && retransmit(unacked)
+            {
+                unacked.retransmitted = true;
+                unacked.sent_ms = now_ms;
+                self.counts.retransmitted += 1;
+            } else {
+                *entry = None;
+                dropped += 1;
+            }
+        }
+        self.counts.unacked += dropped as u64;
+        dropped
+    }
+
+    /// Replies awaiting an acknowledgement.
+    pub fn len(&self) -> usize {
+        self.entries.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unacked_replies_are_retransmitted_once_then_counted() {
+        let policy = AckPolicy {
+            ttl_ms: 50,
+            retransmit_to: Some(([10, 0, 0, 9], 9100)),
+        };
+        let mut acks = AckTracker::<4>::new(policy);
+        for nonce in 1..=3 {
+            acks.sent(nonce, Opportunity::unscored(nonce), 7, 1_000);
+        }
+        assert!(acks.ack(2));
+        assert!(!acks.ack(2), "acknowledged once");
+        assert_eq!(
+            ReplyAck::parse(bytemuck::bytes_of(&ReplyAck::new(2))).map(ReplyAck::nonce),
+            Some(2)
+        );
+        assert!(ReplyAck::parse(&[0; 16]).is_none());
+
+        // Within the TTL nothing moves; past it, 1 and 3 get a second copy.
+        assert_eq!(acks.sweep(1_049, |_| panic!("not due")), 0);
+        let mut resent = Vec::new();
+        assert_eq!(
+            acks.sweep(1_050, |u| {
+                resent.push((u.nonce, u.opportunity.profit, u.rx_ts_ns));
+                u.nonce == 1
+            }),
+            1,
+            "3 found the TX buffer full"
+        );
+        assert_eq!(resent, [(1, 1, 7), (3, 3, 7)]);
+        assert_eq!(acks.sweep(1_099, |_| true), 0);
+        assert_eq!(acks.sweep(1_100, |_| panic!("retransmitted once")), 1);
+        assert!(acks.is_empty());
+
+        // Without a secondary endpoint, an overwritten reply also counts.
+        let mut acks = AckTracker::<1>::new(AckPolicy {
+            retransmit_to: None,
+            ..policy
+        });
+        acks.sent(1, Opportunity::unscored(1), 0, 0);
+        acks.sent(2, Opportunity::unscored(1), 0, 0);
+        assert_eq!(acks.sweep(50, |_| panic!("nowhere to retransmit")), 1);
+        assert_eq!(
+            acks.counts,
+            AckCounts {
+                acked: 0,
+                unacked: 2,
+                retransmitted: 0,
+                unknown: 0
+            }
+        );
+    }
+}
@@ -0,0 +1,602 @@
+//! Authenticated admin control socket.
+//!
+//! A Unix stream socket (`MEV_ADMIN_SOCKET`) accepting one command per line,
+//! polled from the node's main loop without blocking it. Every connection
+//! starts with the role its peer's uid is granted (`SO_PEERCRED`,
+//! `MEV_ADMIN_UIDS`), and `auth <token>` can raise it to the role of a
+//! configured token (`MEV_ADMIN_TOKENS`). Each command needs a role:
+//!
+//! | Command | Role |
+//! |---|---|
+//! | `stats`, `status`, `profile` (stage report) | `readonly` |
+//! | `pools`, `pools bin` (pool table dump) | `readonly` |
+//! | `quarantine` (malformed frame dump) | `readonly` |
+//! | `sources` (per-source breakdown) | `readonly` |
+//! | `errors` (errors carried on from, by code) | `readonly` |
+//! | `pnl` (edge and realized results by token) | `readonly` |
+//! | `halt`, `resume` (kill switch) | `operator` |
+//! | `strategies` (which are enabled) | `readonly` |
+//! | `strategy enable <name>`, `strategy disable <name>` | `operator` |
+//! | `profile start`, `profile stop` | `operator` |
+//! | `quarantine clear` | `operator` |
+//! | `chaos` (faults injected, `chaos` feature) | `readonly` |
+//! | `chaos rx-drop`, `tx-delay`, `corrupt-seq`, `stall`, `off` | `operator` |
+//! | `privacy` (private-routing signature lists, as JSON) | `readonly` |
+//! | `privacy add\|remove builder\|router <address>`, `privacy zero-tip on\|off` | `operator` |
+//!
+//! so a dashboard holding a read-only token or uid can watch the node but
+//! not stop or restart trading. Commands above the connection's role are
+//! answered `denied` and counted.
+use crate::chaos::FaultCommand;
+use crate::privacy::PrivacyCommand;
+use crate::processor::Strategy;
+use std::io::{ErrorKind, Read, Write};
+use std::os::fd::{AsFd, BorrowedFd};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+
+/// Concurrent admin connections; further ones are refused.
+pub const MAX_SESSIONS: usize = 4;
+/// Maximum configured tokens and uids, each.
+pub const MAX_GRANTS: usize = 8;
+/// Longest accepted request line.
+const LINE_MAX: usize = 256;
+/// Reply bytes a connection may leave unread before it is dropped.
+const PENDING_MAX: usize = 16 << 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    ReadOnly,
+    Operator,
+}
+
+impl Role {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "ro" | "readonly" => Some(Self::ReadOnly),
+            "op" | "operator" => Some(Self::Operator),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::ReadOnly => "readonly",
+            Self::Operator => "operator",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Stats,
+    Status,
+    /// Stop emitting opportunities; cancellations still go out.
+    Halt,
+    Resume,
+    /// Which strategies are enabled, as JSON.
+    Strategies,
+    /// Switch one strategy on or off, leaving the others as they are.
+    SetStrategy(Strategy, bool),
+    /// Report the stage profiler's attribution so far.
+    Profile,
+    /// Start the stage profiler afresh.
+    ProfileStart,
+    ProfileStop,
+    /// The pool table as JSON.
+    Pools,
+    /// The pool table as `PoolStateUpdate` records, hex-encoded.
+    PoolsBinary,
+    /// The malformed-frame quarantine as JSON.
+    Quarantine,
+    /// Per-source traffic and opportunities as JSON.
+    Sources,
+    /// Errors the node carried on from, by code, as JSON.
+    Errors,
+    /// Pool-state sources: freshness and arbitration counts, as JSON.
+    PoolSources,
+    /// Pools seen in victims but missing from the table, as JSON.
+    UnknownPools,
+    /// Detected and realized edge by token and in the numéraire, as JSON.
+    Pnl,
+    QuarantineClear,
+    /// Fault injection: show or change the faults in effect.
+    Chaos(FaultCommand),
+    /// Show or edit the private-routing filter.
+    Privacy(PrivacyCommand),
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Option<Self> {
+        match line {
+            "stats" => Some(Self::Stats),
+            "status" => Some(Self::Status),
+            "halt" => Some(Self::Halt),
+            "resume" => Some(Self::Resume),
+            "strategies" => Some(Self::Strategies),
+            "profile" => Some(Self::Profile),
+            "profile start" => Some(Self::ProfileStart),
+            "profile stop" => Some(Self::ProfileStop),
+            "pools" => Some(Self::Pools),
+            "pools bin" => Some(Self::PoolsBinary),
+            "quarantine" => Some(Self::Quarantine),
+            "quarantine clear" => Some(Self::QuarantineClear),
+            "sources" => Some(Self::Sources),
+            "errors" => Some(Self::Errors),
+            "pool sources" => Some(Self::PoolSources),
+            "pools unknown" => Some(Self::UnknownPools),
+            "pnl" => Some(Self::Pnl),
+            "chaos" => Some(Self::Chaos(FaultCommand::Show)),
+            "privacy" => Some(Self::Privacy(PrivacyCommand::Show)),
+            _ => match line.split_once(' ')? {
+                ("chaos", args) => FaultCommand::parse(args).map(Self::Chaos),
+                ("privacy", args) => PrivacyCommand::parse(args).map(Self::Privacy),
+                ("strategy", args) => match args.split_once(' ')? {
+                    ("enable", name) => {
+                        Strategy::from_name(name).map(|s| Self::SetStrategy(s, true))
+                    }
+                    ("disable", name) => {
+                        Strategy::from_name(name).map(|s| Self::SetStrategy(s, false))
+                    }
+                    _ => None,
+                },
+                _ => None,
+            },
+        }
+    }
+
+    pub const fn required_role(self) -> Role {
+        match self {
+            Self::Stats
+            | Self::Status
+            | Self::Profile
+            | Self::Pools
+            | Self::PoolsBinary
+            | Self::Quarantine
+            | Self::Sources
+            | Self::Errors
+            | Self::PoolSources
+            | Self::UnknownPools
+            | Self::Pnl
+            | Self::Strategies => Role::ReadOnly,
+            Self::Chaos(fault) if fault.is_readonly() => Role::ReadOnly,
+            Self::Privacy(cmd) if cmd.is_readonly() => Role::ReadOnly,
+            Self::Halt
+            | Self::Resume
+            | Self::ProfileStart
+            | Self::ProfileStop
+            | Self::QuarantineClear
+            | Self::SetStrategy(..)
+            | Self::Chaos(_)
+            | Self::Privacy(_) => Role::Operator,
+        }
+    }
+}
+
+/// Who gets which role.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct AdminAuth {
+    pub tokens: heapless::Vec<(Role, String), MAX_GRANTS>,
+    pub uids: heapless::Vec<(u32, Role), MAX_GRANTS>,
+}
+
+/// Tokens are secrets: only their roles are shown.
+impl core::fmt::Debug for AdminAuth {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let tokens: heapless::Vec<Role, MAX_GRANTS> =
+            self.tokens.iter().map(|(role, _)| *role).collect();
+        f.debug_struct("AdminAuth")
+            .field("tokens", &tokens)
+            .field("uids", &self.uids)
+            .finish()
+    }
+}
+
+impl AdminAuth {
+    pub fn uid_role(&self, uid: u32) -> Option<Role> {
+        self.uids
+            .iter()
+            .find(|(u, _)| *u == uid)
+            .map(|&(_, role)| role)
+    }
+
+    /// Role of `token`. Every configured token is compared in full, so the
+    /// time taken does not reveal how much of a guess was right.
+    pub fn token_role(&self, token: &str) -> Option<Role> {
+        self.tokens.iter().fold(None, |found, (role, expected)| {
+            let same = expected.len() == token.len()
+                && expected
+                    .bytes()
+                    .zip(token.bytes())
+                    .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                    == 0;
+            if same {
+                Some(*role)
+            } else {
+                found
+            }
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum AdminError {
+    Bind(std::io::Error),
+}
+
+impl core::fmt::Display for AdminError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Bind(e) => write!(f, "admin socket bind failed ({e})"),
+        }
+    }
+}
+
+impl std::error::Error for AdminError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Bind(e) => Some(e),
+        }
+    }
+}
+
+struct Session {
+    stream: UnixStream,
+    role: Option<Role>,
+    buf: [u8; LINE_MAX],
+    len: usize,
+    /// Reply bytes the socket has not taken yet.
+    out: Vec<u8>,
+}
+
+impl Session {
+    /// Write out what the socket takes; `false` on a broken connection.
+    fn flush(&mut self) -> bool {
+        while !self.out.is_empty() {
+            match self.stream.write(&self.out) {
+                Ok(0) => return false,
+                Ok(n) => {
+                    self.out.drain(..n);
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(_) => return false,
+            }
+        }
+        self.out.len() <= PENDING_MAX
+    }
+}
+
+/// The listening admin socket and its open connections.
+pub struct AdminServer {
+    listener: UnixListener,
+    path: String,
+    auth: AdminAuth,
+    sessions: Vec<Session>,
+    /// Remove the socket file on drop; off once it is handed over.
+    unlink: bool,
+    /// Commands refused for lack of a role.
+    pub denied: u64,
+}
+
+impl AdminServer {
+    /// Bind `path` (replacing a stale socket file), owner/group accessible.
+    pub fn bind(path: &str, auth: AdminAuth) -> Result<Self, AdminError> {
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path).map_err(AdminError::Bind)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o660))
+            .map_err(AdminError::Bind)?;
+        Self::adopt(listener, path, auth)
+    }
+
+    /// Serve on `listener`, already bound to `path`, e.g. one handed over
+    /// by the node this one replaces.
+    pub fn adopt(listener: UnixListener, path: &str, auth: AdminAuth) -> Result<Self, AdminError> {
+        listener.set_nonblocking(true).map_err(AdminError::Bind)?;
+        let sessions = Vec::with_capacity(MAX_SESSIONS);
+        Ok(Self {
+            listener,
+            path: path.to_string(),
+            auth,
+            sessions,
+            unlink: true,
+            denied: 0,
+        })
+    }
+
+    /// Leave the socket file in place on drop: it now belongs to the node
+    /// the listener was handed to.
+    pub fn release(&mut self) {
+        self.unlink = false;
+    }
+
+    /// Accept new connections and answer every complete request line,
+    /// running permitted commands through `handler`. Never blocks.
+    pub fn poll(&mut self, mut handler: impl FnMut(Command) -> String) {
+        while let Ok((stream, _)) = self.listener.accept() {
+            self.accept(stream);
+        }
+        let mut i = 0;
+        while i < self.sessions.len() {
+            if self.serve(i, &mut handler) {
+                i += 1;
+            } else {
+                self.sessions.swap_remove(i);
+            }
+        }
+    }
+
+    fn accept(&mut self, mut stream: UnixStream) {
+        if self.sessions.len() == MAX_SESSIONS || stream.set_nonblocking(true).is_err() {
+            let _ = stream.write_all(b"error busy\n");
+            return;
+        }
+        let role = peer_uid(&stream).and_then(|uid| self.auth.uid_role(uid));
+        self.sessions.push(Session {
+            stream,
+            role,
+            buf: [0; LINE_MAX],
+            len: 0,
+            out: Vec::new(),
+        });
+    }
+
+    /// Serve session `i`; `false` once it should be closed.
+    fn serve(&mut self, i: usize, handler: &mut impl FnMut(Command) -> String) -> bool {
+        let session = &mut self.sessions[i];
+        match session.stream.read(&mut session.buf[session.len..]) {
+            // The peer is done sending; finish the replies it is owed.
+            Ok(0) => return !session.out.is_empty() && session.flush(),
+            Ok(n) => session.len += n,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return session.flush(),
+            Err(_) => return false,
+        }
+        while let Some(end) = session.buf[..session.len].iter().position(|&b| b == b'\n') {
+            let line = String::from_utf8_lossy(&session.buf[..end])
+                .trim()
+                .to_string();
+            session.buf.copy_within(end + 1..session.len, 0);
+            session.len -= end + 1;
+            let reply = match line.split_once(' ') {
+                Some(("auth", token)) => match self.auth.token_role(token.trim()) {
+                    Some(role) => {
+                        let role = session.role.map_or(role, |current| current.max(role));
+                        session.role = Some(role);
+                        format!("ok {}", role.name())
+                    }
+                    None => {
+                        self.denied += 1;
+                        log::warn!("admin: authentication failed");
+                        "denied".to_string()
+                    }
+                },
+                _ => match Command::parse(&line) {
+                    None => "error unknown command".to_string(),
+                    Some(cmd) if session.role.is_some_and(|role| role >= cmd.required_role()) => {
+                        handler(cmd)
+                    }
+                    Some(cmd) => {
+                        self.denied += 1;
+                        log::warn!("admin: {cmd:?} denied to role {:?}", session.role);
+                        "denied".to_string()
+                    }
+                },
+            };
+            session.out.extend_from_slice(reply.as_bytes());
+            session.out.push(b'\n');
+        }
+        // A full buffer without a newline is not a request.
+        session.flush() && session.len < LINE_MAX
+    }
+}
+
+impl AsFd for AdminServer {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.listener.as_fd()
+    }
+}
+
+impl Drop for AdminServer {
+    fn drop(&mut self) {
+        if self.unlink {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Send `command` to the admin socket at `path`, after `auth <token>` if
+/// given, and return the node's reply without its newline. A blocking
+/// client, for command-line tools.
+pub fn query(path: &str, token: Option<&str>, command: &str) -> std::io::Result<String> {
+    use std::io::{BufRead, BufReader};
+    let mut stream = UnixStream::connect(path)?;
+    let mut request = String::new();
+    if let Some(token) = token {
+        request = format!("auth {token}\n");
+    }
+    request.push_str(command);
+    request.push('\n');
+    stream.write_all(request.as_bytes())?;
+    let mut lines = BufReader::new(stream).lines();
+    if token.is_some() {
+        lines.next().transpose()?;
+    }
+    lines
+        .next()
+        .transpose()?
+        .ok_or_else(|| ErrorKind::UnexpectedEof.into())
+}
+
+/// Uid of the process on the other end of `stream`.
+fn peer_uid(stream: &UnixStream) -> Option<u32> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = core::mem::size_of::<libc::ucred>() as libc::socklen_t;
+    let rc = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut _ as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    (rc == 0).then_some(cred.uid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+
+    #[test]
+    fn tokens_and_uids_grant_roles() {
+        let mut auth = AdminAuth::default();
+        auth.tokens
+            .push((Role::ReadOnly, "dash".to_string()))
+            .unwrap();
+        auth.tokens
+            .push((Role::Operator, "s3cret".to_string()))
+            .unwrap();
+        auth.uids.push((1000, Role::ReadOnly)).unwrap();
+        assert_eq!(auth.token_role("s3cret"), Some(Role::Operator));
+        assert_eq!(auth.token_role("s3cre"), None);
+        assert_eq!(auth.uid_role(1000), Some(Role::ReadOnly));
+        assert_eq!(auth.uid_role(0), None);
+        assert_eq!(Command::Halt.required_role(), Role::Operator);
+        assert_eq!(
+            Command::parse("profile start").map(Command::required_role),
+            Some(Role::Operator)
+        );
+        assert_eq!(
+            Command::parse("profile").map(Command::required_role),
+            Some(Role::ReadOnly)
+        );
+        assert_eq!(
+            Command::parse("quarantine clear").map(Command::required_role),
+            Some(Role::Operator)
+        );
+        assert_eq!(
+            Command::parse("errors").map(Command::required_role),
+            Some(Role::ReadOnly)
+        );
+        assert_eq!(
+            Command::parse("pool sources").map(Command::required_role),
+            Some(Role::ReadOnly)
+        );
+        assert_eq!(
+            Command::parse("pools unknown").map(Command::required_role),
+            Some(Role::ReadOnly)
+        );
+        assert_eq!(
+            Command::parse("pnl").map(Command::required_role),
+            Some(Role::ReadOnly)
+        );
+        assert_eq!(
+            Command::parse("chaos").map(Command::required_role),
+            Some(Role::ReadOnly)
+        );
+        assert_eq!(
+            Command::parse("chaos stall 200").map(Command::required_role),
+            Some(Role::Operator)
+        );
+        assert_eq!(Command::parse("chaos stall x"), None);
+        assert_eq!(
+            Command::parse("privacy").map(Command::required_role),
+            Some(Role::ReadOnly)
+        );
+        assert_eq!(
+            Command::parse("privacy zero-tip off").map(Command::required_role),
+            Some(Role::Operator)
+        );
+        assert_eq!(
+            Command::parse("strategies").map(Command::required_role),
+            Some(Role::ReadOnly)
+        );
+        let disable = Command::parse("strategy disable book_taker_arb");
+        assert_eq!(
+            disable,
+            Some(Command::SetStrategy(Strategy::BookTakerArb, false))
+        );
+        assert_eq!(disable.map(Command::required_role), Some(Role::Operator));
+        assert_eq!(Command::parse("strategy disable everything"), None);
+        assert!(Role::Operator > Role::ReadOnly);
+        assert!(!format!("{auth:?}").contains("s3cret"));
+    }
+
+    #[test]
+    fn commands_need_their_role() {
+        let path = std::env::temp_dir().join(format!("mev-admin-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut auth = AdminAuth::default();
+        auth.tokens
+            .push((Role::Operator, "s3cret".to_string()))
+            .unwrap();
+        let uid = unsafe { libc::geteuid() };
+        auth.uids.push((uid, Role::ReadOnly)).unwrap();
+        let mut server = AdminServer::bind(path, auth).unwrap();
+
+        let mut client = UnixStream::connect(path).unwrap();
+        client
+            .write_all(b"status\nhalt\nauth nope\nauth s3cret\nhalt\nreboot\n")
+            .unwrap();
+        let mut halted = false;
+        for _ in 0..100 {
+            server.poll(|cmd| match cmd {
+                Command::Halt => {
+                    halted = true;
+                    "ok halted".to_string()
+                }
+                _ => "ok running".to_string(),
+            });
+            if halted {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        let lines: Vec<String> = BufReader::new(client)
+            .lines()
+            .take(6)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                "ok running",
+                "denied",
+                "denied",
+                "ok operator",
+                "ok halted",
+                "error unknown command"
+            ]
+        );
+        assert_eq!(server.denied, 2);
+        drop(server);
+        assert!(!std::path::Path::new(path).exists());
+    }
+
+    #[test]
+    fn replies_larger_than_the_socket_buffer_arrive_whole() {
+        let path = std::env::temp_dir().join(format!("mev-admin-big-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let mut auth = AdminAuth::default();
+        auth.tokens
+            .push((Role::ReadOnly, "dash".to_string()))
+            .unwrap();
+        let mut server = AdminServer::bind(&path, auth).unwrap();
+        let client = {
+            let path = path.clone();
+            std::thread::spawn(move || query(&path, Some("dash"), "pools"))
+        };
+        let dump = "p".repeat(4 << 20);
+        while !client.is_finished() {
+            server.poll(|_| format!("ok {dump}"));
+            std::thread::sleep(std::time::Duration::from_micros(100));
+        }
+        let reply = client.join().unwrap().unwrap();
+        assert_eq!(reply.len(), 3 + dump.len());
+    }
+}
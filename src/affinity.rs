@@ -1,3 +1,12 @@
+/// Where a thread runs; see [`crate::cores`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoreSlot {
+    /// On this core.
+    Core(usize),
+    /// On the highest-numbered core other than this one.
+    AwayFrom(usize),
+}
+
 pub fn pin_current_thread_to(core_index: usize) -> bool {
     let Some(cores) = core_affinity::get_core_ids() else {
         return false;
@@ -7,3 +16,33 @@ pub fn pin_current_thread_to(core_index: usize) -> bool {
     };
     core_affinity::set_for_current(core_id)
 }
+
+/// Pin the current thread to the highest-numbered core other than
+/// `core_index`, keeping background work off the processing core.
+pub fn pin_current_thread_away_from(core_index: usize) -> bool {
+    let Some(cores) = core_affinity::get_core_ids() else {
+        return false;
+    };
+    let Some(core_id) = cores
+        .into_iter()
+        .filter(|c| c.id != core_index)
+        .max_by_key(|c| c.id)
+    else {
+        return false;
+    };
+    core_affinity::set_for_current(core_id)
+}
+
+/// Pin the current thread to `slot`.
+pub fn pin_current_thread(slot: CoreSlot) -> bool {
+    match slot {
+        CoreSlot::Core(core) => pin_current_thread_to(core),
+        CoreSlot::AwayFrom(core) => pin_current_thread_away_from(core),
+    }
+}
+
+/// Cores this process may run on; empty if they cannot be read.
+pub fn online_cores() -> Vec<usize> {
+    core_affinity::get_core_ids()
+        .map_or_else(Vec::new, |cores| cores.into_iter().map(|c| c.id).collect())
+}
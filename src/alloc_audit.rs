@@ -0,0 +1,165 @@
+//! Hot-path allocation audit (`alloc_audit` feature).
+//!
+//! The packet path is meant to be allocation-free. With the feature on, the
+//! binary installs [`AuditAllocator`] as its global allocator, and every
+//! allocation made while a [`HotPath`] guard is alive on the same thread is
+//! counted — or, with [`AuditPolicy::Abort`], stops the process with a
+//! message on stderr. (Panicking out of an allocator is undefined behaviour,
+//! so the trap aborts.)
+//!
+//! Without the feature the guard is a no-op and compiles away.
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// What an allocation on the hot path does.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AuditPolicy {
+    /// Count it in [`hot_path_allocations`] and carry on.
+    #[default]
+    Count,
+    /// Abort the process.
+    Abort,
+}
+
+impl AuditPolicy {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "count" => Some(Self::Count),
+            "abort" | "panic" => Some(Self::Abort),
+            _ => None,
+        }
+    }
+}
+
+static ABORT: AtomicBool = AtomicBool::new(false);
+static HOT_PATH_ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static IN_HOT_PATH: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn set_policy(policy: AuditPolicy) {
+    ABORT.store(policy == AuditPolicy::Abort, Ordering::Relaxed);
+}
+
+/// Allocations seen inside a [`HotPath`] since startup.
+pub fn hot_path_allocations() -> u64 {
+    HOT_PATH_ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+/// Marks the current thread as on the hot path until dropped. Guards do
+/// not nest.
+pub struct HotPath(());
+
+impl HotPath {
+    #[inline(always)]
+    pub fn enter() -> Self {
+        #[cfg(feature = "alloc_audit")]
+        IN_HOT_PATH.with(|flag| flag.set(true));
+        Self(())
+    }
+}
+
+impl Drop for HotPath {
+    #[inline(always)]
+    fn drop(&mut self) {
+        #[cfg(feature = "alloc_audit")]
+        IN_HOT_PATH.with(|flag| flag.set(false));
+    }
+}
+
+/// Global allocator wrapper that checks the hot-path marker.
+pub struct AuditAllocator<A = System> {
+    inner: A,
+}
+
+impl AuditAllocator {
+    pub const fn new() -> Self {
+        Self { inner: System }
+    }
+}
+
+impl Default for AuditAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A> AuditAllocator<A> {
+    #[inline(always)]
+    fn check(&self, layout: Layout) {
+        // `try_with`: the flag may already be gone during thread teardown.
+        if !IN_HOT_PATH.try_with(Cell::get).unwrap_or(false) {
+            return;
+        }
+        HOT_PATH_ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        if ABORT.load(Ordering::Relaxed) {
+            trap(layout);
+        }
+    }
+}
+
+/// Report and abort, without allocating.
+#[cold]
+fn trap(layout: Layout) -> ! {
+    let mut msg = *b"alloc_audit: allocation of 0x0000000000000000 bytes on the hot path\n";
+    let mut size = layout.size();
+    for digit in msg[29..45].iter_mut().rev() {
+        *digit = b"0123456789abcdef"[size & 0xf];
+        size >>= 4;
+    }
+    unsafe { libc::write(2, msg.as_ptr() as *const libc::c_void, msg.len()) };
+    std::process::abort();
+}
+
+// SAFETY: forwards every call to `inner` unchanged.
+unsafe impl<A: GlobalAlloc> GlobalAlloc for AuditAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.check(layout);
+        self.inner.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        self.check(layout);
+        self.inner.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        self.check(layout);
+        self.inner.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_names() {
+        assert_eq!(AuditPolicy::parse("Abort"), Some(AuditPolicy::Abort));
+        assert_eq!(AuditPolicy::parse("panic"), Some(AuditPolicy::Abort));
+        assert_eq!(AuditPolicy::parse("count"), Some(AuditPolicy::Count));
+        assert_eq!(AuditPolicy::parse("log"), None);
+    }
+
+    #[cfg(feature = "alloc_audit")]
+    #[test]
+    fn only_allocations_inside_the_guard_are_counted() {
+        let allocator = AuditAllocator::new();
+        let layout = Layout::from_size_align(64, 8).unwrap();
+        let before = hot_path_allocations();
+        unsafe {
+            allocator.dealloc(allocator.alloc(layout), layout);
+            let hot = HotPath::enter();
+            allocator.dealloc(allocator.alloc(layout), layout);
+            drop(hot);
+            allocator.dealloc(allocator.alloc(layout), layout);
+        }
+        assert_eq!(hot_path_allocations() - before, 1);
+    }
+}
@@ -0,0 +1,348 @@
+//! Rate-of-update anomalies per pool.
+//!
+//! A pool being manipulated tends to show it in its feed before it shows
+//! in a victim: updates arrive in a burst, or its reserves swing far more
+//! per update than they usually do. Priced against such a pool, a
+//! sandwich is as likely to be bait as an opportunity.
+//!
+//! [`AnomalyDetector`] keeps two exponentially weighted averages per pool
+//! of the time between updates and of the reserve move per update: a fast
+//! one (the last few updates) and a slow one (the pool's norm). Once a pool
+//! has had `WARMUP_UPDATES` updates, it is flagged while
+//! - updates arrive `factor` times faster than its norm, or
+//! - its reserves move `factor` times more than its norm, and at least
+//!   `min_move_bps`, so a pool that never moves does not flag on its first
+//!   tick.
+//!
+//! With `suppress_ms` set, a flagged pool is entered in [`SuppressedPools`]
+//! and every strategy skips it until it has been calm for `suppress_ms`.
+//! Memory is fixed: `MAX_WATCHED_POOLS` pools are watched, the rest not.
+use crate::pool_registry::{pool_key, PoolAddress};
+use crate::processor::AmmPoolState;
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use heapless::FnvIndexMap;
+
+/// Pools whose norms are kept.
+pub const MAX_WATCHED_POOLS: usize = 1024;
+/// Pools that can be suppressed at once.
+pub const MAX_SUPPRESSED_POOLS: usize = 16;
+/// Updates before a pool's norms are trusted.
+const WARMUP_UPDATES: u32 = 32;
+/// Weight of a new sample in the fast averages: 1/4.
+const FAST_SHIFT: u32 = 2;
+/// Weight of a new sample in the slow averages: 1/64.
+const SLOW_SHIFT: u32 = 6;
+/// Fractional bits the averages are kept with.
+const SCALE_BITS: u32 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AnomalyConfig {
+    /// How far past its norm a pool's update rate or reserve moves must be.
+    pub factor: u32,
+    /// Smallest average move, in basis points, that flags.
+    pub min_move_bps: u32,
+    /// How long a flagged pool stays suppressed after its last anomalous
+    /// update; 0 only flags.
+    pub suppress_ms: u64,
+}
+
+impl Default for AnomalyConfig {
+    fn default() -> Self {
+        Self {
+            factor: 8,
+            min_move_bps: 50,
+            suppress_ms: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anomaly {
+    /// Updates arrive far faster than usual.
+    Rate,
+    /// Reserves move far more per update than usual.
+    Volatility,
+}
+
+impl Anomaly {
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Rate => "rate",
+            Self::Volatility => "volatility",
+        }
+    }
+}
+
+/// Averages of one pool, scaled by `2^SCALE_BITS`.
+#[derive(Clone, Copy, Debug, Default)]
+struct PoolNorms {
+    last_ns: u64,
+    reserve0: u64,
+    updates: u32,
+    fast_interval_us: u64,
+    slow_interval_us: u64,
+    fast_move_bps: u64,
+    slow_move_bps: u64,
+    /// Suppressed until this time; 0 if not.
+    suppressed_until_ns: u64,
+    flagged: bool,
+}
+
+#[inline(always)]
+fn ewma(average: &mut u64, sample: u64, shift: u32) {
+    let sample = sample.saturating_mul(1 << SCALE_BITS);
+    *average = if sample >= *average {
+        *average + ((sample - *average) >> shift)
+    } else {
+        *average - ((*average - sample) >> shift)
+    };
+}
+
+pub struct AnomalyDetector {
+    config: AnomalyConfig,
+    pools: FnvIndexMap<PoolAddress, PoolNorms, MAX_WATCHED_POOLS>,
+    /// Updates on pools that found the map full.
+    pub unwatched: u64,
+    /// Times a pool was flagged.
+    pub flagged: u64,
+}
+
+impl AnomalyDetector {
+    pub fn new(config: AnomalyConfig) -> Self {
+        Self {
+            config,
+            pools: FnvIndexMap::new(),
+            unwatched: 0,
+            flagged: 0,
+        }
+    }
+
+    pub fn config(&self) -> &AnomalyConfig {
+        &self.config
+    }
+
+    /// Account for an update of `pool` to `state` at `now_ns`; returns the
+    /// anomaly if the update newly flags the pool. A flagged pool is
+    /// entered in `suppressed` if the config suppresses.
+    pub fn observe(
+        &mut self,
+        pool: &PoolAddress,
+        state: &AmmPoolState,
+        now_ns: u64,
+        suppressed: &SuppressedPools,
+    ) -> Option<Anomaly> {
+        let norms = match self.pools.get_mut(pool) {
+            Some(norms) => norms,
+            None => {
+                let first = PoolNorms {
+                    last_ns: now_ns,
+                    reserve0: state.reserve0,
+                    ..PoolNorms::default()
+                };
+                if self.pools.insert(*pool, first).is_err() {
+                    self.unwatched += 1;
+                }
+                return None;
+            }
+        };
+        let interval_us = now_ns.saturating_sub(norms.last_ns) / 1_000;
+        let moved = state.reserve0.abs_diff(norms.reserve0) as u128 * 10_000;
+        let move_bps = (moved / norms.reserve0.max(1) as u128).min(u32::MAX as u128) as u64;
+        (norms.last_ns, norms.reserve0) = (now_ns, state.reserve0);
+        norms.updates = norms.updates.saturating_add(1);
+        if norms.updates == 1 {
+            // Averages start at the first sample, not at zero.
+            let scaled = |v: u64| v.saturating_mul(1 << SCALE_BITS);
+            (norms.fast_interval_us, norms.slow_interval_us) =
+                (scaled(interval_us), scaled(interval_us));
+            (norms.fast_move_bps, norms.slow_move_bps) = (scaled(move_bps), scaled(move_bps));
+        } else {
+            ewma(&mut norms.fast_interval_us, interval_us, FAST_SHIFT);
+            ewma(&mut norms.slow_interval_us, interval_us, SLOW_SHIFT);
+            ewma(&mut norms.fast_move_bps, move_bps, FAST_SHIFT);
+            ewma(&mut norms.slow_move_bps, move_bps, SLOW_SHIFT);
+        }
+        if norms.updates < WARMUP_UPDATES {
+            return None;
+        }
+        let factor = self.config.factor.max(1) as u64;
+        let anomaly = if norms.fast_interval_us.saturating_mul(factor) < norms.slow_interval_us {
+            Some(Anomaly::Rate)
+        } else if norms.fast_move_bps > norms.slow_move_bps.saturating_mul(factor)
+            && norms.fast_move_bps >= (self.config.min_move_bps as u64) << SCALE_BITS
+        {
+            Some(Anomaly::Volatility)
+        } else {
+            None
+        };
+        let newly = anomaly.filter(|_| !norms.flagged);
+        norms.flagged = anomaly.is_some();
+        if newly.is_some() {
+            self.flagged += 1;
+        }
+        // Suppressed until calm for `suppress_ms`: each anomalous update
+        // pushes the end out.
+        let suppress = anomaly.is_some() && self.config.suppress_ms != 0;
+        if suppress && (norms.suppressed_until_ns != 0 || suppressed.suppress(pool)) {
+            norms.suppressed_until_ns = now_ns.saturating_add(self.config.suppress_ms * 1_000_000);
+        }
+        newly
+    }
+
+    /// Lift the suppressions that ran out by `now_ns`.
+    pub fn sweep(&mut self, now_ns: u64, suppressed: &SuppressedPools) {
+        if suppressed.is_empty() {
+            return;
+        }
+        for (pool, norms) in self.pools.iter_mut() {
+            if norms.suppressed_until_ns != 0 && norms.suppressed_until_ns <= now_ns {
+                norms.suppressed_until_ns = 0;
+                suppressed.lift(pool);
+            }
+        }
+    }
+}
+
+/// Pools every strategy skips for now; written by the detector's thread,
+/// read by every evaluator.
+pub struct SuppressedPools {
+    keys: [AtomicU64; MAX_SUPPRESSED_POOLS],
+    len: AtomicU32,
+    /// Opportunities skipped because their pool was suppressed.
+    skips: AtomicU64,
+    /// Pools that could not be suppressed for want of room.
+    overflow: AtomicU64,
+}
+
+impl SuppressedPools {
+    pub const fn new() -> Self {
+        Self {
+            keys: [const { AtomicU64::new(0) }; MAX_SUPPRESSED_POOLS],
+            len: AtomicU32::new(0),
+            skips: AtomicU64::new(0),
+            overflow: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether strategies skip `pool`; counted as a skip if so. One load
+    /// while nothing is suppressed.
+    #[inline(always)]
+    pub fn is_suppressed(&self, pool: &PoolAddress) -> bool {
+        if self.len.load(Ordering::Relaxed) == 0 {
+            return false;
+        }
+        let key = pool_key(pool);
+        let hit = self.keys.iter().any(|k| k.load(Ordering::Relaxed) == key);
+        if hit {
+            self.skips.fetch_add(1, Ordering::Relaxed);
+        }
+        hit
+    }
+
+    /// `false` if every slot is taken.
+    pub fn suppress(&self, pool: &PoolAddress) -> bool {
+        let key = pool_key(pool);
+        let Some(slot) = self.keys.iter().find(|k| k.load(Ordering::Relaxed) == 0) else {
+            self.overflow.fetch_add(1, Ordering::Relaxed);
+            return false;
+        };
+        slot.store(key, Ordering::Relaxed);
+        self.len.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    pub fn lift(&self, pool: &PoolAddress) {
+        let key = pool_key(pool);
+        if let Some(slot) = self.keys.iter().find(|k| k.load(Ordering::Relaxed) == key) {
+            slot.store(0, Ordering::Relaxed);
+            self.len.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed) as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn skips(&self) -> u64 {
+        self.skips.load(Ordering::Relaxed)
+    }
+
+    pub fn overflow(&self) -> u64 {
+        self.overflow.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for SuppressedPools {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_bursts_and_swings_past_the_norm_and_suppresses_for_a_while() {
+        let config = AnomalyConfig {
+            factor: 4,
+            min_move_bps: 50,
+            suppress_ms: 10,
+        };
+        let (mut detector, suppressed) = (AnomalyDetector::new(config), SuppressedPools::new());
+        let (pool, other) = ([1; 20], [2; 20]);
+        let state = |reserve0| AmmPoolState::new(reserve0, 1_000_000, 997, 1_000);
+
+        // A pool updating every 10 ms, moving 0.2% each time.
+        let mut now_ns = 0;
+        let mut reserve0 = 1_000_000u64;
+        for i in 0..100 {
+            now_ns += 10_000_000;
+            reserve0 = if i % 2 == 0 {
+                reserve0 + 2_000
+            } else {
+                reserve0 - 2_000
+            };
+            assert_eq!(
+                detector.observe(&pool, &state(reserve0), now_ns, &suppressed),
+                None,
+                "update {i}"
+            );
+        }
+        assert!(!suppressed.is_suppressed(&pool));
+
+        // A burst: updates 100 us apart flag within a few.
+        let flags: Vec<_> = (0..8)
+            .filter_map(|_| {
+                now_ns += 100_000;
+                detector.observe(&pool, &state(reserve0), now_ns, &suppressed)
+            })
+            .collect();
+        assert_eq!(flags, [Anomaly::Rate], "flagged once");
+        assert!(suppressed.is_suppressed(&pool) && !suppressed.is_suppressed(&other));
+        assert_eq!((suppressed.skips(), detector.flagged), (1, 1));
+
+        // Lifted once it has been calm for `suppress_ms`.
+        detector.sweep(now_ns + 9_000_000, &suppressed);
+        assert_eq!(suppressed.len(), 1);
+        detector.sweep(now_ns + 10_000_000, &suppressed);
+        assert!(suppressed.is_empty());
+
+        // A 20% swing on a quiet pool flags as volatility.
+        let mut detector = AnomalyDetector::new(AnomalyConfig {
+            suppress_ms: 0,
+            ..config
+        });
+        for i in 0..40 {
+            let reserve0 = if i % 2 == 0 { 1_000_000 } else { 1_010_000 };
+            detector.observe(&other, &state(reserve0), i * 10_000_000, &suppressed);
+        }
+        let swung = detector.observe(&other, &state(1_200_000), 400_000_000, &suppressed);
+        assert_eq!(swung, Some(Anomaly::Volatility));
+        assert!(suppressed.is_empty(), "flag only");
+    }
+}
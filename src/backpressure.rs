@@ -0,0 +1,257 @@
+//! Backpressure from the TX path to the RX stages.
+//!
+//! When replies cannot leave as fast as they are produced (the TX buffer is
+//! full, the pacer's delay queue is filling or dropping), the RX stages
+//! keep ingesting at full rate and make it worse: every victim evaluated
+//! is one more reply queued behind replies already late, every pool update
+//! applied one more revalidation of replies that cannot be sent. The TX
+//! stage reports once per poll whether it was congested; a [`PressureGauge`]
+//! turns that into a [`Pressure`] level with hysteresis and publishes it in
+//! a [`Backpressure`] signal, an atomic any stage (or thread) reads without
+//! locking. Each RX stage reacts through its own hook in
+//! [`BackpressurePolicy`]:
+//! - pool feed: from `conflate_at` (default `backed`), only the newest
+//!   update per pool of a batch is applied, even with `MEV_POOL_CONFLATE=0`;
+//! - victims: from `head_drop_at` (default `saturated`), only the newest
+//!   `head_drop_keep` frames of a batch are evaluated and the older ones
+//!   ahead of them are dropped.
+//!
+//! The level rises one step after `raise_polls` congested polls in a row
+//! and falls one step after `clear_cycles` without congestion, so a single
+//! full TX buffer does not shed traffic and a recovered path is not
+//! flapped back into shedding.
+use crate::runtime::CacheAlignedAtomicU64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum Pressure {
+    /// Replies leave as fast as they are made.
+    Clear = 0,
+    /// The TX path has been backing up.
+    Backed = 1,
+    /// It kept backing up with the `Backed` hooks in effect.
+    Saturated = 2,
+}
+
+impl Pressure {
+    const fn from_u64(level: u64) -> Self {
+        match level {
+            0 => Self::Clear,
+            1 => Self::Backed,
+            _ => Self::Saturated,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Clear => "clear",
+            Self::Backed => "backed",
+            Self::Saturated => "saturated",
+        }
+    }
+
+    const fn raised(self) -> Self {
+        match self {
+            Self::Clear => Self::Backed,
+            _ => Self::Saturated,
+        }
+    }
+
+    const fn lowered(self) -> Self {
+        match self {
+            Self::Saturated => Self::Backed,
+            _ => Self::Clear,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BackpressurePolicy {
+    /// `MEV_BACKPRESSURE_POLLS`: congested polls in a row that raise the
+    /// level a step; 0 disables backpressure.
+    pub raise_polls: u32,
+    /// `MEV_BACKPRESSURE_CLEAR_CYCLES`: time without congestion that lowers
+    /// it a step.
+    pub clear_cycles: u64,
+    /// Level from which pool updates are conflated.
+    pub conflate_at: Pressure,
+    /// Level from which victim frames are head-dropped.
+    pub head_drop_at: Pressure,
+    /// `MEV_BACKPRESSURE_KEEP`: newest victim frames of a batch still
+    /// evaluated under head-drop.
+    pub head_drop_keep: usize,
+}
+
+impl BackpressurePolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.raise_polls > 0
+    }
+
+    /// Pool-feed hook: whether to conflate this batch, `configured` being
+    /// `MEV_POOL_CONFLATE`.
+    #[inline(always)]
+    pub fn conflate(&self, level: Pressure, configured: bool) -> bool {
+        configured || (self.is_enabled() && level >= self.conflate_at)
+    }
+
+    /// Victim hook: whether to drop a frame with `waiting` frames (it
+    /// included) still to be worked through in its batch.
+    #[inline(always)]
+    pub fn head_drop(&self, level: Pressure, waiting: usize) -> bool {
+        self.is_enabled() && level >= self.head_drop_at && waiting > self.head_drop_keep
+    }
+}
+
+impl Default for BackpressurePolicy {
+    fn default() -> Self {
+        Self {
+            raise_polls: 8,
+            clear_cycles: 3_000_000,
+            conflate_at: Pressure::Backed,
+            head_drop_at: Pressure::Saturated,
+            head_drop_keep: 4,
+        }
+    }
+}
+
+/// The level in effect, shared by every stage.
+pub struct Backpressure {
+    level: CacheAlignedAtomicU64,
+    /// Times the level rose.
+    raises: CacheAlignedAtomicU64,
+}
+
+impl Backpressure {
+    pub const fn new() -> Self {
+        Self {
+            level: CacheAlignedAtomicU64::new(0),
+            raises: CacheAlignedAtomicU64::new(0),
+        }
+    }
+
+    #[inline(always)]
+    pub fn level(&self) -> Pressure {
+        Pressure::from_u64(self.level.load())
+    }
+
+    pub fn raises(&self) -> u64 {
+        self.raises.load()
+    }
+
+    fn set(&self, level: Pressure) {
+        if level > self.level() {
+            self.raises.inc();
+        }
+        self.level.store(level as u64);
+    }
+}
+
+impl Default for Backpressure {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Turns per-poll TX congestion into a level; owned by the TX stage.
+pub struct PressureGauge {
+    policy: BackpressurePolicy,
+    level: Pressure,
+    congested_polls: u32,
+    /// Last congested poll, or the last step down since.
+    calm_since: u64,
+}
+
+impl PressureGauge {
+    pub fn new(policy: BackpressurePolicy) -> Self {
+        Self {
+            policy,
+            level: Pressure::Clear,
+            congested_polls: 0,
+            calm_since: 0,
+        }
+    }
+
+    /// Account for a poll at cycle `now` that found the TX path congested
+    /// if `congested`, and publish the level in `signal`; returns it.
+    #[inline(always)]
+    pub fn observe(&mut self, congested: bool, now: u64, signal: &Backpressure) -> Pressure {
+        if !self.policy.is_enabled() {
+            return Pressure::Clear;
+        }
+        let level = self.level;
+        if congested {
+            self.calm_since = now;
+            self.congested_polls += 1;
+            if self.congested_polls >= self.policy.raise_polls {
+                self.congested_polls = 0;
+                self.level = level.raised();
+            }
+        } else {
+            self.congested_polls = 0;
+            if level != Pressure::Clear
+                && now.wrapping_sub(self.calm_since) >= self.policy.clear_cycles
+            {
+                self.calm_since = now;
+                self.level = level.lowered();
+            }
+        }
+        if self.level != level {
+            signal.set(self.level);
+        }
+        self.level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn congestion_raises_hooks_stage_by_stage_and_calm_lowers_them() {
+        let policy = BackpressurePolicy {
+            raise_polls: 3,
+            clear_cycles: 100,
+            ..BackpressurePolicy::default()
+        };
+        let signal = Backpressure::new();
+        let mut gauge = PressureGauge::new(policy);
+
+        // Two congested polls, then one calm: the run starts over.
+        for (now, congested) in [(1, true), (2, true), (3, false), (4, true), (5, true)] {
+            assert_eq!(gauge.observe(congested, now, &signal), Pressure::Clear);
+        }
+        assert_eq!(gauge.observe(true, 6, &signal), Pressure::Backed);
+        assert_eq!(signal.level(), Pressure::Backed);
+        assert!(policy.conflate(signal.level(), false) && !policy.head_drop(signal.level(), 100));
+        for now in 7..=9 {
+            gauge.observe(true, now, &signal);
+        }
+        assert_eq!((signal.level(), signal.raises()), (Pressure::Saturated, 2));
+        assert_eq!(
+            [5, 4, 1].map(|waiting| policy.head_drop(signal.level(), waiting)),
+            [true, false, false]
+        );
+        gauge.observe(true, 12, &signal);
+        assert_eq!(signal.level(), Pressure::Saturated, "already at the top");
+
+        // Down a step per calm period.
+        assert_eq!(gauge.observe(false, 111, &signal), Pressure::Saturated);
+        assert_eq!(gauge.observe(false, 112, &signal), Pressure::Backed);
+        assert_eq!(gauge.observe(false, 211, &signal), Pressure::Backed);
+        assert_eq!(gauge.observe(false, 212, &signal), Pressure::Clear);
+        assert_eq!(signal.raises(), 2);
+
+        // Disabled: never raised, conflation only as configured.
+        let off = BackpressurePolicy {
+            raise_polls: 0,
+            ..policy
+        };
+        let mut gauge = PressureGauge::new(off);
+        assert_eq!(
+            gauge.observe(true, 1, &Backpressure::new()),
+            Pressure::Clear
+        );
+        assert!(!off.conflate(Pressure::Saturated, false) && off.conflate(Pressure::Clear, true));
+        assert!(!off.head_drop(Pressure::Saturated, 100));
+    }
+}
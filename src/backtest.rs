@@ -0,0 +1,591 @@
+//! Pool-state timelines: recording, and backtesting strategies against them.
+//!
+//! With `MEV_RECORD_TIMELINE` the node appends every accepted pool update
+//! and every victim swap it evaluates to a timeline file, each with its RX
+//! timestamp. Each reply it sends follows its victim as a decision record,
+//! with the confidence score it carried, so scores can be calibrated
+//! against outcomes. [`Backtest`] plays a timeline back through the production
+//! evaluator and pool table, in recorded order: pool updates move the
+//! table, victims are evaluated against it at their recorded time (a
+//! [`ReplayClock`]), not the time of the replay.
+//!
+//! Every opportunity is then settled once per latency scenario: at
+//! `rx + latency` the victim is evaluated again against the pool state of
+//! that moment — the opportunity may have moved away — and, if still
+//! profitable, raced under the [`RaceModel`] and booked as in the dry-run
+//! [`Simulator`]. Comparing scenarios shows what a microsecond is worth.
+//!
+//! The node writes the timeline as a rotating [`crate::journal`]; each
+//! segment is a timeline of its own, and [`Backtest::play`] continues one
+//! from where the previous left off.
+//!
+//! File layout: [`TIMELINE_MAGIC`], then records of a [`TimelineHeader`]
+//! followed by the payload exactly as received.
+use crate::clock::ReplayClock;
+use crate::payload::DexSwapTx;
+use crate::pool_registry::PoolTable;
+use crate::processor::{Evaluator, Strategy};
+use crate::schema::VersionWindow;
+use crate::sim::{PnlEntry, RaceModel, Simulator};
+use crate::validator::{decode_pool_update, validate_pool_update, ValidationError};
+use bytemuck::{Pod, Zeroable};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+/// First bytes of a timeline file ("MVTL", format version 1).
+pub const TIMELINE_MAGIC: [u8; 8] = *b"MVTL\0\0\0\x01";
+
+/// Largest victim payload kept for settlement (`DexSwapTxEvm`).
+const MAX_VICTIM: usize = 96;
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventKind {
+    /// A `PoolStateUpdate` the node applied.
+    PoolUpdate = 1,
+    /// A victim swap the node evaluated.
+    Victim = 2,
+    /// The `OpportunityReply` the node sent for the victim before it.
+    Decision = 3,
+}
+
+impl EventKind {
+    pub const fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(Self::PoolUpdate),
+            2 => Some(Self::Victim),
+            3 => Some(Self::Decision),
+            _ => None,
+        }
+    }
+}
+
+/// Framing of one timeline record.
+///
+/// Layout (16 bytes, little-endian):
+///   [0..8]   rx_ts_ns  u64 — when the node received the payload
+///   [8..10]  len       u16 — payload bytes that follow
+///   [10]     kind      `EventKind`
+///   [11..16] _reserved
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct TimelineHeader {
+    pub rx_ts_ns_le: [u8; 8],
+    pub len_le: [u8; 2],
+    pub kind: u8,
+    pub _reserved: [u8; 5],
+}
+
+const _: () = assert!(core::mem::size_of::<TimelineHeader>() == 16);
+
+impl TimelineHeader {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<TimelineHeader>();
+
+    pub fn new(kind: EventKind, rx_ts_ns: u64, len: u16) -> Self {
+        Self {
+            rx_ts_ns_le: rx_ts_ns.to_le_bytes(),
+            len_le: len.to_le_bytes(),
+            kind: kind as u8,
+            _reserved: [0; 5],
+        }
+    }
+
+    #[inline(always)]
+    pub fn rx_ts_ns(&self) -> u64 {
+        u64::from_le_bytes(self.rx_ts_ns_le)
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        u16::from_le_bytes(self.len_le) as usize
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Appends records to a timeline.
+pub struct TimelineWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> TimelineWriter<W> {
+    /// Start a timeline on `out`, writing the magic.
+    pub fn new(mut out: W) -> io::Result<Self> {
+        out.write_all(&TIMELINE_MAGIC)?;
+        Ok(Self { out })
+    }
+
+    pub fn record(&mut self, kind: EventKind, rx_ts_ns: u64, payload: &[u8]) -> io::Result<()> {
+        let len = u16::try_from(payload.len())
+            .map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+        self.out.write_all(bytemuck::bytes_of(&TimelineHeader::new(
+            kind, rx_ts_ns, len,
+        )))?;
+        self.out.write_all(payload)
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimelineError {
+    /// The file does not start with [`TIMELINE_MAGIC`].
+    BadMagic,
+    /// The record at `offset` runs past the end of the file.
+    Truncated { offset: usize },
+    /// The record at `offset` has an unknown kind.
+    UnknownKind { offset: usize, kind: u8 },
+}
+
+impl core::fmt::Display for TimelineError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a timeline file"),
+            Self::Truncated { offset } => write!(f, "truncated record at byte {offset}"),
+            Self::UnknownKind { offset, kind } => {
+                write!(f, "unknown record kind {kind} at byte {offset}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for TimelineError {}
+
+/// One recorded payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimelineEvent<'a> {
+    pub kind: EventKind,
+    pub rx_ts_ns: u64,
+    pub payload: &'a [u8],
+}
+
+/// Zero-copy reader over a timeline file's bytes.
+pub struct Timeline<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Timeline<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, TimelineError> {
+        if !data.starts_with(&TIMELINE_MAGIC) {
+            return Err(TimelineError::BadMagic);
+        }
+        Ok(Self {
+            data,
+            offset: TIMELINE_MAGIC.len(),
+        })
+    }
+}
+
+impl<'a> Iterator for Timeline<'a> {
+    type Item = Result<TimelineEvent<'a>, TimelineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let offset = self.offset;
+        let rest = self.data.get(offset..).filter(|r| !r.is_empty())?;
+        let truncated = Err(TimelineError::Truncated { offset });
+        let Some(header) = rest.get(..TimelineHeader::WIRE_SIZE) else {
+            self.offset = self.data.len();
+            return Some(truncated);
+        };
+        let header: &TimelineHeader = bytemuck::from_bytes(header);
+        let end = TimelineHeader::WIRE_SIZE + header.len();
+        let Some(payload) = rest.get(TimelineHeader::WIRE_SIZE..end) else {
+            self.offset = self.data.len();
+            return Some(truncated);
+        };
+        self.offset += end;
+        Some(match EventKind::from_u8(header.kind) {
+            Some(kind) => Ok(TimelineEvent {
+                kind,
+                rx_ts_ns: header.rx_ts_ns(),
+                payload,
+            }),
+            None => Err(TimelineError::UnknownKind {
+                offset,
+                kind: header.kind,
+            }),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BacktestConfig {
+    /// Decision-to-landing latencies to compare, in ns.
+    pub latencies_ns: Vec<u64>,
+    /// Competitor latencies and inclusion rate.
+    pub race: RaceModel,
+    /// Gas per landed bundle, numéraire units.
+    pub gas_cost: u64,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            latencies_ns: vec![0, 100_000, 500_000, 1_000_000, 5_000_000],
+            race: RaceModel::default(),
+            gas_cost: 0,
+        }
+    }
+}
+
+struct Pending {
+    due_ns: u64,
+    rx_ts_ns: u64,
+    victim: heapless::Vec<u8, MAX_VICTIM>,
+}
+
+struct Scenario {
+    latency_ns: u64,
+    sim: Simulator,
+    pending: VecDeque<Pending>,
+    /// Opportunities gone by the time the bundle would land.
+    moved: u64,
+}
+
+/// Results of one latency scenario.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScenarioReport {
+    pub latency_ns: u64,
+    /// Opportunities found at RX.
+    pub opportunities: u64,
+    /// Of those, unprofitable against the pool state at landing time.
+    pub moved: u64,
+    /// Raced, landed and PnL, per strategy.
+    pub strategies: [(Strategy, PnlEntry); Strategy::COUNT],
+    pub totals: PnlEntry,
+}
+
+impl ScenarioReport {
+    /// Landed share of the opportunities, in basis points.
+    pub fn fill_bps(&self) -> u64 {
+        (self.totals.landed * 10_000)
+            .checked_div(self.opportunities)
+            .unwrap_or(0)
+    }
+}
+
+impl core::fmt::Display for ScenarioReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "latency={}ns opportunities={} moved={} raced={} landed={} fill={}bps pnl={}",
+            self.latency_ns,
+            self.opportunities,
+            self.moved,
+            self.totals.emitted,
+            self.totals.landed,
+            self.fill_bps(),
+            self.totals.pnl
+        )?;
+        for (strategy, e) in &self.strategies {
+            write!(
+                f,
+                "\n  {}: raced={} landed={} pnl={}",
+                strategy.name(),
+                e.emitted,
+                e.landed,
+                e.pnl
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// What a replay decided for one victim.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Verdict {
+    pub nonce: u64,
+    /// Profit of the opportunity found at RX; `None` if no reply.
+    pub profit: Option<u64>,
+}
+
+pub struct Backtest {
+    evaluator: Evaluator,
+    /// The recorded time, moved by each event's RX stamp.
+    clock: ReplayClock,
+    pools: &'static PoolTable,
+    gas_cost: u64,
+    scenarios: Vec<Scenario>,
+    pub pool_updates: u64,
+    /// Pool updates that failed validation or named an unknown pool.
+    pub pool_rejects: u64,
+    pub victims: u64,
+    pub opportunities: u64,
+}
+
+impl Backtest {
+    /// Play timelines through `evaluator`, which must read `pools`.
+    pub fn new(evaluator: Evaluator, pools: &'static PoolTable, config: &BacktestConfig) -> Self {
+        let scenarios = config
+            .latencies_ns
+            .iter()
+            .map(|&latency_ns| Scenario {
+                latency_ns,
+                sim: Simulator::new(config.race),
+                pending: VecDeque::new(),
+                moved: 0,
+            })
+            .collect();
+        Self {
+            evaluator,
+            clock: ReplayClock::default(),
+            pools,
+            gas_cost: config.gas_cost,
+            scenarios,
+            pool_updates: 0,
+            pool_rejects: 0,
+            victims: 0,
+            opportunities: 0,
+        }
+    }
+
+    /// Play a whole timeline, then settle what is still pending. A damaged
+    /// record ends the run there; everything before it is settled.
+    pub fn run(&mut self, timeline: Timeline<'_>) -> Result<(), TimelineError> {
+        let result = self.play(timeline);
+        self.finish();
+        result
+    }
+
+    /// Play a timeline without settling what is pending at its end, so the
+    /// next segment of a journal continues it. Stops at a damaged record.
+    pub fn play(&mut self, timeline: Timeline<'_>) -> Result<(), TimelineError> {
+        for event in timeline {
+            self.replay(event?);
+        }
+        Ok(())
+    }
+
+    /// Settle everything still pending, at the end of the last timeline.
+    pub fn finish(&mut self) {
+        self.settle(u64::MAX);
+    }
+
+    /// Play one event: first settle every bundle that lands before it.
+    /// Returns the verdict on a victim.
+    pub fn replay(&mut self, event: TimelineEvent<'_>) -> Option<Verdict> {
+        self.clock.advance_to(event.rx_ts_ns);
+        self.settle(event.rx_ts_ns);
+        match event.kind {
+            EventKind::PoolUpdate => {
+                self.apply_pool_update(event.payload);
+                None
+            }
+            EventKind::Victim => {
+                self.victims += 1;
+                let nonce = DexSwapTx::header(event.payload).map_or(0, |tx| tx.nonce());
+                let Ok(victim) = heapless::Vec::from_slice(event.payload) else {
+                    return Some(Verdict {
+                        nonce,
+                        profit: None,
+                    });
+                };
+                let profit = self.evaluator.process_packet_on(&self.clock, event.payload);
+                if profit.is_some() {
+                    self.opportunities += 1;
+                    for scenario in &mut self.scenarios {
+                        let due_ns = event.rx_ts_ns.saturating_add(scenario.latency_ns);
+                        scenario.pending.push_back(Pending {
+                            due_ns,
+                            rx_ts_ns: event.rx_ts_ns,
+                            victim: victim.clone(),
+                        });
+                    }
+                }
+                Some(Verdict { nonce, profit })
+            }
+            // What the node decided then; the backtest decides afresh.
+            EventKind::Decision => None,
+        }
+    }
+
+    /// As the node does: a sequence gap is applied anyway.
+    fn apply_pool_update(&mut self, payload: &[u8]) {
+        let last_seq = payload
+            .get(..20)
+            .and_then(|a| self.pools.peek(&a.try_into().ok()?))
+            .map_or(0, |e| e.seq);
+        let update = match validate_pool_update(payload, last_seq) {
+            Err(ValidationError::SequenceGap { .. }) => validate_pool_update(payload, 0),
+            other => other,
+        };
+        let applied = update.is_ok_and(|u| {
+            decode_pool_update(u, VersionWindow::ALL)
+                .is_ok_and(|decoded| self.pools.apply(&u.pool_address, &decoded))
+        });
+        if applied {
+            self.pool_updates += 1;
+        } else {
+            self.pool_rejects += 1;
+        }
+    }
+
+    /// Settle every bundle due by `now_ns` against the current pool state.
+    fn settle(&mut self, now_ns: u64) {
+        for scenario in &mut self.scenarios {
+            while scenario.pending.front().is_some_and(|p| p.due_ns <= now_ns) {
+                let Some(p) = scenario.pending.pop_front() else {
+                    break;
+                };
+                match self
+                    .evaluator
+                    .process_packet_at(&p.victim, p.rx_ts_ns / 1_000_000)
+                {
+                    Some(profit) => {
+                        let pool =
+                            DexSwapTx::header(&p.victim).map_or([0; 20], |tx| tx.pool_address);
+                        scenario.sim.settle(
+                            Strategy::Sandwich,
+                            pool,
+                            profit,
+                            self.gas_cost,
+                            scenario.latency_ns,
+                        );
+                    }
+                    None => scenario.moved += 1,
+                }
+            }
+        }
+    }
+
+    /// One report per latency scenario, in configured order.
+    pub fn reports(&self) -> Vec<ScenarioReport> {
+        self.scenarios
+            .iter()
+            .map(|s| {
+                let strategies = Strategy::ALL.map(|strategy| {
+                    let mut total = PnlEntry::default();
+                    for (_, _, e) in s.sim.entries().filter(|(st, _, _)| *st == strategy) {
+                        total.emitted += e.emitted;
+                        total.landed += e.landed;
+                        total.pnl = total.pnl.saturating_add(e.pnl);
+                    }
+                    (strategy, total)
+                });
+                ScenarioReport {
+                    latency_ns: s.latency_ns,
+                    opportunities: self.opportunities,
+                    moved: s.moved,
+                    strategies,
+                    totals: s.sim.totals,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::{self, DEFAULT_EVALUATOR};
+    use crate::validator::PoolStateUpdate;
+    use zerocopy::IntoBytes;
+
+    const POOL: [u8; 20] = [0xaa; 20];
+
+    fn timeline(events: &[(EventKind, u64, Vec<u8>)]) -> Vec<u8> {
+        let mut w = TimelineWriter::new(Vec::new()).unwrap();
+        for (kind, ts, payload) in events {
+            w.record(*kind, *ts, payload).unwrap();
+        }
+        w.out
+    }
+
+    #[test]
+    fn timeline_round_trips_and_rejects_damage() {
+        let bytes = timeline(&[
+            (EventKind::Victim, 5, vec![1, 2, 3]),
+            (EventKind::PoolUpdate, 9, vec![]),
+        ]);
+        let events: Vec<_> = Timeline::parse(&bytes)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            events[0],
+            TimelineEvent {
+                kind: EventKind::Victim,
+                rx_ts_ns: 5,
+                payload: &[1, 2, 3]
+            }
+        );
+        assert_eq!(
+            (events[1].kind, events[1].payload.len()),
+            (EventKind::PoolUpdate, 0)
+        );
+
+        let last = Timeline::parse(&bytes[..bytes.len() - 1]).unwrap().last();
+        assert_eq!(last, Some(Err(TimelineError::Truncated { offset: 27 })));
+        assert!(matches!(
+            Timeline::parse(b"pcap"),
+            Err(TimelineError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn slower_scenarios_lose_opportunities_the_pool_moved_away_from() {
+        let pools: &'static PoolTable = Box::leak(Box::new(PoolTable::new()));
+        pools.upsert(processor::mock_pool_entry(POOL)).unwrap();
+        let evaluator = DEFAULT_EVALUATOR.with_pools(pools);
+        let state = processor::MOCK_POOL;
+
+        let victim = DexSwapTx::from_parts(1, POOL, 500_000_000, 0, 0);
+        // 50 µs after the victim the pool is so deep the victim no longer
+        // moves it enough to pay.
+        let deep = PoolStateUpdate::new(
+            POOL,
+            state.reserve0 * 1_000_000,
+            state.reserve1 * 1_000_000,
+            2,
+            1,
+        );
+        let bytes = timeline(&[
+            (
+                EventKind::Victim,
+                1_000_000,
+                bytemuck::bytes_of(&victim).to_vec(),
+            ),
+            (EventKind::PoolUpdate, 1_050_000, deep.as_bytes().to_vec()),
+            (EventKind::PoolUpdate, 1_060_000, vec![0; 8]),
+        ]);
+
+        let race = RaceModel {
+            competitor_min_ns: 1_000_000,
+            competitor_max_ns: 1_000_000,
+            inclusion_bps: 10_000,
+            seed: 1,
+        };
+        let config = BacktestConfig {
+            latencies_ns: vec![10_000, 100_000],
+            race,
+            gas_cost: 0,
+        };
+        let mut backtest = Backtest::new(evaluator, pools, &config);
+        backtest.run(Timeline::parse(&bytes).unwrap()).unwrap();
+
+        assert_eq!((backtest.victims, backtest.opportunities), (1, 1));
+        assert_eq!((backtest.pool_updates, backtest.pool_rejects), (1, 1));
+        let [fast, slow] = &backtest.reports()[..] else {
+            panic!("two scenarios")
+        };
+        assert_eq!(
+            (fast.moved, fast.totals.landed, fast.fill_bps()),
+            (0, 1, 10_000)
+        );
+        assert!(fast.strategies[0].1.pnl > 0);
+        assert_eq!(
+            (slow.moved, slow.totals.emitted, slow.fill_bps()),
+            (1, 0, 0)
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Adaptive receive batch size.
+//!
+//! Each main-loop iteration drains up to a batch of frames before working
+//! through them, so the first frame of a batch waits for the last one to be
+//! copied in. At low load small batches keep that wait short; under load
+//! larger ones amortize the per-poll work and keep the socket from
+//! overflowing. [`BurstShaper`] moves between the two within the configured
+//! bounds (`MEV_RX_BATCH=min-max`):
+//! - a poll that leaves frames queued doubles the batch, up to `max`;
+//! - [`IDLE_POLLS`] polls in a row that fill at most a quarter of it
+//!   halve it, down to `min`.
+//!
+//! Growth is fast and shrinking slow, so a burst is drained in few polls
+//! and one quiet poll between bursts does not undo it. The batch size in
+//! effect at every non-empty poll is recorded in a [`BatchSizes`]
+//! histogram, reported with the periodic stats and pushed to statsd.
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Largest batch the node can be configured for.
+pub const MAX_RX_BATCH: usize = crate::dimensions::ActiveDimensions::BATCH_SIZE;
+
+// The AF_XDP transport tracks a burst's frames in a `u64` bitmask.
+const _: () = assert!(MAX_RX_BATCH <= 64, "MAX_RX_BATCH must fit a u64 bitmask");
+
+/// Consecutive light polls after which the batch shrinks.
+pub const IDLE_POLLS: u32 = 256;
+
+/// Bounds of the batch size, in frames.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BurstConfig {
+    pub min: usize,
+    pub max: usize,
+}
+
+impl Default for BurstConfig {
+    fn default() -> Self {
+        Self { min: 8, max: 32 }
+    }
+}
+
+pub struct BurstShaper {
+    config: BurstConfig,
+    size: usize,
+    light_polls: u32,
+}
+
+impl BurstShaper {
+    /// A shaper starting at `config.min`; bounds are clamped to
+    /// `1..=MAX_RX_BATCH`.
+    pub fn new(config: BurstConfig) -> Self {
+        let max = config.max.clamp(1, MAX_RX_BATCH);
+        let config = BurstConfig {
+            min: config.min.clamp(1, max),
+            max,
+        };
+        Self {
+            config,
+            size: config.min,
+            light_polls: 0,
+        }
+    }
+
+    /// Frames to take on the next poll.
+    #[inline(always)]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Account for a poll that took `received` frames and left frames
+    /// queued if `backlog`; returns the size for the next one.
+    #[inline(always)]
+    pub fn observe(&mut self, received: usize, backlog: bool) -> usize {
+        if backlog {
+            self.size = (self.size * 2).min(self.config.max);
+            self.light_polls = 0;
+        } else if received <= self.size / 4 {
+            self.light_polls += 1;
+            if self.light_polls >= IDLE_POLLS {
+                self.size = (self.size / 2).max(self.config.min);
+                self.light_polls = 0;
+            }
+        } else {
+            self.light_polls = 0;
+        }
+        self.size
+    }
+}
+
+/// How often each batch size was in effect, sizes `1..=MAX_RX_BATCH`.
+pub struct BatchSizes {
+    counts: [AtomicU64; MAX_RX_BATCH],
+}
+
+impl BatchSizes {
+    pub const fn new() -> Self {
+        Self {
+            counts: [const { AtomicU64::new(0) }; MAX_RX_BATCH],
+        }
+    }
+
+    #[inline(always)]
+    pub fn record(&self, size: usize) {
+        if let Some(count) = self.counts.get(size.wrapping_sub(1)) {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Smallest size at or below which `pct` percent of polls ran; 0
+    /// before any.
+    pub fn percentile(&self, pct: u64) -> usize {
+        let counts = self.counts.each_ref().map(|c| c.load(Ordering::Relaxed));
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return 0;
+        }
+        let rank = (total * pct).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (i, count) in counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return i + 1;
+            }
+        }
+        MAX_RX_BATCH
+    }
+}
+
+impl Default for BatchSizes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grows_on_backlog_and_shrinks_after_a_quiet_spell() {
+        let mut shaper = BurstShaper::new(BurstConfig { min: 4, max: 48 });
+        assert_eq!(shaper.size(), 4);
+        assert_eq!(
+            [8, 16, 32, 48, 48].map(|_| shaper.observe(shaper.size(), true)),
+            [8, 16, 32, 48, 48]
+        );
+        // A full poll that drained the queue keeps the size; light polls
+        // shrink it only once there have been IDLE_POLLS in a row.
+        assert_eq!(shaper.observe(48, false), 48);
+        for _ in 1..IDLE_POLLS {
+            assert_eq!(shaper.observe(1, false), 48);
+        }
+        assert_eq!(shaper.observe(0, false), 24);
+        (0..4 * IDLE_POLLS).for_each(|_| {
+            shaper.observe(0, false);
+        });
+        assert_eq!(shaper.size(), 4);
+        assert_eq!(
+            BurstShaper::new(BurstConfig {
+                min: 100,
+                max: 1_000
+            })
+            .size(),
+            MAX_RX_BATCH
+        );
+
+        let sizes = BatchSizes::new();
+        assert_eq!(sizes.percentile(50), 0);
+        [4, 4, 4, 8, 64, 0, 65]
+            .into_iter()
+            .for_each(|size| sizes.record(size));
+        assert_eq!(
+            (
+                sizes.percentile(50),
+                sizes.percentile(80),
+                sizes.percentile(99)
+            ),
+            (4, 8, 64)
+        );
+    }
+
+    #[test]
+    fn batch_bound_is_the_active_dimension() {
+        use crate::dimensions::{ActiveDimensions, DefaultDimensions, LargeDimensions};
+
+        assert_eq!(MAX_RX_BATCH, ActiveDimensions::BATCH_SIZE);
+        assert_eq!(
+            (DefaultDimensions::BATCH_SIZE, LargeDimensions::BATCH_SIZE),
+            (64, 64)
+        );
+    }
+}
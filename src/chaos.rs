@@ -0,0 +1,278 @@
+//! Fault injection for chaos drills.
+//!
+//! Built with the `chaos` feature, the node takes faults on the admin socket
+//! (operator role) and injects them into its own main loop, so operators can
+//! rehearse degraded conditions against a live feed:
+//!
+//! | Command | Fault |
+//! |---|---|
+//! | `chaos rx-drop <pct>` | lose that percentage of victim and pool-feed frames on receipt |
+//! | `chaos tx-delay <us>` | hold every reply that long before it is sent (the loop waits with it) |
+//! | `chaos corrupt-seq <pct>` | replace the sequence number of that percentage of pool updates |
+//! | `chaos stall <us>` | stall the loop once, for that long |
+//! | `chaos off` | stop injecting |
+//! | `chaos` | the faults in effect and those injected so far, as JSON (`readonly`) |
+//!
+//! Without the feature every hook is a constant no-op, and the commands
+//! are answered with an error. Faults are drawn from a xorshift generator,
+//! so drops and corruptions are spread evenly rather than in bursts.
+use crate::validator::PoolStateUpdate;
+use std::time::{Duration, Instant};
+
+/// Whether this build injects faults.
+pub const ENABLED: bool = cfg!(feature = "chaos");
+
+/// Longest delay or stall accepted, µs.
+pub const MAX_FAULT_US: u32 = 1_000_000;
+
+const SEQ_OFFSET: usize = core::mem::offset_of!(PoolStateUpdate, seq);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FaultCommand {
+    Show,
+    RxDrop(u8),
+    TxDelay(u32),
+    CorruptSeq(u8),
+    Stall(u32),
+    Off,
+}
+
+impl FaultCommand {
+    /// Parse what follows `chaos` on an admin line.
+    pub fn parse(args: &str) -> Option<Self> {
+        let pct = |v: &str| v.parse::<u8>().ok().filter(|p| *p <= 100);
+        let us = |v: &str| v.parse::<u32>().ok().filter(|us| *us <= MAX_FAULT_US);
+        match args.split_once(' ').unwrap_or((args, "")) {
+            ("", "") => Some(Self::Show),
+            ("off", "") => Some(Self::Off),
+            ("rx-drop", v) => pct(v).map(Self::RxDrop),
+            ("tx-delay", v) => us(v).map(Self::TxDelay),
+            ("corrupt-seq", v) => pct(v).map(Self::CorruptSeq),
+            ("stall", v) => us(v).map(Self::Stall),
+            _ => None,
+        }
+    }
+
+    /// `Show` only reads.
+    pub const fn is_readonly(self) -> bool {
+        matches!(self, Self::Show)
+    }
+}
+
+/// Faults injected so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaultCounts {
+    pub rx_dropped: u64,
+    pub tx_delayed: u64,
+    pub seqs_corrupted: u64,
+    pub stalls: u64,
+}
+
+pub struct FaultInjector {
+    rx_drop_pct: u8,
+    tx_delay_us: u32,
+    corrupt_seq_pct: u8,
+    /// One-shot stall still to be taken.
+    stall_us: u32,
+    rng: u64,
+    pub injected: FaultCounts,
+}
+
+impl FaultInjector {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rx_drop_pct: 0,
+            tx_delay_us: 0,
+            corrupt_seq_pct: 0,
+            stall_us: 0,
+            rng: seed | 1,
+            injected: FaultCounts::default(),
+        }
+    }
+
+    /// Apply an admin command; `None` if this build cannot inject faults.
+    pub fn command(&mut self, cmd: FaultCommand) -> Option<&Self> {
+        if !ENABLED {
+            return None;
+        }
+        match cmd {
+            FaultCommand::Show => {}
+            FaultCommand::RxDrop(pct) => self.rx_drop_pct = pct,
+            FaultCommand::TxDelay(us) => self.tx_delay_us = us,
+            FaultCommand::CorruptSeq(pct) => self.corrupt_seq_pct = pct,
+            FaultCommand::Stall(us) => self.stall_us = us,
+            FaultCommand::Off => {
+                (
+                    self.rx_drop_pct,
+                    self.tx_delay_us,
+                    self.corrupt_seq_pct,
+                    self.stall_us,
+                ) = (0, 0, 0, 0)
+            }
+        }
+        Some(self)
+    }
+
+    /// Whether any fault is in effect.
+    pub fn is_active(&self) -> bool {
+        ENABLED
+            && (self.rx_drop_pct != 0
+                || self.corrupt_seq_pct != 0
+                || self.tx_delay_us != 0
+                || self.stall_us != 0)
+    }
+
+    /// xorshift64.
+    fn next_u64(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    fn roll(&mut self, pct: u8) -> bool {
+        pct != 0 && self.next_u64() % 100 < pct as u64
+    }
+
+    /// Whether to lose the frame just received.
+    #[inline(always)]
+    pub fn drop_rx(&mut self) -> bool {
+        if !ENABLED || !self.roll(self.rx_drop_pct) {
+            return false;
+        }
+        self.injected.rx_dropped += 1;
+        true
+    }
+
+    /// Hold a reply about to be sent.
+    #[inline(always)]
+    pub fn delay_tx(&mut self) {
+        if ENABLED && self.tx_delay_us != 0 {
+            self.injected.tx_delayed += 1;
+            spin(self.tx_delay_us);
+        }
+    }
+
+    /// Take the pending stall, if any.
+    #[inline(always)]
+    pub fn stall(&mut self) {
+        if ENABLED && self.stall_us != 0 {
+            self.injected.stalls += 1;
+            spin(core::mem::take(&mut self.stall_us));
+        }
+    }
+
+    /// `payload`, or a copy in `scratch` with a random sequence number if
+    /// it is a pool update picked for corruption.
+    #[inline(always)]
+    pub fn corrupt_seq<'a>(
+        &mut self,
+        payload: &'a [u8],
+        scratch: &'a mut [u8; PoolStateUpdate::WIRE_SIZE],
+    ) -> &'a [u8] {
+        if !ENABLED
+            || payload.len() != PoolStateUpdate::WIRE_SIZE
+            || !self.roll(self.corrupt_seq_pct)
+        {
+            return payload;
+        }
+        self.injected.seqs_corrupted += 1;
+        scratch.copy_from_slice(payload);
+        let seq = (self.next_u64() as u32).to_le_bytes();
+        scratch[SEQ_OFFSET..SEQ_OFFSET + 4].copy_from_slice(&seq);
+        scratch
+    }
+}
+
+/// Busy-wait `us` µs, as a loop that is slow rather than asleep.
+fn spin(us: u32) {
+    let until = Instant::now() + Duration::from_micros(us as u64);
+    while Instant::now() < until {
+        core::hint::spin_loop();
+    }
+}
+
+impl core::fmt::Display for FaultInjector {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let n = &self.injected;
+        write!(
+            f,
+            "{{\"rx_drop_pct\":{},\"tx_delay_us\":{},\"corrupt_seq_pct\":{},\"stall_pending_us\":{},\
+             \"injected\":{{\"rx_dropped\":{},\"tx_delayed\":{},\"seqs_corrupted\":{},\"stalls\":{}}}}}",
+            self.rx_drop_pct,
+            self.tx_delay_us,
+            self.corrupt_seq_pct,
+            self.stall_us,
+            n.rx_dropped,
+            n.tx_delayed,
+            n.seqs_corrupted,
+            n.stalls
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn injects_only_what_was_commanded() {
+        assert_eq!(FaultCommand::parse(""), Some(FaultCommand::Show));
+        assert_eq!(
+            FaultCommand::parse("rx-drop 25"),
+            Some(FaultCommand::RxDrop(25))
+        );
+        assert_eq!(
+            FaultCommand::parse("stall 500"),
+            Some(FaultCommand::Stall(500))
+        );
+        for bad in [
+            "rx-drop 101",
+            "tx-delay",
+            "stall 2000000",
+            "off now",
+            "drop 5",
+        ] {
+            assert_eq!(FaultCommand::parse(bad), None, "{bad}");
+        }
+
+        let mut chaos = FaultInjector::new(7);
+        let update = PoolStateUpdate::new([1; 20], 10, 20, 3, 42);
+        let mut scratch = [0; PoolStateUpdate::WIRE_SIZE];
+        assert!(!(0..1_000).any(|_| chaos.drop_rx()));
+        assert_eq!(
+            chaos
+                .corrupt_seq(zerocopy::IntoBytes::as_bytes(&update), &mut scratch)
+                .len(),
+            64
+        );
+        if !ENABLED {
+            assert!(chaos.command(FaultCommand::RxDrop(50)).is_none());
+            assert!(!chaos.drop_rx() && !chaos.is_active());
+            return;
+        }
+
+        chaos.command(FaultCommand::RxDrop(50));
+        chaos.command(FaultCommand::CorruptSeq(100));
+        let dropped = (0..10_000).filter(|_| chaos.drop_rx()).count();
+        assert!((4_500..5_500).contains(&dropped), "{dropped}");
+        let corrupted = chaos.corrupt_seq(zerocopy::IntoBytes::as_bytes(&update), &mut scratch);
+        let corrupted =
+            <PoolStateUpdate as zerocopy::FromBytes>::read_from_bytes(corrupted).unwrap();
+        assert_ne!(corrupted.seq(), 42);
+        assert_eq!(
+            (corrupted.pool_address, corrupted.slot()),
+            (update.pool_address, 3)
+        );
+
+        chaos.command(FaultCommand::Stall(50));
+        let started = Instant::now();
+        chaos.stall();
+        chaos.stall();
+        assert!(started.elapsed() >= Duration::from_micros(50));
+        assert_eq!(chaos.injected.stalls, 1, "a stall is taken once");
+        chaos.command(FaultCommand::Off);
+        assert!(!chaos.is_active());
+        assert!(chaos.to_string().starts_with("{\"rx_drop_pct\":0,"));
+    }
+}
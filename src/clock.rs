@@ -0,0 +1,98 @@
+//! Where the node's time comes from.
+//!
+//! Deadline and staleness checks, timers and latency samples take their
+//! time from a [`Clock`] instead of reading the TSC or the system clock
+//! themselves. Live, that is the calibrated TSC of
+//! [`crate::runtime::LoopClock`]. Replaying a journal, it is a
+//! [`ReplayClock`] driven by the recorded RX stamps: the same timeline
+//! evaluates the same way on every run and every host, however fast the
+//! replay runs.
+//!
+//! A [`ReplayClock`] counts one cycle per nanosecond, so cycle-denominated
+//! settings (`MEV_STALE_AGE_CYCLES`, pacing) read as nanoseconds
+//! in a replay.
+
+/// A source of cycles and unix time.
+pub trait Clock {
+    /// Cycles now: the counter timers, pacing and latencies run on.
+    fn now_cycles(&self) -> u64;
+
+    /// Unix time now, in nanoseconds.
+    fn now_unix_ns(&self) -> u64;
+
+    /// Cycles per microsecond; at least 1.
+    fn cycles_per_us(&self) -> u64;
+
+    #[inline(always)]
+    fn now_unix_ms(&self) -> u64 {
+        self.now_unix_ns() / 1_000_000
+    }
+}
+
+/// Recorded time: stands still between [`ReplayClock::advance_to`] calls.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReplayClock {
+    unix_ns: u64,
+}
+
+impl ReplayClock {
+    /// A clock reading unix time `unix_ns`.
+    pub const fn at(unix_ns: u64) -> Self {
+        Self { unix_ns }
+    }
+
+    /// Move to the stamp `unix_ns` of the next record. Never backwards:
+    /// records stamped out of order leave the clock where it is.
+    #[inline(always)]
+    pub fn advance_to(&mut self, unix_ns: u64) {
+        self.unix_ns = self.unix_ns.max(unix_ns);
+    }
+}
+
+impl Clock for ReplayClock {
+    #[inline(always)]
+    fn now_cycles(&self) -> u64 {
+        self.unix_ns
+    }
+
+    #[inline(always)]
+    fn now_unix_ns(&self) -> u64 {
+        self.unix_ns
+    }
+
+    fn cycles_per_us(&self) -> u64 {
+        1_000
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::freshness::FreshnessPolicy;
+    use crate::timer::TimerWheel;
+
+    #[test]
+    fn replayed_time_drives_timers_and_staleness_checks() {
+        let mut clock = ReplayClock::at(1_000_000);
+        let mut wheel: TimerWheel<u8, 64, 4> = TimerWheel::on(&clock);
+        wheel.schedule(7, 5).unwrap();
+        let policy = FreshnessPolicy {
+            backlog_frames: 1,
+            max_age_cycles: 2_000,
+        };
+        let rx_cycles = clock.now_cycles();
+
+        // Out-of-order stamps do not move it back.
+        clock.advance_to(1_004_000);
+        clock.advance_to(1_003_000);
+        assert_eq!((clock.now_unix_ns(), clock.now_unix_ms()), (1_004_000, 1));
+        let mut fired = Vec::new();
+        wheel.advance(clock.now_cycles(), |event| fired.push(event));
+        assert!(fired.is_empty(), "4 us of 5");
+        assert!(policy.skip(2, rx_cycles, &clock), "4 us old");
+
+        clock.advance_to(1_005_000);
+        wheel.advance(clock.now_cycles(), |event| fired.push(event));
+        assert_eq!(fired, [7]);
+    }
+}
@@ -0,0 +1,243 @@
+//! Opportunistic coalescing of replies to the same executor.
+//!
+//! A burst of victims often yields several opportunities for one executor
+//! within a single poll. Sent one datagram each, every reply pays for its
+//! own headers, TX descriptor and executor-side receive. With coalescing on,
+//! replies are held in a [`ReplyCoalescer`] while the poll's batch is
+//! worked through and flushed at its end: a destination with one reply gets
+//! it as a plain `OpportunityReply`, one with several gets a single datagram
+//! of a [`ReplyBatchHeader`] followed by the replies back to back. Nothing
+//! waits for traffic that has not arrived yet, so coalescing adds at most
+//! the rest of the batch to a reply's latency; TX stamps are taken at flush.
+use crate::pacing::Destination;
+use crate::payload::OpportunityReply;
+use bytemuck::{Pod, Zeroable};
+
+/// Magic prefix of a `ReplyBatchHeader` ("MVRB").
+pub const REPLY_BATCH_MAGIC: [u8; 4] = *b"MVRB";
+/// Most replies in one batch; a destination's further replies in the same
+/// poll are sent on their own.
+pub const MAX_BATCH_REPLIES: usize = 8;
+/// Executors with replies held at once; replies to further ones are sent
+/// uncoalesced.
+pub const MAX_COALESCED_DESTINATIONS: usize = 8;
+/// Largest batch datagram.
+pub const MAX_REPLY_BATCH: usize =
+    ReplyBatchHeader::WIRE_SIZE + MAX_BATCH_REPLIES * OpportunityReply::WIRE_SIZE;
+
+/// Prefix of a batched reply datagram, followed by `count` replies.
+///
+/// Layout (8 bytes):
+///   [0..4]   magic      "MVRB"
+///   [4]      count      `OpportunityReply`s that follow, 2..=MAX_BATCH_REPLIES
+///   [5..8]   _reserved
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ReplyBatchHeader {
+    pub magic: [u8; 4],
+    pub count: u8,
+    pub _reserved: [u8; 3],
+}
+
+const _: () = assert!(core::mem::size_of::<ReplyBatchHeader>() == 8);
+
+impl ReplyBatchHeader {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<ReplyBatchHeader>();
+
+    pub fn new(count: u8) -> Self {
+        Self {
+            magic: REPLY_BATCH_MAGIC,
+            count,
+            _reserved: [0; 3],
+        }
+    }
+
+    /// The replies in `datagram`: a batch, or a single plain reply. `None`
+    /// for anything else, including a batch whose count disagrees with its
+    /// length.
+    pub fn replies(datagram: &[u8]) -> Option<&[OpportunityReply]> {
+        if datagram.len() == OpportunityReply::WIRE_SIZE {
+            return bytemuck::try_cast_slice(datagram).ok();
+        }
+        let (header, replies) = datagram.split_at_checked(Self::WIRE_SIZE)?;
+        let header: &Self = bytemuck::try_from_bytes(header).ok()?;
+        if header.magic != REPLY_BATCH_MAGIC
+            || replies.len() != usize::from(header.count) * OpportunityReply::WIRE_SIZE
+        {
+            return None;
+        }
+        bytemuck::try_cast_slice(replies).ok()
+    }
+}
+
+/// What a [`ReplyCoalescer::flush`] sent.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Flushed {
+    /// Datagrams handed out, batched or not.
+    pub datagrams: u64,
+    /// Those of them that were batches.
+    pub batches: u64,
+    /// Replies that left in a batch rather than alone.
+    pub coalesced: u64,
+    /// Replies in datagrams that could not be sent.
+    pub dropped: u64,
+}
+
+struct Pending {
+    to: Destination,
+    replies: heapless::Vec<OpportunityReply, MAX_BATCH_REPLIES>,
+}
+
+/// Replies held for the end of the poll, by destination.
+#[derive(Default)]
+pub struct ReplyCoalescer {
+    pending: heapless::Vec<Pending, MAX_COALESCED_DESTINATIONS>,
+}
+
+impl ReplyCoalescer {
+    pub const fn new() -> Self {
+        Self {
+            pending: heapless::Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Room for one more reply to `to`, zeroed, to be filled in place.
+    /// `None` once `to`'s batch is full or no more destinations fit: the
+    /// reply is then sent on its own.
+    #[inline]
+    pub fn reserve(&mut self, to: Destination) -> Option<&mut [u8]> {
+        let i = match self.pending.iter().position(|p| p.to == to) {
+            Some(i) => i,
+            None => {
+                self.pending
+                    .push(Pending {
+                        to,
+                        replies: heapless::Vec::new(),
+                    })
+                    .ok()?;
+                self.pending.len() - 1
+            }
+        };
+        let replies = &mut self.pending[i].replies;
+        replies.push(OpportunityReply::zeroed()).ok()?;
+        replies.last_mut().map(bytemuck::bytes_of_mut)
+    }
+
+    /// Hand every destination's replies to `send` as one datagram, each
+    /// stamped `tx_ts_ns`, and forget them. `send` returns `false` if the
+    /// datagram could not be sent.
+    pub fn flush(
+        &mut self,
+        tx_ts_ns: u64,
+        mut send: impl FnMut(Destination, &[u8]) -> bool,
+    ) -> Flushed {
+        let mut flushed = Flushed::default();
+        let mut datagram = [0u8; MAX_REPLY_BATCH];
+        for pending in &mut self.pending {
+            for reply in pending.replies.iter_mut() {
+                reply.tx_ts_ns_le = tx_ts_ns.to_le_bytes();
+            }
+            let replies: &[u8] = bytemuck::cast_slice(&pending.replies);
+            let count = pending.replies.len() as u64;
+            let bytes = if count == 1 {
+                replies
+            } else {
+                let len = ReplyBatchHeader::WIRE_SIZE + replies.len();
+                datagram[..ReplyBatchHeader::WIRE_SIZE]
+                    .copy_from_slice(bytemuck::bytes_of(&ReplyBatchHeader::new(count as u8)));
+                datagram[ReplyBatchHeader::WIRE_SIZE..len].copy_from_slice(replies);
+                &datagram[..len]
+            };
+            if send(pending.to, bytes) {
+                flushed.datagrams += 1;
+                if count > 1 {
+                    flushed.batches += 1;
+                    flushed.coalesced += count;
+                }
+            } else {
+                flushed.dropped += count;
+            }
+        }
+        self.pending.clear();
+        flushed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXEC: Destination = ([10, 0, 0, 5], 9000);
+    const OTHER: Destination = ([10, 0, 0, 6], 9000);
+
+    fn reply(coalescer: &mut ReplyCoalescer, to: Destination, nonce: u64) -> bool {
+        let Some(buf) = coalescer.reserve(to) else {
+            return false;
+        };
+        let reply = OpportunityReply::emplace(buf).unwrap();
+        reply.nonce_le = nonce.to_le_bytes();
+        true
+    }
+
+    #[test]
+    fn replies_to_one_executor_share_a_datagram() {
+        let mut coalescer = ReplyCoalescer::new();
+        for nonce in 1..=MAX_BATCH_REPLIES as u64 {
+            assert!(reply(&mut coalescer, EXEC, nonce));
+        }
+        assert!(
+            !reply(&mut coalescer, EXEC, 99),
+            "a full batch refuses more"
+        );
+        assert!(reply(&mut coalescer, OTHER, 7));
+
+        let mut sent = Vec::new();
+        let flushed = coalescer.flush(5_000, |to, bytes| {
+            sent.push((to, bytes.to_vec()));
+            true
+        });
+        assert_eq!(
+            flushed,
+            Flushed {
+                datagrams: 2,
+                batches: 1,
+                coalesced: MAX_BATCH_REPLIES as u64,
+                dropped: 0
+            }
+        );
+        assert!(coalescer.is_empty());
+
+        let (to, batch) = &sent[0];
+        assert_eq!(
+            (*to, batch.len(), &batch[..5]),
+            (EXEC, MAX_REPLY_BATCH, &b"MVRB\x08"[..])
+        );
+        let replies = ReplyBatchHeader::replies(batch).unwrap();
+        assert_eq!(
+            replies
+                .iter()
+                .map(OpportunityReply::nonce)
+                .collect::<Vec<_>>(),
+            (1..=8).collect::<Vec<_>>()
+        );
+        assert!(replies.iter().all(|r| r.tx_ts_ns() == 5_000));
+        // A lone reply goes out as it always did.
+        let (to, single) = &sent[1];
+        assert_eq!((*to, single.len()), (OTHER, OpportunityReply::WIRE_SIZE));
+        assert_eq!(ReplyBatchHeader::replies(single).unwrap()[0].nonce(), 7);
+        assert!(ReplyBatchHeader::replies(&batch[..batch.len() - 1]).is_none());
+
+        assert!(reply(&mut coalescer, EXEC, 1) && reply(&mut coalescer, EXEC, 2));
+        assert_eq!(
+            coalescer.flush(0, |_, _| false),
+            Flushed {
+                dropped: 2,
+                ..Flushed::default()
+            }
+        );
+    }
+}
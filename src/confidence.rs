@@ -0,0 +1,233 @@
+//! Confidence score reported next to an opportunity's profit.
+//!
+//! Profit is what an opportunity is worth if the node's view of the pool is
+//! current and its bundle lands first. The score, in basis points, says how
+//! far to trust both, from three inputs:
+//! - feed staleness: time since the pool feed last delivered an update. A
+//!   silent feed means any pool may have moved unseen;
+//! - pool update age: slots between the pool's last update and the newest
+//!   slot the feed has reported, i.e. how old the reserves priced against are;
+//! - inferred swaps: pending victim swaps applied to the pool's reserves since
+//!   that update (`MEV_INFER_MAX_SWAPS`), each of which may not land;
+//! - competition: the realized win rate of emitted opportunities, or a prior
+//!   until enough outcomes are known (as for the adaptive threshold).
+//!
+//! The two ages and the inferred swaps scale confidence down linearly, reaching zero at their
+//! configured limits; the win rate scales it directly. Executors size or
+//! skip opportunities by the score, and each reply is recorded with it in
+//! the timeline so the model can be calibrated against outcomes.
+use crate::runtime::CacheAlignedAtomicU64;
+
+/// A score of certainty, in basis points.
+pub const FULL_CONFIDENCE_BPS: u16 = 10_000;
+
+/// Stored in place of a win rate that is not known yet.
+const NO_WIN_RATE: u64 = u64::MAX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConfidenceModel {
+    /// Feed silence, in ms, at which confidence reaches zero; 0 ignores it.
+    pub feed_stale_ms: u64,
+    /// Pool update age, in slots, at which confidence reaches zero; 0
+    /// ignores it.
+    pub pool_stale_slots: u64,
+    /// Inferred swaps at which confidence reaches zero; 0 ignores them.
+    pub inferred_stale_swaps: u64,
+    /// Win rate assumed until enough outcomes have been observed (bps).
+    pub prior_win_rate_bps: u32,
+}
+
+impl ConfidenceModel {
+    pub const DEFAULT: Self = Self {
+        feed_stale_ms: 2_000,
+        pool_stale_slots: 32,
+        inferred_stale_swaps: 16,
+        prior_win_rate_bps: 5_000,
+    };
+
+    /// Score `inputs`:
+    ///
+    ///   feed  = 1 - min(feed_age, feed_stale) / feed_stale
+    ///   pool  = 1 - min(pool_age, pool_stale) / pool_stale
+    ///   infer = 1 - min(inferred, inferred_stale) / inferred_stale
+    ///   score = feed * pool * infer * win_rate
+    pub fn score(&self, inputs: &ConfidenceInputs) -> u16 {
+        let full = FULL_CONFIDENCE_BPS as u64;
+        let win_bps = inputs
+            .win_rate_bps
+            .unwrap_or(self.prior_win_rate_bps)
+            .min(FULL_CONFIDENCE_BPS as u32) as u64;
+        let score = decay(inputs.feed_age_ms, self.feed_stale_ms)
+            * decay(inputs.pool_age_slots, self.pool_stale_slots)
+            / full
+            * decay(inputs.inferred_swaps, self.inferred_stale_swaps)
+            / full
+            * win_bps
+            / full;
+        score as u16
+    }
+}
+
+impl Default for ConfidenceModel {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// `age` against `limit`, from full confidence at 0 to none at `limit`.
+fn decay(age: u64, limit: u64) -> u64 {
+    let full = FULL_CONFIDENCE_BPS as u64;
+    if limit == 0 {
+        return full;
+    }
+    full * (limit - age.min(limit)) / limit
+}
+
+/// Inputs to one score.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConfidenceInputs {
+    /// Time since the pool feed last delivered an update, in ms.
+    pub feed_age_ms: u64,
+    /// Slots between the pool's last update and the newest slot seen.
+    pub pool_age_slots: u64,
+    /// Victim swaps inferred into the pool's reserves since that update.
+    pub inferred_swaps: u64,
+    /// Realized win rate in basis points, `None` if not enough samples.
+    pub win_rate_bps: Option<u32>,
+}
+
+/// Shared live inputs: written by the pool feed and the threshold
+/// controller, read by evaluators with relaxed loads.
+pub struct ConfidenceSignals {
+    newest_slot: CacheAlignedAtomicU64,
+    last_update_ms: CacheAlignedAtomicU64,
+    win_rate_bps: CacheAlignedAtomicU64,
+}
+
+impl ConfidenceSignals {
+    pub const fn new() -> Self {
+        Self {
+            newest_slot: CacheAlignedAtomicU64::new(0),
+            last_update_ms: CacheAlignedAtomicU64::new(0),
+            win_rate_bps: CacheAlignedAtomicU64::new(NO_WIN_RATE),
+        }
+    }
+
+    /// Record a pool update for `slot` applied at unix time `now_ms`.
+    pub fn pool_updated(&self, slot: u64, now_ms: u64) {
+        if slot > self.newest_slot.load() {
+            self.newest_slot.store(slot);
+        }
+        self.last_update_ms.store(now_ms);
+    }
+
+    /// Newest slot the pool feed has reported, 0 before its first update.
+    pub fn newest_slot(&self) -> u64 {
+        self.newest_slot.load()
+    }
+
+    pub fn set_win_rate(&self, win_rate_bps: Option<u32>) {
+        self.win_rate_bps
+            .store(win_rate_bps.map_or(NO_WIN_RATE, u64::from));
+    }
+
+    /// Inputs for an opportunity on a pool last updated at `pool_slot`.
+    /// Until the feed delivers its first update, it counts as fresh. No
+    /// swaps are inferred; that count comes with the pool.
+    #[inline(always)]
+    pub fn inputs(&self, pool_slot: u64, now_ms: u64) -> ConfidenceInputs {
+        let last_update_ms = self.last_update_ms.load();
+        let win_rate_bps = self.win_rate_bps.load();
+        ConfidenceInputs {
+            feed_age_ms: if last_update_ms == 0 {
+                0
+            } else {
+                now_ms.saturating_sub(last_update_ms)
+            },
+            pool_age_slots: self.newest_slot.load().saturating_sub(pool_slot),
+            inferred_swaps: 0,
+            win_rate_bps: (win_rate_bps != NO_WIN_RATE).then_some(win_rate_bps as u32),
+        }
+    }
+}
+
+impl Default for ConfidenceSignals {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staleness_and_competition_discount_the_score() {
+        let model = ConfidenceModel {
+            feed_stale_ms: 1_000,
+            pool_stale_slots: 10,
+            inferred_stale_swaps: 4,
+            prior_win_rate_bps: 5_000,
+        };
+        let fresh = ConfidenceInputs {
+            win_rate_bps: Some(10_000),
+            ..ConfidenceInputs::default()
+        };
+        assert_eq!(model.score(&fresh), FULL_CONFIDENCE_BPS);
+        assert_eq!(
+            model.score(&ConfidenceInputs {
+                win_rate_bps: None,
+                ..fresh
+            }),
+            5_000
+        );
+        let aging = ConfidenceInputs {
+            feed_age_ms: 250,
+            pool_age_slots: 5,
+            win_rate_bps: Some(8_000),
+            inferred_swaps: 0,
+        };
+        assert_eq!(model.score(&aging), 3_000);
+        assert_eq!(
+            model.score(&ConfidenceInputs {
+                inferred_swaps: 2,
+                ..aging
+            }),
+            1_500
+        );
+        assert_eq!(
+            model.score(&ConfidenceInputs {
+                feed_age_ms: 5_000,
+                ..aging
+            }),
+            0
+        );
+        let ignore_ages = ConfidenceModel {
+            feed_stale_ms: 0,
+            pool_stale_slots: 0,
+            inferred_stale_swaps: 0,
+            ..model
+        };
+        assert_eq!(
+            ignore_ages.score(&ConfidenceInputs {
+                feed_age_ms: u64::MAX,
+                inferred_swaps: 9,
+                ..aging
+            }),
+            8_000
+        );
+
+        let signals = ConfidenceSignals::new();
+        assert_eq!(signals.inputs(0, 9_000), ConfidenceInputs::default());
+        signals.pool_updated(120, 8_000);
+        signals.pool_updated(115, 8_500);
+        signals.set_win_rate(Some(4_200));
+        let inputs = ConfidenceInputs {
+            feed_age_ms: 500,
+            pool_age_slots: 20,
+            inferred_swaps: 0,
+            win_rate_bps: Some(4_200),
+        };
+        assert_eq!(signals.inputs(100, 9_000), inputs);
+    }
+}
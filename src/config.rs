@@ -0,0 +1,1888 @@
+//! Node configuration, read once at startup from `MEV_*` environment variables.
+//!
+//! Parsing goes through a lookup closure so it can be exercised in tests
+//! without touching the process environment. Unparseable values fall back to
+//! defaults and are reported with `log::warn!`.
+use crate::ack::AckPolicy;
+use crate::admin::{AdminAuth, Role, MAX_GRANTS};
+use crate::alloc_audit::AuditPolicy;
+use crate::anomaly::AnomalyConfig;
+use crate::backpressure::BackpressurePolicy;
+use crate::burst::{BurstConfig, MAX_RX_BATCH};
+use crate::confidence::ConfidenceModel;
+use crate::cores::CoreLayout;
+use crate::discovery::DiscoveryConfig;
+use crate::divergence::DivergenceConfig;
+use crate::dpdk::{DpdkConfig, UdpEndpoint};
+use crate::flow_control::FlowPolicy;
+use crate::freshness::FreshnessPolicy;
+use crate::horizon::MAX_HORIZON_SLOTS;
+use crate::idle::{IdleMode, IdlePolicy, MAX_IDLE_WAIT_NS};
+use crate::journal::JournalConfig;
+use crate::leader::LeaderConfig;
+use crate::metrics_shm::MetricsShmConfig;
+use crate::mirror::MirrorConfig;
+use crate::netlink::{ExecutorNeighbor, ReplyQdisc, MAX_EXECUTORS};
+use crate::pacing::PacingPolicy;
+use crate::pool_registry::PoolAddress;
+use crate::ports::PortLayout;
+use crate::privacy::{PrivacyConfig, MAX_SIGNATURES};
+use crate::processor::{self, Evaluator, PriorityFees, Strategy};
+use crate::reorder::MAX_REORDER_DEPTH;
+use crate::reply_routes::ReplyRoutes;
+use crate::reputation::ReputationPolicy;
+use crate::schema::VersionWindow;
+use crate::selftest::CalibrationMode;
+use crate::sign::{ReplySigner, MAX_KEY_LEN};
+use crate::sim::RaceModel;
+use crate::slo::{Slo, SloPolicy};
+use crate::source_filter::SourceFilter;
+use crate::state_source::{SourceKind, StateSourceConfig};
+use crate::statsd::StatsdConfig;
+use crate::threshold::ThresholdConfig;
+use crate::tokens::{self, ProfitNormalizer, TokenId};
+use crate::transport::tap::SocketBuffers;
+use crate::warmup::WarmupConfig;
+use crate::xdp::{InterfaceName, MAX_INTERFACE_NAME};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Tap,
+    AfXdp,
+    Dpdk,
+}
+
+/// Maximum number of `MEV_POOL_MIN_PROFIT` entries.
+pub const MAX_POOL_OVERRIDES: usize = 64;
+
+/// Exactly two hex digits. `from_str_radix` alone also takes a sign, so
+/// `+f` would pass for a byte.
+fn hex_byte(pair: &[u8]) -> Option<u8> {
+    let [hi, lo] = *pair else {
+        return None;
+    };
+    let digit = |d: u8| (d as char).to_digit(16);
+    Some(((digit(hi)? << 4) | digit(lo)?) as u8)
+}
+
+/// `aa:bb:cc:dd:ee:ff`.
+fn parse_mac(s: &str) -> Option<[u8; 6]> {
+    let mut mac = [0u8; 6];
+    let mut parts = s.split(':');
+    for byte in mac.iter_mut() {
+        *byte = hex_byte(parts.next()?.as_bytes())?;
+    }
+    parts.next().is_none().then_some(mac)
+}
+
+/// 20-byte address as 40 hex digits, with or without a `0x` prefix.
+pub(crate) fn parse_address(s: &str) -> Option<PoolAddress> {
+    let hex = s.strip_prefix("0x").unwrap_or(s);
+    if hex.len() != 40 {
+        return None;
+    }
+    let mut address = [0u8; 20];
+    for (byte, pair) in address.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = hex_byte(pair)?;
+    }
+    Some(address)
+}
+
+/// Hex key of at most [`MAX_KEY_LEN`] bytes, with or without a `0x` prefix.
+fn parse_key(s: &str) -> Option<heapless::Vec<u8, MAX_KEY_LEN>> {
+    let hex = s.strip_prefix("0x").unwrap_or(s);
+    if !hex.len().is_multiple_of(2) || hex.len() > 2 * MAX_KEY_LEN {
+        return None;
+    }
+    hex.as_bytes().chunks(2).map(hex_byte).collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NodeConfig {
+    /// `MEV_BACKEND`: `tap` (default), `af_xdp` or `dpdk`.
+    pub backend: Backend,
+    /// `MEV_NUMERAIRE`: token symbol profits are reported in.
+    pub numeraire: TokenId,
+    /// `MEV_MIN_PROFIT`: minimum profit in raw numéraire units.
+    pub min_profit: u64,
+    /// `MEV_MIN_PROFIT_<STRATEGY>` (e.g. `MEV_MIN_PROFIT_SANDWICH`):
+    /// per-strategy override of `min_profit`.
+    pub strategy_min_profit: [Option<u64>; Strategy::COUNT],
+    /// `MEV_POOL_MIN_PROFIT`: comma-separated `<hex address>:<min profit>`
+    /// overrides, applied to the pool-table entries at startup.
+    pub pool_min_profit: heapless::Vec<(PoolAddress, u64), MAX_POOL_OVERRIDES>,
+    /// `MEV_POOL_SEED_FILE`: CSV of pool states the table starts from (see
+    /// [`crate::pool_seed`]) instead of the traffic generator's mock pools.
+    pub pool_seed_file: Option<String>,
+    /// `MEV_EXEC_LATENCY_MS`: expected decision-to-landing latency.
+    pub execution_latency_ms: u64,
+    /// `MEV_GAS_COST`: gas cost of one bundle in numéraire units.
+    pub gas_cost: u64,
+    /// `MEV_OUTBID_GAS` / `MEV_FEE_TOKEN`: gas our front-run pays a
+    /// victim's priority fee on (0, the default, ignores victim fees) and
+    /// the token fees are paid in (default WETH).
+    pub priority_fees: PriorityFees,
+    /// `MEV_MIN_AMOUNT_IN_FLOOR` / `MEV_MIN_AMOUNT_IN_CEIL` /
+    /// `MEV_LATENCY_BUDGET_CYCLES`: adaptive threshold bounds.
+    pub threshold: ThresholdConfig,
+    /// `MEV_CONFIDENCE_FEED_STALE_MS` / `MEV_CONFIDENCE_POOL_STALE_SLOTS` /
+    /// `MEV_CONFIDENCE_INFERRED_SWAPS`: feed silence, pool update age and
+    /// inferred swaps at which an opportunity's confidence reaches zero (0
+    /// ignores that input).
+    pub confidence: ConfidenceModel,
+    /// `MEV_INFER_MAX_SWAPS`: victim swaps applied to a pool's reserves
+    /// between explicit updates; 0 (default) disables reserve inference.
+    pub infer_max_swaps: u16,
+    /// `MEV_CALIBRATE`: `off`, `report` or `apply` — what to do with the
+    /// startup measurement of per-stage costs. Defaults to `apply`, or to
+    /// `report` when `MEV_LATENCY_BUDGET_CYCLES` is set explicitly.
+    pub calibration: CalibrationMode,
+    /// `MEV_PORTS`: local UDP port per message class,
+    /// `swaps=8080,pools=8081,control=8082`; see [`crate::ports`].
+    pub ports: PortLayout,
+    /// `MEV_CORES`: core per pipeline role,
+    /// `rx=2,strategy=2,tx=2,housekeeping=0,exporter=1`; see [`crate::cores`].
+    pub cores: CoreLayout,
+    /// `MEV_ALLOWED_SOURCES`: comma-separated `ip[/len][:port[-port]]`
+    /// rules; empty allows all sources.
+    pub allowed_sources: SourceFilter,
+    /// `MEV_XDP_IFACE`: interface the AF_XDP socket binds to (default
+    /// `veth0`).
+    pub xdp_iface: InterfaceName,
+    /// `MEV_XDP_PREFILTER`: attach the in-kernel port/length/magic filter
+    /// in AF_XDP mode (`1`/`true`).
+    pub xdp_prefilter: bool,
+    /// `MEV_XDP_MAX_FRAME_AGE_CYCLES`: AF_XDP frames known to be older
+    /// than this at evaluation are dropped (0, the default, disables).
+    pub xdp_max_frame_age_cycles: u64,
+    /// `MEV_RELAY_FEED`: relayer `ip:port` to pull the pool feed from over
+    /// a resumable TCP session, alongside the UDP feed.
+    pub relay_feed: Option<([u8; 4], u16)>,
+    /// `MEV_MIRROR_VICTIMS` / `MEV_MIRROR_POOLS` (`ip:port`, unicast or
+    /// multicast): re-publish the normalized feed downstream.
+    pub mirror: MirrorConfig,
+    /// `MEV_RELAY_XOFF_PCT` / `MEV_RELAY_XON_PCT`: receive-buffer occupancy
+    /// at which the relayer is sent XOFF, and drained to before XON.
+    /// Unset, the relayer is never paused.
+    pub relay_flow: FlowPolicy,
+    /// `MEV_SIM`: dry run — opportunities are settled by the simulator
+    /// instead of being sent. Tuned by `MEV_SIM_COMPETITOR_NS` (`min-max`),
+    /// `MEV_SIM_INCLUSION_BPS` and `MEV_SIM_SEED`.
+    pub sim: Option<RaceModel>,
+    /// `MEV_DIVERGENCE_BPS`: book-vs-pool divergence alerts, sent to
+    /// `MEV_DIVERGENCE_ALERT_TO` (`ip:port`) after more than
+    /// `MEV_DIVERGENCE_UPDATES` consecutive diverged updates.
+    pub divergence: Option<DivergenceConfig>,
+    /// `MEV_ANOMALY_FACTOR`: flag pools whose update rate or reserve moves
+    /// run that many times (at least 2) past their norm, if the moves average at least
+    /// `MEV_ANOMALY_MIN_MOVE_BPS`; with `MEV_ANOMALY_SUPPRESS_MS`, no
+    /// strategy touches a flagged pool until it has been calm that long.
+    pub anomaly: Option<AnomalyConfig>,
+    /// `MEV_DISCOVERY_TO` (`ip:port`): where requests for pools seen in
+    /// victims but missing from the table go, once a pool has been missed
+    /// `MEV_DISCOVERY_MIN_HITS` times; repeated every
+    /// `MEV_DISCOVERY_RETRY_MS` until the feed delivers it.
+    pub discovery: DiscoveryConfig,
+    /// `MEV_LEADER_PEER` (`ip:port`): hot/standby with the node there, only
+    /// the elected one replying. `MEV_LEADER_ID` must differ between the
+    /// two; heartbeats are received on `MEV_LEADER_PORT`, sent every
+    /// `MEV_LEADER_HEARTBEAT_US`, and a standby takes over after
+    /// `MEV_LEADER_TIMEOUT_US` without one from the leader.
+    pub leader: Option<LeaderConfig>,
+    /// Victim sender reputation: `MEV_REPUTATION_MIN_SAMPLES` outcomes before
+    /// a sender's hit rate applies, `MEV_REPUTATION_SKIP_BPS` hit rate below
+    /// which its victims are skipped.
+    pub reputation: ReputationPolicy,
+    /// Private-routing filter (`src/privacy.rs`): `MEV_PRIVATE_BUILDERS` and
+    /// `MEV_PRIVATE_ROUTERS`, comma-separated sender addresses, and
+    /// `MEV_PRIVATE_ZERO_TIP=1` to flag EVM victims paying no priority fee.
+    pub privacy: PrivacyConfig,
+    /// `MEV_HORIZON_SLOTS`: slots a victim that did not pay on arrival stays
+    /// parked and is priced again at each new one (`src/horizon.rs`); 0
+    /// disables, at most `MAX_HORIZON_SLOTS`.
+    pub horizon_slots: u64,
+    /// `MEV_REPLY_IFACE`: interface replies leave through; defaults to the
+    /// backend's interface.
+    pub reply_iface: Option<String>,
+    /// `MEV_EXECUTORS`: comma-separated `<ip>@<mac>` executor endpoints,
+    /// installed as /32 routes and permanent neighbor entries on the reply
+    /// interface at startup.
+    pub executors: heapless::Vec<ExecutorNeighbor, MAX_EXECUTORS>,
+    /// `MEV_REPLY_ROUTES`: per (strategy, pool, message kind) executor
+    /// destinations, see [`crate::reply_routes`]; unmatched messages go back
+    /// to their source. Headers are built from `MEV_REPLY_SOURCE`
+    /// (`ip:port@mac`, default the TAP node's address).
+    pub reply_routes: ReplyRoutes,
+    pub reply_source: UdpEndpoint,
+    /// `MEV_PACE_INTERVAL_CYCLES`: cycles per message each destination is
+    /// allowed on average, in bursts of up to `MEV_PACE_BURST` (default 8);
+    /// messages above the rate are delayed. 0, the default, disables pacing.
+    pub pacing: PacingPolicy,
+    /// `MEV_REPLY_COALESCE`: UDP replies to the same executor within one
+    /// poll leave as one batched datagram (`src/coalesce.rs`).
+    pub reply_coalesce: bool,
+    /// `MEV_REPLY_QDISC`: `noqueue` or `mq` root qdisc for the reply
+    /// interface; left untouched when unset.
+    pub reply_qdisc: Option<ReplyQdisc>,
+    /// `MEV_REPLY_ACK_TTL_MS`: expect a `ReplyAck` for every reply within
+    /// this many milliseconds and count those that get none
+    /// (`src/ack.rs`); unset, replies are not tracked. With
+    /// `MEV_REPLY_RETRANSMIT_TO=ip:port`, an unacknowledged reply is first
+    /// sent once more there.
+    pub reply_ack: Option<AckPolicy>,
+    /// `MEV_NODE_ID`: this instance's id, carried in reply signatures.
+    pub node_id: u32,
+    /// `MEV_REPLY_KEY`: hex key, 16 to 64 bytes, every reply is signed with
+    /// (`src/sign.rs`); unset, replies go out unsigned.
+    pub reply_signer: Option<ReplySigner>,
+    /// `MEV_REORDER_DEPTH`: pool updates held back waiting for a missing
+    /// predecessor (0 disables reordering, at most `MAX_REORDER_DEPTH`), each
+    /// for at most `MEV_REORDER_HOLD_CYCLES`.
+    pub reorder_depth: usize,
+    pub reorder_hold_cycles: u64,
+    /// `MEV_POOL_FEC`: decode XOR-parity coded pool-feed datagrams
+    /// (`src/fec.rs`), rebuilding one lost message per group.
+    pub pool_fec: bool,
+    /// `MEV_POOL_CONFLATE`: apply only the newest update per pool of each
+    /// pool-feed batch (`src/conflate.rs`); on by default, `0` disables.
+    pub pool_conflate: bool,
+    /// `MEV_BACKPRESSURE_POLLS` (0 disables), `MEV_BACKPRESSURE_CLEAR_CYCLES`
+    /// and `MEV_BACKPRESSURE_KEEP`: how a backed-up TX path makes the RX
+    /// stages shed load; see [`crate::backpressure`].
+    pub backpressure: BackpressurePolicy,
+    /// `MEV_FEED_CLOCK_CORRECTION`: move the send times of version 3 pool
+    /// updates onto the node's clock by each feed's estimated skew
+    /// (`src/skew.rs`); on by default, `0` trusts the relayers' clocks.
+    pub feed_clock_correction: bool,
+    /// `MEV_WIRE_VERSIONS`: schema versions accepted on the feeds, `min-max`
+    /// or a single version (default: every version this build decodes).
+    pub wire_versions: VersionWindow,
+    /// `MEV_STALE_BACKLOG`: queued victim frames above which frames older
+    /// than `MEV_STALE_AGE_CYCLES` are skipped (0, the default, disables).
+    pub freshness: FreshnessPolicy,
+    /// `MEV_RX_BATCH` (`min-max`): bounds of the adaptive victim RX batch,
+    /// in frames (default 8-32, at most 64).
+    pub rx_batch: BurstConfig,
+    /// `MEV_IDLE`: `spin` (default), `pause`, `tpause` or `umwait` — how a
+    /// polling loop waits once `MEV_IDLE_SPIN_POLLS` polls in a row found
+    /// nothing, for at most `MEV_IDLE_WAIT_NS`; see [`crate::idle`].
+    pub idle: IdlePolicy,
+    /// `MEV_PHC_DEVICE`: PTP hardware clock (e.g. `/dev/ptp0`) that reply
+    /// timestamps are expressed in, for cross-host one-way latency.
+    pub phc_device: Option<String>,
+    /// `MEV_STATE_FILE`: file pool sequences and recent replies are mapped
+    /// to, so a restart resumes from them; `MEV_STATE_SYNC_MS` is how often
+    /// it is rewritten.
+    pub state_file: Option<String>,
+    pub state_sync_ms: u64,
+    /// `MEV_RECORD_TIMELINE`: file every applied pool update and evaluated
+    /// victim is appended to, for `backtest` runs.
+    pub record_timeline: Option<String>,
+    /// `MEV_JOURNAL_ROTATE_BYTES`, `MEV_JOURNAL_ROTATE_MS`,
+    /// `MEV_JOURNAL_KEEP`, `MEV_JOURNAL_COMPRESS`, `MEV_JOURNAL_PARQUET`:
+    /// how the recorded timeline is rotated, compressed, exported and pruned.
+    pub journal: JournalConfig,
+    /// `MEV_STATS_INTERVAL_MS`: how often the stats line is logged.
+    pub stats_interval_ms: u64,
+    /// `MEV_POOL_REPACK_MS`: how often the most-quoted pools are repacked
+    /// into the pool table's hot tier; 0 (the default) never.
+    pub pool_repack_ms: u64,
+    /// `MEV_STATSD=ip:port`: push counters to a statsd collector every
+    /// `MEV_STATSD_INTERVAL_MS` (default 10000), names prefixed with
+    /// `MEV_STATSD_PREFIX` (default `mev`).
+    pub statsd: Option<StatsdConfig>,
+    /// `MEV_METRICS_SHM=path`: publish the counters to a shared-memory
+    /// segment for scrapers every `MEV_METRICS_SHM_INTERVAL_MS` (default
+    /// 1000).
+    pub metrics_shm: Option<MetricsShmConfig>,
+    /// `MEV_SLO_PARSE_P99_NS` / `MEV_SLO_E2E_P99_NS`: latency SLOs whose
+    /// alarm is raised after `MEV_SLO_WINDOW` (default 10) one-second checks
+    /// in a row above the threshold. Unset SLOs are not monitored.
+    pub slo: SloPolicy,
+    /// `MEV_PROFILE_SAMPLE_EVERY`: the stage profiler samples one main-loop
+    /// iteration in this many (default 64). `MEV_PROFILE=1` starts it at
+    /// boot instead of on `profile start` from the admin socket.
+    pub profile_sample_every: u32,
+    pub profile_at_start: bool,
+    /// Startup warmup before the first packet: `MEV_WARMUP=0` skips it,
+    /// `MEV_WARMUP_PACKETS` synthetic victims prime the evaluator (default
+    /// 4096), `MEV_MLOCK=1` also locks all memory mapped by then.
+    pub warmup: WarmupConfig,
+    /// `MEV_TAP_MTU`: MTU set on the TAP device (e.g. 9000 for jumbo
+    /// frames); unset keeps the device's.
+    pub tap_mtu: Option<u32>,
+    /// smoltcp socket buffers: `MEV_TCP_RX_BYTES`, `MEV_TCP_TX_BYTES`,
+    /// `MEV_UDP_RX_BYTES`, `MEV_UDP_RX_PACKETS`, `MEV_UDP_TX_BYTES`,
+    /// `MEV_UDP_TX_PACKETS`, `MEV_POOL_RX_BYTES`, `MEV_POOL_RX_PACKETS`,
+    /// `MEV_CONTROL_RX_BYTES`, `MEV_CONTROL_RX_PACKETS` and
+    /// `MEV_RELAY_RX_BYTES`.
+    pub socket_buffers: SocketBuffers,
+    /// `MEV_ADMIN_SOCKET`: Unix socket path of the admin API. Roles come
+    /// from `MEV_ADMIN_UIDS` (`<uid>:<role>`) and `MEV_ADMIN_TOKENS`
+    /// (`<role>:<token>`), comma-separated; roles are `readonly`/`ro` and
+    /// `operator`/`op`.
+    pub admin_socket: Option<String>,
+    pub admin_auth: AdminAuth,
+    /// `MEV_HANDOVER_SOCKET`: Unix socket path for downtime-free upgrades.
+    /// The node takes over from one listening there at startup, then
+    /// listens there for its own successor.
+    pub handover_socket: Option<String>,
+    /// `MEV_STATE_SNAPSHOT`: pool snapshot file (`dump-pools --bin`) applied
+    /// at startup. `MEV_SOURCE_PRIORITY_<KIND>` (`WIRE`, `GEYSER`, `RPC`,
+    /// `SNAPSHOT`; lower wins) ranks sources that disagree on a slot, while
+    /// they have delivered within `MEV_SOURCE_STALE_MS`.
+    pub state_sources: StateSourceConfig,
+    /// `MEV_ALLOC_AUDIT`: `count` or `abort` on a heap allocation inside
+    /// the packet loop. Only takes effect in `alloc_audit` builds.
+    pub alloc_audit: AuditPolicy,
+    /// DPDK backend: `MEV_DPDK_EAL_ARGS`, `MEV_DPDK_PORT`, `MEV_DPDK_QUEUES`
+    /// (one worker lcore each), `MEV_DPDK_MBUFS`, `MEV_DPDK_SHARD_POOLS`
+    /// (per-worker pool tables, RSS-aligned) and `MEV_DPDK_RX_CHECKSUM`
+    /// (`0` skips software checksum verification).
+    pub dpdk: DpdkConfig,
+}
+
+impl Default for NodeConfig {
+    fn default() -> Self {
+        Self {
+            backend: Backend::Tap,
+            numeraire: tokens::USDC,
+            min_profit: processor::DEFAULT_MIN_PROFIT,
+            strategy_min_profit: [None; Strategy::COUNT],
+            pool_min_profit: heapless::Vec::new(),
+            pool_seed_file: None,
+            execution_latency_ms: 0,
+            gas_cost: 10_000,
+            priority_fees: PriorityFees::OFF,
+            threshold: ThresholdConfig::default(),
+            confidence: ConfidenceModel::DEFAULT,
+            infer_max_swaps: 0,
+            calibration: CalibrationMode::Apply,
+            ports: PortLayout::default(),
+            cores: CoreLayout::default(),
+            allowed_sources: SourceFilter::allow_all(),
+            xdp_iface: InterfaceName::DEFAULT,
+            xdp_prefilter: false,
+            xdp_max_frame_age_cycles: 0,
+            relay_feed: None,
+            mirror: MirrorConfig::default(),
+            relay_flow: FlowPolicy::DISABLED,
+            sim: None,
+            divergence: None,
+            anomaly: None,
+            discovery: DiscoveryConfig::default(),
+            leader: None,
+            reputation: ReputationPolicy::default(),
+            privacy: PrivacyConfig::default(),
+            horizon_slots: 0,
+            reply_iface: None,
+            executors: heapless::Vec::new(),
+            reply_routes: ReplyRoutes::default(),
+            reply_source: UdpEndpoint {
+                mac: [0x02, 0, 0, 0, 0, 0x01],
+                addr: [192, 168, 69, 2],
+                port: 8080,
+            },
+            pacing: PacingPolicy {
+                interval_cycles: 0,
+                burst: 8,
+            },
+            reply_coalesce: false,
+            reply_qdisc: None,
+            reply_ack: None,
+            node_id: 0,
+            reply_signer: None,
+            reorder_depth: 0,
+            reorder_hold_cycles: 300_000,
+            pool_fec: false,
+            pool_conflate: true,
+            backpressure: BackpressurePolicy::default(),
+            feed_clock_correction: true,
+            wire_versions: VersionWindow::ALL,
+            freshness: FreshnessPolicy {
+                backlog_frames: 0,
+                max_age_cycles: 300_000,
+            },
+            rx_batch: BurstConfig::default(),
+            idle: IdlePolicy::default(),
+            phc_device: None,
+            state_file: None,
+            state_sync_ms: 10,
+            record_timeline: None,
+            journal: JournalConfig::default(),
+            stats_interval_ms: 10_000,
+            pool_repack_ms: 0,
+            statsd: None,
+            metrics_shm: None,
+            slo: SloPolicy::default(),
+            profile_sample_every: 64,
+            profile_at_start: false,
+            warmup: WarmupConfig::default(),
+            tap_mtu: None,
+            socket_buffers: SocketBuffers::default(),
+            admin_socket: None,
+            admin_auth: AdminAuth::default(),
+            handover_socket: None,
+            state_sources: StateSourceConfig::default(),
+            alloc_audit: AuditPolicy::Count,
+            dpdk: DpdkConfig::default(),
+        }
+    }
+}
+
+impl NodeConfig {
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    pub fn from_lookup(get: impl Fn(&str) -> Option<String>) -> Self {
+        let mut cfg = Self::default();
+        let num = |key: &str, default: u64| -> u64 {
+            match get(key) {
+                Some(v) => v.parse().unwrap_or_else(|_| {
+                    log::warn!("{key}={v:?} is not a number, using {default}");
+                    default
+                }),
+                None => default,
+            }
+        };
+
+        if let Some(v) = get("MEV_BACKEND") {
+            cfg.backend = match v.to_ascii_lowercase().as_str() {
+                "af_xdp" => Backend::AfXdp,
+                "dpdk" => Backend::Dpdk,
+                _ => Backend::Tap,
+            };
+        }
+        if let Some(symbol) = get("MEV_NUMERAIRE") {
+            match tokens::token_by_symbol(&symbol).filter(|&id| ProfitNormalizer::new(id).is_some())
+            {
+                Some(id) => cfg.numeraire = id,
+                None => log::warn!("Unknown or unpriced numeraire {symbol:?}, keeping USDC"),
+            }
+        }
+        cfg.min_profit = num("MEV_MIN_PROFIT", cfg.min_profit);
+        for strategy in Strategy::ALL {
+            let key = format!("MEV_MIN_PROFIT_{}", strategy.name().to_ascii_uppercase());
+            if get(&key).is_some() {
+                cfg.strategy_min_profit[strategy as usize] = Some(num(&key, cfg.min_profit));
+            }
+        }
+        if let Some(list) = get("MEV_POOL_MIN_PROFIT") {
+            for item in list.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+                let parsed = item
+                    .split_once(':')
+                    .and_then(|(a, v)| Some((parse_address(a)?, v.parse().ok()?)));
+                match parsed {
+                    Some(entry) => {
+                        if cfg.pool_min_profit.push(entry).is_err() {
+                            log::warn!("More than {MAX_POOL_OVERRIDES} MEV_POOL_MIN_PROFIT entries, ignoring the rest");
+                            break;
+                        }
+                    }
+                    None => log::warn!("Invalid MEV_POOL_MIN_PROFIT entry {item:?}, expected <address>:<min profit>"),
+                }
+            }
+        }
+        cfg.pool_seed_file = get("MEV_POOL_SEED_FILE").filter(|v| !v.is_empty());
+        cfg.execution_latency_ms = num("MEV_EXEC_LATENCY_MS", cfg.execution_latency_ms);
+        cfg.gas_cost = num("MEV_GAS_COST", cfg.gas_cost);
+        cfg.priority_fees.outbid_gas = num("MEV_OUTBID_GAS", cfg.priority_fees.outbid_gas);
+        if let Some(symbol) = get("MEV_FEE_TOKEN") {
+            match tokens::token_by_symbol(&symbol).filter(|&id| ProfitNormalizer::new(id).is_some())
+            {
+                Some(id) => cfg.priority_fees.fee_token = id,
+                None => log::warn!("Unknown or unpriced MEV_FEE_TOKEN {symbol:?}, keeping WETH"),
+            }
+        }
+        cfg.threshold.floor = num("MEV_MIN_AMOUNT_IN_FLOOR", cfg.threshold.floor);
+        cfg.threshold.ceiling = num("MEV_MIN_AMOUNT_IN_CEIL", cfg.threshold.ceiling);
+        cfg.threshold.latency_budget_cycles = num(
+            "MEV_LATENCY_BUDGET_CYCLES",
+            cfg.threshold.latency_budget_cycles,
+        );
+        if get("MEV_LATENCY_BUDGET_CYCLES").is_some() {
+            cfg.calibration = CalibrationMode::Report;
+        }
+        cfg.confidence.feed_stale_ms =
+            num("MEV_CONFIDENCE_FEED_STALE_MS", cfg.confidence.feed_stale_ms);
+        cfg.confidence.pool_stale_slots = num(
+            "MEV_CONFIDENCE_POOL_STALE_SLOTS",
+            cfg.confidence.pool_stale_slots,
+        );
+        cfg.confidence.inferred_stale_swaps = num(
+            "MEV_CONFIDENCE_INFERRED_SWAPS",
+            cfg.confidence.inferred_stale_swaps,
+        );
+        cfg.confidence.prior_win_rate_bps = cfg.threshold.prior_win_rate_bps;
+        cfg.infer_max_swaps =
+            num("MEV_INFER_MAX_SWAPS", cfg.infer_max_swaps as u64).min(u16::MAX as u64) as u16;
+        if let Some(v) = get("MEV_CALIBRATE") {
+            cfg.calibration = match v.to_ascii_lowercase().as_str() {
+                "off" => CalibrationMode::Off,
+                "report" => CalibrationMode::Report,
+                "apply" => CalibrationMode::Apply,
+                _ => {
+                    log::warn!("Invalid MEV_CALIBRATE {v:?}, expected off, report or apply");
+                    cfg.calibration
+                }
+            };
+        }
+        if let Some(v) = get("MEV_XDP_IFACE") {
+            match InterfaceName::new(&v) {
+                Some(name) => cfg.xdp_iface = name,
+                None => log::warn!(
+                    "Invalid MEV_XDP_IFACE {v:?}, expected 1 to {MAX_INTERFACE_NAME} bytes"
+                ),
+            }
+        }
+        if let Some(v) = get("MEV_XDP_PREFILTER") {
+            cfg.xdp_prefilter = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        cfg.xdp_max_frame_age_cycles =
+            num("MEV_XDP_MAX_FRAME_AGE_CYCLES", cfg.xdp_max_frame_age_cycles);
+        if let Some(relay) = get("MEV_RELAY_FEED") {
+            match relay.parse::<std::net::SocketAddrV4>() {
+                Ok(addr) => cfg.relay_feed = Some((addr.ip().octets(), addr.port())),
+                Err(_) => log::warn!("Invalid MEV_RELAY_FEED {relay:?}, expected ip:port"),
+            }
+        }
+        for (var, target) in [
+            ("MEV_MIRROR_VICTIMS", &mut cfg.mirror.victims_to),
+            ("MEV_MIRROR_POOLS", &mut cfg.mirror.pools_to),
+        ] {
+            if let Some(v) = get(var) {
+                match v.parse::<std::net::SocketAddrV4>() {
+                    Ok(addr) => *target = Some((addr.ip().octets(), addr.port())),
+                    Err(_) => log::warn!("Invalid {var} {v:?}, expected ip:port"),
+                }
+            }
+        }
+        let xoff = num("MEV_RELAY_XOFF_PCT", 0).min(100) as u8;
+        if xoff > 0 {
+            let xon = num("MEV_RELAY_XON_PCT", u64::from(xoff / 2)).min(u64::from(xoff - 1)) as u8;
+            cfg.relay_flow = FlowPolicy {
+                xoff_pct: xoff,
+                xon_pct: xon,
+            };
+        }
+        if let Some(spec) = get("MEV_PORTS") {
+            match PortLayout::parse(&spec) {
+                Ok(ports) => cfg.ports = ports,
+                Err(e) => log::warn!("Invalid MEV_PORTS {spec:?} ({e}), keeping {}", cfg.ports),
+            }
+        }
+        if let Some(spec) = get("MEV_CORES") {
+            match CoreLayout::parse(&spec) {
+                Ok(cores) => cfg.cores = cores,
+                Err(e) => log::warn!("Invalid MEV_CORES {spec:?} ({e}), keeping {}", cfg.cores),
+            }
+        }
+        if let Some(list) = get("MEV_ALLOWED_SOURCES") {
+            match SourceFilter::parse(&list) {
+                Some(filter) => cfg.allowed_sources = filter,
+                None => log::warn!("Invalid MEV_ALLOWED_SOURCES {list:?}, allowing all sources"),
+            }
+        }
+        if get("MEV_SIM").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true")) {
+            let mut model = RaceModel::default();
+            if let Some(range) = get("MEV_SIM_COMPETITOR_NS") {
+                match range
+                    .split_once('-')
+                    .and_then(|(lo, hi)| Some((lo.parse().ok()?, hi.parse().ok()?)))
+                {
+                    Some((lo, hi)) if lo <= hi => {
+                        (model.competitor_min_ns, model.competitor_max_ns) = (lo, hi)
+                    }
+                    _ => log::warn!("Invalid MEV_SIM_COMPETITOR_NS {range:?}, expected min-max"),
+                }
+            }
+            model.inclusion_bps =
+                num("MEV_SIM_INCLUSION_BPS", model.inclusion_bps as u64).min(10_000) as u32;
+            model.seed = num("MEV_SIM_SEED", model.seed);
+            cfg.sim = Some(model);
+        }
+        if get("MEV_DIVERGENCE_BPS").is_some() {
+            let defaults = DivergenceConfig::default();
+            let mut alerts = DivergenceConfig {
+                threshold_bps: num("MEV_DIVERGENCE_BPS", defaults.threshold_bps as u64)
+                    .min(u32::MAX as u64) as u32,
+                min_updates: num("MEV_DIVERGENCE_UPDATES", defaults.min_updates as u64)
+                    .min(u32::MAX as u64) as u32,
+                alert_to: None,
+            };
+            if let Some(to) = get("MEV_DIVERGENCE_ALERT_TO") {
+                match to.parse::<std::net::SocketAddrV4>() {
+                    Ok(addr) => alerts.alert_to = Some((addr.ip().octets(), addr.port())),
+                    Err(_) => log::warn!(
+                        "Invalid MEV_DIVERGENCE_ALERT_TO {to:?}, alerts are only counted"
+                    ),
+                }
+            }
+            cfg.divergence = Some(alerts);
+        }
+        if get("MEV_ANOMALY_FACTOR").is_some() {
+            let defaults = AnomalyConfig::default();
+            cfg.anomaly = Some(AnomalyConfig {
+                factor: num("MEV_ANOMALY_FACTOR", defaults.factor as u64).clamp(2, u32::MAX as u64)
+                    as u32,
+                min_move_bps: num("MEV_ANOMALY_MIN_MOVE_BPS", defaults.min_move_bps as u64)
+                    .min(10_000) as u32,
+                suppress_ms: num("MEV_ANOMALY_SUPPRESS_MS", defaults.suppress_ms),
+            });
+        }
+        if let Some(to) = get("MEV_DISCOVERY_TO") {
+            match to.parse::<std::net::SocketAddrV4>() {
+                Ok(addr) => cfg.discovery.request_to = Some((addr.ip().octets(), addr.port())),
+                Err(_) => {
+                    log::warn!("Invalid MEV_DISCOVERY_TO {to:?}, unknown pools are only counted")
+                }
+            }
+        }
+        cfg.discovery.min_hits = num("MEV_DISCOVERY_MIN_HITS", cfg.discovery.min_hits as u64)
+            .clamp(1, u32::MAX as u64) as u32;
+        cfg.discovery.retry_ms = num("MEV_DISCOVERY_RETRY_MS", cfg.discovery.retry_ms);
+        if let Some(peer) = get("MEV_LEADER_PEER") {
+            match peer.parse::<std::net::SocketAddrV4>() {
+                Ok(addr) => {
+                    let mut leader = LeaderConfig::new((addr.ip().octets(), addr.port()));
+                    leader.node_id = num("MEV_LEADER_ID", 0).min(u32::MAX as u64) as u32;
+                    leader.port =
+                        num("MEV_LEADER_PORT", leader.port as u64).min(u16::MAX as u64) as u16;
+                    leader.heartbeat_us =
+                        num("MEV_LEADER_HEARTBEAT_US", leader.heartbeat_us).max(1);
+                    // A timeout within two heartbeats would fail over on one lost datagram.
+                    leader.timeout_us = num("MEV_LEADER_TIMEOUT_US", leader.timeout_us)
+                        .max(3 * leader.heartbeat_us);
+                    cfg.leader = Some(leader);
+                }
+                Err(_) => log::warn!("Invalid MEV_LEADER_PEER {peer:?}, this node replies alone"),
+            }
+        }
+        let defaults = cfg.reputation;
+        cfg.reputation = ReputationPolicy {
+            min_samples: num("MEV_REPUTATION_MIN_SAMPLES", defaults.min_samples as u64)
+                .min(u32::MAX as u64) as u32,
+            skip_below_bps: num("MEV_REPUTATION_SKIP_BPS", defaults.skip_below_bps as u64)
+                .min(10_000) as u32,
+        };
+        let privacy = &mut cfg.privacy;
+        for (key, list) in [
+            ("MEV_PRIVATE_BUILDERS", &mut privacy.builders),
+            ("MEV_PRIVATE_ROUTERS", &mut privacy.routers),
+        ] {
+            for item in get(key)
+                .iter()
+                .flat_map(|v| v.split(','))
+                .map(str::trim)
+                .filter(|i| !i.is_empty())
+            {
+                match parse_address(item) {
+                    Some(address) => {
+                        if list.push(address).is_err() {
+                            log::warn!(
+                                "More than {MAX_SIGNATURES} {key} entries, ignoring the rest"
+                            );
+                            break;
+                        }
+                    }
+                    None => log::warn!("Invalid {key} entry {item:?}, expected a hex address"),
+                }
+            }
+        }
+        cfg.privacy.zero_tip =
+            get("MEV_PRIVATE_ZERO_TIP").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        cfg.horizon_slots = num("MEV_HORIZON_SLOTS", cfg.horizon_slots).min(MAX_HORIZON_SLOTS);
+        cfg.reply_iface = get("MEV_REPLY_IFACE").filter(|v| !v.is_empty());
+        if let Some(list) = get("MEV_EXECUTORS") {
+            for item in list.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+                let parsed = item.split_once('@').and_then(|(ip, mac)| {
+                    Some(ExecutorNeighbor {
+                        addr: ip.parse::<std::net::Ipv4Addr>().ok()?.octets(),
+                        mac: parse_mac(mac)?,
+                    })
+                });
+                match parsed {
+                    Some(executor) => {
+                        if cfg.executors.push(executor).is_err() {
+                            log::warn!("More than {MAX_EXECUTORS} MEV_EXECUTORS entries, ignoring the rest");
+                            break;
+                        }
+                    }
+                    None => log::warn!("Invalid MEV_EXECUTORS entry {item:?}, expected <ip>@<mac>"),
+                }
+            }
+        }
+        if let Some(source) = get("MEV_REPLY_SOURCE") {
+            let parsed = source.split_once('@').and_then(|(addr, mac)| {
+                let addr = addr.parse::<std::net::SocketAddrV4>().ok()?;
+                Some(UdpEndpoint {
+                    mac: parse_mac(mac)?,
+                    addr: addr.ip().octets(),
+                    port: addr.port(),
+                })
+            });
+            match parsed {
+                Some(source) => cfg.reply_source = source,
+                None => {
+                    log::warn!("Invalid MEV_REPLY_SOURCE {source:?}, expected <ip>:<port>@<mac>")
+                }
+            }
+        }
+        if let Some(spec) = get("MEV_REPLY_ROUTES") {
+            let executor_mac = |addr| cfg.executors.iter().find(|e| e.addr == addr).map(|e| e.mac);
+            match ReplyRoutes::parse(&spec, &cfg.reply_source, executor_mac, parse_address) {
+                Ok(routes) => cfg.reply_routes = routes,
+                Err(e) => {
+                    log::warn!("Invalid MEV_REPLY_ROUTES ({e}), replies go back to their source")
+                }
+            }
+        }
+        cfg.pacing.interval_cycles = num("MEV_PACE_INTERVAL_CYCLES", cfg.pacing.interval_cycles);
+        cfg.pacing.burst = num("MEV_PACE_BURST", cfg.pacing.burst).max(1);
+        cfg.reply_coalesce =
+            get("MEV_REPLY_COALESCE").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        if let Some(qdisc) = get("MEV_REPLY_QDISC") {
+            cfg.reply_qdisc = ReplyQdisc::parse(&qdisc);
+            if cfg.reply_qdisc.is_none() {
+                log::warn!("Unknown MEV_REPLY_QDISC {qdisc:?}, expected noqueue or mq");
+            }
+        }
+        if get("MEV_REPLY_ACK_TTL_MS").is_some() {
+            let mut policy = AckPolicy {
+                ttl_ms: num("MEV_REPLY_ACK_TTL_MS", 100).max(1),
+                retransmit_to: None,
+            };
+            if let Some(to) = get("MEV_REPLY_RETRANSMIT_TO") {
+                match to.parse::<std::net::SocketAddrV4>() {
+                    Ok(addr) => policy.retransmit_to = Some((addr.ip().octets(), addr.port())),
+                    Err(_) => log::warn!(
+                        "Invalid MEV_REPLY_RETRANSMIT_TO {to:?}, unacked replies are only counted"
+                    ),
+                }
+            }
+            cfg.reply_ack = Some(policy);
+        }
+        cfg.node_id = num("MEV_NODE_ID", 0).min(u32::MAX as u64) as u32;
+        if let Some(key) = get("MEV_REPLY_KEY") {
+            // Never echo what may be a mistyped secret.
+            match parse_key(&key).map(|key| ReplySigner::new(cfg.node_id, &key)) {
+                Some(Ok(signer)) => cfg.reply_signer = Some(signer),
+                Some(Err(e)) => log::warn!("Invalid MEV_REPLY_KEY ({e}), replies are not signed"),
+                None => log::warn!("Invalid MEV_REPLY_KEY, expected hex; replies are not signed"),
+            }
+        }
+        cfg.reorder_depth = num("MEV_REORDER_DEPTH", cfg.reorder_depth as u64)
+            .min(MAX_REORDER_DEPTH as u64) as usize;
+        cfg.reorder_hold_cycles = num("MEV_REORDER_HOLD_CYCLES", cfg.reorder_hold_cycles);
+        cfg.pool_fec =
+            get("MEV_POOL_FEC").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        if let Some(v) = get("MEV_POOL_CONFLATE") {
+            cfg.pool_conflate = !(v == "0" || v.eq_ignore_ascii_case("false"));
+        }
+        cfg.backpressure.raise_polls = num(
+            "MEV_BACKPRESSURE_POLLS",
+            cfg.backpressure.raise_polls as u64,
+        ) as u32;
+        cfg.backpressure.clear_cycles = num(
+            "MEV_BACKPRESSURE_CLEAR_CYCLES",
+            cfg.backpressure.clear_cycles,
+        );
+        cfg.backpressure.head_drop_keep = num(
+            "MEV_BACKPRESSURE_KEEP",
+            cfg.backpressure.head_drop_keep as u64,
+        ) as usize;
+        if let Some(v) = get("MEV_FEED_CLOCK_CORRECTION") {
+            cfg.feed_clock_correction = !(v == "0" || v.eq_ignore_ascii_case("false"));
+        }
+        if let Some(v) = get("MEV_WIRE_VERSIONS") {
+            match VersionWindow::parse(&v) {
+                Some(window) => cfg.wire_versions = window,
+                None => log::warn!(
+                    "Invalid MEV_WIRE_VERSIONS {v:?}, expected versions within {}",
+                    VersionWindow::ALL
+                ),
+            }
+        }
+        cfg.freshness.backlog_frames =
+            num("MEV_STALE_BACKLOG", cfg.freshness.backlog_frames as u64) as usize;
+        cfg.freshness.max_age_cycles = num("MEV_STALE_AGE_CYCLES", cfg.freshness.max_age_cycles);
+        if let Some(range) = get("MEV_RX_BATCH") {
+            match range
+                .split_once('-')
+                .and_then(|(lo, hi)| Some((lo.parse().ok()?, hi.parse().ok()?)))
+            {
+                Some((min, max)) if 1 <= min && min <= max && max <= MAX_RX_BATCH => {
+                    cfg.rx_batch = BurstConfig { min, max }
+                }
+                _ => log::warn!(
+                    "Invalid MEV_RX_BATCH {range:?}, expected min-max within 1-{MAX_RX_BATCH}"
+                ),
+            }
+        }
+        if let Some(v) = get("MEV_IDLE") {
+            match IdleMode::parse(&v) {
+                Some(mode) => cfg.idle.mode = mode,
+                None => {
+                    log::warn!("Invalid MEV_IDLE {v:?}, expected spin, pause, tpause or umwait")
+                }
+            }
+        }
+        cfg.idle.spin_polls =
+            num("MEV_IDLE_SPIN_POLLS", cfg.idle.spin_polls as u64).min(u32::MAX as u64) as u32;
+        cfg.idle.wait_ns =
+            num("MEV_IDLE_WAIT_NS", cfg.idle.wait_ns as u64).clamp(1, MAX_IDLE_WAIT_NS) as u32;
+        cfg.phc_device = get("MEV_PHC_DEVICE").filter(|v| !v.is_empty());
+        cfg.state_file = get("MEV_STATE_FILE").filter(|v| !v.is_empty());
+        cfg.state_sync_ms = num("MEV_STATE_SYNC_MS", cfg.state_sync_ms).max(1);
+        cfg.record_timeline = get("MEV_RECORD_TIMELINE").filter(|v| !v.is_empty());
+        cfg.journal.rotate_bytes =
+            num("MEV_JOURNAL_ROTATE_BYTES", cfg.journal.rotate_bytes).max(4096);
+        cfg.journal.rotate_ms = num("MEV_JOURNAL_ROTATE_MS", cfg.journal.rotate_ms);
+        cfg.journal.keep = num("MEV_JOURNAL_KEEP", cfg.journal.keep as u64) as usize;
+        if let Some(v) = get("MEV_JOURNAL_COMPRESS") {
+            cfg.journal.compress = !(v == "0" || v.eq_ignore_ascii_case("false"));
+        }
+        cfg.journal.parquet =
+            get("MEV_JOURNAL_PARQUET").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        cfg.stats_interval_ms = num("MEV_STATS_INTERVAL_MS", cfg.stats_interval_ms).max(1);
+        cfg.pool_repack_ms = num("MEV_POOL_REPACK_MS", cfg.pool_repack_ms);
+        if let Some(v) = get("MEV_STATSD") {
+            match v.parse::<std::net::SocketAddrV4>() {
+                Ok(target) => {
+                    cfg.statsd = Some(StatsdConfig {
+                        target,
+                        interval_ms: num("MEV_STATSD_INTERVAL_MS", 10_000).max(100),
+                        prefix: get("MEV_STATSD_PREFIX").unwrap_or_else(|| "mev".to_owned()),
+                    })
+                }
+                Err(_) => log::warn!("Invalid MEV_STATSD {v:?}, expected ip:port"),
+            }
+        }
+        cfg.metrics_shm = get("MEV_METRICS_SHM")
+            .filter(|v| !v.is_empty())
+            .map(|path| MetricsShmConfig {
+                path,
+                interval_ms: num("MEV_METRICS_SHM_INTERVAL_MS", 1_000).max(10),
+            });
+        for (slo, key) in [
+            (Slo::ParseP99, "MEV_SLO_PARSE_P99_NS"),
+            (Slo::EndToEndP99, "MEV_SLO_E2E_P99_NS"),
+        ] {
+            cfg.slo.thresholds_ns[slo as usize] = get(key).and_then(|v| match v.parse::<u64>() {
+                Ok(ns) if ns > 0 => Some(ns),
+                _ => {
+                    log::warn!("Invalid {key} {v:?}, expected nanoseconds");
+                    None
+                }
+            });
+        }
+        cfg.slo.window =
+            num("MEV_SLO_WINDOW", cfg.slo.window as u64).clamp(1, u32::MAX as u64) as u32;
+        cfg.profile_sample_every = num("MEV_PROFILE_SAMPLE_EVERY", cfg.profile_sample_every as u64)
+            .clamp(1, u32::MAX as u64) as u32;
+        cfg.profile_at_start =
+            get("MEV_PROFILE").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        let flag = |key: &str| get(key).map(|v| v == "1" || v.eq_ignore_ascii_case("true"));
+        cfg.warmup.enabled = flag("MEV_WARMUP").unwrap_or(cfg.warmup.enabled);
+        cfg.warmup.packets = num("MEV_WARMUP_PACKETS", cfg.warmup.packets as u64) as usize;
+        cfg.warmup.lock_memory = flag("MEV_MLOCK").unwrap_or(cfg.warmup.lock_memory);
+        cfg.tap_mtu = get("MEV_TAP_MTU").and_then(|v| match v.parse::<u32>() {
+            Ok(mtu) if (68..=65_535).contains(&mtu) => Some(mtu),
+            _ => {
+                log::warn!("MEV_TAP_MTU={v:?} is not an MTU between 68 and 65535, ignoring");
+                None
+            }
+        });
+        let buffers = &mut cfg.socket_buffers;
+        for (key, size) in [
+            ("MEV_TCP_RX_BYTES", &mut buffers.tcp_rx_bytes),
+            ("MEV_TCP_TX_BYTES", &mut buffers.tcp_tx_bytes),
+            ("MEV_UDP_RX_BYTES", &mut buffers.udp_rx_bytes),
+            ("MEV_UDP_RX_PACKETS", &mut buffers.udp_rx_packets),
+            ("MEV_UDP_TX_BYTES", &mut buffers.udp_tx_bytes),
+            ("MEV_UDP_TX_PACKETS", &mut buffers.udp_tx_packets),
+            ("MEV_POOL_RX_BYTES", &mut buffers.pool_rx_bytes),
+            ("MEV_POOL_RX_PACKETS", &mut buffers.pool_rx_packets),
+            ("MEV_CONTROL_RX_BYTES", &mut buffers.control_rx_bytes),
+            ("MEV_CONTROL_RX_PACKETS", &mut buffers.control_rx_packets),
+            ("MEV_RELAY_RX_BYTES", &mut buffers.relay_rx_bytes),
+        ] {
+            *size = num(key, *size as u64).max(1) as usize;
+        }
+        cfg.admin_socket = get("MEV_ADMIN_SOCKET").filter(|v| !v.is_empty());
+        cfg.handover_socket = get("MEV_HANDOVER_SOCKET").filter(|v| !v.is_empty());
+        cfg.state_sources.snapshot = get("MEV_STATE_SNAPSHOT").filter(|v| !v.is_empty());
+        for kind in SourceKind::ALL {
+            let key = format!("MEV_SOURCE_PRIORITY_{}", kind.name().to_ascii_uppercase());
+            let priority = &mut cfg.state_sources.priority[kind as usize];
+            *priority = num(&key, *priority as u64).min(u8::MAX as u64) as u8;
+        }
+        cfg.state_sources.stale_after_ms =
+            num("MEV_SOURCE_STALE_MS", cfg.state_sources.stale_after_ms);
+        if let Some(list) = get("MEV_ADMIN_UIDS") {
+            for item in list.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+                let parsed = item
+                    .split_once(':')
+                    .and_then(|(uid, role)| Some((uid.parse().ok()?, Role::parse(role)?)));
+                match parsed {
+                    Some(grant) => {
+                        if cfg.admin_auth.uids.push(grant).is_err() {
+                            log::warn!(
+                                "More than {MAX_GRANTS} MEV_ADMIN_UIDS entries, ignoring the rest"
+                            );
+                            break;
+                        }
+                    }
+                    None => {
+                        log::warn!("Invalid MEV_ADMIN_UIDS entry {item:?}, expected <uid>:<role>")
+                    }
+                }
+            }
+        }
+        if let Some(list) = get("MEV_ADMIN_TOKENS") {
+            for item in list.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+                let parsed = item
+                    .split_once(':')
+                    .filter(|(_, token)| !token.is_empty())
+                    .and_then(|(role, token)| Some((Role::parse(role)?, token.to_string())));
+                match parsed {
+                    Some(grant) => {
+                        if cfg.admin_auth.tokens.push(grant).is_err() {
+                            log::warn!("More than {MAX_GRANTS} MEV_ADMIN_TOKENS entries, ignoring the rest");
+                            break;
+                        }
+                    }
+                    // Never echo what may be a mistyped secret.
+                    None => log::warn!("Invalid MEV_ADMIN_TOKENS entry, expected <role>:<token>"),
+                }
+            }
+        }
+        if let Some(policy) = get("MEV_ALLOC_AUDIT") {
+            match AuditPolicy::parse(&policy) {
+                Some(policy) => cfg.alloc_audit = policy,
+                None => log::warn!("Unknown MEV_ALLOC_AUDIT {policy:?}, expected count or abort"),
+            }
+        }
+        if let Some(args) = get("MEV_DPDK_EAL_ARGS") {
+            cfg.dpdk.eal_args = args;
+        }
+        cfg.dpdk.port_id =
+            num("MEV_DPDK_PORT", cfg.dpdk.port_id as u64).min(u16::MAX as u64) as u16;
+        cfg.dpdk.queues =
+            num("MEV_DPDK_QUEUES", cfg.dpdk.queues as u64).clamp(1, u16::MAX as u64) as u16;
+        cfg.dpdk.mbufs = num("MEV_DPDK_MBUFS", cfg.dpdk.mbufs as u64).min(u32::MAX as u64) as u32;
+        if let Some(v) = get("MEV_DPDK_SHARD_POOLS") {
+            cfg.dpdk.shard_pools = v == "1" || v.eq_ignore_ascii_case("true");
+        }
+        if let Some(v) = get("MEV_DPDK_RX_CHECKSUM") {
+            cfg.dpdk.rx_checksum = !(v == "0" || v.eq_ignore_ascii_case("false"));
+        }
+        if cfg.threshold.ceiling < cfg.threshold.floor {
+            log::warn!("MEV_MIN_AMOUNT_IN_CEIL below floor, clamping to floor");
+            cfg.threshold.ceiling = cfg.threshold.floor;
+        }
+        cfg
+    }
+
+    /// Evaluator with the configured numéraire, profit floor and latency.
+    pub fn evaluator(&self) -> Evaluator {
+        let normalizer = ProfitNormalizer::new(self.numeraire).unwrap_or_default();
+        Evaluator {
+            execution_latency_ms: self.execution_latency_ms,
+            strategy_min_profit: self.strategy_min_profit,
+            infer_max_swaps: self.infer_max_swaps,
+            priority_fees: self.priority_fees,
+            ..Evaluator::new(normalizer, self.min_profit)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup<'a>(pairs: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            pairs
+                .iter()
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn parses_known_keys_and_ignores_garbage() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_BACKEND", "AF_XDP"),
+            ("MEV_NUMERAIRE", "wsol"),
+            ("MEV_MIN_PROFIT", "42"),
+            ("MEV_EXEC_LATENCY_MS", "not-a-number"),
+            ("MEV_MIN_AMOUNT_IN_FLOOR", "500"),
+            ("MEV_MIN_AMOUNT_IN_CEIL", "100"),
+            ("MEV_ALLOWED_SOURCES", "192.168.69.0/24"),
+        ]));
+        assert_eq!(cfg.backend, Backend::AfXdp);
+        assert_eq!(cfg.numeraire, tokens::WSOL);
+        assert_eq!(cfg.min_profit, 42);
+        assert_eq!(cfg.execution_latency_ms, 0);
+        assert_eq!((cfg.threshold.floor, cfg.threshold.ceiling), (500, 500));
+        assert!(cfg.allowed_sources.allows([192, 168, 69, 1], 1));
+        assert!(!cfg.allowed_sources.allows([10, 0, 0, 1], 1));
+        assert_eq!(cfg.relay_feed, None);
+    }
+
+    #[test]
+    fn mirror_streams_are_configured_separately() {
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_MIRROR_POOLS", "239.1.1.1:8081")]));
+        assert_eq!(
+            cfg.mirror,
+            MirrorConfig {
+                victims_to: None,
+                pools_to: Some(([239, 1, 1, 1], 8081))
+            }
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_MIRROR_VICTIMS", "downstream")]));
+        assert!(!cfg.mirror.is_enabled());
+    }
+
+    #[test]
+    fn relay_feed_is_an_ipv4_endpoint() {
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_RELAY_FEED", "10.0.0.7:9200")]));
+        assert_eq!(cfg.relay_feed, Some(([10, 0, 0, 7], 9200)));
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_RELAY_FEED", "relayer:9200")]));
+        assert_eq!(cfg.relay_feed, None);
+    }
+
+    #[test]
+    fn relay_flow_control_is_opt_in() {
+        assert!(!NodeConfig::default().relay_flow.is_enabled());
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_RELAY_XOFF_PCT", "80")]));
+        assert_eq!(
+            cfg.relay_flow,
+            FlowPolicy {
+                xoff_pct: 80,
+                xon_pct: 40
+            }
+        );
+        // XON at or above XOFF would never pause.
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_RELAY_XOFF_PCT", "60"),
+            ("MEV_RELAY_XON_PCT", "90"),
+        ]));
+        assert_eq!(
+            cfg.relay_flow,
+            FlowPolicy {
+                xoff_pct: 60,
+                xon_pct: 59
+            }
+        );
+    }
+
+    #[test]
+    fn parses_strategy_and_pool_thresholds() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_MIN_PROFIT_BOOK_TAKER_ARB", "5000"),
+            (
+                "MEV_POOL_MIN_PROFIT",
+                "0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48:25000, nonsense:1,cbcd:2",
+            ),
+        ]));
+        assert_eq!(cfg.strategy_min_profit, [None, Some(5_000)]);
+        assert_eq!(cfg.evaluator().strategy_min_profit, cfg.strategy_min_profit);
+        assert_eq!(cfg.pool_min_profit.len(), 1);
+        assert_eq!(cfg.pool_min_profit[0].0[..2], [0xa0, 0xb8]);
+        assert_eq!(cfg.pool_min_profit[0].1, 25_000);
+    }
+
+    #[test]
+    fn parses_pool_seed_file() {
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_POOL_SEED_FILE", "/etc/mev/pools.csv")]));
+        assert_eq!(cfg.pool_seed_file.as_deref(), Some("/etc/mev/pools.csv"));
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_POOL_SEED_FILE", "")])).pool_seed_file,
+            None
+        );
+    }
+
+    #[test]
+    fn sim_mode_is_opt_in() {
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_SIM_SEED", "9")]));
+        assert_eq!(cfg.sim, None);
+
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_SIM", "1"),
+            ("MEV_SIM_COMPETITOR_NS", "1000-5000"),
+            ("MEV_SIM_INCLUSION_BPS", "20000"),
+        ]));
+        let model = cfg.sim.expect("sim enabled");
+        assert_eq!(
+            (
+                model.competitor_min_ns,
+                model.competitor_max_ns,
+                model.inclusion_bps
+            ),
+            (1_000, 5_000, 10_000)
+        );
+    }
+
+    #[test]
+    fn divergence_alerts_need_a_threshold() {
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_DIVERGENCE_ALERT_TO", "10.0.0.9:9100")]));
+        assert_eq!(cfg.divergence, None);
+
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_DIVERGENCE_BPS", "250"),
+            ("MEV_DIVERGENCE_ALERT_TO", "10.0.0.9:9100"),
+        ]));
+        let alerts = cfg.divergence.expect("enabled");
+        assert_eq!((alerts.threshold_bps, alerts.min_updates), (250, 3));
+        assert_eq!(alerts.alert_to, Some(([10, 0, 0, 9], 9100)));
+    }
+
+    #[test]
+    fn anomaly_detection_needs_a_factor() {
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_ANOMALY_SUPPRESS_MS", "500")]));
+        assert_eq!(cfg.anomaly, None);
+
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_ANOMALY_FACTOR", "1"),
+            ("MEV_ANOMALY_SUPPRESS_MS", "500"),
+        ]));
+        assert_eq!(
+            cfg.anomaly,
+            Some(AnomalyConfig {
+                factor: 2,
+                min_move_bps: 50,
+                suppress_ms: 500
+            })
+        );
+    }
+
+    #[test]
+    fn parses_discovery_target() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_DISCOVERY_TO", "10.0.0.7:9200"),
+            ("MEV_DISCOVERY_MIN_HITS", "0"),
+            ("MEV_DISCOVERY_RETRY_MS", "30000"),
+        ]));
+        assert_eq!(cfg.discovery.request_to, Some(([10, 0, 0, 7], 9200)));
+        assert_eq!(
+            (cfg.discovery.min_hits, cfg.discovery.retry_ms),
+            (1, 30_000)
+        );
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_DISCOVERY_TO", "feed")]))
+                .discovery
+                .request_to,
+            None
+        );
+    }
+
+    #[test]
+    fn leader_election_needs_a_peer() {
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_LEADER_ID", "2")])).leader,
+            None
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_LEADER_PEER", "10.0.0.8:9400"),
+            ("MEV_LEADER_ID", "2"),
+            ("MEV_LEADER_HEARTBEAT_US", "500"),
+            ("MEV_LEADER_TIMEOUT_US", "1000"),
+        ]));
+        let leader = cfg.leader.expect("enabled");
+        assert_eq!(
+            (leader.peer, leader.node_id, leader.port),
+            (([10, 0, 0, 8], 9400), 2, 9400)
+        );
+        assert_eq!((leader.heartbeat_us, leader.timeout_us), (500, 1_500));
+    }
+
+    #[test]
+    fn dpdk_backend_takes_eal_arguments_verbatim() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_BACKEND", "dpdk"),
+            ("MEV_DPDK_EAL_ARGS", "-l 0-2 -a 0000:3b:00.0"),
+            ("MEV_DPDK_QUEUES", "0"),
+            ("MEV_DPDK_SHARD_POOLS", "true"),
+            ("MEV_DPDK_RX_CHECKSUM", "0"),
+        ]));
+        assert_eq!(cfg.backend, Backend::Dpdk);
+        let expected = DpdkConfig {
+            eal_args: "-l 0-2 -a 0000:3b:00.0".into(),
+            queues: 1,
+            shard_pools: true,
+            rx_checksum: false,
+            ..DpdkConfig::default()
+        };
+        assert_eq!(cfg.dpdk, expected);
+    }
+
+    #[test]
+    fn parses_executor_reply_path() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_REPLY_IFACE", "veth0"),
+            (
+                "MEV_EXECUTORS",
+                "10.0.0.9@02:00:00:00:00:09, 10.0.0.10@02:00:00:00:0a, bad",
+            ),
+            ("MEV_REPLY_QDISC", "NoQueue"),
+        ]));
+        assert_eq!(cfg.reply_iface.as_deref(), Some("veth0"));
+        assert_eq!(
+            cfg.executors[..],
+            [ExecutorNeighbor {
+                addr: [10, 0, 0, 9],
+                mac: [2, 0, 0, 0, 0, 9]
+            }]
+        );
+        assert_eq!(cfg.reply_qdisc, Some(ReplyQdisc::NoQueue));
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_REPLY_QDISC", "fq")])).reply_qdisc,
+            None
+        );
+    }
+
+    #[test]
+    fn reply_key_signs_as_the_node_id() {
+        let key = "0x000102030405060708090a0b0c0d0e0f";
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_NODE_ID", "12"), ("MEV_REPLY_KEY", key)]));
+        let signer = cfg.reply_signer.expect("16-byte key");
+        assert_eq!(signer.node_id(), 12);
+        let reply = [0u8; 40];
+        let expected = ReplySigner::new(12, &core::array::from_fn::<u8, 16, _>(|i| i as u8))
+            .unwrap()
+            .sign(&reply);
+        assert_eq!(signer.sign(&reply).tag, expected.tag);
+        for bad in ["0011", "xyz0", &"00".repeat(65)] {
+            assert_eq!(
+                NodeConfig::from_lookup(lookup(&[("MEV_REPLY_KEY", bad)])).reply_signer,
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn hex_fields_reject_signed_digits() {
+        assert_eq!(hex_byte(b"0f"), Some(0x0f));
+        assert_eq!(hex_byte(b"+f"), None);
+        assert_eq!(parse_key("+f00"), None);
+        assert_eq!(parse_mac("02:00:00:00:00:+5"), None);
+        assert_eq!(parse_address(&format!("+f{}", "00".repeat(19))), None);
+        assert_eq!(
+            parse_address(&format!("0f{}", "00".repeat(19))),
+            Some(core::array::from_fn(|i| if i == 0 { 0x0f } else { 0 }))
+        );
+    }
+
+    #[test]
+    fn reply_acks_are_opt_in() {
+        assert_eq!(NodeConfig::default().reply_ack, None);
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_REPLY_ACK_TTL_MS", "0"),
+            ("MEV_REPLY_RETRANSMIT_TO", "10.0.0.11:9100"),
+        ]));
+        assert_eq!(
+            cfg.reply_ack,
+            Some(AckPolicy {
+                ttl_ms: 1,
+                retransmit_to: Some(([10, 0, 0, 11], 9100))
+            })
+        );
+    }
+
+    #[test]
+    fn reorder_depth_is_capped() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_REORDER_DEPTH", "1000"),
+            ("MEV_REORDER_HOLD_CYCLES", "5000"),
+        ]));
+        assert_eq!(
+            (cfg.reorder_depth, cfg.reorder_hold_cycles),
+            (MAX_REORDER_DEPTH, 5_000)
+        );
+    }
+
+    #[test]
+    fn pool_fec_is_opt_in() {
+        assert!(!NodeConfig::from_lookup(lookup(&[])).pool_fec);
+        assert!(NodeConfig::from_lookup(lookup(&[("MEV_POOL_FEC", "true")])).pool_fec);
+    }
+
+    #[test]
+    fn pool_conflation_is_opt_out() {
+        assert!(NodeConfig::from_lookup(lookup(&[])).pool_conflate);
+        assert!(!NodeConfig::from_lookup(lookup(&[("MEV_POOL_CONFLATE", "0")])).pool_conflate);
+    }
+
+    #[test]
+    fn parses_backpressure_policy() {
+        assert!(NodeConfig::default().backpressure.is_enabled());
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_BACKPRESSURE_POLLS", "32"),
+            ("MEV_BACKPRESSURE_CLEAR_CYCLES", "9000000"),
+            ("MEV_BACKPRESSURE_KEEP", "2"),
+        ]));
+        let bp = cfg.backpressure;
+        assert_eq!(
+            (bp.raise_polls, bp.clear_cycles, bp.head_drop_keep),
+            (32, 9_000_000, 2)
+        );
+        assert!(
+            !NodeConfig::from_lookup(lookup(&[("MEV_BACKPRESSURE_POLLS", "0")]))
+                .backpressure
+                .is_enabled()
+        );
+    }
+
+    #[test]
+    fn feed_clock_correction_is_opt_out() {
+        assert!(NodeConfig::from_lookup(lookup(&[])).feed_clock_correction);
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_FEED_CLOCK_CORRECTION", "false")]));
+        assert!(!cfg.feed_clock_correction);
+    }
+
+    #[test]
+    fn wire_versions_narrow_to_a_valid_window() {
+        assert_eq!(NodeConfig::default().wire_versions, VersionWindow::ALL);
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_WIRE_VERSIONS", "2")]));
+        assert_eq!(cfg.wire_versions, VersionWindow { min: 2, max: 2 });
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_WIRE_VERSIONS", "1-7")]));
+        assert_eq!(cfg.wire_versions, VersionWindow::ALL);
+    }
+
+    #[test]
+    fn reply_routes_resolve_executor_macs() {
+        use crate::processor::Strategy;
+        use crate::reply_routes::MessageKind;
+
+        let routes = "sandwich/*/*=10.0.0.5:9000";
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_EXECUTORS", "10.0.0.5@02:00:00:00:00:05"),
+            ("MEV_REPLY_SOURCE", "10.0.0.2:8080@02:00:00:00:00:02"),
+            ("MEV_REPLY_ROUTES", routes),
+        ]));
+        assert_eq!(cfg.reply_source.addr, [10, 0, 0, 2]);
+        let dest = cfg
+            .reply_routes
+            .lookup(Strategy::Sandwich, &[0; 20], MessageKind::Reply)
+            .next()
+            .unwrap();
+        assert_eq!(
+            dest.endpoint,
+            UdpEndpoint {
+                mac: [0x02, 0, 0, 0, 0, 0x05],
+                addr: [10, 0, 0, 5],
+                port: 9000
+            }
+        );
+        assert_eq!(
+            dest.headers(MessageKind::Reply)[6..12],
+            [0x02, 0, 0, 0, 0, 0x02]
+        );
+        // Without the executor entry the MAC is unknown: no routes.
+        assert!(
+            NodeConfig::from_lookup(lookup(&[("MEV_REPLY_ROUTES", routes)]))
+                .reply_routes
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn parses_pacing() {
+        assert!(!NodeConfig::default().pacing.is_enabled());
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_PACE_INTERVAL_CYCLES", "30000000"),
+            ("MEV_PACE_BURST", "0"),
+        ]));
+        assert_eq!(
+            cfg.pacing,
+            PacingPolicy {
+                interval_cycles: 30_000_000,
+                burst: 1
+            }
+        );
+    }
+
+    #[test]
+    fn reply_coalescing_is_opt_in() {
+        assert!(!NodeConfig::default().reply_coalesce);
+        assert!(NodeConfig::from_lookup(lookup(&[("MEV_REPLY_COALESCE", "true")])).reply_coalesce);
+        assert!(!NodeConfig::from_lookup(lookup(&[("MEV_REPLY_COALESCE", "0")])).reply_coalesce);
+    }
+
+    #[test]
+    fn parses_xdp_interface() {
+        assert_eq!(NodeConfig::default().xdp_iface, InterfaceName::DEFAULT);
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_XDP_IFACE", "enp59s0f0")]));
+        assert_eq!(cfg.xdp_iface.as_str(), "enp59s0f0");
+        let cfg =
+            NodeConfig::from_lookup(lookup(&[("MEV_XDP_IFACE", "an-interface-name-too-long")]));
+        assert_eq!(cfg.xdp_iface, InterfaceName::DEFAULT);
+    }
+
+    #[test]
+    fn parses_xdp_frame_age() {
+        assert_eq!(NodeConfig::default().xdp_max_frame_age_cycles, 0);
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_XDP_MAX_FRAME_AGE_CYCLES", "15000000")]));
+        assert_eq!(cfg.xdp_max_frame_age_cycles, 15_000_000);
+    }
+
+    #[test]
+    fn parses_freshness_policy() {
+        assert!(!NodeConfig::default().freshness.is_enabled());
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_STALE_BACKLOG", "8"),
+            ("MEV_STALE_AGE_CYCLES", "90000"),
+        ]));
+        assert_eq!(
+            cfg.freshness,
+            FreshnessPolicy {
+                backlog_frames: 8,
+                max_age_cycles: 90_000
+            }
+        );
+    }
+
+    #[test]
+    fn parses_rx_batch_bounds() {
+        assert_eq!(
+            NodeConfig::default().rx_batch,
+            BurstConfig { min: 8, max: 32 }
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_RX_BATCH", "2-64")]));
+        assert_eq!(cfg.rx_batch, BurstConfig { min: 2, max: 64 });
+        for bad in ["0-8", "16-8", "8-65", "8"] {
+            assert_eq!(
+                NodeConfig::from_lookup(lookup(&[("MEV_RX_BATCH", bad)])).rx_batch,
+                BurstConfig::default()
+            );
+        }
+    }
+
+    #[test]
+    fn parses_idle_policy() {
+        assert_eq!(NodeConfig::default().idle, IdlePolicy::default());
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_IDLE", "tpause"),
+            ("MEV_IDLE_SPIN_POLLS", "64"),
+            ("MEV_IDLE_WAIT_NS", "10000000"),
+        ]));
+        assert_eq!(
+            cfg.idle,
+            IdlePolicy {
+                mode: IdleMode::Tpause,
+                spin_polls: 64,
+                wait_ns: MAX_IDLE_WAIT_NS as u32
+            }
+        );
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_IDLE", "sleep")]))
+                .idle
+                .mode,
+            IdleMode::Spin
+        );
+    }
+
+    #[test]
+    fn explicit_latency_budget_keeps_calibration_to_a_report() {
+        assert_eq!(NodeConfig::default().calibration, CalibrationMode::Apply);
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_LATENCY_BUDGET_CYCLES", "8000")]));
+        assert_eq!(
+            (cfg.threshold.latency_budget_cycles, cfg.calibration),
+            (8_000, CalibrationMode::Report)
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_LATENCY_BUDGET_CYCLES", "8000"),
+            ("MEV_CALIBRATE", "apply"),
+        ]));
+        assert_eq!(cfg.calibration, CalibrationMode::Apply);
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_CALIBRATE", "OFF")])).calibration,
+            CalibrationMode::Off
+        );
+    }
+
+    #[test]
+    fn parses_state_file() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_STATE_FILE", "/dev/shm/mev.state"),
+            ("MEV_STATE_SYNC_MS", "0"),
+        ]));
+        assert_eq!(
+            (cfg.state_file.as_deref(), cfg.state_sync_ms),
+            (Some("/dev/shm/mev.state"), 1)
+        );
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_STATE_FILE", "")])).state_file,
+            None
+        );
+    }
+
+    #[test]
+    fn timeline_recording_is_opt_in() {
+        assert_eq!(NodeConfig::from_lookup(lookup(&[])).record_timeline, None);
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_RECORD_TIMELINE", "/data/feed.mvtl")]));
+        assert_eq!(cfg.record_timeline.as_deref(), Some("/data/feed.mvtl"));
+    }
+
+    #[test]
+    fn parses_journal_rotation() {
+        assert_eq!(NodeConfig::default().journal, JournalConfig::default());
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_JOURNAL_ROTATE_BYTES", "16"),
+            ("MEV_JOURNAL_ROTATE_MS", "0"),
+            ("MEV_JOURNAL_KEEP", "5"),
+            ("MEV_JOURNAL_COMPRESS", "false"),
+            ("MEV_JOURNAL_PARQUET", "1"),
+        ]));
+        let expected = JournalConfig {
+            rotate_bytes: 4096,
+            rotate_ms: 0,
+            keep: 5,
+            compress: false,
+            parquet: true,
+        };
+        assert_eq!(cfg.journal, expected);
+    }
+
+    #[test]
+    fn parses_stats_interval() {
+        assert_eq!(NodeConfig::default().stats_interval_ms, 10_000);
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_STATS_INTERVAL_MS", "0")]));
+        assert_eq!(cfg.stats_interval_ms, 1);
+    }
+
+    #[test]
+    fn pool_repack_is_opt_in() {
+        assert_eq!(NodeConfig::default().pool_repack_ms, 0);
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_POOL_REPACK_MS", "250")]));
+        assert_eq!(cfg.pool_repack_ms, 250);
+    }
+
+    #[test]
+    fn statsd_push_needs_a_collector() {
+        assert_eq!(NodeConfig::default().statsd, None);
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_STATSD", "10.0.0.5:8125"),
+            ("MEV_STATSD_INTERVAL_MS", "10"),
+        ]));
+        let statsd = cfg.statsd.expect("enabled");
+        assert_eq!(
+            (
+                statsd.target.port(),
+                statsd.interval_ms,
+                statsd.prefix.as_str()
+            ),
+            (8125, 100, "mev")
+        );
+    }
+
+    #[test]
+    fn metrics_segment_needs_a_path() {
+        assert_eq!(NodeConfig::default().metrics_shm, None);
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_METRICS_SHM", "")])).metrics_shm,
+            None
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_METRICS_SHM", "/dev/shm/mev"),
+            ("MEV_METRICS_SHM_INTERVAL_MS", "1"),
+        ]));
+        assert_eq!(
+            cfg.metrics_shm,
+            Some(MetricsShmConfig {
+                path: "/dev/shm/mev".into(),
+                interval_ms: 10
+            })
+        );
+    }
+
+    #[test]
+    fn slos_are_unmonitored_unless_set() {
+        assert!(!NodeConfig::default().slo.is_enabled());
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_SLO_PARSE_P99_NS", "2500"),
+            ("MEV_SLO_E2E_P99_NS", "fast"),
+            ("MEV_SLO_WINDOW", "0"),
+        ]));
+        assert_eq!(
+            cfg.slo,
+            SloPolicy {
+                thresholds_ns: [Some(2_500), None],
+                window: 1
+            }
+        );
+    }
+
+    #[test]
+    fn profiler_is_off_until_asked_for() {
+        let cfg = NodeConfig::default();
+        assert_eq!(
+            (cfg.profile_sample_every, cfg.profile_at_start),
+            (64, false)
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_PROFILE", "1"),
+            ("MEV_PROFILE_SAMPLE_EVERY", "0"),
+        ]));
+        assert_eq!((cfg.profile_sample_every, cfg.profile_at_start), (1, true));
+    }
+
+    #[test]
+    fn warmup_is_on_and_memory_unlocked_by_default() {
+        assert_eq!(
+            NodeConfig::default().warmup,
+            WarmupConfig {
+                enabled: true,
+                packets: 4096,
+                lock_memory: false
+            }
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_WARMUP", "0"),
+            ("MEV_WARMUP_PACKETS", "100"),
+            ("MEV_MLOCK", "true"),
+        ]));
+        assert_eq!(
+            cfg.warmup,
+            WarmupConfig {
+                enabled: false,
+                packets: 100,
+                lock_memory: true
+            }
+        );
+    }
+
+    #[test]
+    fn parses_socket_buffers_and_mtu() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_TAP_MTU", "9000"),
+            ("MEV_RELAY_RX_BYTES", "1048576"),
+            ("MEV_UDP_RX_PACKETS", "0"),
+        ]));
+        assert_eq!(cfg.tap_mtu, Some(9000));
+        assert_eq!(cfg.socket_buffers.relay_rx_bytes, 1 << 20);
+        assert_eq!(cfg.socket_buffers.udp_rx_packets, 1);
+        assert_eq!(
+            cfg.socket_buffers.tcp_rx_bytes,
+            SocketBuffers::default().tcp_rx_bytes
+        );
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_TAP_MTU", "20")])).tap_mtu,
+            None
+        );
+    }
+
+    #[test]
+    fn port_layout_splits_control_only_when_asked() {
+        assert!(!NodeConfig::default().ports.control_split());
+        let cfg = NodeConfig::from_lookup(lookup(&[(
+            "MEV_PORTS",
+            "swaps=8080,pools=8081,control=8082",
+        )]));
+        assert_eq!(
+            cfg.ports,
+            PortLayout {
+                swaps: 8080,
+                pools: 8081,
+                control: 8082
+            }
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_PORTS", "swaps=8081")]));
+        assert_eq!(cfg.ports, PortLayout::default());
+    }
+
+    #[test]
+    fn parses_core_layout() {
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_CORES", "rx=3,exporter=1")]));
+        assert_eq!(
+            cfg.cores,
+            CoreLayout {
+                polling: 3,
+                housekeeping: None,
+                exporter: Some(1)
+            }
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_CORES", "rx=3,tx=4")]));
+        assert_eq!(cfg.cores, CoreLayout::default());
+    }
+
+    #[test]
+    fn parses_admin_grants() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_ADMIN_SOCKET", "/run/mev/admin.sock"),
+            ("MEV_ADMIN_UIDS", "0:op, 1000:readonly, 1001:root"),
+            ("MEV_ADMIN_TOKENS", "ro:dash-token,op:,operator:kill-token"),
+        ]));
+        assert_eq!(cfg.admin_socket.as_deref(), Some("/run/mev/admin.sock"));
+        assert_eq!(
+            cfg.admin_auth.uids,
+            [(0, Role::Operator), (1000, Role::ReadOnly)]
+        );
+        assert_eq!(
+            cfg.admin_auth.token_role("dash-token"),
+            Some(Role::ReadOnly)
+        );
+        assert_eq!(
+            cfg.admin_auth.token_role("kill-token"),
+            Some(Role::Operator)
+        );
+        assert_eq!(cfg.admin_auth.tokens.len(), 2);
+    }
+
+    #[test]
+    fn parses_handover_socket() {
+        let cfg =
+            NodeConfig::from_lookup(lookup(&[("MEV_HANDOVER_SOCKET", "/run/mev/handover.sock")]));
+        assert_eq!(
+            cfg.handover_socket.as_deref(),
+            Some("/run/mev/handover.sock")
+        );
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_HANDOVER_SOCKET", "")])).handover_socket,
+            None
+        );
+    }
+
+    #[test]
+    fn parses_confidence_limits() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_CONFIDENCE_FEED_STALE_MS", "500"),
+            ("MEV_CONFIDENCE_POOL_STALE_SLOTS", "0"),
+            ("MEV_CONFIDENCE_INFERRED_SWAPS", "6"),
+            ("MEV_INFER_MAX_SWAPS", "70000"),
+        ]));
+        assert_eq!(
+            (
+                cfg.confidence.feed_stale_ms,
+                cfg.confidence.pool_stale_slots
+            ),
+            (500, 0)
+        );
+        assert_eq!(
+            (
+                cfg.confidence.inferred_stale_swaps,
+                cfg.evaluator().infer_max_swaps
+            ),
+            (6, u16::MAX)
+        );
+        assert_eq!(
+            cfg.confidence.prior_win_rate_bps,
+            cfg.threshold.prior_win_rate_bps
+        );
+    }
+
+    #[test]
+    fn priority_fees_are_priced_in_a_known_token() {
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[]))
+                .evaluator()
+                .priority_fees,
+            PriorityFees::OFF
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_OUTBID_GAS", "150000"),
+            ("MEV_FEE_TOKEN", "wsol"),
+        ]));
+        assert_eq!(
+            cfg.evaluator().priority_fees,
+            PriorityFees {
+                fee_token: tokens::WSOL,
+                outbid_gas: 150_000
+            }
+        );
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_FEE_TOKEN", "doge")]));
+        assert_eq!(cfg.priority_fees.fee_token, tokens::WETH);
+    }
+
+    #[test]
+    fn parses_state_sources() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_STATE_SNAPSHOT", "/var/lib/mev/pools.bin"),
+            ("MEV_SOURCE_PRIORITY_SNAPSHOT", "0"),
+            ("MEV_SOURCE_PRIORITY_WIRE", "900"),
+            ("MEV_SOURCE_STALE_MS", "250"),
+        ]));
+        let sources = &cfg.state_sources;
+        assert_eq!(sources.snapshot.as_deref(), Some("/var/lib/mev/pools.bin"));
+        assert_eq!(
+            (
+                sources.priority(SourceKind::Snapshot),
+                sources.priority(SourceKind::WireFeed)
+            ),
+            (0, 255)
+        );
+        assert_eq!(
+            (
+                sources.priority(SourceKind::RpcPoll),
+                sources.stale_after_ms
+            ),
+            (2, 250)
+        );
+    }
+
+    #[test]
+    fn parses_alloc_audit_policy() {
+        let cfg = NodeConfig::from_lookup(lookup(&[("MEV_ALLOC_AUDIT", "abort")]));
+        assert_eq!(cfg.alloc_audit, AuditPolicy::Abort);
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_ALLOC_AUDIT", "trap")])).alloc_audit,
+            AuditPolicy::Count
+        );
+    }
+
+    #[test]
+    fn reputation_skip_rate_is_capped() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            ("MEV_REPUTATION_MIN_SAMPLES", "20"),
+            ("MEV_REPUTATION_SKIP_BPS", "25000"),
+        ]));
+        assert_eq!(
+            cfg.reputation,
+            ReputationPolicy {
+                min_samples: 20,
+                skip_below_bps: 10_000
+            }
+        );
+    }
+
+    #[test]
+    fn parses_private_routing_signatures() {
+        let cfg = NodeConfig::from_lookup(lookup(&[
+            (
+                "MEV_PRIVATE_BUILDERS",
+                "0xb1b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1b1, nope",
+            ),
+            (
+                "MEV_PRIVATE_ROUTERS",
+                "c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1",
+            ),
+            ("MEV_PRIVATE_ZERO_TIP", "true"),
+        ]));
+        assert_eq!(
+            (&cfg.privacy.builders[..], &cfg.privacy.routers[..]),
+            (&[[0xb1; 20]][..], &[[0xc1; 20]][..])
+        );
+        assert!(cfg.privacy.zero_tip);
+    }
+
+    #[test]
+    fn horizon_is_capped() {
+        assert_eq!(NodeConfig::from_lookup(lookup(&[])).horizon_slots, 0);
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_HORIZON_SLOTS", "4")])).horizon_slots,
+            4
+        );
+        assert_eq!(
+            NodeConfig::from_lookup(lookup(&[("MEV_HORIZON_SLOTS", "1000")])).horizon_slots,
+            MAX_HORIZON_SLOTS
+        );
+    }
+
+    #[test]
+    fn empty_environment_is_default() {
+        assert_eq!(NodeConfig::from_lookup(|_| None), NodeConfig::default());
+    }
+}
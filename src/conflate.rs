@@ -0,0 +1,143 @@
+//! Pool update conflation.
+//!
+//! A pool update carries the pool's full reserves, so once updates back up
+//! only the newest per pool matters: applying the ones it replaces costs a
+//! table write, an emitted-reply revalidation and book checks each, for a
+//! state that is already gone. Before a pool-feed batch is applied,
+//! [`Conflator::plan`] finds the newest update of every pool in it (by
+//! sequence number, so an update overtaken on the wire does not win) and
+//! marks the others to be skipped. Without a backlog every pool appears
+//! once per batch and nothing is skipped.
+//!
+//! The update kept carries how many it replaced, so that the sequence
+//! check can count them in and report only real gaps in the feed. Skipped
+//! updates are counted (`pool_conflated`); they are neither applied,
+//! mirrored nor recorded. `MEV_POOL_CONFLATE=0` turns conflation off.
+use crate::pool_registry::PoolAddress;
+use heapless::FnvIndexMap;
+
+/// Marks an update to be skipped.
+const SKIP: u16 = u16::MAX;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Conflated {
+    /// Apply it; it replaces that many older updates of its pool.
+    Apply { replaced: u16 },
+    /// A newer update of the same pool follows in the batch.
+    Skip,
+}
+
+#[derive(Clone, Copy)]
+struct Newest {
+    index: u16,
+    seq: u32,
+    replaced: u16,
+}
+
+/// Conflation plan of one batch of up to `N` messages; `N` must be a power
+/// of two.
+pub struct Conflator<const N: usize> {
+    newest: FnvIndexMap<PoolAddress, Newest, N>,
+    /// Per message: updates it replaces, or [`SKIP`].
+    plan: heapless::Vec<u16, N>,
+}
+
+impl<const N: usize> Conflator<N> {
+    pub fn new() -> Self {
+        Self {
+            newest: FnvIndexMap::new(),
+            plan: heapless::Vec::new(),
+        }
+    }
+
+    /// Plan a batch, given each message's pool and sequence number, `None`
+    /// for messages that are not pool updates. Messages beyond `N`, and
+    /// pools beyond `N` distinct ones, are applied as they come.
+    pub fn plan(&mut self, batch: impl IntoIterator<Item = Option<(PoolAddress, u32)>>) {
+        self.newest.clear();
+        self.plan.clear();
+        for (index, key) in batch.into_iter().enumerate() {
+            if self.plan.push(0).is_err() {
+                break;
+            }
+            let Some((pool, seq)) = key else { continue };
+            let index = index as u16;
+            match self.newest.get_mut(&pool) {
+                Some(newest) => {
+                    newest.replaced += 1;
+                    // Sequence numbers wrap: newer is less than half the
+                    // space ahead.
+                    if (seq.wrapping_sub(newest.seq) as i32) > 0 {
+                        self.plan[newest.index as usize] = SKIP;
+                        (newest.index, newest.seq) = (index, seq);
+                    } else {
+                        self.plan[index as usize] = SKIP;
+                    }
+                    self.plan[newest.index as usize] = newest.replaced;
+                }
+                None => {
+                    let _ = self.newest.insert(
+                        pool,
+                        Newest {
+                            index,
+                            seq,
+                            replaced: 0,
+                        },
+                    );
+                }
+            }
+        }
+    }
+
+    /// What to do with message `index` of the planned batch.
+    #[inline(always)]
+    pub fn verdict(&self, index: usize) -> Conflated {
+        match self.plan.get(index) {
+            Some(&SKIP) => Conflated::Skip,
+            Some(&replaced) => Conflated::Apply { replaced },
+            None => Conflated::Apply { replaced: 0 },
+        }
+    }
+}
+
+impl<const N: usize> Default for Conflator<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_the_newest_update_of_each_pool() {
+        let (a, b) = ([0xAA; 20], [0xBB; 20]);
+        let mut conflator = Conflator::<8>::new();
+        // a:5, book, b:9, a:6, a:4 (overtaken), b:10
+        conflator.plan([
+            Some((a, 5)),
+            None,
+            Some((b, 9)),
+            Some((a, 6)),
+            Some((a, 4)),
+            Some((b, 10)),
+        ]);
+        let verdicts: Vec<_> = (0..7).map(|i| conflator.verdict(i)).collect();
+        use Conflated::{Apply, Skip};
+        let kept = |replaced| Apply { replaced };
+        assert_eq!(
+            verdicts,
+            [Skip, kept(0), Skip, kept(2), Skip, kept(1), kept(0)]
+        );
+
+        // Across the wrap, and a fresh plan per batch.
+        conflator.plan([Some((a, u32::MAX)), Some((a, 0))]);
+        assert_eq!(
+            (conflator.verdict(0), conflator.verdict(1)),
+            (Skip, Apply { replaced: 1 })
+        );
+        conflator.plan([Some((a, 7))]);
+        assert_eq!(conflator.verdict(0), Apply { replaced: 0 });
+    }
+}
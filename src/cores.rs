@@ -0,0 +1,214 @@
+//! CPU core layout: which core each pipeline role runs on.
+//!
+//! `MEV_CORES=rx=2,strategy=2,tx=2,housekeeping=0,exporter=1` places the
+//! roles; roles left out keep their default. The polling thread receives,
+//! evaluates and replies in one loop, so `rx`, `strategy` and `tx` name
+//! one core and must agree (they default to core 0). The background roles
+//! are `housekeeping` (journal compression, pool discovery) and `exporter`
+//! (statsd, metrics segment). Left out, each runs on the highest-numbered
+//! core other than the polling one.
+//!
+//! [`CoreLayout::validate`] checks a layout against the cores online (the
+//! process's affinity mask) at startup: every core named must be online,
+//! and no background role may share the polling core, where it would
+//! preempt the loop.
+use crate::affinity::CoreSlot;
+
+/// A pipeline role.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Rx,
+    Strategy,
+    Tx,
+    Housekeeping,
+    Exporter,
+}
+
+impl Role {
+    pub const ALL: [Role; 5] = [
+        Role::Rx,
+        Role::Strategy,
+        Role::Tx,
+        Role::Housekeeping,
+        Role::Exporter,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Role::Rx => "rx",
+            Role::Strategy => "strategy",
+            Role::Tx => "tx",
+            Role::Housekeeping => "housekeeping",
+            Role::Exporter => "exporter",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CoreLayoutError {
+    /// Not `<role>=<core>`, or an unknown role.
+    UnknownRole,
+    /// Not a core number.
+    InvalidCore,
+    /// `rx`, `strategy` and `tx` name different cores.
+    SplitPollingThread,
+    /// The role's core is not online.
+    Offline(Role, usize),
+    /// A background role is placed on the polling core.
+    OnPollingCore(Role),
+}
+
+impl core::fmt::Display for CoreLayoutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownRole => {
+                write!(f, "expected <rx|strategy|tx|housekeeping|exporter>=<core>")
+            }
+            Self::InvalidCore => write!(f, "cores are numbers"),
+            Self::SplitPollingThread => write!(
+                f,
+                "rx, strategy and tx run on one thread and need the same core"
+            ),
+            Self::Offline(role, core) => write!(f, "{} core {core} is not online", role.name()),
+            Self::OnPollingCore(role) => write!(f, "{} would share the polling core", role.name()),
+        }
+    }
+}
+
+impl core::error::Error for CoreLayoutError {}
+
+/// The default: polling on core 0, background roles away from it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CoreLayout {
+    /// Core of the polling thread: `rx`, `strategy` and `tx`.
+    pub polling: usize,
+    /// `None`: away from the polling core.
+    pub housekeeping: Option<usize>,
+    pub exporter: Option<usize>,
+}
+
+impl CoreLayout {
+    /// Parse `rx=2,strategy=2,tx=2,housekeeping=0,exporter=1`.
+    pub fn parse(spec: &str) -> Result<Self, CoreLayoutError> {
+        let mut cores = [None; Role::ALL.len()];
+        for item in spec.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (role, core) = item.split_once('=').ok_or(CoreLayoutError::UnknownRole)?;
+            let role = Role::ALL
+                .iter()
+                .position(|r| r.name().eq_ignore_ascii_case(role.trim()))
+                .ok_or(CoreLayoutError::UnknownRole)?;
+            cores[role] = Some(
+                core.trim()
+                    .parse::<usize>()
+                    .map_err(|_| CoreLayoutError::InvalidCore)?,
+            );
+        }
+        let [rx, strategy, tx, housekeeping, exporter] = cores;
+        let mut polling = [rx, strategy, tx].into_iter().flatten();
+        let first = polling.next();
+        if polling.any(|core| Some(core) != first) {
+            return Err(CoreLayoutError::SplitPollingThread);
+        }
+        Ok(Self {
+            polling: first.unwrap_or(Self::default().polling),
+            housekeeping,
+            exporter,
+        })
+    }
+
+    /// Where `role` runs.
+    pub fn slot(&self, role: Role) -> CoreSlot {
+        let pinned = match role {
+            Role::Rx | Role::Strategy | Role::Tx => Some(self.polling),
+            Role::Housekeeping => self.housekeeping,
+            Role::Exporter => self.exporter,
+        };
+        pinned.map_or(CoreSlot::AwayFrom(self.polling), CoreSlot::Core)
+    }
+
+    /// Check the layout against the cores `online`.
+    pub fn validate(&self, online: &[usize]) -> Result<(), CoreLayoutError> {
+        for role in [Role::Rx, Role::Housekeeping, Role::Exporter] {
+            let CoreSlot::Core(core) = self.slot(role) else {
+                continue;
+            };
+            if !online.contains(&core) {
+                return Err(CoreLayoutError::Offline(role, core));
+            }
+            if role != Role::Rx && core == self.polling {
+                return Err(CoreLayoutError::OnPollingCore(role));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl core::fmt::Display for CoreLayout {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let p = self.polling;
+        write!(f, "rx={p},strategy={p},tx={p}")?;
+        for role in [Role::Housekeeping, Role::Exporter] {
+            match self.slot(role) {
+                CoreSlot::Core(core) => write!(f, ",{}={core}", role.name())?,
+                CoreSlot::AwayFrom(_) => write!(f, ",{}=auto", role.name())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_parse_and_validate_against_the_online_cores() {
+        let layout =
+            CoreLayout::parse("rx=2, strategy=2, TX=2, housekeeping=0, exporter=1").unwrap();
+        assert_eq!(
+            layout,
+            CoreLayout {
+                polling: 2,
+                housekeeping: Some(0),
+                exporter: Some(1)
+            }
+        );
+        assert_eq!(layout.slot(Role::Strategy), CoreSlot::Core(2));
+        assert_eq!(
+            layout.to_string(),
+            "rx=2,strategy=2,tx=2,housekeeping=0,exporter=1"
+        );
+        assert_eq!(layout.validate(&[0, 1, 2, 3]), Ok(()));
+        assert_eq!(
+            layout.validate(&[0, 2, 3]),
+            Err(CoreLayoutError::Offline(Role::Exporter, 1))
+        );
+        assert_eq!(
+            layout.validate(&[0, 1]),
+            Err(CoreLayoutError::Offline(Role::Rx, 2))
+        );
+
+        // Background roles left out move away from the polling core.
+        let layout = CoreLayout::parse("tx=3").unwrap();
+        assert_eq!(layout.slot(Role::Exporter), CoreSlot::AwayFrom(3));
+        assert_eq!(
+            layout.to_string(),
+            "rx=3,strategy=3,tx=3,housekeeping=auto,exporter=auto"
+        );
+        assert_eq!(CoreLayout::parse(""), Ok(CoreLayout::default()));
+
+        for (spec, e) in [
+            ("rx=1,tx=2", CoreLayoutError::SplitPollingThread),
+            ("io=1", CoreLayoutError::UnknownRole),
+            ("rx", CoreLayoutError::UnknownRole),
+            ("rx=-1", CoreLayoutError::InvalidCore),
+        ] {
+            assert_eq!(CoreLayout::parse(spec), Err(e), "{spec}");
+        }
+        let shared = CoreLayout::parse("rx=1,housekeeping=1").unwrap();
+        assert_eq!(
+            shared.validate(&[0, 1]),
+            Err(CoreLayoutError::OnPollingCore(Role::Housekeeping))
+        );
+    }
+}
@@ -0,0 +1,95 @@
+#[cfg(feature = "std")]
+use crate::xdp::UmemConfig;
+
+/// Compile-time sizing of the node's fixed-capacity structures.
+///
+/// Every hot-path container (pool table, batch scratch, UMEM frame pool) is
+/// sized by a const generic so storage is laid out statically and never
+/// grows. `NodeDimensions` bundles those constants into one type so a
+/// deployment picks its sizing once via [`ActiveDimensions`] instead of
+/// repeating literals at each call site.
+///
+/// All sizes are checked to be non-zero powers of two at compile time: the
+/// pool table splits its capacity evenly across shards and reduces hashes
+/// `% N`, which a power of two turns into a mask, and the kernel rejects
+/// non-power-of-two UMEM/ring sizes.
+pub struct NodeDimensions<
+    const POOL_CAPACITY: usize,
+    const BATCH_SIZE: usize,
+    const UMEM_FRAMES: u32,
+>;
+
+impl<const POOL_CAPACITY: usize, const BATCH_SIZE: usize, const UMEM_FRAMES: u32>
+    NodeDimensions<POOL_CAPACITY, BATCH_SIZE, UMEM_FRAMES>
+{
+    /// Maximum number of pools tracked in the pool table.
+    pub const POOL_CAPACITY: usize = POOL_CAPACITY;
+    /// Largest RX batch ([`crate::burst::MAX_RX_BATCH`]): the bound of
+    /// `MEV_RX_BATCH` and the size of the batch scratch arrays.
+    pub const BATCH_SIZE: usize = BATCH_SIZE;
+    /// Number of frames in the AF_XDP UMEM region.
+    pub const UMEM_FRAMES: u32 = UMEM_FRAMES;
+
+    /// Evaluated on first use; fails compilation for invalid dimensions.
+    pub const VALID: () = {
+        assert!(
+            POOL_CAPACITY.is_power_of_two(),
+            "POOL_CAPACITY must be a power of two"
+        );
+        assert!(
+            BATCH_SIZE.is_power_of_two(),
+            "BATCH_SIZE must be a power of two"
+        );
+        assert!(
+            UMEM_FRAMES.is_power_of_two(),
+            "UMEM_FRAMES must be a power of two"
+        );
+    };
+
+    /// UMEM parameters derived from these dimensions.
+    ///
+    /// Fill/RX/TX rings are half the frame count so half the frames can be
+    /// in flight in each direction.
+    #[cfg(feature = "std")]
+    pub fn umem_config() -> UmemConfig {
+        let () = Self::VALID;
+        UmemConfig {
+            frame_count: UMEM_FRAMES,
+            fill_ring_size: UMEM_FRAMES / 2,
+            rx_tx_ring_size: UMEM_FRAMES / 2,
+            ..UmemConfig::default()
+        }
+    }
+}
+
+/// Default sizing: 256 pools, 64-frame batches, 4096 frames.
+///
+/// 64 frames is the largest `MEV_RX_BATCH` (default 8-32) and the most
+/// the AF_XDP burst bitmask holds; the RX scratch arrays are sized for it.
+pub type DefaultDimensions = NodeDimensions<256, 64, 4096>;
+
+/// Larger tables for deployments tracking many pools (`large_tables` feature).
+pub type LargeDimensions = NodeDimensions<4096, 64, 16384>;
+
+/// Dimensions the binary is built with.
+#[cfg(not(feature = "large_tables"))]
+pub type ActiveDimensions = DefaultDimensions;
+#[cfg(feature = "large_tables")]
+pub type ActiveDimensions = LargeDimensions;
+
+const _: () = DefaultDimensions::VALID;
+const _: () = LargeDimensions::VALID;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn umem_config_follows_frame_count() {
+        let cfg = DefaultDimensions::umem_config();
+        assert_eq!(cfg.frame_count, 4096);
+        assert_eq!(cfg.fill_ring_size, 2048);
+        assert_eq!(cfg.rx_tx_ring_size, 2048);
+        assert_eq!(cfg.frame_size, UmemConfig::default().frame_size);
+    }
+}
@@ -0,0 +1,384 @@
+//! Pool discovery from observed swap traffic.
+//!
+//! A victim swap on a pool the table does not hold is skipped: there are no
+//! reserves to price it against. [`UnknownPools`] counts those misses per
+//! pool, so the pools the traffic actually trades show up even before the
+//! feed carries them. A background thread ([`spawn`]) walks the table once
+//! per interval and, for each pool missed at least `min_hits` times, sends a
+//! `PoolDiscoveryRequest` to the feed provider, which is expected to add the
+//! pool to its feed. Unanswered requests are repeated every `retry_ms`; a
+//! pool is forgotten once it appears in the pool table.
+//!
+//! Misses are recorded from the packet path: slots are claimed with a
+//! compare-and-swap and counted with relaxed atomics, as in
+//! [`crate::source_stats`]. Pools past [`MAX_UNKNOWN_POOLS`] are only
+//! counted as overflow. A miss that races the discovery thread forgetting
+//! its pool may be lost.
+use crate::pool_registry::{pool_key, PoolAddress};
+use bytemuck::{Pod, Zeroable};
+use core::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+
+/// Magic prefix identifying a `PoolDiscoveryRequest` datagram ("MVPD").
+pub const POOL_DISCOVERY_MAGIC: [u8; 4] = *b"MVPD";
+
+/// Unknown pools tracked individually.
+pub const MAX_UNKNOWN_POOLS: usize = 64;
+
+/// Request for the feed provider to start publishing a pool.
+///
+/// Layout (32 bytes, little-endian):
+///   [0..4]   magic     "MVPD"
+///   [4..24]  pool      pool address seen in victim swaps
+///   [24..28] hits      u32 — swaps skipped for want of the pool
+///   [28..32] attempt   u32 — 1 for the first request for the pool
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct PoolDiscoveryRequest {
+    pub magic: [u8; 4],
+    pub pool: PoolAddress,
+    pub hits_le: [u8; 4],
+    pub attempt_le: [u8; 4],
+}
+
+impl PoolDiscoveryRequest {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<PoolDiscoveryRequest>();
+
+    pub fn new(pool: PoolAddress, hits: u32, attempt: u32) -> Self {
+        Self {
+            magic: POOL_DISCOVERY_MAGIC,
+            pool,
+            hits_le: hits.to_le_bytes(),
+            attempt_le: attempt.to_le_bytes(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn hits(&self) -> u32 {
+        u32::from_le_bytes(self.hits_le)
+    }
+
+    #[inline(always)]
+    pub fn attempt(&self) -> u32 {
+        u32::from_le_bytes(self.attempt_le)
+    }
+
+    /// Zero-copy parse; returns `None` unless the payload is exactly one
+    /// `PoolDiscoveryRequest` carrying the expected magic.
+    pub fn parse(data: &[u8]) -> Option<&PoolDiscoveryRequest> {
+        if data.len() != Self::WIRE_SIZE {
+            return None;
+        }
+        let request = bytemuck::try_from_bytes::<PoolDiscoveryRequest>(data).ok()?;
+        (request.magic == POOL_DISCOVERY_MAGIC).then_some(request)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DiscoveryConfig {
+    /// Where requests are sent (IPv4, UDP port); misses are only counted
+    /// when `None`.
+    pub request_to: Option<([u8; 4], u16)>,
+    /// Misses before a pool is requested.
+    pub min_hits: u32,
+    /// Wait before repeating a request the feed has not answered.
+    pub retry_ms: u64,
+    /// How often the discovery thread walks the table.
+    pub interval_ms: u64,
+}
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            request_to: None,
+            min_hits: 3,
+            retry_ms: 10_000,
+            interval_ms: 1_000,
+        }
+    }
+}
+
+/// An unknown pool at one point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownPool {
+    pub address: PoolAddress,
+    pub hits: u64,
+    /// Requests sent for it so far.
+    pub requests: u32,
+    /// Unix time of the last request, 0 before the first.
+    pub last_request_ms: u64,
+}
+
+struct UnknownSlot {
+    /// Hash of the address; 0 while the slot is free.
+    key: AtomicU64,
+    /// Set once `address` is written.
+    ready: AtomicBool,
+    address: [AtomicU32; 5],
+    hits: AtomicU64,
+    /// Written by the discovery thread only.
+    requests: AtomicU32,
+    last_request_ms: AtomicU64,
+}
+
+impl UnknownSlot {
+    const fn new() -> Self {
+        Self {
+            key: AtomicU64::new(0),
+            ready: AtomicBool::new(false),
+            address: [const { AtomicU32::new(0) }; 5],
+            hits: AtomicU64::new(0),
+            requests: AtomicU32::new(0),
+            last_request_ms: AtomicU64::new(0),
+        }
+    }
+
+    fn publish(&self, address: &PoolAddress) {
+        for (word, bytes) in self.address.iter().zip(address.chunks_exact(4)) {
+            word.store(
+                u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+                Ordering::Relaxed,
+            );
+        }
+        self.ready.store(true, Ordering::Release);
+    }
+
+    fn snapshot(&self) -> Option<UnknownPool> {
+        if !self.ready.load(Ordering::Acquire) {
+            return None;
+        }
+        let mut address = [0u8; 20];
+        for (bytes, word) in address.chunks_exact_mut(4).zip(&self.address) {
+            bytes.copy_from_slice(&word.load(Ordering::Relaxed).to_le_bytes());
+        }
+        Some(UnknownPool {
+            address,
+            hits: self.hits.load(Ordering::Relaxed),
+            requests: self.requests.load(Ordering::Relaxed),
+            last_request_ms: self.last_request_ms.load(Ordering::Relaxed),
+        })
+    }
+
+    fn forget(&self) {
+        self.ready.store(false, Ordering::Relaxed);
+        self.hits.store(0, Ordering::Relaxed);
+        self.requests.store(0, Ordering::Relaxed);
+        self.last_request_ms.store(0, Ordering::Relaxed);
+        self.key.store(0, Ordering::Release);
+    }
+}
+
+pub struct UnknownPools {
+    slots: [UnknownSlot; MAX_UNKNOWN_POOLS],
+    /// Misses on pools that found the table full.
+    overflow: AtomicU64,
+}
+
+impl Default for UnknownPools {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UnknownPools {
+    pub const fn new() -> Self {
+        Self {
+            slots: [const { UnknownSlot::new() }; MAX_UNKNOWN_POOLS],
+            overflow: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a swap skipped because `address` is not in the pool table,
+    /// claiming a slot on first sight.
+    #[inline]
+    pub fn record(&self, address: &PoolAddress) {
+        let key = pool_key(address);
+        // Forgotten slots leave holes, so look for the pool everywhere
+        // before claiming one.
+        if let Some(slot) = self
+            .slots
+            .iter()
+            .find(|s| s.key.load(Ordering::Relaxed) == key)
+        {
+            slot.hits.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        for slot in &self.slots {
+            match slot
+                .key
+                .compare_exchange(0, key, Ordering::Acquire, Ordering::Relaxed)
+            {
+                Ok(_) => {
+                    slot.publish(address);
+                    slot.hits.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                // Another writer claimed it meanwhile, for this pool.
+                Err(k) if k == key => {
+                    slot.hits.fetch_add(1, Ordering::Relaxed);
+                    return;
+                }
+                Err(_) => {}
+            }
+        }
+        self.overflow.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn overflow(&self) -> u64 {
+        self.overflow.load(Ordering::Relaxed)
+    }
+
+    /// Every tracked pool, in slot order.
+    pub fn for_each(&self, mut f: impl FnMut(UnknownPool)) {
+        self.slots
+            .iter()
+            .filter_map(UnknownSlot::snapshot)
+            .for_each(&mut f);
+    }
+
+    /// One round of the discovery thread at unix time `now_ms`: forget the
+    /// pools `is_known` now finds, and hand `request` one request for each
+    /// pool due one.
+    pub fn poll(
+        &self,
+        config: &DiscoveryConfig,
+        now_ms: u64,
+        is_known: impl Fn(&PoolAddress) -> bool,
+        mut request: impl FnMut(PoolDiscoveryRequest),
+    ) {
+        for slot in &self.slots {
+            let Some(pool) = slot.snapshot() else {
+                continue;
+            };
+            if is_known(&pool.address) {
+                slot.forget();
+                continue;
+            }
+            let retry_due = pool.requests == 0
+                || now_ms.saturating_sub(pool.last_request_ms) >= config.retry_ms;
+            if pool.hits < config.min_hits as u64 || !retry_due {
+                continue;
+            }
+            slot.requests.store(pool.requests + 1, Ordering::Relaxed);
+            slot.last_request_ms.store(now_ms, Ordering::Relaxed);
+            request(PoolDiscoveryRequest::new(
+                pool.address,
+                pool.hits.min(u32::MAX as u64) as u32,
+                pool.requests + 1,
+            ));
+        }
+    }
+}
+
+/// One line of JSON:
+/// `{"overflow":..,"pools":[{"address":"0x..","hits":..,"requests":..,"last_request_ms":..},..]}`.
+impl core::fmt::Display for UnknownPools {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{{\"overflow\":{},\"pools\":[", self.overflow())?;
+        let mut result = Ok(());
+        let mut first = true;
+        self.for_each(|pool| {
+            let sep = if core::mem::take(&mut first) { "" } else { "," };
+            result = result.and_then(|()| {
+                write!(f, "{sep}{{\"address\":\"0x")?;
+                pool.address
+                    .iter()
+                    .try_for_each(|byte| write!(f, "{byte:02x}"))?;
+                write!(
+                    f,
+                    "\",\"hits\":{},\"requests\":{},\"last_request_ms\":{}}}",
+                    pool.hits, pool.requests, pool.last_request_ms
+                )
+            });
+        });
+        result?;
+        write!(f, "]}}")
+    }
+}
+
+/// Start the discovery thread: every `interval_ms` until `stop` is set, it
+/// polls `pools` against `table` and sends the due requests from an
+/// ephemeral UDP port. The thread runs on `core`.
+#[cfg(feature = "std")]
+pub fn spawn(
+    config: DiscoveryConfig,
+    pools: &'static UnknownPools,
+    table: &'static crate::pool_registry::PoolTable,
+    stop: &'static AtomicBool,
+    core: crate::affinity::CoreSlot,
+) -> std::io::Result<std::thread::JoinHandle<()>> {
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0")?;
+    std::thread::Builder::new().name("discovery".into()).spawn(move || {
+        crate::affinity::pin_current_thread(core);
+        let interval = std::time::Duration::from_millis(config.interval_ms);
+        while !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(interval);
+            pools.poll(&config, crate::runtime::unix_time_ms(), |pool| table.peek(pool).is_some(), |request| {
+                let (pool, hits, attempt) = (request.pool, request.hits(), request.attempt());
+                log::info!("requesting unknown pool {pool:02x?} ({hits} misses, attempt {attempt})");
+                let Some((ip, port)) = config.request_to else { return };
+                if let Err(e) = socket.send_to(bytemuck::bytes_of(&request), (std::net::Ipv4Addr::from(ip), port)) {
+                    log::debug!("pool discovery request not sent: {e}");
+                }
+            });
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn misses_turn_into_requests_until_the_pool_is_known() {
+        let pools = UnknownPools::new();
+        let config = DiscoveryConfig {
+            min_hits: 2,
+            retry_ms: 1_000,
+            ..DiscoveryConfig::default()
+        };
+        let (a, b) = ([0xAA; 20], [0xBB; 20]);
+        pools.record(&a);
+        pools.record(&b);
+        pools.record(&a);
+
+        let mut sent = Vec::new();
+        pools.poll(
+            &config,
+            5_000,
+            |_| false,
+            |r| sent.push((r.pool, r.hits(), r.attempt())),
+        );
+        assert_eq!(sent, [(a, 2, 1)]);
+        pools.poll(
+            &config,
+            5_500,
+            |_| false,
+            |r| sent.push((r.pool, r.hits(), r.attempt())),
+        );
+        assert_eq!(sent.len(), 1, "retry not due yet");
+        pools.record(&b);
+        pools.poll(
+            &config,
+            6_000,
+            |_| false,
+            |r| sent.push((r.pool, r.hits(), r.attempt())),
+        );
+        assert_eq!(sent[1..], [(a, 2, 2), (b, 2, 1)]);
+
+        // Once `a` is in the table it is forgotten, and its slot reused.
+        pools.poll(&config, 6_100, |pool| *pool == a, |_| {});
+        pools.record(&[0xCC; 20]);
+        let mut seen = Vec::new();
+        pools.for_each(|p| seen.push((p.address[0], p.hits, p.requests)));
+        assert_eq!(seen, [(0xCC, 1, 0), (0xBB, 2, 1)]);
+        assert!(pools
+            .to_string()
+            .starts_with("{\"overflow\":0,\"pools\":[{\"address\":\"0xcccc"));
+
+        let request = PoolDiscoveryRequest::new(b, 2, 1);
+        assert_eq!(
+            PoolDiscoveryRequest::parse(bytemuck::bytes_of(&request)).map(|r| r.pool),
+            Some(b)
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Cross-venue divergence alerts.
+//!
+//! Each order-book market is compared with the AMM pool trading the same
+//! pair every time either side updates. When the book mid and the pool spot
+//! price stay more than `threshold_bps` apart for more than `min_updates`
+//! consecutive comparisons, a `DivergenceAlert` is emitted once; the market
+//! re-arms when the two prices converge again. Alerts are informational —
+//! downstream strategies may act on them even when the node itself does not.
+use crate::orderbook::{OrderBookState, BOOK_CAPACITY, PRICE_SCALE};
+use crate::pool_registry::PoolAddress;
+use crate::processor::AmmPoolState;
+use bytemuck::{Pod, Zeroable};
+use heapless::FnvIndexMap;
+
+/// Magic prefix identifying a `DivergenceAlert` datagram ("MVDV").
+pub const DIVERGENCE_ALERT_MAGIC: [u8; 4] = *b"MVDV";
+
+/// Alert sent when a book and its reference pool disagree on price.
+///
+/// Layout (80 bytes, little-endian):
+///   [0..4]   magic           "MVDV"
+///   [4..24]  market          order-book market
+///   [24..44] pool            AMM pool trading the same pair
+///   [44..48] divergence_bps  i32 — (book mid − pool price) / pool price
+///   [48..52] updates         u32 — consecutive diverged comparisons
+///   [52..60] book_mid        u64 — quote per 10^9 base
+///   [60..68] pool_price      u64 — quote per 10^9 base
+///   [68..76] slot            u64 — slot of the triggering update
+///   [76..80] _reserved
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct DivergenceAlert {
+    pub magic: [u8; 4],
+    pub market: PoolAddress,
+    pub pool: PoolAddress,
+    pub divergence_bps_le: [u8; 4],
+    pub updates_le: [u8; 4],
+    pub book_mid_le: [u8; 8],
+    pub pool_price_le: [u8; 8],
+    pub slot_le: [u8; 8],
+    pub _reserved: [u8; 4],
+}
+
+const _: () = assert!(core::mem::size_of::<DivergenceAlert>() == 80);
+
+impl DivergenceAlert {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<DivergenceAlert>();
+
+    #[inline(always)]
+    pub fn divergence_bps(&self) -> i32 {
+        i32::from_le_bytes(self.divergence_bps_le)
+    }
+
+    #[inline(always)]
+    pub fn updates(&self) -> u32 {
+        u32::from_le_bytes(self.updates_le)
+    }
+
+    #[inline(always)]
+    pub fn book_mid(&self) -> u64 {
+        u64::from_le_bytes(self.book_mid_le)
+    }
+
+    #[inline(always)]
+    pub fn pool_price(&self) -> u64 {
+        u64::from_le_bytes(self.pool_price_le)
+    }
+
+    #[inline(always)]
+    pub fn slot(&self) -> u64 {
+        u64::from_le_bytes(self.slot_le)
+    }
+
+    /// Zero-copy parse; returns `None` unless the payload is exactly one
+    /// `DivergenceAlert` carrying the expected magic.
+    pub fn parse(data: &[u8]) -> Option<&DivergenceAlert> {
+        if data.len() != Self::WIRE_SIZE {
+            return None;
+        }
+        let alert = bytemuck::try_from_bytes::<DivergenceAlert>(data).ok()?;
+        (alert.magic == DIVERGENCE_ALERT_MAGIC).then_some(alert)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DivergenceConfig {
+    /// Relative price gap that counts as diverged, in basis points.
+    pub threshold_bps: u32,
+    /// Consecutive diverged comparisons tolerated before alerting.
+    pub min_updates: u32,
+    /// Where alerts are sent (IPv4, UDP port); counted only when `None`.
+    pub alert_to: Option<([u8; 4], u16)>,
+}
+
+impl Default for DivergenceConfig {
+    fn default() -> Self {
+        Self {
+            threshold_bps: 100,
+            min_updates: 3,
+            alert_to: None,
+        }
+    }
+}
+
+/// Pool spot price in book units: raw `token1` per [`PRICE_SCALE`] raw `token0`.
+pub fn pool_price(pool: &AmmPoolState) -> Option<u64> {
+    if pool.reserve0 == 0 {
+        return None;
+    }
+    u64::try_from(pool.reserve1 as u128 * PRICE_SCALE / pool.reserve0 as u128).ok()
+}
+
+/// Midpoint of the best bid and ask; `None` unless both sides are quoted.
+pub fn book_mid(book: &OrderBookState) -> Option<u64> {
+    let (bid, ask) = (book.best_bid()?, book.best_ask()?);
+    Some(((bid as u128 + ask as u128) / 2) as u64)
+}
+
+/// Divergence streaks per market, owned by the feed-processing thread.
+pub struct DivergenceTracker {
+    config: DivergenceConfig,
+    streaks: FnvIndexMap<PoolAddress, u32, BOOK_CAPACITY>,
+}
+
+impl DivergenceTracker {
+    pub fn new(config: DivergenceConfig) -> Self {
+        Self {
+            config,
+            streaks: FnvIndexMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &DivergenceConfig {
+        &self.config
+    }
+
+    /// Compare `market`'s book with `pool`'s spot price. Returns an alert on
+    /// the comparison that extends a streak past `min_updates`.
+    pub fn observe(
+        &mut self,
+        market: &PoolAddress,
+        book: &OrderBookState,
+        pool_address: &PoolAddress,
+        pool: &AmmPoolState,
+        slot: u64,
+    ) -> Option<DivergenceAlert> {
+        let (mid, price) = (book_mid(book)?, pool_price(pool).filter(|&p| p != 0)?);
+        let gap_bps = (mid as i128 - price as i128) * 10_000 / price as i128;
+        let streak = if gap_bps.unsigned_abs() > self.config.threshold_bps as u128 {
+            match self.streaks.get_mut(market) {
+                Some(streak) => {
+                    *streak = streak.saturating_add(1);
+                    *streak
+                }
+                // Untracked markets beyond capacity are never alerted on.
+                None => self.streaks.insert(*market, 1).map_or(0, |_| 1),
+            }
+        } else {
+            self.streaks.remove(market);
+            0
+        };
+        (streak == self.config.min_updates.saturating_add(1)).then(|| DivergenceAlert {
+            magic: DIVERGENCE_ALERT_MAGIC,
+            market: *market,
+            pool: *pool_address,
+            divergence_bps_le: (gap_bps.clamp(i32::MIN as i128, i32::MAX as i128) as i32)
+                .to_le_bytes(),
+            updates_le: streak.to_le_bytes(),
+            book_mid_le: mid.to_le_bytes(),
+            pool_price_le: price.to_le_bytes(),
+            slot_le: slot.to_le_bytes(),
+            _reserved: [0; 4],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orderbook::PriceLevel;
+    use crate::processor::MOCK_POOL;
+
+    fn book_around(mid: u64) -> OrderBookState {
+        let mut book = OrderBookState::EMPTY;
+        book.bids[0] = PriceLevel::new(mid - 1_000, 1);
+        book.asks[0] = PriceLevel::new(mid + 1_000, 1);
+        book
+    }
+
+    #[test]
+    fn alerts_once_after_sustained_divergence_and_rearms() {
+        let config = DivergenceConfig {
+            threshold_bps: 100,
+            min_updates: 2,
+            alert_to: None,
+        };
+        let mut tracker = DivergenceTracker::new(config);
+        let (market, pool) = ([1; 20], [2; 20]);
+        let price = pool_price(&MOCK_POOL).unwrap();
+        assert_eq!(price, 500_000_000);
+        let rich = book_around(price + price / 50);
+
+        assert!(tracker
+            .observe(&market, &rich, &pool, &MOCK_POOL, 1)
+            .is_none());
+        assert!(tracker
+            .observe(&market, &rich, &pool, &MOCK_POOL, 2)
+            .is_none());
+        let alert = tracker
+            .observe(&market, &rich, &pool, &MOCK_POOL, 3)
+            .expect("third diverged update");
+        let parsed = DivergenceAlert::parse(bytemuck::bytes_of(&alert)).unwrap();
+        assert_eq!(
+            (parsed.divergence_bps(), parsed.updates(), parsed.slot()),
+            (200, 3, 3)
+        );
+        assert_eq!(
+            (parsed.book_mid(), parsed.pool_price(), parsed.pool),
+            (price + price / 50, price, pool)
+        );
+        assert!(
+            tracker
+                .observe(&market, &rich, &pool, &MOCK_POOL, 4)
+                .is_none(),
+            "one alert per episode"
+        );
+
+        // Within the threshold: streak resets.
+        assert!(tracker
+            .observe(&market, &book_around(price), &pool, &MOCK_POOL, 5)
+            .is_none());
+        for slot in 6..8 {
+            assert!(tracker
+                .observe(&market, &rich, &pool, &MOCK_POOL, slot)
+                .is_none());
+        }
+        assert!(tracker
+            .observe(&market, &rich, &pool, &MOCK_POOL, 8)
+            .is_some());
+    }
+
+    #[test]
+    fn one_sided_books_are_not_compared() {
+        let mut tracker = DivergenceTracker::new(DivergenceConfig {
+            min_updates: 0,
+            ..DivergenceConfig::default()
+        });
+        let mut book = OrderBookState::EMPTY;
+        book.bids[0] = PriceLevel::new(1, 1);
+        assert!(tracker
+            .observe(&[1; 20], &book, &[2; 20], &MOCK_POOL, 1)
+            .is_none());
+    }
+}
@@ -0,0 +1,535 @@
+//! Startup consistency checks, run by the `doctor` subcommand.
+//!
+//! The node trusts its host: it binds whatever AF_XDP queue it is built for,
+//! registers a UMEM of whatever size its dimensions give, and pins its
+//! threads to the cores `MEV_CORES` names, and a host that does not match
+//! shows up as a bind error, truncated frames, or latency that is merely
+//! worse than it should be. `doctor` compares what the node will ask for ([`Plan`])
+//! against what the host has ([`HostFacts`], read from procfs and sysfs) and
+//! prints one [`Finding`] per check, each mismatch with the command that
+//! fixes it. Gathering and judging are separate, so [`diagnose`] is tested
+//! against made-up hosts.
+use crate::config::{Backend, NodeConfig};
+use crate::cores::CoreLayout;
+use crate::dimensions::ActiveDimensions;
+use crate::xdp::XdpConfig;
+use core::fmt;
+
+/// Ethernet header plus one VLAN tag: what a frame carries beyond the MTU.
+const L2_OVERHEAD: u32 = 14 + 4;
+
+/// Hugepage memory per DPDK mbuf: the 128-byte `rte_mbuf` header and a
+/// 2048-byte data room behind 128 bytes of headroom.
+const MBUF_BYTES: u64 = 128 + 128 + 2048;
+
+/// Exempts a process from `RLIMIT_MEMLOCK`.
+const CAP_IPC_LOCK: u32 = 14;
+
+/// First kernel with AF_XDP sockets.
+const AF_XDP_KERNEL: (u32, u32) = (4, 18);
+/// First kernel with `XDP_USE_NEED_WAKEUP`; before it, the kernel has to
+/// be kicked with a syscall on every TX batch.
+const NEED_WAKEUP_KERNEL: (u32, u32) = (5, 4);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok,
+    /// Runs, but slower or less reliably than it could.
+    Warn,
+    /// Will not start, or will mishandle traffic.
+    Fail,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub check: &'static str,
+    pub status: Status,
+    pub detail: String,
+    /// What to run or change, for anything not `Ok`.
+    pub remedy: Option<String>,
+}
+
+impl Finding {
+    fn ok(check: &'static str, detail: String) -> Self {
+        Self {
+            check,
+            status: Status::Ok,
+            detail,
+            remedy: None,
+        }
+    }
+
+    fn bad(check: &'static str, status: Status, detail: String, remedy: String) -> Self {
+        Self {
+            check,
+            status,
+            detail,
+            remedy: Some(remedy),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self.status {
+            Status::Ok => "ok",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+        };
+        write!(f, "{status:<4}  {}: {}", self.check, self.detail)?;
+        if let Some(remedy) = &self.remedy {
+            write!(f, "\n      fix: {remedy}")?;
+        }
+        Ok(())
+    }
+}
+
+/// What the node will ask the host for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Plan {
+    pub backend: Backend,
+    pub interface: String,
+    pub queue_id: u32,
+    pub frame_size: u32,
+    pub umem_bytes: u64,
+    /// Where its threads run.
+    pub cores: CoreLayout,
+    /// DPDK mbuf pool size, in mbufs.
+    pub mbufs: u32,
+}
+
+impl Plan {
+    pub fn new(config: &NodeConfig) -> Self {
+        let xdp = XdpConfig {
+            interface: config.xdp_iface,
+            ..XdpConfig::default()
+        };
+        let umem = ActiveDimensions::umem_config();
+        Self {
+            backend: config.backend,
+            interface: xdp.interface.to_string(),
+            queue_id: xdp.queue_id,
+            frame_size: umem.frame_size,
+            umem_bytes: umem.total_size() as u64,
+            cores: config.cores,
+            mbufs: config.dpdk.mbufs,
+        }
+    }
+}
+
+/// What the host has. `None` where it could not be read.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct HostFacts {
+    /// `(major, minor)` of the running kernel.
+    pub kernel: Option<(u32, u32)>,
+    /// RX queues of the interface; `None` if it does not exist.
+    pub rx_queues: Option<u32>,
+    pub mtu: Option<u32>,
+    /// NUMA node of the NIC; `None` for no affinity (sysfs reports -1).
+    pub nic_numa: Option<u32>,
+    /// NUMA node of the pinned core.
+    pub core_numa: Option<u32>,
+    /// Cores on the NIC's NUMA node, as a sysfs cpulist.
+    pub nic_cpus: Option<String>,
+    /// `RLIMIT_MEMLOCK` in bytes, `None` for unlimited or `CAP_IPC_LOCK`.
+    pub memlock: Option<u64>,
+    pub hugepage_bytes: u64,
+    pub hugepages_total: u64,
+    pub hugepages_free: u64,
+    /// Cores the process may run on; empty if they could not be read.
+    pub online_cores: Vec<usize>,
+}
+
+fn read(path: &str) -> Option<String> {
+    std::fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+}
+
+/// `(major, minor)` of a kernel release such as `6.8.0-45-generic`.
+pub fn parse_kernel(release: &str) -> Option<(u32, u32)> {
+    let mut parts = release.split(|c: char| !c.is_ascii_digit());
+    Some((parts.next()?.parse().ok()?, parts.next()?.parse().ok()?))
+}
+
+/// A `/proc/meminfo` field, in the unit it is given in.
+fn meminfo_field(meminfo: &str, name: &str) -> Option<u64> {
+    let line = meminfo
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))?;
+    line.split_whitespace().next()?.parse().ok()
+}
+
+impl HostFacts {
+    /// Read the facts `plan` is judged against.
+    pub fn probe(plan: &Plan) -> Self {
+        let net = format!("/sys/class/net/{}", plan.interface);
+        let rx_queues = std::fs::read_dir(format!("{net}/queues")).ok().map(|dir| {
+            dir.flatten()
+                .filter(|e| e.file_name().to_string_lossy().starts_with("rx-"))
+                .count() as u32
+        });
+        let nic_numa = read(&format!("{net}/device/numa_node")).and_then(|n| n.parse().ok());
+        let core = plan.cores.polling;
+        let core_numa = std::fs::read_dir(format!("/sys/devices/system/cpu/cpu{core}"))
+            .ok()
+            .and_then(|dir| {
+                dir.flatten()
+                    .find_map(|e| e.file_name().to_str()?.strip_prefix("node")?.parse().ok())
+            });
+        let status = read("/proc/self/status").unwrap_or_default();
+        let mut limit = libc::rlimit {
+            rlim_cur: 0,
+            rlim_max: 0,
+        };
+        let memlock = if crate::transport::tap::has_capability(&status, CAP_IPC_LOCK)
+            || unsafe { libc::getrlimit(libc::RLIMIT_MEMLOCK, &mut limit) } != 0
+            || limit.rlim_cur == libc::RLIM_INFINITY
+        {
+            None
+        } else {
+            Some(limit.rlim_cur)
+        };
+        let meminfo = read("/proc/meminfo").unwrap_or_default();
+        Self {
+            kernel: read("/proc/sys/kernel/osrelease")
+                .as_deref()
+                .and_then(parse_kernel),
+            rx_queues,
+            mtu: read(&format!("{net}/mtu")).and_then(|mtu| mtu.parse().ok()),
+            nic_numa,
+            core_numa,
+            nic_cpus: nic_numa
+                .and_then(|node| read(&format!("/sys/devices/system/node/node{node}/cpulist"))),
+            memlock,
+            hugepage_bytes: meminfo_field(&meminfo, "Hugepagesize").unwrap_or(0) * 1024,
+            hugepages_total: meminfo_field(&meminfo, "HugePages_Total").unwrap_or(0),
+            hugepages_free: meminfo_field(&meminfo, "HugePages_Free").unwrap_or(0),
+            online_cores: crate::affinity::online_cores(),
+        }
+    }
+}
+
+fn version((major, minor): (u32, u32)) -> String {
+    format!("{major}.{minor}")
+}
+
+/// Judge `plan` against `facts`; the checks that do not apply to the
+/// plan's backend are left out.
+pub fn diagnose(plan: &Plan, facts: &HostFacts) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    match plan.backend {
+        Backend::Tap => findings.push(Finding::ok(
+            "backend",
+            "tap: needs no NIC queue, UMEM or hugepages".into(),
+        )),
+        Backend::AfXdp => af_xdp(plan, facts, &mut findings),
+        Backend::Dpdk => hugepages(plan, facts, &mut findings),
+    }
+    cores(plan, facts, &mut findings);
+    findings
+}
+
+fn cores(plan: &Plan, facts: &HostFacts, findings: &mut Vec<Finding>) {
+    if facts.online_cores.is_empty() {
+        findings.push(Finding::ok(
+            "cores",
+            format!("{} (online cores unknown)", plan.cores),
+        ));
+        return;
+    }
+    findings.push(match plan.cores.validate(&facts.online_cores) {
+        Ok(()) => Finding::ok("cores", plan.cores.to_string()),
+        Err(e) => {
+            let online: Vec<String> = facts.online_cores.iter().map(usize::to_string).collect();
+            Finding::bad(
+                "cores",
+                Status::Fail,
+                format!("{}: {e}", plan.cores),
+                format!(
+                    "set MEV_CORES to cores among {}, background roles off the polling core",
+                    online.join(",")
+                ),
+            )
+        }
+    });
+}
+
+fn af_xdp(plan: &Plan, facts: &HostFacts, findings: &mut Vec<Finding>) {
+    let iface = &plan.interface;
+    findings.push(match facts.kernel {
+        None => Finding::bad(
+            "kernel",
+            Status::Warn,
+            "release unreadable".into(),
+            "check /proc/sys/kernel/osrelease".into(),
+        ),
+        Some(kernel) if kernel < AF_XDP_KERNEL => Finding::bad(
+            "kernel",
+            Status::Fail,
+            format!("{} has no AF_XDP sockets", version(kernel)),
+            format!(
+                "upgrade to {} or later, or run MEV_BACKEND=tap",
+                version(NEED_WAKEUP_KERNEL)
+            ),
+        ),
+        Some(kernel) if kernel < NEED_WAKEUP_KERNEL => Finding::bad(
+            "kernel",
+            Status::Warn,
+            format!(
+                "{} lacks XDP_USE_NEED_WAKEUP; every TX batch costs a syscall",
+                version(kernel)
+            ),
+            format!("upgrade to {} or later", version(NEED_WAKEUP_KERNEL)),
+        ),
+        Some(kernel) => Finding::ok(
+            "kernel",
+            format!("{} supports need_wakeup", version(kernel)),
+        ),
+    });
+
+    let Some(queues) = facts.rx_queues else {
+        findings.push(Finding::bad(
+            "interface",
+            Status::Fail,
+            format!("{iface} does not exist"),
+            format!("create {iface} (scripts/run_tcpreplay_veth.sh sets up a veth pair) or rebuild for the NIC"),
+        ));
+        return;
+    };
+    findings.push(if plan.queue_id < queues {
+        Finding::ok(
+            "queue",
+            format!(
+                "{iface} has {queues} RX queues, binding queue {}",
+                plan.queue_id
+            ),
+        )
+    } else {
+        Finding::bad(
+            "queue",
+            Status::Fail,
+            format!(
+                "{iface} has {queues} RX queues, queue {} does not exist",
+                plan.queue_id
+            ),
+            format!("ethtool -L {iface} combined {}", plan.queue_id + 1),
+        )
+    });
+
+    let largest = plan.frame_size.saturating_sub(L2_OVERHEAD);
+    findings.push(match facts.mtu {
+        Some(mtu) if mtu > largest => Finding::bad(
+            "mtu",
+            Status::Fail,
+            format!(
+                "{iface} MTU {mtu} does not fit a {}-byte UMEM frame; larger frames are dropped",
+                plan.frame_size
+            ),
+            format!("ip link set dev {iface} mtu {largest}"),
+        ),
+        Some(mtu) => Finding::ok(
+            "mtu",
+            format!(
+                "{iface} MTU {mtu} fits a {}-byte UMEM frame",
+                plan.frame_size
+            ),
+        ),
+        None => Finding::bad(
+            "mtu",
+            Status::Warn,
+            format!("{iface} MTU unreadable"),
+            format!("ip link show {iface}"),
+        ),
+    });
+
+    findings.push(match facts.memlock {
+        Some(limit) if limit < plan.umem_bytes => Finding::bad(
+            "umem",
+            Status::Fail,
+            format!(
+                "a {} KiB UMEM cannot be locked under RLIMIT_MEMLOCK {} KiB",
+                plan.umem_bytes >> 10,
+                limit >> 10
+            ),
+            "ulimit -l unlimited (LimitMEMLOCK=infinity under systemd) or grant CAP_IPC_LOCK"
+                .into(),
+        ),
+        _ => Finding::ok(
+            "umem",
+            format!("{} KiB can be locked", plan.umem_bytes >> 10),
+        ),
+    });
+
+    findings.push(match (facts.nic_numa, facts.core_numa) {
+        (Some(nic), Some(core)) if nic != core => Finding::bad(
+            "numa",
+            Status::Warn,
+            format!(
+                "{iface} is on node {nic}, core {} on node {core}; every packet crosses the interconnect",
+                plan.cores.polling
+            ),
+            format!(
+                "move {iface} to a slot on node {core}, or run where core {} is on node {nic} (its cores: {})",
+                plan.cores.polling,
+                facts.nic_cpus.as_deref().unwrap_or("?"),
+            ),
+        ),
+        (Some(nic), Some(_)) => Finding::ok("numa", format!("{iface} and core {} share node {nic}", plan.cores.polling)),
+        _ => Finding::ok("numa", "no NUMA affinity to match".into()),
+    });
+}
+
+fn hugepages(plan: &Plan, facts: &HostFacts, findings: &mut Vec<Finding>) {
+    let needed = plan.mbufs as u64 * MBUF_BYTES;
+    let free = facts.hugepages_free * facts.hugepage_bytes;
+    findings.push(if free >= needed {
+        Finding::ok(
+            "hugepages",
+            format!(
+                "{} MiB free for a {} MiB mbuf pool",
+                free >> 20,
+                needed >> 20
+            ),
+        )
+    } else {
+        // `nr_hugepages` is the total, including the pages in use.
+        let size = facts.hugepage_bytes.max(2 << 20);
+        let pages = facts.hugepages_total - facts.hugepages_free.min(facts.hugepages_total)
+            + needed.div_ceil(size);
+        Finding::bad(
+            "hugepages",
+            Status::Fail,
+            format!(
+                "{} MiB free, the {}-mbuf pool needs {} MiB",
+                free >> 20,
+                plan.mbufs,
+                needed >> 20
+            ),
+            format!(
+                "echo {} > /sys/kernel/mm/hugepages/hugepages-{}kB/nr_hugepages",
+                pages,
+                size >> 10
+            ),
+        )
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mismatches_come_with_a_remedy() {
+        let plan = Plan {
+            backend: Backend::AfXdp,
+            interface: "eth1".into(),
+            queue_id: 2,
+            frame_size: 4096,
+            umem_bytes: 16 << 20,
+            cores: CoreLayout::default(),
+            mbufs: 8191,
+        };
+        let healthy = HostFacts {
+            kernel: parse_kernel("6.8.0-45-generic"),
+            rx_queues: Some(4),
+            mtu: Some(1500),
+            nic_numa: Some(0),
+            core_numa: Some(0),
+            online_cores: vec![0, 1, 2, 3],
+            ..HostFacts::default()
+        };
+        let findings = diagnose(&plan, &healthy);
+        assert_eq!(findings.len(), 6);
+        assert!(
+            findings
+                .iter()
+                .all(|f| f.status == Status::Ok && f.remedy.is_none()),
+            "{findings:?}"
+        );
+
+        let sick = HostFacts {
+            kernel: parse_kernel("5.3.18"),
+            rx_queues: Some(2),
+            mtu: Some(9000),
+            nic_numa: Some(1),
+            nic_cpus: Some("8-15".into()),
+            memlock: Some(64 << 10),
+            ..healthy.clone()
+        };
+        let findings = diagnose(&plan, &sick);
+        let statuses: Vec<_> = findings.iter().map(|f| (f.check, f.status)).collect();
+        assert_eq!(
+            statuses,
+            [
+                ("kernel", Status::Warn),
+                ("queue", Status::Fail),
+                ("mtu", Status::Fail),
+                ("umem", Status::Fail),
+                ("numa", Status::Warn),
+                ("cores", Status::Ok),
+            ]
+        );
+        assert_eq!(
+            findings[1].remedy.as_deref(),
+            Some("ethtool -L eth1 combined 3")
+        );
+        assert_eq!(
+            findings[2].remedy.as_deref(),
+            Some("ip link set dev eth1 mtu 4078")
+        );
+        assert!(
+            findings[4].to_string().ends_with("(its cores: 8-15)"),
+            "{}",
+            findings[4]
+        );
+
+        let missing = diagnose(
+            &plan,
+            &HostFacts {
+                rx_queues: None,
+                ..sick
+            },
+        );
+        assert_eq!(
+            (missing.len(), missing[1].check, missing[1].status),
+            (3, "interface", Status::Fail)
+        );
+
+        // Exporter pinned to a core that is not there.
+        let misplaced = Plan {
+            cores: CoreLayout::parse("rx=1,exporter=7").unwrap(),
+            ..plan.clone()
+        };
+        let cores = diagnose(&misplaced, &healthy).pop().unwrap();
+        assert_eq!(cores.status, Status::Fail);
+        assert_eq!(
+            cores.to_string(),
+            "FAIL  cores: rx=1,strategy=1,tx=1,housekeeping=auto,exporter=7: exporter core 7 is not online\n      \
+             fix: set MEV_CORES to cores among 0,1,2,3, background roles off the polling core"
+        );
+
+        // DPDK: 8191 mbufs need 9 pages of 2 MiB; 4 of 8 are free.
+        let dpdk = Plan {
+            backend: Backend::Dpdk,
+            ..plan
+        };
+        let facts = HostFacts {
+            hugepage_bytes: 2 << 20,
+            hugepages_total: 8,
+            hugepages_free: 4,
+            ..HostFacts::default()
+        };
+        let findings = diagnose(&dpdk, &facts);
+        assert_eq!(findings[0].status, Status::Fail);
+        assert_eq!(
+            findings[0].remedy.as_deref(),
+            Some("echo 13 > /sys/kernel/mm/hugepages/hugepages-2048kB/nr_hugepages")
+        );
+        let plenty = HostFacts {
+            hugepages_free: 1024,
+            ..facts
+        };
+        assert_eq!(diagnose(&dpdk, &plenty)[0].status, Status::Ok);
+    }
+}
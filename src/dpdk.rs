@@ -0,0 +1,647 @@
+//! DPDK poll-mode-driver backend (`dpdk` feature).
+//!
+//! For NICs whose AF_XDP zero-copy support is weak, the node can drive the
+//! port through DPDK instead: the EAL is initialised from `MEV_DPDK_EAL_ARGS`,
+//! the port gets one RX/TX queue pair per worker lcore (RSS spreads flows
+//! across them), and each lcore polls its queue in bursts. A received mbuf is
+//! exposed as a borrowed `&[u8]` ([`MbufFrame::data`]), so the payload goes
+//! through the same `Evaluator`/validator code as on the smoltcp path, and a
+//! reply is written into the same mbuf through
+//! [`Transport`](crate::transport::Transport) after the
+//! headers are turned around ([`turn_around`]).
+//!
+//! DPDK's burst and mbuf accessors are `static inline`; `src/dpdk_shim.c`
+//! wraps them (built by `build.rs`). Header handling below is plain Rust and
+//! available without the feature.
+
+/// Ethernet + IPv4 (no options) + UDP header bytes before the payload.
+pub const HEADERS_LEN: usize = 42;
+
+/// Mbufs received per `rx_burst` call.
+pub const RX_BURST: usize = 32;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DpdkConfig {
+    /// EAL arguments, whitespace-separated (e.g. `-l 0-2 -a 0000:3b:00.0`).
+    pub eal_args: String,
+    pub port_id: u16,
+    /// RX/TX queue pairs, one worker lcore each.
+    pub queues: u16,
+    /// Mbufs in the packet pool; `2^n - 1` is optimal for the mempool ring.
+    pub mbufs: u32,
+    /// Give each worker a private pool table holding the pools
+    /// [`rss::pool_queue`](crate::rss::pool_queue) assigns to its queue.
+    pub shard_pools: bool,
+    /// Verify IPv4 header and UDP checksums in software ([`checksums_ok`]);
+    /// nothing else does on this path.
+    pub rx_checksum: bool,
+}
+
+impl Default for DpdkConfig {
+    fn default() -> Self {
+        Self {
+            eal_args: String::new(),
+            port_id: 0,
+            queues: 1,
+            mbufs: 8191,
+            shard_pools: false,
+            rx_checksum: true,
+        }
+    }
+}
+
+/// UDP destination port and payload of an Ethernet/IPv4/UDP frame. Frames
+/// with IP options, fragments or inconsistent lengths are rejected.
+#[inline(always)]
+pub fn udp_payload(frame: &[u8]) -> Option<(u16, &[u8])> {
+    let headers = frame.get(..HEADERS_LEN)?;
+    let fragmented = headers[20] & 0x3F != 0 || headers[21] != 0;
+    if headers[12..14] != [0x08, 0x00] || headers[14] != 0x45 || headers[23] != 17 || fragmented {
+        return None;
+    }
+    let dst_port = u16::from_be_bytes([headers[36], headers[37]]);
+    let udp_len = u16::from_be_bytes([headers[38], headers[39]]) as usize;
+    let payload = frame.get(HEADERS_LEN..HEADERS_LEN - 8 + udp_len.max(8))?;
+    Some((dst_port, payload))
+}
+
+/// Ones' complement sum of big-endian 16-bit words, an odd last byte
+/// padded with zero, not yet folded.
+fn ones_complement_sum(acc: u32, bytes: &[u8]) -> u32 {
+    bytes.chunks(2).fold(acc, |acc, w| {
+        acc + u16::from_be_bytes([w[0], *w.get(1).unwrap_or(&0)]) as u32
+    })
+}
+
+fn fold(mut sum: u32) -> u16 {
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+    sum as u16
+}
+
+/// Whether the IPv4 header checksum of a frame [`udp_payload`] accepts
+/// verifies, and its UDP checksum too if the sender filled one in (zero
+/// means none over IPv4).
+pub fn checksums_ok(frame: &[u8]) -> bool {
+    let Some(h) = frame.get(..HEADERS_LEN) else {
+        return false;
+    };
+    if fold(ones_complement_sum(0, &h[14..34])) != 0xFFFF {
+        return false;
+    }
+    if h[40..42] == [0, 0] {
+        return true;
+    }
+    let udp_len = u16::from_be_bytes([h[38], h[39]]);
+    let Some(udp) = frame.get(34..34 + udp_len as usize) else {
+        return false;
+    };
+    // Pseudo-header: addresses, protocol, UDP length.
+    let pseudo = ones_complement_sum(17 + udp_len as u32, &h[26..34]);
+    fold(ones_complement_sum(pseudo, udp)) == 0xFFFF
+}
+
+/// Rewrite the headers of a received frame into those of a reply carrying
+/// `payload_len` bytes: MAC addresses, IPv4 addresses and UDP ports are
+/// swapped, lengths and the IPv4 checksum recomputed. The UDP checksum is
+/// left at zero (optional over IPv4).
+pub fn turn_around(headers: &mut [u8], payload_len: usize) -> bool {
+    let Some(h) = headers.get_mut(..HEADERS_LEN) else {
+        return false;
+    };
+    let Ok(udp_len) = u16::try_from(8 + payload_len) else {
+        return false;
+    };
+    for (a, b, n) in [(0, 6, 6), (26, 30, 4), (34, 36, 2)] {
+        let (left, right) = h.split_at_mut(b);
+        left[a..a + n].swap_with_slice(&mut right[..n]);
+    }
+    finish_headers(h, udp_len);
+    true
+}
+
+/// One end of a UDP flow, as it appears in frame headers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UdpEndpoint {
+    pub mac: [u8; 6],
+    pub addr: [u8; 4],
+    pub port: u16,
+}
+
+/// Headers of a frame from `src` to `dst` carrying `payload_len` bytes, as
+/// [`turn_around`] would leave them. `None` if the payload is too long.
+pub fn build_headers(
+    src: &UdpEndpoint,
+    dst: &UdpEndpoint,
+    payload_len: usize,
+) -> Option<[u8; HEADERS_LEN]> {
+    let udp_len = u16::try_from(8 + payload_len).ok()?;
+    let mut h = [0u8; HEADERS_LEN];
+    h[..6].copy_from_slice(&dst.mac);
+    h[6..12].copy_from_slice(&src.mac);
+    h[12..14].copy_from_slice(&[0x08, 0x00]);
+    h[14] = 0x45;
+    h[23] = 17;
+    h[26..30].copy_from_slice(&src.addr);
+    h[30..34].copy_from_slice(&dst.addr);
+    h[34..36].copy_from_slice(&src.port.to_be_bytes());
+    h[36..38].copy_from_slice(&dst.port.to_be_bytes());
+    finish_headers(&mut h, udp_len);
+    Some(h)
+}
+
+/// Lengths, flags, TTL and checksums of addressed headers.
+fn finish_headers(h: &mut [u8], udp_len: u16) {
+    h[16..18].copy_from_slice(&(udp_len + 20).to_be_bytes());
+    h[20..22].copy_from_slice(&[0x40, 0x00]); // DF, no fragment offset
+    h[22] = 64;
+    h[24..26].copy_from_slice(&[0, 0]);
+    let sum = h[14..34]
+        .chunks(2)
+        .fold(0u32, |acc, w| acc + u16::from_be_bytes([w[0], w[1]]) as u32);
+    let folded = (sum & 0xFFFF) + (sum >> 16);
+    h[24..26].copy_from_slice(&(!((folded & 0xFFFF) + (folded >> 16)) as u16).to_be_bytes());
+    h[38..40].copy_from_slice(&udp_len.to_be_bytes());
+    h[40..42].copy_from_slice(&[0, 0]);
+}
+
+#[cfg(feature = "dpdk")]
+pub use port::*;
+
+#[cfg(feature = "dpdk")]
+mod port {
+    use super::*;
+    use crate::transport::Transport;
+    use std::ffi::{c_char, c_int, c_uint, c_void, CString};
+
+    #[repr(C)]
+    pub struct RteMbuf {
+        _private: [u8; 0],
+    }
+
+    #[repr(C)]
+    pub struct RteMempool {
+        _private: [u8; 0],
+    }
+
+    const MBUF_CACHE: c_uint = 256;
+    /// `RTE_MBUF_DEFAULT_BUF_SIZE`: 2048 bytes of data plus headroom.
+    const MBUF_DATA_ROOM: u16 = 2048 + 128;
+
+    extern "C" {
+        fn rte_eal_init(argc: c_int, argv: *mut *mut c_char) -> c_int;
+        fn rte_socket_id() -> c_uint;
+        fn rte_pktmbuf_pool_create(
+            name: *const c_char,
+            n: c_uint,
+            cache_size: c_uint,
+            priv_size: u16,
+            data_room_size: u16,
+            socket_id: c_int,
+        ) -> *mut RteMempool;
+        fn rte_lcore_count() -> c_uint;
+        fn rte_get_next_lcore(i: c_uint, skip_main: c_int, wrap: c_int) -> c_uint;
+        fn rte_eal_remote_launch(
+            f: extern "C" fn(*mut c_void) -> c_int,
+            arg: *mut c_void,
+            worker: c_uint,
+        ) -> c_int;
+        fn rte_eal_mp_wait_lcore();
+
+        fn mev_dpdk_rx_burst(port: u16, queue: u16, pkts: *mut *mut RteMbuf, n: u16) -> u16;
+        fn mev_dpdk_tx_burst(port: u16, queue: u16, pkts: *mut *mut RteMbuf, n: u16) -> u16;
+        fn mev_dpdk_free(m: *mut RteMbuf);
+        fn mev_dpdk_data(m: *mut RteMbuf, len: *mut u16, capacity: *mut u16) -> *mut u8;
+        fn mev_dpdk_set_len(m: *mut RteMbuf, len: u16);
+        fn mev_dpdk_alloc(pool: *mut RteMempool) -> *mut RteMbuf;
+        fn mev_dpdk_wait_rx(port: u16, queue: u16, tsc_deadline: u64) -> c_int;
+        fn mev_dpdk_port_init(
+            port: u16,
+            queues: u16,
+            pool: *mut RteMempool,
+            rss_key: *mut u8,
+            rss_key_len: u8,
+            reta_size: *mut u16,
+        ) -> c_int;
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum DpdkError {
+        /// `rte_eal_init` failed (bad arguments, no hugepages, ...).
+        EalInit(i32),
+        Mempool,
+        /// Port configuration or start failed with this negative errno.
+        PortInit {
+            port: u16,
+            code: i32,
+        },
+        /// Fewer worker lcores than queues in the EAL core list.
+        NotEnoughLcores {
+            queues: u16,
+            workers: u16,
+        },
+    }
+
+    impl std::fmt::Display for DpdkError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::EalInit(code) => write!(f, "rte_eal_init failed ({code})"),
+                Self::Mempool => write!(f, "mbuf pool allocation failed"),
+                Self::PortInit { port, code } => write!(f, "port {port} setup failed ({code})"),
+                Self::NotEnoughLcores { queues, workers } => {
+                    write!(
+                        f,
+                        "{queues} queues need as many worker lcores, EAL has {workers}"
+                    )
+                }
+            }
+        }
+    }
+
+    impl std::error::Error for DpdkError {}
+
+    /// One received mbuf, owned until dropped (freed) or sent.
+    pub struct MbufFrame {
+        mbuf: *mut RteMbuf,
+        data: *mut u8,
+        len: u16,
+        capacity: u16,
+        reserved: bool,
+    }
+
+    impl MbufFrame {
+        /// # Safety
+        /// `mbuf` must be a valid mbuf owned by the caller.
+        unsafe fn from_raw(mbuf: *mut RteMbuf) -> Self {
+            let (mut len, mut capacity) = (0, 0);
+            let data = mev_dpdk_data(mbuf, &mut len, &mut capacity);
+            Self {
+                mbuf,
+                data,
+                len,
+                capacity,
+                reserved: false,
+            }
+        }
+
+        /// The frame as received: a borrowed view of the mbuf's data room.
+        #[inline(always)]
+        pub fn data(&self) -> &[u8] {
+            // SAFETY: `data..data+len` is the mbuf's packet data, owned by self.
+            unsafe { std::slice::from_raw_parts(self.data, self.len as usize) }
+        }
+
+        /// The first [`HEADERS_LEN`] bytes, for [`turn_around`].
+        pub fn headers_mut(&mut self) -> &mut [u8] {
+            let len = (self.len as usize).min(HEADERS_LEN);
+            // SAFETY: as in `data`, and `&mut self` makes the view unique.
+            unsafe { std::slice::from_raw_parts_mut(self.data, len) }
+        }
+    }
+
+    /// A reply replaces the received payload; one message per frame.
+    impl Transport for MbufFrame {
+        #[inline(always)]
+        fn tx_reserve(&mut self, len: usize) -> Option<&mut [u8]> {
+            let end = HEADERS_LEN.checked_add(len)?;
+            if self.reserved || end > self.capacity as usize {
+                return None;
+            }
+            self.reserved = true;
+            self.len = end as u16;
+            // SAFETY: `end` is within the mbuf's data room.
+            unsafe {
+                mev_dpdk_set_len(self.mbuf, self.len);
+                Some(std::slice::from_raw_parts_mut(
+                    self.data.add(HEADERS_LEN),
+                    len,
+                ))
+            }
+        }
+    }
+
+    impl Drop for MbufFrame {
+        fn drop(&mut self) {
+            // SAFETY: still owned; `send` forgets frames the driver took.
+            unsafe { mev_dpdk_free(self.mbuf) }
+        }
+    }
+
+    /// Frames of one RX burst; those the caller does not take are freed on drop.
+    pub struct RxFrames<'b> {
+        mbufs: &'b [*mut RteMbuf],
+    }
+
+    impl Iterator for RxFrames<'_> {
+        type Item = MbufFrame;
+
+        #[inline(always)]
+        fn next(&mut self) -> Option<MbufFrame> {
+            let (&first, rest) = self.mbufs.split_first()?;
+            self.mbufs = rest;
+            // SAFETY: the driver handed us ownership of every mbuf in the burst.
+            Some(unsafe { MbufFrame::from_raw(first) })
+        }
+
+        fn size_hint(&self) -> (usize, Option<usize>) {
+            (self.mbufs.len(), Some(self.mbufs.len()))
+        }
+    }
+
+    impl ExactSizeIterator for RxFrames<'_> {}
+
+    impl Drop for RxFrames<'_> {
+        fn drop(&mut self) {
+            for &m in self.mbufs {
+                // SAFETY: owned and never turned into an `MbufFrame`.
+                unsafe { mev_dpdk_free(m) }
+            }
+        }
+    }
+
+    /// Scratch array for one RX burst.
+    pub struct RxBurst {
+        mbufs: [*mut RteMbuf; RX_BURST],
+    }
+
+    impl RxBurst {
+        pub fn new() -> Self {
+            Self {
+                mbufs: [std::ptr::null_mut(); RX_BURST],
+            }
+        }
+    }
+
+    impl Default for RxBurst {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    /// An initialised EAL and started port.
+    pub struct DpdkPort {
+        port_id: u16,
+        queues: u16,
+        reta_size: u16,
+        pool: *mut RteMempool,
+    }
+
+    // The port handle is ids and the mempool, whose per-lcore caches make
+    // allocation thread-safe; each queue is polled by exactly one lcore.
+    unsafe impl Sync for DpdkPort {}
+
+    impl DpdkPort {
+        /// Initialise the EAL, allocate the mbuf pool and start the port.
+        /// Call once per process.
+        pub fn init(config: &DpdkConfig) -> Result<Self, DpdkError> {
+            let args: Vec<CString> = std::iter::once("mev-zerocopy-node")
+                .chain(config.eal_args.split_whitespace())
+                .map(|a| CString::new(a).expect("no NUL in EAL arguments"))
+                .collect();
+            let mut argv: Vec<*mut c_char> =
+                args.iter().map(|a| a.as_ptr() as *mut c_char).collect();
+            // SAFETY: argv points at NUL-terminated strings that outlive the call.
+            let rc = unsafe { rte_eal_init(argv.len() as c_int, argv.as_mut_ptr()) };
+            if rc < 0 {
+                return Err(DpdkError::EalInit(rc));
+            }
+
+            let name = CString::new("mev_mbufs").unwrap();
+            // SAFETY: EAL is initialised.
+            let pool = unsafe {
+                let socket = rte_socket_id() as c_int;
+                rte_pktmbuf_pool_create(
+                    name.as_ptr(),
+                    config.mbufs,
+                    MBUF_CACHE,
+                    0,
+                    MBUF_DATA_ROOM,
+                    socket,
+                )
+            };
+            if pool.is_null() {
+                return Err(DpdkError::Mempool);
+            }
+            // The driver copies the key; a local copy keeps the const intact.
+            let mut key = crate::rss::RSS_KEY;
+            let mut reta_size = 0;
+            // SAFETY: pool is a valid mempool; key and reta_size outlive the call.
+            let code = unsafe {
+                mev_dpdk_port_init(
+                    config.port_id,
+                    config.queues,
+                    pool,
+                    key.as_mut_ptr(),
+                    key.len() as u8,
+                    &mut reta_size,
+                )
+            };
+            if code < 0 {
+                return Err(DpdkError::PortInit {
+                    port: config.port_id,
+                    code,
+                });
+            }
+            Ok(Self {
+                port_id: config.port_id,
+                queues: config.queues,
+                reta_size,
+                pool,
+            })
+        }
+
+        pub fn queues(&self) -> u16 {
+            self.queues
+        }
+
+        /// Entries in the RSS indirection table, filled round-robin over the
+        /// queues (see [`crate::rss`]).
+        pub fn reta_size(&self) -> u16 {
+            self.reta_size
+        }
+
+        /// Poll `queue` once; yields up to `max` frames, at most [`RX_BURST`].
+        #[inline(always)]
+        pub fn rx_burst<'b>(&self, queue: u16, burst: &'b mut RxBurst, max: usize) -> RxFrames<'b> {
+            let max = max.clamp(1, RX_BURST) as u16;
+            // SAFETY: `mbufs` has room for RX_BURST pointers.
+            let n =
+                unsafe { mev_dpdk_rx_burst(self.port_id, queue, burst.mbufs.as_mut_ptr(), max) };
+            RxFrames {
+                mbufs: &burst.mbufs[..n as usize],
+            }
+        }
+
+        /// Sleep until a frame lands on `queue` or the TSC reaches
+        /// `deadline` (see [`crate::idle`]); `false` if the driver or CPU
+        /// cannot monitor the RX ring.
+        #[inline(always)]
+        pub fn wait_rx(&self, queue: u16, deadline: u64) -> bool {
+            // SAFETY: plain values; the shim only reads the port's RX ring.
+            unsafe { mev_dpdk_wait_rx(self.port_id, queue, deadline) == 0 }
+        }
+
+        /// An empty frame from the port's mbuf pool, for sending more than
+        /// one message per received frame. `None` if the pool is exhausted.
+        #[inline(always)]
+        pub fn alloc_frame(&self) -> Option<MbufFrame> {
+            // SAFETY: `pool` is the live mempool created in `init`.
+            let mbuf = unsafe { mev_dpdk_alloc(self.pool) };
+            // SAFETY: a freshly allocated mbuf is ours.
+            (!mbuf.is_null()).then(|| unsafe { MbufFrame::from_raw(mbuf) })
+        }
+
+        /// Queue `frame` on `queue`'s TX ring. `false` (frame freed) if the
+        /// ring is full.
+        #[inline(always)]
+        pub fn send(&self, queue: u16, frame: MbufFrame) -> bool {
+            let mut mbuf = frame.mbuf;
+            // SAFETY: one valid mbuf; on success the driver owns it.
+            let sent = unsafe { mev_dpdk_tx_burst(self.port_id, queue, &mut mbuf, 1) } == 1;
+            if sent {
+                std::mem::forget(frame);
+            }
+            sent
+        }
+
+        /// Run `worker(queue)` for every queue, each on its own worker lcore,
+        /// and wait for all of them to return.
+        pub fn run_per_queue(&self, worker: &(dyn Fn(u16) + Sync)) -> Result<(), DpdkError> {
+            // SAFETY: EAL is initialised.
+            let workers = unsafe { rte_lcore_count() }.saturating_sub(1);
+            if workers < self.queues as c_uint {
+                return Err(DpdkError::NotEnoughLcores {
+                    queues: self.queues,
+                    workers: workers as u16,
+                });
+            }
+            let mut lcore = c_uint::MAX;
+            let lcores: Vec<c_uint> = (0..self.queues)
+                .map(|_| {
+                    // SAFETY: EAL is initialised; enough workers were checked above.
+                    lcore = unsafe { rte_get_next_lcore(lcore, 1, 0) };
+                    lcore
+                })
+                .collect();
+
+            struct Job<'w> {
+                worker: &'w (dyn Fn(u16) + Sync),
+                queue: u16,
+            }
+            extern "C" fn trampoline(arg: *mut c_void) -> c_int {
+                // SAFETY: `arg` points at a `Job` kept alive until mp_wait returns.
+                let job = unsafe { &*(arg as *const Job) };
+                (job.worker)(job.queue);
+                0
+            }
+            let mut jobs: Vec<Job> = (0..self.queues)
+                .map(|queue| Job { worker, queue })
+                .collect();
+            for (job, &lcore) in jobs.iter_mut().zip(&lcores) {
+                // SAFETY: the lcore is an idle worker; `jobs` outlives the wait below.
+                unsafe { rte_eal_remote_launch(trampoline, job as *mut Job as *mut c_void, lcore) };
+            }
+            // SAFETY: EAL is initialised.
+            unsafe { rte_eal_mp_wait_lcore() };
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(payload: &[u8]) -> Vec<u8> {
+        let mut f = vec![0u8; HEADERS_LEN];
+        f[..6].copy_from_slice(&[0x02, 0, 0, 0, 0, 0x01]);
+        f[6..12].copy_from_slice(&[0x02, 0, 0, 0, 0, 0x02]);
+        f[12..14].copy_from_slice(&[0x08, 0x00]);
+        f[14] = 0x45;
+        f[16..18].copy_from_slice(&((28 + payload.len()) as u16).to_be_bytes());
+        f[23] = 17;
+        f[26..30].copy_from_slice(&[10, 0, 0, 1]);
+        f[30..34].copy_from_slice(&[10, 0, 0, 2]);
+        f[34..36].copy_from_slice(&9000u16.to_be_bytes());
+        f[36..38].copy_from_slice(&8080u16.to_be_bytes());
+        f[38..40].copy_from_slice(&((8 + payload.len()) as u16).to_be_bytes());
+        f.extend_from_slice(payload);
+        // Ethernet minimum-size padding must not leak into the payload.
+        f.resize(f.len().max(60), 0);
+        f
+    }
+
+    #[test]
+    fn payload_is_borrowed_from_the_frame() {
+        let f = frame(&[7u8; 5]);
+        let (port, payload) = udp_payload(&f).expect("UDP over IPv4");
+        assert_eq!((port, payload), (8080, &[7u8; 5][..]));
+        assert_eq!(payload.as_ptr(), f[HEADERS_LEN..].as_ptr());
+
+        let mut options = f.clone();
+        options[14] = 0x46;
+        let mut fragment = f.clone();
+        fragment[20] = 0x20;
+        assert!(udp_payload(&options).is_none() && udp_payload(&fragment).is_none());
+    }
+
+    #[test]
+    fn checksums_are_verified() {
+        let mut f = frame(&[7u8; 5]);
+        assert!(!checksums_ok(&f), "header checksum left at zero");
+        assert!(turn_around(&mut f, 5));
+        assert!(checksums_ok(&f), "no UDP checksum");
+
+        // Fill in the UDP checksum the way a sender would.
+        let sum = ones_complement_sum(17 + 13, &f[26..34]);
+        let udp = !fold(ones_complement_sum(sum, &f[34..47]));
+        f[40..42].copy_from_slice(&udp.to_be_bytes());
+        assert!(checksums_ok(&f));
+        f[44] ^= 1;
+        assert!(!checksums_ok(&f));
+        assert!(!checksums_ok(&f[..20]));
+    }
+
+    #[test]
+    fn reply_headers_are_turned_around() {
+        let mut f = frame(&[0u8; 48]);
+        assert!(turn_around(&mut f, 32));
+        assert_eq!(
+            (&f[..6], &f[6..12]),
+            (&[0x02, 0, 0, 0, 0, 0x02][..], &[0x02, 0, 0, 0, 0, 0x01][..])
+        );
+        assert_eq!(
+            (&f[26..30], &f[30..34]),
+            (&[10, 0, 0, 2][..], &[10, 0, 0, 1][..])
+        );
+        assert_eq!(u16::from_be_bytes([f[34], f[35]]), 8080);
+        assert_eq!(u16::from_be_bytes([f[36], f[37]]), 9000);
+        assert_eq!(u16::from_be_bytes([f[16], f[17]]), 20 + 8 + 32);
+        assert_eq!(u16::from_be_bytes([f[38], f[39]]), 8 + 32);
+        // A valid header checksums to zero.
+        let sum = f[14..34]
+            .chunks(2)
+            .fold(0u32, |acc, w| acc + u16::from_be_bytes([w[0], w[1]]) as u32);
+        assert_eq!((sum & 0xFFFF) + (sum >> 16), 0xFFFF);
+    }
+
+    #[test]
+    fn built_headers_match_a_turned_around_frame() {
+        let mut f = frame(&[0u8; 48]);
+        assert!(turn_around(&mut f, 32));
+        let node = UdpEndpoint {
+            mac: [0x02, 0, 0, 0, 0, 0x01],
+            addr: [10, 0, 0, 2],
+            port: 8080,
+        };
+        let peer = UdpEndpoint {
+            mac: [0x02, 0, 0, 0, 0, 0x02],
+            addr: [10, 0, 0, 1],
+            port: 9000,
+        };
+        // The received frame carried no ID or TOS; everything else is ours.
+        assert_eq!(build_headers(&node, &peer, 32).unwrap(), f[..HEADERS_LEN]);
+        assert_eq!(build_headers(&node, &peer, 70_000), None);
+    }
+}
@@ -0,0 +1,112 @@
+/*
+ * Thin C wrappers around DPDK's static-inline fast-path API (burst RX/TX,
+ * mbuf access) and the rte_eth_conf-heavy port setup, so the Rust side only
+ * binds plain exported symbols. Compiled by build.rs with the `dpdk` feature.
+ */
+#include <string.h>
+
+#include <rte_ethdev.h>
+#include <rte_mbuf.h>
+#include <rte_power_intrinsics.h>
+
+#define MEV_DPDK_RING_SIZE 1024
+
+uint16_t mev_dpdk_rx_burst(uint16_t port, uint16_t queue, struct rte_mbuf **pkts, uint16_t n)
+{
+    return rte_eth_rx_burst(port, queue, pkts, n);
+}
+
+uint16_t mev_dpdk_tx_burst(uint16_t port, uint16_t queue, struct rte_mbuf **pkts, uint16_t n)
+{
+    return rte_eth_tx_burst(port, queue, pkts, n);
+}
+
+void mev_dpdk_free(struct rte_mbuf *m)
+{
+    rte_pktmbuf_free(m);
+}
+
+/* Start of the packet data; `len` is the frame length, `capacity` the room
+ * from the start of the data to the end of the buffer. */
+uint8_t *mev_dpdk_data(struct rte_mbuf *m, uint16_t *len, uint16_t *capacity)
+{
+    *len = rte_pktmbuf_data_len(m);
+    *capacity = rte_pktmbuf_data_room_size(m->pool) - m->data_off;
+    return rte_pktmbuf_mtod(m, uint8_t *);
+}
+
+void mev_dpdk_set_len(struct rte_mbuf *m, uint16_t len)
+{
+    m->data_len = len;
+    m->pkt_len = len;
+}
+
+struct rte_mbuf *mev_dpdk_alloc(struct rte_mempool *pool)
+{
+    return rte_pktmbuf_alloc(pool);
+}
+
+/* Sleep (umonitor/umwait) until the NIC writes the queue's next RX
+ * descriptor or the TSC reaches `tsc_deadline`. Returns 0, or a negative
+ * errno if the driver or CPU cannot (-ENOTSUP) and nothing was waited. */
+int mev_dpdk_wait_rx(uint16_t port, uint16_t queue, uint64_t tsc_deadline)
+{
+    struct rte_power_monitor_cond pmc;
+    int rc = rte_eth_get_monitor_addr(port, queue, &pmc);
+    if (rc < 0)
+        return rc;
+    return rte_power_monitor(&pmc, tsc_deadline);
+}
+
+/* Configure `queues` RX/TX queue pairs (RSS across them, hashed with
+ * `rss_key` and spread round-robin by the indirection table), start the port
+ * and enable promiscuous mode. The table size goes to `reta_size`. Returns 0
+ * or a negative errno. */
+int mev_dpdk_port_init(uint16_t port, uint16_t queues, struct rte_mempool *pool, uint8_t *rss_key,
+                       uint8_t rss_key_len, uint16_t *reta_size)
+{
+    struct rte_eth_dev_info info;
+    struct rte_eth_conf conf;
+    int rc = rte_eth_dev_info_get(port, &info);
+    if (rc < 0)
+        return rc;
+
+    memset(&conf, 0, sizeof(conf));
+    if (queues > 1) {
+        conf.rxmode.mq_mode = RTE_ETH_MQ_RX_RSS;
+        conf.rx_adv_conf.rss_conf.rss_hf = (RTE_ETH_RSS_IP | RTE_ETH_RSS_UDP) & info.flow_type_rss_offloads;
+        conf.rx_adv_conf.rss_conf.rss_key = rss_key;
+        conf.rx_adv_conf.rss_conf.rss_key_len = rss_key_len;
+    }
+    *reta_size = info.reta_size;
+    rc = rte_eth_dev_configure(port, queues, queues, &conf);
+    if (rc < 0)
+        return rc;
+
+    int socket = rte_eth_dev_socket_id(port);
+    for (uint16_t q = 0; q < queues; q++) {
+        rc = rte_eth_rx_queue_setup(port, q, MEV_DPDK_RING_SIZE, socket, NULL, pool);
+        if (rc < 0)
+            return rc;
+        rc = rte_eth_tx_queue_setup(port, q, MEV_DPDK_RING_SIZE, socket, NULL);
+        if (rc < 0)
+            return rc;
+    }
+    rc = rte_eth_dev_start(port);
+    if (rc < 0)
+        return rc;
+    if (queues > 1 && info.reta_size > 0) {
+        struct rte_eth_rss_reta_entry64 reta[RTE_ETH_RSS_RETA_SIZE_512 / RTE_ETH_RETA_GROUP_SIZE];
+        uint16_t size = info.reta_size < RTE_ETH_RSS_RETA_SIZE_512 ? info.reta_size : RTE_ETH_RSS_RETA_SIZE_512;
+        memset(reta, 0, sizeof(reta));
+        for (uint16_t i = 0; i < size; i++) {
+            reta[i / RTE_ETH_RETA_GROUP_SIZE].mask |= 1ULL << (i % RTE_ETH_RETA_GROUP_SIZE);
+            reta[i / RTE_ETH_RETA_GROUP_SIZE].reta[i % RTE_ETH_RETA_GROUP_SIZE] = i % queues;
+        }
+        rc = rte_eth_dev_rss_reta_update(port, reta, size);
+        if (rc < 0)
+            return rc;
+        *reta_size = size;
+    }
+    return rte_eth_promiscuous_enable(port);
+}
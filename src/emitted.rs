@@ -0,0 +1,263 @@
+//! Fixed ring of recently emitted opportunities.
+//!
+//! Every reply the node sends is remembered together with the victim it
+//! targets and where the reply went. When a pool update moves a pool, the
+//! live opportunities on it are re-evaluated and the ones that no longer pay
+//! are marked for cancellation; so are opportunities whose victim deadline
+//! has passed. Marked entries are drained by the TX path, which sends one
+//! `OpportunityCancel` each. The oldest entry is overwritten once the ring is
+//! full — by then its victim has long been mined or dropped. An executor
+//! reporting the victim landed settles its live entry instead.
+use crate::nonce::Address;
+use crate::payload::{CancelReason, DexSwapTxExt};
+use crate::pool_registry::PoolAddress;
+use crate::processor::Opportunity;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Live,
+    CancelPending(CancelReason),
+    Retired,
+}
+
+/// One emitted opportunity. `R` is where its reply was sent.
+#[derive(Clone, Copy, Debug)]
+pub struct EmittedOpportunity<R> {
+    /// Victim record; `deadline_ms` is 0 for base `DexSwapTx` payloads.
+    pub victim: DexSwapTxExt,
+    /// Victim account for EVM payloads, zero otherwise.
+    pub sender: Address,
+    /// As replied, for the PnL books once the victim settles.
+    pub opportunity: Opportunity,
+    pub route: R,
+    state: State,
+}
+
+impl<R> EmittedOpportunity<R> {
+    #[inline(always)]
+    pub fn nonce(&self) -> u64 {
+        self.victim.base.nonce()
+    }
+
+    #[inline(always)]
+    pub fn pool(&self) -> &PoolAddress {
+        &self.victim.base.pool_address
+    }
+}
+
+pub struct EmittedRing<R: Copy, const N: usize> {
+    entries: [Option<EmittedOpportunity<R>>; N],
+    head: usize,
+    pending: usize,
+}
+
+impl<R: Copy, const N: usize> EmittedRing<R, N> {
+    pub fn new() -> Self {
+        Self {
+            entries: [None; N],
+            head: 0,
+            pending: 0,
+        }
+    }
+
+    /// Remember an opportunity whose reply was just sent.
+    pub fn push(
+        &mut self,
+        victim: DexSwapTxExt,
+        sender: Address,
+        opportunity: Opportunity,
+        route: R,
+    ) {
+        let slot = &mut self.entries[self.head];
+        if let Some(EmittedOpportunity {
+            state: State::CancelPending(_),
+            ..
+        }) = slot
+        {
+            self.pending -= 1;
+        }
+        *slot = Some(EmittedOpportunity {
+            victim,
+            sender,
+            opportunity,
+            route,
+            state: State::Live,
+        });
+        self.head = (self.head + 1) % N;
+    }
+
+    fn mark(&mut self, reason: impl Fn(&EmittedOpportunity<R>) -> Option<CancelReason>) -> usize {
+        let mut marked = 0;
+        for entry in self
+            .entries
+            .iter_mut()
+            .flatten()
+            .filter(|e| e.state == State::Live)
+        {
+            if let Some(reason) = reason(entry) {
+                entry.state = State::CancelPending(reason);
+                marked += 1;
+            }
+        }
+        self.pending += marked;
+        marked
+    }
+
+    /// Re-check the live opportunities on `pool` after it moved; those for
+    /// which `still_profitable` fails are marked [`CancelReason::PoolMoved`].
+    pub fn invalidate_pool(
+        &mut self,
+        pool: &PoolAddress,
+        still_profitable: impl Fn(&DexSwapTxExt) -> bool,
+    ) -> usize {
+        self.mark(|e| {
+            (e.pool() == pool && !still_profitable(&e.victim)).then_some(CancelReason::PoolMoved)
+        })
+    }
+
+    /// Mark live opportunities whose victim expires before
+    /// `now_ms + execution_latency_ms` as [`CancelReason::Expired`].
+    pub fn expire(&mut self, now_ms: u64, execution_latency_ms: u64) -> usize {
+        let horizon = now_ms.saturating_add(execution_latency_ms);
+        self.mark(|e| {
+            let deadline = e.victim.deadline_ms();
+            (deadline != 0 && deadline < horizon).then_some(CancelReason::Expired)
+        })
+    }
+
+    /// Stop tracking the opportunity against `(sender, nonce)`: its
+    /// cancellation was already sent by other means.
+    pub fn retire(&mut self, sender: &Address, nonce: u64) {
+        for entry in self.entries.iter_mut().flatten() {
+            if entry.sender == *sender && entry.nonce() == nonce {
+                if let State::CancelPending(_) = entry.state {
+                    self.pending -= 1;
+                }
+                entry.state = State::Retired;
+            }
+        }
+    }
+
+    /// The live opportunity against `(sender, nonce)`, retired: its victim
+    /// landed, and with it the reply. Cancelled ones are not returned.
+    pub fn settle(&mut self, sender: &Address, nonce: u64) -> Option<Opportunity> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .flatten()
+            .find(|e| e.state == State::Live && e.sender == *sender && e.nonce() == nonce)?;
+        entry.state = State::Retired;
+        Some(entry.opportunity)
+    }
+
+    /// Cancellations waiting to be sent.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Hand every pending cancellation to `send`. Entries for which `send`
+    /// returns `false` (TX buffer full) stay pending for the next drain.
+    /// Returns the number sent.
+    pub fn drain(
+        &mut self,
+        mut send: impl FnMut(&EmittedOpportunity<R>, CancelReason) -> bool,
+    ) -> usize {
+        if self.pending == 0 {
+            return 0;
+        }
+        let mut sent = 0;
+        for entry in self.entries.iter_mut().flatten() {
+            if let State::CancelPending(reason) = entry.state {
+                if send(entry, reason) {
+                    entry.state = State::Retired;
+                    sent += 1;
+                }
+            }
+        }
+        self.pending -= sent;
+        sent
+    }
+}
+
+impl<R: Copy, const N: usize> Default for EmittedRing<R, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::DexSwapTx;
+
+    fn victim(nonce: u64, pool: u8, deadline_ms: u64) -> DexSwapTxExt {
+        DexSwapTxExt::from_parts(
+            DexSwapTx::from_parts(nonce, [pool; 20], 50_000_000, 1, 0),
+            deadline_ms,
+        )
+    }
+
+    #[test]
+    fn pool_moves_and_expiry_queue_one_cancel_each() {
+        let mut ring: EmittedRing<u16, 8> = EmittedRing::new();
+        ring.push(victim(1, 0xA, 0), [0; 20], Opportunity::unscored(1), 9000);
+        ring.push(
+            victim(2, 0xA, 5_000),
+            [0; 20],
+            Opportunity::unscored(1),
+            9001,
+        );
+        ring.push(victim(3, 0xB, 0), [0; 20], Opportunity::unscored(1), 9002);
+
+        // Nonce 2 still pays after the move; nonce 1 does not.
+        assert_eq!(ring.invalidate_pool(&[0xA; 20], |v| v.base.nonce() == 2), 1);
+        assert_eq!(ring.expire(4_900, 200), 1);
+        assert_eq!(ring.expire(4_900, 200), 0, "already pending");
+
+        let mut sent = Vec::new();
+        assert_eq!(
+            ring.drain(|e, reason| {
+                sent.push((e.nonce(), e.route, reason));
+                true
+            }),
+            2
+        );
+        assert_eq!(
+            sent,
+            [
+                (1, 9000, CancelReason::PoolMoved),
+                (2, 9001, CancelReason::Expired)
+            ]
+        );
+        assert_eq!(ring.pending(), 0);
+        assert_eq!(
+            ring.invalidate_pool(&[0xA; 20], |_| false),
+            0,
+            "cancelled opportunities are not revisited"
+        );
+        assert_eq!(ring.settle(&[0; 20], 1), None, "cancelled");
+        assert_eq!(ring.settle(&[0; 20], 3).map(|o| o.profit), Some(1));
+        assert_eq!(ring.settle(&[0; 20], 3), None, "settled once");
+    }
+
+    #[test]
+    fn full_tx_buffer_keeps_cancels_pending() {
+        let mut ring: EmittedRing<(), 4> = EmittedRing::new();
+        ring.push(victim(1, 0xA, 0), [0; 20], Opportunity::unscored(1), ());
+        ring.invalidate_pool(&[0xA; 20], |_| false);
+        assert_eq!(ring.drain(|_, _| false), 0);
+        assert_eq!(ring.pending(), 1);
+        assert_eq!(ring.drain(|_, _| true), 1);
+    }
+
+    #[test]
+    fn overwrite_and_retire_drop_pending_cancels() {
+        let mut ring: EmittedRing<(), 2> = EmittedRing::new();
+        ring.push(victim(1, 0xA, 0), [0x11; 20], Opportunity::unscored(1), ());
+        ring.push(victim(2, 0xA, 0), [0x22; 20], Opportunity::unscored(1), ());
+        assert_eq!(ring.invalidate_pool(&[0xA; 20], |_| false), 2);
+        ring.retire(&[0x22; 20], 2);
+        ring.push(victim(3, 0xB, 0), [0; 20], Opportunity::unscored(1), ());
+        assert_eq!(ring.pending(), 0);
+    }
+}
@@ -0,0 +1,212 @@
+//! Health-scored submission endpoints (builders / relays).
+//!
+//! Submitting to a single relay ties our landing rate to its worst moment.
+//! [`EndpointSet`] keeps, per endpoint, an EWMA of acknowledgement latency
+//! and of the error indicator, and picks the best `fanout` endpoints for
+//! each submission, which then goes to all of them in parallel. An endpoint
+//! whose error rate crosses `max_error_bps` fails over: it is taken out of
+//! rotation for `cooldown_cycles` and then probed again with its error
+//! history reset to half the threshold.
+//!
+//! If every endpoint is cooling down, the one that comes back soonest is
+//! still used — a submission never goes nowhere.
+//!
+//! The node does not submit bundles yet, so nothing configures or uses the
+//! set; it is here for when it does.
+
+/// Maximum number of submission endpoints.
+pub const MAX_ENDPOINTS: usize = 8;
+
+/// EWMA weight of a new sample, as a shift (1/8).
+const EWMA_SHIFT: u32 = 3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EndpointPolicy {
+    /// Endpoints each submission goes to.
+    pub fanout: usize,
+    /// Error-rate EWMA (bps) above which an endpoint fails over.
+    pub max_error_bps: u32,
+    /// How long a failed endpoint sits out before it is probed again.
+    pub cooldown_cycles: u64,
+}
+
+impl Default for EndpointPolicy {
+    fn default() -> Self {
+        Self {
+            fanout: 2,
+            max_error_bps: 2_000,
+            cooldown_cycles: 3_000_000_000,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EndpointHealth {
+    /// EWMA of submission-to-acknowledgement latency, nanoseconds.
+    pub latency_ewma_ns: u64,
+    /// EWMA of the error indicator, basis points.
+    pub error_ewma_bps: u32,
+    /// Acknowledged submissions.
+    pub successes: u64,
+    /// Failed or unacknowledged submissions.
+    pub errors: u64,
+    /// Out of rotation until this cycle.
+    pub down_until: u64,
+}
+
+impl EndpointHealth {
+    /// Lower is better: latency inflated by the error rate. Endpoints with
+    /// no samples yet score 0, so they are tried first.
+    #[inline(always)]
+    pub fn score(&self) -> u64 {
+        self.latency_ewma_ns
+            .saturating_mul(10_000 + 4 * self.error_ewma_bps as u64)
+            / 10_000
+    }
+}
+
+/// Up to `N` endpoints of type `E` (e.g. `([u8; 4], u16)`) with their health.
+pub struct EndpointSet<E, const N: usize = MAX_ENDPOINTS> {
+    policy: EndpointPolicy,
+    endpoints: heapless::Vec<(E, EndpointHealth), N>,
+    /// Endpoints taken out of rotation so far.
+    pub failovers: u64,
+}
+
+impl<E, const N: usize> EndpointSet<E, N> {
+    pub fn new(policy: EndpointPolicy) -> Self {
+        Self {
+            policy,
+            endpoints: heapless::Vec::new(),
+            failovers: 0,
+        }
+    }
+
+    /// Add an endpoint, returning its index; gives it back if the set is full.
+    pub fn add(&mut self, endpoint: E) -> Result<usize, E> {
+        self.endpoints
+            .push((endpoint, EndpointHealth::default()))
+            .map_err(|(e, _)| e)?;
+        Ok(self.endpoints.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.endpoints.is_empty()
+    }
+
+    pub fn endpoint(&self, index: usize) -> &E {
+        &self.endpoints[index].0
+    }
+
+    pub fn health(&self, index: usize) -> &EndpointHealth {
+        &self.endpoints[index].1
+    }
+
+    /// Record an acknowledged submission to endpoint `index`.
+    pub fn record_success(&mut self, index: usize, latency_ns: u64) {
+        let Some((_, health)) = self.endpoints.get_mut(index) else {
+            return;
+        };
+        health.latency_ewma_ns = if health.successes == 0 {
+            latency_ns
+        } else {
+            health.latency_ewma_ns - (health.latency_ewma_ns >> EWMA_SHIFT)
+                + (latency_ns >> EWMA_SHIFT)
+        };
+        health.error_ewma_bps -= health.error_ewma_bps >> EWMA_SHIFT;
+        health.successes += 1;
+    }
+
+    /// Record a failed submission to endpoint `index` at cycle `now`; takes
+    /// the endpoint out of rotation once its error rate crosses the policy.
+    pub fn record_error(&mut self, index: usize, now: u64) {
+        let policy = self.policy;
+        let Some((_, health)) = self.endpoints.get_mut(index) else {
+            return;
+        };
+        health.error_ewma_bps =
+            health.error_ewma_bps - (health.error_ewma_bps >> EWMA_SHIFT) + (10_000 >> EWMA_SHIFT);
+        health.errors += 1;
+        if health.error_ewma_bps > policy.max_error_bps && health.down_until <= now {
+            health.down_until = now.saturating_add(policy.cooldown_cycles);
+            health.error_ewma_bps = policy.max_error_bps / 2;
+            self.failovers += 1;
+        }
+    }
+
+    /// Indices of the endpoints to submit to at cycle `now`, best first.
+    pub fn select(&self, now: u64) -> heapless::Vec<usize, N> {
+        let mut picked: heapless::Vec<usize, N> = (0..self.endpoints.len())
+            .filter(|&i| self.endpoints[i].1.down_until <= now)
+            .collect();
+        picked.sort_unstable_by_key(|&i| (self.endpoints[i].1.score(), i));
+        picked.truncate(self.policy.fanout.max(1));
+        if picked.is_empty() {
+            if let Some(soonest) =
+                (0..self.endpoints.len()).min_by_key(|&i| self.endpoints[i].1.down_until)
+            {
+                let _ = picked.push(soonest);
+            }
+        }
+        picked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn four(fanout: usize) -> EndpointSet<u8, 4> {
+        let mut set = EndpointSet::new(EndpointPolicy {
+            fanout,
+            max_error_bps: 2_000,
+            cooldown_cycles: 1_000,
+        });
+        for id in 0..4 {
+            set.add(id).unwrap();
+        }
+        set
+    }
+
+    #[test]
+    fn submits_to_the_fastest_healthy_endpoints() {
+        let mut set = four(2);
+        assert_eq!(set.add(9), Err(9));
+        // Endpoints without samples score 0 and are probed first.
+        assert_eq!(set.select(0), [0, 1]);
+        for (i, latency) in [800, 200, 500, 250].into_iter().enumerate() {
+            set.record_success(i, latency);
+        }
+        assert_eq!(set.select(0), [1, 3]);
+        // Errors inflate an endpoint's score before it fails over.
+        set.record_error(1, 0);
+        assert_eq!(set.select(0), [3, 1]);
+        assert_eq!(set.failovers, 0);
+    }
+
+    #[test]
+    fn failing_endpoint_sits_out_its_cooldown() {
+        let mut set = four(1);
+        for (i, latency) in [100, 900, 700, 800].into_iter().enumerate() {
+            set.record_success(i, latency);
+        }
+        set.record_error(0, 10);
+        set.record_error(0, 20);
+        assert_eq!(set.failovers, 1);
+        assert_eq!(set.health(0).down_until, 1_020);
+        assert_eq!(set.select(500), [2]);
+        assert_eq!(set.select(1_020), [0]);
+
+        // With everything down, the endpoint back soonest is still used.
+        let mut down = four(2);
+        for (i, at) in [40, 10, 30, 20].into_iter().enumerate() {
+            down.record_error(i, at);
+            down.record_error(i, at);
+        }
+        assert_eq!(down.select(100), [1]);
+    }
+}
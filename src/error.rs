@@ -0,0 +1,468 @@
+//! Crate-level error type.
+//!
+//! Each module keeps its own small error enum, matched on where it is
+//! handled. [`NodeError`] wraps any of them (`?` converts through `From`),
+//! adds the operation that failed ([`Context`]) and gives every variant a
+//! stable numeric code, so dashboards and runbooks can key on `E501` rather
+//! than on message text. Codes are grouped by area and never reused:
+//!
+//! | Codes | Area |
+//! |---|---|
+//! | 100–129 | wire: validation, encoding, feed sessions |
+//! | 200–229 | configuration: port layout, reply routes, pool seeding |
+//! | 300–319 | persistence: state file, timelines |
+//! | 400–419 | clocks and timers |
+//! | 500–549 | network setup: netlink, TAP, AF_XDP, XDP filter, DPDK |
+//! | 600–629 | admin socket, handover and other I/O |
+//!
+//! Errors the node survives are kept in an [`ErrorLog`], one entry per
+//! code, and served by the admin `errors` command.
+use crate::admin::AdminError;
+use crate::backtest::TimelineError;
+use crate::handover::HandoverError;
+use crate::netlink::NetlinkError;
+use crate::persist::StateFileError;
+use crate::phc::PhcError;
+use crate::pool_seed::PoolSeedError;
+use crate::ports::PortLayoutError;
+use crate::reply_routes::RouteError;
+use crate::session::SessionError;
+use crate::timer::TimerError;
+use crate::transport::tap::TapError;
+use crate::validator::ValidationError;
+use crate::wire::encoder::EncodeError;
+use std::borrow::Cow;
+use std::sync::Mutex;
+
+/// Distinct codes an [`ErrorLog`] keeps; further codes are only logged.
+pub const MAX_ERROR_CODES: usize = 32;
+
+/// The module error a [`NodeError`] wraps.
+#[derive(Debug)]
+pub enum NodeErrorKind {
+    Validation(ValidationError),
+    Encode(EncodeError),
+    Session(SessionError),
+    PortLayout(PortLayoutError),
+    Route(RouteError),
+    PoolSeed(PoolSeedError),
+    StateFile(StateFileError),
+    Timeline(TimelineError),
+    Phc(PhcError),
+    Timer(TimerError),
+    Netlink(NetlinkError),
+    Tap(TapError),
+    #[cfg(target_os = "linux")]
+    Xdp(crate::xdp::XdpError),
+    #[cfg(target_os = "linux")]
+    XdpFilter(crate::xdp_filter::XdpFilterError),
+    #[cfg(feature = "dpdk")]
+    Dpdk(crate::dpdk::DpdkError),
+    Admin(AdminError),
+    Handover(HandoverError),
+    Io(std::io::Error),
+}
+
+impl NodeErrorKind {
+    /// Short name of the wrapped error's module.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Validation(_) => "validation",
+            Self::Encode(_) => "encode",
+            Self::Session(_) => "session",
+            Self::PortLayout(_) => "port_layout",
+            Self::Route(_) => "route",
+            Self::PoolSeed(_) => "pool_seed",
+            Self::StateFile(_) => "state_file",
+            Self::Timeline(_) => "timeline",
+            Self::Phc(_) => "phc",
+            Self::Timer(_) => "timer",
+            Self::Netlink(_) => "netlink",
+            Self::Tap(_) => "tap",
+            #[cfg(target_os = "linux")]
+            Self::Xdp(_) => "xdp",
+            #[cfg(target_os = "linux")]
+            Self::XdpFilter(_) => "xdp_filter",
+            #[cfg(feature = "dpdk")]
+            Self::Dpdk(_) => "dpdk",
+            Self::Admin(_) => "admin",
+            Self::Handover(_) => "handover",
+            Self::Io(_) => "io",
+        }
+    }
+
+    /// Stable numeric code; see the module table.
+    pub const fn code(&self) -> u16 {
+        match self {
+            Self::Validation(e) => match e {
+                ValidationError::TooShort => 100,
+                ValidationError::LayoutMismatch => 101,
+                ValidationError::ZeroReserves => 102,
+                ValidationError::SequenceGap { .. } => 103,
+                ValidationError::UnsupportedVersion(_) => 104,
+                ValidationError::FeeOutOfRange(_) => 105,
+            },
+            Self::Encode(e) => match e {
+                EncodeError::BufferTooSmall { .. } => 110,
+                EncodeError::ZeroPoolAddress => 111,
+                EncodeError::ZeroAmount => 112,
+                EncodeError::ZeroReserves => 113,
+                EncodeError::ExtendedInBatch => 114,
+                EncodeError::SentWithoutFee => 115,
+            },
+            Self::Session(e) => match e {
+                SessionError::BadWelcome => 120,
+                SessionError::UnsupportedVersion(_) => 121,
+                SessionError::UnsupportedWireVersion(_) => 122,
+                SessionError::RecordTooLarge(_) => 123,
+            },
+            Self::PortLayout(e) => match e {
+                PortLayoutError::UnknownClass => 200,
+                PortLayoutError::InvalidPort => 201,
+                PortLayoutError::PoolsShared => 202,
+            },
+            Self::Route(e) => match e {
+                RouteError::Syntax => 210,
+                RouteError::UnknownStrategy => 211,
+                RouteError::InvalidPool => 212,
+                RouteError::UnknownKind => 213,
+                RouteError::InvalidDestination => 214,
+                RouteError::UnknownExecutor(_) => 215,
+                RouteError::TooMany => 216,
+            },
+            Self::PoolSeed(e) => match e {
+                PoolSeedError::Read(_) => 220,
+                PoolSeedError::Syntax { .. } => 221,
+                PoolSeedError::InvalidAddress { .. } => 222,
+                PoolSeedError::InvalidReserves { .. } => 223,
+                PoolSeedError::InvalidFee { .. } => 224,
+                PoolSeedError::UnknownKind { .. } => 225,
+                PoolSeedError::UnknownToken { .. } => 226,
+            },
+            Self::StateFile(e) => match e {
+                StateFileError::Open(_) => 300,
+                StateFileError::Resize(_) => 301,
+                StateFileError::Mmap(_) => 302,
+            },
+            Self::Timeline(e) => match e {
+                TimelineError::BadMagic => 310,
+                TimelineError::Truncated { .. } => 311,
+                TimelineError::UnknownKind { .. } => 312,
+            },
+            Self::Phc(e) => match e {
+                PhcError::Open(_) => 400,
+                PhcError::SysOffset(_) => 401,
+                PhcError::NoSamples => 402,
+            },
+            Self::Timer(TimerError::Full) => 410,
+            Self::Netlink(e) => match e {
+                NetlinkError::Socket(_) => 500,
+                NetlinkError::Send(_) => 501,
+                NetlinkError::Recv(_) => 502,
+                NetlinkError::Kernel(_) => 503,
+                NetlinkError::BadReply => 504,
+                NetlinkError::IfNotFound => 505,
+            },
+            Self::Tap(e) => match e {
+                TapError::MissingCapability => 510,
+                TapError::NameTooLong => 511,
+                TapError::TunOpen(_) => 512,
+                TapError::TunIoctl(_) => 513,
+                TapError::Netlink(_) => 514,
+            },
+            #[cfg(target_os = "linux")]
+            Self::Xdp(e) => {
+                use crate::xdp::XdpError;
+                match e {
+                    XdpError::SocketOpen(_) => 520,
+                    XdpError::MmapFailed(_) => 521,
+                    XdpError::MlockFailed(_) => 522,
+                    XdpError::UmemReg(_) => 523,
+                    XdpError::IfNotFound => 524,
+                    XdpError::BindFailed(_) => 525,
+                    XdpError::RingMmap(_) => 526,
+                }
+            }
+            #[cfg(target_os = "linux")]
+            Self::XdpFilter(e) => {
+                use crate::xdp_filter::XdpFilterError;
+                match e {
+                    XdpFilterError::MapCreate(_) => 530,
+                    XdpFilterError::MapUpdate(_) => 531,
+                    XdpFilterError::ProgLoad(_) => 532,
+                    XdpFilterError::Attach(_) => 533,
+                    XdpFilterError::IfNotFound => 534,
+                }
+            }
+            #[cfg(feature = "dpdk")]
+            Self::Dpdk(e) => {
+                use crate::dpdk::DpdkError;
+                match e {
+                    DpdkError::EalInit(_) => 540,
+                    DpdkError::Mempool => 541,
+                    DpdkError::PortInit { .. } => 542,
+                    DpdkError::NotEnoughLcores { .. } => 543,
+                }
+            }
+            Self::Admin(AdminError::Bind(_)) => 600,
+            Self::Handover(e) => match e {
+                HandoverError::Io(_) => 620,
+                HandoverError::BadHeader => 621,
+                HandoverError::FdCount { .. } => 622,
+                HandoverError::UnknownRole(_) => 623,
+            },
+            Self::Io(_) => 610,
+        }
+    }
+
+    fn inner(&self) -> &(dyn std::error::Error + 'static) {
+        match self {
+            Self::Validation(e) => e,
+            Self::Encode(e) => e,
+            Self::Session(e) => e,
+            Self::PortLayout(e) => e,
+            Self::Route(e) => e,
+            Self::PoolSeed(e) => e,
+            Self::StateFile(e) => e,
+            Self::Timeline(e) => e,
+            Self::Phc(e) => e,
+            Self::Timer(e) => e,
+            Self::Netlink(e) => e,
+            Self::Tap(e) => e,
+            #[cfg(target_os = "linux")]
+            Self::Xdp(e) => e,
+            #[cfg(target_os = "linux")]
+            Self::XdpFilter(e) => e,
+            #[cfg(feature = "dpdk")]
+            Self::Dpdk(e) => e,
+            Self::Admin(e) => e,
+            Self::Handover(e) => e,
+            Self::Io(e) => e,
+        }
+    }
+}
+
+/// A module error with the operation it interrupted.
+#[derive(Debug)]
+pub struct NodeError {
+    kind: NodeErrorKind,
+    /// What was being done, outermost first; empty if not given.
+    context: Cow<'static, str>,
+}
+
+impl NodeError {
+    pub fn kind(&self) -> &NodeErrorKind {
+        &self.kind
+    }
+
+    pub const fn code(&self) -> u16 {
+        self.kind.code()
+    }
+
+    /// Name the operation that failed, e.g. `"reply path on eth1"`. Context
+    /// added later reads first.
+    pub fn context(mut self, context: impl Into<Cow<'static, str>>) -> Self {
+        let context = context.into();
+        self.context = if self.context.is_empty() {
+            context
+        } else {
+            format!("{context}: {}", self.context).into()
+        };
+        self
+    }
+}
+
+impl core::fmt::Display for NodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        if !self.context.is_empty() {
+            write!(f, "{}: ", self.context)?;
+        }
+        write!(f, "{} [E{}]", self.kind.inner(), self.code())
+    }
+}
+
+impl std::error::Error for NodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.kind.inner())
+    }
+}
+
+impl From<NodeErrorKind> for NodeError {
+    fn from(kind: NodeErrorKind) -> Self {
+        Self {
+            kind,
+            context: Cow::Borrowed(""),
+        }
+    }
+}
+
+macro_rules! node_error_from {
+    ($($(#[$cfg:meta])* $error:ty => $variant:ident,)+) => {
+        $(
+            $(#[$cfg])*
+            impl From<$error> for NodeError {
+                fn from(e: $error) -> Self {
+                    NodeErrorKind::$variant(e).into()
+                }
+            }
+        )+
+    };
+}
+
+node_error_from! {
+    ValidationError => Validation,
+    EncodeError => Encode,
+    SessionError => Session,
+    PortLayoutError => PortLayout,
+    RouteError => Route,
+    PoolSeedError => PoolSeed,
+    StateFileError => StateFile,
+    TimelineError => Timeline,
+    PhcError => Phc,
+    TimerError => Timer,
+    NetlinkError => Netlink,
+    TapError => Tap,
+    #[cfg(target_os = "linux")]
+    crate::xdp::XdpError => Xdp,
+    #[cfg(target_os = "linux")]
+    crate::xdp_filter::XdpFilterError => XdpFilter,
+    #[cfg(feature = "dpdk")]
+    crate::dpdk::DpdkError => Dpdk,
+    AdminError => Admin,
+    HandoverError => Handover,
+    std::io::Error => Io,
+}
+
+/// `.context(..)` on any result whose error converts into [`NodeError`].
+pub trait Context<T> {
+    fn context(self, context: impl Into<Cow<'static, str>>) -> Result<T, NodeError>;
+}
+
+impl<T, E: Into<NodeError>> Context<T> for Result<T, E> {
+    fn context(self, context: impl Into<Cow<'static, str>>) -> Result<T, NodeError> {
+        self.map_err(|e| e.into().context(context))
+    }
+}
+
+/// The latest occurrence of one error code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorRecord {
+    pub code: u16,
+    pub kind: &'static str,
+    pub count: u64,
+    /// The last occurrence, as displayed.
+    pub last: String,
+}
+
+/// Errors the node carried on from, one entry per code in order of first
+/// occurrence. Written off the packet path only.
+pub struct ErrorLog {
+    records: Mutex<Vec<ErrorRecord>>,
+}
+
+impl Default for ErrorLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ErrorLog {
+    pub const fn new() -> Self {
+        Self {
+            records: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn record(&self, error: &NodeError) {
+        let mut records = self.records.lock().unwrap_or_else(|e| e.into_inner());
+        let code = error.code();
+        if let Some(record) = records.iter_mut().find(|r| r.code == code) {
+            record.count += 1;
+            record.last = error.to_string();
+        } else if records.len() < MAX_ERROR_CODES {
+            records.push(ErrorRecord {
+                code,
+                kind: error.kind.name(),
+                count: 1,
+                last: error.to_string(),
+            });
+        }
+    }
+
+    pub fn records(&self) -> Vec<ErrorRecord> {
+        self.records
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// One line of JSON: `{"errors":[{"code":505,"kind":"netlink","count":1,"last":".."},..]}`.
+impl core::fmt::Display for ErrorLog {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{{\"errors\":[")?;
+        for (i, r) in self.records().iter().enumerate() {
+            let sep = if i == 0 { "" } else { "," };
+            write!(
+                f,
+                "{sep}{{\"code\":{},\"kind\":\"{}\",\"count\":{},\"last\":\"",
+                r.code, r.kind, r.count
+            )?;
+            for c in r.last.chars() {
+                match c {
+                    '"' | '\\' => write!(f, "\\{c}")?,
+                    c if c.is_control() => write!(f, "\\u{:04x}", c as u32)?,
+                    c => write!(f, "{c}")?,
+                }
+            }
+            write!(f, "\"}}")?;
+        }
+        write!(f, "]}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route_to(iface: &str) -> Result<(), NodeError> {
+        Err(NetlinkError::IfNotFound).context(format!("reply path on {iface}"))
+    }
+
+    #[test]
+    fn errors_carry_context_and_a_stable_code() {
+        let error = route_to("eth1").unwrap_err().context("startup");
+        assert_eq!(error.code(), 505);
+        assert_eq!(error.kind().name(), "netlink");
+        assert_eq!(
+            error.to_string(),
+            "startup: reply path on eth1: network interface not found [E505]"
+        );
+        assert!(std::error::Error::source(&error).is_some());
+
+        let io: NodeError = std::io::Error::from(std::io::ErrorKind::NotFound).into();
+        assert_eq!(
+            (
+                io.code(),
+                NodeError::from(ValidationError::ZeroReserves).code()
+            ),
+            (610, 102)
+        );
+
+        let log = ErrorLog::new();
+        log.record(&error);
+        log.record(&route_to("eth\"2").unwrap_err());
+        log.record(&io);
+        assert_eq!(
+            log.records()
+                .iter()
+                .map(|r| (r.code, r.count))
+                .collect::<Vec<_>>(),
+            [(505, 2), (610, 1)]
+        );
+        assert!(log.to_string().starts_with(
+            "{\"errors\":[{\"code\":505,\"kind\":\"netlink\",\"count\":2,\
+             \"last\":\"reply path on eth\\\"2: network interface not found [E505]\"},"
+        ));
+    }
+}
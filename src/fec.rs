@@ -0,0 +1,382 @@
+//! XOR-parity erasure coding for the pool feed.
+//!
+//! A feed sent as plain datagrams loses a message with every dropped
+//! packet. Coded, it is sent in groups of `k` data shards plus one parity
+//! shard, the XOR of the `k` data shards zero-padded to a common length; any
+//! `k` of the `k + 1` shards rebuild the group, so one loss per group costs
+//! nothing but the parity's bandwidth.
+//!
+//! Every shard is a [`FecShardHeader`] followed by `shard_len` bytes: a data
+//! shard carries one feed message (`PoolStateUpdate`, `BookUpdate`, ...)
+//! padded with zeros, the parity shard the XOR. [`FecDecoder`] passes each
+//! data shard on the moment it arrives — coding adds no latency to packets
+//! that were not lost — and folds it into the group's accumulator. Once the
+//! parity and all but one data shard are in, the accumulator is the missing
+//! shard and it is passed on too. Groups live in a fixed ring of slots; a
+//! group whose slot is taken by a newer one before it could be rebuilt counts
+//! its missing shards as unrecovered.
+use bytemuck::{Pod, Zeroable};
+
+/// Magic prefix of a `FecShardHeader` ("MVFC").
+pub const FEC_SHARD_MAGIC: [u8; 4] = *b"MVFC";
+/// Largest data shards per group.
+pub const MAX_FEC_K: usize = 16;
+/// Largest shard payload; fits a `BookUpdate`.
+pub const MAX_FEC_SHARD: usize = 320;
+
+/// Prefix of every coded datagram.
+///
+/// Layout (16 bytes, little-endian):
+///   [0..4]   magic      "MVFC"
+///   [4..8]   group      u32 — group sequence number
+///   [8]      index      0..k for data shards, k for the parity shard
+///   [9]      k          data shards in the group, 1..=MAX_FEC_K
+///   [10..12] len        u16 — data: message length; parity: XOR of them
+///   [12..14] shard_len  u16 — payload bytes that follow, same in the group
+///   [14..16] _reserved
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct FecShardHeader {
+    pub magic: [u8; 4],
+    pub group_le: [u8; 4],
+    pub index: u8,
+    pub k: u8,
+    pub len_le: [u8; 2],
+    pub shard_len_le: [u8; 2],
+    pub _reserved: [u8; 2],
+}
+
+const _: () = assert!(core::mem::size_of::<FecShardHeader>() == 16);
+
+impl FecShardHeader {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<FecShardHeader>();
+
+    pub fn new(group: u32, index: u8, k: u8, len: u16, shard_len: u16) -> Self {
+        Self {
+            magic: FEC_SHARD_MAGIC,
+            group_le: group.to_le_bytes(),
+            index,
+            k,
+            len_le: len.to_le_bytes(),
+            shard_len_le: shard_len.to_le_bytes(),
+            _reserved: [0; 2],
+        }
+    }
+
+    #[inline(always)]
+    pub fn group(&self) -> u32 {
+        u32::from_le_bytes(self.group_le)
+    }
+
+    #[inline(always)]
+    pub fn message_len(&self) -> usize {
+        u16::from_le_bytes(self.len_le) as usize
+    }
+
+    #[inline(always)]
+    pub fn shard_len(&self) -> usize {
+        u16::from_le_bytes(self.shard_len_le) as usize
+    }
+
+    pub fn is_parity(&self) -> bool {
+        self.index == self.k
+    }
+
+    /// Split a datagram into header and shard payload; `None` unless it
+    /// carries the magic and exactly `shard_len` payload bytes.
+    pub fn parse(data: &[u8]) -> Option<(&FecShardHeader, &[u8])> {
+        let header =
+            bytemuck::try_from_bytes::<FecShardHeader>(data.get(..Self::WIRE_SIZE)?).ok()?;
+        let shard = &data[Self::WIRE_SIZE..];
+        (header.magic == FEC_SHARD_MAGIC && shard.len() == header.shard_len())
+            .then_some((header, shard))
+    }
+}
+
+/// Counters since startup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FecStats {
+    /// Data shards rebuilt from parity.
+    pub recovered: u64,
+    /// Data shards still missing when their group's slot was reused.
+    pub unrecovered: u64,
+    /// Shards received twice.
+    pub duplicates: u64,
+    /// Malformed shards, shards disagreeing with their group's `k` or
+    /// length, and shards of groups already evicted.
+    pub rejected: u64,
+}
+
+#[derive(Clone, Copy)]
+struct Group<const SHARD: usize> {
+    id: u32,
+    live: bool,
+    k: u8,
+    shard_len: u16,
+    /// Bit `i`: shard `i` received (or, for data, rebuilt).
+    received: u32,
+    len_xor: u16,
+    /// XOR of every shard received.
+    acc: [u8; SHARD],
+}
+
+impl<const SHARD: usize> Group<SHARD> {
+    const EMPTY: Self = Self {
+        id: 0,
+        live: false,
+        k: 0,
+        shard_len: 0,
+        received: 0,
+        len_xor: 0,
+        acc: [0; SHARD],
+    };
+
+    fn data_mask(&self) -> u32 {
+        (1u32 << self.k) - 1
+    }
+
+    fn missing_data(&self) -> u64 {
+        u64::from((self.data_mask() & !self.received).count_ones())
+    }
+}
+
+/// `GROUPS`: groups decoded at once. `SHARD`: largest shard payload
+/// accepted (at most `u16::MAX`).
+pub struct FecDecoder<const GROUPS: usize, const SHARD: usize> {
+    groups: [Group<SHARD>; GROUPS],
+    pub stats: FecStats,
+}
+
+impl<const GROUPS: usize, const SHARD: usize> FecDecoder<GROUPS, SHARD> {
+    pub fn new() -> Self {
+        assert!(
+            GROUPS > 0 && SHARD <= u16::MAX as usize,
+            "FEC decoder dimensions out of range"
+        );
+        Self {
+            groups: [Group::EMPTY; GROUPS],
+            stats: FecStats::default(),
+        }
+    }
+
+    /// Take one shard (as split by [`FecShardHeader::parse`]) and pass every
+    /// feed message it yields — its own, a rebuilt one, or both — to
+    /// `deliver`.
+    pub fn offer(&mut self, header: &FecShardHeader, shard: &[u8], mut deliver: impl FnMut(&[u8])) {
+        let (k, index) = (header.k, header.index);
+        if k == 0
+            || usize::from(k) > MAX_FEC_K
+            || index > k
+            || shard.len() > SHARD
+            || shard.len() != header.shard_len()
+            || (!header.is_parity() && header.message_len() > shard.len())
+        {
+            self.stats.rejected += 1;
+            return;
+        }
+        let id = header.group();
+        let group = &mut self.groups[id as usize % GROUPS];
+        if !group.live || group.id != id {
+            if group.live && (id.wrapping_sub(group.id) as i32) < 0 {
+                self.stats.rejected += 1;
+                return;
+            }
+            if group.live {
+                self.stats.unrecovered += group.missing_data();
+            }
+            *group = Group {
+                id,
+                live: true,
+                k,
+                shard_len: shard.len() as u16,
+                ..Group::EMPTY
+            };
+        } else if group.k != k || usize::from(group.shard_len) != shard.len() {
+            self.stats.rejected += 1;
+            return;
+        }
+        let bit = 1u32 << index;
+        if group.received & bit != 0 {
+            self.stats.duplicates += 1;
+            return;
+        }
+        group.received |= bit;
+        group.len_xor ^= u16::from_le_bytes(header.len_le);
+        for (acc, byte) in group.acc.iter_mut().zip(shard) {
+            *acc ^= byte;
+        }
+        if !header.is_parity() {
+            deliver(&shard[..header.message_len()]);
+        }
+        // Parity plus all data shards but one: the accumulator is that one.
+        let parity = 1u32 << k;
+        if group.received & parity != 0 && group.missing_data() == 1 {
+            let len = usize::from(group.len_xor);
+            group.received |= group.data_mask();
+            if len <= usize::from(group.shard_len) {
+                self.stats.recovered += 1;
+                deliver(&group.acc[..len]);
+            } else {
+                self.stats.rejected += 1;
+            }
+        }
+    }
+}
+
+impl<const GROUPS: usize, const SHARD: usize> Default for FecDecoder<GROUPS, SHARD> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Producer side: codes one group of `k` messages into `k + 1` datagrams.
+pub struct FecEncoder<const SHARD: usize> {
+    group: u32,
+    k: u8,
+    shard_len: u16,
+    pushed: u8,
+    len_xor: u16,
+    acc: [u8; SHARD],
+}
+
+impl<const SHARD: usize> FecEncoder<SHARD> {
+    /// Group `group` of `k` (1..=MAX_FEC_K) messages, each padded to
+    /// `shard_len` (at most `SHARD`) bytes.
+    pub fn new(group: u32, k: u8, shard_len: u16) -> Self {
+        assert!(
+            k > 0 && usize::from(k) <= MAX_FEC_K && usize::from(shard_len) <= SHARD,
+            "FEC group out of range"
+        );
+        Self {
+            group,
+            k,
+            shard_len,
+            pushed: 0,
+            len_xor: 0,
+            acc: [0; SHARD],
+        }
+    }
+
+    /// Encode the next data shard carrying `message` into `out` and return
+    /// the datagram length; `None` if the group is full, the message longer
+    /// than `shard_len` or `out` too short.
+    pub fn push(&mut self, message: &[u8], out: &mut [u8]) -> Option<usize> {
+        let shard_len = usize::from(self.shard_len);
+        if self.pushed == self.k || message.len() > shard_len {
+            return None;
+        }
+        let datagram = out.get_mut(..FecShardHeader::WIRE_SIZE + shard_len)?;
+        let header = FecShardHeader::new(
+            self.group,
+            self.pushed,
+            self.k,
+            message.len() as u16,
+            self.shard_len,
+        );
+        datagram[..FecShardHeader::WIRE_SIZE].copy_from_slice(bytemuck::bytes_of(&header));
+        let shard = &mut datagram[FecShardHeader::WIRE_SIZE..];
+        shard.fill(0);
+        shard[..message.len()].copy_from_slice(message);
+        for (acc, byte) in self.acc.iter_mut().zip(message) {
+            *acc ^= byte;
+        }
+        self.len_xor ^= message.len() as u16;
+        self.pushed += 1;
+        Some(datagram.len())
+    }
+
+    /// Encode the parity shard into `out`; `None` until all `k` messages
+    /// were pushed, or if `out` is too short.
+    pub fn parity(&self, out: &mut [u8]) -> Option<usize> {
+        if self.pushed < self.k {
+            return None;
+        }
+        let shard_len = usize::from(self.shard_len);
+        let datagram = out.get_mut(..FecShardHeader::WIRE_SIZE + shard_len)?;
+        let header = FecShardHeader::new(self.group, self.k, self.k, self.len_xor, self.shard_len);
+        datagram[..FecShardHeader::WIRE_SIZE].copy_from_slice(bytemuck::bytes_of(&header));
+        datagram[FecShardHeader::WIRE_SIZE..].copy_from_slice(&self.acc[..shard_len]);
+        Some(datagram.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Decoder = FecDecoder<4, 64>;
+
+    /// Datagrams of group `group`: three messages of different lengths,
+    /// then parity.
+    fn coded(group: u32) -> Vec<Vec<u8>> {
+        let messages: [&[u8]; 3] = [&[group as u8; 40], &[0xbb; 64], &[0xcc; 1]];
+        let mut encoder = FecEncoder::<64>::new(group, 3, 64);
+        let mut out = [0u8; 128];
+        let mut datagrams = Vec::new();
+        for message in messages {
+            let len = encoder.push(message, &mut out).unwrap();
+            datagrams.push(out[..len].to_vec());
+        }
+        let len = encoder.parity(&mut out).unwrap();
+        datagrams.push(out[..len].to_vec());
+        datagrams
+    }
+
+    fn offer(decoder: &mut Decoder, datagram: &[u8]) -> Vec<Vec<u8>> {
+        let (header, shard) = FecShardHeader::parse(datagram).unwrap();
+        let mut out = Vec::new();
+        decoder.offer(header, shard, |message| out.push(message.to_vec()));
+        out
+    }
+
+    #[test]
+    fn one_lost_data_shard_is_rebuilt_from_parity() {
+        let mut decoder = Decoder::new();
+        let group = coded(7);
+        assert_eq!(offer(&mut decoder, &group[0]), [vec![7; 40]]);
+        // Shard 1 lost; parity arrives before shard 2.
+        assert!(offer(&mut decoder, &group[3]).is_empty());
+        assert_eq!(
+            offer(&mut decoder, &group[2]),
+            [vec![0xcc; 1], vec![0xbb; 64]]
+        );
+        // The lost shard turning up late is a duplicate of the rebuilt one.
+        assert!(offer(&mut decoder, &group[1]).is_empty());
+        assert_eq!(
+            decoder.stats,
+            FecStats {
+                recovered: 1,
+                unrecovered: 0,
+                duplicates: 1,
+                rejected: 0
+            }
+        );
+    }
+
+    #[test]
+    fn groups_losing_two_shards_count_as_unrecovered_when_evicted() {
+        let mut decoder = Decoder::new();
+        let group = coded(1);
+        assert_eq!(offer(&mut decoder, &group[0]).len(), 1);
+        assert!(offer(&mut decoder, &group[3]).is_empty());
+        // Group 5 takes group 1's slot; 1's two missing shards are lost.
+        assert_eq!(offer(&mut decoder, &coded(5)[0]).len(), 1);
+        // Group 1 is gone.
+        assert!(offer(&mut decoder, &group[1]).is_empty());
+        assert_eq!(
+            decoder.stats,
+            FecStats {
+                recovered: 0,
+                unrecovered: 2,
+                duplicates: 0,
+                rejected: 1
+            }
+        );
+
+        let mut bad = coded(2)[0].clone();
+        bad[8] = 9;
+        assert!(offer(&mut decoder, &bad).is_empty());
+        assert_eq!(decoder.stats.rejected, 2);
+        assert!(FecShardHeader::parse(&bad[..40]).is_none());
+    }
+}
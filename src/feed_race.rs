@@ -0,0 +1,191 @@
+//! Which pool feed delivers an update first, and by how much.
+//!
+//! With several feeds carrying the same pool updates — the UDP feed from
+//! more than one relayer, the relayer TCP session, feeds on different NICs —
+//! the node applies whichever copy of an update arrives first and rejects
+//! the rest as stale. [`FeedRace`] tells the copies apart by `(pool, seq)`,
+//! credits the first to its feed and, for each later copy, records how far
+//! behind its feed was in a per-feed log2 histogram of cycles. Operators can
+//! then tell which relayer or NIC actually provides the edge. Each feed's
+//! clock is followed alongside, from the send times of version 3 updates
+//! ([`crate::skew`]).
+//!
+//! First arrivals are remembered in a direct-mapped table: an update whose
+//! slot has since been reused is no longer matched, so copies arriving more
+//! than a few thousand updates apart are not compared.
+use crate::pool_registry::{address_hash, PoolAddress};
+use crate::skew::SkewFilter;
+
+/// Feeds tracked; updates from further sources are not counted.
+pub const MAX_FEEDS: usize = 8;
+/// Recent first arrivals remembered for matching later copies.
+pub const RACE_SLOTS: usize = 4096;
+/// Lag histogram buckets: bucket `i` counts lags in `[2^i, 2^(i+1))` cycles
+/// (bucket 0 also takes 0), the last one everything above.
+pub const LAG_BUCKETS: usize = 32;
+
+/// IPv4 address and port of a feed.
+pub type FeedSource = ([u8; 4], u16);
+
+/// Race results of one feed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FeedStats {
+    pub source: FeedSource,
+    /// Updates this feed delivered before any other.
+    pub first: u64,
+    /// Updates another feed had already delivered.
+    pub late: u64,
+    /// How far behind the first copy the late ones were.
+    pub lag: [u64; LAG_BUCKETS],
+    /// The feed's clock against ours.
+    pub clock: SkewFilter,
+}
+
+impl FeedStats {
+    const fn new(source: FeedSource) -> Self {
+        Self {
+            source,
+            first: 0,
+            late: 0,
+            lag: [0; LAG_BUCKETS],
+            clock: SkewFilter::new(),
+        }
+    }
+
+    /// Upper bound of the bucket holding the `pct` percentile of lags, in
+    /// cycles; 0 without late copies.
+    pub fn lag_percentile(&self, pct: u64) -> u64 {
+        let rank = (self.late * pct).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (i, &count) in self.lag.iter().enumerate() {
+            seen += count;
+            if count != 0 && seen >= rank {
+                return if i + 1 == LAG_BUCKETS {
+                    u64::MAX
+                } else {
+                    (2u64 << i) - 1
+                };
+            }
+        }
+        0
+    }
+}
+
+#[derive(Clone, Copy)]
+struct FirstArrival {
+    pool: PoolAddress,
+    seq: u32,
+    feed: u8,
+    rx_cycles: u64,
+}
+
+pub struct FeedRace<const F: usize = MAX_FEEDS, const S: usize = RACE_SLOTS> {
+    feeds: heapless::Vec<FeedStats, F>,
+    arrivals: [Option<FirstArrival>; S],
+}
+
+impl<const F: usize, const S: usize> FeedRace<F, S> {
+    const VALID: () = assert!(S.is_power_of_two(), "race slots must be a power of two");
+
+    pub const fn new() -> Self {
+        let () = Self::VALID;
+        Self {
+            feeds: heapless::Vec::new(),
+            arrivals: [None; S],
+        }
+    }
+
+    /// Feeds seen so far, in order of their first update.
+    pub fn feeds(&self) -> &[FeedStats] {
+        &self.feeds
+    }
+
+    /// Record that `source` delivered update `seq` of `pool` at `rx_cycles`.
+    #[inline(always)]
+    pub fn observe(&mut self, source: FeedSource, pool: &PoolAddress, seq: u32, rx_cycles: u64) {
+        let Some(feed) = self.feed(source) else {
+            return;
+        };
+        let hash = address_hash(pool) ^ (seq as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        let slot = &mut self.arrivals[(hash >> 32) as usize & (S - 1)];
+        match slot {
+            Some(first) if first.seq == seq && first.pool == *pool => {
+                // A feed repeating its own update is not a race.
+                if first.feed as usize != feed {
+                    let lag = rx_cycles.saturating_sub(first.rx_cycles);
+                    let bucket = (u64::BITS - 1).saturating_sub(lag.leading_zeros()) as usize;
+                    let stats = &mut self.feeds[feed];
+                    stats.late += 1;
+                    stats.lag[bucket.min(LAG_BUCKETS - 1)] += 1;
+                }
+            }
+            _ => {
+                *slot = Some(FirstArrival {
+                    pool: *pool,
+                    seq,
+                    feed: feed as u8,
+                    rx_cycles,
+                });
+                self.feeds[feed].first += 1;
+            }
+        }
+    }
+
+    /// `sent_ns`, the send time `source` stamped on an update it delivered
+    /// at `rx_ns`, on our clock; the feed's clock estimate learns from it.
+    /// Unchanged for feeds beyond capacity.
+    pub fn correct_sent(&mut self, source: FeedSource, sent_ns: u64, rx_ns: u64) -> u64 {
+        let Some(feed) = self.feed(source) else {
+            return sent_ns;
+        };
+        let clock = &mut self.feeds[feed].clock;
+        clock.observe(sent_ns, rx_ns);
+        clock.correct(sent_ns, rx_ns)
+    }
+
+    fn feed(&mut self, source: FeedSource) -> Option<usize> {
+        if let Some(i) = self.feeds.iter().position(|f| f.source == source) {
+            return Some(i);
+        }
+        self.feeds.push(FeedStats::new(source)).ok()?;
+        Some(self.feeds.len() - 1)
+    }
+}
+
+impl<const F: usize, const S: usize> Default for FeedRace<F, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RELAY_A: FeedSource = ([10, 0, 0, 9], 7000);
+    const RELAY_B: FeedSource = ([10, 0, 1, 9], 7000);
+
+    #[test]
+    fn credits_the_first_feed_and_bins_the_lag_of_the_others() {
+        let mut race: FeedRace<2, 64> = FeedRace::new();
+        let pool = [0xaa; 20];
+        race.observe(RELAY_A, &pool, 1, 1_000);
+        race.observe(RELAY_B, &pool, 1, 1_300);
+        race.observe(RELAY_B, &pool, 2, 2_000);
+        race.observe(RELAY_A, &pool, 2, 2_005);
+        race.observe(RELAY_A, &pool, 3, 3_000);
+        race.observe(RELAY_B, &pool, 3, 3_600);
+        // Repeats from the same feed and feeds beyond capacity are ignored.
+        race.observe(RELAY_A, &pool, 3, 3_700);
+        race.observe(([10, 0, 2, 9], 7000), &pool, 3, 3_800);
+
+        let [a, b] = race.feeds() else {
+            panic!("expected two feeds")
+        };
+        assert_eq!((a.first, a.late, b.first, b.late), (2, 1, 1, 2));
+        assert_eq!(a.lag[2], 1);
+        assert_eq!((b.lag[8], b.lag[9]), (1, 1));
+        assert_eq!((b.lag_percentile(50), b.lag_percentile(99)), (511, 1_023));
+        assert_eq!(FeedStats::new(RELAY_A).lag_percentile(99), 0);
+    }
+}
@@ -0,0 +1,590 @@
+//! C ABI over the wire format, for relayers and executors not written in
+//! Rust (`ffi` feature).
+//!
+//! Encoding goes through [`crate::wire::encoder`] behind an opaque
+//! `MevSwapEncoder` handle; decoding copies a message's fields into a plain
+//! `#[repr(C)]` struct. Every function returns a byte count (encoders) or
+//! the message's wire size (decoders) on success and a negative `MEV_ERR_*`
+//! code otherwise. Nothing here keeps a pointer past the call.
+//!
+//! `include/mev_wire.h` is generated from this file by cbindgen
+//! (`cbindgen --config cbindgen.toml --output include/mev_wire.h`); the
+//! library builds with
+//! `cargo rustc --release --lib --features ffi --crate-type cdylib`.
+use crate::payload::{DexSwapTx, DexSwapTxEvm, DexSwapTxExt, OpportunityCancel, OpportunityReply};
+use crate::validator::{validate_pool_update, ValidationError};
+use crate::wire::encoder::{
+    Direction, EncodeError, PoolUpdateEncoder, SwapEncoder, TxBatchEncoder,
+};
+
+/// A required pointer argument was null.
+pub const MEV_ERR_NULL: i32 = -1;
+/// The output buffer is too small for the message.
+pub const MEV_ERR_BUFFER_TOO_SMALL: i32 = -2;
+/// The pool address is all zero.
+pub const MEV_ERR_ZERO_POOL_ADDRESS: i32 = -3;
+/// A swap with no input amount.
+pub const MEV_ERR_ZERO_AMOUNT: i32 = -4;
+/// Both pool reserves are zero.
+pub const MEV_ERR_ZERO_RESERVES: i32 = -5;
+/// Batch records cannot carry deadline, priority fee or EVM fields.
+pub const MEV_ERR_EXTENDED_IN_BATCH: i32 = -6;
+/// The bytes are not a message of the expected type.
+pub const MEV_ERR_MALFORMED: i32 = -7;
+/// The pool update does not follow `last_seq`.
+pub const MEV_ERR_SEQUENCE_GAP: i32 = -8;
+/// A pool update with a send time but no fee.
+pub const MEV_ERR_SENT_WITHOUT_FEE: i32 = -9;
+
+impl EncodeError {
+    const fn code(self) -> i32 {
+        match self {
+            Self::BufferTooSmall { .. } => MEV_ERR_BUFFER_TOO_SMALL,
+            Self::ZeroPoolAddress => MEV_ERR_ZERO_POOL_ADDRESS,
+            Self::ZeroAmount => MEV_ERR_ZERO_AMOUNT,
+            Self::ZeroReserves => MEV_ERR_ZERO_RESERVES,
+            Self::ExtendedInBatch => MEV_ERR_EXTENDED_IN_BATCH,
+            Self::SentWithoutFee => MEV_ERR_SENT_WITHOUT_FEE,
+        }
+    }
+}
+
+/// A victim swap being built; see [`SwapEncoder`].
+pub struct MevSwapEncoder(SwapEncoder);
+
+/// A decoded `DexSwapTx`, `DexSwapTxExt` or `DexSwapTxEvm`. Fields the
+/// message does not carry are zero.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MevSwap {
+    pub nonce: u64,
+    pub pool: [u8; 20],
+    pub amount_in: u64,
+    pub min_amount_out: u64,
+    /// 0: token0 in, token1 out; 1: the reverse.
+    pub direction: u8,
+    pub deadline_ms: u64,
+    pub sender: [u8; 20],
+    pub gas_price: u64,
+    pub priority_fee: u64,
+}
+
+/// A decoded `PoolStateUpdate`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MevPoolUpdate {
+    pub pool: [u8; 20],
+    pub reserve0: u64,
+    pub reserve1: u64,
+    pub slot: u64,
+    pub seq: u32,
+}
+
+/// A decoded `OpportunityReply`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MevOpportunityReply {
+    pub nonce: u64,
+    pub profit: u64,
+    pub rx_ts_ns: u64,
+    pub tx_ts_ns: u64,
+    /// Confidence in `profit`, in basis points.
+    pub confidence_bps: u16,
+}
+
+/// A decoded `OpportunityCancel`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MevOpportunityCancel {
+    pub sender: [u8; 20],
+    pub nonce: u64,
+    /// 1 replaced, 2 pool moved, 3 expired.
+    pub reason: u8,
+    pub tx_ts_ns: u64,
+}
+
+fn result(encoded: Result<usize, EncodeError>) -> i32 {
+    match encoded {
+        Ok(len) => len as i32,
+        Err(e) => e.code(),
+    }
+}
+
+/// `len` bytes at `data`, or `None` for a null pointer.
+unsafe fn input<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    // SAFETY: the caller guarantees `data..data+len` is readable.
+    (!data.is_null()).then(|| unsafe { core::slice::from_raw_parts(data, len) })
+}
+
+/// `len` writable bytes at `out`, or `None` for a null pointer.
+unsafe fn output<'a>(out: *mut u8, len: usize) -> Option<&'a mut [u8]> {
+    // SAFETY: the caller guarantees `out..out+len` is writable and unaliased.
+    (!out.is_null()).then(|| unsafe { core::slice::from_raw_parts_mut(out, len) })
+}
+
+/// The 20-byte address at `ptr`, or `None` for a null pointer.
+unsafe fn address(ptr: *const u8) -> Option<[u8; 20]> {
+    // SAFETY: the caller guarantees 20 readable bytes.
+    unsafe { input(ptr, 20) }.map(|bytes| bytes.try_into().unwrap())
+}
+
+/// A new swap encoder, or null if `pool` is null or `direction` is not 0
+/// or 1. Release it with [`mev_swap_encoder_free`].
+///
+/// # Safety
+/// `pool` must point to 20 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mev_swap_encoder_new(
+    nonce: u64,
+    pool: *const u8,
+    direction: u8,
+) -> *mut MevSwapEncoder {
+    let direction = match direction {
+        0 => Direction::ZeroForOne,
+        1 => Direction::OneForZero,
+        _ => return core::ptr::null_mut(),
+    };
+    // SAFETY: the caller guarantees 20 readable bytes.
+    let Some(pool) = (unsafe { address(pool) }) else {
+        return core::ptr::null_mut();
+    };
+    Box::into_raw(Box::new(MevSwapEncoder(SwapEncoder::new(
+        nonce, pool, direction,
+    ))))
+}
+
+/// # Safety
+/// `encoder` must come from [`mev_swap_encoder_new`] and not be used
+/// afterwards; null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn mev_swap_encoder_free(encoder: *mut MevSwapEncoder) {
+    if !encoder.is_null() {
+        // SAFETY: allocated by `mev_swap_encoder_new`, freed once.
+        drop(unsafe { Box::from_raw(encoder) });
+    }
+}
+
+/// Apply `f` to the encoder behind `encoder`.
+unsafe fn update(encoder: *mut MevSwapEncoder, f: impl FnOnce(SwapEncoder) -> SwapEncoder) -> i32 {
+    // SAFETY: the caller guarantees a live handle.
+    match unsafe { encoder.as_mut() } {
+        Some(MevSwapEncoder(swap)) => {
+            *swap = f(*swap);
+            0
+        }
+        None => MEV_ERR_NULL,
+    }
+}
+
+/// # Safety
+/// `encoder` must be a live handle from [`mev_swap_encoder_new`].
+#[no_mangle]
+pub unsafe extern "C" fn mev_swap_encoder_set_amount_in(
+    encoder: *mut MevSwapEncoder,
+    amount_in: u64,
+) -> i32 {
+    unsafe { update(encoder, |swap| swap.amount_in(amount_in)) }
+}
+
+/// # Safety
+/// `encoder` must be a live handle from [`mev_swap_encoder_new`].
+#[no_mangle]
+pub unsafe extern "C" fn mev_swap_encoder_set_min_amount_out(
+    encoder: *mut MevSwapEncoder,
+    min_amount_out: u64,
+) -> i32 {
+    unsafe { update(encoder, |swap| swap.min_amount_out(min_amount_out)) }
+}
+
+/// Makes the swap a 64-byte `DexSwapTxExt`.
+///
+/// # Safety
+/// `encoder` must be a live handle from [`mev_swap_encoder_new`].
+#[no_mangle]
+pub unsafe extern "C" fn mev_swap_encoder_set_deadline_ms(
+    encoder: *mut MevSwapEncoder,
+    deadline_ms: u64,
+) -> i32 {
+    unsafe { update(encoder, |swap| swap.deadline_ms(deadline_ms)) }
+}
+
+/// Makes the swap a 64-byte `DexSwapTxExt`.
+///
+/// # Safety
+/// `encoder` must be a live handle from [`mev_swap_encoder_new`].
+#[no_mangle]
+pub unsafe extern "C" fn mev_swap_encoder_set_priority_fee(
+    encoder: *mut MevSwapEncoder,
+    priority_fee: u64,
+) -> i32 {
+    unsafe { update(encoder, |swap| swap.priority_fee(priority_fee)) }
+}
+
+/// Makes the swap a 96-byte `DexSwapTxEvm`.
+///
+/// # Safety
+/// `encoder` must be a live handle from [`mev_swap_encoder_new`] and
+/// `sender` must point to 20 readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn mev_swap_encoder_set_evm(
+    encoder: *mut MevSwapEncoder,
+    sender: *const u8,
+    gas_price: u64,
+) -> i32 {
+    // SAFETY: the caller guarantees 20 readable bytes.
+    let Some(sender) = (unsafe { address(sender) }) else {
+        return MEV_ERR_NULL;
+    };
+    unsafe { update(encoder, |swap| swap.evm(sender, gas_price)) }
+}
+
+/// Bytes [`mev_swap_encoder_encode`] writes: 48, 64 or 96.
+///
+/// # Safety
+/// `encoder` must be a live handle from [`mev_swap_encoder_new`].
+#[no_mangle]
+pub unsafe extern "C" fn mev_swap_encoder_wire_size(encoder: *const MevSwapEncoder) -> i32 {
+    // SAFETY: the caller guarantees a live handle.
+    unsafe { encoder.as_ref() }.map_or(MEV_ERR_NULL, |MevSwapEncoder(swap)| swap.wire_size() as i32)
+}
+
+/// Write the swap to `out`; returns its length.
+///
+/// # Safety
+/// `encoder` must be a live handle and `out..out+out_len` writable.
+#[no_mangle]
+pub unsafe extern "C" fn mev_swap_encoder_encode(
+    encoder: *const MevSwapEncoder,
+    out: *mut u8,
+    out_len: usize,
+) -> i32 {
+    // SAFETY: the caller guarantees both pointers.
+    match unsafe { (encoder.as_ref(), output(out, out_len)) } {
+        (Some(MevSwapEncoder(swap)), Some(out)) => result(swap.encode(out)),
+        _ => MEV_ERR_NULL,
+    }
+}
+
+/// Append the swap as a batch record to the `*used` bytes already in
+/// `out`, advancing `*used`; returns the records now in the batch.
+///
+/// # Safety
+/// `encoder` must be a live handle, `out..out+out_len` writable and `used`
+/// a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn mev_tx_batch_push(
+    encoder: *const MevSwapEncoder,
+    out: *mut u8,
+    out_len: usize,
+    used: *mut usize,
+) -> i32 {
+    // SAFETY: the caller guarantees all three pointers.
+    let (Some(MevSwapEncoder(swap)), Some(out), Some(used)) =
+        (unsafe { (encoder.as_ref(), output(out, out_len), used.as_mut()) })
+    else {
+        return MEV_ERR_NULL;
+    };
+    let Some(rest) = out.get_mut(*used..) else {
+        return MEV_ERR_BUFFER_TOO_SMALL;
+    };
+    let mut batch = TxBatchEncoder::new(rest);
+    if let Err(e) = batch.push(swap) {
+        return e.code();
+    }
+    *used += batch.bytes().len();
+    (*used / DexSwapTx::WIRE_SIZE) as i32
+}
+
+/// Write a `PoolStateUpdate` to `out`; returns its length.
+///
+/// # Safety
+/// `pool` must point to 20 readable bytes and `out..out+out_len` be
+/// writable.
+#[no_mangle]
+pub unsafe extern "C" fn mev_pool_update_encode(
+    pool: *const u8,
+    seq: u32,
+    reserve0: u64,
+    reserve1: u64,
+    slot: u64,
+    out: *mut u8,
+    out_len: usize,
+) -> i32 {
+    // SAFETY: the caller guarantees both pointers.
+    let (Some(pool), Some(out)) = (unsafe { (address(pool), output(out, out_len)) }) else {
+        return MEV_ERR_NULL;
+    };
+    result(
+        PoolUpdateEncoder::new(pool, seq)
+            .reserves(reserve0, reserve1)
+            .slot(slot)
+            .encode(out),
+    )
+}
+
+/// Decode a swap, telling the variants apart by length: exactly 48, 64 or
+/// 96 bytes. Returns the length.
+///
+/// # Safety
+/// `data..data+len` must be readable and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn mev_decode_swap(data: *const u8, len: usize, out: *mut MevSwap) -> i32 {
+    // SAFETY: the caller guarantees both pointers.
+    let (Some(data), Some(out)) = (unsafe { (input(data, len), out.as_mut()) }) else {
+        return MEV_ERR_NULL;
+    };
+    let (base, ext, sender, gas_price) = match len {
+        DexSwapTx::WIRE_SIZE => match DexSwapTx::header(data) {
+            Some(tx) => (tx, None, [0; 20], 0),
+            None => return MEV_ERR_MALFORMED,
+        },
+        DexSwapTxExt::WIRE_SIZE => match DexSwapTxExt::parse(data) {
+            Some(ext) => (&ext.base, Some(ext), [0; 20], 0),
+            None => return MEV_ERR_MALFORMED,
+        },
+        DexSwapTxEvm::WIRE_SIZE => match DexSwapTxEvm::parse(data) {
+            Some(evm) => (&evm.ext.base, Some(&evm.ext), evm.sender, evm.gas_price()),
+            None => return MEV_ERR_MALFORMED,
+        },
+        _ => return MEV_ERR_MALFORMED,
+    };
+    *out = MevSwap {
+        nonce: base.nonce(),
+        pool: base.pool_address,
+        amount_in: base.amount_in(),
+        min_amount_out: base.min_amount_out(),
+        direction: base.token_direction,
+        deadline_ms: ext.map_or(0, |e| e.deadline_ms()),
+        sender,
+        gas_price,
+        priority_fee: ext.map_or(0, |e| e.priority_fee()),
+    };
+    len as i32
+}
+
+/// Decode and validate a `PoolStateUpdate` as the node does; `last_seq` 0
+/// skips the sequence check. Returns the length.
+///
+/// # Safety
+/// `data..data+len` must be readable and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn mev_decode_pool_update(
+    data: *const u8,
+    len: usize,
+    last_seq: u32,
+    out: *mut MevPoolUpdate,
+) -> i32 {
+    // SAFETY: the caller guarantees both pointers.
+    let (Some(data), Some(out)) = (unsafe { (input(data, len), out.as_mut()) }) else {
+        return MEV_ERR_NULL;
+    };
+    match validate_pool_update(data, last_seq) {
+        Ok(update) => {
+            *out = MevPoolUpdate {
+                pool: update.pool_address,
+                reserve0: update.reserve0(),
+                reserve1: update.reserve1(),
+                slot: update.slot(),
+                seq: update.seq(),
+            };
+            crate::validator::PoolStateUpdate::WIRE_SIZE as i32
+        }
+        Err(ValidationError::ZeroReserves) => MEV_ERR_ZERO_RESERVES,
+        Err(ValidationError::SequenceGap { .. }) => MEV_ERR_SEQUENCE_GAP,
+        Err(
+            ValidationError::TooShort
+            | ValidationError::LayoutMismatch
+            | ValidationError::UnsupportedVersion(_)
+            | ValidationError::FeeOutOfRange(_),
+        ) => MEV_ERR_MALFORMED,
+    }
+}
+
+/// Decode an `OpportunityReply` (exactly 40 bytes). Returns the length.
+///
+/// # Safety
+/// `data..data+len` must be readable and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn mev_decode_opportunity_reply(
+    data: *const u8,
+    len: usize,
+    out: *mut MevOpportunityReply,
+) -> i32 {
+    // SAFETY: the caller guarantees both pointers.
+    let (Some(data), Some(out)) = (unsafe { (input(data, len), out.as_mut()) }) else {
+        return MEV_ERR_NULL;
+    };
+    let Ok(reply) = bytemuck::try_from_bytes::<OpportunityReply>(data) else {
+        return MEV_ERR_MALFORMED;
+    };
+    *out = MevOpportunityReply {
+        nonce: reply.nonce(),
+        profit: reply.profit(),
+        rx_ts_ns: reply.rx_ts_ns(),
+        tx_ts_ns: reply.tx_ts_ns(),
+        confidence_bps: reply.confidence_bps(),
+    };
+    len as i32
+}
+
+/// Decode an `OpportunityCancel` (exactly 48 bytes, "MVCX" magic).
+/// Returns the length.
+///
+/// # Safety
+/// `data..data+len` must be readable and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn mev_decode_opportunity_cancel(
+    data: *const u8,
+    len: usize,
+    out: *mut MevOpportunityCancel,
+) -> i32 {
+    // SAFETY: the caller guarantees both pointers.
+    let (Some(data), Some(out)) = (unsafe { (input(data, len), out.as_mut()) }) else {
+        return MEV_ERR_NULL;
+    };
+    let Some(cancel) = OpportunityCancel::parse(data) else {
+        return MEV_ERR_MALFORMED;
+    };
+    *out = MevOpportunityCancel {
+        sender: cancel.sender,
+        nonce: cancel.nonce(),
+        reason: cancel.reason,
+        tx_ts_ns: cancel.tx_ts_ns(),
+    };
+    len as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POOL: [u8; 20] = [0xaa; 20];
+
+    #[test]
+    fn swap_handle_encodes_and_decodes_through_the_c_abi() {
+        unsafe {
+            let enc = mev_swap_encoder_new(9, POOL.as_ptr(), 1);
+            assert_eq!(mev_swap_encoder_set_amount_in(enc, 5_000), 0);
+            assert_eq!(mev_swap_encoder_set_deadline_ms(enc, 77), 0);
+            assert_eq!(mev_swap_encoder_set_priority_fee(enc, 2), 0);
+            assert_eq!(mev_swap_encoder_set_evm(enc, [0xcc; 20].as_ptr(), 30), 0);
+            let mut buf = [0u8; 96];
+            assert_eq!(
+                mev_swap_encoder_encode(enc, buf.as_mut_ptr(), 64),
+                MEV_ERR_BUFFER_TOO_SMALL
+            );
+            assert_eq!(
+                mev_swap_encoder_encode(enc, buf.as_mut_ptr(), buf.len()),
+                96
+            );
+            let mut swap = MevSwap::default();
+            assert_eq!(mev_decode_swap(buf.as_ptr(), 96, &mut swap), 96);
+            assert_eq!((swap.nonce, swap.amount_in, swap.direction), (9, 5_000, 1));
+            assert_eq!(
+                (
+                    swap.deadline_ms,
+                    swap.sender,
+                    swap.gas_price,
+                    swap.priority_fee
+                ),
+                (77, [0xcc; 20], 30, 2)
+            );
+            assert_eq!(
+                mev_decode_swap(buf.as_ptr(), 95, &mut swap),
+                MEV_ERR_MALFORMED
+            );
+            // Extended swaps do not go into batches.
+            let mut used = 0;
+            assert_eq!(
+                mev_tx_batch_push(enc, buf.as_mut_ptr(), buf.len(), &mut used),
+                MEV_ERR_EXTENDED_IN_BATCH
+            );
+            mev_swap_encoder_free(enc);
+
+            assert!(mev_swap_encoder_new(1, POOL.as_ptr(), 2).is_null());
+            assert_eq!(
+                mev_swap_encoder_set_amount_in(core::ptr::null_mut(), 1),
+                MEV_ERR_NULL
+            );
+            let base = mev_swap_encoder_new(1, POOL.as_ptr(), 0);
+            assert_eq!(
+                mev_tx_batch_push(base, buf.as_mut_ptr(), buf.len(), &mut used),
+                MEV_ERR_ZERO_AMOUNT
+            );
+            mev_swap_encoder_set_amount_in(base, 10);
+            assert_eq!(
+                mev_tx_batch_push(base, buf.as_mut_ptr(), buf.len(), &mut used),
+                1
+            );
+            assert_eq!(
+                mev_tx_batch_push(base, buf.as_mut_ptr(), buf.len(), &mut used),
+                2
+            );
+            assert_eq!(
+                mev_tx_batch_push(base, buf.as_mut_ptr(), buf.len(), &mut used),
+                MEV_ERR_BUFFER_TOO_SMALL
+            );
+            assert_eq!(used, 96);
+            mev_swap_encoder_free(base);
+        }
+    }
+
+    #[test]
+    fn pool_update_round_trips_with_node_validation() {
+        unsafe {
+            let mut buf = [0u8; 64];
+            assert_eq!(
+                mev_pool_update_encode(
+                    POOL.as_ptr(),
+                    6,
+                    1_000,
+                    2_000,
+                    3,
+                    buf.as_mut_ptr(),
+                    buf.len()
+                ),
+                64
+            );
+            let mut update = MevPoolUpdate::default();
+            assert_eq!(
+                mev_decode_pool_update(buf.as_ptr(), buf.len(), 5, &mut update),
+                64
+            );
+            assert_eq!(
+                update,
+                MevPoolUpdate {
+                    pool: POOL,
+                    reserve0: 1_000,
+                    reserve1: 2_000,
+                    slot: 3,
+                    seq: 6
+                }
+            );
+            assert_eq!(
+                mev_decode_pool_update(buf.as_ptr(), buf.len(), 9, &mut update),
+                MEV_ERR_SEQUENCE_GAP
+            );
+            assert_eq!(
+                mev_pool_update_encode(POOL.as_ptr(), 7, 0, 0, 3, buf.as_mut_ptr(), buf.len()),
+                MEV_ERR_ZERO_RESERVES
+            );
+            assert_eq!(
+                mev_decode_pool_update(core::ptr::null(), 64, 0, &mut update),
+                MEV_ERR_NULL
+            );
+        }
+    }
+
+    #[test]
+    fn header_declares_every_exported_function() {
+        let header = include_str!("../include/mev_wire.h");
+        let source = include_str!("ffi.rs");
+        let exported = source.split("pub unsafe extern \"C\" fn ").skip(1);
+        for name in exported.map(|rest| &rest[..rest.find('(').unwrap()]) {
+            assert!(
+                header.contains(&format!("{name}(")),
+                "{name} missing from include/mev_wire.h; rerun cbindgen"
+            );
+        }
+    }
+}
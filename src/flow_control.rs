@@ -0,0 +1,186 @@
+//! Receive-window monitoring and XOFF/XON for TCP feeds.
+//!
+//! A TCP peer whose data we do not read fast enough sees our receive window
+//! close and stalls without either side noticing: the relayer's replay
+//! backs up, the executor's victims arrive late. [`WindowMonitor`] watches
+//! one connection's receive buffer each poll and counts how long it stays
+//! full.
+//!
+//! For the relayer feed it also drives explicit flow control: once the
+//! buffer is `xoff_pct` full it asks for an XOFF, telling the relayer to
+//! hold records on its side (where they are replayed in order) instead of
+//! pushing them into a closed window; once it drains to `xon_pct` it asks
+//! for an XON. The messages themselves are `session::FlowControl`.
+
+/// Occupancy thresholds, in percent of the receive buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FlowPolicy {
+    /// Occupancy at which to send XOFF; 0 disables flow control messages.
+    pub xoff_pct: u8,
+    /// Occupancy at or below which a paused peer is sent XON.
+    pub xon_pct: u8,
+}
+
+impl FlowPolicy {
+    pub const DISABLED: Self = Self {
+        xoff_pct: 0,
+        xon_pct: 0,
+    };
+
+    pub fn is_enabled(&self) -> bool {
+        self.xoff_pct > 0
+    }
+}
+
+impl Default for FlowPolicy {
+    fn default() -> Self {
+        Self::DISABLED
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FlowAction {
+    /// Ask the peer to stop sending.
+    Xoff,
+    /// Ask a paused peer to resume.
+    Xon,
+}
+
+/// Counters since startup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FlowStats {
+    /// Milliseconds the receive buffer spent full.
+    pub stalled_ms: u64,
+    /// Times the buffer went from not full to full.
+    pub stalls: u64,
+    pub xoffs: u64,
+    pub xons: u64,
+}
+
+/// One connection's receive-window state.
+#[derive(Clone, Copy, Debug)]
+pub struct WindowMonitor {
+    policy: FlowPolicy,
+    /// XOFF sent and not yet lifted.
+    paused: bool,
+    /// When the buffer was last seen full, while it stays full.
+    full_since: Option<u64>,
+    pub stats: FlowStats,
+}
+
+impl WindowMonitor {
+    pub const fn new(policy: FlowPolicy) -> Self {
+        Self {
+            policy,
+            paused: false,
+            full_since: None,
+            stats: FlowStats {
+                stalled_ms: 0,
+                stalls: 0,
+                xoffs: 0,
+                xons: 0,
+            },
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Record the buffer holding `queued` of `capacity` bytes at `now_ms`
+    /// and return the flow control message due, if any. Call [`sent`] once
+    /// it went out; until then it is asked for again.
+    ///
+    /// [`sent`]: Self::sent
+    pub fn observe(&mut self, queued: usize, capacity: usize, now_ms: u64) -> Option<FlowAction> {
+        let full = capacity > 0 && queued >= capacity;
+        match (full, self.full_since) {
+            (true, Some(since)) => {
+                self.stats.stalled_ms += now_ms.saturating_sub(since);
+                self.full_since = Some(now_ms.max(since));
+            }
+            (true, None) => {
+                self.stats.stalls += 1;
+                self.full_since = Some(now_ms);
+            }
+            (false, _) => self.full_since = None,
+        }
+        if !self.policy.is_enabled() || capacity == 0 {
+            return None;
+        }
+        let pct = queued.saturating_mul(100) / capacity;
+        if !self.paused && pct >= usize::from(self.policy.xoff_pct) {
+            Some(FlowAction::Xoff)
+        } else if self.paused && pct <= usize::from(self.policy.xon_pct) {
+            Some(FlowAction::Xon)
+        } else {
+            None
+        }
+    }
+
+    /// `action` reached the peer.
+    pub fn sent(&mut self, action: FlowAction) {
+        match action {
+            FlowAction::Xoff => {
+                self.paused = true;
+                self.stats.xoffs += 1;
+            }
+            FlowAction::Xon => {
+                self.paused = false;
+                self.stats.xons += 1;
+            }
+        }
+    }
+
+    /// The connection dropped: a new one starts unpaused.
+    pub fn disconnected(&mut self) {
+        self.paused = false;
+        self.full_since = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pauses_above_xoff_and_resumes_at_xon() {
+        let mut monitor = WindowMonitor::new(FlowPolicy {
+            xoff_pct: 75,
+            xon_pct: 25,
+        });
+        assert_eq!(monitor.observe(500, 1000, 0), None);
+        assert_eq!(monitor.observe(800, 1000, 1), Some(FlowAction::Xoff));
+        // Not sent (no TX room): asked for again.
+        assert_eq!(monitor.observe(800, 1000, 2), Some(FlowAction::Xoff));
+        monitor.sent(FlowAction::Xoff);
+        assert_eq!(monitor.observe(400, 1000, 3), None);
+        assert_eq!(monitor.observe(250, 1000, 4), Some(FlowAction::Xon));
+        monitor.sent(FlowAction::Xon);
+        assert_eq!(monitor.observe(250, 1000, 5), None);
+        assert_eq!((monitor.stats.xoffs, monitor.stats.xons), (1, 1));
+
+        let mut disabled = WindowMonitor::new(FlowPolicy::DISABLED);
+        assert_eq!(disabled.observe(1000, 1000, 0), None);
+    }
+
+    #[test]
+    fn counts_time_spent_full() {
+        let mut monitor = WindowMonitor::new(FlowPolicy::DISABLED);
+        monitor.observe(1000, 1000, 10);
+        monitor.observe(1000, 1000, 15);
+        monitor.observe(1000, 1000, 40);
+        monitor.observe(10, 1000, 50);
+        monitor.observe(1000, 1000, 60);
+        monitor.observe(1000, 1000, 62);
+        assert_eq!(
+            monitor.stats,
+            FlowStats {
+                stalled_ms: 32,
+                stalls: 2,
+                xoffs: 0,
+                xons: 0
+            }
+        );
+    }
+}
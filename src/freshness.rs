@@ -0,0 +1,79 @@
+//! Freshness-first handling of a receive backlog.
+//!
+//! Processing victims strictly in arrival order is right while the node
+//! keeps up. Once frames queue behind a slow evaluation, the oldest ones are
+//! the least likely to still be actionable — their victims may already be
+//! in a block — and working through them delays the fresh ones behind. While
+//! more than `backlog_frames` frames are waiting, [`FreshnessPolicy`] skips
+//! any frame older than `max_age_cycles` instead of evaluating it, so the
+//! head of the queue is shed and the node catches up on new traffic.
+//!
+//! Only victim evaluation is skipped; state updates (pools, prices) are
+//! always applied.
+use crate::clock::Clock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FreshnessPolicy {
+    /// Waiting frames (this one included) above which stale frames are
+    /// skipped; 0 disables the policy.
+    pub backlog_frames: usize,
+    /// Age since receive, in cycles, at which a frame counts as stale.
+    pub max_age_cycles: u64,
+}
+
+impl FreshnessPolicy {
+    pub const DISABLED: Self = Self {
+        backlog_frames: 0,
+        max_age_cycles: u64::MAX,
+    };
+
+    pub fn is_enabled(&self) -> bool {
+        self.backlog_frames > 0
+    }
+
+    /// Whether to skip a frame received at `rx_cycles` with `waiting` frames
+    /// (it included) still queued now, by `clock`.
+    #[inline(always)]
+    pub fn skip(&self, waiting: usize, rx_cycles: u64, clock: &impl Clock) -> bool {
+        self.is_enabled()
+            && waiting > self.backlog_frames
+            && clock.now_cycles().wrapping_sub(rx_cycles) > self.max_age_cycles
+    }
+}
+
+impl Default for FreshnessPolicy {
+    fn default() -> Self {
+        Self::DISABLED
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ReplayClock;
+
+    const POLICY: FreshnessPolicy = FreshnessPolicy {
+        backlog_frames: 4,
+        max_age_cycles: 1_000,
+    };
+
+    #[test]
+    fn skips_stale_frames_behind_a_backlog() {
+        assert!(POLICY.skip(5, 100, &ReplayClock::at(2_000)));
+    }
+
+    #[test]
+    fn works_through_stale_frames_in_a_short_queue() {
+        assert!(!POLICY.skip(4, 100, &ReplayClock::at(2_000)));
+    }
+
+    #[test]
+    fn keeps_fresh_frames_behind_a_backlog() {
+        assert!(!POLICY.skip(32, 1_500, &ReplayClock::at(2_000)));
+    }
+
+    #[test]
+    fn disabled_policy_never_skips() {
+        assert!(!FreshnessPolicy::DISABLED.skip(usize::MAX, 0, &ReplayClock::at(u64::MAX)));
+    }
+}
@@ -0,0 +1,453 @@
+//! Downtime-free upgrades: the running node hands its live descriptors to
+//! its successor.
+//!
+//! With `MEV_HANDOVER_SOCKET` set, a node listens on that Unix socket, and
+//! at startup first tries to connect to it. A new binary started next to a
+//! running one therefore finds it there and is sent, in one message:
+//!
+//! - the TAP device's fd, still attached to the device with its receive
+//!   queue intact, so frames that arrive meanwhile wait for the new node
+//!   instead of being dropped (a TAP device takes one fd at a time, so it
+//!   could not be opened afresh while the old node holds it);
+//! - the state file's fd, written just before the handover, so the new node
+//!   maps the very pool table and reply history the old one left;
+//! - the admin and handover listeners, so neither socket path disappears;
+//! - the relayer feed's resume token and last sequence: the TCP connection
+//!   itself lives in the old node's userspace stack, so the new node
+//!   reconnects and resumes where the old one stopped, without a replay gap.
+//!
+//! Descriptors travel as `SCM_RIGHTS` ancillary data with a
+//! [`HandoverHeader`] naming the role of each. After sending, the old node
+//! leaves the socket paths and the TAP device in place for the new owner and
+//! exits. Only a process that can connect to the socket (mode 0600, same
+//! uid) can take the descriptors; keep it in a directory only the node's
+//! user can write to.
+use crate::session::ResumeToken;
+use bytemuck::{Pod, Zeroable};
+use std::io::{self, ErrorKind};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::time::Duration;
+
+/// Magic prefix of a `HandoverHeader` ("MVHO").
+pub const HANDOVER_MAGIC: [u8; 4] = *b"MVHO";
+pub const HANDOVER_VERSION: u8 = 1;
+/// Most descriptors one handover carries.
+pub const MAX_HANDOVER_FDS: usize = 4;
+/// Room for the `SCM_RIGHTS` control message of `MAX_HANDOVER_FDS` fds.
+const CONTROL_WORDS: usize = 8;
+
+/// What a handed-over descriptor is.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FdRole {
+    Tap = 1,
+    StateFile = 2,
+    AdminListener = 3,
+    HandoverListener = 4,
+}
+
+impl FdRole {
+    pub fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(Self::Tap),
+            2 => Some(Self::StateFile),
+            3 => Some(Self::AdminListener),
+            4 => Some(Self::HandoverListener),
+            _ => None,
+        }
+    }
+}
+
+/// The data part of a handover; the descriptors ride along as `SCM_RIGHTS`.
+///
+/// Layout (40 bytes):
+///   [0..4]   magic            "MVHO"
+///   [4]      version          1
+///   [5]      fd_count         descriptors attached, 0..=MAX_HANDOVER_FDS
+///   [6..10]  roles            `FdRole` of each descriptor, in order
+///   [10..12] _reserved
+///   [12..16] tap_frame_le     largest frame the TAP device takes, u32 LE
+///   [16..32] feed_token       relayer session resume token
+///   [32..40] feed_last_seq_le last relayer record delivered, u64 LE
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct HandoverHeader {
+    pub magic: [u8; 4],
+    pub version: u8,
+    pub fd_count: u8,
+    pub roles: [u8; MAX_HANDOVER_FDS],
+    pub _reserved: [u8; 2],
+    pub tap_frame_le: [u8; 4],
+    pub feed_token: ResumeToken,
+    pub feed_last_seq_le: [u8; 8],
+}
+
+const _: () = assert!(core::mem::size_of::<HandoverHeader>() == 40);
+
+impl HandoverHeader {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<HandoverHeader>();
+}
+
+/// Where the relayer feed stood; all zero before the first session.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FeedPosition {
+    pub token: ResumeToken,
+    pub last_seq: u64,
+}
+
+#[derive(Debug)]
+pub enum HandoverError {
+    Io(io::Error),
+    /// Not a handover header of this version, or cut short.
+    BadHeader,
+    /// The header names `expected` descriptors; `got` arrived.
+    FdCount {
+        expected: u8,
+        got: usize,
+    },
+    UnknownRole(u8),
+}
+
+impl core::fmt::Display for HandoverError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "handover socket failed ({e})"),
+            Self::BadHeader => write!(f, "not a version {HANDOVER_VERSION} handover"),
+            Self::FdCount { expected, got } => {
+                write!(f, "handover names {expected} descriptors, {got} arrived")
+            }
+            Self::UnknownRole(role) => write!(f, "unknown descriptor role {role}"),
+        }
+    }
+}
+
+impl std::error::Error for HandoverError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// What the running node offers its successor.
+#[derive(Default)]
+pub struct HandoverOffer<'a> {
+    fds: heapless::Vec<(FdRole, BorrowedFd<'a>), MAX_HANDOVER_FDS>,
+    tap_frame: u32,
+    feed: FeedPosition,
+}
+
+impl<'a> HandoverOffer<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a descriptor; past [`MAX_HANDOVER_FDS`] it is left out.
+    pub fn with_fd(mut self, role: FdRole, fd: BorrowedFd<'a>) -> Self {
+        let _ = self.fds.push((role, fd));
+        self
+    }
+
+    pub fn with_tap_frame(mut self, frame: usize) -> Self {
+        self.tap_frame = frame as u32;
+        self
+    }
+
+    pub fn with_feed(mut self, feed: FeedPosition) -> Self {
+        self.feed = feed;
+        self
+    }
+
+    pub fn header(&self) -> HandoverHeader {
+        let mut roles = [0; MAX_HANDOVER_FDS];
+        for (slot, (role, _)) in roles.iter_mut().zip(&self.fds) {
+            *slot = *role as u8;
+        }
+        HandoverHeader {
+            magic: HANDOVER_MAGIC,
+            version: HANDOVER_VERSION,
+            fd_count: self.fds.len() as u8,
+            roles,
+            _reserved: [0; 2],
+            tap_frame_le: self.tap_frame.to_le_bytes(),
+            feed_token: self.feed.token,
+            feed_last_seq_le: self.feed.last_seq.to_le_bytes(),
+        }
+    }
+
+    /// Send the header with the descriptors attached.
+    pub fn send(&self, stream: &UnixStream) -> io::Result<()> {
+        let header = self.header();
+        let bytes = bytemuck::bytes_of(&header);
+        let mut iov = libc::iovec {
+            iov_base: bytes.as_ptr() as *mut libc::c_void,
+            iov_len: bytes.len(),
+        };
+        let mut control = [0u64; CONTROL_WORDS];
+        // SAFETY: an all-zero msghdr is valid; the pointers set below
+        // outlive the sendmsg call.
+        let mut msg: libc::msghdr = unsafe { core::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        if !self.fds.is_empty() {
+            let data_len = (self.fds.len() * core::mem::size_of::<RawFd>()) as u32;
+            msg.msg_control = control.as_mut_ptr().cast();
+            msg.msg_controllen = unsafe { libc::CMSG_SPACE(data_len) } as _;
+            // SAFETY: `control` has room for CMSG_SPACE of MAX_HANDOVER_FDS
+            // fds and is 8-aligned, so the first header fits.
+            unsafe {
+                let cmsg = libc::CMSG_FIRSTHDR(&msg);
+                (*cmsg).cmsg_level = libc::SOL_SOCKET;
+                (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+                (*cmsg).cmsg_len = libc::CMSG_LEN(data_len) as _;
+                let data = libc::CMSG_DATA(cmsg) as *mut RawFd;
+                for (i, (_, fd)) in self.fds.iter().enumerate() {
+                    data.add(i).write_unaligned(fd.as_raw_fd());
+                }
+            }
+        }
+        let sent = unsafe { libc::sendmsg(stream.as_raw_fd(), &msg, libc::MSG_NOSIGNAL) };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if sent as usize != bytes.len() {
+            return Err(ErrorKind::WriteZero.into());
+        }
+        Ok(())
+    }
+}
+
+/// What the successor received.
+#[derive(Debug)]
+pub struct Handover {
+    fds: heapless::Vec<(FdRole, OwnedFd), MAX_HANDOVER_FDS>,
+    /// Largest frame the TAP device takes; 0 if not sent.
+    pub tap_frame: usize,
+    pub feed: FeedPosition,
+}
+
+impl Handover {
+    /// The descriptor for `role`, if one was handed over and not yet taken.
+    pub fn take(&mut self, role: FdRole) -> Option<OwnedFd> {
+        let i = self.fds.iter().position(|(r, _)| *r == role)?;
+        Some(self.fds.swap_remove(i).1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.fds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fds.is_empty()
+    }
+}
+
+/// Read one handover from `stream`, blocking up to its read timeout.
+/// Descriptors that arrive are closed again if the message is rejected.
+pub fn receive(stream: &UnixStream) -> Result<Handover, HandoverError> {
+    let mut header = HandoverHeader::zeroed();
+    let bytes = bytemuck::bytes_of_mut(&mut header);
+    let mut iov = libc::iovec {
+        iov_base: bytes.as_mut_ptr() as *mut libc::c_void,
+        iov_len: bytes.len(),
+    };
+    let mut control = [0u64; CONTROL_WORDS];
+    // SAFETY: as in `HandoverOffer::send`.
+    let mut msg: libc::msghdr = unsafe { core::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = core::mem::size_of_val(&control) as _;
+    let received = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msg, libc::MSG_CMSG_CLOEXEC) };
+    if received < 0 {
+        return Err(HandoverError::Io(io::Error::last_os_error()));
+    }
+    let mut fds: heapless::Vec<OwnedFd, MAX_HANDOVER_FDS> = heapless::Vec::new();
+    let mut got = 0;
+    // SAFETY: the kernel filled `control` with well-formed cmsgs; each
+    // SCM_RIGHTS fd is new to this process and owned from here on.
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let count = ((*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize)
+                    / core::mem::size_of::<RawFd>();
+                let data = libc::CMSG_DATA(cmsg) as *const RawFd;
+                for i in 0..count {
+                    // Past capacity the fd is dropped, i.e. closed.
+                    let _ = fds.push(OwnedFd::from_raw_fd(data.add(i).read_unaligned()));
+                    got += 1;
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    if received as usize != HandoverHeader::WIRE_SIZE
+        || header.magic != HANDOVER_MAGIC
+        || header.version != HANDOVER_VERSION
+        || msg.msg_flags & libc::MSG_CTRUNC != 0
+    {
+        return Err(HandoverError::BadHeader);
+    }
+    if usize::from(header.fd_count) != got || got > MAX_HANDOVER_FDS {
+        return Err(HandoverError::FdCount {
+            expected: header.fd_count,
+            got,
+        });
+    }
+    let mut handover = Handover {
+        fds: heapless::Vec::new(),
+        tap_frame: u32::from_le_bytes(header.tap_frame_le) as usize,
+        feed: FeedPosition {
+            token: header.feed_token,
+            last_seq: u64::from_le_bytes(header.feed_last_seq_le),
+        },
+    };
+    for (&role, fd) in header.roles.iter().zip(fds) {
+        let role = FdRole::from_u8(role).ok_or(HandoverError::UnknownRole(role))?;
+        let _ = handover.fds.push((role, fd));
+    }
+    Ok(handover)
+}
+
+/// Take over from the node listening at `path`. `None` if no node is
+/// listening there.
+pub fn take_over(path: &str, timeout: Duration) -> Result<Option<Handover>, HandoverError> {
+    let stream = match UnixStream::connect(path) {
+        Ok(stream) => stream,
+        Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+            return Ok(None)
+        }
+        Err(e) => return Err(HandoverError::Io(e)),
+    };
+    stream
+        .set_read_timeout(Some(timeout))
+        .map_err(HandoverError::Io)?;
+    receive(&stream).map(Some)
+}
+
+/// The socket a successor connects to.
+pub struct HandoverListener {
+    listener: UnixListener,
+    path: String,
+    /// Remove the socket file on drop; off once it is handed over.
+    unlink: bool,
+}
+
+impl HandoverListener {
+    /// Bind `path` (replacing a stale socket file), owner only.
+    pub fn bind(path: &str) -> io::Result<Self> {
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        Self::adopt(listener, path)
+    }
+
+    /// Listen on `listener`, already bound to `path`, e.g. one handed over.
+    pub fn adopt(listener: UnixListener, path: &str) -> io::Result<Self> {
+        listener.set_nonblocking(true)?;
+        Ok(Self {
+            listener,
+            path: path.to_string(),
+            unlink: true,
+        })
+    }
+
+    /// A successor waiting for the handover, if one connected. Never blocks.
+    pub fn accept(&self) -> Option<UnixStream> {
+        self.listener.accept().ok().map(|(stream, _)| stream)
+    }
+
+    /// Leave the socket file in place on drop: it now belongs to the node
+    /// the listener was handed to.
+    pub fn release(&mut self) {
+        self.unlink = false;
+    }
+}
+
+impl AsFd for HandoverListener {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.listener.as_fd()
+    }
+}
+
+impl Drop for HandoverListener {
+    fn drop(&mut self) {
+        if self.unlink {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Seek, Write};
+
+    #[test]
+    fn descriptors_reach_the_successor_with_their_roles() {
+        let path = std::env::temp_dir().join(format!("mev-handover-{}.sock", std::process::id()));
+        let path = path.to_str().unwrap();
+        let listener = HandoverListener::bind(path).unwrap();
+        let mut state = tempfile();
+        state.write_all(b"pool table").unwrap();
+
+        let successor = {
+            let path = path.to_string();
+            std::thread::spawn(move || take_over(&path, Duration::from_secs(5)))
+        };
+        let stream = loop {
+            if let Some(stream) = listener.accept() {
+                break stream;
+            }
+            std::thread::sleep(Duration::from_millis(1));
+        };
+        let feed = FeedPosition {
+            token: [7; 16],
+            last_seq: 1234,
+        };
+        HandoverOffer::new()
+            .with_fd(FdRole::StateFile, state.as_fd())
+            .with_fd(FdRole::HandoverListener, listener.as_fd())
+            .with_tap_frame(1514)
+            .with_feed(feed)
+            .send(&stream)
+            .unwrap();
+
+        let mut handover = successor.join().unwrap().unwrap().unwrap();
+        assert_eq!(
+            (handover.len(), handover.tap_frame, handover.feed),
+            (2, 1514, feed)
+        );
+        assert!(handover.take(FdRole::Tap).is_none());
+        // The same open file: what the old node wrote, the new one reads.
+        let mut inherited = std::fs::File::from(handover.take(FdRole::StateFile).unwrap());
+        let mut contents = String::new();
+        inherited.rewind().unwrap();
+        inherited.read_to_string(&mut contents).unwrap();
+        assert_eq!(contents, "pool table");
+        assert!(handover.take(FdRole::HandoverListener).is_some() && handover.is_empty());
+
+        drop(listener);
+        assert!(
+            take_over(path, Duration::from_secs(1)).unwrap().is_none(),
+            "nobody left to take over from"
+        );
+    }
+
+    fn tempfile() -> std::fs::File {
+        let path = std::env::temp_dir().join(format!("mev-handover-state-{}", std::process::id()));
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)
+            .unwrap();
+        let _ = std::fs::remove_file(&path);
+        file
+    }
+}
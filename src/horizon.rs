@@ -0,0 +1,210 @@
+//! Multi-slot opportunity horizon.
+//!
+//! A victim is normally priced once, against the pool state when it
+//! arrives, and dropped if the sandwich does not pay. But a victim pending
+//! in the mempool can still be sandwiched in a later slot, against a pool
+//! that moved in between: an oracle update expected next slot, a large swap
+//! landing ahead of it. With a horizon of N slots, victims that did not pay
+//! on arrival are parked here, and every time the feed reaches a new slot
+//! the parked ones are priced again. One that pays is promoted: its reply
+//! goes out along the route the victim came in on. One still parked N slots
+//! after it arrived, or past its deadline, lapses.
+//!
+//! The ring is fixed-size; once full, parking a victim lapses the oldest.
+//! A victim parked again (a fee replacement of the same sender and nonce)
+//! takes the place of the one it replaces.
+use crate::nonce::Address;
+use crate::payload::DexSwapTxExt;
+use crate::processor::Opportunity;
+
+/// Longest horizon accepted, in slots.
+pub const MAX_HORIZON_SLOTS: u64 = 32;
+
+/// A parked victim. `R` is where its reply would go.
+#[derive(Clone, Copy, Debug)]
+pub struct Pending<R> {
+    /// Victim record; `deadline_ms` is 0 for base `DexSwapTx` payloads.
+    pub victim: DexSwapTxExt,
+    /// Victim account for EVM payloads, zero otherwise.
+    pub sender: Address,
+    /// Sender weight the victim was screened with.
+    pub weight_bps: u32,
+    pub route: R,
+    /// Last slot it may be promoted in.
+    until_slot: u64,
+}
+
+/// Victims parked, promoted and lapsed so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HorizonCounts {
+    pub parked: u64,
+    pub promoted: u64,
+    pub lapsed: u64,
+}
+
+pub struct HorizonRing<R: Copy, const N: usize> {
+    entries: [Option<Pending<R>>; N],
+    head: usize,
+    slots: u64,
+    /// Newest slot the ring was advanced to.
+    slot: u64,
+    pub counts: HorizonCounts,
+}
+
+impl<R: Copy, const N: usize> HorizonRing<R, N> {
+    /// Keep victims for `slots` slots after the one they arrived in, at most
+    /// [`MAX_HORIZON_SLOTS`].
+    pub fn new(slots: u64) -> Self {
+        let slots = slots.min(MAX_HORIZON_SLOTS);
+        Self {
+            entries: [None; N],
+            head: 0,
+            slots,
+            slot: 0,
+            counts: HorizonCounts::default(),
+        }
+    }
+
+    /// Park a victim that did not pay in the current slot.
+    pub fn park(&mut self, victim: DexSwapTxExt, sender: Address, weight_bps: u32, route: R) {
+        let until_slot = self.slot.saturating_add(self.slots);
+        let pending = Pending {
+            victim,
+            sender,
+            weight_bps,
+            route,
+            until_slot,
+        };
+        self.counts.parked += 1;
+        if sender != [0; 20] {
+            let nonce = victim.base.nonce();
+            let same = |e: &Pending<R>| e.sender == sender && e.victim.base.nonce() == nonce;
+            if let Some(entry) = self
+                .entries
+                .iter_mut()
+                .find(|e| e.as_ref().is_some_and(same))
+            {
+                *entry = Some(pending);
+                return;
+            }
+        }
+        if self.entries[self.head].replace(pending).is_some() {
+            self.counts.lapsed += 1;
+        }
+        self.head = (self.head + 1) % N;
+    }
+
+    /// Drop the parked victim `(sender, nonce)`, if any.
+    pub fn forget(&mut self, sender: &Address, nonce: u64) {
+        for entry in &mut self.entries {
+            if entry.is_some_and(|e| e.sender == *sender && e.victim.base.nonce() == nonce) {
+                *entry = None;
+            }
+        }
+    }
+
+    /// The feed reached `slot` at unix time `now_ms`. If it is a new slot,
+    /// lapse the victims past their horizon or deadline and price the others
+    /// with `score`; those that pay are handed to `promote` and leave the
+    /// ring. Returns the number promoted.
+    pub fn advance(
+        &mut self,
+        slot: u64,
+        now_ms: u64,
+        mut score: impl FnMut(&Pending<R>) -> Option<Opportunity>,
+        mut promote: impl FnMut(&Pending<R>, Opportunity),
+    ) -> usize {
+        if slot <= self.slot {
+            return 0;
+        }
+        self.slot = slot;
+        let mut promoted = 0;
+        for entry in &mut self.entries {
+            let Some(pending) = entry else { continue };
+            let deadline = pending.victim.deadline_ms();
+            if slot > pending.until_slot || (deadline != 0 && deadline < now_ms) {
+                self.counts.lapsed += 1;
+                *entry = None;
+            } else if let Some(opportunity) = score(pending) {
+                promote(pending, opportunity);
+                promoted += 1;
+                *entry = None;
+            }
+        }
+        self.counts.promoted += promoted as u64;
+        promoted
+    }
+
+    /// Victims parked now.
+    pub fn len(&self) -> usize {
+        self.entries.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::DexSwapTx;
+
+    fn victim(nonce: u64, deadline_ms: u64) -> DexSwapTxExt {
+        DexSwapTxExt::from_parts(
+            DexSwapTx::from_parts(nonce, [0xAA; 20], 5_000_000, 1, 0),
+            deadline_ms,
+        )
+    }
+
+    #[test]
+    fn parked_victims_are_promoted_or_lapse_by_slot() {
+        let mut ring = HorizonRing::<u8, 4>::new(2);
+        ring.advance(10, 0, |_| None, |_, _| {});
+        ring.park(victim(1, 0), [0; 20], 10_000, 1);
+        ring.park(victim(2, 0), [0xB1; 20], 10_000, 2);
+        // A replacement takes its predecessor's place.
+        ring.park(victim(2, 0), [0xB1; 20], 5_000, 2);
+        assert_eq!(ring.len(), 2);
+
+        // Same slot: nothing is priced again.
+        assert_eq!(
+            ring.advance(10, 0, |_| panic!("priced twice in one slot"), |_, _| {}),
+            0
+        );
+        let mut promoted = Vec::new();
+        let pays = |p: &Pending<u8>| (p.victim.base.nonce() == 2).then(|| Opportunity::unscored(7));
+        assert_eq!(
+            ring.advance(11, 0, pays, |p, o| promoted.push((
+                p.route,
+                p.weight_bps,
+                o.profit
+            ))),
+            1
+        );
+        assert_eq!(promoted, [(2, 5_000, 7)]);
+
+        // Victim 1 is kept through slot 12 and lapses after; one past its
+        // deadline lapses before its horizon.
+        assert_eq!(ring.advance(12, 0, |_| None, |_, _| {}), 0);
+        assert_eq!(ring.len(), 1);
+        ring.park(victim(3, 1_000), [0; 20], 10_000, 3);
+        ring.advance(13, 2_000, |_| None, |_, _| {});
+        assert!(ring.is_empty());
+
+        // A full ring lapses its oldest; forgotten victims are not priced.
+        for nonce in 0..5 {
+            ring.park(victim(nonce, 0), [0xC1; 20], 10_000, 0);
+        }
+        ring.forget(&[0xC1; 20], 4);
+        assert_eq!(ring.len(), 3);
+        assert_eq!(
+            ring.counts,
+            HorizonCounts {
+                parked: 9,
+                promoted: 1,
+                lapsed: 3
+            }
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! What a polling loop does between empty polls.
+//!
+//! The loops never block, so a core with nothing to receive spins at full
+//! power: it runs hot, and the heat takes turbo headroom from its package
+//! neighbours. With `MEV_IDLE` set, after [`IdlePolicy::spin_polls`] empty
+//! polls in a row the loop waits up to [`IdlePolicy::wait_ns`] before it
+//! polls again:
+//! - `spin`: it does not wait (the default);
+//! - `pause`: it spins on `pause` until the deadline, which hands the core
+//!   to an SMT sibling and draws less power than polling;
+//! - `tpause`: it sleeps in C0.1 until the deadline (WAITPKG: Tremont,
+//!   Sapphire Rapids and later). C0.1 wakes within tens of nanoseconds;
+//! - `umwait`: as `tpause`, but a write to a line the transport watches
+//!   (`umonitor`) ends the sleep. The DPDK backend watches the queue's next
+//!   RX descriptor, which the NIC writes when a frame lands; the TAP
+//!   transport has nothing in the node's memory to watch and sleeps as with
+//!   `tpause`.
+//!
+//! `tpause` and `umwait` fall back to `pause` on CPUs without WAITPKG. The
+//! kernel caps one sleep at `/sys/devices/system/cpu/umwait_control/max_time`
+//! cycles; a longer wait ends early. Any poll that finds work resets the
+//! count, so bursts are polled through at full speed. A frame that arrives
+//! during a wait that nothing wakes is picked up at the deadline, so
+//! `wait_ns` bounds what a lull adds to the first frame after it. The TAP
+//! loop also ends a wait at smoltcp's next timer (`poll_delay`), so TCP
+//! retransmits and ARP retries run on time.
+use crate::runtime::now_cycles;
+
+/// Longest wait that can be configured: 1 ms.
+pub const MAX_IDLE_WAIT_NS: u64 = 1_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdleMode {
+    Spin,
+    Pause,
+    Tpause,
+    Umwait,
+}
+
+impl IdleMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "spin" => Some(Self::Spin),
+            "pause" => Some(Self::Pause),
+            "tpause" => Some(Self::Tpause),
+            "umwait" => Some(Self::Umwait),
+            _ => None,
+        }
+    }
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Spin => "spin",
+            Self::Pause => "pause",
+            Self::Tpause => "tpause",
+            Self::Umwait => "umwait",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IdlePolicy {
+    pub mode: IdleMode,
+    /// `MEV_IDLE_SPIN_POLLS`: empty polls in a row before waiting.
+    pub spin_polls: u32,
+    /// `MEV_IDLE_WAIT_NS`: longest wait.
+    pub wait_ns: u32,
+}
+
+impl Default for IdlePolicy {
+    fn default() -> Self {
+        Self {
+            mode: IdleMode::Spin,
+            spin_polls: 1_024,
+            wait_ns: 2_000,
+        }
+    }
+}
+
+pub struct Idler {
+    /// The policy's mode, as this CPU can run it.
+    mode: IdleMode,
+    spin_polls: u32,
+    wait_cycles: u64,
+    empty_polls: u32,
+}
+
+impl Idler {
+    /// An idler for a TSC of `cycles_per_us`.
+    pub fn new(policy: IdlePolicy, cycles_per_us: u64) -> Self {
+        let mode = match policy.mode {
+            IdleMode::Tpause | IdleMode::Umwait if !waitpkg_supported() => IdleMode::Pause,
+            mode => mode,
+        };
+        Self {
+            mode,
+            spin_polls: policy.spin_polls,
+            wait_cycles: (policy.wait_ns as u64 * cycles_per_us / 1_000).max(1),
+            empty_polls: 0,
+        }
+    }
+
+    pub fn mode(&self) -> IdleMode {
+        self.mode
+    }
+
+    /// After a poll that found work if `busy`; returns the cycles waited,
+    /// 0 if it did not wait. In `umwait` mode, `monitor` is handed the TSC
+    /// deadline to sleep until on the transport's RX ring; it returns
+    /// `false` if it cannot, and the idler sleeps as with `tpause`.
+    #[inline(always)]
+    pub fn idle(&mut self, busy: bool, monitor: impl FnOnce(u64) -> bool) -> u64 {
+        self.idle_capped(busy, || u64::MAX, monitor)
+    }
+
+    /// As [`idle`](Self::idle), but waits no more than `cap` cycles: the
+    /// transport's own next deadline, such as a TCP retransmit, asked for
+    /// only once the idler is about to wait. A cap of 0 skips the wait.
+    #[inline(always)]
+    pub fn idle_capped(
+        &mut self,
+        busy: bool,
+        cap: impl FnOnce() -> u64,
+        monitor: impl FnOnce(u64) -> bool,
+    ) -> u64 {
+        if busy || self.mode == IdleMode::Spin {
+            self.empty_polls = 0;
+            return 0;
+        }
+        if self.empty_polls < self.spin_polls {
+            self.empty_polls += 1;
+            return 0;
+        }
+        let wait_cycles = self.wait_cycles.min(cap());
+        if wait_cycles == 0 {
+            return 0;
+        }
+        let start = now_cycles();
+        let deadline = start + wait_cycles;
+        match self.mode {
+            IdleMode::Spin => {}
+            IdleMode::Pause => {
+                while now_cycles() < deadline {
+                    core::hint::spin_loop();
+                }
+            }
+            IdleMode::Tpause => tpause(deadline),
+            IdleMode::Umwait => {
+                if !monitor(deadline) {
+                    tpause(deadline);
+                }
+            }
+        }
+        now_cycles().saturating_sub(start).max(1)
+    }
+}
+
+/// Whether the CPU has WAITPKG (`umonitor`, `umwait`, `tpause`).
+pub fn waitpkg_supported() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        use core::arch::x86_64::{__cpuid_count, __get_cpuid_max};
+        __get_cpuid_max(0).0 >= 7 && __cpuid_count(7, 0).ecx & (1 << 5) != 0
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    false
+}
+
+/// Sleep in C0.1 until the TSC reads `deadline`, or an interrupt. Only
+/// called once [`waitpkg_supported`] held.
+#[inline(always)]
+fn tpause(deadline: u64) {
+    #[cfg(target_arch = "x86_64")]
+    // SAFETY: WAITPKG is present; `tpause` touches no memory and only sets
+    // flags. ECX bit 0 selects C0.1.
+    unsafe {
+        core::arch::asm!(
+            "tpause {control:e}",
+            control = in(reg) 1u32,
+            in("edx") (deadline >> 32) as u32,
+            in("eax") deadline as u32,
+            options(nomem, nostack),
+        );
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    let _ = deadline;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_only_after_a_run_of_empty_polls() {
+        assert_eq!(IdleMode::parse("TPAUSE"), Some(IdleMode::Tpause));
+        assert_eq!(IdleMode::parse("mwait"), None);
+
+        let policy = IdlePolicy {
+            mode: IdleMode::Pause,
+            spin_polls: 2,
+            wait_ns: 1_000,
+        };
+        let mut idler = Idler::new(policy, 1_000);
+        for busy in [false, false, true, false, false] {
+            assert_eq!(
+                idler.idle(busy, |_| unreachable!("not umwait")),
+                0,
+                "work resets the run"
+            );
+        }
+        assert!(idler.idle(false, |_| unreachable!("not umwait")) >= 1_000);
+        // Something due now skips the wait; the run of empty polls goes on.
+        assert_eq!(
+            idler.idle_capped(false, || 0, |_| unreachable!("not umwait")),
+            0
+        );
+        assert!(idler.idle_capped(false, || 500, |_| unreachable!("not umwait")) >= 500);
+
+        // WAITPKG modes run as `pause` where the CPU lacks it; `umwait`
+        // hands the monitor a deadline and sleeps out a refusal.
+        let mut idler = Idler::new(
+            IdlePolicy {
+                mode: IdleMode::Umwait,
+                spin_polls: 0,
+                ..policy
+            },
+            1_000,
+        );
+        let expected = if waitpkg_supported() {
+            IdleMode::Umwait
+        } else {
+            IdleMode::Pause
+        };
+        assert_eq!(idler.mode(), expected);
+        let mut armed = None;
+        let waited = idler.idle(false, |deadline| {
+            armed = Some(deadline);
+            false
+        });
+        assert_eq!((armed.is_some(), waited != 0), (waitpkg_supported(), true));
+
+        let mut idler = Idler::new(
+            IdlePolicy {
+                mode: IdleMode::Spin,
+                spin_polls: 0,
+                ..policy
+            },
+            1_000,
+        );
+        assert_eq!(idler.idle(false, |_| unreachable!("spin never waits")), 0);
+    }
+}
@@ -0,0 +1,364 @@
+//! Timeline journal: rotation, compression and retention.
+//!
+//! At production rates the timeline (`MEV_RECORD_TIMELINE`, see
+//! [`crate::backtest`]) grows by gigabytes an hour. [`Journal`] writes it as
+//! a series of segments. The active one is at the configured path; once it
+//! passes `MEV_JOURNAL_ROTATE_BYTES` or has been open for
+//! `MEV_JOURNAL_ROTATE_MS`, it is renamed `<path>.<start_ms>` (its start
+//! time, zero-padded so names sort by age) and a fresh one is started.
+//! Rotation happens between records: every segment is a complete timeline,
+//! magic included.
+//!
+//! A rotated segment is handed to a background thread, off the pinned
+//! core. Built with the `zstd` feature and unless `MEV_JOURNAL_COMPRESS=0`,
+//! the thread compresses it to `<path>.<start_ms>.zst` and removes the
+//! original. Built with the `parquet` feature and with
+//! `MEV_JOURNAL_PARQUET=1`, it first exports the segment's decisions to
+//! `<path>.<start_ms>.parquet` (see [`crate::parquet_export`]). It then
+//! deletes the oldest rotated segments beyond `MEV_JOURNAL_KEEP`, exports
+//! included, so the journal takes bounded space.
+//!
+//! [`segments`] lists a journal's segments oldest first, the active one
+//! last, and [`read_segment`] loads one, decompressing it if it is zstd.
+//! `backtest <path>` plays them all in order.
+use crate::affinity::CoreSlot;
+use crate::backtest::{EventKind, TimelineHeader, TimelineWriter, TIMELINE_MAGIC};
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+/// First bytes of a zstd frame.
+pub const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Suffix of a compressed segment.
+const ZSTD_SUFFIX: &str = ".zst";
+
+/// Suffix of a segment's Parquet export.
+const PARQUET_SUFFIX: &str = ".parquet";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JournalConfig {
+    /// Segment size that triggers rotation, in bytes.
+    pub rotate_bytes: u64,
+    /// Segment age that triggers rotation; 0 rotates by size only.
+    pub rotate_ms: u64,
+    /// Rotated segments kept; older ones are deleted.
+    pub keep: usize,
+    /// Compress rotated segments (needs the `zstd` feature).
+    pub compress: bool,
+    /// Export rotated segments to Parquet (needs the `parquet` feature).
+    pub parquet: bool,
+}
+
+impl Default for JournalConfig {
+    fn default() -> Self {
+        Self {
+            rotate_bytes: 256 << 20,
+            rotate_ms: 3_600_000,
+            keep: 24,
+            compress: cfg!(feature = "zstd"),
+            parquet: false,
+        }
+    }
+}
+
+/// A timeline recorded as rotating segments.
+pub struct Journal {
+    path: PathBuf,
+    config: JournalConfig,
+    writer: TimelineWriter<BufWriter<File>>,
+    /// Bytes in the active segment, magic included.
+    written: u64,
+    started_ms: u64,
+    /// Rotated segments, to the compression thread.
+    sealed: mpsc::Sender<PathBuf>,
+}
+
+impl Journal {
+    /// Start recording to `path` at unix time `now_ms`, and the thread
+    /// that compresses and prunes segments, placed on `core`. A segment left
+    /// at `path` by an earlier run is rotated first, not overwritten.
+    pub fn create(
+        path: impl Into<PathBuf>,
+        config: JournalConfig,
+        now_ms: u64,
+        core: CoreSlot,
+    ) -> io::Result<Self> {
+        let path = path.into();
+        if config.compress && !cfg!(feature = "zstd") {
+            log::warn!("MEV_JOURNAL_COMPRESS needs the zstd feature; segments stay uncompressed");
+        }
+        if config.parquet && !cfg!(feature = "parquet") {
+            log::warn!("MEV_JOURNAL_PARQUET needs the parquet feature; segments are not exported");
+        }
+        let leftover = match std::fs::metadata(&path) {
+            Ok(meta) if meta.len() > 0 => {
+                // Named for its last write, and before the segment replacing it.
+                let modified = meta
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok());
+                let stamp = modified
+                    .map_or(0, |d| d.as_millis() as u64)
+                    .min(now_ms.saturating_sub(1));
+                let segment = segment_path(&path, stamp);
+                std::fs::rename(&path, &segment)?;
+                Some(segment)
+            }
+            _ => None,
+        };
+        let writer = TimelineWriter::new(BufWriter::new(File::create(&path)?))?;
+        let (sealed, rotated) = mpsc::channel::<PathBuf>();
+        let (base, compress, parquet, keep) =
+            (path.clone(), config.compress, config.parquet, config.keep);
+        std::thread::Builder::new()
+            .name("journal".into())
+            .spawn(move || {
+                crate::affinity::pin_current_thread(core);
+                for segment in rotated {
+                    if parquet {
+                        if let Err(e) = export_segment(&segment) {
+                            log::warn!("journal segment {} not exported: {e}", segment.display());
+                        }
+                    }
+                    if compress {
+                        if let Err(e) = compress_segment(&segment) {
+                            log::warn!(
+                                "journal segment {} left uncompressed: {e}",
+                                segment.display()
+                            );
+                        }
+                    }
+                    if let Err(e) = prune(&base, keep) {
+                        log::warn!("journal retention for {}: {e}", base.display());
+                    }
+                }
+            })?;
+        if let Some(segment) = leftover {
+            let _ = sealed.send(segment);
+        }
+        Ok(Self {
+            path,
+            config,
+            writer,
+            written: TIMELINE_MAGIC.len() as u64,
+            started_ms: now_ms,
+            sealed,
+        })
+    }
+
+    /// Append one record, rotating first if the active segment is full.
+    pub fn record(&mut self, kind: EventKind, rx_ts_ns: u64, payload: &[u8]) -> io::Result<()> {
+        if self.written >= self.config.rotate_bytes {
+            self.rotate(rx_ts_ns / 1_000_000)?;
+        }
+        self.writer.record(kind, rx_ts_ns, payload)?;
+        self.written += (TimelineHeader::WIRE_SIZE + payload.len()) as u64;
+        Ok(())
+    }
+
+    /// Flush the active segment, and rotate it if it is older than
+    /// `rotate_ms` at unix time `now_ms` and holds any record.
+    pub fn flush(&mut self, now_ms: u64) -> io::Result<()> {
+        let aged = self.config.rotate_ms != 0
+            && now_ms.saturating_sub(self.started_ms) >= self.config.rotate_ms;
+        if aged && self.written > TIMELINE_MAGIC.len() as u64 {
+            return self.rotate(now_ms);
+        }
+        self.writer.flush()
+    }
+
+    /// Seal the active segment under its start time and open a new one.
+    fn rotate(&mut self, now_ms: u64) -> io::Result<()> {
+        self.writer.flush()?;
+        let segment = segment_path(&self.path, self.started_ms);
+        std::fs::rename(&self.path, &segment)?;
+        self.writer = TimelineWriter::new(BufWriter::new(File::create(&self.path)?))?;
+        (self.written, self.started_ms) = (TIMELINE_MAGIC.len() as u64, now_ms);
+        if self.sealed.send(segment).is_err() {
+            log::warn!("journal thread gone; rotated segments are no longer compressed or pruned");
+        }
+        Ok(())
+    }
+}
+
+/// `<path>.<start_ms>`, the start time zero-padded to 16 digits.
+fn segment_path(path: &Path, start_ms: u64) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{start_ms:016}"));
+    PathBuf::from(name)
+}
+
+/// The rotated segments of the journal at `path`, oldest first, each
+/// either plain or compressed.
+fn rotated(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+        return Ok(Vec::new());
+    };
+    let dir = path
+        .parent()
+        .filter(|d| !d.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let prefix = format!("{file_name}.");
+    let mut found: Vec<(String, PathBuf)> = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let Some(rest) = name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let stamp = rest.strip_suffix(ZSTD_SUFFIX).unwrap_or(rest);
+        if stamp.len() == 16 && stamp.bytes().all(|b| b.is_ascii_digit()) {
+            found.push((stamp.to_owned(), entry.path()));
+        }
+    }
+    found.sort();
+    Ok(found.into_iter().map(|(_, path)| path).collect())
+}
+
+/// Every segment of the journal at `path`, oldest first: the rotated ones,
+/// then the active one if present.
+pub fn segments(path: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+    let path = path.as_ref();
+    let mut all = rotated(path)?;
+    if path.exists() {
+        all.push(path.to_path_buf());
+    }
+    Ok(all)
+}
+
+/// Read one segment, decompressing it if it is zstd.
+pub fn read_segment(path: impl AsRef<Path>) -> io::Result<Vec<u8>> {
+    let bytes = std::fs::read(path)?;
+    if !bytes.starts_with(&ZSTD_MAGIC) {
+        return Ok(bytes);
+    }
+    #[cfg(feature = "zstd")]
+    return zstd::decode_all(&bytes[..]);
+    #[cfg(not(feature = "zstd"))]
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "zstd segment, but built without the zstd feature",
+    ))
+}
+
+/// `<path>.<start_ms>.parquet`, the export of a rotated segment, plain or
+/// compressed.
+pub fn parquet_path(segment: &Path) -> PathBuf {
+    let name = segment.as_os_str().to_string_lossy();
+    let plain = name.strip_suffix(ZSTD_SUFFIX).unwrap_or(&name);
+    PathBuf::from(format!("{plain}{PARQUET_SUFFIX}"))
+}
+
+/// Export the decisions of `segment` to [`parquet_path`]. Without the
+/// `parquet` feature, a no-op.
+fn export_segment(segment: &Path) -> io::Result<()> {
+    #[cfg(feature = "parquet")]
+    crate::parquet_export::export_segment(segment, &parquet_path(segment))?;
+    #[cfg(not(feature = "parquet"))]
+    let _ = segment;
+    Ok(())
+}
+
+/// Compress `segment` to `<segment>.zst` and remove it. Without the
+/// `zstd` feature, a no-op.
+fn compress_segment(segment: &Path) -> io::Result<()> {
+    #[cfg(feature = "zstd")]
+    {
+        let mut compressed = segment.as_os_str().to_owned();
+        compressed.push(ZSTD_SUFFIX);
+        let mut partial = compressed.clone();
+        partial.push(".tmp");
+        zstd::stream::copy_encode(File::open(segment)?, File::create(&partial)?, 3)?;
+        std::fs::rename(&partial, &compressed)?;
+        std::fs::remove_file(segment)?;
+    }
+    #[cfg(not(feature = "zstd"))]
+    let _ = segment;
+    Ok(())
+}
+
+/// Delete the oldest rotated segments of the journal at `path` beyond
+/// `keep`, and their exports.
+fn prune(path: &Path, keep: usize) -> io::Result<()> {
+    let rotated = rotated(path)?;
+    for old in &rotated[..rotated.len().saturating_sub(keep)] {
+        std::fs::remove_file(old)?;
+        match std::fs::remove_file(parquet_path(old)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::Timeline;
+
+    #[test]
+    fn rotates_by_size_and_age_and_keeps_the_newest_segments() {
+        let dir = std::env::temp_dir().join(format!("mev-journal-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timeline.bin");
+        std::fs::write(&path, TIMELINE_MAGIC).unwrap();
+        // Three records of 16 + 4 bytes fill a segment.
+        let config = JournalConfig {
+            rotate_bytes: 8 + 3 * 20,
+            rotate_ms: 1_000,
+            keep: 3,
+            compress: true,
+            parquet: false,
+        };
+        let t0 = crate::runtime::unix_time_ms() + 1_000;
+        let mut journal = Journal::create(&path, config, t0, CoreSlot::AwayFrom(0)).unwrap();
+        for i in 0..7u64 {
+            journal
+                .record(EventKind::Victim, (t0 + i + 1) * 1_000_000, &[i as u8; 4])
+                .unwrap();
+        }
+        journal.flush(t0 + 500).unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap().len(),
+            8 + 20,
+            "one record since the last size rotation"
+        );
+        journal.flush(t0 + 7_000).unwrap();
+        drop(journal);
+
+        // The leftover and three full segments, then the aged one: the
+        // oldest is pruned once the thread has caught up.
+        let mut names = Vec::new();
+        for _ in 0..200 {
+            names = segments(&path).unwrap();
+            if names.len() == 4
+                && names
+                    .iter()
+                    .all(|s| cfg!(not(feature = "zstd")) || s == &path || is_zstd(s))
+            {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert_eq!(names.len(), 4, "{names:?}");
+        assert_eq!(names.last(), Some(&path));
+        let recorded: Vec<u8> = names
+            .iter()
+            .flat_map(|segment| {
+                let bytes = read_segment(segment).unwrap();
+                Timeline::parse(&bytes)
+                    .unwrap()
+                    .map(|e| e.unwrap().payload[0])
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        assert_eq!(recorded, [0, 1, 2, 3, 4, 5, 6]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn is_zstd(segment: &Path) -> bool {
+        std::fs::read(segment).is_ok_and(|b| b.starts_with(&ZSTD_MAGIC))
+    }
+}
@@ -0,0 +1,188 @@
+//! Compile-time checks of the wire layouts.
+//!
+//! Every wire struct is `#[repr(C)]` and built only from byte arrays and
+//! zerocopy's little-endian integers, so it has alignment 1, no padding and
+//! the same bytes on every target: multi-byte fields are read and written
+//! with `from_le_bytes`/`to_le_bytes` and never through a native integer.
+//! The assertions below pin the offset of every field, the size and the
+//! alignment of each struct, so reordering, resizing or retyping a field
+//! (say, `[u8; 8]` to `u64`, which is 4-aligned on some 32-bit targets)
+//! fails the build rather than the peer. Offsets are those documented on
+//! each struct.
+use crate::ack::ReplyAck;
+use crate::coalesce::ReplyBatchHeader;
+use crate::discovery::PoolDiscoveryRequest;
+use crate::divergence::DivergenceAlert;
+use crate::fec::FecShardHeader;
+use crate::orderbook::{BookUpdate, OrderBookState, PriceLevel, BOOK_DEPTH};
+use crate::payload::{DexSwapTx, DexSwapTxEvm, DexSwapTxExt, OpportunityCancel, OpportunityReply};
+use crate::prices::PriceUpdate;
+use crate::reputation::VictimOutcome;
+use crate::sign::ReplySignature;
+use crate::validator::PoolStateUpdate;
+use memoffset::offset_of;
+
+/// Assert `$ty` is `$size` bytes, 1-aligned, with each field at its offset.
+macro_rules! assert_layout {
+    ($ty:path, $size:expr, { $($field:ident: $offset:expr),+ $(,)? }) => {
+        const _: () = {
+            assert!(core::mem::size_of::<$ty>() == $size);
+            assert!(core::mem::align_of::<$ty>() == 1);
+            $(assert!(offset_of!($ty, $field) == $offset);)+
+        };
+    };
+}
+
+assert_layout!(DexSwapTx, 48, {
+    nonce_le: 0,
+    pool_address: 8,
+    amount_in_le: 28,
+    min_amount_out_le: 36,
+    token_direction: 44,
+    _reserved: 45,
+});
+assert_layout!(DexSwapTxExt, 64, { base: 0, deadline_ms_le: 48, priority_fee_le: 56 });
+assert_layout!(DexSwapTxEvm, 96, { ext: 0, sender: 64, gas_price_le: 84, _evm_reserved: 92 });
+assert_layout!(OpportunityReply, 40, {
+    nonce_le: 0,
+    profit_le: 8,
+    rx_ts_ns_le: 16,
+    tx_ts_ns_le: 24,
+    confidence_bps_le: 32,
+    _reserved: 34,
+});
+assert_layout!(OpportunityCancel, 48, {
+    magic: 0,
+    sender: 4,
+    nonce_le: 24,
+    reason: 32,
+    _reserved: 33,
+    tx_ts_ns_le: 40,
+});
+assert_layout!(ReplyBatchHeader, 8, { magic: 0, count: 4, _reserved: 5 });
+assert_layout!(PoolStateUpdate, 64, {
+    pool_address: 0,
+    reserve0: 20,
+    reserve1: 28,
+    slot: 36,
+    seq: 44,
+    version: 48,
+    _reserved: 49,
+    fee_ppm: 52,
+    sent_ns: 56,
+});
+assert_layout!(PriceUpdate, 32, {
+    magic: 0,
+    token_id_le: 4,
+    _reserved: 6,
+    price_le: 8,
+    slot_le: 16,
+    seq_le: 24,
+});
+assert_layout!(PriceLevel, 16, { price_le: 0, size_le: 8 });
+assert_layout!(OrderBookState, 2 * BOOK_DEPTH * 16, { bids: 0, asks: BOOK_DEPTH * 16 });
+assert_layout!(BookUpdate, 56 + 2 * BOOK_DEPTH * 16, {
+    magic: 0,
+    market: 4,
+    ref_pool: 24,
+    seq_le: 44,
+    slot_le: 48,
+    book: 56,
+});
+assert_layout!(VictimOutcome, 40, { magic: 0, sender: 4, nonce_le: 24, outcome: 32, _reserved: 33 });
+assert_layout!(ReplyAck, 16, { magic: 0, nonce_le: 4, _reserved: 12 });
+assert_layout!(ReplySignature, 24, { node_id_le: 0, _reserved: 4, tag: 8 });
+assert_layout!(DivergenceAlert, 80, {
+    magic: 0,
+    market: 4,
+    pool: 24,
+    divergence_bps_le: 44,
+    updates_le: 48,
+    book_mid_le: 52,
+    pool_price_le: 60,
+    slot_le: 68,
+    _reserved: 76,
+});
+assert_layout!(PoolDiscoveryRequest, 32, { magic: 0, pool: 4, hits_le: 24, attempt_le: 28 });
+assert_layout!(FecShardHeader, 16, {
+    magic: 0,
+    group_le: 4,
+    index: 8,
+    k: 9,
+    len_le: 10,
+    shard_len_le: 12,
+    _reserved: 14,
+});
+
+#[cfg(feature = "std")]
+mod session {
+    use crate::session::{FlowControl, RecordHeader, SessionHello, SessionWelcome};
+    use memoffset::offset_of;
+
+    assert_layout!(SessionHello, 32, {
+        magic: 0,
+        version_le: 4,
+        wire_min: 6,
+        wire_max: 7,
+        token: 8,
+        last_seq_le: 24,
+    });
+    assert_layout!(SessionWelcome, 32, {
+        magic: 0,
+        version_le: 4,
+        status: 6,
+        wire_version: 7,
+        token: 8,
+        next_seq_le: 24,
+    });
+    assert_layout!(FlowControl, 16, { magic: 0, action: 4, _reserved: 5, last_seq_le: 8 });
+    assert_layout!(RecordHeader, 16, { seq_le: 0, len_le: 8, _reserved: 10 });
+}
+
+#[cfg(feature = "std")]
+mod handover {
+    use crate::handover::HandoverHeader;
+    use memoffset::offset_of;
+
+    assert_layout!(HandoverHeader, 40, {
+        magic: 0,
+        version: 4,
+        fd_count: 5,
+        roles: 6,
+        _reserved: 10,
+        tap_frame_le: 12,
+        feed_token: 16,
+        feed_last_seq_le: 32,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn fields_decode_little_endian_on_any_host() {
+        let mut bytes = [0u8; PoolStateUpdate::WIRE_SIZE];
+        bytes[20..28].copy_from_slice(&[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08]);
+        bytes[44..48].copy_from_slice(&[0x0A, 0x0B, 0x0C, 0x0D]);
+        let update: &PoolStateUpdate = zerocopy::FromBytes::ref_from_bytes(&bytes[..]).unwrap();
+        assert_eq!(
+            (update.reserve0(), update.seq.get()),
+            (0x0807_0605_0403_0201, 0x0D0C_0B0A)
+        );
+
+        let mut reply = OpportunityReply::zeroed();
+        reply.nonce_le = 0x0102_0304_0506_0708u64.to_le_bytes();
+        assert_eq!(
+            reply.nonce_le,
+            [0x08, 0x07, 0x06, 0x05, 0x04, 0x03, 0x02, 0x01]
+        );
+        // Reading the field as a native integer would only agree on a
+        // little-endian host; the accessors agree everywhere.
+        let native = u64::from_ne_bytes(reply.nonce_le);
+        assert_eq!(native == reply.nonce(), cfg!(target_endian = "little"));
+        reply.nonce_le.reverse();
+        assert_eq!(reply.nonce(), 0x0807_0605_0403_0201);
+    }
+}
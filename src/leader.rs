@@ -0,0 +1,342 @@
+//! Hot/standby leader election between two nodes on the same feed.
+//!
+//! Two nodes reading the same feed would both reply to every victim, and
+//! both replies would be executed. With `MEV_LEADER_PEER` set, each node
+//! runs an election thread ([`spawn`]) that exchanges [`Heartbeat`]s with
+//! its peer over UDP, and only the active node replies.
+//!
+//! A node starts standby. A standby node that has heard no active heartbeat
+//! for `timeout_us` takes over, in the next term. Two active nodes (both
+//! started at once, or a partition healed) settle on the higher term, then
+//! the lower node id; the other yields as soon as it hears the winner.
+//!
+//! Being active is a lease: the election thread renews it for half of
+//! `timeout_us` on every heartbeat, and [`Leadership::is_active`] reads it
+//! against the clock. A leader whose election thread stalls stops replying
+//! before its peer's timeout runs out, so the two never reply at once
+//! unless heartbeats take longer than half the timeout to arrive.
+use bytemuck::{Pod, Zeroable};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Magic prefix identifying a `Heartbeat` datagram ("MVHB").
+pub const HEARTBEAT_MAGIC: [u8; 4] = *b"MVHB";
+
+/// One node's view of the election, sent every heartbeat interval.
+///
+/// Layout (24 bytes, little-endian):
+///   [0..4]   magic     "MVHB"
+///   [4..8]   node_id   u32
+///   [8..16]  term      u64 — the newest term the node knows of
+///   [16]     active    u8 — 1 if the node leads `term`
+///   [17..24] reserved
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct Heartbeat {
+    pub magic: [u8; 4],
+    pub node_id_le: [u8; 4],
+    pub term_le: [u8; 8],
+    pub active: u8,
+    pub _reserved: [u8; 7],
+}
+
+impl Heartbeat {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<Heartbeat>();
+
+    pub fn new(node_id: u32, term: u64, active: bool) -> Self {
+        Self {
+            magic: HEARTBEAT_MAGIC,
+            node_id_le: node_id.to_le_bytes(),
+            term_le: term.to_le_bytes(),
+            active: active as u8,
+            _reserved: [0; 7],
+        }
+    }
+
+    #[inline(always)]
+    pub fn node_id(&self) -> u32 {
+        u32::from_le_bytes(self.node_id_le)
+    }
+
+    #[inline(always)]
+    pub fn term(&self) -> u64 {
+        u64::from_le_bytes(self.term_le)
+    }
+
+    #[inline(always)]
+    pub fn is_active(&self) -> bool {
+        self.active != 0
+    }
+
+    /// Zero-copy parse; returns `None` unless the payload is exactly one
+    /// `Heartbeat` carrying the expected magic.
+    pub fn parse(data: &[u8]) -> Option<&Heartbeat> {
+        if data.len() != Self::WIRE_SIZE {
+            return None;
+        }
+        let heartbeat = bytemuck::try_from_bytes::<Heartbeat>(data).ok()?;
+        (heartbeat.magic == HEARTBEAT_MAGIC).then_some(heartbeat)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LeaderConfig {
+    /// This node's id; must differ from the peer's. The lower id wins a tie.
+    pub node_id: u32,
+    /// Local UDP port heartbeats are received on.
+    pub port: u16,
+    /// The peer's heartbeat endpoint (IPv4, UDP port).
+    pub peer: ([u8; 4], u16),
+    /// How often heartbeats are sent.
+    pub heartbeat_us: u64,
+    /// Silence from the leader after which a standby node takes over.
+    pub timeout_us: u64,
+}
+
+impl LeaderConfig {
+    pub const DEFAULT_PORT: u16 = 9400;
+
+    pub fn new(peer: ([u8; 4], u16)) -> Self {
+        Self {
+            node_id: 0,
+            port: Self::DEFAULT_PORT,
+            peer,
+            heartbeat_us: 1_000,
+            timeout_us: 5_000,
+        }
+    }
+}
+
+/// Whether this node replies; read by every reply path.
+pub struct Leadership {
+    /// Unix time the lease runs out; 0 while standby, `u64::MAX` without an
+    /// election.
+    lease_until_ns: AtomicU64,
+    term: AtomicU64,
+    takeovers: AtomicU64,
+    yields: AtomicU64,
+}
+
+impl Leadership {
+    /// Active for good, until an [`Election`] starts.
+    pub const fn new() -> Self {
+        Self {
+            lease_until_ns: AtomicU64::new(u64::MAX),
+            term: AtomicU64::new(0),
+            takeovers: AtomicU64::new(0),
+            yields: AtomicU64::new(0),
+        }
+    }
+
+    /// Whether this node may reply. `now_ns` is only read while an
+    /// election runs.
+    #[inline(always)]
+    pub fn is_active(&self, now_ns: impl FnOnce() -> u64) -> bool {
+        match self.lease_until_ns.load(Ordering::Relaxed) {
+            u64::MAX => true,
+            0 => false,
+            until => now_ns() < until,
+        }
+    }
+
+    /// The newest term this node knows of.
+    pub fn term(&self) -> u64 {
+        self.term.load(Ordering::Relaxed)
+    }
+
+    /// Times this node took over.
+    pub fn takeovers(&self) -> u64 {
+        self.takeovers.load(Ordering::Relaxed)
+    }
+
+    /// Times this node stood down for a peer.
+    pub fn yields(&self) -> u64 {
+        self.yields.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for Leadership {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The election thread's state; publishes its outcome to a [`Leadership`].
+pub struct Election {
+    config: LeaderConfig,
+    term: u64,
+    active: bool,
+    /// Last time an active heartbeat this node defers to arrived.
+    last_leader_ns: u64,
+}
+
+impl Election {
+    /// Start standby at `now_ns`: a node takes over only after a full
+    /// timeout without hearing a leader.
+    pub fn new(config: LeaderConfig, now_ns: u64, leadership: &Leadership) -> Self {
+        leadership.lease_until_ns.store(0, Ordering::Relaxed);
+        Self {
+            config,
+            term: 0,
+            active: false,
+            last_leader_ns: now_ns,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn term(&self) -> u64 {
+        self.term
+    }
+
+    /// Take in the peer's heartbeat, received at `now_ns`.
+    pub fn on_heartbeat(&mut self, heartbeat: &Heartbeat, now_ns: u64, leadership: &Leadership) {
+        let peer = heartbeat.node_id();
+        if !heartbeat.is_active() || peer == self.config.node_id {
+            return;
+        }
+        let peer_wins = (heartbeat.term(), core::cmp::Reverse(peer))
+            > (self.term, core::cmp::Reverse(self.config.node_id));
+        if self.active && !peer_wins {
+            // The peer yields once it hears us.
+            return;
+        }
+        if self.active {
+            self.active = false;
+            leadership.lease_until_ns.store(0, Ordering::Relaxed);
+            leadership.yields.fetch_add(1, Ordering::Relaxed);
+        }
+        self.term = self.term.max(heartbeat.term());
+        self.last_leader_ns = now_ns;
+        leadership.term.store(self.term, Ordering::Relaxed);
+    }
+
+    /// Once per heartbeat interval: take over if the leader has been silent
+    /// too long, renew the lease if active; returns the heartbeat to send.
+    pub fn tick(&mut self, now_ns: u64, leadership: &Leadership) -> Heartbeat {
+        let timeout_ns = self.config.timeout_us.saturating_mul(1_000);
+        if !self.active && now_ns.saturating_sub(self.last_leader_ns) >= timeout_ns {
+            self.active = true;
+            self.term += 1;
+            leadership.term.store(self.term, Ordering::Relaxed);
+            leadership.takeovers.fetch_add(1, Ordering::Relaxed);
+        }
+        if self.active {
+            leadership.lease_until_ns.store(
+                now_ns.saturating_add(timeout_ns / 2).max(1),
+                Ordering::Relaxed,
+            );
+        }
+        Heartbeat::new(self.config.node_id, self.term, self.active)
+    }
+}
+
+/// Start the election thread: until `stop` is set, it sends a heartbeat to
+/// the peer every `heartbeat_us` from `port`, takes in the peer's, and
+/// publishes the outcome to `leadership`. The thread runs on `core`.
+#[cfg(feature = "std")]
+pub fn spawn(
+    config: LeaderConfig,
+    leadership: &'static Leadership,
+    stop: &'static core::sync::atomic::AtomicBool,
+    core: crate::affinity::CoreSlot,
+) -> std::io::Result<std::thread::JoinHandle<()>> {
+    use crate::runtime::unix_time_ns;
+    let socket = std::net::UdpSocket::bind(("0.0.0.0", config.port))?;
+    let interval_ns = config.heartbeat_us.max(1) * 1_000;
+    socket.set_read_timeout(Some(std::time::Duration::from_nanos(interval_ns)))?;
+    let peer = (std::net::Ipv4Addr::from(config.peer.0), config.peer.1);
+    std::thread::Builder::new()
+        .name("leader".into())
+        .spawn(move || {
+            crate::affinity::pin_current_thread(core);
+            let mut election = Election::new(config, unix_time_ns(), leadership);
+            let (mut next_beat_ns, mut buf) = (0, [0u8; Heartbeat::WIRE_SIZE + 1]);
+            while !stop.load(Ordering::Relaxed) {
+                if let Ok((len, _)) = socket.recv_from(&mut buf) {
+                    if let Some(heartbeat) = Heartbeat::parse(&buf[..len]) {
+                        let was_active = election.is_active();
+                        election.on_heartbeat(heartbeat, unix_time_ns(), leadership);
+                        if was_active && !election.is_active() {
+                            log::warn!(
+                                "leader: standing down for node {} (term {})",
+                                heartbeat.node_id(),
+                                election.term()
+                            );
+                        }
+                    }
+                }
+                let now_ns = unix_time_ns();
+                if now_ns < next_beat_ns {
+                    continue;
+                }
+                next_beat_ns = now_ns + interval_ns;
+                let was_active = election.is_active();
+                let heartbeat = election.tick(now_ns, leadership);
+                if !was_active && election.is_active() {
+                    log::warn!("leader: taking over (term {})", election.term());
+                }
+                if let Err(e) = socket.send_to(bytemuck::bytes_of(&heartbeat), peer) {
+                    log::debug!("heartbeat not sent: {e}");
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_standby_takes_over_after_silence_and_ties_go_to_the_lower_id() {
+        const MS: u64 = 1_000_000;
+        let config = LeaderConfig::new(([127, 0, 0, 1], 9401));
+        let (a, b) = (Leadership::new(), Leadership::new());
+        assert!(a.is_active(|| unreachable!("no election, no clock")));
+        let mut first = Election::new(
+            LeaderConfig {
+                node_id: 1,
+                ..config
+            },
+            0,
+            &a,
+        );
+        let mut second = Election::new(
+            LeaderConfig {
+                node_id: 2,
+                ..config
+            },
+            0,
+            &b,
+        );
+        assert!(!a.is_active(|| 0), "standby until a timeout passes");
+
+        // Started together: both take over term 1, node 1 keeps it.
+        assert!(!first.tick(4 * MS, &a).is_active());
+        let (beat1, beat2) = (first.tick(5 * MS, &a), second.tick(5 * MS, &b));
+        assert!(beat1.is_active() && beat2.is_active());
+        first.on_heartbeat(&beat2, 5 * MS, &a);
+        second.on_heartbeat(&beat1, 5 * MS, &b);
+        assert!(a.is_active(|| 5 * MS) && !b.is_active(|| 5 * MS));
+        assert_eq!((b.term(), b.yields()), (1, 1));
+
+        // The lease runs out without renewal, before the standby's timeout.
+        assert!(a.is_active(|| 7 * MS) && !a.is_active(|| 15 * MS / 2));
+        let beat = first.tick(8 * MS, &a);
+        second.on_heartbeat(
+            Heartbeat::parse(bytemuck::bytes_of(&beat)).unwrap(),
+            8 * MS,
+            &b,
+        );
+        assert!(!second.tick(12 * MS, &b).is_active());
+        let beat = second.tick(13 * MS, &b);
+        assert!(beat.is_active() && beat.term() == 2, "took over term 2");
+        assert_eq!(b.takeovers(), 2);
+
+        // The old leader, back, defers to the newer term.
+        first.on_heartbeat(&beat, 14 * MS, &a);
+        assert!(!first.is_active() && !first.tick(15 * MS, &a).is_active());
+        assert!(!a.is_active(|| 15 * MS) && b.is_active(|| 15 * MS));
+    }
+}
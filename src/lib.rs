@@ -1,7 +1,125 @@
+//! With the default `std` feature off, only the allocation-free core is
+//! built — wire types, evaluator, validator and the tables they read —
+//! and the crate is `#![no_std]`, for embedding the hot path in other
+//! runtimes. The node itself (transport, AF_XDP, config, self-test) needs `std`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+pub mod ack;
+#[cfg(feature = "std")]
+pub mod admin;
+#[cfg(feature = "std")]
 pub mod affinity;
+#[cfg(feature = "std")]
+pub mod alloc_audit;
+pub mod anomaly;
+pub mod backpressure;
+#[cfg(feature = "std")]
+pub mod backtest;
+pub mod burst;
+#[cfg(feature = "std")]
+pub mod chaos;
+pub mod clock;
+pub mod coalesce;
+pub mod confidence;
+#[cfg(feature = "std")]
+pub mod config;
+pub mod conflate;
+#[cfg(feature = "std")]
+pub mod cores;
+pub mod dimensions;
+pub mod discovery;
+pub mod divergence;
+#[cfg(feature = "std")]
+pub mod doctor;
+#[cfg(feature = "std")]
+pub mod dpdk;
+pub mod emitted;
+pub mod endpoints;
+#[cfg(feature = "std")]
+pub mod error;
+pub mod fec;
+pub mod feed_race;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod flow_control;
+pub mod freshness;
+#[cfg(feature = "std")]
+pub mod handover;
+pub mod horizon;
+#[cfg(feature = "std")]
+pub mod idle;
+#[cfg(feature = "std")]
+pub mod journal;
+mod layout;
+pub mod leader;
+pub mod math;
+#[cfg(feature = "std")]
+pub mod metrics_shm;
+#[cfg(feature = "std")]
+pub mod mirror;
+#[cfg(feature = "std")]
+pub mod netlink;
+pub mod nonce;
+pub mod orderbook;
+pub mod pacing;
+#[cfg(feature = "parquet")]
+pub mod parquet_export;
 pub mod payload;
+#[cfg(feature = "std")]
+pub mod persist;
+#[cfg(feature = "std")]
+pub mod phc;
+pub mod pnl;
+#[cfg(feature = "std")]
+pub mod pool_dump;
+pub mod pool_registry;
+#[cfg(feature = "std")]
+pub mod pool_seed;
+pub mod ports;
+pub mod prices;
+pub mod privacy;
 pub mod processor;
-pub mod ring;
+#[cfg(feature = "std")]
+pub mod profiler;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod quarantine;
+pub mod recip;
+pub mod reorder;
+#[cfg(feature = "std")]
+pub mod replay_check;
+#[cfg(feature = "std")]
+pub mod reply_routes;
+pub mod reputation;
+pub mod rss;
 pub mod runtime;
+pub mod schema;
+#[cfg(feature = "std")]
+pub mod selftest;
+#[cfg(feature = "std")]
+pub mod session;
+pub mod sign;
+pub mod sim;
+pub mod skew;
+pub mod slo;
+pub mod source_filter;
+pub mod source_stats;
+#[cfg(feature = "std")]
+pub mod state_source;
+#[cfg(feature = "std")]
+pub mod statsd;
+#[cfg(feature = "std")]
+pub mod testvectors;
+pub mod threshold;
+pub mod timer;
+pub mod tokens;
+#[cfg(feature = "std")]
+pub mod transport;
 pub mod validator;
+#[cfg(feature = "std")]
+pub mod warmup;
+pub mod wire;
+#[cfg(feature = "std")]
 pub mod xdp;
+#[cfg(feature = "std")]
+pub mod xdp_filter;
@@ -1,67 +1,2371 @@
+/// Debug builds with `alloc_audit` trap allocations inside the packet loop.
+#[cfg(feature = "alloc_audit")]
+#[global_allocator]
+static ALLOCATOR: mev_zerocopy_node::alloc_audit::AuditAllocator =
+    mev_zerocopy_node::alloc_audit::AuditAllocator::new();
+
 #[cfg(any(target_os = "linux", target_os = "android"))]
 mod linux_node {
+    use mev_zerocopy_node::ack::{AckTracker, ReplyAck};
+    use mev_zerocopy_node::admin::{AdminServer, Command as AdminCommand};
     use mev_zerocopy_node::affinity;
-    use mev_zerocopy_node::processor;
-    use mev_zerocopy_node::ring::ResponseRing;
-    use mev_zerocopy_node::runtime::{LatencyClock, NodeStats};
-    use mev_zerocopy_node::xdp::{self, XdpConfig};
+    use mev_zerocopy_node::alloc_audit::{self, HotPath};
+    use mev_zerocopy_node::anomaly::{AnomalyDetector, SuppressedPools};
+    use mev_zerocopy_node::backpressure::{Backpressure, PressureGauge};
+    use mev_zerocopy_node::backtest::{Backtest, BacktestConfig, EventKind, Timeline};
+    use mev_zerocopy_node::burst::{BurstShaper, MAX_RX_BATCH};
+    use mev_zerocopy_node::chaos::FaultInjector;
+    use mev_zerocopy_node::clock::Clock;
+    use mev_zerocopy_node::coalesce::ReplyCoalescer;
+    use mev_zerocopy_node::confidence::ConfidenceSignals;
+    use mev_zerocopy_node::config::{Backend, NodeConfig};
+    use mev_zerocopy_node::conflate::{Conflated, Conflator};
+    use mev_zerocopy_node::cores::{CoreLayout, Role};
+    use mev_zerocopy_node::dimensions::ActiveDimensions;
+    use mev_zerocopy_node::discovery::{self, UnknownPools};
+    use mev_zerocopy_node::divergence::{DivergenceAlert, DivergenceTracker};
+    use mev_zerocopy_node::dpdk;
+    #[cfg(feature = "dpdk")]
+    use mev_zerocopy_node::dpdk::{DpdkPort, RxBurst};
+    use mev_zerocopy_node::emitted::EmittedRing;
+    use mev_zerocopy_node::error::{ErrorLog, NodeError};
+    use mev_zerocopy_node::fec::{FecDecoder, FecShardHeader, MAX_FEC_SHARD};
+    use mev_zerocopy_node::feed_race::FeedRace;
+    use mev_zerocopy_node::flow_control::{FlowPolicy, WindowMonitor};
+    use mev_zerocopy_node::handover::{
+        self, FdRole, FeedPosition, Handover, HandoverListener, HandoverOffer,
+    };
+    use mev_zerocopy_node::horizon::{HorizonRing, Pending};
+    use mev_zerocopy_node::idle::{IdleMode, Idler};
+    use mev_zerocopy_node::journal::{self, Journal};
+    use mev_zerocopy_node::leader::{self, Leadership};
+    use mev_zerocopy_node::metrics_shm;
+    use mev_zerocopy_node::mirror::Mirror;
+    use mev_zerocopy_node::netlink::{self, Netlink};
+    use mev_zerocopy_node::nonce::{Address, NonceTracker, Observation};
+    use mev_zerocopy_node::orderbook::{BookEntry, BookTable, BookUpdate};
+    use mev_zerocopy_node::pacing::{Pacer, PACE_QUEUE};
+    use mev_zerocopy_node::payload::{
+        CancelReason, DexSwapTx, DexSwapTxEvm, DexSwapTxExt, OpportunityReply,
+    };
+    use mev_zerocopy_node::persist::{ReplyHistory, StateFile, MAX_PERSISTED_POOLS, REPLY_HISTORY};
+    use mev_zerocopy_node::phc::PhcClock;
+    use mev_zerocopy_node::pool_dump::{self, PoolDump};
+    use mev_zerocopy_node::pool_registry::{PoolAddress, PoolEntry, PoolTable};
+    use mev_zerocopy_node::pool_seed;
+    use mev_zerocopy_node::prices::{PriceCache, PriceUpdate};
+    use mev_zerocopy_node::privacy::{PrivacyCommand, PrivacyFilter};
+    use mev_zerocopy_node::processor::{
+        self, Evaluator, Opportunity, Strategy, StrategyMask, FULL_WEIGHT_BPS,
+    };
+    use mev_zerocopy_node::profiler::{Stage, StageProfiler};
+    use mev_zerocopy_node::quarantine::{Malformed, Quarantine};
+    use mev_zerocopy_node::reorder::{ReorderWindow, MAX_REORDER_DEPTH};
+    use mev_zerocopy_node::replay_check::{Comparison, DecisionLog, Lockstep, Verdicts};
+    use mev_zerocopy_node::reply_routes::MessageKind;
+    use mev_zerocopy_node::reputation::{Outcome, ReputationTable, VictimOutcome};
+    #[cfg(feature = "dpdk")]
+    use mev_zerocopy_node::rss;
+    use mev_zerocopy_node::runtime::{self, unix_time_ns, LoopClock, NodeStats, SystemClock};
+    use mev_zerocopy_node::schema::VersionWindow;
+    use mev_zerocopy_node::selftest::{self, CalibrationConfig, CalibrationMode};
+    use mev_zerocopy_node::session::{FeedSession, SessionEvent, WelcomeStatus};
+    use mev_zerocopy_node::sim::Simulator;
+    use mev_zerocopy_node::slo::SloMonitor;
+    use mev_zerocopy_node::source_filter::SourceFilter;
+    use mev_zerocopy_node::state_source::{
+        SnapshotSource, SourceArbiter, SourceId, SourceKind, StateSource, Verdict, WIRE_FEED,
+    };
+    use mev_zerocopy_node::statsd;
+    use mev_zerocopy_node::threshold::{
+        AdaptiveThreshold, LatencyWindow, ThresholdInputs, WinRateWindow,
+    };
+    use mev_zerocopy_node::timer::TimerWheel;
+    use mev_zerocopy_node::tokens::{self, ProfitNormalizer};
+    use mev_zerocopy_node::transport::tap::{
+        CountingDevice, EgressOnly, RxDropCounter, TapConfig, TapDevice,
+    };
+    use mev_zerocopy_node::transport::xdp::XdpTransport;
+    use mev_zerocopy_node::transport::{
+        self as transport, FrameBatch, FrameMeta, Paced, RoutedUdpReply, Transport, UdpReply,
+    };
+    use mev_zerocopy_node::validator::{self, PoolStateUpdate, ValidationError};
+    use mev_zerocopy_node::warmup::Warmup;
+    use mev_zerocopy_node::xdp::{self, FrameAges, XdpConfig, XdpMode};
+    use mev_zerocopy_node::xdp_filter::{FilterSpec, XdpFilter, XskMap};
     use smoltcp::iface::{Config, Interface, SocketSet, SocketStorage};
-    use smoltcp::phy::{Medium, TunTapInterface};
-    use smoltcp::socket::tcp::{Socket as TcpSocket, SocketBuffer as TcpSocketBuffer};
+    use smoltcp::phy::{Device, Medium, TunTapInterface};
+    use smoltcp::socket::tcp::{
+        Socket as TcpSocket, SocketBuffer as TcpSocketBuffer, State as TcpState,
+    };
     use smoltcp::socket::udp::{
         PacketBuffer as UdpPacketBuffer, PacketMetadata as UdpPacketMetadata, Socket as UdpSocket,
     };
-    use smoltcp::time::Instant;
     use smoltcp::wire::{EthernetAddress, IpAddress, IpCidr, IpEndpoint};
+    use std::os::fd::{AsFd, AsRawFd, BorrowedFd, IntoRawFd, OwnedFd};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use zerocopy::{FromBytes, IntoBytes};
 
-    fn init_runtime() {
-        env_logger::init();
-        if affinity::pin_current_thread_to(0) {
-            log::info!("Pinned processing thread to CPU core 0");
+    /// Set by SIGINT/SIGTERM; the TAP loop exits and tears down what it set up.
+    static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+    /// Kill switch, flipped over the admin socket: no opportunities are
+    /// replied to while set.
+    static TRADING_HALTED: AtomicBool = AtomicBool::new(false);
+    /// Strategies switched on, also over the admin socket: a disabled one
+    /// is not evaluated.
+    static STRATEGIES: StrategyMask = StrategyMask::new(StrategyMask::ALL);
+    /// Whether this node or its hot/standby peer replies; this one, alone.
+    static LEADERSHIP: Leadership = Leadership::new();
+
+    extern "C" fn request_shutdown(_signal: libc::c_int) {
+        SHUTDOWN.store(true, Ordering::Relaxed);
+    }
+
+    /// Reference prices fed by `PriceUpdate` datagrams on the UDP port.
+    static PRICE_CACHE: PriceCache = PriceCache::new();
+
+    /// Pool table shared with strategy workers; written by the pool feed.
+    static POOL_TABLE: PoolTable = PoolTable::new();
+
+    /// Pools traded by the bundled traffic generator, seeded with `MOCK_POOL`
+    /// state until the pool feed delivers live reserves.
+    const SEED_POOLS: [[u8; 20]; 2] = [
+        [
+            0xa0, 0xb8, 0x69, 0x91, 0xc6, 0x21, 0x8b, 0x36, 0xc1, 0xd1, 0x9d, 0x4a, 0x2e, 0x9e,
+            0xb0, 0xce, 0x36, 0x06, 0xeb, 0x48,
+        ],
+        [
+            0xcb, 0xcd, 0xf9, 0x62, 0x6b, 0xc0, 0x3e, 0x24, 0xf7, 0x79, 0x43, 0x41, 0x78, 0xa7,
+            0x3a, 0x0b, 0x4b, 0xad, 0x62, 0xed,
+        ],
+    ];
+
+    /// Local UDP port the feed mirror sends from; clear of the default
+    /// port layout and its usual control port.
+    const MIRROR_PORT: u16 = 8089;
+    /// Local ports for relayer connections; a fresh one per attempt.
+    const RELAY_LOCAL_PORTS: core::ops::Range<u16> = 49152..65535;
+
+    /// Node counters; static so the statsd exporter thread can read them.
+    static NODE_STATS: NodeStats = NodeStats::new();
+
+    /// Errors the node carried on from, for the admin `errors` command.
+    static NODE_ERRORS: ErrorLog = ErrorLog::new();
+
+    /// Victim size threshold, recomputed once per second off the packet path.
+    static MIN_AMOUNT_IN: AdaptiveThreshold = AdaptiveThreshold::new(processor::MIN_AMOUNT_IN);
+
+    /// Feed age and win rate behind each opportunity's confidence score.
+    static CONFIDENCE: ConfidenceSignals = ConfidenceSignals::new();
+
+    /// How backed up the TX path is, for the RX stages to shed load by.
+    static BACKPRESSURE: Backpressure = Backpressure::new();
+
+    /// Pools victims trade that the table lacks; requested by the
+    /// discovery thread.
+    static UNKNOWN_POOLS: UnknownPools = UnknownPools::new();
+
+    /// Pools the anomaly detector keeps every strategy off.
+    static SUPPRESSED_POOLS: SuppressedPools = SuppressedPools::new();
+
+    /// Check `MEV_CORES` against the cores online, falling back to the
+    /// default layout if it does not fit, and pin the polling thread.
+    fn place_threads(config: &mut NodeConfig) {
+        let online = affinity::online_cores();
+        if !online.is_empty() {
+            if let Err(e) = config.cores.validate(&online) {
+                let default = CoreLayout::default();
+                log::warn!(
+                    "MEV_CORES {} does not fit this host ({e}), using {default}",
+                    config.cores
+                );
+                config.cores = default;
+            }
+        }
+        if affinity::pin_current_thread_to(config.cores.polling) {
+            log::info!(
+                "Pinned polling thread to CPU core {} (cores: {})",
+                config.cores.polling,
+                config.cores
+            );
         } else {
             log::warn!("CPU pinning failed or unavailable");
         }
-    }
-
-    fn backend_mode() -> &'static str {
-        match std::env::var("MEV_BACKEND") {
-            Ok(v) if v.eq_ignore_ascii_case("af_xdp") => "af_xdp",
-            _ => "tap",
+    }
+
+    /// Apply the source allow-list to a smoltcp endpoint (TAP mode).
+    fn source_allowed(filter: &SourceFilter, endpoint: IpEndpoint) -> bool {
+        match endpoint.addr {
+            IpAddress::Ipv4(v4) => filter.allows(v4.0, endpoint.port),
+            #[allow(unreachable_patterns)]
+            _ => filter.is_empty(),
+        }
+    }
+
+    /// Pool states the table starts from: those of `MEV_POOL_SEED_FILE`, or
+    /// the `SEED_POOLS` in `MOCK_POOL` state, with per-pool profit
+    /// thresholds applied.
+    fn seed_entries(config: &NodeConfig) -> Vec<PoolEntry> {
+        let mut entries = match &config.pool_seed_file {
+            None => SEED_POOLS.map(processor::mock_pool_entry).to_vec(),
+            Some(path) => match pool_seed::load(path) {
+                Ok(entries) => {
+                    log::info!("Seeding {} pool(s) from {path}", entries.len());
+                    entries
+                }
+                Err(e) => {
+                    report(NodeError::from(e).context(path.clone()), "no pools seeded");
+                    Vec::new()
+                }
+            },
+        };
+        for entry in entries.iter_mut() {
+            if let Some(&(_, min_profit)) = config
+                .pool_min_profit
+                .iter()
+                .find(|(a, _)| *a == entry.address)
+            {
+                *entry = entry.with_min_profit(min_profit);
+            }
+        }
+        for (address, _) in config
+            .pool_min_profit
+            .iter()
+            .filter(|(a, _)| !entries.iter().any(|e| e.address == *a))
+        {
+            log::warn!("MEV_POOL_MIN_PROFIT names unknown pool {address:02x?}, ignoring");
+        }
+        entries
+    }
+
+    /// Seed `table` with the `seeds` that `owns` accepts.
+    fn seed_pools(table: &PoolTable, seeds: &[PoolEntry], owns: impl Fn(&PoolAddress) -> bool) {
+        for &entry in seeds.iter().filter(|e| owns(&e.address)) {
+            if let Ok(Some(evicted)) = table.upsert(entry) {
+                log::warn!("pool table full while seeding; evicted {evicted:02x?}");
+            }
+        }
+    }
+
+    /// Validate a `PoolStateUpdate` and apply it to `table`.
+    ///
+    /// A sequence gap is counted and the update is applied anyway: the feed
+    /// carries full reserves, so the newest update resynchronizes the pool.
+    /// Each update is decoded by its own schema version, if `versions`
+    /// accepts it, and applied if `sources` admits it from `source`, judging
+    /// staleness by `clock`. Returns the updated pool's address, `None` for
+    /// a pool the table does not hold or an update another source overrides,
+    /// and the error for a malformed update. `replaced` is how many updates
+    /// of the pool were conflated into this one, so that they do not count
+    /// as a gap.
+    #[allow(clippy::too_many_arguments)]
+    fn apply_pool_update(
+        table: &PoolTable,
+        payload: &[u8],
+        versions: VersionWindow,
+        sources: &mut SourceArbiter,
+        clock: &impl Clock,
+        source: SourceId,
+        replaced: u32,
+        stats: &NodeStats,
+    ) -> Result<Option<PoolAddress>, ValidationError> {
+        let reject = |e: ValidationError| {
+            match e {
+                ValidationError::UnsupportedVersion(_) => stats.wire_version_rejects.inc(),
+                _ => stats.pool_update_rejects.inc(),
+            }
+            Err(e)
+        };
+        let Some(address) = payload.get(..20).and_then(|a| <[u8; 20]>::try_from(a).ok()) else {
+            return reject(ValidationError::TooShort);
+        };
+        let current = table.peek(&address);
+        let last_seq = current.filter(|e| e.source == source).map_or(0, |e| e.seq);
+        let last_seq = if last_seq == 0 {
+            0
+        } else {
+            last_seq.wrapping_add(replaced)
+        };
+        let update = match validator::validate_pool_update(payload, last_seq) {
+            Ok(update) => update,
+            Err(ValidationError::SequenceGap { expected, got }) => {
+                stats.pool_seq_gaps.inc();
+                log::debug!("pool feed gap: expected seq {expected}, got {got}");
+                match validator::validate_pool_update(payload, 0) {
+                    Ok(update) => update,
+                    Err(e) => return reject(e),
+                }
+            }
+            Err(e) => return reject(e),
+        };
+        let update = match validator::decode_pool_update(update, versions) {
+            Ok(update) => update,
+            Err(e) => return reject(e),
+        };
+        let now_ms = clock.now_unix_ms();
+        if current.is_some_and(|e| sources.admit(source, &e, update.slot, now_ms) != Verdict::Apply)
+        {
+            return Ok(None);
+        }
+        if table.apply_from(&address, &update, source) {
+            // The feed is as fresh as the update was when it was sent.
+            let sent_ms = update
+                .sent_ns
+                .map_or(now_ms, |ns| (ns / 1_000_000).min(now_ms));
+            CONFIDENCE.pool_updated(update.slot, sent_ms);
+            stats.pool_updates.inc();
+            if update.version >= 2 {
+                stats.pool_updates_v2.inc();
+            }
+            Ok(Some(address))
+        } else {
+            stats.pool_update_rejects.inc();
+            Ok(None)
+        }
+    }
+
+    /// Poll the pool-state sources besides the wire feed, dropping those
+    /// that are done or failing. `on_update` gets every pool they moved.
+    fn poll_state_sources(
+        sources: &mut Vec<(SourceId, Box<dyn StateSource>)>,
+        arbiter: &mut SourceArbiter,
+        clock: &impl Clock,
+        versions: VersionWindow,
+        stats: &NodeStats,
+        mut on_update: impl FnMut(PoolAddress),
+    ) {
+        sources.retain_mut(|(id, source)| {
+            let (mut applied, mut rejected) = (0u64, 0u64);
+            let polled = source.poll(&mut |record| match apply_pool_update(
+                &POOL_TABLE,
+                record,
+                versions,
+                arbiter,
+                clock,
+                *id,
+                0,
+                stats,
+            ) {
+                Ok(Some(pool)) => {
+                    applied += 1;
+                    on_update(pool);
+                }
+                Ok(None) => {}
+                Err(_) => rejected += 1,
+            });
+            if applied + rejected != 0 {
+                log::debug!(
+                    "{} source {}: applied {applied}, rejected {rejected}",
+                    source.kind().name(),
+                    source.name()
+                );
+            }
+            match polled {
+                Ok(more) => {
+                    if !more {
+                        log::info!("{} source {} done", source.kind().name(), source.name());
+                    }
+                    more
+                }
+                Err(e) => {
+                    let context = format!("{} source {}", source.kind().name(), source.name());
+                    report(NodeError::from(e).context(context), "dropped");
+                    false
+                }
+            }
+        });
+    }
+
+    /// Count a frame that failed parsing or validation and keep its head.
+    fn quarantine_frame(
+        quarantine: &mut Quarantine,
+        reason: Malformed,
+        frame: &FrameMeta,
+        port: u16,
+        payload: &[u8],
+        stats: &NodeStats,
+    ) {
+        stats.quarantined.inc();
+        stats.sources.source(frame.source.0).count_malformed();
+        quarantine.capture(reason, frame.rx_ts_ns, frame.source, port, payload);
+        log::debug!(
+            "quarantined {} bytes from {:?}: {reason}",
+            payload.len(),
+            frame.source
+        );
+    }
+
+    /// Apply a `PriceUpdate`, `VictimOutcome` or `ReplyAck` that arrived on
+    /// `port`; false if `payload` is none of them. A victim reported landed
+    /// books the live reply against it as realized, at `gas_cost`.
+    fn apply_control(
+        payload: &[u8],
+        frame: &FrameMeta,
+        port: u16,
+        tx_state: &mut TxState,
+        quarantine: &mut Quarantine,
+        stats: &NodeStats,
+        gas_cost: u64,
+    ) -> bool {
+        if let Some(update) = PriceUpdate::parse(payload) {
+            match PRICE_CACHE.apply(update) {
+                Ok(()) => stats.price_updates.inc(),
+                Err(e) => match Malformed::from_price(e) {
+                    Some(reason) => {
+                        quarantine_frame(quarantine, reason, frame, port, payload, stats)
+                    }
+                    None => log::debug!("price update rejected: {e:?}"),
+                },
+            }
+        } else if let Some(report) = VictimOutcome::parse(payload) {
+            if let Some(outcome) = report.outcome() {
+                tx_state.reputation.record(&report.sender, outcome);
+                stats.victim_outcomes.inc();
+                if outcome == Outcome::Landed {
+                    if let Some(landed) = tx_state.emitted.settle(&report.sender, report.nonce()) {
+                        stats.pnl.record_landed(landed.token, landed.edge, gas_cost);
+                    }
+                }
+            }
+        } else if let Some(ack) = ReplyAck::parse(payload) {
+            if let Some(acks) = tx_state.acks.as_mut() {
+                acks.ack(ack.nonce());
+                publish_acks(acks, stats);
+            }
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Replies tracked for executor acknowledgement.
+    const ACK_RING: usize = 1024;
+
+    type AckRing = AckTracker<ACK_RING>;
+
+    /// Copy the acknowledgement counts to the exported counters.
+    fn publish_acks(acks: &AckRing, stats: &NodeStats) {
+        stats.replies_acked.store(acks.counts.acked);
+        stats.replies_unacked.store(acks.counts.unacked);
+        stats.reply_retransmits.store(acks.counts.retransmitted);
+    }
+
+    /// Log an error the node carries on from, with what it costs, and keep
+    /// it for the admin `errors` command.
+    fn report(error: NodeError, consequence: &str) {
+        log::warn!("{error}; {consequence}");
+        NODE_ERRORS.record(&error);
+    }
+
+    /// Re-estimate the PHC offset that reply timestamps are shifted by. On
+    /// failure the last good offset stays in effect.
+    fn sync_wire_clock(phc: &PhcClock) {
+        match phc.estimate_offset() {
+            Ok(estimate) => {
+                runtime::set_wire_clock_offset_ns(estimate.offset_ns);
+                log::debug!(
+                    "PHC offset {}ns (±{}ns)",
+                    estimate.offset_ns,
+                    estimate.delay_ns / 2
+                );
+            }
+            Err(e) => report(NodeError::from(e).context("PHC offset"), "last offset kept"),
+        }
+    }
+
+    /// Install executor routes/neighbors and the root qdisc on the reply
+    /// interface (`MEV_REPLY_IFACE`, else `default_iface`). Failures are
+    /// logged: replies still go out, just without the pinned path.
+    fn configure_reply_path(config: &NodeConfig, default_iface: Option<&str>) {
+        if config.executors.is_empty() && config.reply_qdisc.is_none() {
+            return;
+        }
+        let Some(iface) = config.reply_iface.as_deref().or(default_iface) else {
+            log::warn!(
+                "MEV_EXECUTORS/MEV_REPLY_QDISC set but no reply interface; set MEV_REPLY_IFACE"
+            );
+            return;
+        };
+        let setup = netlink::if_index(iface).and_then(|index| Ok((index, Netlink::open()?)));
+        let (index, mut nl) = match setup {
+            Ok(setup) => setup,
+            Err(e) => {
+                report(
+                    NodeError::from(e).context(format!("reply path on {iface}")),
+                    "not configured",
+                );
+                return;
+            }
+        };
+        for executor in &config.executors {
+            let [a, b, c, d] = executor.addr;
+            match nl.add_executor(index, executor) {
+                Ok(()) => log::info!(
+                    "executor {a}.{b}.{c}.{d} pinned to {:02x?} on {iface}",
+                    executor.mac
+                ),
+                Err(e) => {
+                    let e = NodeError::from(e).context(format!(
+                        "executor {a}.{b}.{c}.{d} route/neighbor on {iface}"
+                    ));
+                    report(e, "not pinned");
+                }
+            }
+        }
+        if let Some(qdisc) = config.reply_qdisc {
+            match nl.set_root_qdisc(index, qdisc) {
+                Ok(()) => log::info!("root qdisc on {iface} set to {qdisc:?}"),
+                Err(e) => report(
+                    NodeError::from(e).context(format!("root qdisc {qdisc:?} on {iface}")),
+                    "left as is",
+                ),
+            }
+        }
+    }
+
+    /// Pool-feed frames after source filtering, FEC decoding and reordering:
+    /// everything received this poll (up to twice over, when each shard
+    /// also completes a group) plus whatever the window releases on top.
+    type OrderedPoolBatch = FrameBatch<
+        { 2 * 32 + MAX_REORDER_DEPTH },
+        { 2 * 16 * 1024 + MAX_REORDER_DEPTH * PoolStateUpdate::WIRE_SIZE },
+    >;
+
+    /// Conflation plan of one `OrderedPoolBatch`.
+    type PoolConflator = Conflator<{ 2 * 32 + MAX_REORDER_DEPTH }>;
+
+    /// The pool and sequence number a pool-feed message is conflated by,
+    /// `None` for book updates and anything that is not a pool update.
+    fn conflation_key(payload: &[u8]) -> Option<(PoolAddress, u32)> {
+        if BookUpdate::parse(payload).is_some() {
+            return None;
+        }
+        let update =
+            PoolStateUpdate::ref_from_bytes(payload.get(..PoolStateUpdate::WIRE_SIZE)?).ok()?;
+        Some((update.pool_address, update.seq()))
+    }
+
+    /// Coded groups decoded at once.
+    type PoolFec = FecDecoder<64, MAX_FEC_SHARD>;
+
+    /// Drop frames from sources outside the allow-list, unwrap FEC shards,
+    /// note which feed delivered each pool update first, move send times
+    /// onto our clock and, with a reorder window, put pool updates back
+    /// into per-pool sequence.
+    fn order_pool_batch(
+        input: &FrameBatch,
+        output: &mut OrderedPoolBatch,
+        config: &NodeConfig,
+        mut reorder: Option<&mut ReorderWindow<MAX_REORDER_DEPTH>>,
+        mut fec: Option<&mut PoolFec>,
+        race: &mut FeedRace,
+        stats: &NodeStats,
+    ) {
+        output.clear();
+        let now = runtime::now_cycles();
+        let correct = config.feed_clock_correction;
+        for (frame, payload) in input.iter() {
+            let (addr, port) = frame.source;
+            if !config.allowed_sources.allows(addr, port) {
+                stats.rejected_sources.inc();
+                continue;
+            }
+            stats.sources.source(addr).count_rx();
+            match (fec.as_deref_mut(), FecShardHeader::parse(payload)) {
+                (Some(decoder), Some((header, shard))) => decoder.offer(header, shard, |message| {
+                    order_pool_message(
+                        message,
+                        frame,
+                        output,
+                        reorder.as_deref_mut(),
+                        race,
+                        now,
+                        correct,
+                    );
+                }),
+                _ => order_pool_message(
+                    payload,
+                    frame,
+                    output,
+                    reorder.as_deref_mut(),
+                    race,
+                    now,
+                    correct,
+                ),
+            }
+        }
+        if let Some(window) = reorder {
+            let rx_ts_ns = unix_time_ns();
+            window.expire(now, |u| {
+                output.push(u.as_bytes(), rx_ts_ns, ([0; 4], 0));
+            });
+        }
+    }
+
+    /// One pool-feed message of [`order_pool_batch`], received in `frame`.
+    /// With `correct`, a version 3 update goes on with its send time on our
+    /// clock.
+    fn order_pool_message(
+        payload: &[u8],
+        frame: &FrameMeta,
+        output: &mut OrderedPoolBatch,
+        reorder: Option<&mut ReorderWindow<MAX_REORDER_DEPTH>>,
+        race: &mut FeedRace,
+        now: u64,
+        correct: bool,
+    ) {
+        let mut update = PoolStateUpdate::read_from_bytes(payload).ok();
+        if let Some(update) = &mut update {
+            race.observe(
+                frame.source,
+                &update.pool_address,
+                update.seq(),
+                frame.rx_cycles,
+            );
+            if update.version() >= 3 && update.sent_ns() != 0 {
+                let sent_ns = race.correct_sent(frame.source, update.sent_ns(), frame.rx_ts_ns);
+                if correct {
+                    *update = update.with_sent_ns(sent_ns);
+                }
+            }
+        }
+        match (reorder, update) {
+            (Some(window), Some(update)) => {
+                let last_seq = POOL_TABLE.peek(&update.pool_address).map_or(0, |e| e.seq);
+                window.offer(&update, last_seq, now, |u| {
+                    output.push(u.as_bytes(), frame.rx_ts_ns, frame.source);
+                });
+            }
+            (None, Some(update)) => {
+                output.push(update.as_bytes(), frame.rx_ts_ns, frame.source);
+            }
+            _ => {
+                output.push(payload, frame.rx_ts_ns, frame.source);
+            }
+        }
+    }
+
+    /// Check one market's book against its reference pool. Book arbitrage has
+    /// no requester to reply to: hits are counted, and settled in dry runs.
+    fn check_book_arb(
+        evaluator: &Evaluator,
+        market: &PoolAddress,
+        entry: &BookEntry,
+        stats: &NodeStats,
+        sim: Option<&mut Simulator>,
+        config: &NodeConfig,
+        rx_ts_ns: u64,
+    ) {
+        if !STRATEGIES.is_enabled(Strategy::BookTakerArb) {
+            stats.strategy_skips.inc();
+            return;
+        }
+        let Some(pool) = POOL_TABLE.get(&entry.ref_pool) else {
+            return;
+        };
+        let Some((arb, profit)) = evaluator.evaluate_book(&entry.book, &pool) else {
+            return;
+        };
+        stats.book_arbs.inc();
+        stats.record_profit(Strategy::BookTakerArb, profit);
+        stats
+            .pnl
+            .record_edge(pool.token1, arb.quote_profit, config.gas_cost);
+        log::debug!(
+            "book arb on {market:02x?}: {:?} {} base, profit {profit}",
+            arb.side,
+            arb.base_amount
+        );
+        if let Some(sim) = sim {
+            let latency_ns =
+                unix_time_ns().saturating_sub(rx_ts_ns) + config.execution_latency_ms * 1_000_000;
+            if sim.settle(
+                Strategy::BookTakerArb,
+                *market,
+                profit,
+                config.gas_cost,
+                latency_ns,
+            ) {
+                stats
+                    .pnl
+                    .record_landed(pool.token1, arb.quote_profit, config.gas_cost);
+            }
+        }
+    }
+
+    /// The swap, price and pool-feed pipeline of the kernel-bypass backends,
+    /// on one received Ethernet frame. `checksums` verifies the IPv4 and UDP
+    /// checksums in software; `misrouted` flags pools another worker owns.
+    /// Returns the reply owed, as (victim nonce, opportunity, pool), if
+    /// replies are on.
+    #[allow(clippy::too_many_arguments)]
+    fn ingest_frame(
+        frame: &[u8],
+        config: &NodeConfig,
+        checksums: bool,
+        (table, evaluator): (&PoolTable, &Evaluator),
+        sources: &mut SourceArbiter,
+        clock: &impl Clock,
+        misrouted: impl Fn(&PoolAddress) -> bool,
+        stats: &NodeStats,
+    ) -> Option<(u64, Opportunity, PoolAddress)> {
+        if !config.allowed_sources.allows_frame(frame) {
+            stats.rejected_sources.inc();
+            return None;
+        }
+        let udp = dpdk::udp_payload(frame);
+        if udp.is_some() && checksums && !dpdk::checksums_ok(frame) {
+            stats.rx_checksum_errors.inc();
+            return None;
+        }
+        let ports = config.ports;
+        let reply = match udp? {
+            (dst, payload) if dst == ports.pools => {
+                if payload
+                    .get(..20)
+                    .and_then(|a| <&PoolAddress>::try_from(a).ok())
+                    .is_some_and(&misrouted)
+                {
+                    stats.rss_misroutes.inc();
+                } else {
+                    let _ = apply_pool_update(
+                        table,
+                        payload,
+                        config.wire_versions,
+                        sources,
+                        clock,
+                        WIRE_FEED,
+                        0,
+                        stats,
+                    );
+                }
+                return None;
+            }
+            (dst, payload) if dst == ports.control && ports.control_split() => {
+                stats.rx_packets.inc();
+                if PriceUpdate::parse(payload)
+                    .is_some_and(|update| PRICE_CACHE.apply(update).is_ok())
+                {
+                    stats.price_updates.inc();
+                }
+                return None;
+            }
+            (dst, payload) if dst == ports.swaps => {
+                stats.rx_packets.inc();
+                if let Some(update) = PriceUpdate::parse(payload) {
+                    if PRICE_CACHE.apply(update).is_ok() {
+                        stats.price_updates.inc();
+                    }
+                    return None;
+                }
+                if DexSwapTx::header(payload).is_some_and(|tx| misrouted(&tx.pool_address)) {
+                    stats.rss_misroutes.inc();
+                    return None;
+                }
+                let enabled = STRATEGIES.is_enabled(Strategy::Sandwich);
+                if !enabled {
+                    stats.strategy_skips.inc();
+                }
+                let scored = enabled
+                    .then(|| evaluator.score_packet(payload, FULL_WEIGHT_BPS))
+                    .flatten();
+                evaluator.infer_reserves(payload);
+                let opportunity = scored?;
+                stats.opportunities.inc();
+                stats.record_profit(Strategy::Sandwich, opportunity.profit);
+                stats
+                    .pnl
+                    .record_edge(opportunity.token, opportunity.edge, config.gas_cost);
+                let tx = DexSwapTx::header(payload)?;
+                (tx.nonce(), opportunity, tx.pool_address)
+            }
+            _ => return None,
+        };
+        if config.sim.is_some() {
+            return None;
+        }
+        if TRADING_HALTED.load(Ordering::Relaxed) {
+            stats.halted_replies.inc();
+            return None;
+        }
+        if !LEADERSHIP.is_active(unix_time_ns) {
+            stats.standby_replies.inc();
+            return None;
+        }
+        Some(reply)
+    }
+
+    /// DPDK backend: one lcore per queue runs the swap, price and pool-feed
+    /// pipeline on its own RX bursts and replies from the received mbuf. RSS
+    /// keeps each flow on one queue, so every feed still has a single writer.
+    /// Order books, RBF tracking and cancellations stay on the smoltcp path.
+    #[cfg(feature = "dpdk")]
+    fn run_dpdk(
+        config: &NodeConfig,
+        evaluator: &Evaluator,
+        seeds: &[PoolEntry],
+        stats: &NodeStats,
+        cycles_per_us: u64,
+    ) {
+        let port = match DpdkPort::init(&config.dpdk) {
+            Ok(port) => port,
+            Err(e) => {
+                log::error!("DPDK backend failed to start: {}", NodeError::from(e));
+                return;
+            }
+        };
+        log::info!(
+            "DPDK port {} running {} queue(s)",
+            config.dpdk.port_id,
+            port.queues()
+        );
+        if config.sim.is_some() {
+            log::info!("Dry run: DPDK replies are counted, not sent");
+        }
+        let (reta_size, queues) = (port.reta_size(), port.queues());
+        // Sharded: one private table per queue, holding the pools RSS
+        // assigns to it. Built here, before any worker runs.
+        let shards: Option<Vec<&'static PoolTable>> = config.dpdk.shard_pools.then(|| {
+            log::info!("Pool table sharded across {queues} queue(s) by RSS hash (indirection table: {reta_size})");
+            (0..queues)
+                .map(|queue| {
+                    let table: &'static PoolTable = Box::leak(Box::new(PoolTable::new()));
+                    seed_pools(table, seeds, |pool| rss::pool_queue(pool, reta_size, queues) == queue);
+                    table
+                })
+                .collect()
+        });
+        let worker = |queue: u16| {
+            let (table, evaluator) = match &shards {
+                // Discovery checks `POOL_TABLE`, which shards leave empty.
+                Some(tables) => {
+                    let table = tables[queue as usize];
+                    (
+                        table,
+                        Evaluator {
+                            unknown_pools: None,
+                            ..evaluator.with_pools(table)
+                        },
+                    )
+                }
+                None => (&POOL_TABLE, *evaluator),
+            };
+            // Traffic for another queue's pools: the sender's steering is off.
+            let misrouted = |pool: &PoolAddress| {
+                shards.is_some() && rss::pool_queue(pool, reta_size, queues) != queue
+            };
+            // The wire feed is this worker's only source.
+            let mut sources = config.state_sources.arbiter();
+            let clock = SystemClock::new(cycles_per_us);
+            let mut burst = RxBurst::new();
+            let mut shaper = BurstShaper::new(config.rx_batch);
+            let mut idler = Idler::new(config.idle, cycles_per_us);
+            loop {
+                let rx_ts_ns = unix_time_ns();
+                let _hot = HotPath::enter();
+                let frames = port.rx_burst(queue, &mut burst, shaper.size());
+                // A full burst is taken as a sign of more queued behind it.
+                let received = frames.len();
+                if received != 0 {
+                    stats.rx_batch_sizes.record(shaper.size());
+                }
+                shaper.observe(received, received == shaper.size());
+                let waited = idler.idle(received != 0, |deadline| port.wait_rx(queue, deadline));
+                if waited != 0 {
+                    stats.idle_waits.inc();
+                    stats.idle_wait_cycles.add(waited);
+                }
+                for mut frame in frames {
+                    let pools = (table, &evaluator);
+                    let checksums = config.dpdk.rx_checksum;
+                    let Some((nonce, opportunity, pool)) = ingest_frame(
+                        frame.data(),
+                        config,
+                        checksums,
+                        pools,
+                        &mut sources,
+                        &clock,
+                        misrouted,
+                        stats,
+                    ) else {
+                        continue;
+                    };
+                    let mut routed = config
+                        .reply_routes
+                        .lookup(Strategy::Sandwich, &pool, MessageKind::Reply)
+                        .peekable();
+                    if routed.peek().is_none() {
+                        if dpdk::turn_around(frame.headers_mut(), OpportunityReply::WIRE_SIZE)
+                            && frame.send_reply(nonce, opportunity, rx_ts_ns).is_some()
+                            && port.send(queue, frame)
+                        {
+                            stats.tx_packets.inc();
+                        }
+                        continue;
+                    }
+                    // The received mbuf carries the first copy; further
+                    // destinations get fresh ones, all with pre-built headers.
+                    let mut received = Some(frame);
+                    for dest in routed {
+                        let Some(mut out) = received.take().or_else(|| port.alloc_frame()) else {
+                            break;
+                        };
+                        if out.send_reply(nonce, opportunity, rx_ts_ns).is_some() {
+                            out.headers_mut()
+                                .copy_from_slice(dest.headers(MessageKind::Reply));
+                            if port.send(queue, out) {
+                                stats.tx_packets.inc();
+                            }
+                        }
+                    }
+                }
+            }
+        };
+        if let Err(e) = port.run_per_queue(&worker) {
+            log::error!("DPDK workers not launched: {}", NodeError::from(e));
+        }
+    }
+
+    /// AF_XDP backend: the swap, price and pool-feed pipeline runs on the
+    /// socket's RX bursts, read in place in the UMEM, and replies go out of
+    /// the received frame. As on the DPDK path, order books, RBF tracking and
+    /// cancellations stay on the smoltcp path. Runs until shutdown; an error
+    /// means the socket or its redirect program could not be set up.
+    fn run_af_xdp(
+        config: &NodeConfig,
+        xdp: XdpConfig,
+        evaluator: &Evaluator,
+        stats: &'static NodeStats,
+        cycles_per_us: u64,
+    ) -> Result<(), NodeError> {
+        let umem = ActiveDimensions::umem_config();
+        let ages =
+            FrameAges::new(&umem, config.xdp_max_frame_age_cycles).with_dwell(&stats.frame_dwell);
+        let mut transport = XdpTransport::open(xdp, umem, ages)
+            .map_err(|e| NodeError::from(e).context("AF_XDP socket"))?;
+        // Frames only reach the socket through a program redirecting them
+        // into it: the pre-filter's classes, or any datagram to the node's
+        // ports. The rest of the interface's traffic goes on to the kernel.
+        let map =
+            XskMap::create(xdp.queue_id + 1).map_err(|e| NodeError::from(e).context("XSKMAP"))?;
+        map.insert(xdp.queue_id, transport.fd())
+            .map_err(|e| NodeError::from(e).context("XSKMAP"))?;
+        let spec = match config.xdp_prefilter {
+            true => FilterSpec::node_default(config.ports),
+            false => FilterSpec::node_ports(config.ports),
+        };
+        let _redirect = XdpFilter::attach(
+            &spec,
+            xdp.interface.as_str(),
+            xdp.mode == XdpMode::Native,
+            Some(&map),
+        )
+        .map_err(|e| NodeError::from(e).context("XDP redirect program"))?;
+        log::info!(
+            "AF_XDP loop running on {} queue {}",
+            xdp.interface,
+            xdp.queue_id
+        );
+        if config.sim.is_some() {
+            log::info!("Dry run: AF_XDP replies are counted, not sent");
+        }
+        let mut sources = config.state_sources.arbiter();
+        let clock = SystemClock::new(cycles_per_us);
+        let mut shaper = BurstShaper::new(config.rx_batch);
+        let mut idler = Idler::new(config.idle, cycles_per_us);
+        unsafe {
+            libc::signal(
+                libc::SIGINT,
+                request_shutdown as *const () as libc::sighandler_t,
+            );
+            libc::signal(
+                libc::SIGTERM,
+                request_shutdown as *const () as libc::sighandler_t,
+            );
+        }
+        while !SHUTDOWN.load(Ordering::Relaxed) {
+            let rx_ts_ns = unix_time_ns();
+            let _hot = HotPath::enter();
+            let received = transport.rx_burst(shaper.size(), runtime::now_cycles());
+            if received != 0 {
+                stats.rx_batch_sizes.record(shaper.size());
+            }
+            shaper.observe(received, received == shaper.size());
+            // The RX ring cannot be monitored: idle waits are timed.
+            let waited = idler.idle(received != 0, |_| false);
+            if waited != 0 {
+                stats.idle_waits.inc();
+                stats.idle_wait_cycles.add(waited);
+            }
+            for i in 0..received {
+                if transport.too_old(i, runtime::now_cycles()) {
+                    stats.freshness_drops.inc();
+                    continue;
+                }
+                let (frame, _) = transport.received(i);
+                let Some((nonce, opportunity, pool)) = ingest_frame(
+                    frame,
+                    config,
+                    true,
+                    (&POOL_TABLE, evaluator),
+                    &mut sources,
+                    &clock,
+                    |_| false,
+                    stats,
+                ) else {
+                    continue;
+                };
+                let mut routed = config
+                    .reply_routes
+                    .lookup(Strategy::Sandwich, &pool, MessageKind::Reply)
+                    .peekable();
+                if routed.peek().is_none() {
+                    let mut out = transport.reply_in_place(i);
+                    let sent = out.send_reply(nonce, opportunity, rx_ts_ns).is_some() && {
+                        let len = out.payload_len();
+                        dpdk::turn_around(out.headers_mut(), len)
+                    };
+                    let desc = out.descriptor();
+                    if transport.release(desc, sent, runtime::now_cycles()) {
+                        stats.tx_packets.inc();
+                    }
+                    continue;
+                }
+                // The received frame carries the first copy; further
+                // destinations get spare frames, all with pre-built headers.
+                for (copy, dest) in routed.enumerate() {
+                    let mut out = match copy {
+                        0 => transport.reply_in_place(i),
+                        _ => match transport.spare() {
+                            Some(out) => out,
+                            None => break,
+                        },
+                    };
+                    let sent = out.send_reply(nonce, opportunity, rx_ts_ns).is_some();
+                    if sent {
+                        out.headers_mut()
+                            .copy_from_slice(dest.headers(MessageKind::Reply));
+                    }
+                    let desc = out.descriptor();
+                    if transport.release(desc, sent, runtime::now_cycles()) {
+                        stats.tx_packets.inc();
+                    }
+                }
+            }
+            transport.finish_burst(runtime::now_cycles());
+        }
+        log::info!(
+            "AF_XDP loop stopped; {} stale frames rejected",
+            transport.stale()
+        );
+        Ok(())
+    }
+
+    /// Victim tracking slots; a power of two.
+    const VICTIM_SLOTS: usize = 1024;
+
+    /// RBF screening result for one swap payload.
+    #[derive(Clone, Copy)]
+    enum Screened {
+        /// Not an EVM payload; no nonce to track.
+        Untracked,
+        /// EVM victim worth evaluating. `cancel_previous` if it replaces one
+        /// an opportunity was already emitted against; `weight_bps` is the
+        /// sender's hit rate (0 if only the cancellation is still owed).
+        /// `replacement` if it replaces a pending victim seen before.
+        Victim {
+            sender: Address,
+            nonce: u64,
+            cancel_previous: bool,
+            replacement: bool,
+            weight_bps: u32,
+        },
+        /// Underpriced replacement, nonce gap or low-reputation sender: not
+        /// evaluated.
+        Skip,
+    }
+
+    fn screen_victim(state: &mut TxState, payload: &[u8], stats: &NodeStats) -> Screened {
+        let Some(tx) = DexSwapTxEvm::parse(payload) else {
+            return Screened::Untracked;
+        };
+        let (cancel_previous, replacement) =
+            match state.nonces.observe(&tx.sender, tx.nonce(), tx.gas_price()) {
+                Observation::Fresh => (false, false),
+                Observation::Replacement { cancel } => {
+                    stats.victim_replacements.inc();
+                    (cancel, true)
+                }
+                Observation::Underpriced | Observation::NonceGap { .. } => {
+                    stats.victims_skipped.inc();
+                    return Screened::Skip;
+                }
+            };
+        // Privately routed victims are skipped like low-reputation senders:
+        // still tracked, so a replacement cancels what was emitted.
+        let weight_bps = if state.privacy.flag(tx).is_some() {
+            stats.privacy_skips.inc();
+            0
+        } else {
+            state.reputation.weight_bps(&tx.sender).unwrap_or_else(|| {
+                stats.reputation_skips.inc();
+                0
+            })
+        };
+        if weight_bps == 0 && !cancel_previous {
+            return Screened::Skip;
+        }
+        Screened::Victim {
+            sender: tx.sender,
+            nonce: tx.nonce(),
+            cancel_previous,
+            replacement,
+            weight_bps,
+        }
+    }
+
+    fn evaluate_victim(
+        evaluator: &Evaluator,
+        clock: &impl Clock,
+        screened: Screened,
+        payload: &[u8],
+        stats: &NodeStats,
+    ) -> Option<Opportunity> {
+        let enabled = STRATEGIES.is_enabled(Strategy::Sandwich);
+        let scored = match screened {
+            _ if !enabled => {
+                stats.strategy_skips.inc();
+                None
+            }
+            Screened::Untracked => evaluator.score_packet_on(clock, payload, FULL_WEIGHT_BPS),
+            Screened::Victim { weight_bps: 0, .. } | Screened::Skip => None,
+            Screened::Victim { weight_bps, .. } => {
+                evaluator.score_packet_on(clock, payload, weight_bps)
+            }
+        };
+        // Inferred after pricing: the sandwich goes in ahead of the victim.
+        // A replacement's swap was inferred with the victim it replaces, and
+        // a sender that rarely lands is not counted on to.
+        if matches!(
+            screened,
+            Screened::Untracked
+                | Screened::Victim {
+                    replacement: false,
+                    weight_bps: 1..,
+                    ..
+                }
+        ) {
+            evaluator.infer_reserves(payload);
+        }
+        scored
+    }
+
+    /// Where a reply went, so a later cancellation can follow it.
+    #[derive(Clone, Copy)]
+    enum ReplyRoute {
+        Udp(IpEndpoint),
+        /// Only valid while the same peer is connected.
+        Tcp(IpEndpoint),
+    }
+
+    /// Recently emitted opportunities kept for cancellation.
+    const EMITTED_RING: usize = 256;
+
+    /// Victim record kept for re-evaluation: the extended record if the
+    /// payload carries one, otherwise the base record without a deadline.
+    fn victim_record(payload: &[u8]) -> Option<DexSwapTxExt> {
+        match DexSwapTxExt::parse(payload) {
+            Some(ext) => Some(*ext),
+            None => DexSwapTx::header(payload).map(|base| DexSwapTxExt::from_parts(*base, 0)),
+        }
+    }
+
+    /// Victims parked for the opportunity horizon.
+    const HORIZON_RING: usize = 64;
+
+    type Horizon = HorizonRing<ReplyRoute, HORIZON_RING>;
+
+    /// Park a victim that was screened in but did not pay, so that later
+    /// slots price it again; a replacement takes the place of the victim it
+    /// replaces, or drops it if it pays itself.
+    fn park_victim(
+        horizon: &mut Horizon,
+        screened: Screened,
+        victim: Option<DexSwapTxExt>,
+        scored: Option<Opportunity>,
+        route: ReplyRoute,
+    ) {
+        let (sender, weight_bps) = match screened {
+            Screened::Untracked => ([0; 20], FULL_WEIGHT_BPS),
+            Screened::Victim {
+                sender,
+                nonce,
+                weight_bps,
+                ..
+            } => {
+                horizon.forget(&sender, nonce);
+                (sender, weight_bps)
+            }
+            Screened::Skip => return,
+        };
+        if let (Some(victim), None, 1..) = (victim, scored, weight_bps) {
+            if STRATEGIES.is_enabled(Strategy::Sandwich) {
+                horizon.park(victim, sender, weight_bps, route);
+            }
+        }
+    }
+
+    /// Reply-side state: what was emitted, to whom, and how fast.
+    struct TxState {
+        /// RX stamp → TX enqueue, in ns.
+        wire_window: LatencyWindow<1024>,
+        nonces: NonceTracker<VICTIM_SLOTS>,
+        /// Victim sender hit rates, from executor `VictimOutcome` reports.
+        reputation: ReputationTable<VICTIM_SLOTS>,
+        privacy: PrivacyFilter,
+        emitted: EmittedRing<ReplyRoute, EMITTED_RING>,
+        /// Victims replied to, carried across restarts by the state file.
+        replies: ReplyHistory<REPLY_HISTORY>,
+        /// Replies awaiting an executor `ReplyAck`, if acknowledgements are on.
+        acks: Option<Box<AckRing>>,
+    }
+
+    impl TxState {
+        /// Send the cancellation a replacement calls for, then the reply for
+        /// a profitable victim (`opportunity`: its record and scored profit),
+        /// on the transport the victim arrived on. Returns the reply sent,
+        /// for the decision log.
+        fn emit(
+            &mut self,
+            tx: &mut impl Transport,
+            route: ReplyRoute,
+            screened: Screened,
+            opportunity: Option<(DexSwapTxExt, Opportunity)>,
+            rx_ts_ns: u64,
+            stats: &NodeStats,
+        ) -> Option<OpportunityReply> {
+            if let Screened::Victim {
+                sender,
+                nonce,
+                cancel_previous: true,
+                ..
+            } = screened
+            {
+                if tx.send_cancel(sender, nonce, CancelReason::Replaced) {
+                    stats.cancellations.inc();
+                    self.emitted.retire(&sender, nonce);
+                }
+            }
+            let (record, opportunity) = opportunity?;
+            let sender = match screened {
+                Screened::Victim { sender, .. } => sender,
+                _ => [0; 20],
+            };
+            if self
+                .replies
+                .replied_before_restart(&sender, record.base.nonce())
+            {
+                stats.duplicate_replies.inc();
+                return None;
+            }
+            if TRADING_HALTED.load(Ordering::Relaxed) {
+                stats.halted_replies.inc();
+                return None;
+            }
+            if !LEADERSHIP.is_active(unix_time_ns) {
+                stats.standby_replies.inc();
+                return None;
+            }
+            let Some(wire_ns) = tx.send_reply(record.base.nonce(), opportunity, rx_ts_ns) else {
+                stats.tx_full.inc();
+                return None;
+            };
+            stats.tx_packets.inc();
+            self.wire_window.record(wire_ns);
+            if let Screened::Victim { nonce, .. } = screened {
+                self.nonces.mark_emitted(&sender, nonce);
+            }
+            self.replies.record(sender, record.base.nonce());
+            self.emitted.push(record, sender, opportunity, route);
+            if let Some(acks) = self.acks.as_mut() {
+                acks.sent(
+                    record.base.nonce(),
+                    opportunity,
+                    rx_ts_ns,
+                    unix_time_ns() / 1_000_000,
+                );
+            }
+            Some(OpportunityReply {
+                nonce_le: record.base.nonce_le,
+                profit_le: opportunity.profit.to_le_bytes(),
+                rx_ts_ns_le: rx_ts_ns.to_le_bytes(),
+                tx_ts_ns_le: (rx_ts_ns + wire_ns).to_le_bytes(),
+                confidence_bps_le: opportunity.confidence_bps.to_le_bytes(),
+                _reserved: [0; 6],
+            })
+        }
+    }
+
+    /// Open the state file (or map the one handed over) and apply its
+    /// snapshot: pool reserves and sequences to the pool table, and the
+    /// reply history it returns.
+    fn restore_state(
+        path: &str,
+        inherited: Option<OwnedFd>,
+    ) -> (Option<StateFile>, ReplyHistory<REPLY_HISTORY>) {
+        let file = match inherited.map_or_else(|| StateFile::open(path), StateFile::from_fd) {
+            Ok(file) => file,
+            Err(e) => {
+                report(
+                    NodeError::from(e).context(path.to_string()),
+                    "state is not persisted",
+                );
+                return (None, ReplyHistory::new());
+            }
+        };
+        let Some(restored) = file.load() else {
+            log::info!("State file {path} has no snapshot yet");
+            return (Some(file), ReplyHistory::new());
+        };
+        let applied = restored
+            .pools
+            .iter()
+            .filter(|u| {
+                POOL_TABLE.update_reserves(
+                    &u.pool_address,
+                    u.reserve0(),
+                    u.reserve1(),
+                    u.slot(),
+                    u.seq(),
+                )
+            })
+            .count();
+        log::info!(
+            "Restored {applied}/{} pool sequences and {} replies ({} emitted) from {path}",
+            restored.pools.len(),
+            restored.replies.len(),
+            restored.emitted
+        );
+        let replies = ReplyHistory::restore(restored.replies, restored.emitted);
+        (Some(file), replies)
+    }
+
+    /// How long a starting node waits for the running one's handover.
+    const HANDOVER_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+    /// Take over from the node listening at `path`, if one is.
+    fn take_over(path: &str) -> Option<Handover> {
+        match handover::take_over(path, HANDOVER_TIMEOUT) {
+            Ok(Some(handover)) => {
+                log::info!(
+                    "Took over {} descriptors from the node at {path}",
+                    handover.len()
+                );
+                Some(handover)
+            }
+            Ok(None) => None,
+            Err(e) => {
+                report(
+                    NodeError::from(e).context(format!("handover from {path}")),
+                    "starting afresh",
+                );
+                None
+            }
+        }
+    }
+
+    /// Send the successor on `stream` the TAP device, the state file, the
+    /// admin and handover listeners and the relayer feed position.
+    fn hand_over(
+        stream: &UnixStream,
+        tap: &TunTapInterface,
+        state_file: Option<&StateFile>,
+        admin: Option<&AdminServer>,
+        listener: &HandoverListener,
+        feed: FeedPosition,
+    ) -> std::io::Result<()> {
+        // SAFETY: the interface owns the fd and outlives the borrow.
+        let tap_fd = unsafe { BorrowedFd::borrow_raw(tap.as_raw_fd()) };
+        let mut offer = HandoverOffer::new()
+            .with_fd(FdRole::Tap, tap_fd)
+            .with_tap_frame(tap.capabilities().max_transmission_unit)
+            .with_fd(FdRole::HandoverListener, listener.as_fd())
+            .with_feed(feed);
+        if let Some(file) = state_file {
+            offer = offer.with_fd(FdRole::StateFile, file.as_fd());
+        }
+        if let Some(admin) = admin {
+            offer = offer.with_fd(FdRole::AdminListener, admin.as_fd());
+        }
+        offer.send(stream)
+    }
+
+    /// Run an admin command the connection is allowed.
+    #[allow(clippy::too_many_arguments)]
+    fn admin_command(
+        cmd: AdminCommand,
+        stats: &NodeStats,
+        profiler: &mut StageProfiler,
+        quarantine: &mut Quarantine,
+        state_sources: &SourceArbiter,
+        clock: &impl Clock,
+        normalizer: &ProfitNormalizer,
+        chaos: &mut FaultInjector,
+    ) -> String {
+        let state = |halted: bool| if halted { "halted" } else { "trading" };
+        match cmd {
+            AdminCommand::Stats => format!(
+                "ok rx={} tx={} opps={} pools={} halted_replies={}",
+                stats.rx_packets.load(),
+                stats.tx_packets.load(),
+                stats.opportunities.load(),
+                stats.pool_updates.load(),
+                stats.halted_replies.load()
+            ),
+            AdminCommand::Status => format!("ok {}", state(TRADING_HALTED.load(Ordering::Relaxed))),
+            AdminCommand::Halt | AdminCommand::Resume => {
+                let halt = cmd == AdminCommand::Halt;
+                TRADING_HALTED.store(halt, Ordering::Relaxed);
+                log::warn!("admin: trading {}", state(halt));
+                format!("ok {}", state(halt))
+            }
+            AdminCommand::Strategies => format!("ok {STRATEGIES}"),
+            AdminCommand::SetStrategy(strategy, enabled) => {
+                STRATEGIES.set(strategy, enabled);
+                log::warn!(
+                    "admin: {} {}",
+                    strategy.name(),
+                    if enabled { "enabled" } else { "disabled" }
+                );
+                format!("ok {STRATEGIES}")
+            }
+            AdminCommand::Profile => format!("ok {}", profiler.report()),
+            AdminCommand::ProfileStart => {
+                profiler.start();
+                log::info!("admin: stage profiler started");
+                "ok on".to_string()
+            }
+            AdminCommand::ProfileStop => {
+                profiler.stop();
+                let report = profiler.report();
+                log::info!("admin: stage profiler stopped: {report}");
+                format!("ok {report}")
+            }
+            AdminCommand::Pools => format!("ok {}", PoolDump::collect(&POOL_TABLE).to_json()),
+            AdminCommand::PoolsBinary => {
+                format!(
+                    "ok {}",
+                    pool_dump::to_hex(&PoolDump::collect(&POOL_TABLE).to_snapshot())
+                )
+            }
+            AdminCommand::Quarantine => format!("ok {quarantine}"),
+            AdminCommand::Sources => format!("ok {}", stats.sources),
+            AdminCommand::Errors => format!("ok {NODE_ERRORS}"),
+            AdminCommand::PoolSources => {
+                format!("ok {}", state_sources.to_json(clock.now_unix_ms()))
+            }
+            AdminCommand::UnknownPools => format!("ok {UNKNOWN_POOLS}"),
+            AdminCommand::Pnl => {
+                format!("ok {}", stats.pnl.snapshot(normalizer, Some(&PRICE_CACHE)))
+            }
+            AdminCommand::QuarantineClear => {
+                quarantine.clear();
+                "ok cleared".to_string()
+            }
+            AdminCommand::Chaos(fault) => match chaos.command(fault) {
+                Some(chaos) => {
+                    if !fault.is_readonly() {
+                        log::warn!("admin: chaos {fault:?}, now {chaos}");
+                    }
+                    format!("ok {chaos}")
+                }
+                None => "error built without the chaos feature".to_string(),
+            },
+            // Answered by `privacy_command`, next to the victim state.
+            AdminCommand::Privacy(_) => "error privacy commands are not handled here".to_string(),
+        }
+    }
+
+    /// Show or edit the private-routing filter, which lives with the victim
+    /// state.
+    fn privacy_command(filter: &mut PrivacyFilter, cmd: PrivacyCommand) -> String {
+        match filter.command(cmd) {
+            Ok(true) => {
+                if !cmd.is_readonly() {
+                    log::warn!("admin: privacy {cmd:?}");
+                }
+                format!("ok {filter}")
+            }
+            Ok(false) => "ok unchanged".to_string(),
+            Err(e) => format!("error {e}"),
+        }
+    }
+
+    /// Write the pool table and reply history to the state file. `pools` is
+    /// scratch space reserved up front.
+    fn persist_state(
+        file: &mut StateFile,
+        replies: &ReplyHistory<REPLY_HISTORY>,
+        pools: &mut Vec<PoolStateUpdate>,
+    ) {
+        pools.clear();
+        POOL_TABLE.for_each(|e| {
+            pools.push(PoolStateUpdate::new(
+                e.address,
+                e.state.reserve0,
+                e.state.reserve1,
+                e.slot,
+                e.seq,
+            ));
+        });
+        file.store(pools.iter().copied(), replies.iter(), replies.emitted());
+    }
+
+    /// Periodic work of the TAP main loop, driven by `NodeTimers`.
+    #[derive(Clone, Copy, Debug)]
+    enum NodeTimer {
+        ExpirySweep,
+        StateSync,
+        ThresholdUpdate,
+        Stats,
+        PaceRelease,
+        PoolRepack,
+    }
+
+    /// 1024 µs per revolution; one entry per `NodeTimer`, with room to spare.
+    type NodeTimers = TimerWheel<NodeTimer, 1024, 8>;
+
+    /// `MEV_RECORD_TIMELINE` output, if recording.
+    type Recorder = Option<Journal>;
+
+    fn open_recorder(path: &str, config: &NodeConfig, clock: &impl Clock) -> Recorder {
+        match Journal::create(
+            path,
+            config.journal,
+            clock.now_unix_ms(),
+            config.cores.slot(Role::Housekeeping),
+        ) {
+            Ok(journal) => {
+                log::info!("Recording pool updates and victims to {path}");
+                Some(journal)
+            }
+            Err(e) => {
+                report(
+                    NodeError::from(e).context(format!("timeline {path}")),
+                    "not recording",
+                );
+                None
+            }
+        }
+    }
+
+    /// Append a payload to the timeline being recorded; a write error stops
+    /// the recording, not the node.
+    fn record(recorder: &mut Recorder, kind: EventKind, rx_ts_ns: u64, payload: &[u8]) {
+        if let Some(journal) = recorder {
+            if let Err(e) = journal.record(kind, rx_ts_ns, payload) {
+                report(NodeError::from(e).context("timeline recording"), "stopped");
+                *recorder = None;
+            }
+        }
+    }
+
+    /// Dry run: race the opportunity in the simulator instead of replying,
+    /// and feed the outcome to the threshold controller's win rate and, if
+    /// it landed, to the PnL books.
+    fn settle_dry_run(
+        sim: &mut Simulator,
+        config: &NodeConfig,
+        win_window: &mut WinRateWindow<256>,
+        payload: &[u8],
+        opportunity: Opportunity,
+        rx_ts_ns: u64,
+        stats: &NodeStats,
+    ) {
+        let pool = DexSwapTx::header(payload).map_or([0; 20], |tx| tx.pool_address);
+        let latency_ns =
+            unix_time_ns().saturating_sub(rx_ts_ns) + config.execution_latency_ms * 1_000_000;
+        let landed = sim.settle(
+            Strategy::Sandwich,
+            pool,
+            opportunity.profit,
+            config.gas_cost,
+            latency_ns,
+        );
+        if landed {
+            stats
+                .pnl
+                .record_landed(opportunity.token, opportunity.edge, config.gas_cost);
+        }
+        win_window.record(landed);
+    }
+
+    /// Drive the relayer feed session: (re)connect through `connect`, send
+    /// the hello once established, and stage complete records into `batch`
+    /// next to the UDP feed's datagrams. Records that do not fit stay with the
+    /// session or in the socket until the next poll.
+    fn poll_relay_feed(
+        tcp: &mut TcpSocket,
+        session: &mut FeedSession,
+        (addr, port): ([u8; 4], u16),
+        batch: &mut FrameBatch,
+        flow: &mut WindowMonitor,
+        stats: &NodeStats,
+        connect: impl FnOnce(&mut TcpSocket, IpEndpoint),
+    ) {
+        if tcp.state() == TcpState::CloseWait {
+            tcp.abort();
+        }
+        if !tcp.is_active() {
+            if !session.awaits_hello() {
+                log::warn!("relayer feed disconnected after seq {}", session.last_seq());
+                session.disconnected();
+                flow.disconnected();
+            }
+            let [a, b, c, d] = addr;
+            connect(tcp, IpEndpoint::new(IpAddress::v4(a, b, c, d), port));
+            return;
+        }
+        if session.awaits_hello() {
+            if tcp.may_send() {
+                session.connected(tcp);
+            }
+            return;
+        }
+        if !tcp.can_recv() && !session.is_streaming() {
+            return;
+        }
+        if tcp.recv_queue() == tcp.recv_capacity() {
+            stats.tcp_rx_full.inc();
+        }
+        let rx_ts_ns = unix_time_ns();
+        if let Some(action) =
+            flow.observe(tcp.recv_queue(), tcp.recv_capacity(), rx_ts_ns / 1_000_000)
+        {
+            if session.flow_control(tcp, action) {
+                flow.sent(action);
+                log::debug!("relayer feed {action:?} at seq {}", session.last_seq());
+            }
+        }
+        let received = tcp.recv(|data| {
+            match session.receive(data, |_, payload| {
+                batch.push(payload, rx_ts_ns, (addr, port))
+            }) {
+                Ok((used, event)) => (used, Ok(event)),
+                Err(e) => (data.len(), Err(e)),
+            }
+        });
+        match received {
+            Ok(Ok(Some(SessionEvent::Welcomed(WelcomeStatus::Resumed)))) => {
+                stats.feed_resumes.inc();
+                log::info!(
+                    "relayer feed resumed after seq {} (wire v{})",
+                    session.last_seq(),
+                    session.wire_version()
+                );
+            }
+            Ok(Ok(Some(SessionEvent::Welcomed(WelcomeStatus::Fresh)))) => {
+                stats.feed_fresh_sessions.inc();
+                log::info!(
+                    "relayer feed started a fresh session at seq {} (wire v{})",
+                    session.last_seq() + 1,
+                    session.wire_version()
+                );
+            }
+            Ok(Ok(Some(SessionEvent::Gap { expected, got }))) => {
+                log::debug!("relayer feed gap: expected record {expected}, got {got}");
+            }
+            Ok(Ok(None)) | Err(_) => {}
+            Ok(Err(e)) => {
+                report(
+                    NodeError::from(e).context("relayer feed"),
+                    "connection dropped",
+                );
+                tcp.abort();
+                session.disconnected();
+                flow.disconnected();
+            }
+        }
+    }
+
+    /// Compare a market with its reference pool and send an alert from the
+    /// pool feed socket once they have stayed apart for long enough.
+    fn check_divergence(
+        tracker: &mut DivergenceTracker,
+        market: &PoolAddress,
+        entry: &BookEntry,
+        socket: &mut UdpSocket,
+        stats: &NodeStats,
+    ) {
+        let Some(pool) = POOL_TABLE.peek(&entry.ref_pool) else {
+            return;
+        };
+        let slot = entry.slot.max(pool.slot);
+        let Some(alert) = tracker.observe(market, &entry.book, &entry.ref_pool, &pool.state, slot)
+        else {
+            return;
+        };
+        stats.divergence_alerts.inc();
+        log::info!(
+            "divergence: market={market:02x?} pool={:02x?} {}bps for {} updates",
+            entry.ref_pool,
+            alert.divergence_bps(),
+            alert.updates()
+        );
+        if let Some(([a, b, c, d], port)) = tracker.config().alert_to {
+            let remote = IpEndpoint::new(IpAddress::v4(a, b, c, d), port);
+            match UdpReply::new(socket, remote).tx_reserve(DivergenceAlert::WIRE_SIZE) {
+                Some(buf) => buf.copy_from_slice(bytemuck::bytes_of(&alert)),
+                None => log::warn!("divergence alert dropped: TX buffer full"),
+            }
+        }
+    }
+
+    /// Feed a pool's update at `now_ns` to the anomaly detector.
+    fn check_anomaly(
+        detector: &mut AnomalyDetector,
+        pool: &PoolAddress,
+        now_ns: u64,
+        stats: &NodeStats,
+    ) {
+        let Some(entry) = POOL_TABLE.peek(pool) else {
+            return;
+        };
+        let Some(anomaly) = detector.observe(pool, &entry.state, now_ns, &SUPPRESSED_POOLS) else {
+            return;
+        };
+        stats.pool_anomalies.inc();
+        log::warn!(
+            "anomaly: pool={pool:02x?} {} spike{}",
+            anomaly.name(),
+            if detector.config().suppress_ms != 0 {
+                ", suppressed"
+            } else {
+                ""
+            }
+        );
+    }
+
+    fn log_sim(sim: &Simulator) {
+        let t = sim.totals;
+        log::info!(
+            "sim: emitted={}, landed={}, pnl={}",
+            t.emitted,
+            t.landed,
+            t.pnl
+        );
+        for (strategy, pool, e) in sim.entries() {
+            log::info!(
+                "sim: strategy={} pool={:02x?} emitted={} landed={} pnl={}",
+                strategy.name(),
+                pool,
+                e.emitted,
+                e.landed,
+                e.pnl
+            );
+        }
+        if sim.overflow.emitted != 0 {
+            let o = sim.overflow;
+            log::info!(
+                "sim: other pools emitted={} landed={} pnl={}",
+                o.emitted,
+                o.landed,
+                o.pnl
+            );
+        }
+    }
+
+    /// The segments of the journal at `path`, oldest first: a rotated
+    /// journal plays as one timeline. `None`, reported, if there are none.
+    fn journal_segments(path: &str) -> Option<Vec<std::path::PathBuf>> {
+        match journal::segments(path) {
+            Ok(segments) if !segments.is_empty() => Some(segments),
+            Ok(_) => {
+                eprintln!("cannot read {path}: no such timeline or rotated segments");
+                None
+            }
+            Err(e) => {
+                eprintln!("cannot read {path}: {e}");
+                None
+            }
+        }
+    }
+
+    const BACKTEST_USAGE: &str = "usage: mev-zerocopy-node backtest <timeline> [--latency-ns N]...";
+
+    /// Entry point for `backtest ...`: play a recorded timeline through the
+    /// evaluator the node would run with this environment, and print one
+    /// report per latency. Returns the process exit code.
+    pub fn backtest(args: &[String]) -> i32 {
+        let Some((path, flags)) = args.split_first() else {
+            eprintln!("{BACKTEST_USAGE}");
+            return 2;
+        };
+        let mut latencies_ns = Vec::new();
+        let mut it = flags.iter();
+        while let Some(flag) = it.next() {
+            match (flag.as_str(), it.next().and_then(|v| v.parse::<u64>().ok())) {
+                ("--latency-ns", Some(ns)) => latencies_ns.push(ns),
+                _ => {
+                    eprintln!("{BACKTEST_USAGE}");
+                    return 2;
+                }
+            }
+        }
+        let Some(segments) = journal_segments(path) else {
+            return 1;
+        };
+
+        let config = NodeConfig::from_env();
+        let mut backtest_config = BacktestConfig {
+            race: config.sim.unwrap_or_default(),
+            gas_cost: config.gas_cost,
+            ..BacktestConfig::default()
+        };
+        if !latencies_ns.is_empty() {
+            backtest_config.latencies_ns = latencies_ns;
+        }
+        // As in `run`, minus reference prices, which timelines do not carry.
+        let evaluator = config
+            .evaluator()
+            .with_adaptive_threshold(&MIN_AMOUNT_IN)
+            .with_pools(&POOL_TABLE);
+        seed_pools(&POOL_TABLE, &seed_entries(&config), |_| true);
+        MIN_AMOUNT_IN.store(config.threshold.compute(&ThresholdInputs {
+            gas_cost: config.gas_cost,
+            ..ThresholdInputs::default()
+        }));
+        let mut backtest = Backtest::new(evaluator, &POOL_TABLE, &backtest_config);
+        let mut result = Ok(());
+        for segment in &segments {
+            let bytes = match journal::read_segment(segment) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    eprintln!("cannot read {}: {e}", segment.display());
+                    return 1;
+                }
+            };
+            result = Timeline::parse(&bytes).and_then(|timeline| backtest.play(timeline));
+            if result.is_err() {
+                break;
+            }
+        }
+        backtest.finish();
+        println!(
+            "backtest {path}: pool_updates={}/{} (ok/rej), victims={}, opportunities={}",
+            backtest.pool_updates, backtest.pool_rejects, backtest.victims, backtest.opportunities
+        );
+        for report in backtest.reports() {
+            println!("{report}");
+        }
+        match result {
+            Ok(()) => 0,
+            Err(e) => {
+                eprintln!("{path}: {e}; reports cover the records before it");
+                1
+            }
+        }
+    }
+
+    const REPLAY_CHECK_USAGE: &str =
+        "usage: mev-zerocopy-node replay-check <timeline> [--write <decisions>] [--against <decisions>]";
+
+    /// Entry point for `replay-check ...`: replay a journal twice through
+    /// the evaluator this environment configures and fail at the first
+    /// victim the replays decide differently. `--write` saves the verdicts
+    /// as a decision log; `--against` also compares them with one another
+    /// build wrote. Returns the process exit code.
+    pub fn replay_check(args: &[String]) -> i32 {
+        let Some((path, flags)) = args.split_first() else {
+            eprintln!("{REPLAY_CHECK_USAGE}");
+            return 2;
+        };
+        let (mut write, mut against) = (None, None);
+        let mut it = flags.iter();
+        while let Some(flag) = it.next() {
+            match (flag.as_str(), it.next()) {
+                ("--write", Some(to)) => write = Some(to),
+                ("--against", Some(log)) => against = Some(log),
+                _ => {
+                    eprintln!("{REPLAY_CHECK_USAGE}");
+                    return 2;
+                }
+            }
+        }
+        let Some(segments) = journal_segments(path) else {
+            return 1;
+        };
+        let reference = match against.map(journal::read_segment).transpose() {
+            Ok(reference) => reference,
+            Err(e) => {
+                eprintln!("cannot read {}: {e}", against.map_or("", |s| s.as_str()));
+                return 1;
+            }
+        };
+        let mut expected = match reference.as_deref().map(Timeline::parse).transpose() {
+            Ok(timeline) => timeline.map(Verdicts::new),
+            Err(e) => {
+                eprintln!("{}: {e}", against.map_or("", |s| s.as_str()));
+                return 1;
+            }
+        };
+        let mut log = match write.map(|to| std::fs::File::create(to).map(std::io::BufWriter::new)) {
+            Some(Ok(out)) => DecisionLog::new(out).ok(),
+            Some(Err(e)) => {
+                eprintln!("cannot write {}: {e}", write.map_or("", |s| s.as_str()));
+                return 1;
+            }
+            None => None,
+        };
+
+        // As in `backtest`, with no latency scenarios: only verdicts count.
+        let config = NodeConfig::from_env();
+        MIN_AMOUNT_IN.store(config.threshold.compute(&ThresholdInputs {
+            gas_cost: config.gas_cost,
+            ..ThresholdInputs::default()
+        }));
+        let replay = || {
+            let table: &'static PoolTable = Box::leak(Box::new(PoolTable::new()));
+            seed_pools(table, &seed_entries(&config), |_| true);
+            let evaluator = config
+                .evaluator()
+                .with_adaptive_threshold(&MIN_AMOUNT_IN)
+                .with_pools(table);
+            (
+                Backtest::new(
+                    evaluator,
+                    table,
+                    &BacktestConfig {
+                        latencies_ns: Vec::new(),
+                        ..Default::default()
+                    },
+                ),
+                table,
+            )
+        };
+        let (checked, table) = replay();
+        let mut lockstep = Lockstep::new([replay(), (checked, table)]);
+        let mut across = Comparison::default();
+        for segment in &segments {
+            let bytes = match journal::read_segment(segment) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    eprintln!("cannot read {}: {e}", segment.display());
+                    return 1;
+                }
+            };
+            let timeline = match Timeline::parse(&bytes) {
+                Ok(timeline) => timeline,
+                Err(e) => {
+                    eprintln!("{}: {e}", segment.display());
+                    return 1;
+                }
+            };
+            for event in timeline {
+                let event = match event {
+                    Ok(event) => event,
+                    Err(e) => {
+                        eprintln!("{}: {e}", segment.display());
+                        return 1;
+                    }
+                };
+                let verdict = match lockstep.replay(event) {
+                    Ok(Some(verdict)) => verdict,
+                    Ok(None) => continue,
+                    Err(divergence) => {
+                        println!("replay-check {path}: two replays disagree\n{divergence}");
+                        return 1;
+                    }
+                };
+                if let Some(Err(e)) = log.as_mut().map(|log| log.record(&event, verdict)) {
+                    eprintln!("cannot write {}: {e}", write.map_or("", |s| s.as_str()));
+                    return 1;
+                }
+                let Some(expected) = expected.as_mut() else {
+                    continue;
+                };
+                let against = against.map_or("", |s| s.as_str());
+                match expected.next() {
+                    Some(Ok(reference)) => {
+                        if let Err(divergence) =
+                            across.compare(&event, reference, verdict, (None, table))
+                        {
+                            println!("replay-check {path}: differs from {against}\n{divergence}");
+                            return 1;
+                        }
+                    }
+                    Some(Err(e)) => {
+                        eprintln!("{against}: {e}");
+                        return 1;
+                    }
+                    None => {
+                        println!(
+                            "replay-check {path}: {against} ends before victim #{}",
+                            across.victims
+                        );
+                        return 1;
+                    }
+                }
+            }
+        }
+        if let Some(Err(e)) = log.as_mut().map(DecisionLog::flush) {
+            eprintln!("cannot write {}: {e}", write.map_or("", |s| s.as_str()));
+            return 1;
+        }
+        if let Some(against) =
+            against.filter(|_| expected.as_mut().is_some_and(|e| e.next().is_some()))
+        {
+            println!(
+                "replay-check {path}: {against} has more victims than the {} replayed",
+                across.victims
+            );
+            return 1;
+        }
+        let c = lockstep.comparison;
+        let matched = against.map_or(String::new(), |log| format!(", as in {log}"));
+        println!(
+            "replay-check {path}: victims={}, replies={}, deterministic{matched}",
+            c.victims, c.replies
+        );
+        0
+    }
+
+    pub fn run() {
+        env_logger::init();
+
+        let mut config = NodeConfig::from_env();
+        place_threads(&mut config);
+        alloc_audit::set_policy(config.alloc_audit);
+        // Per-stage costs on this host, for budgets and capacity planning.
+        let calibration = (config.calibration != CalibrationMode::Off).then(|| {
+            let calibration = selftest::calibrate(&CalibrationConfig::default());
+            log::info!("calibration ({:?}): {calibration}", config.calibration);
+            if config.calibration == CalibrationMode::Apply {
+                config.threshold.latency_budget_cycles = calibration.frame_budget_cycles;
+            }
+            calibration
+        });
+        let evaluator = config
+            .evaluator()
+            .with_prices(&PRICE_CACHE)
+            .with_adaptive_threshold(&MIN_AMOUNT_IN)
+            .with_pools(&POOL_TABLE)
+            .with_confidence(config.confidence, &CONFIDENCE)
+            .with_discovery(&UNKNOWN_POOLS)
+            .with_suppressed_pools(&SUPPRESSED_POOLS);
+        let seeds = seed_entries(&config);
+        seed_pools(&POOL_TABLE, &seeds, |_| true);
+        MIN_AMOUNT_IN.store(config.threshold.compute(&ThresholdInputs {
+            gas_cost: config.gas_cost,
+            ..ThresholdInputs::default()
+        }));
+        // Compute only; wire-to-wire latency is in `tx_state.wire_window`.
+        let mut latency_window: LatencyWindow<1024> = LatencyWindow::new();
+        let mut slo = SloMonitor::new(config.slo);
+        // Fed by executor outcome reports once available; until then the
+        // configured prior win rate applies.
+        let mut win_window: WinRateWindow<256> = WinRateWindow::new();
+        let mut simulator = config.sim.map(Simulator::new);
+        let mut books = BookTable::new();
+        // Per-destination TX rate limits; delayed messages leave from here.
+        let mut pacer: Pacer = Pacer::new(config.pacing);
+        let mut coalescer = config
+            .reply_coalesce
+            .then(|| Box::new(ReplyCoalescer::new()));
+        let mut tx_state = TxState {
+            wire_window: LatencyWindow::new(),
+            nonces: NonceTracker::new(),
+            reputation: ReputationTable::new(config.reputation),
+            privacy: PrivacyFilter::new(&config.privacy),
+            emitted: EmittedRing::new(),
+            replies: ReplyHistory::new(),
+            acks: config
+                .reply_ack
+                .map(|policy| Box::new(AckRing::new(policy))),
+        };
+        // Kept apart from `tx_state`, which emits what it promotes.
+        let mut horizon =
+            (config.horizon_slots != 0).then(|| Box::new(Horizon::new(config.horizon_slots)));
+        // Descriptors and feed position of the node this one replaces.
+        let mut inherited = config.handover_socket.as_deref().and_then(take_over);
+        let mut state_file = None;
+        if let Some(path) = config.state_file.as_deref() {
+            let fd = inherited.as_mut().and_then(|h| h.take(FdRole::StateFile));
+            (state_file, tx_state.replies) = restore_state(path, fd);
+        }
+        let mut state_arbiter = config.state_sources.arbiter();
+        let mut state_sources: Vec<(SourceId, Box<dyn StateSource>)> = Vec::new();
+        if let Some(path) = config.state_sources.snapshot.as_deref() {
+            let (kind, stale_after_ms) =
+                (SourceKind::Snapshot, config.state_sources.stale_after_ms);
+            if let Some(id) =
+                state_arbiter.register(kind, config.state_sources.priority(kind), stale_after_ms)
+            {
+                state_sources.push((id, Box::new(SnapshotSource::new(path))));
+            }
+        }
+        let mut pool_snapshot = Vec::with_capacity(MAX_PERSISTED_POOLS);
+        let mut divergence = config.divergence.map(DivergenceTracker::new);
+        let mut anomalies = config.anomaly.map(AnomalyDetector::new);
+        if let Some(model) = config.sim {
+            log::info!("Dry run: replies are simulated, not sent ({model:?})");
+        }
+        let mut clock = LoopClock::calibrate(std::time::Duration::from_millis(10));
+        let cycles_per_us = clock.cycles_per_us();
+        let mut recorder = config
+            .record_timeline
+            .as_deref()
+            .and_then(|path| open_recorder(path, &config, &clock));
+        let mut timers = NodeTimers::on(&clock);
+        let mut every = |timer, period_us| {
+            timers
+                .every(timer, period_us)
+                .expect("NodeTimers has room for every NodeTimer");
+        };
+        every(NodeTimer::ExpirySweep, 10_000);
+        every(NodeTimer::ThresholdUpdate, 1_000_000);
+        every(NodeTimer::Stats, config.stats_interval_ms * 1_000);
+        if state_file.is_some() {
+            every(NodeTimer::StateSync, config.state_sync_ms * 1_000);
+        }
+        if config.pacing.is_enabled() {
+            every(
+                NodeTimer::PaceRelease,
+                config.pacing.interval_cycles / cycles_per_us,
+            );
+        }
+        if config.pool_repack_ms != 0 {
+            every(NodeTimer::PoolRepack, config.pool_repack_ms * 1_000);
+        }
+        log::info!(
+            "Timer wheel: {cycles_per_us} cycles/us, {} timers",
+            timers.len()
+        );
+        log::info!(
+            "Profit numeraire: {}, min profit: {}",
+            tokens::token(evaluator.normalizer.numeraire()).map_or("?", |t| t.symbol_str()),
+            evaluator.min_profit
+        );
+
+        let stats: &'static NodeStats = &NODE_STATS;
+        let (exporter, housekeeping) = (
+            config.cores.slot(Role::Exporter),
+            config.cores.slot(Role::Housekeeping),
+        );
+        if let Some(statsd) = config.statsd.clone() {
+            let target = statsd.target;
+            match statsd::spawn(
+                statsd,
+                stats,
+                (evaluator.normalizer, &PRICE_CACHE),
+                &SHUTDOWN,
+                exporter,
+            ) {
+                Ok(_) => log::info!("Pushing stats to statsd collector {target}"),
+                Err(e) => report(
+                    NodeError::from(e).context(format!("statsd exporter for {target}")),
+                    "not started",
+                ),
+            }
+        }
+        if let Some(shm) = config.metrics_shm.clone() {
+            let path = shm.path.clone();
+            match metrics_shm::spawn(
+                shm,
+                stats,
+                (evaluator.normalizer, &PRICE_CACHE),
+                &SHUTDOWN,
+                exporter,
+            ) {
+                Ok(_) => log::info!("Publishing metrics to {path}"),
+                Err(e) => report(
+                    NodeError::from(e).context(format!("metrics segment {path}")),
+                    "not published",
+                ),
+            }
         }
-    }
+        match discovery::spawn(
+            config.discovery,
+            &UNKNOWN_POOLS,
+            &POOL_TABLE,
+            &SHUTDOWN,
+            housekeeping,
+        ) {
+            Ok(_) => {
+                if let Some(([a, b, c, d], port)) = config.discovery.request_to {
+                    log::info!("Requesting unknown pools from {a}.{b}.{c}.{d}:{port}");
+                }
+            }
+            Err(e) => report(
+                NodeError::from(e).context("pool discovery"),
+                "unknown pools are not requested",
+            ),
+        }
+        if let Some(leader) = config.leader {
+            let ([a, b, c, d], port) = leader.peer;
+            match leader::spawn(leader, &LEADERSHIP, &SHUTDOWN, housekeeping) {
+                Ok(_) => log::info!("Standby until elected against node at {a}.{b}.{c}.{d}:{port}"),
+                Err(e) => {
+                    // Replying without an election could double the peer's replies.
+                    let error = NodeError::from(e)
+                        .context(format!("leader election on port {}", leader.port));
+                    log::error!("{error}; not replying alone");
+                    return;
+                }
+            }
+        }
+        let mut admin = config.admin_socket.as_deref().and_then(|path| {
+            let auth = config.admin_auth.clone();
+            let server = match inherited
+                .as_mut()
+                .and_then(|h| h.take(FdRole::AdminListener))
+            {
+                Some(fd) => AdminServer::adopt(UnixListener::from(fd), path, auth),
+                None => AdminServer::bind(path, auth),
+            };
+            match server {
+                Ok(server) => {
+                    log::info!("Admin API listening on {path}");
+                    Some(server)
+                }
+                Err(e) => {
+                    report(
+                        NodeError::from(e).context(path.to_string()),
+                        "admin API disabled",
+                    );
+                    None
+                }
+            }
+        });
 
-    pub fn run() {
-        init_runtime();
+        let phc = config
+            .phc_device
+            .as_deref()
+            .and_then(|path| match PhcClock::open(path) {
+                Ok(phc) => {
+                    log::info!("Reply timestamps follow PHC {path}");
+                    Some(phc)
+                }
+                Err(e) => {
+                    report(
+                        NodeError::from(e).context(path.to_string()),
+                        "reply timestamps use the system clock",
+                    );
+                    None
+                }
+            });
+        if let Some(phc) = &phc {
+            sync_wire_clock(phc);
+        }
+        if let Some(signer) = config.reply_signer {
+            transport::set_reply_signer(signer);
+            log::info!("Replies signed as node {}", signer.node_id());
+        }
 
-        let stats = NodeStats::new();
-        let mut response_ring: ResponseRing<1024> = ResponseRing::new();
+        if config.backend == Backend::Dpdk {
+            // The DPDK port has no kernel netdev; only an explicit
+            // MEV_REPLY_IFACE can be configured.
+            #[cfg(feature = "dpdk")]
+            configure_reply_path(&config, None);
+            #[cfg(feature = "dpdk")]
+            return run_dpdk(&config, &evaluator, &seeds, stats, cycles_per_us);
+            #[cfg(not(feature = "dpdk"))]
+            log::warn!("DPDK backend requested but built without the `dpdk` feature, falling back to TAP transport");
+        }
 
-        if backend_mode() == "af_xdp" {
-            let cfg = XdpConfig::default();
+        // Kept alive for the whole run: dropping it detaches the program.
+        let mut _prefilter = None;
+        if config.backend == Backend::AfXdp {
+            let cfg = XdpConfig {
+                interface: config.xdp_iface,
+                ..XdpConfig::default()
+            };
+            let umem = ActiveDimensions::umem_config();
             let available = xdp::probe_af_xdp_socket();
             log::info!(
-                "AF_XDP requested: iface={}, queue={}, mode={:?}, available={}",
+                "AF_XDP requested: iface={}, queue={}, mode={:?}, umem_frames={}, max_frame_age={}cyc, available={}",
                 cfg.interface,
                 cfg.queue_id,
                 cfg.mode,
+                umem.frame_count,
+                config.xdp_max_frame_age_cycles,
                 available
             );
             if !available {
                 log::warn!("AF_XDP socket probe failed, falling back to TAP transport");
             }
+            configure_reply_path(&config, Some(cfg.interface.as_str()));
+            if available {
+                match run_af_xdp(&config, cfg, &evaluator, stats, cycles_per_us) {
+                    Ok(()) => return,
+                    Err(e) => report(e, "falling back to TAP transport"),
+                }
+            }
+            if config.xdp_prefilter {
+                let spec = FilterSpec::node_default(config.ports);
+                match XdpFilter::attach(
+                    &spec,
+                    cfg.interface.as_str(),
+                    cfg.mode == XdpMode::Native,
+                    None,
+                ) {
+                    Ok(filter) => _prefilter = Some(filter),
+                    Err(e) => report(NodeError::from(e).context("XDP pre-filter"), "not attached"),
+                }
+            }
         }
 
         log::info!("Starting MEV node with smoltcp userspace stack");
 
-        let tap_name = "tap0";
-        let mut device = TunTapInterface::new(tap_name, Medium::Ethernet)
-            .expect("failed to open tap0; run scripts/setup_tap.sh first");
+        // Declared before `device`, so the TAP device outlives our handle on it.
+        let mut tap = match TapDevice::ensure(TapConfig {
+            mtu: config.tap_mtu,
+            ..TapConfig::default()
+        }) {
+            Ok(tap) => tap,
+            Err(e) => panic!(
+                "{}; run scripts/setup_tap.sh or grant CAP_NET_ADMIN",
+                NodeError::from(e).context("TAP setup")
+            ),
+        };
+        let device = match inherited
+            .as_mut()
+            .and_then(|h| Some((h.take(FdRole::Tap)?, h.tap_frame)))
+        {
+            // Still attached, with whatever arrived since the handover queued.
+            Some((fd, frame)) => {
+                TunTapInterface::from_fd(fd.into_raw_fd(), Medium::Ethernet, frame)
+            }
+            None => TunTapInterface::new(tap.name(), Medium::Ethernet),
+        }
+        .expect("failed to open TAP device");
+        log::info!(
+            "TAP device {}: frames up to {} bytes",
+            tap.name(),
+            device.capabilities().max_transmission_unit
+        );
+        let mut device = CountingDevice::new(
+            device,
+            &[config.ports.swaps, config.ports.pools, config.ports.control],
+        );
+        unsafe {
+            libc::signal(
+                libc::SIGINT,
+                request_shutdown as *const () as libc::sighandler_t,
+            );
+            libc::signal(
+                libc::SIGTERM,
+                request_shutdown as *const () as libc::sighandler_t,
+            );
+        }
 
         let hardware_addr = EthernetAddress([0x02, 0x00, 0x00, 0x00, 0x00, 0x01]);
         let mut iface = Interface::new(
             Config::new(hardware_addr.into()),
             &mut device,
-            Instant::now(),
+            clock.instant(),
         );
         iface.update_ip_addrs(|ip_addrs| {
             ip_addrs
@@ -69,87 +2373,672 @@ mod linux_node {
                 .unwrap();
         });
 
-        let mut socket_storage = [SocketStorage::EMPTY, SocketStorage::EMPTY];
+        let mut socket_storage = [SocketStorage::EMPTY; 6];
         let mut sockets = SocketSet::new(&mut socket_storage[..]);
 
-        let mut tcp_rx = [0u8; 65_535];
-        let mut tcp_tx = [0u8; 65_535];
+        // Sized from config, allocated once here.
+        let buffers = config.socket_buffers;
         let tcp_socket = TcpSocket::new(
-            TcpSocketBuffer::new(&mut tcp_rx[..]),
-            TcpSocketBuffer::new(&mut tcp_tx[..]),
+            TcpSocketBuffer::new(vec![0u8; buffers.tcp_rx_bytes]),
+            TcpSocketBuffer::new(vec![0u8; buffers.tcp_tx_bytes]),
         );
         let tcp_handle = sockets.add(tcp_socket);
 
-        let mut udp_rx_meta = [UdpPacketMetadata::EMPTY; 64];
-        let mut udp_tx_meta = [UdpPacketMetadata::EMPTY; 64];
-        let mut udp_rx_payload = [0u8; 16 * 1024];
-        let mut udp_tx_payload = [0u8; 16 * 1024];
         let udp_socket = UdpSocket::new(
-            UdpPacketBuffer::new(&mut udp_rx_meta[..], &mut udp_rx_payload[..]),
-            UdpPacketBuffer::new(&mut udp_tx_meta[..], &mut udp_tx_payload[..]),
+            UdpPacketBuffer::new(
+                vec![UdpPacketMetadata::EMPTY; buffers.udp_rx_packets],
+                vec![0u8; buffers.udp_rx_bytes],
+            ),
+            UdpPacketBuffer::new(
+                vec![UdpPacketMetadata::EMPTY; buffers.udp_tx_packets],
+                vec![0u8; buffers.udp_tx_bytes],
+            ),
         );
         let udp_handle = sockets.add(udp_socket);
+        let mut udp_drops = RxDropCounter::default();
+        // What the UDP socket queued since the last poll, drained in one
+        // batch sized to the backlog.
+        let mut udp_batch: FrameBatch<MAX_RX_BATCH, { 32 * 1024 }> = FrameBatch::new();
+        let mut rx_shaper = BurstShaper::new(config.rx_batch);
+        let mut idler = Idler::new(config.idle, cycles_per_us);
+        let mut tx_pressure = PressureGauge::new(config.backpressure);
+        if config.idle.mode != IdleMode::Spin {
+            log::info!(
+                "Idle: {} after {} empty polls, up to {}ns",
+                idler.mode().name(),
+                config.idle.spin_polls,
+                config.idle.wait_ns
+            );
+        }
+        udp_batch.set_limit(rx_shaper.size());
+
+        // Control port, when split off: receive only.
+        let control_handle = config.ports.control_split().then(|| {
+            sockets.add(UdpSocket::new(
+                UdpPacketBuffer::new(
+                    vec![UdpPacketMetadata::EMPTY; buffers.control_rx_packets],
+                    vec![0u8; buffers.control_rx_bytes],
+                ),
+                UdpPacketBuffer::new(vec![UdpPacketMetadata::EMPTY; 1], vec![0u8; 0]),
+            ))
+        });
+        let mut control_drops = RxDropCounter::default();
+        let mut control_batch: FrameBatch = FrameBatch::new();
+
+        // Outgoing: divergence alerts only.
+        let mut pool_tx_meta = [UdpPacketMetadata::EMPTY; 4];
+        let mut pool_tx_payload = [0u8; 4 * DivergenceAlert::WIRE_SIZE];
+        let pool_socket = UdpSocket::new(
+            UdpPacketBuffer::new(
+                vec![UdpPacketMetadata::EMPTY; buffers.pool_rx_packets],
+                vec![0u8; buffers.pool_rx_bytes],
+            ),
+            UdpPacketBuffer::new(&mut pool_tx_meta[..], &mut pool_tx_payload[..]),
+        );
+        let pool_handle = sockets.add(pool_socket);
+
+        // Optional feed mirror: send-only, its own TX buffer.
+        let mut mirror = config
+            .mirror
+            .is_enabled()
+            .then(|| Box::new(Mirror::new(config.mirror)));
+        let mirror_handle = mirror.is_some().then(|| {
+            sockets.add(UdpSocket::new(
+                UdpPacketBuffer::new(vec![UdpPacketMetadata::EMPTY; 1], vec![0u8; 0]),
+                UdpPacketBuffer::new(vec![UdpPacketMetadata::EMPTY; 64], vec![0u8; 32 * 1024]),
+            ))
+        });
+        let mut pool_drops = RxDropCounter::default();
+        // Both pool feeds are handled from one batch: the UDP socket's
+        // datagrams, then the relayer session's records.
+        let mut pool_batch: FrameBatch = FrameBatch::new();
+        let mut pool_ordered = OrderedPoolBatch::new();
+        let mut feed_race: Box<FeedRace> = Box::default();
+        let mut reorder = (config.reorder_depth > 0).then(|| {
+            ReorderWindow::<MAX_REORDER_DEPTH>::new(
+                config.reorder_depth,
+                config.reorder_hold_cycles,
+            )
+        });
+        let mut pool_fec: Option<Box<PoolFec>> = config.pool_fec.then(Box::default);
+        let mut conflator: Box<PoolConflator> = Box::default();
+
+        // Optional relayer TCP feed. Outgoing: the session hello only.
+        let mut relay_tx = [0u8; 256];
+        let relay_handle = config.relay_feed.map(|_| {
+            let relay_rx = vec![0u8; buffers.relay_rx_bytes];
+            sockets.add(TcpSocket::new(
+                TcpSocketBuffer::new(relay_rx),
+                TcpSocketBuffer::new(&mut relay_tx[..]),
+            ))
+        });
+        let mut relay_session = FeedSession::new().with_wire_window(config.wire_versions);
+        if let Some(feed) = inherited.as_ref().map(|h| h.feed) {
+            relay_session = relay_session.resume_from(feed.token, feed.last_seq);
+        }
+        let mut relay_flow = WindowMonitor::new(config.relay_flow);
+        // Executor stream: stalls are counted, the executor is never paused.
+        let mut executor_flow = WindowMonitor::new(FlowPolicy::DISABLED);
+        let mut relay_port = RELAY_LOCAL_PORTS.start;
+        let mut next_relay_connect = clock.instant();
+
+        let mut profiler = StageProfiler::new(config.profile_sample_every);
+        let mut quarantine = Box::new(Quarantine::new());
+        let mut chaos = FaultInjector::new(runtime::now_cycles());
+        let mut corrupted = [0u8; PoolStateUpdate::WIRE_SIZE];
+        if config.profile_at_start {
+            profiler.start();
+        }
+
+        // Page faults, TLB misses and cold predictors, taken now rather
+        // than on the first victims.
+        if config.warmup.enabled {
+            let mut warmup = Warmup::new();
+            warmup.region(&POOL_TABLE);
+            warmup.region(&PRICE_CACHE);
+            warmup.region(stats);
+            warmup.region(&tx_state);
+            warmup.region(&books);
+            warmup.region(&pacer);
+            warmup.region(&latency_window);
+            warmup.region(&udp_batch);
+            if control_handle.is_some() {
+                warmup.region(&control_batch);
+            }
+            warmup.region(&pool_batch);
+            warmup.region(&pool_ordered);
+            warmup.region(&*feed_race);
+            if let Some(reorder) = &reorder {
+                warmup.region(reorder);
+            }
+            if let Some(fec) = &pool_fec {
+                warmup.region(&**fec);
+            }
+            if let Some(mirror) = &mirror {
+                warmup.region(&**mirror);
+            }
+            if let Some(file) = &state_file {
+                warmup.region(file.region());
+            }
+            warmup.region(pool_snapshot.spare_capacity_mut());
+            let seed_addresses: Vec<PoolAddress> = seeds.iter().map(|e| e.address).collect();
+            warmup.prime(config.warmup.packets, &seed_addresses, |payload| {
+                core::hint::black_box(evaluator.process_packet(core::hint::black_box(payload)));
+            });
+            if config.warmup.lock_memory {
+                warmup.lock_memory();
+            }
+            log::info!("warmup: {}", warmup.finish());
+        }
+
+        // Listened on for a successor only once this node is ready to be
+        // replaced; the inherited listener keeps the socket path alive.
+        let mut successor = config.handover_socket.as_deref().and_then(|path| {
+            let listener = match inherited
+                .as_mut()
+                .and_then(|h| h.take(FdRole::HandoverListener))
+            {
+                Some(fd) => HandoverListener::adopt(UnixListener::from(fd), path),
+                None => HandoverListener::bind(path),
+            };
+            match listener {
+                Ok(listener) => Some(listener),
+                Err(e) => {
+                    report(
+                        NodeError::from(e).context(path.to_string()),
+                        "upgrades restart cold",
+                    );
+                    None
+                }
+            }
+        });
+        drop(inherited);
 
-        log::info!("Listening on 192.168.69.2:8080 (TCP+UDP via smoltcp)");
+        log::info!(
+            "Listening on 192.168.69.2:{} (TCP+UDP via smoltcp), pool feed on UDP {}, control on UDP {}",
+            config.ports.swaps,
+            config.ports.pools,
+            config.ports.control
+        );
 
-        loop {
-            let now = Instant::now();
-            iface.poll(now, &mut device, &mut sockets);
+        while !SHUTDOWN.load(Ordering::Relaxed) {
+            // The iteration's one clock read; every timestamp below derives
+            // from it. The software RX stamp covers everything this poll ingests.
+            let now_cycles = clock.tick();
+            let rx_ts_ns = clock.unix_ns();
+            let now = clock.instant();
+            chaos.stall();
+            // Read once: every stage of the poll sheds by the same level.
+            let pressure = BACKPRESSURE.level();
+            let (tx_full, paced_drops) = (stats.tx_full.load(), pacer.dropped);
+            profiler.begin(now_cycles);
+            profiler.enter(Stage::Poll);
+            // Anything received, sent or timed out.
+            let busy = iface.poll(now, &mut device, &mut sockets);
 
+            profiler.enter(Stage::Victims);
             {
                 let udp = sockets.get_mut::<UdpSocket>(udp_handle);
                 if !udp.is_open() {
-                    udp.bind(8080).expect("udp bind failed");
+                    udp.bind(config.ports.swaps).expect("udp bind failed");
                 }
 
-                if udp.can_recv() {
-                    let latency = LatencyClock::start();
-                    if let Ok((payload, meta)) = udp.recv() {
-                        stats.rx_packets.inc();
-                        if let Some(profit) = processor::process_packet(payload) {
+                let queued = udp.rx_batch(&mut udp_batch);
+                let drops = udp_drops.update(
+                    device.delivered(config.ports.swaps),
+                    queued,
+                    !udp.can_recv(),
+                );
+                stats.udp_rx_drops.add(drops);
+                if queued != 0 {
+                    stats.rx_batch_sizes.record(rx_shaper.size());
+                }
+                udp_batch.set_limit(rx_shaper.observe(queued, udp.can_recv()));
+                for (i, (frame, payload)) in udp_batch.iter().enumerate() {
+                    let hot = HotPath::enter();
+                    let started = clock.now_cycles();
+                    let (rx_ts_ns, (addr, port)) = (frame.rx_ts_ns, frame.source);
+                    let remote =
+                        IpEndpoint::new(IpAddress::v4(addr[0], addr[1], addr[2], addr[3]), port);
+                    let source = stats.sources.source(addr);
+                    stats.rx_packets.inc();
+                    source.count_rx();
+                    if !config.allowed_sources.allows(addr, port) {
+                        stats.rejected_sources.inc();
+                    } else if chaos.drop_rx() {
+                        // Injected fault: lost on receipt.
+                    } else if apply_control(
+                        payload,
+                        frame,
+                        config.ports.swaps,
+                        &mut tx_state,
+                        &mut quarantine,
+                        stats,
+                        config.gas_cost,
+                    ) {
+                        // Control message on the swaps port: applied.
+                    } else if DexSwapTx::header(payload).is_none() {
+                        let port = config.ports.swaps;
+                        quarantine_frame(
+                            &mut quarantine,
+                            Malformed::ShortVictim,
+                            frame,
+                            port,
+                            payload,
+                            stats,
+                        );
+                    } else if config.freshness.skip(queued - i, frame.rx_cycles, &clock) {
+                        // A fresh read, not the loop's: frames are stamped
+                        // after it, and age while the batch is worked through.
+                        stats.freshness_drops.inc();
+                    } else if config.backpressure.head_drop(pressure, queued - i) {
+                        stats.backpressure_drops.inc();
+                    } else {
+                        record(&mut recorder, EventKind::Victim, rx_ts_ns, payload);
+                        if let Some(mirror) = mirror.as_mut() {
+                            mirror.victim(payload);
+                        }
+                        let screened = screen_victim(&mut tx_state, payload, stats);
+                        let scored = evaluate_victim(&evaluator, &clock, screened, payload, stats);
+                        if let Some(opportunity) = scored {
                             stats.opportunities.inc();
-                            let _ = response_ring.enqueue(profit.to_le_bytes());
-                            if let Some(reply) = response_ring.dequeue() {
-                                let remote =
-                                    IpEndpoint::new(meta.endpoint.addr, meta.endpoint.port);
-                                let _ = udp.send_slice(&reply, remote);
-                                stats.tx_packets.inc();
+                            stats.record_profit(Strategy::Sandwich, opportunity.profit);
+                            stats.pnl.record_edge(
+                                opportunity.token,
+                                opportunity.edge,
+                                config.gas_cost,
+                            );
+                            source.count_opportunity(opportunity.profit);
+                            if let Some(sim) = simulator.as_mut() {
+                                settle_dry_run(
+                                    sim,
+                                    &config,
+                                    &mut win_window,
+                                    payload,
+                                    opportunity,
+                                    rx_ts_ns,
+                                    stats,
+                                );
+                            }
+                        }
+                        if simulator.is_none() {
+                            let victim = victim_record(payload);
+                            if let Some(horizon) = horizon.as_deref_mut() {
+                                park_victim(
+                                    horizon,
+                                    screened,
+                                    victim,
+                                    scored,
+                                    ReplyRoute::Udp(remote),
+                                );
+                            }
+                            let opportunity = victim.zip(scored);
+                            let pool =
+                                DexSwapTx::header(payload).map_or([0; 20], |tx| tx.pool_address);
+                            let mut tx = RoutedUdpReply::new(
+                                udp,
+                                remote,
+                                &config.reply_routes,
+                                Strategy::Sandwich,
+                                pool,
+                            )
+                            .paced(&mut pacer, now_cycles);
+                            if let Some(coalescer) = coalescer.as_deref_mut() {
+                                tx = tx.coalesced(coalescer);
+                            }
+                            let route = ReplyRoute::Udp(remote);
+                            if opportunity.is_some() {
+                                chaos.delay_tx();
+                            }
+                            if let Some(reply) = tx_state.emit(
+                                &mut tx,
+                                route,
+                                screened,
+                                opportunity,
+                                rx_ts_ns,
+                                stats,
+                            ) {
+                                record(
+                                    &mut recorder,
+                                    EventKind::Decision,
+                                    rx_ts_ns,
+                                    bytemuck::bytes_of(&reply),
+                                );
                             }
                         }
                     }
-                    let sample = latency.stop();
+                    let sample = clock.sample_since(started);
+                    latency_window.record(sample.cycles);
+                    drop(hot);
                     log::debug!(
                         "UDP hot-path latency: {} cycles / {} us",
                         sample.cycles,
                         sample.micros
                     );
                 }
+                if let Some(coalescer) = coalescer.as_deref_mut().filter(|c| !c.is_empty()) {
+                    let flushed =
+                        transport::flush_coalesced(coalescer, udp, Some(&mut pacer), now_cycles);
+                    stats.reply_batches.add(flushed.batches);
+                    stats.coalesced_replies.add(flushed.coalesced);
+                    stats.tx_full.add(flushed.dropped);
+                }
+            }
+
+            profiler.enter(Stage::Control);
+            if let Some(handle) = control_handle {
+                let control = sockets.get_mut::<UdpSocket>(handle);
+                if !control.is_open() {
+                    control
+                        .bind(config.ports.control)
+                        .expect("control bind failed");
+                }
+                let queued = control.rx_batch(&mut control_batch);
+                let drops = control_drops.update(
+                    device.delivered(config.ports.control),
+                    queued,
+                    !control.can_recv(),
+                );
+                stats.udp_rx_drops.add(drops);
+                for (frame, payload) in control_batch.iter() {
+                    let (addr, port) = frame.source;
+                    stats.rx_packets.inc();
+                    stats.sources.source(addr).count_rx();
+                    if !config.allowed_sources.allows(addr, port) {
+                        stats.rejected_sources.inc();
+                        continue;
+                    }
+                    let port = config.ports.control;
+                    if !apply_control(
+                        payload,
+                        frame,
+                        port,
+                        &mut tx_state,
+                        &mut quarantine,
+                        stats,
+                        config.gas_cost,
+                    ) {
+                        log::debug!(
+                            "unknown control message of {} bytes from {:?}",
+                            payload.len(),
+                            frame.source
+                        );
+                    }
+                }
+            }
+
+            profiler.enter(Stage::PoolFeed);
+            {
+                let pool_feed = sockets.get_mut::<UdpSocket>(pool_handle);
+                if !pool_feed.is_open() {
+                    pool_feed
+                        .bind(config.ports.pools)
+                        .expect("pool feed bind failed");
+                }
+                let queued = pool_feed.rx_batch(&mut pool_batch);
+                let drops = pool_drops.update(
+                    device.delivered(config.ports.pools),
+                    queued,
+                    !pool_feed.can_recv(),
+                );
+                stats.udp_rx_drops.add(drops);
+            }
+
+            profiler.enter(Stage::Relay);
+            if let (Some(handle), Some(relay)) = (relay_handle, config.relay_feed) {
+                let relay_tcp = sockets.get_mut::<TcpSocket>(handle);
+                poll_relay_feed(
+                    relay_tcp,
+                    &mut relay_session,
+                    relay,
+                    &mut pool_batch,
+                    &mut relay_flow,
+                    stats,
+                    |tcp, remote| {
+                        if now < next_relay_connect {
+                            return;
+                        }
+                        next_relay_connect = now + smoltcp::time::Duration::from_secs(1);
+                        let span = RELAY_LOCAL_PORTS.len() as u16;
+                        relay_port = RELAY_LOCAL_PORTS.start
+                            + (relay_port - RELAY_LOCAL_PORTS.start + 1) % span;
+                        if let Err(e) = tcp.connect(iface.context(), remote, relay_port) {
+                            log::warn!("relayer connect failed: {e}");
+                        }
+                    },
+                );
+            }
+
+            profiler.enter(Stage::PoolFeed);
+            if !state_sources.is_empty() {
+                let now_ns = clock.unix_ns();
+                poll_state_sources(
+                    &mut state_sources,
+                    &mut state_arbiter,
+                    &clock,
+                    config.wire_versions,
+                    stats,
+                    |pool| {
+                        if let Some(detector) = anomalies.as_mut() {
+                            check_anomaly(detector, &pool, now_ns, stats);
+                        }
+                        tx_state.emitted.invalidate_pool(&pool, |victim| {
+                            evaluator
+                                .process_packet(bytemuck::bytes_of(victim))
+                                .is_some()
+                        });
+                    },
+                );
+            }
+            order_pool_batch(
+                &pool_batch,
+                &mut pool_ordered,
+                &config,
+                reorder.as_mut(),
+                pool_fec.as_deref_mut(),
+                &mut feed_race,
+                stats,
+            );
+            {
+                let pool_feed = sockets.get_mut::<UdpSocket>(pool_handle);
+                let _hot = HotPath::enter();
+                let conflate = config.backpressure.conflate(pressure, config.pool_conflate);
+                if conflate {
+                    conflator.plan(
+                        pool_ordered
+                            .iter()
+                            .map(|(_, payload)| conflation_key(payload)),
+                    );
+                }
+                for (index, (frame, payload)) in pool_ordered.iter().enumerate() {
+                    let rx_ts_ns = frame.rx_ts_ns;
+                    if chaos.drop_rx() {
+                        continue;
+                    }
+                    let replaced = match conflate.then(|| conflator.verdict(index)) {
+                        Some(Conflated::Skip) => {
+                            stats.pool_conflated.inc();
+                            continue;
+                        }
+                        Some(Conflated::Apply { replaced }) => replaced as u32,
+                        None => 0,
+                    };
+                    if let Some(update) = BookUpdate::parse(payload) {
+                        let market = update.market;
+                        match books.apply(update) {
+                            Ok(entry) => {
+                                stats.book_updates.inc();
+                                if let Some(mirror) = mirror.as_mut() {
+                                    mirror.update(payload);
+                                }
+                                let sim = simulator.as_mut();
+                                check_book_arb(
+                                    &evaluator, &market, entry, stats, sim, &config, rx_ts_ns,
+                                );
+                                if let Some(tracker) = divergence.as_mut() {
+                                    check_divergence(tracker, &market, entry, pool_feed, stats);
+                                }
+                            }
+                            Err(e) => {
+                                stats.book_update_rejects.inc();
+                                let Some(reason) = Malformed::from_book(e) else {
+                                    log::debug!("book update rejected: {e:?}");
+                                    continue;
+                                };
+                                quarantine_frame(
+                                    &mut quarantine,
+                                    reason,
+                                    frame,
+                                    config.ports.pools,
+                                    payload,
+                                    stats,
+                                );
+                            }
+                        }
+                    } else {
+                        let payload = chaos.corrupt_seq(payload, &mut corrupted);
+                        let versions = config.wire_versions;
+                        match apply_pool_update(
+                            &POOL_TABLE,
+                            payload,
+                            versions,
+                            &mut state_arbiter,
+                            &clock,
+                            WIRE_FEED,
+                            replaced,
+                            stats,
+                        ) {
+                            Ok(Some(pool)) => {
+                                record(&mut recorder, EventKind::PoolUpdate, rx_ts_ns, payload);
+                                if let Some(mirror) = mirror.as_mut() {
+                                    mirror.update(payload);
+                                }
+                                if let Some(detector) = anomalies.as_mut() {
+                                    check_anomaly(detector, &pool, rx_ts_ns, stats);
+                                }
+                                tx_state.emitted.invalidate_pool(&pool, |victim| {
+                                    evaluator
+                                        .process_packet(bytemuck::bytes_of(victim))
+                                        .is_some()
+                                });
+                                for (market, entry) in books.linked_to(&pool) {
+                                    let sim = simulator.as_mut();
+                                    check_book_arb(
+                                        &evaluator, market, entry, stats, sim, &config, rx_ts_ns,
+                                    );
+                                    if let Some(tracker) = divergence.as_mut() {
+                                        check_divergence(tracker, market, entry, pool_feed, stats);
+                                    }
+                                }
+                            }
+                            Ok(None) => {}
+                            Err(e) => {
+                                let reason = Malformed::PoolUpdate(e);
+                                quarantine_frame(
+                                    &mut quarantine,
+                                    reason,
+                                    frame,
+                                    config.ports.pools,
+                                    payload,
+                                    stats,
+                                );
+                            }
+                        }
+                    }
+                }
             }
 
+            profiler.enter(Stage::Executor);
             {
                 let tcp = sockets.get_mut::<TcpSocket>(tcp_handle);
                 if !tcp.is_open() {
-                    tcp.listen(8080).expect("tcp listen failed");
+                    tcp.listen(config.ports.swaps).expect("tcp listen failed");
                 }
+                executor_flow.observe(tcp.recv_queue(), tcp.recv_capacity(), clock.unix_ms());
 
-                if tcp.can_recv() {
-                    let latency = LatencyClock::start();
-                    if let Ok(maybe_profit) =
-                        tcp.recv(|payload| (payload.len(), processor::process_packet(payload)))
-                    {
+                if tcp.can_recv()
+                    && tcp
+                        .remote_endpoint()
+                        .is_some_and(|ep| !source_allowed(&config.allowed_sources, ep))
+                {
+                    stats.rejected_sources.inc();
+                    tcp.abort();
+                } else if tcp.can_recv() {
+                    if tcp.recv_queue() == tcp.recv_capacity() {
+                        stats.tcp_rx_full.inc();
+                    }
+                    let hot = HotPath::enter();
+                    let started = clock.now_cycles();
+                    if let Ok((screened, victim, scored)) = tcp.recv(|payload| {
+                        record(&mut recorder, EventKind::Victim, rx_ts_ns, payload);
+                        if let Some(mirror) = mirror.as_mut() {
+                            mirror.victim(payload);
+                        }
+                        let screened = screen_victim(&mut tx_state, payload, stats);
+                        let scored = evaluate_victim(&evaluator, &clock, screened, payload, stats);
+                        if let (Some(sim), Some(opportunity)) = (simulator.as_mut(), scored) {
+                            settle_dry_run(
+                                sim,
+                                &config,
+                                &mut win_window,
+                                payload,
+                                opportunity,
+                                rx_ts_ns,
+                                stats,
+                            );
+                        }
+                        (payload.len(), (screened, victim_record(payload), scored))
+                    }) {
+                        let opportunity = victim.zip(scored);
+                        let source = tcp
+                            .remote_endpoint()
+                            .and_then(transport::destination)
+                            .map(|(addr, _)| addr);
+                        let source = stats.sources.source(source.unwrap_or([0; 4]));
                         stats.rx_packets.inc();
-                        if let Some(profit) = maybe_profit {
+                        source.count_rx();
+                        if let Some((
+                            _,
+                            Opportunity {
+                                profit,
+                                token,
+                                edge,
+                                ..
+                            },
+                        )) = opportunity
+                        {
                             stats.opportunities.inc();
-                            let _ = response_ring.enqueue(profit.to_le_bytes());
-                            if let Some(reply) = response_ring.dequeue() {
-                                if tcp.can_send() {
-                                    let _ = tcp.send_slice(&reply);
-                                    stats.tx_packets.inc();
-                                }
+                            stats.record_profit(Strategy::Sandwich, profit);
+                            stats.pnl.record_edge(token, edge, config.gas_cost);
+                            source.count_opportunity(profit);
+                        }
+                        if let (None, Some(peer)) = (&simulator, tcp.remote_endpoint()) {
+                            let route = ReplyRoute::Tcp(peer);
+                            if let Some(horizon) = horizon.as_deref_mut() {
+                                park_victim(horizon, screened, victim, scored, route);
+                            }
+                            let to = transport::destination(peer).unwrap_or(([0; 4], 0));
+                            let mut tx = Paced::new(&mut *tcp, Some(&mut pacer), to, now_cycles);
+                            if let Some(reply) = tx_state.emit(
+                                &mut tx,
+                                route,
+                                screened,
+                                opportunity,
+                                rx_ts_ns,
+                                stats,
+                            ) {
+                                record(
+                                    &mut recorder,
+                                    EventKind::Decision,
+                                    rx_ts_ns,
+                                    bytemuck::bytes_of(&reply),
+                                );
                             }
                         }
                     }
-                    let sample = latency.stop();
+                    let sample = clock.sample_since(started);
+                    latency_window.record(sample.cycles);
+                    drop(hot);
                     log::debug!(
                         "TCP hot-path latency: {} cycles / {} us",
                         sample.cycles,
@@ -158,25 +3047,838 @@ mod linux_node {
                 }
             }
 
-            if stats.rx_packets.load() % 100_000 == 0 && stats.rx_packets.load() != 0 {
-                log::info!(
-                    "stats: rx={}, tx={}, opps={}",
-                    stats.rx_packets.load(),
-                    stats.tx_packets.load(),
-                    stats.opportunities.load()
-                );
+            profiler.enter(Stage::Mirror);
+            if let (Some(mirror), Some(handle)) = (mirror.as_mut(), mirror_handle) {
+                let socket = sockets.get_mut::<UdpSocket>(handle);
+                if !socket.is_open() {
+                    socket.bind(MIRROR_PORT).expect("mirror bind failed");
+                }
+                mirror.flush(socket);
+            }
+
+            profiler.enter(Stage::Timers);
+            let mut due = heapless::Vec::<NodeTimer, 8>::new();
+            // Each timer fires at most once per advance, so `due` has room.
+            timers.advance(now_cycles, |timer| {
+                let _ = due.push(timer);
+            });
+            for timer in due {
+                match timer {
+                    NodeTimer::ExpirySweep => {
+                        if let Some(detector) = anomalies.as_mut() {
+                            detector.sweep(clock.unix_ns(), &SUPPRESSED_POOLS);
+                        }
+                        if let Some(admin) = admin.as_mut() {
+                            let (arbiter, normalizer) = (&state_arbiter, &evaluator.normalizer);
+                            let privacy = &mut tx_state.privacy;
+                            admin.poll(|cmd| match cmd {
+                                AdminCommand::Privacy(cmd) => privacy_command(privacy, cmd),
+                                cmd => admin_command(
+                                    cmd,
+                                    stats,
+                                    &mut profiler,
+                                    &mut quarantine,
+                                    arbiter,
+                                    &clock,
+                                    normalizer,
+                                    &mut chaos,
+                                ),
+                            });
+                        }
+                        if let Some((stream, listener)) =
+                            successor.as_mut().and_then(|l| Some((l.accept()?, l)))
+                        {
+                            // Replies still queued go out; frames not yet read
+                            // stay in the device for the successor.
+                            iface.poll(now, &mut EgressOnly(&mut device), &mut sockets);
+                            if let Some(file) = state_file.as_mut() {
+                                persist_state(file, &tx_state.replies, &mut pool_snapshot);
+                            }
+                            let (token, last_seq) = relay_session.position();
+                            let feed = FeedPosition { token, last_seq };
+                            match hand_over(
+                                &stream,
+                                device.inner(),
+                                state_file.as_ref(),
+                                admin.as_ref(),
+                                listener,
+                                feed,
+                            ) {
+                                Ok(()) => {
+                                    log::info!(
+                                        "Handed over to a successor at seq {last_seq}; exiting"
+                                    );
+                                    tap.release();
+                                    listener.release();
+                                    if let Some(admin) = admin.as_mut() {
+                                        admin.release();
+                                    }
+                                    // The successor writes it from now on.
+                                    state_file = None;
+                                    SHUTDOWN.store(true, Ordering::Relaxed);
+                                }
+                                Err(e) => report(
+                                    NodeError::from(e).context("handover to a successor"),
+                                    "still running",
+                                ),
+                            }
+                        }
+                        tx_state
+                            .emitted
+                            .expire(clock.unix_ms(), config.execution_latency_ms);
+                        // Unacknowledged replies get one more copy, to the
+                        // secondary endpoint, before they are counted lost.
+                        if let Some(acks) = tx_state.acks.as_mut() {
+                            let to = acks.policy().retransmit_to.map(|([a, b, c, d], port)| {
+                                IpEndpoint::new(IpAddress::v4(a, b, c, d), port)
+                            });
+                            acks.sweep(clock.unix_ms(), |unacked| {
+                                let Some(to) = to else { return false };
+                                let udp = sockets.get_mut::<UdpSocket>(udp_handle);
+                                let (nonce, opportunity) = (unacked.nonce, unacked.opportunity);
+                                UdpReply::new(udp, to)
+                                    .send_reply(nonce, opportunity, unacked.rx_ts_ns)
+                                    .is_some()
+                            });
+                            publish_acks(acks, stats);
+                        }
+                        if let Some(Err(e)) = recorder
+                            .as_mut()
+                            .map(|journal| journal.flush(clock.unix_ms()))
+                        {
+                            report(NodeError::from(e).context("timeline recording"), "stopped");
+                            recorder = None;
+                        }
+                    }
+                    NodeTimer::StateSync => {
+                        if let Some(file) = state_file.as_mut() {
+                            persist_state(file, &tx_state.replies, &mut pool_snapshot);
+                        }
+                    }
+                    // Messages held back by pacing, to whichever socket they
+                    // were written for: the executor's TCP stream if it is the peer.
+                    NodeTimer::PaceRelease => {
+                        pacer.release(now_cycles, |to, msg| {
+                            let ([a, b, c, d], port) = to;
+                            let remote = IpEndpoint::new(IpAddress::v4(a, b, c, d), port);
+                            let tcp = sockets.get_mut::<TcpSocket>(tcp_handle);
+                            let buf = if tcp.remote_endpoint() == Some(remote) {
+                                tcp.tx_reserve(msg.len())
+                            } else {
+                                sockets
+                                    .get_mut::<UdpSocket>(udp_handle)
+                                    .send(msg.len(), remote)
+                                    .ok()
+                            };
+                            buf.map(|buf| buf.copy_from_slice(msg)).is_some()
+                        });
+                    }
+                    NodeTimer::PoolRepack => {
+                        let hot = POOL_TABLE.repack();
+                        log::debug!("pool repack: {hot} hot pools");
+                    }
+                    NodeTimer::ThresholdUpdate => {
+                        clock.resync();
+                        if let Some(phc) = &phc {
+                            sync_wire_clock(phc);
+                        }
+                        MIN_AMOUNT_IN.store(config.threshold.compute(&ThresholdInputs {
+                            gas_cost: config.gas_cost,
+                            win_rate_bps: win_window.win_rate_bps(),
+                            p99_cycles: latency_window.p99(),
+                        }));
+                        CONFIDENCE.set_win_rate(win_window.win_rate_bps());
+                        stats.compute_p99_cycles.store(latency_window.p99());
+                        stats
+                            .wire_p50_ns
+                            .store(tx_state.wire_window.percentile(50.0));
+                        stats.wire_p99_ns.store(tx_state.wire_window.p99());
+                        let parse_p99_ns = clock.cycles_to_ns(latency_window.p99());
+                        slo.check([parse_p99_ns, tx_state.wire_window.p99()], |change| {
+                            let (reason, p99, threshold) =
+                                (change.slo.reason(), change.p99_ns, change.threshold_ns);
+                            if change.firing {
+                                log::warn!("SLO alarm {reason}: p99 {p99}ns above {threshold}ns");
+                            } else {
+                                log::info!("SLO alarm {reason} cleared: p99 {p99}ns");
+                            }
+                        });
+                        stats.slo_alarms.store(slo.alarms());
+                    }
+                    NodeTimer::Stats => {
+                        log::info!(
+                            "stats: rx={}, tx={}, opps={}, prices={}, pools={}/{}/{}/{}/{} (ok/rej/gap/conflated/evict), \
+                             relay_sessions={}/{} (resumed/fresh), \
+                             books={}/{} (ok/rej), book_arbs={}, divergence_alerts={}, \
+                             victims={}/{}/{} (replaced/skipped/cancelled), duplicate_replies={}, \
+                             reputation={}/{} (outcomes/skips), privacy_skips={}, \
+                             rejected_src={}, quarantined={}, stale_skips={}, halted_replies={}, strategy_skips={}, \
+                             paced={}/{} (delayed/dropped), \
+                             coalesced={}/{} (batches/replies), \
+                             buffer_full={}/{}/{} (udp_rx_drops/tcp_rx/tx), \
+                             compute_p99={}cyc, wire_p50={}ns, wire_p99={}ns",
+                            stats.rx_packets.load(),
+                            stats.tx_packets.load(),
+                            stats.opportunities.load(),
+                            stats.price_updates.load(),
+                            stats.pool_updates.load(),
+                            stats.pool_update_rejects.load(),
+                            stats.pool_seq_gaps.load(),
+                            stats.pool_conflated.load(),
+                            POOL_TABLE.evictions.load(),
+                            stats.feed_resumes.load(),
+                            stats.feed_fresh_sessions.load(),
+                            stats.book_updates.load(),
+                            stats.book_update_rejects.load(),
+                            stats.book_arbs.load(),
+                            stats.divergence_alerts.load(),
+                            stats.victim_replacements.load(),
+                            stats.victims_skipped.load(),
+                            stats.cancellations.load(),
+                            stats.duplicate_replies.load(),
+                            stats.victim_outcomes.load(),
+                            stats.reputation_skips.load(),
+                            stats.privacy_skips.load(),
+                            stats.rejected_sources.load(),
+                            stats.quarantined.load(),
+                            stats.freshness_drops.load(),
+                            stats.halted_replies.load(),
+                            stats.strategy_skips.load(),
+                            pacer.delayed,
+                            pacer.dropped,
+                            stats.reply_batches.load(),
+                            stats.coalesced_replies.load(),
+                            stats.udp_rx_drops.load(),
+                            stats.tcp_rx_full.load(),
+                            stats.tx_full.load(),
+                            latency_window.p99(),
+                            tx_state.wire_window.percentile(50.0),
+                            tx_state.wire_window.p99()
+                        );
+                        if let Some(horizon) = &horizon {
+                            let counts = horizon.counts;
+                            log::info!(
+                                "horizon: pending={}, parked={}, promoted={}, lapsed={}",
+                                horizon.len(),
+                                counts.parked,
+                                counts.promoted,
+                                counts.lapsed
+                            );
+                        }
+                        if let Some(acks) = &tx_state.acks {
+                            let counts = acks.counts;
+                            log::info!(
+                                "acks: pending={}, acked={}, unacked={}, retransmitted={}, unknown={}",
+                                acks.len(),
+                                counts.acked,
+                                counts.unacked,
+                                counts.retransmitted,
+                                counts.unknown
+                            );
+                        }
+                        if let Some(calibration) = &calibration {
+                            log::info!("calibration: {calibration}");
+                        }
+                        let (executor, relayer) = (executor_flow.stats, relay_flow.stats);
+                        log::info!(
+                            "tcp flow: executor stalled={}ms/{} (ms/stalls), relayer stalled={}ms/{} (ms/stalls), \
+                             relayer xoff/xon={}/{}{}",
+                            executor.stalled_ms,
+                            executor.stalls,
+                            relayer.stalled_ms,
+                            relayer.stalls,
+                            relayer.xoffs,
+                            relayer.xons,
+                            if relay_flow.is_paused() { " (paused)" } else { "" }
+                        );
+                        if cfg!(feature = "alloc_audit") {
+                            log::info!(
+                                "alloc audit: {} hot-path allocations",
+                                alloc_audit::hot_path_allocations()
+                            );
+                        }
+                        if chaos.is_active() {
+                            log::warn!("chaos: {chaos}");
+                        }
+                        stats.sources.for_each(|source| {
+                            log::info!(
+                                "source {}: rx={}, malformed={}, opps={}, profit={}",
+                                source.label(),
+                                source.rx_packets,
+                                source.malformed,
+                                source.opportunities,
+                                source.profit
+                            );
+                        });
+                        for feed in feed_race.feeds() {
+                            let ([a, b, c, d], port) = feed.source;
+                            log::info!(
+                                "pool feed {a}.{b}.{c}.{d}:{port}: first={}, late={}, lag_p50<={}cyc, lag_p99<={}cyc",
+                                feed.first,
+                                feed.late,
+                                feed.lag_percentile(50),
+                                feed.lag_percentile(99)
+                            );
+                            if feed.clock.samples > 0 {
+                                log::info!(
+                                    "pool feed {a}.{b}.{c}.{d}:{port} clock: behind={}us, drift={}ppb, stamps={}",
+                                    feed.clock.offset_ns() / 1_000,
+                                    feed.clock.drift_ppb(),
+                                    feed.clock.samples
+                                );
+                            }
+                        }
+                        log::info!(
+                            "wire: versions={}, v2_updates={}, version_rejects={}",
+                            config.wire_versions,
+                            stats.pool_updates_v2.load(),
+                            stats.wire_version_rejects.load()
+                        );
+                        if let Some(window) = &reorder {
+                            log::info!(
+                                "pool reorder: reordered={}, dropped_late={}, released_gaps={}",
+                                window.stats.reordered,
+                                window.stats.dropped_late,
+                                window.stats.released_gaps
+                            );
+                        }
+                        if let Some(mirror) = &mirror {
+                            log::info!(
+                                "mirror: sent={}, malformed={}, dropped={}",
+                                mirror.stats.sent,
+                                mirror.stats.malformed,
+                                mirror.stats.dropped
+                            );
+                        }
+                        if let Some(fec) = &pool_fec {
+                            log::info!(
+                                "pool fec: recovered={}, unrecovered={}, duplicates={}, rejected={}",
+                                fec.stats.recovered,
+                                fec.stats.unrecovered,
+                                fec.stats.duplicates,
+                                fec.stats.rejected
+                            );
+                        }
+                        for strategy in Strategy::ALL {
+                            let profit = stats.profit[strategy as usize].snapshot();
+                            if profit.count() > 0 {
+                                log::info!(
+                                    "profit {}: n={}, p50<={}, p90<={}, p99<={}",
+                                    strategy.name(),
+                                    profit.count(),
+                                    profit.percentile(50),
+                                    profit.percentile(90),
+                                    profit.percentile(99)
+                                );
+                            }
+                        }
+                        let batch = &stats.rx_batch_sizes;
+                        if batch.percentile(100) != 0 {
+                            log::info!(
+                                "rx batch: now={}, p50={}, p90={}, p99={}",
+                                rx_shaper.size(),
+                                batch.percentile(50),
+                                batch.percentile(90),
+                                batch.percentile(99)
+                            );
+                        }
+                        let [kernel, processing, refill] =
+                            stats.frame_dwell.legs().map(|(_, h)| h.snapshot());
+                        if kernel.count() != 0 {
+                            log::info!(
+                                "frame dwell (p50/p99 cycles): kernel={}/{}, processing={}/{}, refill={}/{}",
+                                kernel.percentile(50),
+                                kernel.percentile(99),
+                                processing.percentile(50),
+                                processing.percentile(99),
+                                refill.percentile(50),
+                                refill.percentile(99)
+                            );
+                        }
+                        if stats.pool_anomalies.load() != 0 {
+                            log::info!(
+                                "anomalies: flagged={}, suppressed={}, skips={}, overflow={}",
+                                stats.pool_anomalies.load(),
+                                SUPPRESSED_POOLS.len(),
+                                SUPPRESSED_POOLS.skips(),
+                                SUPPRESSED_POOLS.overflow()
+                            );
+                        }
+                        if config.leader.is_some() {
+                            log::info!(
+                                "leader: {}, term={}, takeovers={}, yields={}, standby_replies={}",
+                                if LEADERSHIP.is_active(unix_time_ns) {
+                                    "active"
+                                } else {
+                                    "standby"
+                                },
+                                LEADERSHIP.term(),
+                                LEADERSHIP.takeovers(),
+                                LEADERSHIP.yields(),
+                                stats.standby_replies.load()
+                            );
+                        }
+                        if BACKPRESSURE.raises() != 0 {
+                            log::info!(
+                                "backpressure: level={}, raised={}, head_drops={}",
+                                BACKPRESSURE.level().name(),
+                                BACKPRESSURE.raises(),
+                                stats.backpressure_drops.load()
+                            );
+                        }
+                        if idler.mode() != IdleMode::Spin {
+                            log::info!(
+                                "idle: mode={}, waits={}, waited={}us",
+                                idler.mode().name(),
+                                stats.idle_waits.load(),
+                                stats.idle_wait_cycles.load() / cycles_per_us
+                            );
+                        }
+                        if let Some(sim) = &simulator {
+                            log_sim(sim);
+                        }
+                    }
+                }
+            }
+
+            // Parked victims are priced again once per new slot; the ones that
+            // pay now are replied to along the route they came in on.
+            if let Some(horizon) = horizon.as_deref_mut() {
+                let (slot, now_ms) = (CONFIDENCE.newest_slot(), clock.unix_ms());
+                let score = |pending: &Pending<ReplyRoute>| {
+                    let victim = bytemuck::bytes_of(&pending.victim);
+                    let enabled = STRATEGIES.is_enabled(Strategy::Sandwich);
+                    enabled
+                        .then(|| evaluator.score_packet_on(&clock, victim, pending.weight_bps))
+                        .flatten()
+                };
+                horizon.advance(slot, now_ms, score, |pending, opportunity| {
+                    let screened = if pending.sender == [0; 20] {
+                        Screened::Untracked
+                    } else {
+                        Screened::Victim {
+                            sender: pending.sender,
+                            nonce: pending.victim.base.nonce(),
+                            cancel_previous: false,
+                            replacement: false,
+                            weight_bps: pending.weight_bps,
+                        }
+                    };
+                    stats.opportunities.inc();
+                    stats.record_profit(Strategy::Sandwich, opportunity.profit);
+                    stats
+                        .pnl
+                        .record_edge(opportunity.token, opportunity.edge, config.gas_cost);
+                    // Priced now, not on arrival: the reply is stamped so.
+                    let rx_ts_ns = clock.unix_ns();
+                    let opportunity = Some((pending.victim, opportunity));
+                    let pool = pending.victim.base.pool_address;
+                    let reply = match pending.route {
+                        ReplyRoute::Udp(remote) => {
+                            let udp = sockets.get_mut::<UdpSocket>(udp_handle);
+                            let mut tx = RoutedUdpReply::new(
+                                udp,
+                                remote,
+                                &config.reply_routes,
+                                Strategy::Sandwich,
+                                pool,
+                            )
+                            .paced(&mut pacer, now_cycles);
+                            tx_state.emit(
+                                &mut tx,
+                                pending.route,
+                                screened,
+                                opportunity,
+                                rx_ts_ns,
+                                stats,
+                            )
+                        }
+                        ReplyRoute::Tcp(peer) => {
+                            let tcp = sockets.get_mut::<TcpSocket>(tcp_handle);
+                            // The executor it came from is gone.
+                            if tcp.remote_endpoint() != Some(peer) {
+                                return;
+                            }
+                            let to = transport::destination(peer).unwrap_or(([0; 4], 0));
+                            let mut tx = Paced::new(tcp, Some(&mut pacer), to, now_cycles);
+                            tx_state.emit(
+                                &mut tx,
+                                pending.route,
+                                screened,
+                                opportunity,
+                                rx_ts_ns,
+                                stats,
+                            )
+                        }
+                    };
+                    if let Some(reply) = reply {
+                        record(
+                            &mut recorder,
+                            EventKind::Decision,
+                            rx_ts_ns,
+                            bytemuck::bytes_of(&reply),
+                        );
+                    }
+                });
+            }
+
+            // Cancellations follow the route of the reply they withdraw.
+            tx_state.emitted.drain(|entry, reason| {
+                let sent = match entry.route {
+                    ReplyRoute::Udp(remote) => {
+                        let udp = sockets.get_mut::<UdpSocket>(udp_handle);
+                        let pool = entry.victim.base.pool_address;
+                        RoutedUdpReply::new(
+                            udp,
+                            remote,
+                            &config.reply_routes,
+                            Strategy::Sandwich,
+                            pool,
+                        )
+                        .paced(&mut pacer, now_cycles)
+                        .send_cancel(entry.sender, entry.nonce(), reason)
+                    }
+                    ReplyRoute::Tcp(peer) => {
+                        let tcp = sockets.get_mut::<TcpSocket>(tcp_handle);
+                        // The executor disconnected: nobody left to tell.
+                        if tcp.remote_endpoint() != Some(peer) {
+                            return true;
+                        }
+                        let to = transport::destination(peer).unwrap_or(([0; 4], 0));
+                        Paced::new(tcp, Some(&mut pacer), to, now_cycles).send_cancel(
+                            entry.sender,
+                            entry.nonce(),
+                            reason,
+                        )
+                    }
+                };
+                if sent {
+                    stats.cancellations.inc();
+                }
+                sent
+            });
+            profiler.end();
+            // Replies refused for TX room or dropped by the pacer, or its
+            // delay queue half full.
+            let congested = stats.tx_full.load() != tx_full
+                || pacer.dropped != paced_drops
+                || pacer.queued() * 2 >= PACE_QUEUE;
+            tx_pressure.observe(congested, clock.now_cycles(), &BACKPRESSURE);
+            // The TAP device's queue is not in our memory: nothing to monitor.
+            // smoltcp's next timer ends the wait.
+            let timers_due = || match iface.poll_delay(now, &sockets) {
+                Some(delay) => {
+                    (now_cycles + clock.delay_cycles(delay)).saturating_sub(clock.now_cycles())
+                }
+                None => u64::MAX,
+            };
+            let waited = idler.idle_capped(busy, timers_due, |_| false);
+            if waited != 0 {
+                stats.idle_waits.inc();
+                stats.idle_wait_cycles.add(waited);
+            }
+        }
+        if let Some(file) = state_file.as_mut() {
+            persist_state(file, &tx_state.replies, &mut pool_snapshot);
+        }
+        if let Some(Err(e)) = recorder
+            .as_mut()
+            .map(|journal| journal.flush(clock.now_unix_ms()))
+        {
+            log::warn!("timeline recording lost its tail: {e}");
+        }
+        log::info!("Shutting down");
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use mev_zerocopy_node::clock::ReplayClock;
+        use mev_zerocopy_node::processor::mock_pool_entry;
+        use mev_zerocopy_node::wire::encoder::PoolUpdateEncoder;
+
+        const POOL: PoolAddress = [0x42; 20];
+
+        #[test]
+        fn source_staleness_is_judged_by_the_injected_clock() {
+            static TABLE: PoolTable = PoolTable::new();
+            let stats = NodeStats::new();
+            TABLE.upsert(mock_pool_entry(POOL)).unwrap();
+            let mut arbiter = SourceArbiter::new(0, 2_000);
+            let snapshot = arbiter.register(SourceKind::Snapshot, 1, 2_000).unwrap();
+            let mut clock = ReplayClock::at(1_000_000_000_000);
+            let mut buf = [0u8; 64];
+            let mut apply = |clock: &ReplayClock, source, seq| {
+                let update = PoolUpdateEncoder::new(POOL, seq)
+                    .reserves(1_000, 2_000)
+                    .slot(7);
+                let len = update.encode(&mut buf).unwrap();
+                let versions = VersionWindow::default();
+                let payload = &buf[..len];
+                apply_pool_update(
+                    &TABLE,
+                    payload,
+                    versions,
+                    &mut arbiter,
+                    clock,
+                    source,
+                    0,
+                    &stats,
+                )
+            };
+
+            assert_eq!(apply(&clock, WIRE_FEED, 1), Ok(Some(POOL)));
+            // Same slot, the wire feed heard from 2s ago: it still owns the pool.
+            clock.advance_to(1_002_000_000_000);
+            assert_eq!(apply(&clock, snapshot, 1), Ok(None));
+            // A millisecond later by the clock, and only by the clock, it is stale.
+            clock.advance_to(1_002_001_000_000);
+            assert_eq!(apply(&clock, snapshot, 1), Ok(Some(POOL)));
+        }
+    }
+}
+
+mod selftest_cli {
+    use mev_zerocopy_node::alloc_audit;
+    use mev_zerocopy_node::selftest::{self, LatencySelftestConfig};
+
+    const USAGE: &str =
+        "usage: mev-zerocopy-node selftest latency [--iterations N] [--warmup N] [--p99-budget-cycles N]";
+
+    /// Entry point for `selftest ...`; returns the process exit code.
+    pub fn run(args: &[String]) -> i32 {
+        match args.first().map(String::as_str) {
+            Some("latency") => latency(&args[1..]),
+            _ => {
+                eprintln!("{USAGE}");
+                2
+            }
+        }
+    }
+
+    fn latency(args: &[String]) -> i32 {
+        let mut cfg = LatencySelftestConfig::default();
+        if let Some(budget) = std::env::var("MEV_SELFTEST_P99_CYCLES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+        {
+            cfg.p99_budget_cycles = budget;
+        }
+
+        let mut it = args.iter();
+        while let Some(flag) = it.next() {
+            let value = it.next().and_then(|v| v.parse::<u64>().ok());
+            match (flag.as_str(), value) {
+                ("--iterations", Some(v)) if v > 0 => cfg.iterations = v as usize,
+                ("--warmup", Some(v)) => cfg.warmup = v as usize,
+                ("--p99-budget-cycles", Some(v)) => cfg.p99_budget_cycles = v,
+                _ => {
+                    eprintln!("{USAGE}");
+                    return 2;
+                }
+            }
+        }
+
+        let allocations = alloc_audit::hot_path_allocations();
+        let report = selftest::run_latency(&cfg);
+        let allocations = alloc_audit::hot_path_allocations() - allocations;
+        println!(
+            "latency selftest: samples={} p50={} p90={} p99={} p99.9={} max={} (cycles)",
+            report.samples, report.p50, report.p90, report.p99, report.p999, report.max
+        );
+        if allocations > 0 {
+            println!("FAIL: {allocations} heap allocations in the timed loop");
+            return 1;
+        }
+        if report.within_budget(cfg.p99_budget_cycles) {
+            println!(
+                "PASS: p99 {} <= budget {}",
+                report.p99, cfg.p99_budget_cycles
+            );
+            0
+        } else {
+            println!(
+                "FAIL: p99 {} > budget {}",
+                report.p99, cfg.p99_budget_cycles
+            );
+            1
+        }
+    }
+}
+
+mod metrics_cli {
+    use mev_zerocopy_node::metrics_shm::{MetricKind, MetricsReader};
+
+    const USAGE: &str = "usage: mev-zerocopy-node metrics [PATH]";
+
+    /// Entry point for `metrics ...`: print the last snapshot a node
+    /// published to its metrics segment, one `name kind value` line per
+    /// metric. The path defaults to `MEV_METRICS_SHM`.
+    pub fn run(args: &[String]) -> i32 {
+        let path = match args {
+            [path] => path.clone(),
+            [] => match std::env::var("MEV_METRICS_SHM") {
+                Ok(path) => path,
+                Err(_) => {
+                    eprintln!("{USAGE}");
+                    return 2;
+                }
+            },
+            _ => {
+                eprintln!("{USAGE}");
+                return 2;
+            }
+        };
+        let snapshot = match MetricsReader::open(&path).map(|reader| reader.read()) {
+            Ok(Some(snapshot)) => snapshot,
+            Ok(None) => {
+                eprintln!("{path}: not a metrics segment of this version, or busy on every read");
+                return 1;
+            }
+            Err(e) => {
+                eprintln!("{path}: {e}");
+                return 1;
+            }
+        };
+        println!(
+            "# published_ms={} seq={}",
+            snapshot.published_ms, snapshot.seq
+        );
+        for metric in &snapshot.metrics {
+            let kind = match metric.kind() {
+                MetricKind::Counter => "counter",
+                MetricKind::Gauge => "gauge",
+            };
+            println!("{} {kind} {}", metric.name(), metric.value());
+        }
+        0
+    }
+}
+
+mod doctor_cli {
+    use mev_zerocopy_node::config::NodeConfig;
+    use mev_zerocopy_node::doctor::{self, HostFacts, Plan, Status};
+
+    const USAGE: &str = "usage: mev-zerocopy-node doctor";
+
+    /// Entry point for `doctor`: check the `MEV_*` configuration against
+    /// this host's NIC, memory and kernel, printing a fix for every
+    /// mismatch. Fails if any check would keep the node from running.
+    pub fn run(args: &[String]) -> i32 {
+        if !args.is_empty() {
+            eprintln!("{USAGE}");
+            return 2;
+        }
+        let plan = Plan::new(&NodeConfig::from_env());
+        let findings = doctor::diagnose(&plan, &HostFacts::probe(&plan));
+        for finding in &findings {
+            println!("{finding}");
+        }
+        match findings.iter().map(|f| f.status).max() {
+            Some(Status::Fail) => 1,
+            _ => 0,
+        }
+    }
+}
+
+mod dump_pools_cli {
+    use mev_zerocopy_node::admin;
+    use mev_zerocopy_node::testvectors::decode_hex;
+    use std::io::Write;
+
+    const USAGE: &str = "usage: mev-zerocopy-node dump-pools [--socket PATH] [--bin]";
+
+    /// Entry point for `dump-pools ...`: print a running node's pool table,
+    /// as JSON or (`--bin`) raw `PoolStateUpdate` records. The socket
+    /// defaults to `MEV_ADMIN_SOCKET`; `MEV_ADMIN_TOKEN` authenticates.
+    pub fn run(args: &[String]) -> i32 {
+        let mut socket = std::env::var("MEV_ADMIN_SOCKET").ok();
+        let mut binary = false;
+        let mut it = args.iter();
+        while let Some(flag) = it.next() {
+            match (flag.as_str(), it.as_slice().first()) {
+                ("--socket", Some(_)) => socket = it.next().cloned(),
+                ("--bin", _) => binary = true,
+                _ => {
+                    eprintln!("{USAGE}");
+                    return 2;
+                }
+            }
+        }
+        let Some(socket) = socket else {
+            eprintln!("{USAGE}");
+            return 2;
+        };
+        let token = std::env::var("MEV_ADMIN_TOKEN").ok();
+        let command = if binary { "pools bin" } else { "pools" };
+        let reply = match admin::query(&socket, token.as_deref(), command) {
+            Ok(reply) => reply,
+            Err(e) => {
+                eprintln!("{socket}: {e}");
+                return 1;
+            }
+        };
+        let Some(body) = reply.strip_prefix("ok ") else {
+            eprintln!("{socket}: {reply}");
+            return 1;
+        };
+        if !binary {
+            println!("{body}");
+            return 0;
+        }
+        let mut bytes = vec![0u8; body.len() / 2];
+        match decode_hex(body, &mut bytes) {
+            Some(len) if std::io::stdout().write_all(&bytes[..len]).is_ok() => 0,
+            _ => {
+                eprintln!("{socket}: malformed snapshot");
+                1
             }
         }
     }
 }
 
-#[cfg(any(target_os = "linux", target_os = "android"))]
 fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("selftest") => std::process::exit(selftest_cli::run(&args[1..])),
+        Some("dump-pools") => std::process::exit(dump_pools_cli::run(&args[1..])),
+        Some("backtest") => std::process::exit(run_backtest(&args[1..])),
+        Some("replay-check") => std::process::exit(run_replay_check(&args[1..])),
+        Some("metrics") => std::process::exit(metrics_cli::run(&args[1..])),
+        Some("doctor") => std::process::exit(doctor_cli::run(&args[1..])),
+        _ => run_node(),
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn run_node() {
     linux_node::run();
 }
 
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn run_backtest(args: &[String]) -> i32 {
+    linux_node::backtest(args)
+}
+
 #[cfg(not(any(target_os = "linux", target_os = "android")))]
-fn main() {
+fn run_backtest(_args: &[String]) -> i32 {
+    eprintln!("backtest runs the Linux node's evaluator setup; use a Linux runtime.");
+    2
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn run_replay_check(args: &[String]) -> i32 {
+    linux_node::replay_check(args)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn run_replay_check(_args: &[String]) -> i32 {
+    eprintln!("replay-check runs the Linux node's evaluator setup; use a Linux runtime.");
+    2
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn run_node() {
     eprintln!(
         "This node requires Linux/Android kernel networking features. Use Linux runtime for AF_XDP/TAP paths."
     );
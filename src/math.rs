@@ -0,0 +1,314 @@
+//! Integer math primitives for pool pricing.
+//!
+//! Shared by every pool kind so that none re-implements them: integer
+//! square roots, `a * b / c` over a full 256-bit product with an explicit
+//! rounding direction, and the Q64.96 fixed-point helpers concentrated-
+//! liquidity (V3-style) pools are priced with. Everything is `no_std`,
+//! allocation-free and `const` where the language allows; overflow returns
+//! `None` rather than wrapping.
+//!
+//! Prices in Q64.96 are held in a `u128`, so square-root prices are limited
+//! to below 2^32 (prices below 2^64), which covers raw-unit prices of every
+//! token pair the node prices.
+
+/// Which way a result that is not exact is rounded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    /// Toward zero, in the pool's favour for amounts paid out.
+    Down,
+    /// Away from zero, in the pool's favour for amounts paid in.
+    Up,
+}
+
+/// Fractional bits of a Q64.96 number.
+pub const RESOLUTION: u32 = 96;
+
+/// 1.0 in Q64.96.
+pub const Q96: u128 = 1 << RESOLUTION;
+
+/// `floor(sqrt(n))`.
+#[inline(always)]
+pub const fn isqrt(n: u128) -> u64 {
+    n.isqrt() as u64
+}
+
+/// `sqrt(n)`, rounded as asked.
+#[inline(always)]
+pub const fn sqrt(n: u128, rounding: Rounding) -> u64 {
+    let root = isqrt(n);
+    match rounding {
+        Rounding::Up if (root as u128) * (root as u128) < n => root + 1,
+        _ => root,
+    }
+}
+
+/// The full 256-bit product `a * b`, as `(high, low)` halves.
+#[inline(always)]
+pub const fn mul_wide(a: u128, b: u128) -> (u128, u128) {
+    const LOW: u128 = u64::MAX as u128;
+    let (a1, a0, b1, b0) = (a >> 64, a & LOW, b >> 64, b & LOW);
+    let (p00, p01, p10, p11) = (a0 * b0, a0 * b1, a1 * b0, a1 * b1);
+    // At most three 64-bit values: no overflow.
+    let mid = (p00 >> 64) + (p01 & LOW) + (p10 & LOW);
+    (
+        (p11 + (p01 >> 64) + (p10 >> 64) + (mid >> 64)),
+        (p00 & LOW) | (mid << 64),
+    )
+}
+
+/// `(high, low) / d` and its remainder, for `high < d` (the quotient fits
+/// 128 bits).
+const fn div_wide(high: u128, low: u128, d: u128) -> (u128, u128) {
+    let (mut quotient, mut remainder) = (0u128, high);
+    let mut bit = 128;
+    while bit > 0 {
+        bit -= 1;
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> bit) & 1);
+        quotient <<= 1;
+        if carry != 0 || remainder >= d {
+            remainder = remainder.wrapping_sub(d);
+            quotient |= 1;
+        }
+    }
+    (quotient, remainder)
+}
+
+/// `a * b / denominator` without overflow in the product, rounded as
+/// asked. `None` for a zero denominator or a result beyond 128 bits.
+#[inline(always)]
+pub const fn mul_div(a: u128, b: u128, denominator: u128, rounding: Rounding) -> Option<u128> {
+    if denominator == 0 {
+        return None;
+    }
+    let (quotient, remainder) = match a.checked_mul(b) {
+        Some(product) => (product / denominator, product % denominator),
+        None => {
+            let (high, low) = mul_wide(a, b);
+            if high >= denominator {
+                return None;
+            }
+            div_wide(high, low, denominator)
+        }
+    };
+    match rounding {
+        Rounding::Up if remainder != 0 => quotient.checked_add(1),
+        _ => Some(quotient),
+    }
+}
+
+/// `a * b` for Q64.96 `a` and `b`.
+#[inline(always)]
+pub const fn mul_q96(a: u128, b: u128, rounding: Rounding) -> Option<u128> {
+    mul_div(a, b, Q96, rounding)
+}
+
+/// `a / b` for Q64.96 `a` and `b`.
+#[inline(always)]
+pub const fn div_q96(a: u128, b: u128, rounding: Rounding) -> Option<u128> {
+    mul_div(a, Q96, b, rounding)
+}
+
+/// The integer part of a Q64.96 number, rounded as asked.
+#[inline(always)]
+pub const fn from_q96(x: u128, rounding: Rounding) -> u128 {
+    let whole = x >> RESOLUTION;
+    match rounding {
+        Rounding::Up if x & (Q96 - 1) != 0 => whole + 1,
+        _ => whole,
+    }
+}
+
+/// Square-root price, Q64.96, of a pool holding `reserve0` and `reserve1`:
+/// `sqrt(reserve1 / reserve0)`, rounded down; `None` for an empty side.
+pub const fn sqrt_price_x96(reserve0: u64, reserve1: u64) -> Option<u128> {
+    if reserve0 == 0 || reserve1 == 0 {
+        return None;
+    }
+    // The ratio is taken with as many fractional bits as fit 128 (an even
+    // number, `k`), so that its root carries about 64 significant bits:
+    // sqrt(r1 * 2^k / r0) = sqrt(r1 / r0) * 2^(k/2).
+    let magnitude = (64 - reserve1.leading_zeros()) as i32 - (64 - reserve0.leading_zeros()) as i32;
+    let k = ((127 - magnitude) & !1) as u32;
+    let Some(ratio) = mul_div(
+        (reserve1 as u128) << 64,
+        1 << (k - 64),
+        reserve0 as u128,
+        Rounding::Down,
+    ) else {
+        return None;
+    };
+    let root = isqrt(ratio) as u128;
+    Some(if k / 2 >= RESOLUTION {
+        root >> (k / 2 - RESOLUTION)
+    } else {
+        root << (RESOLUTION - k / 2)
+    })
+}
+
+/// Price (`token1` per `token0`), Q64.96, of a Q64.96 square-root price.
+#[inline(always)]
+pub const fn price_x96(sqrt_price_x96: u128, rounding: Rounding) -> Option<u128> {
+    mul_q96(sqrt_price_x96, sqrt_price_x96, rounding)
+}
+
+/// `token0` moved between square-root prices `a` and `b` (either order)
+/// with `liquidity` in range: `liquidity * (1/a - 1/b)`.
+pub const fn amount0_delta(a: u128, b: u128, liquidity: u128, rounding: Rounding) -> Option<u128> {
+    let (lower, upper) = if a < b { (a, b) } else { (b, a) };
+    if lower == 0 {
+        return None;
+    }
+    // liquidity * 2^96 * (upper - lower) / upper / lower
+    let Some(scaled) = mul_div(liquidity, upper - lower, upper, rounding) else {
+        return None;
+    };
+    mul_div(scaled, Q96, lower, rounding)
+}
+
+/// `token1` moved between square-root prices `a` and `b` (either order)
+/// with `liquidity` in range: `liquidity * (b - a)`.
+#[inline(always)]
+pub const fn amount1_delta(a: u128, b: u128, liquidity: u128, rounding: Rounding) -> Option<u128> {
+    mul_div(liquidity, a.abs_diff(b), Q96, rounding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Rounding::{Down, Up};
+
+    #[test]
+    fn sqrt_is_exact_around_perfect_squares() {
+        for root in [
+            0u64,
+            1,
+            2,
+            3,
+            1 << 32,
+            u32::MAX as u64,
+            u64::MAX - 1,
+            u64::MAX,
+        ] {
+            let square = root as u128 * root as u128;
+            assert_eq!((isqrt(square), sqrt(square, Up)), (root, root));
+            if root > 1 {
+                assert_eq!((isqrt(square - 1), sqrt(square - 1, Up)), (root - 1, root));
+            }
+            if (1..u64::MAX).contains(&root) {
+                assert_eq!((isqrt(square + 1), sqrt(square + 1, Up)), (root, root + 1));
+            }
+        }
+        assert_eq!(isqrt(u128::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn mul_div_rounds_as_asked() {
+        assert_eq!(mul_div(6, 7, 3, Down), Some(14));
+        assert_eq!(
+            (mul_div(7, 1, 2, Down), mul_div(7, 1, 2, Up)),
+            (Some(3), Some(4))
+        );
+        assert_eq!(mul_div(u128::MAX, 1, u128::MAX, Up), Some(1));
+        assert_eq!(mul_div(0, u128::MAX, 1, Up), Some(0));
+    }
+
+    #[test]
+    fn mul_div_keeps_256_bit_products() {
+        assert_eq!(
+            mul_div(u128::MAX, u128::MAX, u128::MAX, Down),
+            Some(u128::MAX)
+        );
+        assert_eq!(
+            mul_div(u128::MAX, u128::MAX - 1, u128::MAX, Up),
+            Some(u128::MAX - 1)
+        );
+        assert_eq!(mul_div(u128::MAX, 2, 3, Down), Some(u128::MAX / 3 * 2));
+        assert_eq!(mul_div(1 << 100, 1 << 100, 1 << 73, Down), Some(1 << 127));
+        assert_eq!(
+            mul_div(u128::MAX, u128::MAX, u128::MAX, Up),
+            Some(u128::MAX),
+            "exact: no round-up overflow"
+        );
+    }
+
+    #[test]
+    fn mul_div_rejects_overflow_and_zero_divisors() {
+        assert_eq!(
+            mul_div(1 << 100, 1 << 100, 1 << 72, Down),
+            None,
+            "quotient beyond 128 bits"
+        );
+        assert_eq!(mul_div(u128::MAX, u128::MAX, u128::MAX - 1, Down), None);
+        assert_eq!(mul_div(1, 1, 0, Down), None);
+    }
+
+    #[test]
+    fn mul_div_matches_u128_arithmetic() {
+        let mut x = 0x9E37_79B9_7F4A_7C15u64;
+        for _ in 0..10_000 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            let (a, b, d) = (
+                x as u128,
+                x.rotate_left(21) as u128 >> (x % 64),
+                (x >> (x % 61)) as u128 | 1,
+            );
+            let exact = a * b / d;
+            assert_eq!(mul_div(a, b, d, Down), Some(exact));
+            assert_eq!(mul_div(a, b, d, Up), Some(exact + (a * b % d != 0) as u128));
+            // Through the 256-bit path: a * 2^64 * b / (d * 2^64).
+            if let Some(wide) = d.checked_mul(1 << 64) {
+                assert_eq!(mul_div(a << 64, b, wide, Down), Some(exact), "{a} {b} {d}");
+            }
+        }
+    }
+
+    #[test]
+    fn q96_arithmetic_rounds_as_asked() {
+        assert_eq!(mul_q96(3 * Q96, Q96 / 2, Down), Some(3 * Q96 / 2));
+        assert_eq!(
+            (div_q96(Q96, 3 * Q96, Down), div_q96(Q96, 3 * Q96, Up)),
+            (Some(Q96 / 3), Some(Q96 / 3 + 1))
+        );
+        assert_eq!(
+            (
+                from_q96(Q96 + 1, Down),
+                from_q96(Q96 + 1, Up),
+                from_q96(2 * Q96, Up)
+            ),
+            (1, 2, 2)
+        );
+        assert_eq!(div_q96(1, 0, Down), None);
+    }
+
+    #[test]
+    fn sqrt_price_follows_the_reserves() {
+        assert_eq!(sqrt_price_x96(100, 400), Some(2 * Q96));
+        assert_eq!(sqrt_price_x96(400, 100), Some(Q96 / 2));
+        assert_eq!(sqrt_price_x96(1 << 40, 1 << 20), Some(Q96 >> 10));
+        assert_eq!(sqrt_price_x96(1 << 63, 1 << 1), Some(1 << 65));
+        assert_eq!(
+            (sqrt_price_x96(0, 1), sqrt_price_x96(1, u64::MAX).is_some()),
+            (None, true)
+        );
+        let price = price_x96(sqrt_price_x96(1_000_000, 2_000_000).unwrap(), Down).unwrap();
+        assert!((2 * Q96 - price) < Q96 >> 60, "{price}");
+    }
+
+    #[test]
+    fn amount_deltas_between_two_prices() {
+        // Between prices 1 and 4 (sqrt 1 and 2), liquidity 1000:
+        // token0 1000 * (1 - 1/2) = 500, token1 1000 * (2 - 1) = 1000.
+        let (one, two) = (Q96, 2 * Q96);
+        assert_eq!(amount0_delta(one, two, 1_000, Down), Some(500));
+        assert_eq!(amount0_delta(two, one, 1_000, Up), Some(500));
+        assert_eq!(amount1_delta(two, one, 1_000, Down), Some(1_000));
+        let third = Q96 / 3 * 4;
+        assert_eq!(amount1_delta(one, third, 1, Down), Some(0));
+        assert_eq!(amount1_delta(one, third, 1, Up), Some(1));
+        assert_eq!(amount0_delta(0, one, 1, Down), None);
+        assert_eq!(amount0_delta(one, one, u128::MAX, Up), Some(0));
+    }
+}
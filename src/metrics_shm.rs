@@ -0,0 +1,479 @@
+//! Read-only metrics segment for sidecar scrapers.
+//!
+//! With `MEV_METRICS_SHM=<path>` (usually under `/dev/shm`) a background
+//! thread copies [`NodeStats`] into a small shared file every
+//! `MEV_METRICS_SHM_INTERVAL_MS`: counters as running totals, then the same
+//! gauges the statsd exporter pushes (latency summaries, profit, RX batch
+//! and frame dwell percentiles, SLO alarms, PnL by token). A scraper process maps the
+//! file read-only and never talks to the node, so scraping costs the node
+//! nothing whether or not the statsd exporter runs.
+//!
+//! Layout, little-endian: a [`SegmentHeader`] (magic `MEVMETRC`, version,
+//! capacity, sequence, publication time, metric count), then
+//! [`MAX_METRICS`] [`MetricEntry`] slots of a NUL-padded name, a kind and
+//! a value. The sequence is a seqlock: odd while a snapshot is being
+//! written, bumped to the next even number once it is complete. A reader
+//! copies the snapshot between two loads of the sequence and keeps it only
+//! if both are the same even value; [`MetricsReader`] does exactly that.
+//! A higher [`VERSION`] means a changed layout.
+//!
+//! The segment outlives the node: a scraper tells a stale one by its
+//! publication time.
+//!
+//! [`NodeStats`]: crate::runtime::NodeStats
+use crate::prices::PriceCache;
+use crate::processor::Strategy;
+use crate::runtime::{self, NodeStats};
+use crate::slo::Slo;
+use crate::tokens::{self, ProfitNormalizer};
+use core::sync::atomic::{fence, AtomicBool, AtomicU64, Ordering};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use zerocopy::byteorder::little_endian::{U32, U64};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
+
+/// Metric slots in a segment.
+pub const MAX_METRICS: usize = 128;
+/// Longest metric name; longer ones are truncated.
+pub const METRIC_NAME_LEN: usize = 48;
+/// Segment layout version.
+pub const VERSION: u32 = 1;
+
+const MAGIC: [u8; 8] = *b"MEVMETRC";
+/// Reads that may find a snapshot in progress before giving up.
+const READ_ATTEMPTS: usize = 1_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetricsShmConfig {
+    /// File the segment is mapped from.
+    pub path: String,
+    pub interval_ms: u64,
+}
+
+/// Segment header. `capacity` is [`MAX_METRICS`]; `seq` is the seqlock.
+#[repr(C)]
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+pub struct SegmentHeader {
+    magic: [u8; 8],
+    version: U32,
+    capacity: U32,
+    seq: U64,
+    /// Unix time of the snapshot, ms.
+    published_ms: U64,
+    count: U32,
+    _pad: [u8; 4],
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetricKind {
+    /// Running total since the node started.
+    Counter = 0,
+    Gauge = 1,
+}
+
+/// One metric slot.
+#[repr(C)]
+#[derive(Clone, Copy, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+pub struct MetricEntry {
+    name: [u8; METRIC_NAME_LEN],
+    kind: U32,
+    _pad: [u8; 4],
+    value: U64,
+}
+
+impl MetricEntry {
+    pub fn new(name: &str, kind: MetricKind, value: u64) -> Self {
+        let mut entry = Self {
+            name: [0; METRIC_NAME_LEN],
+            kind: U32::new(kind as u32),
+            _pad: [0; 4],
+            value: U64::new(value),
+        };
+        let len = name.len().min(METRIC_NAME_LEN);
+        entry.name[..len].copy_from_slice(&name.as_bytes()[..len]);
+        entry
+    }
+
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(METRIC_NAME_LEN);
+        core::str::from_utf8(&self.name[..len]).unwrap_or("")
+    }
+
+    pub fn kind(&self) -> MetricKind {
+        if self.kind.get() == MetricKind::Counter as u32 {
+            MetricKind::Counter
+        } else {
+            MetricKind::Gauge
+        }
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value.get()
+    }
+}
+
+impl core::fmt::Debug for MetricEntry {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}={} ({:?})", self.name(), self.value(), self.kind())
+    }
+}
+
+const HEADER_SIZE: usize = core::mem::size_of::<SegmentHeader>();
+const ENTRY_SIZE: usize = core::mem::size_of::<MetricEntry>();
+const SEQ_OFFSET: usize = core::mem::offset_of!(SegmentHeader, seq);
+
+/// Size of the segment file.
+pub const SEGMENT_SIZE: usize = HEADER_SIZE + MAX_METRICS * ENTRY_SIZE;
+
+// Pinned so scrapers in other languages can hard-code the layout; `seq`
+// must be 8-aligned in the page-aligned mapping to be read atomically.
+const _: () = assert!(HEADER_SIZE == 40 && ENTRY_SIZE == 64 && SEQ_OFFSET == 16);
+
+/// A `MAP_SHARED` mapping of a whole segment.
+struct Mapping {
+    ptr: *mut u8,
+}
+
+// SAFETY: the mapping is plain shared memory; writes go through
+// `MetricsWriter`, which takes `&mut self`.
+unsafe impl Send for Mapping {}
+
+impl Mapping {
+    fn new(file: &File, prot: libc::c_int) -> io::Result<Self> {
+        let ptr = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                SEGMENT_SIZE,
+                prot,
+                libc::MAP_SHARED,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            ptr: ptr as *mut u8,
+        })
+    }
+
+    fn seq(&self) -> &AtomicU64 {
+        // SAFETY: in bounds and 8-aligned (see the layout assertion).
+        unsafe { &*(self.ptr.add(SEQ_OFFSET) as *const AtomicU64) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr as *mut libc::c_void, SEGMENT_SIZE) };
+    }
+}
+
+/// The node's side of a segment.
+pub struct MetricsWriter {
+    map: Mapping,
+}
+
+impl MetricsWriter {
+    /// Create the segment at `path`, or reset the one a previous run left,
+    /// in place and under the seqlock so scrapers that have it mapped carry
+    /// on. The file is world-readable so scrapers can run as another user.
+    pub fn create(path: &str) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .mode(0o644)
+            .open(path)?;
+        file.set_len(SEGMENT_SIZE as u64)?;
+        let map = Mapping::new(&file, libc::PROT_READ | libc::PROT_WRITE)?;
+        let header = SegmentHeader {
+            magic: MAGIC,
+            version: U32::new(VERSION),
+            capacity: U32::new(MAX_METRICS as u32),
+            seq: U64::new(0),
+            published_ms: U64::new(0),
+            count: U32::new(0),
+            _pad: [0; 4],
+        };
+        let seq = map.seq();
+        // Odd already if the previous writer died mid-snapshot.
+        let busy = seq.load(Ordering::Relaxed) | 1;
+        seq.store(busy, Ordering::Relaxed);
+        fence(Ordering::Release);
+        let (fixed, rest) = header.as_bytes().split_at(SEQ_OFFSET);
+        // SAFETY: in bounds; `seq` itself is left to the atomic.
+        unsafe {
+            core::ptr::copy_nonoverlapping(fixed.as_ptr(), map.ptr, fixed.len());
+            core::ptr::copy_nonoverlapping(
+                rest[8..].as_ptr(),
+                map.ptr.add(SEQ_OFFSET + 8),
+                rest.len() - 8,
+            );
+        }
+        seq.store(busy + 1, Ordering::Release);
+        Ok(Self { map })
+    }
+
+    /// Publish `metrics` as of unix time `published_ms`; those beyond
+    /// [`MAX_METRICS`] are dropped.
+    pub fn publish(&mut self, published_ms: u64, metrics: &[MetricEntry]) {
+        let metrics = &metrics[..metrics.len().min(MAX_METRICS)];
+        let seq = self.map.seq();
+        let start = seq.load(Ordering::Relaxed);
+        seq.store(start + 1, Ordering::Relaxed);
+        // The odd sequence must be visible before any of the snapshot.
+        fence(Ordering::Release);
+        let header = self.map.ptr;
+        // SAFETY: in bounds; the fields and slots written do not overlap `seq`.
+        unsafe {
+            let published = U64::new(published_ms);
+            let count = U32::new(metrics.len() as u32);
+            let published_at = header.add(core::mem::offset_of!(SegmentHeader, published_ms));
+            core::ptr::copy_nonoverlapping(published.as_bytes().as_ptr(), published_at, 8);
+            let count_at = header.add(core::mem::offset_of!(SegmentHeader, count));
+            core::ptr::copy_nonoverlapping(count.as_bytes().as_ptr(), count_at, 4);
+            let slots = metrics.as_bytes();
+            core::ptr::copy_nonoverlapping(slots.as_ptr(), header.add(HEADER_SIZE), slots.len());
+        }
+        seq.store(start + 2, Ordering::Release);
+    }
+}
+
+/// A consistent copy of a segment.
+#[derive(Clone, Debug)]
+pub struct MetricsSnapshot {
+    /// Sequence number, even; moves on with every snapshot.
+    pub seq: u64,
+    pub published_ms: u64,
+    pub metrics: Vec<MetricEntry>,
+}
+
+impl MetricsSnapshot {
+    pub fn get(&self, name: &str) -> Option<u64> {
+        self.metrics
+            .iter()
+            .find(|m| m.name() == name)
+            .map(MetricEntry::value)
+    }
+}
+
+/// A scraper's read-only view of a segment.
+pub struct MetricsReader {
+    map: Mapping,
+}
+
+impl MetricsReader {
+    pub fn open(path: &str) -> io::Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() < SEGMENT_SIZE as u64 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a metrics segment: too short",
+            ));
+        }
+        Ok(Self {
+            map: Mapping::new(&file, libc::PROT_READ)?,
+        })
+    }
+
+    /// The last published snapshot; `None` if the segment is not one this
+    /// build reads, or a snapshot was in progress on every attempt.
+    pub fn read(&self) -> Option<MetricsSnapshot> {
+        let mut header = [0u8; HEADER_SIZE];
+        let mut slots = vec![0u8; MAX_METRICS * ENTRY_SIZE];
+        let seq = self.map.seq();
+        for _ in 0..READ_ATTEMPTS {
+            let before = seq.load(Ordering::Acquire);
+            if before % 2 == 1 {
+                core::hint::spin_loop();
+                continue;
+            }
+            // SAFETY: in bounds. The copy may race the writer; the sequence
+            // check below discards it if it did.
+            unsafe {
+                core::ptr::copy_nonoverlapping(self.map.ptr, header.as_mut_ptr(), HEADER_SIZE);
+                core::ptr::copy_nonoverlapping(
+                    self.map.ptr.add(HEADER_SIZE),
+                    slots.as_mut_ptr(),
+                    slots.len(),
+                );
+            }
+            fence(Ordering::Acquire);
+            if seq.load(Ordering::Relaxed) != before {
+                continue;
+            }
+            let header = SegmentHeader::ref_from_bytes(&header).ok()?;
+            let valid = header.magic == MAGIC
+                && header.version.get() == VERSION
+                && header.capacity.get() as usize == MAX_METRICS;
+            if !valid {
+                return None;
+            }
+            let count = (header.count.get() as usize).min(MAX_METRICS);
+            let metrics = <[MetricEntry]>::ref_from_prefix_with_elems(&slots, count)
+                .ok()?
+                .0;
+            return Some(MetricsSnapshot {
+                seq: before,
+                published_ms: header.published_ms.get(),
+                metrics: metrics.to_vec(),
+            });
+        }
+        None
+    }
+}
+
+/// Fill `out` (cleared first) with the metrics of `stats`, valuing PnL
+/// with `normalizer` at `prices`.
+pub fn collect(
+    stats: &NodeStats,
+    (normalizer, prices): (&ProfitNormalizer, &PriceCache),
+    out: &mut Vec<MetricEntry>,
+) {
+    use MetricKind::{Counter, Gauge};
+    out.clear();
+    out.extend(
+        stats
+            .counters()
+            .iter()
+            .map(|(name, value)| MetricEntry::new(name, Counter, *value)),
+    );
+    out.extend(
+        stats
+            .gauges()
+            .iter()
+            .map(|(name, value)| MetricEntry::new(name, Gauge, *value)),
+    );
+    for pct in [50, 90, 99] {
+        let name = format!("rx_batch.p{pct}");
+        out.push(MetricEntry::new(
+            &name,
+            Gauge,
+            stats.rx_batch_sizes.percentile(pct) as u64,
+        ));
+    }
+    for strategy in Strategy::ALL {
+        let profit = stats.profit[strategy as usize].snapshot();
+        for pct in [50, 90, 99] {
+            let name = format!("profit.{}.p{pct}", strategy.name());
+            out.push(MetricEntry::new(&name, Gauge, profit.percentile(pct)));
+        }
+    }
+    for (leg, dwell) in stats.frame_dwell.legs() {
+        let dwell = dwell.snapshot();
+        for pct in [50, 99] {
+            out.push(MetricEntry::new(
+                &format!("frame_dwell.{leg}.p{pct}"),
+                Gauge,
+                dwell.percentile(pct),
+            ));
+        }
+    }
+    for (slo, (_, firing)) in Slo::ALL.iter().zip(stats.slo_alarms()) {
+        out.push(MetricEntry::new(
+            &format!("slo_alarm.{}", slo.reason()),
+            Gauge,
+            firing,
+        ));
+    }
+    let pnl = stats.pnl.snapshot(normalizer, Some(prices));
+    out.push(MetricEntry::new("pnl.est_costs", Gauge, pnl.est_costs));
+    out.push(MetricEntry::new("pnl.paid_costs", Gauge, pnl.paid_costs));
+    for (meta, token) in tokens::TOKENS.iter().zip(&pnl.tokens) {
+        let symbol = meta.symbol_str();
+        for (field, value) in [
+            ("opportunities", token.opportunities),
+            ("edge_value", token.edge_value),
+            ("landed", token.landed),
+            ("realized_value", token.realized_value),
+        ] {
+            out.push(MetricEntry::new(
+                &format!("pnl.{field}.{symbol}"),
+                Gauge,
+                value,
+            ));
+        }
+    }
+}
+
+/// Create the segment and start the thread that publishes to it every
+/// `interval_ms` until `stop` is set. The thread runs on `core`.
+pub fn spawn(
+    config: MetricsShmConfig,
+    stats: &'static NodeStats,
+    (normalizer, prices): (ProfitNormalizer, &'static PriceCache),
+    stop: &'static AtomicBool,
+    core: crate::affinity::CoreSlot,
+) -> io::Result<JoinHandle<()>> {
+    let mut writer = MetricsWriter::create(&config.path)?;
+    std::thread::Builder::new()
+        .name("metrics-shm".into())
+        .spawn(move || {
+            crate::affinity::pin_current_thread(core);
+            let mut metrics = Vec::with_capacity(MAX_METRICS);
+            let interval = Duration::from_millis(config.interval_ms);
+            while !stop.load(Ordering::Relaxed) {
+                collect(stats, (&normalizer, prices), &mut metrics);
+                writer.publish(runtime::unix_time_ms(), &metrics);
+                std::thread::sleep(interval);
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scrapers_see_whole_snapshots_only() {
+        let path = std::env::temp_dir().join(format!("mev-metrics-test-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        let mut writer = MetricsWriter::create(path).unwrap();
+        let reader = MetricsReader::open(path).unwrap();
+        let empty = reader.read().unwrap();
+        assert_eq!((empty.published_ms, empty.metrics.len()), (0, 0));
+
+        let stats = NodeStats::new();
+        stats.rx_packets.add(1_520);
+        stats.pnl.record_edge(tokens::USDC, 7_000_000, 0);
+        let mut metrics = Vec::new();
+        collect(
+            &stats,
+            (&ProfitNormalizer::USDC_E6, &PriceCache::new()),
+            &mut metrics,
+        );
+        assert!(metrics.len() <= MAX_METRICS);
+        writer.publish(1_700_000_000_000, &metrics);
+        let snapshot = reader.read().unwrap();
+        assert_eq!(
+            (snapshot.seq, snapshot.published_ms),
+            (empty.seq + 2, 1_700_000_000_000)
+        );
+        assert_eq!(snapshot.metrics[0].kind(), MetricKind::Counter);
+        assert_eq!(snapshot.get("rx_packets"), Some(1_520));
+        assert_eq!(snapshot.get("pnl.edge_value.USDC"), Some(7_000_000));
+        assert_eq!(snapshot.get("slo_alarm.e2e_p99"), Some(0));
+
+        // A writer that died mid-snapshot leaves the sequence odd: nothing
+        // torn is ever returned, and the next node resets the segment under
+        // the mapped scraper.
+        writer.map.seq().fetch_add(1, Ordering::Relaxed);
+        assert!(reader.read().is_none());
+        drop(writer);
+        MetricsWriter::create(path).unwrap();
+        let reset = reader.read().unwrap();
+        assert_eq!((reset.seq, reset.metrics.len()), (snapshot.seq + 2, 0));
+        std::fs::remove_file(path).unwrap();
+    }
+}
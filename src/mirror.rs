@@ -0,0 +1,177 @@
+//! Feed mirroring: re-publish the normalized feed to downstream consumers.
+//!
+//! With a mirror configured the node doubles as a feed normalizer. Every
+//! victim it evaluates and every pool or book update it applies is sent on,
+//! over UDP, to a unicast or multicast endpoint. Other nodes and loggers
+//! then read one clean stream instead of each source:
+//!
+//! - Victims are re-encoded as exactly one `DexSwapTx`, `DexSwapTxExt` or
+//!   `DexSwapTxEvm`. Trailing bytes are cut and reserved fields zeroed.
+//!   Records with a zero pool, zero amount or unknown direction are dropped.
+//! - Pool and book updates are sent only once validated and applied. Late
+//!   copies from a second path, out-of-sequence updates and garbage never
+//!   reach the mirror.
+//!
+//! Messages are queued while the poll is processed and sent afterwards from
+//! a socket of their own, so mirroring never takes TX room from replies.
+use crate::payload::{DexSwapTx, DexSwapTxEvm, DexSwapTxExt};
+use crate::transport::{FrameBatch, Transport, UdpReply};
+use smoltcp::socket::udp;
+use smoltcp::wire::{IpAddress, IpEndpoint};
+
+/// Where mirrored messages go; either stream can be off.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MirrorConfig {
+    /// `MEV_MIRROR_VICTIMS`: normalized victims.
+    pub victims_to: Option<([u8; 4], u16)>,
+    /// `MEV_MIRROR_POOLS`: applied pool and book updates.
+    pub pools_to: Option<([u8; 4], u16)>,
+}
+
+impl MirrorConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.victims_to.is_some() || self.pools_to.is_some()
+    }
+}
+
+/// Counters since startup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct MirrorStats {
+    pub sent: u64,
+    /// Victims that failed normalization.
+    pub malformed: u64,
+    /// Messages lost to a full queue or TX buffer.
+    pub dropped: u64,
+}
+
+/// Re-encode the victim record at the start of `payload` into `out`;
+/// returns its length, or `None` if it is not a valid victim or `out` is
+/// too short. The longest record the payload carries wins, as in the
+/// evaluator.
+pub fn normalize_victim(payload: &[u8], out: &mut [u8]) -> Option<usize> {
+    let tx = DexSwapTx::header(payload)?;
+    if tx.pool_address == [0; 20] || tx.amount_in() == 0 || tx.token_direction > 1 {
+        return None;
+    }
+    let base = DexSwapTx::from_parts(
+        tx.nonce(),
+        tx.pool_address,
+        tx.amount_in(),
+        tx.min_amount_out(),
+        tx.token_direction,
+    );
+    let copy = |bytes: &[u8], out: &mut [u8]| {
+        out.get_mut(..bytes.len())?.copy_from_slice(bytes);
+        Some(bytes.len())
+    };
+    let ext = DexSwapTxExt::parse(payload).map(|e| {
+        DexSwapTxExt::from_parts(base, e.deadline_ms()).with_priority_fee(e.priority_fee())
+    });
+    match (ext, DexSwapTxEvm::parse(payload)) {
+        (Some(ext), Some(evm)) => copy(
+            bytemuck::bytes_of(&DexSwapTxEvm::from_parts(ext, evm.sender, evm.gas_price())),
+            out,
+        ),
+        (Some(ext), None) => copy(bytemuck::bytes_of(&ext), out),
+        _ => copy(bytemuck::bytes_of(&base), out),
+    }
+}
+
+/// Messages queued during one poll, and the counters.
+pub struct Mirror {
+    config: MirrorConfig,
+    /// Each frame's source field holds its destination.
+    queue: FrameBatch<64, { 16 * 1024 }>,
+    pub stats: MirrorStats,
+}
+
+impl Mirror {
+    pub fn new(config: MirrorConfig) -> Self {
+        Self {
+            config,
+            queue: FrameBatch::new(),
+            stats: MirrorStats::default(),
+        }
+    }
+
+    /// Queue a victim the node is about to evaluate.
+    pub fn victim(&mut self, payload: &[u8]) {
+        let Some(to) = self.config.victims_to else {
+            return;
+        };
+        let mut record = [0u8; DexSwapTxEvm::WIRE_SIZE];
+        match normalize_victim(payload, &mut record) {
+            Some(len) => self.queue(&record[..len], to),
+            None => self.stats.malformed += 1,
+        }
+    }
+
+    /// Queue a pool or book update the node applied.
+    pub fn update(&mut self, payload: &[u8]) {
+        if let Some(to) = self.config.pools_to {
+            self.queue(payload, to);
+        }
+    }
+
+    fn queue(&mut self, message: &[u8], to: ([u8; 4], u16)) {
+        if !self.queue.push(message, 0, to) {
+            self.stats.dropped += 1;
+        }
+    }
+
+    /// Send everything queued from `socket`.
+    pub fn flush(&mut self, socket: &mut udp::Socket<'_>) {
+        for (frame, message) in self.queue.iter() {
+            let ([a, b, c, d], port) = frame.source;
+            let remote = IpEndpoint::new(IpAddress::v4(a, b, c, d), port);
+            match UdpReply::new(socket, remote).tx_reserve(message.len()) {
+                Some(buf) => {
+                    buf.copy_from_slice(message);
+                    self.stats.sent += 1;
+                }
+                None => self.stats.dropped += 1,
+            }
+        }
+        self.queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn victims_are_cut_to_their_record_and_reserved_bytes_zeroed() {
+        let tx = DexSwapTx::from_parts(7, [0xaa; 20], 1_000, 900, 1);
+        let mut payload = [0u8; 70];
+        payload[..DexSwapTx::WIRE_SIZE].copy_from_slice(bytemuck::bytes_of(&tx));
+        payload[DexSwapTx::WIRE_SIZE - 1] = 0xff;
+        payload[DexSwapTx::WIRE_SIZE..DexSwapTx::WIRE_SIZE + 8]
+            .copy_from_slice(&5_000u64.to_le_bytes());
+        payload[DexSwapTxExt::WIRE_SIZE - 8..]
+            .copy_from_slice(&[3, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+
+        let mut out = [0u8; DexSwapTxEvm::WIRE_SIZE];
+        assert_eq!(
+            normalize_victim(&payload, &mut out),
+            Some(DexSwapTxExt::WIRE_SIZE)
+        );
+        let expected = DexSwapTxExt::from_parts(tx, 5_000).with_priority_fee(3);
+        assert_eq!(
+            &out[..DexSwapTxExt::WIRE_SIZE],
+            bytemuck::bytes_of(&expected)
+        );
+        assert_eq!(
+            normalize_victim(&payload[..50], &mut out),
+            Some(DexSwapTx::WIRE_SIZE)
+        );
+        assert_eq!(&out[..DexSwapTx::WIRE_SIZE], bytemuck::bytes_of(&tx));
+
+        let zero_amount = DexSwapTx::from_parts(7, [0xaa; 20], 0, 900, 1);
+        assert_eq!(
+            normalize_victim(bytemuck::bytes_of(&zero_amount), &mut out),
+            None
+        );
+        assert_eq!(normalize_victim(&payload[..40], &mut out), None);
+    }
+}
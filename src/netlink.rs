@@ -0,0 +1,465 @@
+//! Minimal `NETLINK_ROUTE` client for configuring the node's interfaces.
+//!
+//! Only what the node itself needs — bringing a link up or down, assigning an
+//! IPv4 address, deleting a link, and pinning the reply path to executors
+//! (host routes, permanent neighbor entries, root qdisc) — without `ip(8)`,
+//! `tc(8)` or an `rtnetlink` crate.
+//! Every request asks for an ACK and blocks until the kernel answers; none of
+//! this runs on the hot path.
+//!
+//! Messages are built by [`NlMsg`], which is independent of the socket so
+//! the encoding can be checked in tests.
+
+const NLMSG_HDR_LEN: usize = 16;
+const NLMSG_ERROR: u16 = 2;
+const NLM_F_REQUEST: u16 = 0x01;
+const NLM_F_ACK: u16 = 0x04;
+const NLM_F_REPLACE: u16 = 0x100;
+const NLM_F_EXCL: u16 = 0x200;
+const NLM_F_CREATE: u16 = 0x400;
+const RTM_NEWLINK: u16 = 16;
+const RTM_DELLINK: u16 = 17;
+const RTM_NEWADDR: u16 = 20;
+const RTM_NEWROUTE: u16 = 24;
+const RTM_NEWNEIGH: u16 = 28;
+const RTM_NEWQDISC: u16 = 36;
+const IFLA_MTU: u16 = 4;
+const IFA_ADDRESS: u16 = 1;
+const IFA_LOCAL: u16 = 2;
+const RTA_DST: u16 = 1;
+const RTA_OIF: u16 = 4;
+const NDA_DST: u16 = 1;
+const NDA_LLADDR: u16 = 2;
+const TCA_KIND: u16 = 1;
+const IFF_UP: u32 = 0x1;
+const AF_INET: u8 = 2;
+const AF_UNSPEC: u8 = 0;
+const RT_TABLE_MAIN: u8 = 254;
+const RTPROT_STATIC: u8 = 4;
+const RT_SCOPE_LINK: u8 = 253;
+const RTN_UNICAST: u8 = 1;
+const NUD_PERMANENT: u16 = 0x80;
+const TC_H_ROOT: u32 = 0xffff_ffff;
+
+/// Maximum number of executor endpoints on the reply path.
+pub const MAX_EXECUTORS: usize = 16;
+
+/// An executor the node replies to directly on the reply interface: a /32
+/// route and a permanent neighbor entry are installed for it, so the first
+/// reply never waits on ARP or a route lookup miss.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ExecutorNeighbor {
+    pub addr: [u8; 4],
+    pub mac: [u8; 6],
+}
+
+/// Root qdisc for the reply interface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplyQdisc {
+    /// No queue: replies go straight to the driver.
+    NoQueue,
+    /// One child qdisc per hardware TX queue, no shared root lock.
+    Mq,
+}
+
+impl ReplyQdisc {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "noqueue" => Some(Self::NoQueue),
+            "mq" => Some(Self::Mq),
+            _ => None,
+        }
+    }
+
+    /// `TCA_KIND`, NUL-terminated.
+    fn kind(self) -> &'static [u8] {
+        match self {
+            Self::NoQueue => b"noqueue\0",
+            Self::Mq => b"mq\0",
+        }
+    }
+}
+
+/// Largest request the node builds.
+const MAX_MSG_LEN: usize = 256;
+
+/// Errors from a netlink request; errnos are positive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetlinkError {
+    Socket(i32),
+    Send(i32),
+    Recv(i32),
+    /// The kernel rejected the request.
+    Kernel(i32),
+    /// The reply was not an ACK for our request.
+    BadReply,
+    IfNotFound,
+}
+
+impl core::fmt::Display for NetlinkError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Socket(e) => write!(f, "netlink socket failed (errno={})", e),
+            Self::Send(e) => write!(f, "netlink send failed (errno={})", e),
+            Self::Recv(e) => write!(f, "netlink recv failed (errno={})", e),
+            Self::Kernel(e) => write!(f, "netlink request rejected (errno={})", e),
+            Self::BadReply => write!(f, "unexpected netlink reply"),
+            Self::IfNotFound => write!(f, "network interface not found"),
+        }
+    }
+}
+
+impl std::error::Error for NetlinkError {}
+
+/// One netlink request: header, family-specific body, then attributes.
+pub struct NlMsg {
+    buf: [u8; MAX_MSG_LEN],
+    len: usize,
+}
+
+impl NlMsg {
+    pub fn new(msg_type: u16, flags: u16, seq: u32) -> Self {
+        let mut msg = Self {
+            buf: [0; MAX_MSG_LEN],
+            len: NLMSG_HDR_LEN,
+        };
+        msg.buf[4..6].copy_from_slice(&msg_type.to_ne_bytes());
+        msg.buf[6..8].copy_from_slice(&(flags | NLM_F_REQUEST | NLM_F_ACK).to_ne_bytes());
+        msg.buf[8..12].copy_from_slice(&seq.to_ne_bytes());
+        msg
+    }
+
+    /// Append raw bytes, padded to the 4-byte netlink alignment.
+    pub fn push(&mut self, bytes: &[u8]) -> &mut Self {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len = (self.len + bytes.len() + 3) & !3;
+        self
+    }
+
+    /// Append one `rtattr`.
+    pub fn attr(&mut self, kind: u16, data: &[u8]) -> &mut Self {
+        let rta_len = (4 + data.len()) as u16;
+        let mut header = [0u8; 4];
+        header[..2].copy_from_slice(&rta_len.to_ne_bytes());
+        header[2..].copy_from_slice(&kind.to_ne_bytes());
+        self.buf[self.len..self.len + 4].copy_from_slice(&header);
+        self.len += 4;
+        self.push(data)
+    }
+
+    pub fn seq(&self) -> u32 {
+        u32::from_ne_bytes(self.buf[8..12].try_into().unwrap())
+    }
+
+    /// The finished message, with its total length filled in.
+    pub fn bytes(&mut self) -> &[u8] {
+        let len = self.len as u32;
+        self.buf[..4].copy_from_slice(&len.to_ne_bytes());
+        &self.buf[..self.len]
+    }
+}
+
+/// `struct ifinfomsg`: family, type, index, flags, change mask.
+fn ifinfomsg(index: u32, flags: u32, change: u32) -> [u8; 16] {
+    let mut body = [0u8; 16];
+    body[4..8].copy_from_slice(&index.to_ne_bytes());
+    body[8..12].copy_from_slice(&flags.to_ne_bytes());
+    body[12..16].copy_from_slice(&change.to_ne_bytes());
+    body
+}
+
+/// `RTM_NEWLINK` setting or clearing `IFF_UP`.
+pub fn set_link_up_msg(index: u32, up: bool, seq: u32) -> NlMsg {
+    let mut msg = NlMsg::new(RTM_NEWLINK, 0, seq);
+    msg.push(&ifinfomsg(index, if up { IFF_UP } else { 0 }, IFF_UP));
+    msg
+}
+
+/// `RTM_NEWLINK` setting the link MTU.
+pub fn set_mtu_msg(index: u32, mtu: u32, seq: u32) -> NlMsg {
+    let mut msg = NlMsg::new(RTM_NEWLINK, 0, seq);
+    msg.push(&ifinfomsg(index, 0, 0))
+        .attr(IFLA_MTU, &mtu.to_ne_bytes());
+    msg
+}
+
+/// `RTM_DELLINK` for `index`.
+pub fn delete_link_msg(index: u32, seq: u32) -> NlMsg {
+    let mut msg = NlMsg::new(RTM_DELLINK, 0, seq);
+    msg.push(&ifinfomsg(index, 0, 0));
+    msg
+}
+
+/// `RTM_NEWADDR` assigning `addr/prefix_len` to `index`.
+pub fn add_address_msg(index: u32, addr: [u8; 4], prefix_len: u8, seq: u32) -> NlMsg {
+    let mut msg = NlMsg::new(RTM_NEWADDR, NLM_F_CREATE | NLM_F_EXCL, seq);
+    // struct ifaddrmsg: family, prefixlen, flags, scope, index
+    let mut body = [AF_INET, prefix_len, 0, 0, 0, 0, 0, 0];
+    body[4..].copy_from_slice(&index.to_ne_bytes());
+    msg.push(&body)
+        .attr(IFA_LOCAL, &addr)
+        .attr(IFA_ADDRESS, &addr);
+    msg
+}
+
+/// `RTM_NEWROUTE` for a /32 link-scope route to `dst` out of `index`.
+pub fn host_route_msg(index: u32, dst: [u8; 4], seq: u32) -> NlMsg {
+    let mut msg = NlMsg::new(RTM_NEWROUTE, NLM_F_CREATE | NLM_F_REPLACE, seq);
+    // struct rtmsg: family, dst_len, src_len, tos, table, protocol, scope, type, flags
+    let body = [
+        AF_INET,
+        32,
+        0,
+        0,
+        RT_TABLE_MAIN,
+        RTPROT_STATIC,
+        RT_SCOPE_LINK,
+        RTN_UNICAST,
+        0,
+        0,
+        0,
+        0,
+    ];
+    msg.push(&body)
+        .attr(RTA_DST, &dst)
+        .attr(RTA_OIF, &index.to_ne_bytes());
+    msg
+}
+
+/// `RTM_NEWNEIGH` for a permanent `addr` → `mac` entry on `index`.
+pub fn neighbor_msg(index: u32, addr: [u8; 4], mac: [u8; 6], seq: u32) -> NlMsg {
+    let mut msg = NlMsg::new(RTM_NEWNEIGH, NLM_F_CREATE | NLM_F_REPLACE, seq);
+    // struct ndmsg: family, pad, pad, ifindex, state, flags, type
+    let mut body = [AF_INET, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    body[4..8].copy_from_slice(&index.to_ne_bytes());
+    body[8..10].copy_from_slice(&NUD_PERMANENT.to_ne_bytes());
+    msg.push(&body).attr(NDA_DST, &addr).attr(NDA_LLADDR, &mac);
+    msg
+}
+
+/// `RTM_NEWQDISC` replacing the root qdisc of `index`.
+pub fn root_qdisc_msg(index: u32, qdisc: ReplyQdisc, seq: u32) -> NlMsg {
+    let mut msg = NlMsg::new(RTM_NEWQDISC, NLM_F_CREATE | NLM_F_REPLACE, seq);
+    // struct tcmsg: family, pad, pad, ifindex, handle, parent, info
+    let mut body = [0u8; 20];
+    body[0] = AF_UNSPEC;
+    body[4..8].copy_from_slice(&index.to_ne_bytes());
+    body[12..16].copy_from_slice(&TC_H_ROOT.to_ne_bytes());
+    msg.push(&body).attr(TCA_KIND, qdisc.kind());
+    msg
+}
+
+/// Parse the kernel's answer to request `seq`: `Ok` for a zero-error ACK.
+pub fn parse_ack(reply: &[u8], seq: u32) -> Result<(), NetlinkError> {
+    let header = reply
+        .get(..NLMSG_HDR_LEN + 4)
+        .ok_or(NetlinkError::BadReply)?;
+    let msg_type = u16::from_ne_bytes([header[4], header[5]]);
+    let reply_seq = u32::from_ne_bytes(header[8..12].try_into().unwrap());
+    if msg_type != NLMSG_ERROR || reply_seq != seq {
+        return Err(NetlinkError::BadReply);
+    }
+    match i32::from_ne_bytes(header[16..20].try_into().unwrap()) {
+        0 => Ok(()),
+        e => Err(NetlinkError::Kernel(-e)),
+    }
+}
+
+/// Interface index of `name`.
+pub fn if_index(name: &str) -> Result<u32, NetlinkError> {
+    let name = std::ffi::CString::new(name).map_err(|_| NetlinkError::IfNotFound)?;
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(NetlinkError::IfNotFound),
+        index => Ok(index),
+    }
+}
+
+fn errno() -> i32 {
+    unsafe { *libc::__errno_location() }
+}
+
+/// A `NETLINK_ROUTE` socket. Requests need `CAP_NET_ADMIN`.
+pub struct Netlink {
+    fd: i32,
+    seq: u32,
+}
+
+impl Netlink {
+    pub fn open() -> Result<Self, NetlinkError> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd < 0 {
+            return Err(NetlinkError::Socket(errno()));
+        }
+        Ok(Self { fd, seq: 0 })
+    }
+
+    fn next_seq(&mut self) -> u32 {
+        self.seq = self.seq.wrapping_add(1);
+        self.seq
+    }
+
+    /// Send one request and wait for its ACK.
+    pub fn request(&mut self, msg: &mut NlMsg) -> Result<(), NetlinkError> {
+        let seq = msg.seq();
+        let bytes = msg.bytes();
+        let sent = unsafe { libc::send(self.fd, bytes.as_ptr().cast(), bytes.len(), 0) };
+        if sent < 0 {
+            return Err(NetlinkError::Send(errno()));
+        }
+        let mut reply = [0u8; 1024];
+        let n = unsafe { libc::recv(self.fd, reply.as_mut_ptr().cast(), reply.len(), 0) };
+        if n < 0 {
+            return Err(NetlinkError::Recv(errno()));
+        }
+        parse_ack(&reply[..n as usize], seq)
+    }
+
+    pub fn set_link_up(&mut self, index: u32, up: bool) -> Result<(), NetlinkError> {
+        let seq = self.next_seq();
+        self.request(&mut set_link_up_msg(index, up, seq))
+    }
+
+    pub fn set_mtu(&mut self, index: u32, mtu: u32) -> Result<(), NetlinkError> {
+        let seq = self.next_seq();
+        self.request(&mut set_mtu_msg(index, mtu, seq))
+    }
+
+    pub fn delete_link(&mut self, index: u32) -> Result<(), NetlinkError> {
+        let seq = self.next_seq();
+        self.request(&mut delete_link_msg(index, seq))
+    }
+
+    /// Route `executor` directly out of `index` and pin its MAC.
+    pub fn add_executor(
+        &mut self,
+        index: u32,
+        executor: &ExecutorNeighbor,
+    ) -> Result<(), NetlinkError> {
+        let seq = self.next_seq();
+        self.request(&mut host_route_msg(index, executor.addr, seq))?;
+        let seq = self.next_seq();
+        self.request(&mut neighbor_msg(index, executor.addr, executor.mac, seq))
+    }
+
+    pub fn set_root_qdisc(&mut self, index: u32, qdisc: ReplyQdisc) -> Result<(), NetlinkError> {
+        let seq = self.next_seq();
+        self.request(&mut root_qdisc_msg(index, qdisc, seq))
+    }
+
+    /// Assign `addr/prefix_len`; an address that is already there is fine.
+    pub fn add_address(
+        &mut self,
+        index: u32,
+        addr: [u8; 4],
+        prefix_len: u8,
+    ) -> Result<(), NetlinkError> {
+        let seq = self.next_seq();
+        match self.request(&mut add_address_msg(index, addr, prefix_len, seq)) {
+            Err(NetlinkError::Kernel(libc::EEXIST)) => Ok(()),
+            result => result,
+        }
+    }
+}
+
+impl Drop for Netlink {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_request_layout() {
+        let mut msg = add_address_msg(7, [192, 168, 69, 1], 24, 3);
+        let bytes = msg.bytes();
+        // header 16 + ifaddrmsg 8 + two 8-byte IPv4 attributes
+        assert_eq!(bytes.len(), 40);
+        assert_eq!(u32::from_ne_bytes(bytes[..4].try_into().unwrap()), 40);
+        assert_eq!(u16::from_ne_bytes([bytes[4], bytes[5]]), RTM_NEWADDR);
+        let flags = u16::from_ne_bytes([bytes[6], bytes[7]]);
+        assert_eq!(flags, NLM_F_REQUEST | NLM_F_ACK | NLM_F_CREATE | NLM_F_EXCL);
+        assert_eq!(&bytes[16..20], &[AF_INET, 24, 0, 0]);
+        assert_eq!(u32::from_ne_bytes(bytes[20..24].try_into().unwrap()), 7);
+        assert_eq!(u16::from_ne_bytes([bytes[24], bytes[25]]), 8);
+        assert_eq!(u16::from_ne_bytes([bytes[26], bytes[27]]), IFA_LOCAL);
+        assert_eq!(&bytes[28..32], &[192, 168, 69, 1]);
+        assert_eq!(&bytes[36..40], &[192, 168, 69, 1]);
+    }
+
+    #[test]
+    fn executor_requests_layout() {
+        let mut route = host_route_msg(3, [10, 0, 0, 9], 1);
+        let bytes = route.bytes();
+        // header 16 + rtmsg 12 + RTA_DST 8 + RTA_OIF 8
+        assert_eq!(bytes.len(), 44);
+        assert_eq!(
+            &bytes[16..24],
+            &[
+                AF_INET,
+                32,
+                0,
+                0,
+                RT_TABLE_MAIN,
+                RTPROT_STATIC,
+                RT_SCOPE_LINK,
+                RTN_UNICAST
+            ]
+        );
+        assert_eq!(&bytes[32..36], &[10, 0, 0, 9]);
+        assert_eq!(u32::from_ne_bytes(bytes[40..44].try_into().unwrap()), 3);
+
+        let mut neigh = neighbor_msg(3, [10, 0, 0, 9], [2, 0, 0, 0, 0, 9], 2);
+        let bytes = neigh.bytes();
+        // header 16 + ndmsg 12 + NDA_DST 8 + NDA_LLADDR 10 padded to 12
+        assert_eq!(bytes.len(), 48);
+        assert_eq!(u16::from_ne_bytes([bytes[24], bytes[25]]), NUD_PERMANENT);
+        assert_eq!(u16::from_ne_bytes([bytes[36], bytes[37]]), 10);
+        assert_eq!(&bytes[40..46], &[2, 0, 0, 0, 0, 9]);
+
+        let mut qdisc = root_qdisc_msg(3, ReplyQdisc::NoQueue, 3);
+        let bytes = qdisc.bytes();
+        // header 16 + tcmsg 20 + TCA_KIND "noqueue\0"
+        assert_eq!(bytes.len(), 48);
+        assert_eq!(
+            u32::from_ne_bytes(bytes[28..32].try_into().unwrap()),
+            TC_H_ROOT
+        );
+        assert_eq!(&bytes[40..48], b"noqueue\0");
+    }
+
+    #[test]
+    fn mtu_request_layout() {
+        let mut msg = set_mtu_msg(5, 9000, 1);
+        let bytes = msg.bytes();
+        // header 16 + ifinfomsg 16 + IFLA_MTU 8
+        assert_eq!(bytes.len(), 40);
+        assert_eq!(u16::from_ne_bytes([bytes[4], bytes[5]]), RTM_NEWLINK);
+        assert_eq!(u32::from_ne_bytes(bytes[20..24].try_into().unwrap()), 5);
+        assert_eq!(u16::from_ne_bytes([bytes[34], bytes[35]]), IFLA_MTU);
+        assert_eq!(u32::from_ne_bytes(bytes[36..40].try_into().unwrap()), 9000);
+    }
+
+    #[test]
+    fn ack_is_matched_by_sequence() {
+        let ack = |seq: u32, error: i32| {
+            let mut reply = [0u8; 36];
+            reply[4..6].copy_from_slice(&NLMSG_ERROR.to_ne_bytes());
+            reply[8..12].copy_from_slice(&seq.to_ne_bytes());
+            reply[16..20].copy_from_slice(&error.to_ne_bytes());
+            reply
+        };
+        assert_eq!(parse_ack(&ack(5, 0), 5), Ok(()));
+        assert_eq!(parse_ack(&ack(5, -17), 5), Err(NetlinkError::Kernel(17)));
+        assert_eq!(parse_ack(&ack(4, 0), 5), Err(NetlinkError::BadReply));
+        assert_eq!(parse_ack(&[0; 8], 5), Err(NetlinkError::BadReply));
+    }
+}
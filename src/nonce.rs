@@ -0,0 +1,216 @@
+//! Victim nonce and fee-replacement (RBF) tracking for EVM payloads.
+//!
+//! Pending victims are remembered by `(sender, nonce)`. A later payload with
+//! the same key and a higher gas price replaces the earlier one; if an
+//! opportunity was already emitted against the replaced transaction it must
+//! be cancelled, because the transaction it sandwiches will never be mined.
+//!
+//! Both tables are direct-mapped and fixed-size: a colliding entry simply
+//! overwrites the previous one. Losing an entry only costs a missed
+//! replacement or gap check, never a wrong cancellation.
+use crate::pool_registry::address_hash;
+
+/// EVM account address.
+pub type Address = [u8; 20];
+
+/// Outcome of observing one victim transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Observation {
+    /// First sighting of this `(sender, nonce)`.
+    Fresh,
+    /// Higher-fee replacement of a pending transaction. `cancel` is set if
+    /// an opportunity was emitted against the one it replaces.
+    Replacement { cancel: bool },
+    /// Does not outbid the pending transaction with the same nonce, so it
+    /// will not replace it.
+    Underpriced,
+    /// Nonce beyond the sender's next one: cannot be mined before the
+    /// missing transactions are.
+    NonceGap { expected: u64, got: u64 },
+}
+
+impl Observation {
+    /// Whether the transaction is worth evaluating.
+    #[inline(always)]
+    pub fn should_evaluate(self) -> bool {
+        matches!(self, Observation::Fresh | Observation::Replacement { .. })
+    }
+}
+
+#[derive(Clone, Copy)]
+struct PendingTx {
+    sender: Address,
+    nonce: u64,
+    gas_price: u64,
+    emitted: bool,
+    occupied: bool,
+}
+
+impl PendingTx {
+    const EMPTY: Self = Self {
+        sender: [0; 20],
+        nonce: 0,
+        gas_price: 0,
+        emitted: false,
+        occupied: false,
+    };
+}
+
+#[derive(Clone, Copy)]
+struct SenderNonce {
+    sender: Address,
+    /// Highest nonce seen plus one.
+    next: u64,
+    occupied: bool,
+}
+
+impl SenderNonce {
+    const EMPTY: Self = Self {
+        sender: [0; 20],
+        next: 0,
+        occupied: false,
+    };
+}
+
+/// Per-sender nonces and pending victims; `N` slots per table.
+pub struct NonceTracker<const N: usize> {
+    pending: [PendingTx; N],
+    senders: [SenderNonce; N],
+    /// Occupied slots overwritten by a colliding key.
+    pub overwrites: u64,
+}
+
+impl<const N: usize> NonceTracker<N> {
+    const VALID: () = assert!(N.is_power_of_two(), "N must be a power of two");
+
+    pub const fn new() -> Self {
+        let () = Self::VALID;
+        Self {
+            pending: [PendingTx::EMPTY; N],
+            senders: [SenderNonce::EMPTY; N],
+            overwrites: 0,
+        }
+    }
+
+    #[inline(always)]
+    fn sender_slot(sender: &Address) -> usize {
+        (address_hash(sender) >> 32) as usize & (N - 1)
+    }
+
+    #[inline(always)]
+    fn pending_slot(sender: &Address, nonce: u64) -> usize {
+        ((address_hash(sender) ^ nonce.wrapping_mul(0x9E37_79B9_7F4A_7C15)) >> 32) as usize
+            & (N - 1)
+    }
+
+    /// Classify a victim transaction and remember it.
+    pub fn observe(&mut self, sender: &Address, nonce: u64, gas_price: u64) -> Observation {
+        let tx = &mut self.pending[Self::pending_slot(sender, nonce)];
+        if tx.occupied && tx.sender == *sender && tx.nonce == nonce {
+            if gas_price <= tx.gas_price {
+                return Observation::Underpriced;
+            }
+            let cancel = tx.emitted;
+            (tx.gas_price, tx.emitted) = (gas_price, false);
+            return Observation::Replacement { cancel };
+        }
+
+        let known = &mut self.senders[Self::sender_slot(sender)];
+        if known.occupied && known.sender == *sender {
+            if nonce > known.next {
+                return Observation::NonceGap {
+                    expected: known.next,
+                    got: nonce,
+                };
+            }
+            known.next = known.next.max(nonce.saturating_add(1));
+        } else {
+            self.overwrites += known.occupied as u64;
+            *known = SenderNonce {
+                sender: *sender,
+                next: nonce.saturating_add(1),
+                occupied: true,
+            };
+        }
+
+        self.overwrites += tx.occupied as u64;
+        *tx = PendingTx {
+            sender: *sender,
+            nonce,
+            gas_price,
+            emitted: false,
+            occupied: true,
+        };
+        Observation::Fresh
+    }
+
+    /// Record that an opportunity was emitted against `(sender, nonce)`.
+    pub fn mark_emitted(&mut self, sender: &Address, nonce: u64) {
+        let tx = &mut self.pending[Self::pending_slot(sender, nonce)];
+        if tx.occupied && tx.sender == *sender && tx.nonce == nonce {
+            tx.emitted = true;
+        }
+    }
+}
+
+impl<const N: usize> Default for NonceTracker<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replacements_supersede_and_cancel_emitted_opportunities() {
+        let mut nonces: NonceTracker<64> = NonceTracker::new();
+        let alice = [0xA1; 20];
+        assert_eq!(nonces.observe(&alice, 7, 100), Observation::Fresh);
+        assert_eq!(nonces.observe(&alice, 7, 100), Observation::Underpriced);
+        assert_eq!(
+            nonces.observe(&alice, 7, 110),
+            Observation::Replacement { cancel: false }
+        );
+
+        nonces.mark_emitted(&alice, 7);
+        assert_eq!(
+            nonces.observe(&alice, 7, 150),
+            Observation::Replacement { cancel: true }
+        );
+        // The opportunity against the replacement has not been emitted yet.
+        assert_eq!(
+            nonces.observe(&alice, 7, 200),
+            Observation::Replacement { cancel: false }
+        );
+        assert!(!Observation::Underpriced.should_evaluate());
+    }
+
+    #[test]
+    fn nonce_gaps_are_flagged_until_filled() {
+        let mut nonces: NonceTracker<64> = NonceTracker::new();
+        let bob = [0xB0; 20];
+        assert_eq!(nonces.observe(&bob, 3, 1), Observation::Fresh);
+        assert_eq!(
+            nonces.observe(&bob, 5, 1),
+            Observation::NonceGap {
+                expected: 4,
+                got: 5
+            }
+        );
+        assert_eq!(nonces.observe(&bob, 4, 1), Observation::Fresh);
+        assert_eq!(nonces.observe(&bob, 5, 1), Observation::Fresh);
+        // Older nonces we never saw are not gaps.
+        assert_eq!(nonces.observe(&bob, 1, 1), Observation::Fresh);
+    }
+
+    #[test]
+    fn collisions_overwrite_instead_of_growing() {
+        let mut nonces: NonceTracker<4> = NonceTracker::new();
+        for i in 0..32u8 {
+            assert_eq!(nonces.observe(&[i; 20], 0, 1), Observation::Fresh);
+        }
+        assert!(nonces.overwrites >= 2 * (32 - 4));
+    }
+}
@@ -0,0 +1,496 @@
+//! Order-book venues (Phoenix/OpenBook style): price levels instead of reserves.
+//!
+//! A `BookUpdate` snapshots the top [`BOOK_DEPTH`] levels of one market and
+//! names the AMM pool trading the same pair. Whenever either side changes,
+//! [`taker_arb`] checks whether taking liquidity from the book and offsetting
+//! it on the AMM (or the reverse) is profitable.
+//!
+//! Conventions: the book's base token is the pool's `token0` and its quote
+//! token is `token1`. Prices are raw quote units per [`PRICE_SCALE`] raw base
+//! units; sizes are raw base units.
+use crate::pool_registry::PoolAddress;
+use crate::processor::AmmPoolState;
+use bytemuck::{Pod, Zeroable};
+use heapless::FnvIndexMap;
+
+/// Magic prefix identifying a `BookUpdate` datagram ("MVBK").
+pub const BOOK_UPDATE_MAGIC: [u8; 4] = *b"MVBK";
+
+/// Levels per side carried on the wire and kept per market.
+pub const BOOK_DEPTH: usize = 8;
+
+/// Fixed-point scale of [`PriceLevel`] prices.
+pub const PRICE_SCALE: u128 = 1_000_000_000;
+
+/// One resting price level. A zero `size` marks the end of the side.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Pod, Zeroable)]
+pub struct PriceLevel {
+    pub price_le: [u8; 8],
+    pub size_le: [u8; 8],
+}
+
+impl PriceLevel {
+    pub const EMPTY: Self = Self {
+        price_le: [0; 8],
+        size_le: [0; 8],
+    };
+
+    pub const fn new(price: u64, size: u64) -> Self {
+        Self {
+            price_le: price.to_le_bytes(),
+            size_le: size.to_le_bytes(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn price(&self) -> u64 {
+        u64::from_le_bytes(self.price_le)
+    }
+
+    #[inline(always)]
+    pub fn size(&self) -> u64 {
+        u64::from_le_bytes(self.size_le)
+    }
+}
+
+/// Top of book, best level first on both sides: bids by descending price,
+/// asks by ascending price.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Pod, Zeroable)]
+pub struct OrderBookState {
+    pub bids: [PriceLevel; BOOK_DEPTH],
+    pub asks: [PriceLevel; BOOK_DEPTH],
+}
+
+const _: () = assert!(core::mem::size_of::<OrderBookState>() == 2 * BOOK_DEPTH * 16);
+
+impl OrderBookState {
+    pub const EMPTY: Self = Self {
+        bids: [PriceLevel::EMPTY; BOOK_DEPTH],
+        asks: [PriceLevel::EMPTY; BOOK_DEPTH],
+    };
+
+    /// Populated bid levels, best first.
+    #[inline(always)]
+    pub fn bids(&self) -> impl Iterator<Item = &PriceLevel> {
+        self.bids.iter().take_while(|l| l.size() != 0)
+    }
+
+    /// Populated ask levels, best first.
+    #[inline(always)]
+    pub fn asks(&self) -> impl Iterator<Item = &PriceLevel> {
+        self.asks.iter().take_while(|l| l.size() != 0)
+    }
+
+    pub fn best_bid(&self) -> Option<u64> {
+        self.bids().next().map(PriceLevel::price)
+    }
+
+    pub fn best_ask(&self) -> Option<u64> {
+        self.asks().next().map(PriceLevel::price)
+    }
+
+    /// Levels are strictly ordered on each side and the book is not crossed.
+    pub fn is_well_formed(&self) -> bool {
+        let ordered = |levels: &[PriceLevel], better: fn(u64, u64) -> bool| {
+            let n = levels.iter().take_while(|l| l.size() != 0).count();
+            levels[..n]
+                .windows(2)
+                .all(|w| better(w[0].price(), w[1].price()))
+                && levels[n..].iter().all(|l| l.size() == 0)
+        };
+        ordered(&self.bids, |a, b| a > b)
+            && ordered(&self.asks, |a, b| a < b)
+            && match (self.best_bid(), self.best_ask()) {
+                (Some(bid), Some(ask)) => bid < ask,
+                _ => true,
+            }
+    }
+}
+
+/// Book snapshot broadcast by a venue feed.
+///
+/// Layout (312 bytes, little-endian):
+///   [0..4]     magic      "MVBK"
+///   [4..24]    market     venue market address
+///   [24..44]   ref_pool   AMM pool trading the same pair
+///   [44..48]   seq        u32 — per-market sequence number
+///   [48..56]   slot       u64 — block/slot the book was observed at
+///   [56..184]  bids       [BOOK_DEPTH] × (price u64, size u64)
+///   [184..312] asks       [BOOK_DEPTH] × (price u64, size u64)
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct BookUpdate {
+    pub magic: [u8; 4],
+    pub market: PoolAddress,
+    pub ref_pool: PoolAddress,
+    pub seq_le: [u8; 4],
+    pub slot_le: [u8; 8],
+    pub book: OrderBookState,
+}
+
+const _: () = assert!(core::mem::size_of::<BookUpdate>() == 312);
+
+impl BookUpdate {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<BookUpdate>();
+
+    pub fn new(
+        market: PoolAddress,
+        ref_pool: PoolAddress,
+        seq: u32,
+        slot: u64,
+        book: OrderBookState,
+    ) -> Self {
+        let (seq_le, slot_le) = (seq.to_le_bytes(), slot.to_le_bytes());
+        Self {
+            magic: BOOK_UPDATE_MAGIC,
+            market,
+            ref_pool,
+            seq_le,
+            slot_le,
+            book,
+        }
+    }
+
+    #[inline(always)]
+    pub fn seq(&self) -> u32 {
+        u32::from_le_bytes(self.seq_le)
+    }
+
+    #[inline(always)]
+    pub fn slot(&self) -> u64 {
+        u64::from_le_bytes(self.slot_le)
+    }
+
+    /// Zero-copy parse; returns `None` unless the payload is exactly one
+    /// `BookUpdate` carrying the expected magic.
+    #[inline(always)]
+    pub fn parse(data: &[u8]) -> Option<&BookUpdate> {
+        if data.len() != Self::WIRE_SIZE {
+            return None;
+        }
+        let update = bytemuck::try_from_bytes::<BookUpdate>(data).ok()?;
+        (update.magic == BOOK_UPDATE_MAGIC).then_some(update)
+    }
+}
+
+/// Latest book of one market.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BookEntry {
+    pub ref_pool: PoolAddress,
+    pub seq: u32,
+    pub slot: u64,
+    pub book: OrderBookState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BookRejection {
+    /// Sequence number not newer than the stored book.
+    Stale { last: u32, got: u32 },
+    /// Unordered levels or a crossed book.
+    Malformed,
+    /// New market and no free entry.
+    Full,
+}
+
+/// Markets tracked individually.
+pub const BOOK_CAPACITY: usize = 64;
+
+/// Latest book per market, owned by the feed-processing thread.
+pub struct BookTable {
+    books: FnvIndexMap<PoolAddress, BookEntry, BOOK_CAPACITY>,
+}
+
+impl BookTable {
+    pub const fn new() -> Self {
+        Self {
+            books: FnvIndexMap::new(),
+        }
+    }
+
+    /// Replace a market's book with `update`. Sequence numbers are compared
+    /// with wrapping arithmetic, so a feed restart needs a fresh market entry.
+    pub fn apply(&mut self, update: &BookUpdate) -> Result<&BookEntry, BookRejection> {
+        if !update.book.is_well_formed() {
+            return Err(BookRejection::Malformed);
+        }
+        let entry = BookEntry {
+            ref_pool: update.ref_pool,
+            seq: update.seq(),
+            slot: update.slot(),
+            book: update.book,
+        };
+        if let Some(last) = self.books.get(&update.market) {
+            if (entry.seq.wrapping_sub(last.seq) as i32) <= 0 {
+                return Err(BookRejection::Stale {
+                    last: last.seq,
+                    got: entry.seq,
+                });
+            }
+        }
+        self.books
+            .insert(update.market, entry)
+            .map_err(|_| BookRejection::Full)?;
+        Ok(&self.books[&update.market])
+    }
+
+    pub fn get(&self, market: &PoolAddress) -> Option<&BookEntry> {
+        self.books.get(market)
+    }
+
+    /// Markets whose reference pool is `pool`.
+    pub fn linked_to<'a>(
+        &'a self,
+        pool: &'a PoolAddress,
+    ) -> impl Iterator<Item = (&'a PoolAddress, &'a BookEntry)> {
+        self.books.iter().filter(move |(_, e)| e.ref_pool == *pool)
+    }
+
+    pub fn len(&self) -> usize {
+        self.books.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.books.is_empty()
+    }
+}
+
+impl Default for BookTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which side of the book the taker leg hits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TakerSide {
+    /// Lift asks, sell the base into the AMM.
+    BuyBook,
+    /// Buy the base from the AMM, hit bids.
+    SellBook,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TakerArb {
+    pub side: TakerSide,
+    /// Base traded on both legs, raw units.
+    pub base_amount: u64,
+    /// Profit in raw quote units (the pool's `token1`).
+    pub quote_profit: u64,
+}
+
+/// Quote value of taking `base` from `levels`, best level first; `None` if
+/// the levels are not deep enough. Rounded up when paying, down when receiving.
+#[inline(always)]
+fn fill_quote<'a>(
+    levels: impl Iterator<Item = &'a PriceLevel>,
+    mut base: u64,
+    round_up: bool,
+) -> Option<u128> {
+    let mut quote = 0u128;
+    for level in levels {
+        if base == 0 {
+            break;
+        }
+        let take = base.min(level.size());
+        let notional = take as u128 * level.price() as u128;
+        quote += if round_up {
+            notional.div_ceil(PRICE_SCALE)
+        } else {
+            notional / PRICE_SCALE
+        };
+        base -= take;
+    }
+    (base == 0).then_some(quote)
+}
+
+/// Maximize a concave `profit(base)` over `0..=max`: integer ternary search,
+/// plus every level boundary in `breakpoints`, where the book side's slope
+/// changes and rounding can flatten the curve enough to mislead the search.
+fn maximize(
+    max: u64,
+    breakpoints: impl Iterator<Item = u64>,
+    profit: impl Fn(u64) -> i128,
+) -> (u64, i128) {
+    let (mut lo, mut hi) = (0u64, max);
+    while hi - lo > 2 {
+        let third = (hi - lo) / 3;
+        let (m1, m2) = (lo + third, hi - third);
+        if profit(m1) < profit(m2) {
+            lo = m1 + 1;
+        } else {
+            hi = m2;
+        }
+    }
+    (lo..=hi)
+        .chain(breakpoints.filter(|&b| b <= max))
+        .map(|b| (b, profit(b)))
+        .max_by_key(|&(_, p)| p)
+        .unwrap_or((0, 0))
+}
+
+/// Cumulative sizes at the end of each level.
+fn level_ends(levels: &[PriceLevel]) -> impl Iterator<Item = u64> + '_ {
+    levels
+        .iter()
+        .take_while(|l| l.size() != 0)
+        .scan(0u64, |total, l| {
+            *total = total.saturating_add(l.size());
+            Some(*total)
+        })
+}
+
+/// Best book-vs-AMM taker arbitrage, sized to maximize quote profit.
+///
+/// Both legs are concave in the traded size (AMM slippage, book levels
+/// getting worse), so their difference has a single maximum.
+pub fn taker_arb(book: &OrderBookState, pool: &AmmPoolState) -> Option<TakerArb> {
+    let buy_book = |base: u64| -> i128 {
+        let Some(cost) = fill_quote(book.asks(), base, true) else {
+            return i128::MIN;
+        };
+        pool.get_amount_out(base, true).unwrap_or(0) as i128 - cost as i128
+    };
+    let sell_book = |base: u64| -> i128 {
+        let (Some(proceeds), Some(cost)) = (
+            fill_quote(book.bids(), base, false),
+            pool.get_amount_in(base, false),
+        ) else {
+            return i128::MIN;
+        };
+        proceeds as i128 - cost as i128
+    };
+    let ask_depth = level_ends(&book.asks).last().unwrap_or(0);
+    // The AMM cannot pay out its whole base reserve.
+    let bid_depth = level_ends(&book.bids)
+        .last()
+        .unwrap_or(0)
+        .min(pool.reserve0.saturating_sub(1));
+
+    [
+        (
+            TakerSide::BuyBook,
+            maximize(ask_depth, level_ends(&book.asks), buy_book),
+        ),
+        (
+            TakerSide::SellBook,
+            maximize(bid_depth, level_ends(&book.bids), sell_book),
+        ),
+    ]
+    .into_iter()
+    .filter(|&(_, (base, p))| base != 0 && p > 0)
+    .max_by_key(|&(_, (_, p))| p)
+    .map(|(side, (base_amount, p))| TakerArb {
+        side,
+        base_amount,
+        quote_profit: p.min(u64::MAX as i128) as u64,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::MOCK_POOL;
+
+    /// `MOCK_POOL` mid: 0.5 raw WSOL per raw USDC.
+    const MID: u64 = 500_000_000;
+
+    fn book(bids: &[(u64, u64)], asks: &[(u64, u64)]) -> OrderBookState {
+        let mut book = OrderBookState::EMPTY;
+        for (slot, &(p, s)) in book.bids.iter_mut().zip(bids) {
+            *slot = PriceLevel::new(p, s);
+        }
+        for (slot, &(p, s)) in book.asks.iter_mut().zip(asks) {
+            *slot = PriceLevel::new(p, s);
+        }
+        book
+    }
+
+    #[test]
+    fn update_round_trips_and_rejects_bad_books() {
+        let update = BookUpdate::new(
+            [1; 20],
+            [2; 20],
+            5,
+            99,
+            book(&[(MID - 10, 100)], &[(MID + 10, 100)]),
+        );
+        let parsed = BookUpdate::parse(bytemuck::bytes_of(&update)).expect("valid update");
+        assert_eq!(
+            (parsed.seq(), parsed.slot(), parsed.book.best_bid()),
+            (5, 99, Some(MID - 10))
+        );
+
+        let mut table = BookTable::new();
+        assert!(table.apply(parsed).is_ok());
+        assert_eq!(
+            table.apply(parsed),
+            Err(BookRejection::Stale { last: 5, got: 5 })
+        );
+        assert_eq!(table.linked_to(&[2; 20]).count(), 1);
+
+        let crossed = BookUpdate::new(
+            [1; 20],
+            [2; 20],
+            6,
+            100,
+            book(&[(MID + 20, 1)], &[(MID + 10, 1)]),
+        );
+        assert_eq!(table.apply(&crossed), Err(BookRejection::Malformed));
+        let unordered = BookUpdate::new(
+            [1; 20],
+            [2; 20],
+            6,
+            100,
+            book(&[], &[(MID + 10, 1), (MID + 5, 1)]),
+        );
+        assert_eq!(table.apply(&unordered), Err(BookRejection::Malformed));
+    }
+
+    #[test]
+    fn aligned_book_has_no_arb() {
+        // Spread wider than the pool fee on both sides.
+        let quiet = book(
+            &[(MID - MID / 100, 1_000_000_000)],
+            &[(MID + MID / 100, 1_000_000_000)],
+        );
+        assert_eq!(taker_arb(&quiet, &MOCK_POOL), None);
+    }
+
+    #[test]
+    fn cheap_asks_are_lifted_and_sold_into_the_pool() {
+        let cheap = book(
+            &[],
+            &[
+                (MID * 9 / 10, 100_000_000),
+                (MID * 95 / 100, 100_000_000),
+                (MID * 2, 1 << 40),
+            ],
+        );
+        let arb = taker_arb(&cheap, &MOCK_POOL).expect("asks 5-10% below the pool");
+        assert_eq!(arb.side, TakerSide::BuyBook);
+        // Sized to the first two levels only; the third is far above the pool.
+        assert!(
+            arb.base_amount > 100_000_000 && arb.base_amount <= 200_000_000,
+            "{arb:?}"
+        );
+        let cost = fill_quote(cheap.asks(), arb.base_amount, true).unwrap() as u64;
+        assert_eq!(
+            MOCK_POOL.get_amount_out(arb.base_amount, true).unwrap() - cost,
+            arb.quote_profit
+        );
+    }
+
+    #[test]
+    fn rich_bids_are_hit_with_base_bought_from_the_pool() {
+        let rich = book(&[(MID * 11 / 10, 50_000_000)], &[]);
+        let arb = taker_arb(&rich, &MOCK_POOL).expect("bid 10% above the pool");
+        assert_eq!(
+            (arb.side, arb.base_amount),
+            (TakerSide::SellBook, 50_000_000)
+        );
+        let in_quote = MOCK_POOL.get_amount_in(50_000_000, false).unwrap();
+        assert_eq!(arb.quote_profit, 27_500_000 - in_quote);
+    }
+}
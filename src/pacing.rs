@@ -0,0 +1,269 @@
+//! Per-destination token-bucket pacing of outgoing messages.
+//!
+//! Executors and relays cap how fast they accept requests, and a burst of
+//! opportunities above the cap gets the node throttled or banned. Every
+//! destination gets a token bucket refilled by the TSC: one token per
+//! `interval_cycles`, holding at most `burst`. A message that finds its
+//! destination's bucket empty is not dropped but written into a bounded
+//! delay queue, and [`Pacer::release`] sends it once a token is available.
+//! Only a full queue drops.
+//!
+//! Messages to one destination leave in the order they were sent: while a
+//! destination has delayed messages, new ones queue behind them.
+use crate::coalesce::MAX_REPLY_BATCH;
+use crate::payload::{OpportunityCancel, OpportunityReply};
+
+/// Destinations with their own bucket; messages to further ones are not paced.
+pub const MAX_PACED_DESTINATIONS: usize = 16;
+/// Messages held back waiting for a token, across all destinations.
+pub const PACE_QUEUE: usize = 64;
+/// Largest message that can be delayed: a reply, a cancellation or a
+/// coalesced batch of replies.
+pub const MAX_PACED_MESSAGE: usize = max(
+    max(OpportunityReply::WIRE_SIZE, OpportunityCancel::WIRE_SIZE),
+    MAX_REPLY_BATCH,
+);
+
+const fn max(a: usize, b: usize) -> usize {
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+/// IPv4 address and port.
+pub type Destination = ([u8; 4], u16);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PacingPolicy {
+    /// Cycles per token, i.e. the sustained rate; 0 disables pacing.
+    pub interval_cycles: u64,
+    /// Tokens a bucket holds: messages sent back to back after a pause.
+    pub burst: u64,
+}
+
+impl PacingPolicy {
+    pub const DISABLED: Self = Self {
+        interval_cycles: 0,
+        burst: 1,
+    };
+
+    pub fn is_enabled(&self) -> bool {
+        self.interval_cycles > 0
+    }
+}
+
+impl Default for PacingPolicy {
+    fn default() -> Self {
+        Self::DISABLED
+    }
+}
+
+/// A token bucket kept in cycles of credit, so refill is a subtraction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct TokenBucket {
+    credit: u64,
+    last: u64,
+    /// Delayed messages to this destination still queued.
+    queued: usize,
+}
+
+impl TokenBucket {
+    /// Take a token at cycle `now` if one has accrued.
+    #[inline(always)]
+    fn take(&mut self, policy: &PacingPolicy, now: u64) -> bool {
+        let capacity = policy.interval_cycles.saturating_mul(policy.burst.max(1));
+        self.credit = self
+            .credit
+            .saturating_add(now.saturating_sub(self.last))
+            .min(capacity);
+        self.last = self.last.max(now);
+        if self.credit < policy.interval_cycles {
+            return false;
+        }
+        self.credit -= policy.interval_cycles;
+        true
+    }
+}
+
+struct Delayed {
+    to: Destination,
+    len: usize,
+    bytes: [u8; MAX_PACED_MESSAGE],
+}
+
+pub struct Pacer<const N: usize = MAX_PACED_DESTINATIONS, const Q: usize = PACE_QUEUE> {
+    policy: PacingPolicy,
+    buckets: heapless::Vec<(Destination, TokenBucket), N>,
+    queue: heapless::Deque<Delayed, Q>,
+    /// Messages held back for a token.
+    pub delayed: u64,
+    /// Messages dropped because the delay queue was full.
+    pub dropped: u64,
+}
+
+impl<const N: usize, const Q: usize> Pacer<N, Q> {
+    pub fn new(policy: PacingPolicy) -> Self {
+        Self {
+            policy,
+            buckets: heapless::Vec::new(),
+            queue: heapless::Deque::new(),
+            delayed: 0,
+            dropped: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.policy.is_enabled()
+    }
+
+    /// Messages waiting for a token.
+    pub fn queued(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn bucket(&mut self, to: Destination, now: u64) -> Option<&mut TokenBucket> {
+        let i = match self.buckets.iter().position(|(d, _)| *d == to) {
+            Some(i) => i,
+            None => {
+                let full = TokenBucket {
+                    credit: self
+                        .policy
+                        .interval_cycles
+                        .saturating_mul(self.policy.burst.max(1)),
+                    last: now,
+                    queued: 0,
+                };
+                self.buckets.push((to, full)).ok()?;
+                self.buckets.len() - 1
+            }
+        };
+        Some(&mut self.buckets[i].1)
+    }
+
+    /// Whether a message to `to` may be sent at cycle `now`, taking a token
+    /// if so. Refused while earlier messages to `to` are still delayed.
+    #[inline(always)]
+    pub fn admit(&mut self, to: Destination, now: u64) -> bool {
+        if !self.policy.is_enabled() {
+            return true;
+        }
+        let policy = self.policy;
+        match self.bucket(to, now) {
+            Some(bucket) => bucket.queued == 0 && bucket.take(&policy, now),
+            None => true,
+        }
+    }
+
+    /// Room for a `len`-byte message to `to` in the delay queue, counted as
+    /// delayed; `None` (counted as dropped) if the queue is full.
+    pub fn delay(&mut self, to: Destination, len: usize) -> Option<&mut [u8]> {
+        if len > MAX_PACED_MESSAGE || self.queue.is_full() {
+            self.dropped += 1;
+            return None;
+        }
+        if let Some((_, bucket)) = self.buckets.iter_mut().find(|(d, _)| *d == to) {
+            bucket.queued += 1;
+        }
+        self.delayed += 1;
+        let _ = self.queue.push_back(Delayed {
+            to,
+            len,
+            bytes: [0; MAX_PACED_MESSAGE],
+        });
+        self.queue.back_mut().map(|d| &mut d.bytes[..len])
+    }
+
+    /// Hand every delayed message whose destination has a token at cycle
+    /// `now` to `send`; it returns `false` if the message could not be
+    /// sent, which keeps it (and later ones to its destination) queued.
+    /// Returns the number sent.
+    pub fn release(&mut self, now: u64, mut send: impl FnMut(Destination, &[u8]) -> bool) -> usize {
+        let policy = self.policy;
+        let mut stalled: heapless::Vec<Destination, Q> = heapless::Vec::new();
+        let mut sent = 0;
+        for _ in 0..self.queue.len() {
+            let Some(msg) = self.queue.pop_front() else {
+                break;
+            };
+            let slot = self.buckets.iter().position(|(d, _)| *d == msg.to);
+            let ready = !stalled.contains(&msg.to)
+                && slot.is_none_or(|i| self.buckets[i].1.take(&policy, now));
+            if ready && send(msg.to, &msg.bytes[..msg.len]) {
+                if let Some(i) = slot {
+                    self.buckets[i].1.queued -= 1;
+                }
+                sent += 1;
+                continue;
+            }
+            if let (true, Some(i)) = (ready, slot) {
+                // Not sent: the token is given back.
+                self.buckets[i].1.credit += policy.interval_cycles;
+            }
+            if !stalled.contains(&msg.to) {
+                let _ = stalled.push(msg.to);
+            }
+            let _ = self.queue.push_back(msg);
+        }
+        sent
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXEC: Destination = ([10, 0, 0, 5], 9000);
+    const RELAY: Destination = ([10, 0, 0, 6], 9000);
+
+    #[test]
+    fn bucket_allows_a_burst_then_the_sustained_rate() {
+        let mut pacer: Pacer = Pacer::new(PacingPolicy {
+            interval_cycles: 100,
+            burst: 3,
+        });
+        assert_eq!((0..5).filter(|_| pacer.admit(EXEC, 1_000)).count(), 3);
+        // Other destinations have their own bucket.
+        assert!(pacer.admit(RELAY, 1_000));
+        assert!(!pacer.admit(EXEC, 1_099));
+        assert!(pacer.admit(EXEC, 1_100));
+        // A long pause refills to the burst, no further.
+        assert_eq!((0..5).filter(|_| pacer.admit(EXEC, 100_000)).count(), 3);
+        let mut off: Pacer = Pacer::new(PacingPolicy::DISABLED);
+        assert!((0..100).all(|_| off.admit(EXEC, 0)));
+    }
+
+    #[test]
+    fn delayed_messages_leave_in_order_as_tokens_accrue() {
+        let mut pacer: Pacer<4, 3> = Pacer::new(PacingPolicy {
+            interval_cycles: 100,
+            burst: 1,
+        });
+        assert!(pacer.admit(EXEC, 0));
+        for n in 1..=3u8 {
+            assert!(!pacer.admit(EXEC, 0));
+            pacer.delay(EXEC, 2).unwrap().copy_from_slice(&[n, n]);
+        }
+        assert!(pacer.delay(EXEC, 2).is_none());
+        assert_eq!((pacer.delayed, pacer.dropped), (3, 1));
+
+        let mut out = Vec::new();
+        let mut record = |_: Destination, m: &[u8]| {
+            out.push(m[0]);
+            true
+        };
+        assert_eq!(pacer.release(50, &mut record), 0);
+        // Queued messages go first: a token does not jump the queue.
+        assert!(!pacer.admit(EXEC, 100));
+        assert_eq!(pacer.release(100, &mut record), 1);
+        // A refused send keeps the message, and its token.
+        assert_eq!(pacer.release(200, |_, _| false), 0);
+        assert_eq!(pacer.release(200, &mut record), 1);
+        assert_eq!(pacer.release(10_000, &mut record), 1);
+        assert_eq!((out, pacer.queued()), (vec![1, 2, 3], 0));
+        // The last release took the refilled token.
+        assert!(!pacer.admit(EXEC, 10_000));
+        assert!(pacer.admit(EXEC, 10_100));
+    }
+}
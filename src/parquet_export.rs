@@ -0,0 +1,250 @@
+//! Columnar export of the decision log (`parquet` feature).
+//!
+//! The timeline records each reply the node sent after the victim it was
+//! for (see [`crate::backtest`]). Research tooling wants those decisions as
+//! a table, not as a replay: [`DecisionBatches`] turns a timeline into Arrow
+//! record batches of one row per decision, joined with its victim, and
+//! [`export_segment`] writes a segment's batches to a Parquet file.
+//!
+//! With `MEV_JOURNAL_PARQUET=1` the journal's background thread exports
+//! every rotated segment to `<path>.<start_ms>.parquet` before compressing
+//! it, so the packet loop never touches Arrow. An export is pruned with its
+//! segment.
+//!
+//! Columns, in order ([`schema`]): `rx_ts_ns`, `tx_ts_ns`, `nonce`,
+//! `profit`, `confidence_bps` from the reply; `pool`, `amount_in`,
+//! `min_amount_out`, `zero_for_one` from the victim, null if the record
+//! before the decision is not its victim.
+use crate::backtest::{EventKind, Timeline, TimelineEvent};
+use crate::payload::{DexSwapTx, OpportunityReply};
+use arrow_array::builder::{BooleanBuilder, FixedSizeBinaryBuilder, UInt16Builder, UInt64Builder};
+use arrow_array::{ArrayRef, RecordBatch};
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+use parquet::arrow::ArrowWriter;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+/// Rows per record batch, and so per Parquet row group at most.
+pub const BATCH_ROWS: usize = 8_192;
+
+/// The decision table's schema.
+pub fn schema() -> SchemaRef {
+    static SCHEMA: OnceLock<SchemaRef> = OnceLock::new();
+    SCHEMA
+        .get_or_init(|| {
+            Arc::new(Schema::new(vec![
+                Field::new("rx_ts_ns", DataType::UInt64, false),
+                Field::new("tx_ts_ns", DataType::UInt64, false),
+                Field::new("nonce", DataType::UInt64, false),
+                Field::new("profit", DataType::UInt64, false),
+                Field::new("confidence_bps", DataType::UInt16, false),
+                Field::new("pool", DataType::FixedSizeBinary(20), true),
+                Field::new("amount_in", DataType::UInt64, true),
+                Field::new("min_amount_out", DataType::UInt64, true),
+                Field::new("zero_for_one", DataType::Boolean, true),
+            ]))
+        })
+        .clone()
+}
+
+/// Decisions of a timeline, gathered into record batches.
+pub struct DecisionBatches {
+    /// The last victim seen, until its decision or the next record.
+    victim: Option<DexSwapTx>,
+    rows: usize,
+    rx_ts_ns: UInt64Builder,
+    tx_ts_ns: UInt64Builder,
+    nonce: UInt64Builder,
+    profit: UInt64Builder,
+    confidence_bps: UInt16Builder,
+    pool: FixedSizeBinaryBuilder,
+    amount_in: UInt64Builder,
+    min_amount_out: UInt64Builder,
+    zero_for_one: BooleanBuilder,
+}
+
+impl DecisionBatches {
+    pub fn new() -> Self {
+        Self {
+            victim: None,
+            rows: 0,
+            rx_ts_ns: UInt64Builder::with_capacity(BATCH_ROWS),
+            tx_ts_ns: UInt64Builder::with_capacity(BATCH_ROWS),
+            nonce: UInt64Builder::with_capacity(BATCH_ROWS),
+            profit: UInt64Builder::with_capacity(BATCH_ROWS),
+            confidence_bps: UInt16Builder::with_capacity(BATCH_ROWS),
+            pool: FixedSizeBinaryBuilder::with_capacity(BATCH_ROWS, 20),
+            amount_in: UInt64Builder::with_capacity(BATCH_ROWS),
+            min_amount_out: UInt64Builder::with_capacity(BATCH_ROWS),
+            zero_for_one: BooleanBuilder::with_capacity(BATCH_ROWS),
+        }
+    }
+
+    /// Take in one timeline event; returns a batch once [`BATCH_ROWS`]
+    /// decisions are gathered. A decision that is not an
+    /// `OpportunityReply` is skipped.
+    pub fn push(&mut self, event: TimelineEvent<'_>) -> Result<Option<RecordBatch>, ArrowError> {
+        let victim = self.victim.take();
+        match event.kind {
+            EventKind::PoolUpdate => return Ok(None),
+            EventKind::Victim => {
+                self.victim = DexSwapTx::header(event.payload).copied();
+                return Ok(None);
+            }
+            EventKind::Decision => {}
+        }
+        let Ok(reply) = bytemuck::try_from_bytes::<OpportunityReply>(event.payload) else {
+            return Ok(None);
+        };
+        self.rx_ts_ns.append_value(reply.rx_ts_ns());
+        self.tx_ts_ns.append_value(reply.tx_ts_ns());
+        self.nonce.append_value(reply.nonce());
+        self.profit.append_value(reply.profit());
+        self.confidence_bps.append_value(reply.confidence_bps());
+        match victim.filter(|v| v.nonce() == reply.nonce()) {
+            Some(victim) => {
+                self.pool.append_value(victim.pool_address)?;
+                self.amount_in.append_value(victim.amount_in());
+                self.min_amount_out.append_value(victim.min_amount_out());
+                self.zero_for_one.append_value(victim.token_direction == 0);
+            }
+            None => {
+                self.pool.append_null();
+                self.amount_in.append_null();
+                self.min_amount_out.append_null();
+                self.zero_for_one.append_null();
+            }
+        }
+        self.rows += 1;
+        if self.rows < BATCH_ROWS {
+            return Ok(None);
+        }
+        self.flush().map(Some)
+    }
+
+    /// The decisions gathered since the last batch, if any.
+    pub fn finish(mut self) -> Result<Option<RecordBatch>, ArrowError> {
+        if self.rows == 0 {
+            return Ok(None);
+        }
+        self.flush().map(Some)
+    }
+
+    fn flush(&mut self) -> Result<RecordBatch, ArrowError> {
+        self.rows = 0;
+        let columns: Vec<ArrayRef> = vec![
+            Arc::new(self.rx_ts_ns.finish()),
+            Arc::new(self.tx_ts_ns.finish()),
+            Arc::new(self.nonce.finish()),
+            Arc::new(self.profit.finish()),
+            Arc::new(self.confidence_bps.finish()),
+            Arc::new(self.pool.finish()),
+            Arc::new(self.amount_in.finish()),
+            Arc::new(self.min_amount_out.finish()),
+            Arc::new(self.zero_for_one.finish()),
+        ];
+        RecordBatch::try_new(schema(), columns)
+    }
+}
+
+impl Default for DecisionBatches {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Write the decisions of the journal segment `segment` (plain or zstd)
+/// to the Parquet file `to`; returns the rows written. The file appears
+/// complete or not at all.
+pub fn export_segment(segment: &Path, to: &Path) -> io::Result<usize> {
+    let bytes = crate::journal::read_segment(segment)?;
+    let timeline =
+        Timeline::parse(&bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let mut partial = to.as_os_str().to_owned();
+    partial.push(".tmp");
+    let mut writer =
+        ArrowWriter::try_new(File::create(&partial)?, schema(), None).map_err(io::Error::other)?;
+    let mut batches = DecisionBatches::new();
+    let mut rows = 0;
+    for event in timeline {
+        let event = event.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if let Some(batch) = batches.push(event).map_err(io::Error::other)? {
+            rows += batch.num_rows();
+            writer.write(&batch).map_err(io::Error::other)?;
+        }
+    }
+    if let Some(batch) = batches.finish().map_err(io::Error::other)? {
+        rows += batch.num_rows();
+        writer.write(&batch).map_err(io::Error::other)?;
+    }
+    writer.close().map_err(io::Error::other)?;
+    std::fs::rename(&partial, to)?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::TimelineWriter;
+    use arrow_array::cast::AsArray;
+    use arrow_array::types::UInt64Type;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    fn reply(nonce: u64, profit: u64) -> OpportunityReply {
+        let mut reply: OpportunityReply = bytemuck::Zeroable::zeroed();
+        reply.nonce_le = nonce.to_le_bytes();
+        reply.profit_le = profit.to_le_bytes();
+        reply.confidence_bps_le = 9_000u16.to_le_bytes();
+        reply
+    }
+
+    #[test]
+    fn exports_one_row_per_decision_joined_with_its_victim() {
+        let dir = std::env::temp_dir().join(format!("mev-parquet-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (segment, export) = (
+            dir.join("timeline.bin.0000000000000001"),
+            dir.join("decisions.parquet"),
+        );
+        let mut writer = TimelineWriter::new(File::create(&segment).unwrap()).unwrap();
+        let victim = DexSwapTx::from_parts(7, [0xAB; 20], 500, 450, 1);
+        writer.record(EventKind::PoolUpdate, 1, &[0; 8]).unwrap();
+        writer
+            .record(EventKind::Victim, 2, bytemuck::bytes_of(&victim))
+            .unwrap();
+        writer
+            .record(EventKind::Decision, 2, bytemuck::bytes_of(&reply(7, 30)))
+            .unwrap();
+        // A decision whose victim is not the record before it.
+        writer
+            .record(EventKind::Decision, 3, bytemuck::bytes_of(&reply(8, 40)))
+            .unwrap();
+        writer.record(EventKind::Decision, 4, &[0; 3]).unwrap();
+        drop(writer);
+
+        assert_eq!(export_segment(&segment, &export).unwrap(), 2);
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&export).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<RecordBatch> = reader.map(Result::unwrap).collect();
+        assert_eq!(batches.len(), 1);
+        let batch = &batches[0];
+        assert_eq!(batch.schema(), schema());
+        let column = |name: &str| batch.column_by_name(name).unwrap().clone();
+        assert_eq!(
+            column("nonce").as_primitive::<UInt64Type>().values(),
+            &[7, 8]
+        );
+        assert_eq!(
+            column("profit").as_primitive::<UInt64Type>().values(),
+            &[30, 40]
+        );
+        assert_eq!(column("pool").as_fixed_size_binary().value(0), &[0xAB; 20]);
+        assert!(column("amount_in").is_null(1));
+        assert!(!column("zero_for_one").as_boolean().value(0));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -33,6 +33,24 @@ impl DexSwapTx {
         u64::from_le_bytes(self.min_amount_out_le)
     }
 
+    /// The base record at the start of a `DexSwapTx` or `DexSwapTxExt`
+    /// payload, without length dispatch.
+    #[inline(always)]
+    pub fn header(data: &[u8]) -> Option<&DexSwapTx> {
+        bytemuck::try_from_bytes(data.get(..Self::WIRE_SIZE)?).ok()
+    }
+
+    /// Zero-copy view over a batch of back-to-back `DexSwapTx` records.
+    ///
+    /// `DexSwapTx` has alignment 1, so the whole batch is validated with a
+    /// single `cast_slice` instead of one `try_from_bytes` per record.
+    /// Trailing bytes that do not form a full record are ignored.
+    #[inline(always)]
+    pub fn cast_batch(data: &[u8]) -> &[DexSwapTx] {
+        let whole = data.len() - data.len() % Self::WIRE_SIZE;
+        bytemuck::cast_slice(&data[..whole])
+    }
+
     #[inline(always)]
     pub fn from_parts(
         nonce: u64,
@@ -52,9 +70,265 @@ impl DexSwapTx {
     }
 }
 
+/// Extended swap payload: a `DexSwapTx` followed by victim metadata.
+///
+/// The first 48 bytes are exactly a `DexSwapTx`, so consumers that only
+/// understand the base record keep working. Total size is 64 bytes — one
+/// cache line.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct DexSwapTxExt {
+    pub base: DexSwapTx,
+    /// Victim's on-chain deadline as unix time in milliseconds (0 = none).
+    pub deadline_ms_le: [u8; 8],
+    /// Victim's priority fee per gas unit, in raw units of the chain's fee
+    /// token (0 = none). A front-run must out-bid it to be ordered first.
+    pub priority_fee_le: [u8; 8],
+}
+
+const _: () = assert!(core::mem::size_of::<DexSwapTxExt>() == 64);
+
+impl DexSwapTxExt {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<DexSwapTxExt>();
+
+    #[inline(always)]
+    pub fn deadline_ms(&self) -> u64 {
+        u64::from_le_bytes(self.deadline_ms_le)
+    }
+
+    #[inline(always)]
+    pub fn priority_fee(&self) -> u64 {
+        u64::from_le_bytes(self.priority_fee_le)
+    }
+
+    /// Zero-copy view of the extension, if `data` is long enough to carry it.
+    #[inline(always)]
+    pub fn parse(data: &[u8]) -> Option<&DexSwapTxExt> {
+        bytemuck::try_from_bytes(data.get(..Self::WIRE_SIZE)?).ok()
+    }
+
+    #[inline(always)]
+    pub fn from_parts(base: DexSwapTx, deadline_ms: u64) -> Self {
+        Self {
+            base,
+            deadline_ms_le: deadline_ms.to_le_bytes(),
+            priority_fee_le: [0; 8],
+        }
+    }
+
+    #[inline(always)]
+    pub fn with_priority_fee(mut self, priority_fee: u64) -> Self {
+        self.priority_fee_le = priority_fee.to_le_bytes();
+        self
+    }
+}
+
+/// EVM victim payload: a `DexSwapTxExt` followed by the fields needed to
+/// follow the victim's nonce and fee replacements (RBF).
+///
+/// For these payloads `DexSwapTx::nonce` is the sender's account nonce. Total
+/// size is 96 bytes; the first 64 are exactly a `DexSwapTxExt`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct DexSwapTxEvm {
+    pub ext: DexSwapTxExt,
+    /// Victim account.
+    pub sender: [u8; 20],
+    /// Effective gas price in wei; a replacement must pay more.
+    pub gas_price_le: [u8; 8],
+    pub _evm_reserved: [u8; 4],
+}
+
+const _: () = assert!(core::mem::size_of::<DexSwapTxEvm>() == 96);
+
+impl DexSwapTxEvm {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<DexSwapTxEvm>();
+
+    #[inline(always)]
+    pub fn gas_price(&self) -> u64 {
+        u64::from_le_bytes(self.gas_price_le)
+    }
+
+    #[inline(always)]
+    pub fn nonce(&self) -> u64 {
+        self.ext.base.nonce()
+    }
+
+    /// Zero-copy view of the EVM fields, if `data` is long enough to carry them.
+    #[inline(always)]
+    pub fn parse(data: &[u8]) -> Option<&DexSwapTxEvm> {
+        bytemuck::try_from_bytes(data.get(..Self::WIRE_SIZE)?).ok()
+    }
+
+    #[inline(always)]
+    pub fn from_parts(ext: DexSwapTxExt, sender: [u8; 20], gas_price: u64) -> Self {
+        Self {
+            ext,
+            sender,
+            gas_price_le: gas_price.to_le_bytes(),
+            _evm_reserved: [0; 4],
+        }
+    }
+}
+
+/// Reply sent back to the sender of a profitable swap.
+///
+/// Built in place inside the transport's TX buffer (see
+/// [`crate::transport::Transport::tx_reserve`]), never as an owned copy.
+/// Both timestamps are unix nanoseconds, so executors can drop signals that
+/// are already stale by their own clock. The confidence score lets them
+/// size a bundle, or skip it, by how far the profit estimate can be trusted.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct OpportunityReply {
+    /// Victim nonce: identifies the opportunity in a later
+    /// [`OpportunityCancel`].
+    pub nonce_le: [u8; 8],
+    /// Estimated profit in numéraire units.
+    pub profit_le: [u8; 8],
+    /// When the triggering packet entered the node: the NIC hardware stamp
+    /// where the backend provides one, otherwise the software stamp taken
+    /// as the userspace stack polled it in.
+    pub rx_ts_ns_le: [u8; 8],
+    /// When this reply was enqueued for transmission.
+    pub tx_ts_ns_le: [u8; 8],
+    /// Confidence in `profit`, in basis points (see [`crate::confidence`]).
+    pub confidence_bps_le: [u8; 2],
+    pub _reserved: [u8; 6],
+}
+
+const _: () = assert!(core::mem::size_of::<OpportunityReply>() == 40);
+
+impl OpportunityReply {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<OpportunityReply>();
+
+    #[inline(always)]
+    pub fn nonce(&self) -> u64 {
+        u64::from_le_bytes(self.nonce_le)
+    }
+
+    #[inline(always)]
+    pub fn profit(&self) -> u64 {
+        u64::from_le_bytes(self.profit_le)
+    }
+
+    #[inline(always)]
+    pub fn confidence_bps(&self) -> u16 {
+        u16::from_le_bytes(self.confidence_bps_le)
+    }
+
+    #[inline(always)]
+    pub fn rx_ts_ns(&self) -> u64 {
+        u64::from_le_bytes(self.rx_ts_ns_le)
+    }
+
+    #[inline(always)]
+    pub fn tx_ts_ns(&self) -> u64 {
+        u64::from_le_bytes(self.tx_ts_ns_le)
+    }
+
+    /// Wire-to-wire latency inside the node: RX stamp to TX enqueue.
+    #[inline(always)]
+    pub fn wire_latency_ns(&self) -> u64 {
+        self.tx_ts_ns().saturating_sub(self.rx_ts_ns())
+    }
+
+    /// View a reserved TX buffer as a reply to be filled in.
+    /// `None` if `buf` is not exactly [`Self::WIRE_SIZE`] bytes.
+    #[inline(always)]
+    pub fn emplace(buf: &mut [u8]) -> Option<&mut Self> {
+        bytemuck::try_from_bytes_mut(buf).ok()
+    }
+}
+
+/// Magic prefix identifying an `OpportunityCancel` message ("MVCX").
+pub const OPPORTUNITY_CANCEL_MAGIC: [u8; 4] = *b"MVCX";
+
+/// Why an emitted opportunity was withdrawn.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CancelReason {
+    /// The victim transaction was replaced by one with a higher fee.
+    Replaced = 1,
+    /// A newer pool update leaves the opportunity unprofitable.
+    PoolMoved = 2,
+    /// The victim's deadline passes before a bundle could land.
+    Expired = 3,
+}
+
+impl CancelReason {
+    pub const fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            1 => Some(CancelReason::Replaced),
+            2 => Some(CancelReason::PoolMoved),
+            3 => Some(CancelReason::Expired),
+            _ => None,
+        }
+    }
+}
+
+/// Follow-up to an [`OpportunityReply`]: the opportunity against victim
+/// `(sender, nonce)` must not be acted on. `sender` is zero for victims
+/// without EVM fields; executors then match on the reply's nonce alone.
+///
+/// Layout (48 bytes, little-endian):
+///   [0..4]   magic   "MVCX"
+///   [4..24]  sender  victim account
+///   [24..32] nonce   u64 — victim account nonce
+///   [32]     reason  `CancelReason`
+///   [33..40] _reserved
+///   [40..48] tx_ts_ns u64 — when the cancellation was enqueued
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct OpportunityCancel {
+    pub magic: [u8; 4],
+    pub sender: [u8; 20],
+    pub nonce_le: [u8; 8],
+    pub reason: u8,
+    pub _reserved: [u8; 7],
+    pub tx_ts_ns_le: [u8; 8],
+}
+
+const _: () = assert!(core::mem::size_of::<OpportunityCancel>() == 48);
+
+impl OpportunityCancel {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<OpportunityCancel>();
+
+    #[inline(always)]
+    pub fn nonce(&self) -> u64 {
+        u64::from_le_bytes(self.nonce_le)
+    }
+
+    #[inline(always)]
+    pub fn reason(&self) -> Option<CancelReason> {
+        CancelReason::from_u8(self.reason)
+    }
+
+    #[inline(always)]
+    pub fn tx_ts_ns(&self) -> u64 {
+        u64::from_le_bytes(self.tx_ts_ns_le)
+    }
+
+    /// View a reserved TX buffer as a cancellation to be filled in.
+    #[inline(always)]
+    pub fn emplace(buf: &mut [u8]) -> Option<&mut Self> {
+        bytemuck::try_from_bytes_mut(buf).ok()
+    }
+
+    /// Zero-copy parse; returns `None` unless the payload is exactly one
+    /// `OpportunityCancel` carrying the expected magic.
+    pub fn parse(data: &[u8]) -> Option<&OpportunityCancel> {
+        if data.len() != Self::WIRE_SIZE {
+            return None;
+        }
+        let cancel = bytemuck::try_from_bytes::<OpportunityCancel>(data).ok()?;
+        (cancel.magic == OPPORTUNITY_CANCEL_MAGIC).then_some(cancel)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::DexSwapTx;
+    use super::{DexSwapTx, DexSwapTxEvm, DexSwapTxExt, OpportunityReply};
     use bytemuck::bytes_of;
 
     #[test]
@@ -81,4 +355,81 @@ mod tests {
         assert_eq!(parsed.amount_in(), 2_000_000);
         assert_eq!(parsed.min_amount_out(), 1_980_000);
     }
+
+    #[test]
+    fn extended_payload_prefix_is_base_record() {
+        let base = DexSwapTx::from_parts(5, [0xCD; 20], 3_000_000, 1, 0);
+        let ext =
+            DexSwapTxExt::from_parts(base, 1_700_000_000_000).with_priority_fee(2_000_000_000);
+        let raw = bytes_of(&ext);
+
+        let parsed = DexSwapTxExt::parse(raw).expect("64-byte payload");
+        assert_eq!(
+            (parsed.deadline_ms(), parsed.priority_fee()),
+            (1_700_000_000_000, 2_000_000_000)
+        );
+        assert_eq!(DexSwapTxExt::from_parts(base, 0).priority_fee(), 0);
+        let prefix = bytemuck::try_from_bytes::<DexSwapTx>(&raw[..DexSwapTx::WIRE_SIZE]).unwrap();
+        assert_eq!(prefix.amount_in(), 3_000_000);
+        assert!(DexSwapTxExt::parse(bytes_of(&base)).is_none());
+    }
+
+    #[test]
+    fn evm_payload_extends_ext_record() {
+        let base = DexSwapTx::from_parts(17, [0xCD; 20], 3_000_000, 1, 0);
+        let evm = DexSwapTxEvm::from_parts(
+            DexSwapTxExt::from_parts(base, 0),
+            [0x5E; 20],
+            30_000_000_000,
+        );
+        let raw = bytes_of(&evm);
+
+        let parsed = DexSwapTxEvm::parse(raw).expect("96-byte payload");
+        assert_eq!(
+            (parsed.nonce(), parsed.sender, parsed.gas_price()),
+            (17, [0x5E; 20], 30_000_000_000)
+        );
+        assert_eq!(
+            DexSwapTxExt::parse(raw).unwrap().base.amount_in(),
+            3_000_000
+        );
+        assert!(DexSwapTxEvm::parse(&raw[..DexSwapTxExt::WIRE_SIZE]).is_none());
+    }
+
+    #[test]
+    fn batch_view_ignores_trailing_partial_record() {
+        let txs = [
+            DexSwapTx::from_parts(1, [0x01; 20], 1_000, 900, 0),
+            DexSwapTx::from_parts(2, [0x02; 20], 2_000, 1_900, 1),
+        ];
+        let mut raw = [0u8; DexSwapTx::WIRE_SIZE * 2 + 5];
+        raw[..DexSwapTx::WIRE_SIZE * 2].copy_from_slice(bytemuck::cast_slice(&txs));
+
+        let batch = DexSwapTx::cast_batch(&raw);
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0].nonce(), 1);
+        assert_eq!(batch[1].amount_in(), 2_000);
+        assert_eq!(batch.as_ptr() as usize, raw.as_ptr() as usize);
+    }
+
+    #[test]
+    fn reply_is_written_in_place() {
+        let mut buf = [0xFFu8; OpportunityReply::WIRE_SIZE];
+        let reply = OpportunityReply::emplace(&mut buf).unwrap();
+        reply.nonce_le = 9u64.to_le_bytes();
+        reply.profit_le = 1_234u64.to_le_bytes();
+        reply.rx_ts_ns_le = 1_000u64.to_le_bytes();
+        reply.tx_ts_ns_le = 4_500u64.to_le_bytes();
+        reply.confidence_bps_le = 7_500u16.to_le_bytes();
+        assert_eq!(
+            (reply.wire_latency_ns(), reply.confidence_bps()),
+            (3_500, 7_500)
+        );
+        assert_eq!(buf[8..16], 1_234u64.to_le_bytes());
+        assert_eq!(buf[24..32], 4_500u64.to_le_bytes());
+        assert_eq!(buf[32..34], 7_500u16.to_le_bytes());
+        // Not reset by `emplace`: senders fill every field.
+        assert_eq!(buf[34..], [0xFF; 6]);
+        assert!(OpportunityReply::emplace(&mut [0u8; 8]).is_none());
+    }
 }
@@ -0,0 +1,458 @@
+//! Crash-only node state in a memory-mapped file.
+//!
+//! Two things go wrong when the node restarts with empty tables: the pool
+//! table forgets every pool's feed sequence, so a late copy of an old update
+//! is applied as if it were new; and the node forgets which victims it has
+//! already replied to, so a victim the relayer delivers again gets a second
+//! reply. Both are kept in a small file mapped `MAP_SHARED`, rewritten off
+//! the hot path every few milliseconds, and read back on startup.
+//!
+//! The file holds two snapshot slots and the index of the valid one. A new
+//! snapshot goes into the other slot and is published by flipping the index
+//! last, so a crash mid-write leaves the previous snapshot intact. Mapped
+//! pages outlive the process, not the host: put the file on persistent
+//! storage if it has to survive a reboot.
+use crate::dimensions::ActiveDimensions;
+use crate::nonce::Address;
+use crate::validator::PoolStateUpdate;
+use core::sync::atomic::{fence, Ordering};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd};
+use zerocopy::byteorder::little_endian::{U32, U64};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
+
+/// Pools one snapshot holds: the whole pool table.
+pub const MAX_PERSISTED_POOLS: usize = ActiveDimensions::POOL_CAPACITY;
+/// Most recent replies remembered across a restart.
+pub const REPLY_HISTORY: usize = 256;
+
+const MAGIC: [u8; 8] = *b"MEVSTATE";
+const VERSION: u32 = 1;
+
+/// A victim the node replied to: its sender (zero for non-EVM payloads) and
+/// nonce.
+#[repr(C)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned,
+)]
+pub struct ReplyKey {
+    pub sender: Address,
+    pub nonce: U64,
+}
+
+impl ReplyKey {
+    pub fn new(sender: Address, nonce: u64) -> Self {
+        Self {
+            sender,
+            nonce: U64::new(nonce),
+        }
+    }
+}
+
+/// File header. `active` is written last and selects the valid slot.
+#[repr(C)]
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+struct FileHeader {
+    magic: [u8; 8],
+    version: U32,
+    active: U32,
+    /// Capacities the file was laid out with; a build with other table
+    /// sizes starts fresh.
+    pool_capacity: U32,
+    reply_capacity: U32,
+    _pad: [u8; 8],
+}
+
+#[repr(C)]
+#[derive(FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned)]
+struct SlotHeader {
+    /// Replies emitted since the state file was created.
+    emitted: U64,
+    pools: U32,
+    replies: U32,
+    _pad: [u8; 16],
+}
+
+const HEADER_SIZE: usize = core::mem::size_of::<FileHeader>();
+const SLOT_HEADER_SIZE: usize = core::mem::size_of::<SlotHeader>();
+const POOLS_SIZE: usize = MAX_PERSISTED_POOLS * PoolStateUpdate::WIRE_SIZE;
+const SLOT_SIZE: usize =
+    SLOT_HEADER_SIZE + POOLS_SIZE + REPLY_HISTORY * core::mem::size_of::<ReplyKey>();
+
+/// Size of the state file.
+pub const STATE_FILE_SIZE: usize = HEADER_SIZE + 2 * SLOT_SIZE;
+
+/// State read back from a snapshot.
+#[derive(Debug)]
+pub struct Restored<'a> {
+    /// Last applied update of each pool.
+    pub pools: &'a [PoolStateUpdate],
+    /// Replies, oldest first.
+    pub replies: &'a [ReplyKey],
+    pub emitted: u64,
+}
+
+fn slot(region: &[u8], index: u32) -> &[u8] {
+    let start = HEADER_SIZE + index as usize * SLOT_SIZE;
+    &region[start..start + SLOT_SIZE]
+}
+
+/// The valid snapshot in `region`, or `None` if it was never written or
+/// was laid out by a different build.
+pub fn load(region: &[u8]) -> Option<Restored<'_>> {
+    let (header, _) = FileHeader::ref_from_prefix(region).ok()?;
+    let valid = header.magic == MAGIC
+        && header.version.get() == VERSION
+        && header.pool_capacity.get() as usize == MAX_PERSISTED_POOLS
+        && header.reply_capacity.get() as usize == REPLY_HISTORY
+        && header.active.get() < 2
+        && region.len() >= STATE_FILE_SIZE;
+    if !valid {
+        return None;
+    }
+    let (slot_header, body) =
+        SlotHeader::ref_from_prefix(slot(region, header.active.get())).ok()?;
+    let (pools, replies) = body.split_at(POOLS_SIZE);
+    let pool_count = (slot_header.pools.get() as usize).min(MAX_PERSISTED_POOLS);
+    let reply_count = (slot_header.replies.get() as usize).min(REPLY_HISTORY);
+    Some(Restored {
+        pools: <[PoolStateUpdate]>::ref_from_prefix_with_elems(pools, pool_count)
+            .ok()?
+            .0,
+        replies: <[ReplyKey]>::ref_from_prefix_with_elems(replies, reply_count)
+            .ok()?
+            .0,
+        emitted: slot_header.emitted.get(),
+    })
+}
+
+/// Write a snapshot into the inactive slot of `region`, then publish it.
+/// Pools beyond [`MAX_PERSISTED_POOLS`] and replies beyond
+/// [`REPLY_HISTORY`] are dropped.
+pub fn store(
+    region: &mut [u8],
+    pools: impl IntoIterator<Item = PoolStateUpdate>,
+    replies: impl IntoIterator<Item = ReplyKey>,
+    emitted: u64,
+) {
+    assert!(region.len() >= STATE_FILE_SIZE, "state region too small");
+    let active = core::mem::offset_of!(FileHeader, active);
+    let next = match load(region) {
+        Some(_) => {
+            1 - u32::from_le_bytes([
+                region[active],
+                region[active + 1],
+                region[active + 2],
+                region[active + 3],
+            ])
+        }
+        None => {
+            let header = FileHeader {
+                magic: MAGIC,
+                version: U32::new(VERSION),
+                active: U32::new(1),
+                pool_capacity: U32::new(MAX_PERSISTED_POOLS as u32),
+                reply_capacity: U32::new(REPLY_HISTORY as u32),
+                _pad: [0; 8],
+            };
+            region[..HEADER_SIZE].copy_from_slice(header.as_bytes());
+            0
+        }
+    };
+    let start = HEADER_SIZE + next as usize * SLOT_SIZE;
+    let body = &mut region[start + SLOT_HEADER_SIZE..start + SLOT_SIZE];
+    let (pool_bytes, reply_bytes) = body.split_at_mut(POOLS_SIZE);
+    let mut pool_count = 0;
+    for (dst, update) in pool_bytes
+        .chunks_exact_mut(PoolStateUpdate::WIRE_SIZE)
+        .zip(pools)
+    {
+        dst.copy_from_slice(update.as_bytes());
+        pool_count += 1;
+    }
+    let mut reply_count = 0;
+    for (dst, key) in reply_bytes
+        .chunks_exact_mut(core::mem::size_of::<ReplyKey>())
+        .zip(replies)
+    {
+        dst.copy_from_slice(key.as_bytes());
+        reply_count += 1;
+    }
+    let slot_header = SlotHeader {
+        emitted: U64::new(emitted),
+        pools: U32::new(pool_count),
+        replies: U32::new(reply_count),
+        _pad: [0; 16],
+    };
+    region[start..start + SLOT_HEADER_SIZE].copy_from_slice(slot_header.as_bytes());
+    // The slot must be complete before the index points at it.
+    fence(Ordering::Release);
+    // SAFETY: in-bounds; volatile so the store is neither elided nor hoisted.
+    unsafe {
+        core::ptr::write_volatile(
+            region[active..active + 4].as_mut_ptr() as *mut [u8; 4],
+            next.to_le_bytes(),
+        )
+    };
+}
+
+/// Ring of the last `N` replies, indexed by emission count, remembering
+/// which of them were sent before the last restart.
+pub struct ReplyHistory<const N: usize> {
+    keys: [ReplyKey; N],
+    /// Replies ever emitted, including before restarts.
+    emitted: u64,
+    /// `emitted` when the history was restored.
+    restored_until: u64,
+}
+
+impl<const N: usize> ReplyHistory<N> {
+    pub fn new() -> Self {
+        Self {
+            keys: [ReplyKey::new([0; 20], 0); N],
+            emitted: 0,
+            restored_until: 0,
+        }
+    }
+
+    /// Rebuild from persisted `keys` (oldest first) and emission count.
+    pub fn restore(keys: &[ReplyKey], emitted: u64) -> Self {
+        let mut history = Self::new();
+        let keys = &keys[keys.len().saturating_sub(N)..];
+        history.emitted = emitted.saturating_sub(keys.len() as u64);
+        keys.iter()
+            .for_each(|key| history.record(key.sender, key.nonce.get()));
+        history.restored_until = history.emitted;
+        history
+    }
+
+    #[inline(always)]
+    fn slot(e: u64) -> usize {
+        (e % N as u64) as usize
+    }
+
+    /// Emission numbers still held in the ring.
+    fn held(&self) -> core::ops::Range<u64> {
+        self.emitted.saturating_sub(N as u64)..self.emitted
+    }
+
+    pub fn record(&mut self, sender: Address, nonce: u64) {
+        self.keys[Self::slot(self.emitted)] = ReplyKey::new(sender, nonce);
+        self.emitted += 1;
+    }
+
+    pub fn emitted(&self) -> u64 {
+        self.emitted
+    }
+
+    /// Whether a reply for this victim went out before the last restart.
+    /// Replies since then are screened by the nonce tracker instead.
+    pub fn replied_before_restart(&self, sender: &Address, nonce: u64) -> bool {
+        let held = self.held();
+        (held.start..self.restored_until.min(held.end)).any(|e| {
+            let key = &self.keys[Self::slot(e)];
+            key.sender == *sender && key.nonce.get() == nonce
+        })
+    }
+
+    /// Held replies, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = ReplyKey> + '_ {
+        self.held().map(|e| self.keys[Self::slot(e)])
+    }
+}
+
+impl<const N: usize> Default for ReplyHistory<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum StateFileError {
+    Open(i32),
+    Resize(i32),
+    Mmap(i32),
+}
+
+impl core::fmt::Display for StateFileError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Open(e) => write!(f, "state file open failed (errno={})", e),
+            Self::Resize(e) => write!(f, "state file resize failed (errno={})", e),
+            Self::Mmap(e) => write!(f, "state file mmap failed (errno={})", e),
+        }
+    }
+}
+
+impl std::error::Error for StateFileError {}
+
+fn errno() -> i32 {
+    unsafe { *libc::__errno_location() }
+}
+
+/// The mapped state file.
+pub struct StateFile {
+    ptr: *mut u8,
+    /// Kept open so the mapping can be handed to a successor.
+    fd: OwnedFd,
+}
+
+impl StateFile {
+    /// Map `path`, creating it (empty, with no snapshot) if needed.
+    pub fn open(path: &str) -> Result<Self, StateFileError> {
+        let path = std::ffi::CString::new(path).map_err(|_| StateFileError::Open(libc::EINVAL))?;
+        let fd = unsafe {
+            libc::open(
+                path.as_ptr(),
+                libc::O_RDWR | libc::O_CREAT | libc::O_CLOEXEC,
+                0o600,
+            )
+        };
+        if fd < 0 {
+            return Err(StateFileError::Open(errno()));
+        }
+        // SAFETY: just opened, owned by nobody else.
+        Self::from_fd(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+
+    /// Map an already open state file, e.g. one handed over by the node
+    /// this one replaces.
+    pub fn from_fd(fd: OwnedFd) -> Result<Self, StateFileError> {
+        // Grows a fresh file with zeroes; an existing one of the right size
+        // is left as is.
+        if unsafe { libc::ftruncate(fd.as_raw_fd(), STATE_FILE_SIZE as libc::off_t) } != 0 {
+            return Err(StateFileError::Resize(errno()));
+        }
+        let ptr = unsafe {
+            libc::mmap(
+                core::ptr::null_mut(),
+                STATE_FILE_SIZE,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fd.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(StateFileError::Mmap(errno()));
+        }
+        Ok(Self {
+            ptr: ptr as *mut u8,
+            fd,
+        })
+    }
+
+    /// The whole mapping.
+    pub fn region(&self) -> &[u8] {
+        // SAFETY: mapped for STATE_FILE_SIZE bytes until drop.
+        unsafe { core::slice::from_raw_parts(self.ptr, STATE_FILE_SIZE) }
+    }
+
+    /// The last published snapshot, if any.
+    pub fn load(&self) -> Option<Restored<'_>> {
+        load(self.region())
+    }
+
+    pub fn store(
+        &mut self,
+        pools: impl IntoIterator<Item = PoolStateUpdate>,
+        replies: impl IntoIterator<Item = ReplyKey>,
+        emitted: u64,
+    ) {
+        // SAFETY: as in `region`; `&mut self` makes this the only view.
+        let region = unsafe { core::slice::from_raw_parts_mut(self.ptr, STATE_FILE_SIZE) };
+        store(region, pools, replies, emitted);
+    }
+}
+
+impl AsFd for StateFile {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl Drop for StateFile {
+    fn drop(&mut self) {
+        unsafe { libc::munmap(self.ptr as *mut libc::c_void, STATE_FILE_SIZE) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool(tag: u8, seq: u32) -> PoolStateUpdate {
+        PoolStateUpdate::new([tag; 20], 1_000, 2_000, 7, seq)
+    }
+
+    #[test]
+    fn snapshots_alternate_and_survive_a_torn_write() {
+        let mut region = vec![0u8; STATE_FILE_SIZE];
+        assert!(load(&region).is_none());
+
+        store(
+            &mut region,
+            [pool(1, 10), pool(2, 20)],
+            [ReplyKey::new([9; 20], 4)],
+            1,
+        );
+        let restored = load(&region).unwrap();
+        assert_eq!(restored.pools, [pool(1, 10), pool(2, 20)]);
+        assert_eq!(
+            (restored.replies, restored.emitted),
+            (&[ReplyKey::new([9; 20], 4)][..], 1)
+        );
+
+        store(&mut region, [pool(1, 11)], [], 1);
+        assert_eq!(load(&region).unwrap().pools, [pool(1, 11)]);
+
+        // A crash while filling the other slot: the index still points at
+        // the last complete snapshot.
+        let other = HEADER_SIZE
+            + SLOT_SIZE * (1 - region[core::mem::offset_of!(FileHeader, active)] as usize);
+        region[other..other + SLOT_SIZE].fill(0xFF);
+        assert_eq!(load(&region).unwrap().pools, [pool(1, 11)]);
+    }
+
+    #[test]
+    fn only_replies_from_before_the_restart_are_duplicates() {
+        let mut before = ReplyHistory::<4>::new();
+        for nonce in 1..=6 {
+            before.record([1; 20], nonce);
+        }
+        let keys: Vec<_> = before.iter().collect();
+        assert_eq!(
+            keys.iter().map(|k| k.nonce.get()).collect::<Vec<_>>(),
+            [3, 4, 5, 6]
+        );
+
+        let mut after = ReplyHistory::<4>::restore(&keys, before.emitted());
+        assert!(after.replied_before_restart(&[1; 20], 5));
+        assert!(!after.replied_before_restart(&[1; 20], 2));
+        assert!(!after.replied_before_restart(&[2; 20], 5));
+
+        // New replies push the restored ones out and are not flagged.
+        after.record([1; 20], 7);
+        after.record([1; 20], 8);
+        assert_eq!(after.emitted(), 8);
+        assert!(!after.replied_before_restart(&[1; 20], 3));
+        assert!(after.replied_before_restart(&[1; 20], 6));
+        assert!(!after.replied_before_restart(&[1; 20], 7));
+    }
+
+    #[test]
+    fn state_file_round_trips_through_the_mapping() {
+        let path = std::env::temp_dir().join(format!("mev-state-test-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        {
+            let mut file = StateFile::open(path).unwrap();
+            assert!(file.load().is_none());
+            file.store([pool(3, 30)], [ReplyKey::new([0; 20], 77)], 12);
+        }
+        let file = StateFile::open(path).unwrap();
+        let restored = file.load().unwrap();
+        assert_eq!((restored.pools, restored.emitted), (&[pool(3, 30)][..], 12));
+        assert_eq!(restored.replies[0].nonce.get(), 77);
+        drop(file);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,193 @@
+//! PTP hardware clock (PHC) offset tracking for cross-host timestamps.
+//!
+//! Reply timestamps are taken from the system clock, which on each host in
+//! the pipeline drifts on its own. The NIC's hardware clock is what PTP
+//! (`ptp4l`) disciplines against the grandmaster, so stamps expressed in PHC
+//! time are comparable between relayer, node and executor, and their
+//! difference is a true one-way latency.
+//!
+//! Reading the PHC on every packet would cost a syscall, so the node instead
+//! estimates the PHC − system clock offset off the hot path
+//! ([`PhcClock::estimate_offset`]) and publishes it through
+//! [`runtime::set_wire_clock_offset_ns`](crate::runtime::set_wire_clock_offset_ns);
+//! outgoing stamps are the system time plus that offset.
+//!
+//! The estimate uses `PTP_SYS_OFFSET`: the kernel reads system, PHC, system,
+//! ... back to back, and the pair with the tightest system-clock bracket
+//! gives the offset with the least uncertainty.
+
+/// Samples requested per `PTP_SYS_OFFSET` call (kernel maximum: 25).
+pub const OFFSET_SAMPLES: usize = 9;
+const PTP_MAX_SAMPLES: usize = 25;
+
+/// One PHC − system offset estimate.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OffsetEstimate {
+    /// PHC time minus system time.
+    pub offset_ns: i64,
+    /// Width of the system-clock bracket around the PHC read: the
+    /// estimate's uncertainty.
+    pub delay_ns: u64,
+}
+
+/// Best offset from interleaved `[sys, phc, sys, phc, ..., sys]` readings:
+/// the PHC read compared against the midpoint of its tightest bracket.
+pub fn best_offset(readings: &[u64]) -> Option<OffsetEstimate> {
+    if readings.len() < 3 || readings.len().is_multiple_of(2) {
+        return None;
+    }
+    readings
+        .windows(3)
+        .step_by(2)
+        .filter(|w| w[2] >= w[0])
+        .map(|w| {
+            let delay_ns = w[2] - w[0];
+            let midpoint = w[0] + delay_ns / 2;
+            OffsetEstimate {
+                offset_ns: w[1] as i64 - midpoint as i64,
+                delay_ns,
+            }
+        })
+        .min_by_key(|e| e.delay_ns)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum PhcError {
+    Open(i32),
+    /// `PTP_SYS_OFFSET` failed: not a PHC, or the driver lacks support.
+    SysOffset(i32),
+    NoSamples,
+}
+
+impl core::fmt::Display for PhcError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Open(e) => write!(f, "PHC open failed (errno={})", e),
+            Self::SysOffset(e) => write!(f, "PTP_SYS_OFFSET failed (errno={})", e),
+            Self::NoSamples => write!(f, "PHC returned no usable samples"),
+        }
+    }
+}
+
+impl std::error::Error for PhcError {}
+
+fn errno() -> i32 {
+    unsafe { *libc::__errno_location() }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct PtpClockTime {
+    sec: i64,
+    nsec: u32,
+    _reserved: u32,
+}
+
+#[repr(C)]
+struct PtpSysOffset {
+    n_samples: u32,
+    _rsv: [u32; 3],
+    ts: [PtpClockTime; 2 * PTP_MAX_SAMPLES + 1],
+}
+
+/// `_IOW('=', 5, struct ptp_sys_offset)`.
+const PTP_SYS_OFFSET: libc::c_ulong = (1 << 30)
+    | ((core::mem::size_of::<PtpSysOffset>() as libc::c_ulong) << 16)
+    | ((b'=' as libc::c_ulong) << 8)
+    | 5;
+
+/// An open PHC character device (`/dev/ptpN`).
+pub struct PhcClock {
+    fd: i32,
+}
+
+impl PhcClock {
+    pub fn open(path: &str) -> Result<Self, PhcError> {
+        let path = std::ffi::CString::new(path).map_err(|_| PhcError::Open(libc::EINVAL))?;
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDONLY | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(PhcError::Open(errno()));
+        }
+        Ok(Self { fd })
+    }
+
+    /// Dynamic POSIX clock id of the device (`FD_TO_CLOCKID`).
+    pub fn clock_id(&self) -> libc::clockid_t {
+        ((!self.fd) << 3) | 3
+    }
+
+    /// Current PHC time in nanoseconds.
+    pub fn now_ns(&self) -> Option<u64> {
+        let mut ts = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        if unsafe { libc::clock_gettime(self.clock_id(), &mut ts) } != 0 {
+            return None;
+        }
+        Some(ts.tv_sec as u64 * 1_000_000_000 + ts.tv_nsec as u64)
+    }
+
+    /// Estimate the PHC − system (`CLOCK_REALTIME`) offset.
+    pub fn estimate_offset(&self) -> Result<OffsetEstimate, PhcError> {
+        let mut req = PtpSysOffset {
+            n_samples: OFFSET_SAMPLES as u32,
+            _rsv: [0; 3],
+            ts: [PtpClockTime::default(); 2 * PTP_MAX_SAMPLES + 1],
+        };
+        if unsafe { libc::ioctl(self.fd, PTP_SYS_OFFSET as _, &mut req as *mut PtpSysOffset) } < 0 {
+            return Err(PhcError::SysOffset(errno()));
+        }
+        let mut readings = [0u64; 2 * OFFSET_SAMPLES + 1];
+        for (ns, ts) in readings.iter_mut().zip(&req.ts) {
+            *ns = ts.sec as u64 * 1_000_000_000 + ts.nsec as u64;
+        }
+        best_offset(&readings).ok_or(PhcError::NoSamples)
+    }
+}
+
+impl Drop for PhcClock {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tightest_bracket_wins() {
+        // PHC runs 37 s (TAI - UTC) plus 500 ns ahead of the system clock.
+        let ahead = 37_000_000_500u64;
+        let readings = [
+            1_000,
+            1_200 + ahead, // 600 ns bracket, PHC read early in it
+            1_600,
+            1_650 + ahead, // 100 ns bracket
+            1_700,
+            2_000 + ahead, // 900 ns bracket
+            2_600,
+        ];
+        assert_eq!(
+            best_offset(&readings),
+            Some(OffsetEstimate {
+                offset_ns: ahead as i64,
+                delay_ns: 100
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_readings_are_rejected() {
+        assert_eq!(best_offset(&[]), None);
+        assert_eq!(best_offset(&[1, 2]), None);
+        // System clock stepped backwards inside the only bracket.
+        assert_eq!(best_offset(&[5_000, 7_000, 4_000]), None);
+        // PHC behind the system clock gives a negative offset.
+        assert_eq!(
+            best_offset(&[1_000, 900, 1_000]).map(|e| e.offset_ns),
+            Some(-100)
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! Profit and loss by token, and in the numéraire.
+//!
+//! Every opportunity pays out in one token — the input token of the victim's
+//! pool, the quote token of a book market — while thresholds and replies
+//! speak the numéraire (`MEV_NUMERAIRE`). [`PnlLedger`] keeps the books in
+//! the tokens themselves, so a move in prices revalues them rather than
+//! freezing the rate each opportunity happened to be found at:
+//! - detected edge: the profit of every opportunity, before any sender
+//!   weighting, with its estimated cost (`MEV_GAS_COST`, numéraire units);
+//! - realized: the edge of the opportunities that landed, and the gas they
+//!   paid. In `MEV_SIM` mode the simulator's race decides; otherwise an
+//!   executor `VictimOutcome` reporting the victim of a live reply landed.
+//!
+//! Token amounts are kept in millionths of a whole token
+//! ([`ACCOUNT_DECIMALS`]), so that sums of 18-decimal tokens fit 64 bits.
+//! [`PnlLedger::snapshot`] converts them into the numéraire at the prices of
+//! the moment, live reference prices first. Snapshots are served as JSON on
+//! the admin `pnl` command and pushed by the statsd exporter.
+//!
+//! Counters are relaxed atomics, written by the packet loop and read by the
+//! exporter, like the rest of [`NodeStats`].
+//!
+//! [`NodeStats`]: crate::runtime::NodeStats
+use crate::prices::PriceCache;
+use crate::tokens::{rescale, token, ProfitNormalizer, TokenId, TOKEN_COUNT};
+use core::sync::atomic::{AtomicU64, Ordering};
+
+/// Decimals token amounts are booked with.
+pub const ACCOUNT_DECIMALS: u8 = 6;
+
+struct TokenBook {
+    opportunities: AtomicU64,
+    edge: AtomicU64,
+    landed: AtomicU64,
+    realized: AtomicU64,
+}
+
+impl TokenBook {
+    const fn new() -> Self {
+        Self {
+            opportunities: AtomicU64::new(0),
+            edge: AtomicU64::new(0),
+            landed: AtomicU64::new(0),
+            realized: AtomicU64::new(0),
+        }
+    }
+}
+
+pub struct PnlLedger {
+    tokens: [TokenBook; TOKEN_COUNT],
+    /// Estimated cost of the opportunities detected, numéraire units.
+    est_costs: AtomicU64,
+    /// Gas paid by those that landed, numéraire units.
+    paid_costs: AtomicU64,
+}
+
+impl PnlLedger {
+    pub const fn new() -> Self {
+        Self {
+            tokens: [const { TokenBook::new() }; TOKEN_COUNT],
+            est_costs: AtomicU64::new(0),
+            paid_costs: AtomicU64::new(0),
+        }
+    }
+
+    /// Book an opportunity worth `raw` units of `token`, expected to cost
+    /// `cost` numéraire units. Unknown tokens are ignored.
+    #[inline(always)]
+    pub fn record_edge(&self, token_id: TokenId, raw: u64, cost: u64) {
+        let Some((book, amount)) = self.book(token_id, raw) else {
+            return;
+        };
+        book.opportunities.fetch_add(1, Ordering::Relaxed);
+        book.edge.fetch_add(amount, Ordering::Relaxed);
+        self.est_costs.fetch_add(cost, Ordering::Relaxed);
+    }
+
+    /// Book an opportunity that landed, earning `raw` units of `token` for
+    /// `cost` numéraire units of gas.
+    pub fn record_landed(&self, token_id: TokenId, raw: u64, cost: u64) {
+        let Some((book, amount)) = self.book(token_id, raw) else {
+            return;
+        };
+        book.landed.fetch_add(1, Ordering::Relaxed);
+        book.realized.fetch_add(amount, Ordering::Relaxed);
+        self.paid_costs.fetch_add(cost, Ordering::Relaxed);
+    }
+
+    fn book(&self, token_id: TokenId, raw: u64) -> Option<(&TokenBook, u64)> {
+        let amount = rescale(raw, token(token_id)?.decimals, ACCOUNT_DECIMALS).unwrap_or(u64::MAX);
+        Some((self.tokens.get(token_id as usize)?, amount))
+    }
+
+    /// The books now, valued in `normalizer`'s numéraire; at live prices
+    /// from `prices` where it has them.
+    pub fn snapshot(
+        &self,
+        normalizer: &ProfitNormalizer,
+        prices: Option<&PriceCache>,
+    ) -> PnlSnapshot {
+        let tokens = core::array::from_fn(|i| {
+            let book = &self.tokens[i];
+            let token = i as TokenId;
+            let (edge, realized) = (
+                book.edge.load(Ordering::Relaxed),
+                book.realized.load(Ordering::Relaxed),
+            );
+            TokenPnl {
+                token,
+                opportunities: book.opportunities.load(Ordering::Relaxed),
+                edge,
+                edge_value: value(normalizer, prices, token, edge),
+                landed: book.landed.load(Ordering::Relaxed),
+                realized,
+                realized_value: value(normalizer, prices, token, realized),
+            }
+        });
+        PnlSnapshot {
+            numeraire: normalizer.numeraire(),
+            tokens,
+            est_costs: self.est_costs.load(Ordering::Relaxed),
+            paid_costs: self.paid_costs.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for PnlLedger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// `amount` millionths of `token` in numéraire units; 0 without a price.
+fn value(
+    normalizer: &ProfitNormalizer,
+    prices: Option<&PriceCache>,
+    token_id: TokenId,
+    amount: u64,
+) -> u64 {
+    let Some(meta) = token(token_id) else {
+        return 0;
+    };
+    let unit = 10u64.pow(meta.decimals as u32);
+    let price = match prices {
+        Some(cache) => normalizer.to_numeraire_live(cache, token_id, unit),
+        None => normalizer.to_numeraire(token_id, unit),
+    };
+    let value = amount as u128 * price.unwrap_or(0) as u128 / 10u128.pow(ACCOUNT_DECIMALS as u32);
+    u64::try_from(value).unwrap_or(u64::MAX)
+}
+
+/// One token's books; amounts in millionths of the token, values in the
+/// numéraire.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TokenPnl {
+    pub token: TokenId,
+    pub opportunities: u64,
+    pub edge: u64,
+    pub edge_value: u64,
+    pub landed: u64,
+    pub realized: u64,
+    pub realized_value: u64,
+}
+
+/// [`PnlLedger`] at one point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PnlSnapshot {
+    pub numeraire: TokenId,
+    /// Indexed by `TokenId`.
+    pub tokens: [TokenPnl; TOKEN_COUNT],
+    pub est_costs: u64,
+    pub paid_costs: u64,
+}
+
+impl PnlSnapshot {
+    /// Detected edge across tokens, numéraire units.
+    pub fn edge(&self) -> u64 {
+        self.tokens
+            .iter()
+            .fold(0u64, |sum, t| sum.saturating_add(t.edge_value))
+    }
+
+    /// Realized edge across tokens, numéraire units.
+    pub fn realized(&self) -> u64 {
+        self.tokens
+            .iter()
+            .fold(0u64, |sum, t| sum.saturating_add(t.realized_value))
+    }
+
+    /// Detected edge net of estimated costs.
+    pub fn expected_net(&self) -> i64 {
+        (self.edge() as i128 - self.est_costs as i128).clamp(i64::MIN as i128, i64::MAX as i128)
+            as i64
+    }
+
+    /// Realized edge net of gas paid.
+    pub fn realized_net(&self) -> i64 {
+        (self.realized() as i128 - self.paid_costs as i128)
+            .clamp(i64::MIN as i128, i64::MAX as i128) as i64
+    }
+}
+
+impl core::fmt::Display for PnlSnapshot {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let symbol = |id| token(id).map_or("?", |t| t.symbol_str());
+        write!(
+            f,
+            "{{\"numeraire\":\"{}\",\"edge\":{},\"est_costs\":{},\"expected_net\":{},\"realized\":{},\"paid_costs\":{},\
+             \"realized_net\":{},\"tokens\":[",
+            symbol(self.numeraire),
+            self.edge(),
+            self.est_costs,
+            self.expected_net(),
+            self.realized(),
+            self.paid_costs,
+            self.realized_net()
+        )?;
+        for (i, t) in self.tokens.iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            write!(
+                f,
+                "{{\"token\":\"{}\",\"opportunities\":{},\"edge\":{},\"edge_value\":{},\"landed\":{},\"realized\":{},\
+                 \"realized_value\":{}}}",
+                symbol(t.token),
+                t.opportunities,
+                t.edge,
+                t.edge_value,
+                t.landed,
+                t.realized,
+                t.realized_value
+            )?;
+        }
+        f.write_str("]}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prices::PriceUpdate;
+    use crate::tokens::{USDC, WETH, WSOL};
+
+    #[test]
+    fn books_tokens_apart_and_values_them_in_the_numeraire() {
+        let ledger = PnlLedger::new();
+        ledger.record_edge(WSOL, 2_500_000_000, 10_000); // 2.5 SOL
+        ledger.record_edge(WETH, 1_000_000_000_000_000, 10_000); // 0.001 ETH
+        ledger.record_edge(USDC, 7_000_000, 10_000);
+        ledger.record_landed(WSOL, 2_500_000_000, 10_000);
+        ledger.record_edge(99, 1, 1);
+
+        let snapshot = ledger.snapshot(&ProfitNormalizer::USDC_E6, None);
+        let sol = snapshot.tokens[WSOL as usize];
+        assert_eq!(
+            (sol.opportunities, sol.edge, sol.edge_value),
+            (1, 2_500_000, 5_000_000_000)
+        );
+        assert_eq!((sol.landed, sol.realized_value), (1, 5_000_000_000));
+        assert_eq!(
+            (
+                snapshot.tokens[WETH as usize].edge,
+                snapshot.tokens[WETH as usize].edge_value
+            ),
+            (1_000, 3_000_000)
+        );
+        assert_eq!(
+            (snapshot.edge(), snapshot.est_costs),
+            (5_010_000_000, 30_000)
+        );
+        assert_eq!(
+            (snapshot.expected_net(), snapshot.realized_net()),
+            (5_009_970_000, 4_999_990_000)
+        );
+
+        // The same books in SOL, and at a live SOL price of 100 USDC.
+        let in_sol = ledger.snapshot(&ProfitNormalizer::new(WSOL).unwrap(), None);
+        assert_eq!(
+            (in_sol.numeraire, in_sol.tokens[WSOL as usize].edge_value),
+            (WSOL, 2_500_000_000)
+        );
+        let cache = PriceCache::new();
+        cache
+            .apply(&PriceUpdate::new(USDC, 1_000_000, 1, 1))
+            .unwrap();
+        cache
+            .apply(&PriceUpdate::new(WSOL, 100_000_000, 1, 1))
+            .unwrap();
+        let live = ledger.snapshot(&ProfitNormalizer::USDC_E6, Some(&cache));
+        assert_eq!(live.tokens[WSOL as usize].edge_value, 250_000_000);
+        assert!(live
+            .to_string()
+            .starts_with("{\"numeraire\":\"USDC\",\"edge\":260000000,"));
+    }
+}
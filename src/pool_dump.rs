@@ -0,0 +1,145 @@
+//! Pool table dumps, for finding out why an opportunity was not detected.
+//!
+//! A dump is a consistent copy of every pool in the table, sorted by
+//! address, in one of two forms:
+//!
+//! - JSON, for reading: address, token ids, reserves, fee, per-pool profit
+//!   threshold, the slot and feed sequence of the last applied update, and
+//!   `slots_behind`, how far that slot trails the newest in the table. A
+//!   pool that is far behind is stale. `inferred` counts the victim swaps
+//!   reserve inference applied on top of that update.
+//! - A snapshot of `PoolStateUpdate` records, one per pool. It can be
+//!   replayed into a node's pool feed (UDP 8081) to reproduce the table.
+//!   Tokens, fees and thresholds are not part of that message and come from
+//!   the receiving node's own registry.
+//!
+//! The admin socket serves both (`pools`, `pools bin`); `mev-zerocopy-node
+//! dump-pools` asks a running node for one.
+use crate::pool_registry::{PoolEntry, PoolRegistry};
+use crate::validator::PoolStateUpdate;
+use core::fmt::Write;
+use zerocopy::IntoBytes;
+
+pub struct PoolDump {
+    pub pools: Vec<PoolEntry>,
+    /// Highest last-update slot of any pool.
+    pub newest_slot: u64,
+}
+
+impl PoolDump {
+    /// Copy out `table`; not for the hot path.
+    pub fn collect<const S: usize, const N: usize>(table: &PoolRegistry<S, N>) -> Self {
+        let mut pools = Vec::new();
+        table.for_each(|entry| pools.push(*entry));
+        pools.sort_unstable_by_key(|entry| entry.address);
+        let newest_slot = pools.iter().map(|entry| entry.slot).max().unwrap_or(0);
+        Self { pools, newest_slot }
+    }
+
+    /// Slots between `entry`'s last update and the newest in the table.
+    pub fn slots_behind(&self, entry: &PoolEntry) -> u64 {
+        self.newest_slot.saturating_sub(entry.slot)
+    }
+
+    /// One line of JSON: `{"newest_slot":..,"pools":[{"address":"0x..",..},..]}`.
+    pub fn to_json(&self) -> String {
+        let mut out = format!("{{\"newest_slot\":{},\"pools\":[", self.newest_slot);
+        for (i, entry) in self.pools.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"address\":\"0x");
+            for byte in entry.address {
+                let _ = write!(out, "{byte:02x}");
+            }
+            let state = &entry.state;
+            let _ = write!(
+                out,
+                "\",\"token0\":{},\"token1\":{},\"reserve0\":{},\"reserve1\":{},\"fee_num\":{},\"fee_den\":{},\
+                 \"min_profit\":{},\"slot\":{},\"seq\":{},\"slots_behind\":{},\"inferred\":{}}}",
+                entry.token0,
+                entry.token1,
+                state.reserve0,
+                state.reserve1,
+                state.fee_num,
+                state.fee_den,
+                entry.min_profit.map_or("null".to_string(), |p| p.to_string()),
+                entry.slot,
+                entry.seq,
+                self.slots_behind(entry),
+                entry.inferred
+            );
+        }
+        out.push_str("]}");
+        out
+    }
+
+    /// Back-to-back `PoolStateUpdate` records, one per pool.
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.pools.len() * PoolStateUpdate::WIRE_SIZE);
+        for entry in &self.pools {
+            let update = PoolStateUpdate::new(
+                entry.address,
+                entry.state.reserve0,
+                entry.state.reserve1,
+                entry.slot,
+                entry.seq,
+            );
+            out.extend_from_slice(update.as_bytes());
+        }
+        out
+    }
+}
+
+/// Lowercase hex, to carry a snapshot over the line-based admin socket.
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(out, "{byte:02x}");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pool_registry::PoolTable;
+    use crate::processor::AmmPoolState;
+    use crate::testvectors::decode_hex;
+    use crate::validator;
+
+    #[test]
+    fn dumps_are_sorted_and_mark_stale_pools() {
+        let table = PoolTable::new();
+        let state = AmmPoolState::new(1_000, 2_000, 3, 1_000);
+        table
+            .upsert(PoolEntry::new([0xbb; 20], 1, 2, state).with_min_profit(50))
+            .unwrap();
+        table
+            .upsert(PoolEntry::new([0xaa; 20], 3, 4, state))
+            .unwrap();
+        assert!(table.update_reserves(&[0xbb; 20], 1_500, 1_400, 120, 9));
+        assert!(table.update_reserves(&[0xaa; 20], 1_000, 2_000, 100, 4));
+
+        let dump = PoolDump::collect(&table);
+        assert_eq!(dump.newest_slot, 120);
+        let json = dump.to_json();
+        assert!(json.starts_with(
+            "{\"newest_slot\":120,\"pools\":[{\"address\":\"0xaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\",\"token0\":3,"
+        ));
+        assert!(json.contains(
+            "\"min_profit\":null,\"slot\":100,\"seq\":4,\"slots_behind\":20,\"inferred\":0}"
+        ));
+        assert!(json.ends_with("\"reserve0\":1500,\"reserve1\":1400,\"fee_num\":3,\"fee_den\":1000,\"min_profit\":50,\"slot\":120,\"seq\":9,\"slots_behind\":0,\"inferred\":0}]}"));
+
+        let hex = to_hex(&dump.to_snapshot());
+        let mut bytes = [0u8; 2 * PoolStateUpdate::WIRE_SIZE];
+        assert_eq!(decode_hex(&hex, &mut bytes), Some(bytes.len()));
+        let update =
+            validator::validate_pool_update(&bytes[PoolStateUpdate::WIRE_SIZE..], 0).unwrap();
+        assert_eq!(
+            (update.pool_address, update.reserve0(), update.seq()),
+            ([0xbb; 20], 1_500, 9)
+        );
+    }
+}
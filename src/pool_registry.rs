@@ -0,0 +1,955 @@
+/// Sharded pool registry with per-shard seqlocks.
+///
+/// Pools are spread across `S` shards by a hash of the pool address. Each
+/// shard is a fixed open-addressing table of `N` slots guarded by a seqlock:
+///
+/// - Writers (update appliers) take the shard's sequence from even to odd,
+///   write the slot, then publish the next even value. Writers on different
+///   shards never contend; writers on the same shard serialize on a CAS.
+/// - Readers (strategy workers) never block. They copy the entry between two
+///   sequence loads and retry if a write overlapped (odd sequence, or
+///   sequence changed) — a torn read is always detected and discarded. The
+///   only shared write on the read path is the CLOCK reference bit below.
+///
+/// Capacity is fixed. When a new pool lands in a full shard, the shard evicts
+/// its least-recently-quoted pool using CLOCK: [`PoolRegistry::get`] sets a
+/// per-slot reference bit, and the eviction hand clears set bits until it
+/// finds a pool nobody quoted since the last sweep.
+///
+/// When a few pools take most of the traffic, their slots are still spread
+/// over every shard. [`PoolRegistry::repack`], run periodically off the hot
+/// path, ranks pools by how often they were quoted and copies the most
+/// quoted into a hot tier: one small table, a few dozen cache lines, that
+/// `get` probes before the shards. The shards stay authoritative; writers
+/// update the shard, then refresh the hot copy, and a hot read that
+/// overlaps any write to the tier falls through to the shard.
+///
+/// All storage is inline; the registry is `const`-constructible so it can
+/// live in a `static` shared by every worker thread.
+use crate::dimensions::ActiveDimensions;
+use crate::processor::AmmPoolState;
+use crate::runtime::{prefetch_read, CacheAlignedAtomicU64};
+use crate::tokens::TokenId;
+use crate::validator::PoolUpdate;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{fence, AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+
+/// Pool address as carried on the wire.
+pub type PoolAddress = [u8; 20];
+
+/// Reserved "empty slot" address.
+const EMPTY_ADDRESS: PoolAddress = [0; 20];
+
+/// FNV-1a of the address, never 0: a one-word key for lock-free tables.
+pub(crate) fn pool_key(address: &PoolAddress) -> u64 {
+    let hash = address.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, &b| {
+        (h ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+    hash.max(1)
+}
+
+/// One pool: identity, token pair, and latest AMM state.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolEntry {
+    pub state: AmmPoolState,
+    pub address: PoolAddress,
+    pub token0: TokenId,
+    pub token1: TokenId,
+    /// Block/slot of the last applied update.
+    pub slot: u64,
+    /// Feed sequence number of the last applied update.
+    pub seq: u32,
+    /// Pool-state source of the last applied update (`state_source`); 0 is
+    /// the wire feed.
+    pub source: u8,
+    /// Victim swaps applied by reserve inference since the last update.
+    pub inferred: u16,
+    /// Profit threshold for opportunities on this pool, overriding the
+    /// strategy and global thresholds.
+    pub min_profit: Option<u64>,
+}
+
+impl PoolEntry {
+    pub const EMPTY: Self = Self {
+        state: AmmPoolState::new(0, 0, 0, 0),
+        address: EMPTY_ADDRESS,
+        token0: 0,
+        token1: 0,
+        slot: 0,
+        seq: 0,
+        source: 0,
+        inferred: 0,
+        min_profit: None,
+    };
+
+    pub const fn new(
+        address: PoolAddress,
+        token0: TokenId,
+        token1: TokenId,
+        state: AmmPoolState,
+    ) -> Self {
+        Self {
+            state,
+            address,
+            token0,
+            token1,
+            slot: 0,
+            seq: 0,
+            source: 0,
+            inferred: 0,
+            min_profit: None,
+        }
+    }
+
+    pub const fn with_min_profit(mut self, min_profit: u64) -> Self {
+        self.min_profit = Some(min_profit);
+        self
+    }
+}
+
+/// Returned when inserting a pool with the all-zero address, which marks
+/// empty slots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ZeroAddress;
+
+/// FxHash-style mix of the address bytes; pool addresses are already
+/// uniformly distributed, so this only needs to be cheap.
+#[inline(always)]
+pub fn address_hash(address: &PoolAddress) -> u64 {
+    let a = u64::from_le_bytes([
+        address[0], address[1], address[2], address[3], address[4], address[5], address[6],
+        address[7],
+    ]);
+    let b = u64::from_le_bytes([
+        address[8],
+        address[9],
+        address[10],
+        address[11],
+        address[12],
+        address[13],
+        address[14],
+        address[15],
+    ]);
+    let c = u32::from_le_bytes([address[16], address[17], address[18], address[19]]) as u64;
+    (a ^ b.rotate_left(21) ^ c.rotate_left(42)).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+/// Take a seqlock's sequence from even to odd; returns the even value.
+#[inline(always)]
+fn seq_lock(seq: &AtomicU64) -> u64 {
+    loop {
+        let s = seq.load(Ordering::Relaxed);
+        if s & 1 == 0
+            && seq
+                .compare_exchange_weak(s, s + 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        {
+            fence(Ordering::Release);
+            return s;
+        }
+        core::hint::spin_loop();
+    }
+}
+
+#[inline(always)]
+fn seq_unlock(seq: &AtomicU64, s: u64) {
+    seq.store(s + 2, Ordering::Release);
+}
+
+/// Count a quote. A plain load and store: quotes racing on the same pool
+/// may be lost, which only blurs the ranking.
+#[inline(always)]
+fn count_hit(hits: &AtomicU32) {
+    hits.store(
+        hits.load(Ordering::Relaxed).saturating_add(1),
+        Ordering::Relaxed,
+    );
+}
+
+/// Empty slot `i` of the `n`-slot linear-probing table at `slots` with
+/// backward-shift deletion, so probe chains stay intact without tombstones.
+/// `moved(from, to)` follows every entry shifted back. Returns the slot
+/// left empty.
+///
+/// # Safety
+/// `slots` points to `n` entries the caller holds the write lock for.
+unsafe fn shift_out(
+    slots: *mut PoolEntry,
+    n: usize,
+    mut i: usize,
+    home: impl Fn(&PoolAddress) -> usize,
+    mut moved: impl FnMut(usize, usize),
+) -> usize {
+    let mut j = i;
+    loop {
+        j = (j + 1) % n;
+        let e = core::ptr::read_volatile(slots.add(j));
+        if e.address == EMPTY_ADDRESS || j == i {
+            break;
+        }
+        // `e` may fill the hole unless its home lies cyclically in (i, j].
+        if (j + n - home(&e.address)) % n >= (j + n - i) % n {
+            core::ptr::write_volatile(slots.add(i), e);
+            moved(j, i);
+            i = j;
+        }
+    }
+    core::ptr::write_volatile(slots.add(i), PoolEntry::EMPTY);
+    i
+}
+
+#[repr(align(64))]
+struct Shard<const N: usize> {
+    seq: AtomicU64,
+    slots: UnsafeCell<[PoolEntry; N]>,
+    /// CLOCK reference bits, set by readers outside the seqlock.
+    referenced: [AtomicBool; N],
+    /// CLOCK hand; only moved under the write lock.
+    hand: AtomicUsize,
+    /// Quotes per slot since the last [`PoolRegistry::repack`].
+    hits: [AtomicU32; N],
+}
+
+impl<const N: usize> Shard<N> {
+    const fn new() -> Self {
+        Self {
+            seq: AtomicU64::new(0),
+            slots: UnsafeCell::new([PoolEntry::EMPTY; N]),
+            referenced: [const { AtomicBool::new(false) }; N],
+            hand: AtomicUsize::new(0),
+            hits: [const { AtomicU32::new(0) }; N],
+        }
+    }
+
+    #[inline(always)]
+    fn home(address: &PoolAddress) -> usize {
+        ((address_hash(address) >> 32) as usize) % N
+    }
+
+    /// Mark slot `i` as recently quoted. Skips the store when the bit is
+    /// already set so hot pools don't bounce the cache line between readers.
+    #[inline(always)]
+    fn touch(&self, i: usize) {
+        if !self.referenced[i].load(Ordering::Relaxed) {
+            self.referenced[i].store(true, Ordering::Relaxed);
+        }
+    }
+
+    #[inline(always)]
+    fn slot_ptr(&self, i: usize) -> *mut PoolEntry {
+        // SAFETY: `i < N` is guaranteed by every caller (index is masked).
+        unsafe { (self.slots.get() as *mut PoolEntry).add(i) }
+    }
+
+    /// Probe for `address`; returns the slot index of the match or of the
+    /// first empty slot. Caller must hold the write lock or validate the read.
+    #[inline(always)]
+    fn probe(&self, address: &PoolAddress, start: usize) -> Option<(usize, bool)> {
+        for step in 0..N {
+            let i = (start + step) % N;
+            // SAFETY: in-bounds; racy reads are validated by the seqlock.
+            let addr = unsafe { core::ptr::read_volatile(&(*self.slot_ptr(i)).address) };
+            if addr == *address {
+                return Some((i, true));
+            }
+            if addr == EMPTY_ADDRESS {
+                return Some((i, false));
+            }
+        }
+        None
+    }
+
+    #[inline(always)]
+    fn write_lock(&self) -> u64 {
+        seq_lock(&self.seq)
+    }
+
+    #[inline(always)]
+    fn write_unlock(&self, s: u64) {
+        seq_unlock(&self.seq, s)
+    }
+
+    /// Advance the CLOCK hand to a slot whose reference bit is clear,
+    /// clearing set bits on the way. Caller holds the write lock and the
+    /// shard is full, so this terminates within two sweeps.
+    fn clock_victim(&self) -> usize {
+        loop {
+            let i = self.hand.load(Ordering::Relaxed);
+            self.hand.store((i + 1) % N, Ordering::Relaxed);
+            if !self.referenced[i].swap(false, Ordering::Relaxed) {
+                return i;
+            }
+        }
+    }
+
+    /// Empty slot `i`, see [`shift_out`]. Caller holds the write lock.
+    fn remove_at(&self, i: usize) {
+        let moved = |from: usize, to: usize| {
+            self.referenced[to].store(
+                self.referenced[from].load(Ordering::Relaxed),
+                Ordering::Relaxed,
+            );
+            self.hits[to].store(self.hits[from].load(Ordering::Relaxed), Ordering::Relaxed);
+        };
+        // SAFETY: `N` slots, exclusive under the write lock.
+        let i = unsafe { shift_out(self.slot_ptr(0), N, i, Self::home, moved) };
+        self.referenced[i].store(false, Ordering::Relaxed);
+        self.hits[i].store(0, Ordering::Relaxed);
+    }
+}
+
+/// Slots in the hot tier.
+pub const HOT_SLOTS: usize = 64;
+
+/// Pools [`PoolRegistry::repack`] makes hot; fewer than [`HOT_SLOTS`], so
+/// probe chains stay short and always end in an empty slot.
+pub const HOT_POOLS: usize = 48;
+
+/// Copies of the most-quoted pools, packed into one table under its own
+/// seqlock.
+#[repr(align(64))]
+struct HotTier {
+    seq: AtomicU64,
+    /// Pools held; `get` skips the tier while it is 0.
+    len: AtomicUsize,
+    slots: UnsafeCell<[PoolEntry; HOT_SLOTS]>,
+    /// Quotes per slot since the last repack.
+    hits: [AtomicU32; HOT_SLOTS],
+}
+
+impl HotTier {
+    const fn new() -> Self {
+        Self {
+            seq: AtomicU64::new(0),
+            len: AtomicUsize::new(0),
+            slots: UnsafeCell::new([PoolEntry::EMPTY; HOT_SLOTS]),
+            hits: [const { AtomicU32::new(0) }; HOT_SLOTS],
+        }
+    }
+
+    #[inline(always)]
+    fn home(address: &PoolAddress) -> usize {
+        ((address_hash(address) >> 16) as usize) % HOT_SLOTS
+    }
+
+    #[inline(always)]
+    fn slot_ptr(&self, i: usize) -> *mut PoolEntry {
+        // SAFETY: `i < HOT_SLOTS` is guaranteed by every caller.
+        unsafe { (self.slots.get() as *mut PoolEntry).add(i) }
+    }
+
+    /// Slot holding `address`, or the empty slot ending its probe chain.
+    /// Caller must hold the write lock or validate the read.
+    #[inline(always)]
+    fn probe(&self, address: &PoolAddress) -> (usize, bool) {
+        let start = Self::home(address);
+        for step in 0..HOT_SLOTS {
+            let i = (start + step) % HOT_SLOTS;
+            // SAFETY: in-bounds; racy reads are validated by the seqlock.
+            let addr = unsafe { core::ptr::read_volatile(&(*self.slot_ptr(i)).address) };
+            if addr == *address || addr == EMPTY_ADDRESS {
+                return (i, addr == *address);
+            }
+        }
+        (start, false)
+    }
+
+    /// Consistent copy of a hot pool. `None` if it is not hot, or if a write
+    /// to the tier overlapped: the shard has it either way.
+    #[inline(always)]
+    fn get(&self, address: &PoolAddress) -> Option<PoolEntry> {
+        let before = self.seq.load(Ordering::Acquire);
+        if before & 1 != 0 {
+            return None;
+        }
+        let (i, hit) = self.probe(address);
+        // SAFETY: in-bounds; validated against the sequence below.
+        let entry = unsafe { core::ptr::read_volatile(self.slot_ptr(i)) };
+        fence(Ordering::Acquire);
+        if !hit || self.seq.load(Ordering::Relaxed) != before {
+            return None;
+        }
+        count_hit(&self.hits[i]);
+        Some(entry)
+    }
+
+    /// Whether `address` may be hot: a consistent probe says so, or a write
+    /// to the tier is under way.
+    fn holds(&self, address: &PoolAddress) -> bool {
+        let before = self.seq.load(Ordering::Acquire);
+        if before & 1 != 0 {
+            return true;
+        }
+        let (_, hit) = self.probe(address);
+        fence(Ordering::Acquire);
+        hit || self.seq.load(Ordering::Relaxed) != before
+    }
+
+    /// Replace the copy of `address`, if hot, with `entry`; drop it for
+    /// `None`. Caller holds the write lock.
+    fn set(&self, address: &PoolAddress, entry: Option<PoolEntry>) {
+        let (i, hit) = self.probe(address);
+        match entry {
+            // SAFETY: in-bounds; exclusive under the write lock.
+            Some(entry) if hit => unsafe { core::ptr::write_volatile(self.slot_ptr(i), entry) },
+            None if hit => {
+                let moved = |from: usize, to: usize| {
+                    self.hits[to].store(self.hits[from].load(Ordering::Relaxed), Ordering::Relaxed);
+                };
+                // SAFETY: `HOT_SLOTS` slots, exclusive under the write lock.
+                let i = unsafe { shift_out(self.slot_ptr(0), HOT_SLOTS, i, Self::home, moved) };
+                self.hits[i].store(0, Ordering::Relaxed);
+                self.len.fetch_sub(1, Ordering::Relaxed);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Pool table: `S` shards × `N` slots per shard.
+pub struct PoolRegistry<const S: usize, const N: usize> {
+    shards: [Shard<N>; S],
+    hot: HotTier,
+    /// Reads retried because a concurrent write was detected.
+    pub read_retries: CacheAlignedAtomicU64,
+    /// Pools evicted to make room for new ones.
+    pub evictions: CacheAlignedAtomicU64,
+}
+
+// SAFETY: all shared mutation goes through the per-shard seqlock; readers only
+// copy out and validate.
+unsafe impl<const S: usize, const N: usize> Sync for PoolRegistry<S, N> {}
+
+impl<const S: usize, const N: usize> PoolRegistry<S, N> {
+    const VALID: () = {
+        assert!(S.is_power_of_two(), "shard count must be a power of two");
+        assert!(N > 0, "shards need at least one slot");
+    };
+
+    pub const fn new() -> Self {
+        let () = Self::VALID;
+        Self {
+            shards: [const { Shard::new() }; S],
+            hot: HotTier::new(),
+            read_retries: CacheAlignedAtomicU64::new(0),
+            evictions: CacheAlignedAtomicU64::new(0),
+        }
+    }
+
+    pub const CAPACITY: usize = S * N;
+
+    #[inline(always)]
+    fn locate(&self, address: &PoolAddress) -> (&Shard<N>, usize) {
+        let shard = &self.shards[(address_hash(address) as usize) & (S - 1)];
+        (shard, Shard::<N>::home(address))
+    }
+
+    /// Consistent snapshot of a pool, or `None` if unknown. Never blocks.
+    /// Marks the pool as recently quoted for eviction purposes, and counts
+    /// the quote for [`repack`](Self::repack).
+    #[inline(always)]
+    pub fn get(&self, address: &PoolAddress) -> Option<PoolEntry> {
+        if self.hot.len.load(Ordering::Relaxed) != 0 {
+            if let Some(entry) = self.hot.get(address) {
+                return Some(entry);
+            }
+        }
+        let (shard, i, entry) = self.read(address)?;
+        shard.touch(i);
+        count_hit(&shard.hits[i]);
+        Some(entry)
+    }
+
+    /// Like [`get`](Self::get), but leaves the eviction state untouched —
+    /// for bookkeeping reads such as the feed checking sequence numbers.
+    #[inline(always)]
+    pub fn peek(&self, address: &PoolAddress) -> Option<PoolEntry> {
+        self.read(address).map(|(_, _, entry)| entry)
+    }
+
+    #[inline(always)]
+    fn read(&self, address: &PoolAddress) -> Option<(&Shard<N>, usize, PoolEntry)> {
+        let (shard, start) = self.locate(address);
+        loop {
+            let before = shard.seq.load(Ordering::Acquire);
+            if before & 1 == 0 {
+                let found = shard.probe(address, start).and_then(|(i, hit)| {
+                    // SAFETY: in-bounds; validated against the sequence below.
+                    hit.then(|| (i, unsafe { core::ptr::read_volatile(shard.slot_ptr(i)) }))
+                });
+                fence(Ordering::Acquire);
+                if shard.seq.load(Ordering::Relaxed) == before {
+                    return found.map(|(i, entry)| (shard, i, entry));
+                }
+            }
+            self.read_retries.inc();
+            core::hint::spin_loop();
+        }
+    }
+
+    /// Raw pointer to the slot a pool hashes to, for software prefetching.
+    /// Points at the first probe position; not a consistent read.
+    #[inline(always)]
+    pub fn slot_hint(&self, address: &PoolAddress) -> *const PoolEntry {
+        let (shard, start) = self.locate(address);
+        shard.slot_ptr(start)
+    }
+
+    /// Prefetch every cache line a [`get`](Self::get) of `address` touches
+    /// first: the home slot and its reference bit. Issue it an iteration or
+    /// two ahead so the DRAM miss overlaps with other work.
+    #[inline(always)]
+    pub fn prefetch(&self, address: &PoolAddress) {
+        let (shard, start) = self.locate(address);
+        let slot = shard.slot_ptr(start) as *const u8;
+        let mut line = 0;
+        while line < core::mem::size_of::<PoolEntry>() {
+            // SAFETY: stays within the slot; prefetching never faults.
+            prefetch_read(unsafe { slot.add(line) });
+            line += 64;
+        }
+        prefetch_read(&shard.referenced[start]);
+    }
+
+    /// Insert or replace a pool. If the pool is new and its shard is full,
+    /// the shard's least-recently-quoted pool is evicted and its address
+    /// returned.
+    pub fn upsert(&self, entry: PoolEntry) -> Result<Option<PoolAddress>, ZeroAddress> {
+        if entry.address == EMPTY_ADDRESS {
+            return Err(ZeroAddress);
+        }
+        let (shard, start) = self.locate(&entry.address);
+        let s = shard.write_lock();
+        let mut evicted = None;
+        let i = match shard.probe(&entry.address, start) {
+            Some((i, true)) => i,
+            Some((i, false)) => {
+                shard.referenced[i].store(false, Ordering::Relaxed);
+                shard.hits[i].store(0, Ordering::Relaxed);
+                i
+            }
+            None => {
+                // Quotes of hot pools leave their reference bits clear; pass
+                // over them while there is anything else to evict.
+                let mut victim = shard.clock_victim();
+                for _ in 1..N {
+                    // SAFETY: in-bounds; exclusive under the write lock.
+                    if !self
+                        .hot
+                        .holds(unsafe { &(*shard.slot_ptr(victim)).address })
+                    {
+                        break;
+                    }
+                    victim = shard.clock_victim();
+                }
+                // SAFETY: in-bounds; exclusive under the write lock.
+                evicted =
+                    Some(unsafe { core::ptr::read_volatile(&(*shard.slot_ptr(victim)).address) });
+                shard.remove_at(victim);
+                self.evictions.inc();
+                // The removal left an empty slot, so the probe cannot fail.
+                shard
+                    .probe(&entry.address, start)
+                    .map_or(victim, |(i, _)| i)
+            }
+        };
+        // SAFETY: exclusive under the write lock.
+        unsafe { core::ptr::write_volatile(shard.slot_ptr(i), entry) };
+        shard.write_unlock(s);
+        self.sync_hot(&entry.address);
+        if let Some(evicted) = &evicted {
+            self.sync_hot(evicted);
+        }
+        Ok(evicted)
+    }
+
+    /// Apply new reserves to a known pool. Returns `false` for unknown pools.
+    pub fn update_reserves(
+        &self,
+        address: &PoolAddress,
+        reserve0: u64,
+        reserve1: u64,
+        slot: u64,
+        seq: u32,
+    ) -> bool {
+        self.apply(
+            address,
+            &PoolUpdate {
+                version: 1,
+                reserve0,
+                reserve1,
+                slot,
+                seq,
+                fee: None,
+                sent_ns: None,
+            },
+        )
+    }
+
+    /// Apply a decoded pool update, fee included if it carries one, to a
+    /// known pool. Returns `false` for unknown pools.
+    pub fn apply(&self, address: &PoolAddress, update: &PoolUpdate) -> bool {
+        self.apply_from(address, update, 0)
+    }
+
+    /// [`PoolRegistry::apply`] an update delivered by pool-state `source`.
+    pub fn apply_from(&self, address: &PoolAddress, update: &PoolUpdate, source: u8) -> bool {
+        let (shard, start) = self.locate(address);
+        let s = shard.write_lock();
+        let hit = match shard.probe(address, start) {
+            Some((i, true)) => {
+                // SAFETY: exclusive under the write lock.
+                unsafe {
+                    let p = shard.slot_ptr(i);
+                    let mut e = core::ptr::read_volatile(p);
+                    e.state.reserve0 = update.reserve0;
+                    e.state.reserve1 = update.reserve1;
+                    if let Some((fee_num, fee_den)) = update.fee {
+                        e.state.fee_num = fee_num;
+                        e.state.fee_den = fee_den;
+                    }
+                    e.state = e.state.refreshed();
+                    e.slot = update.slot;
+                    e.seq = update.seq;
+                    e.source = source;
+                    e.inferred = 0;
+                    core::ptr::write_volatile(p, e);
+                }
+                true
+            }
+            _ => false,
+        };
+        shard.write_unlock(s);
+        if hit {
+            self.sync_hot(address);
+        }
+        hit
+    }
+
+    /// Apply a pending swap of `amount_in` to a known pool's reserves, as
+    /// if it had landed. Slot and sequence stay those of the last update,
+    /// which resets the pool to the feed's reserves. Returns `false`, and
+    /// changes nothing, for unknown pools, for a swap that would revert
+    /// (output below `min_amount_out`), and once `max_swaps` swaps have been
+    /// applied since the last update.
+    pub fn infer_swap(
+        &self,
+        address: &PoolAddress,
+        amount_in: u64,
+        min_amount_out: u64,
+        zero_for_one: bool,
+        max_swaps: u16,
+    ) -> bool {
+        let (shard, start) = self.locate(address);
+        let s = shard.write_lock();
+        let hit = match shard.probe(address, start) {
+            Some((i, true)) => {
+                // SAFETY: exclusive under the write lock.
+                unsafe {
+                    let p = shard.slot_ptr(i);
+                    let mut e = core::ptr::read_volatile(p);
+                    match e.state.after_swap(amount_in, zero_for_one) {
+                        Some((state, out)) if e.inferred < max_swaps && out >= min_amount_out => {
+                            e.state = state;
+                            e.inferred += 1;
+                            core::ptr::write_volatile(p, e);
+                            true
+                        }
+                        _ => false,
+                    }
+                }
+            }
+            _ => false,
+        };
+        shard.write_unlock(s);
+        if hit {
+            self.sync_hot(address);
+        }
+        hit
+    }
+
+    /// After a write to `address` in its shard: refresh its hot copy, or drop
+    /// it if the pool is gone. The fence pairs with the one in `repack`: a
+    /// pool being made hot either is seen here or is copied after this write.
+    fn sync_hot(&self, address: &PoolAddress) {
+        fence(Ordering::SeqCst);
+        if self.hot.holds(address) {
+            let s = seq_lock(&self.hot.seq);
+            self.hot.set(address, self.peek(address));
+            seq_unlock(&self.hot.seq, s);
+        }
+    }
+
+    /// Rank pools by quotes since the last repack and make the
+    /// [`HOT_POOLS`] most quoted (of those quoted at all) the hot tier, in
+    /// place of the pools hot now. Walks every shard: run it off the hot
+    /// path, from a timer. Returns the number of hot pools.
+    pub fn repack(&self) -> usize {
+        let mut hot = [(EMPTY_ADDRESS, 0u32); HOT_SLOTS];
+        for (i, (address, hits)) in hot.iter_mut().enumerate() {
+            // SAFETY: in-bounds; racy, only used for ranking.
+            *address = unsafe { core::ptr::read_volatile(&(*self.hot.slot_ptr(i)).address) };
+            *hits = self.hot.hits[i].swap(0, Ordering::Relaxed);
+        }
+        // (quotes, address), replacing the least quoted once full.
+        let mut top = [(0u32, EMPTY_ADDRESS); HOT_POOLS];
+        for shard in &self.shards {
+            for i in 0..N {
+                let mut hits = shard.hits[i].swap(0, Ordering::Relaxed);
+                // SAFETY: in-bounds; racy, checked against the shard below.
+                let address = unsafe { core::ptr::read_volatile(&(*shard.slot_ptr(i)).address) };
+                if address == EMPTY_ADDRESS {
+                    continue;
+                }
+                if let Some((_, hot_hits)) = hot.iter_mut().find(|(a, _)| *a == address) {
+                    hits = hits.saturating_add(core::mem::take(hot_hits));
+                }
+                let least = top
+                    .iter_mut()
+                    .min_by_key(|(hits, _)| *hits)
+                    .expect("HOT_POOLS > 0");
+                if hits > least.0 {
+                    *least = (hits, address);
+                }
+            }
+        }
+        let s = seq_lock(&self.hot.seq);
+        // A write landing after this fence finds the tier locked and syncs
+        // once it is rebuilt; one before it is in the copies taken below.
+        fence(Ordering::SeqCst);
+        for i in 0..HOT_SLOTS {
+            // SAFETY: in-bounds; exclusive under the write lock.
+            unsafe { core::ptr::write_volatile(self.hot.slot_ptr(i), PoolEntry::EMPTY) };
+            self.hot.hits[i].store(0, Ordering::Relaxed);
+        }
+        let mut len = 0;
+        for (_, address) in top.iter().filter(|(hits, _)| *hits > 0) {
+            if let Some(entry) = self.peek(address) {
+                let (i, _) = self.hot.probe(address);
+                // SAFETY: as above.
+                unsafe { core::ptr::write_volatile(self.hot.slot_ptr(i), entry) };
+                len += 1;
+            }
+        }
+        self.hot.len.store(len, Ordering::Relaxed);
+        seq_unlock(&self.hot.seq, s);
+        len
+    }
+
+    /// Pools in the hot tier.
+    pub fn hot_len(&self) -> usize {
+        self.hot.len.load(Ordering::Relaxed)
+    }
+
+    /// Number of occupied slots (walks every shard; not for the hot path).
+    pub fn len(&self) -> usize {
+        let mut n = 0;
+        for shard in &self.shards {
+            for i in 0..N {
+                // SAFETY: in-bounds; approximate under concurrent writes.
+                let addr = unsafe { core::ptr::read_volatile(&(*shard.slot_ptr(i)).address) };
+                n += (addr != EMPTY_ADDRESS) as usize;
+            }
+        }
+        n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Visit a consistent copy of every pool, shard by shard (not for the
+    /// hot path). A shard written to mid-walk is read again.
+    pub fn for_each(&self, mut f: impl FnMut(&PoolEntry)) {
+        for shard in &self.shards {
+            let mut copy = [PoolEntry::EMPTY; N];
+            loop {
+                let before = shard.seq.load(Ordering::Acquire);
+                if before & 1 == 0 {
+                    for (i, e) in copy.iter_mut().enumerate() {
+                        // SAFETY: in-bounds; validated against the sequence below.
+                        *e = unsafe { core::ptr::read_volatile(shard.slot_ptr(i)) };
+                    }
+                    fence(Ordering::Acquire);
+                    if shard.seq.load(Ordering::Relaxed) == before {
+                        break;
+                    }
+                }
+                self.read_retries.inc();
+                core::hint::spin_loop();
+            }
+            copy.iter()
+                .filter(|e| e.address != EMPTY_ADDRESS)
+                .for_each(&mut f);
+        }
+    }
+}
+
+impl<const S: usize, const N: usize> Default for PoolRegistry<S, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Number of shards in the node's pool table.
+pub const POOL_SHARDS: usize = 16;
+
+/// Pool table sized from [`ActiveDimensions::POOL_CAPACITY`].
+pub type PoolTable = PoolRegistry<POOL_SHARDS, { ActiveDimensions::POOL_CAPACITY / POOL_SHARDS }>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    fn entry(tag: u8, reserve0: u64) -> PoolEntry {
+        let state = AmmPoolState::new(reserve0, reserve0 * 2, 3, 1_000);
+        PoolEntry::new([tag; 20], 0, 1, state)
+    }
+
+    #[test]
+    fn upsert_get_and_update() {
+        let reg: PoolRegistry<4, 8> = PoolRegistry::new();
+        reg.upsert(entry(1, 100)).unwrap();
+        reg.upsert(entry(2, 200)).unwrap();
+        assert_eq!(reg.get(&[1; 20]).unwrap().state.reserve0, 100);
+        assert!(reg.get(&[3; 20]).is_none());
+
+        assert!(reg.update_reserves(&[2; 20], 7, 14, 99, 5));
+        let e = reg.get(&[2; 20]).unwrap();
+        assert_eq!(
+            (e.state.reserve0, e.state.reserve1, e.slot, e.seq),
+            (7, 14, 99, 5)
+        );
+        assert!(!reg.update_reserves(&[9; 20], 1, 1, 1, 1));
+        assert_eq!(reg.len(), 2);
+
+        let mut seqs = Vec::new();
+        reg.for_each(|e| seqs.push((e.address[0], e.seq)));
+        seqs.sort();
+        assert_eq!(seqs, [(1, 0), (2, 5)]);
+    }
+
+    #[test]
+    fn full_shard_evicts_least_recently_quoted() {
+        let reg: PoolRegistry<1, 4> = PoolRegistry::new();
+        for tag in 1..=4 {
+            assert_eq!(reg.upsert(entry(tag, 1)), Ok(None));
+        }
+        reg.get(&[1; 20]).unwrap();
+        reg.get(&[3; 20]).unwrap();
+        assert_eq!(
+            reg.upsert(entry(1, 5)),
+            Ok(None),
+            "replacing an existing pool never evicts"
+        );
+
+        let evicted = reg.upsert(entry(5, 1)).unwrap().expect("shard was full");
+        assert!(
+            evicted == [2; 20] || evicted == [4; 20],
+            "evicted a quoted pool"
+        );
+        for tag in [1, 3, 5] {
+            assert!(reg.peek(&[tag; 20]).is_some());
+        }
+        assert_eq!((reg.len(), reg.evictions.load()), (4, 1));
+        assert_eq!(reg.upsert(PoolEntry::EMPTY), Err(ZeroAddress));
+    }
+
+    #[test]
+    fn eviction_keeps_probe_chains_intact() {
+        let reg: PoolRegistry<1, 8> = PoolRegistry::new();
+        let mut resident = Vec::new();
+        for tag in 1..=200u8 {
+            if let Some(gone) = reg.upsert(entry(tag, tag as u64)).unwrap() {
+                resident.retain(|a| *a != gone);
+            }
+            resident.push([tag; 20]);
+            for a in &resident {
+                assert_eq!(reg.peek(a).map(|e| e.address), Some(*a));
+            }
+        }
+        assert_eq!((reg.len(), reg.evictions.load()), (8, 192));
+    }
+
+    #[test]
+    fn repack_makes_the_most_quoted_pools_hot() {
+        let reg: PoolRegistry<2, 128> = PoolRegistry::new();
+        for tag in 1..=100 {
+            reg.upsert(entry(tag, tag as u64)).unwrap();
+        }
+        assert_eq!(reg.repack(), 0, "nothing quoted yet");
+        for tag in 1..=30 {
+            reg.get(&[tag; 20]).unwrap();
+        }
+        assert_eq!(reg.repack(), 30);
+        for tag in 1..=100 {
+            assert_eq!(reg.get(&[tag; 20]), reg.peek(&[tag; 20]));
+            assert_eq!(reg.hot.holds(&[tag; 20]), tag <= 30);
+        }
+
+        // Writes reach the hot copies; quotes through the tier are counted.
+        assert!(reg.update_reserves(&[7; 20], 70, 140, 9, 9));
+        assert!(reg.infer_swap(&[7; 20], 10, 0, true, 1));
+        assert_eq!(reg.get(&[7; 20]), reg.peek(&[7; 20]));
+        assert_eq!(reg.get(&[7; 20]).unwrap().state.reserve0, 80);
+        for tag in 31..=31 + HOT_POOLS as u8 {
+            reg.get(&[tag; 20]).unwrap();
+        }
+        assert_eq!(reg.repack(), HOT_POOLS);
+        assert!(
+            reg.hot.holds(&[7; 20]),
+            "quoted three times since the last repack"
+        );
+        assert!(!reg.hot.holds(&[1; 20]));
+
+        // An evicted pool leaves the tier, though hot pools go last.
+        let small: PoolRegistry<1, 4> = PoolRegistry::new();
+        for tag in 1..=4 {
+            small.upsert(entry(tag, 1)).unwrap();
+            small.get(&[tag; 20]).unwrap();
+        }
+        assert_eq!(small.repack(), 4);
+        let gone = small.upsert(entry(5, 1)).unwrap().expect("shard was full");
+        assert_eq!((small.hot_len(), small.get(&gone)), (3, None));
+        assert!(small.get(&[5; 20]).is_some());
+    }
+
+    #[test]
+    fn readers_never_observe_torn_entries() {
+        let reg: Arc<PoolRegistry<2, 4>> = Arc::new(PoolRegistry::new());
+        reg.upsert(PoolEntry {
+            slot: 1,
+            ..entry(7, 1)
+        })
+        .unwrap();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let (reg, stop) = (reg.clone(), stop.clone());
+                std::thread::spawn(move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        let e = reg.get(&[7; 20]).expect("pool present");
+                        assert_eq!(e.state.reserve1, e.state.reserve0 * 2, "torn read");
+                        assert_eq!(e.slot, e.state.reserve0);
+                    }
+                })
+            })
+            .collect();
+
+        for r0 in 1..50_000u64 {
+            reg.update_reserves(&[7; 20], r0, r0 * 2, r0, r0 as u32);
+            // Readers switch between the hot tier and the shard.
+            if r0 % 1_000 == 0 {
+                reg.repack();
+            }
+        }
+        stop.store(true, Ordering::Relaxed);
+        for r in readers {
+            r.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,171 @@
+//! Pool-table seeding from a file at startup.
+//!
+//! Until the pool feed delivers a pool, the node cannot price swaps on it.
+//! Where the feed lags boot, `MEV_POOL_SEED_FILE` names a CSV file of pool
+//! states to start from, one pool per line:
+//!
+//! ```text
+//! address,reserve0,reserve1,fee_ppm,kind[,token0,token1]
+//! 0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48,10000000000,5000000000,3000,constant_product,USDC,WSOL
+//! ```
+//!
+//! `kind` is the AMM curve; only `constant_product` (or `cp`) is priced.
+//! Tokens are symbols from [`crate::tokens`], `MOCK_POOL_TOKENS` if left
+//! out. Blank lines, `#` comments and a header line starting with `address`
+//! are skipped. Seeded entries carry slot 0, so the first feed update for a
+//! pool replaces them.
+use crate::config::parse_address;
+use crate::pool_registry::PoolEntry;
+use crate::processor::{AmmPoolState, MOCK_POOL_TOKENS};
+use crate::tokens::token_by_symbol;
+use crate::validator::FEE_PPM_DENOMINATOR;
+
+/// Why a seed file was rejected; `line` is 1-based.
+#[derive(Debug)]
+pub enum PoolSeedError {
+    Read(std::io::Error),
+    /// Not 5 or 7 comma-separated fields.
+    Syntax {
+        line: usize,
+    },
+    InvalidAddress {
+        line: usize,
+    },
+    /// A reserve is not a number, or zero.
+    InvalidReserves {
+        line: usize,
+    },
+    /// The fee is not a number of ppm below 1,000,000.
+    InvalidFee {
+        line: usize,
+    },
+    UnknownKind {
+        line: usize,
+    },
+    UnknownToken {
+        line: usize,
+    },
+}
+
+impl core::fmt::Display for PoolSeedError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Read(e) => write!(f, "cannot read seed file: {e}"),
+            Self::Syntax { line } => {
+                write!(
+                    f,
+                    "line {line}: expected address,reserve0,reserve1,fee_ppm,kind[,token0,token1]"
+                )
+            }
+            Self::InvalidAddress { line } => {
+                write!(f, "line {line}: pool is not a 20-byte hex address")
+            }
+            Self::InvalidReserves { line } => {
+                write!(f, "line {line}: reserves must be non-zero integers")
+            }
+            Self::InvalidFee { line } => write!(
+                f,
+                "line {line}: fee must be below {FEE_PPM_DENOMINATOR} ppm"
+            ),
+            Self::UnknownKind { line } => {
+                write!(f, "line {line}: pool kind is not constant_product")
+            }
+            Self::UnknownToken { line } => write!(f, "line {line}: unknown token symbol"),
+        }
+    }
+}
+
+impl std::error::Error for PoolSeedError {}
+
+/// Read and parse the seed file at `path`.
+pub fn load(path: &str) -> Result<Vec<PoolEntry>, PoolSeedError> {
+    parse(&std::fs::read_to_string(path).map_err(PoolSeedError::Read)?)
+}
+
+/// Parse seed file contents, in file order.
+pub fn parse(text: &str) -> Result<Vec<PoolEntry>, PoolSeedError> {
+    let mut entries = Vec::new();
+    for (index, row) in text.lines().enumerate() {
+        let (line, row) = (index + 1, row.trim());
+        if row.is_empty() || row.starts_with('#') || row.starts_with("address") {
+            continue;
+        }
+        let fields: Vec<&str> = row.split(',').map(str::trim).collect();
+        let (address, reserve0, reserve1, fee_ppm, kind, tokens) = match fields[..] {
+            [a, r0, r1, fee, kind] => (a, r0, r1, fee, kind, None),
+            [a, r0, r1, fee, kind, t0, t1] => (a, r0, r1, fee, kind, Some((t0, t1))),
+            _ => return Err(PoolSeedError::Syntax { line }),
+        };
+        let address = parse_address(address).ok_or(PoolSeedError::InvalidAddress { line })?;
+        let reserve = |r: &str| {
+            r.parse::<u64>()
+                .ok()
+                .filter(|&r| r != 0)
+                .ok_or(PoolSeedError::InvalidReserves { line })
+        };
+        let (reserve0, reserve1) = (reserve(reserve0)?, reserve(reserve1)?);
+        let fee_ppm = fee_ppm
+            .parse::<u64>()
+            .ok()
+            .filter(|&fee| fee < FEE_PPM_DENOMINATOR)
+            .ok_or(PoolSeedError::InvalidFee { line })?;
+        if !matches!(kind, "constant_product" | "cp") {
+            return Err(PoolSeedError::UnknownKind { line });
+        }
+        let (token0, token1) = match tokens {
+            Some((t0, t1)) => (
+                token_by_symbol(t0).ok_or(PoolSeedError::UnknownToken { line })?,
+                token_by_symbol(t1).ok_or(PoolSeedError::UnknownToken { line })?,
+            ),
+            None => MOCK_POOL_TOKENS,
+        };
+        let state = AmmPoolState::new(reserve0, reserve1, fee_ppm, FEE_PPM_DENOMINATOR);
+        entries.push(PoolEntry::new(address, token0, token1, state));
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokens;
+
+    #[test]
+    fn parses_pools_and_names_the_bad_line() {
+        let text = "address,reserve0,reserve1,fee_ppm,kind,token0,token1\n\
+                    # mainnet USDC/WETH\n\
+                    0x88e6a0c2ddd26feeb64f039a2c41296fcb3f5640, 1000, 2000, 500, constant_product, USDC, WETH\n\
+                    \n\
+                    cbcdf9626bc03e24f779434178a73a0b4bad62ed,10,20,3000,cp\n";
+        let entries = parse(text).unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            (entries[0].token0, entries[0].token1),
+            (tokens::USDC, tokens::WETH)
+        );
+        assert_eq!(
+            (
+                entries[0].state.reserve1,
+                entries[0].state.fee_num,
+                entries[0].slot
+            ),
+            (2_000, 500, 0)
+        );
+        assert_eq!(
+            (
+                entries[1].address[0],
+                (entries[1].token0, entries[1].token1)
+            ),
+            (0xcb, MOCK_POOL_TOKENS)
+        );
+
+        let bad = |row: &str| parse(&format!("# pools\n{row}\n")).unwrap_err().to_string();
+        let pool = "cbcdf9626bc03e24f779434178a73a0b4bad62ed";
+        assert!(bad(&format!("{pool},10,20,3000")).starts_with("line 2: expected"));
+        assert!(bad(&format!("{pool},0,20,3000,cp")).contains("non-zero"));
+        assert!(bad(&format!("{pool},10,20,1000000,cp")).contains("fee"));
+        assert!(bad(&format!("{pool},10,20,3000,stable")).contains("kind"));
+        assert!(bad(&format!("{pool},10,20,3000,cp,USDC,DOGE")).contains("token"));
+        assert!(bad("0xcbcd,10,20,3000,cp").contains("address"));
+    }
+}
@@ -0,0 +1,174 @@
+//! Port layout: the local UDP port each message class arrives on.
+//!
+//! Victims ("swaps"), the pool-state and order-book feed ("pools") and
+//! control messages (price updates and victim outcome reports) each come in
+//! on a port of their own, bound to a socket of its own and drained by a
+//! pipeline stage of its own, so a burst of one class never queues behind
+//! another. By default control messages share the swaps port; giving them a
+//! port splits them off. The swaps port keeps accepting them either way, so
+//! senders can move over one at a time.
+
+/// Default ports: 8080 swaps, 8081 pools, control on the swaps port.
+pub const DEFAULT_SWAP_PORT: u16 = 8080;
+pub const DEFAULT_POOL_PORT: u16 = 8081;
+
+/// A class of inbound message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortClass {
+    /// `DexSwapTx` victims in all their layouts.
+    Swaps,
+    /// `PoolStateUpdate` and `BookUpdate`.
+    Pools,
+    /// `PriceUpdate`, `VictimOutcome` and `ReplyAck`.
+    Control,
+}
+
+impl PortClass {
+    pub const ALL: [PortClass; 3] = [PortClass::Swaps, PortClass::Pools, PortClass::Control];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            PortClass::Swaps => "swaps",
+            PortClass::Pools => "pools",
+            PortClass::Control => "control",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PortLayoutError {
+    /// Not `<class>=<port>`, or an unknown class.
+    UnknownClass,
+    /// Not a port number, or 0.
+    InvalidPort,
+    /// Pools share a port with another class.
+    PoolsShared,
+}
+
+impl core::fmt::Display for PortLayoutError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnknownClass => write!(f, "expected <swaps|pools|control>=<port>"),
+            Self::InvalidPort => write!(f, "ports are 1-65535"),
+            Self::PoolsShared => write!(f, "the pools port cannot be shared"),
+        }
+    }
+}
+
+impl core::error::Error for PortLayoutError {}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PortLayout {
+    pub swaps: u16,
+    pub pools: u16,
+    /// Equal to `swaps` when control messages are not split off.
+    pub control: u16,
+}
+
+impl Default for PortLayout {
+    fn default() -> Self {
+        Self {
+            swaps: DEFAULT_SWAP_PORT,
+            pools: DEFAULT_POOL_PORT,
+            control: DEFAULT_SWAP_PORT,
+        }
+    }
+}
+
+impl PortLayout {
+    /// Parse `swaps=8080,pools=8081,control=8082`. Classes left out keep
+    /// their default; control follows the swaps port unless given.
+    pub fn parse(spec: &str) -> Result<Self, PortLayoutError> {
+        let mut ports = [None; 3];
+        for item in spec.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            let (class, port) = item.split_once('=').ok_or(PortLayoutError::UnknownClass)?;
+            let class = PortClass::ALL
+                .iter()
+                .position(|c| c.name().eq_ignore_ascii_case(class.trim()))
+                .ok_or(PortLayoutError::UnknownClass)?;
+            match port.trim().parse::<u16>() {
+                Ok(port) if port != 0 => ports[class] = Some(port),
+                _ => return Err(PortLayoutError::InvalidPort),
+            }
+        }
+        let defaults = Self::default();
+        let swaps = ports[0].unwrap_or(defaults.swaps);
+        let layout = Self {
+            swaps,
+            pools: ports[1].unwrap_or(defaults.pools),
+            control: ports[2].unwrap_or(swaps),
+        };
+        if layout.pools == layout.swaps || layout.pools == layout.control {
+            return Err(PortLayoutError::PoolsShared);
+        }
+        Ok(layout)
+    }
+
+    /// Control messages have a port and a socket of their own.
+    pub fn control_split(&self) -> bool {
+        self.control != self.swaps
+    }
+
+    /// Port of `class`.
+    pub const fn port(&self, class: PortClass) -> u16 {
+        match class {
+            PortClass::Swaps => self.swaps,
+            PortClass::Pools => self.pools,
+            PortClass::Control => self.control,
+        }
+    }
+}
+
+impl core::fmt::Display for PortLayout {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "swaps={},pools={},control={}",
+            self.swaps, self.pools, self.control
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts_keep_pools_on_a_port_of_their_own() {
+        let layout = PortLayout::parse("swaps=8080, pools=8081, control=8082").unwrap();
+        assert_eq!(
+            layout,
+            PortLayout {
+                swaps: 8080,
+                pools: 8081,
+                control: 8082
+            }
+        );
+        assert!(layout.control_split());
+        assert_eq!(layout.to_string(), "swaps=8080,pools=8081,control=8082");
+
+        // Control follows the swaps port unless given one.
+        let layout = PortLayout::parse("swaps=9000").unwrap();
+        assert_eq!(
+            (layout.pools, layout.control, layout.control_split()),
+            (8081, 9000, false)
+        );
+
+        assert_eq!(
+            PortLayout::parse("control=8081"),
+            Err(PortLayoutError::PoolsShared)
+        );
+        assert_eq!(
+            PortLayout::parse("pools=8080"),
+            Err(PortLayoutError::PoolsShared)
+        );
+        assert_eq!(
+            PortLayout::parse("swaps=0"),
+            Err(PortLayoutError::InvalidPort)
+        );
+        assert_eq!(
+            PortLayout::parse("prices=8083"),
+            Err(PortLayoutError::UnknownClass)
+        );
+    }
+}
@@ -0,0 +1,191 @@
+//! Reference price feed: `PriceUpdate` wire message and lock-free price cache.
+//!
+//! Profits computed in a pool's input token are converted into the numéraire
+//! using an external reference rate instead of the pool's own spot price,
+//! which the very swap being sandwiched can move. The cache stores one
+//! USDC-e6 price per token price slot (see `tokens::TokenMeta::price_slot`);
+//! the hot path only performs relaxed atomic loads.
+use crate::runtime::CacheAlignedAtomicU64;
+use crate::tokens::{self, TokenId, PRICE_SLOTS};
+use bytemuck::{Pod, Zeroable};
+
+/// Magic prefix identifying a `PriceUpdate` datagram ("MVPX").
+pub const PRICE_UPDATE_MAGIC: [u8; 4] = *b"MVPX";
+
+/// Reference price broadcast by a price relayer.
+///
+/// Layout (32 bytes, little-endian):
+///   [0..4]   magic        "MVPX"
+///   [4..6]   token_id     u16
+///   [6..8]   _reserved
+///   [8..16]  price_usdc_e6 u64 — value of one whole token in USDC-e6
+///   [16..24] slot         u64 — block/slot the price was observed at
+///   [24..32] seq          u64 — per-feed sequence number
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct PriceUpdate {
+    pub magic: [u8; 4],
+    pub token_id_le: [u8; 2],
+    pub _reserved: [u8; 2],
+    pub price_le: [u8; 8],
+    pub slot_le: [u8; 8],
+    pub seq_le: [u8; 8],
+}
+
+const _: () = assert!(core::mem::size_of::<PriceUpdate>() == 32);
+
+impl PriceUpdate {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<PriceUpdate>();
+
+    #[inline(always)]
+    pub fn token_id(&self) -> TokenId {
+        u16::from_le_bytes(self.token_id_le)
+    }
+
+    #[inline(always)]
+    pub fn price_usdc_e6(&self) -> u64 {
+        u64::from_le_bytes(self.price_le)
+    }
+
+    #[inline(always)]
+    pub fn slot(&self) -> u64 {
+        u64::from_le_bytes(self.slot_le)
+    }
+
+    #[inline(always)]
+    pub fn seq(&self) -> u64 {
+        u64::from_le_bytes(self.seq_le)
+    }
+
+    pub fn new(token_id: TokenId, price_usdc_e6: u64, slot: u64, seq: u64) -> Self {
+        Self {
+            magic: PRICE_UPDATE_MAGIC,
+            token_id_le: token_id.to_le_bytes(),
+            _reserved: [0; 2],
+            price_le: price_usdc_e6.to_le_bytes(),
+            slot_le: slot.to_le_bytes(),
+            seq_le: seq.to_le_bytes(),
+        }
+    }
+
+    /// Zero-copy parse; returns `None` unless the payload is exactly one
+    /// `PriceUpdate` carrying the expected magic.
+    #[inline(always)]
+    pub fn parse(data: &[u8]) -> Option<&PriceUpdate> {
+        if data.len() != Self::WIRE_SIZE {
+            return None;
+        }
+        let update = bytemuck::try_from_bytes::<PriceUpdate>(data).ok()?;
+        (update.magic == PRICE_UPDATE_MAGIC).then_some(update)
+    }
+}
+
+/// Why a `PriceUpdate` was not applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceRejection {
+    UnknownToken,
+    ZeroPrice,
+    /// Sequence number not newer than the cached one (replayed or reordered).
+    Stale {
+        cached_seq: u64,
+        got: u64,
+    },
+}
+
+/// Latest reference price per price slot, in USDC-e6 per whole token.
+///
+/// Single writer (the ingest loop), any number of readers. A price of `0`
+/// means "no live price yet"; callers fall back to static prices.
+pub struct PriceCache {
+    prices: [CacheAlignedAtomicU64; PRICE_SLOTS],
+    seqs: [CacheAlignedAtomicU64; PRICE_SLOTS],
+}
+
+impl PriceCache {
+    pub const fn new() -> Self {
+        Self {
+            prices: [const { CacheAlignedAtomicU64::new(0) }; PRICE_SLOTS],
+            seqs: [const { CacheAlignedAtomicU64::new(0) }; PRICE_SLOTS],
+        }
+    }
+
+    /// Apply a reference price update.
+    pub fn apply(&self, update: &PriceUpdate) -> Result<(), PriceRejection> {
+        let meta = tokens::token(update.token_id()).ok_or(PriceRejection::UnknownToken)?;
+        let price = update.price_usdc_e6();
+        if price == 0 {
+            return Err(PriceRejection::ZeroPrice);
+        }
+        let slot = meta.price_slot as usize;
+        let cached_seq = self.seqs[slot].load();
+        if cached_seq != 0 && update.seq() <= cached_seq {
+            return Err(PriceRejection::Stale {
+                cached_seq,
+                got: update.seq(),
+            });
+        }
+        self.prices[slot].store(price);
+        self.seqs[slot].store(update.seq());
+        Ok(())
+    }
+
+    /// Live USDC-e6 price for a price slot, or `0` if none received yet.
+    #[inline(always)]
+    pub fn price_usdc_e6(&self, slot: u8) -> u64 {
+        self.prices.get(slot as usize).map_or(0, |p| p.load())
+    }
+}
+
+impl Default for PriceCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokens::{USDC, WSOL};
+
+    #[test]
+    fn parses_only_magic_tagged_updates() {
+        let update = PriceUpdate::new(WSOL, 150_000_000, 10, 1);
+        let raw = bytemuck::bytes_of(&update);
+        let parsed = PriceUpdate::parse(raw).expect("valid price update");
+        assert_eq!(parsed.token_id(), WSOL);
+        assert_eq!(parsed.price_usdc_e6(), 150_000_000);
+
+        let mut bad = [0u8; PriceUpdate::WIRE_SIZE];
+        bad.copy_from_slice(raw);
+        bad[0] = b'X';
+        assert!(PriceUpdate::parse(&bad).is_none());
+        assert!(PriceUpdate::parse(&raw[..16]).is_none());
+    }
+
+    #[test]
+    fn cache_rejects_stale_and_invalid_updates() {
+        let cache = PriceCache::new();
+        assert_eq!(
+            cache.apply(&PriceUpdate::new(WSOL, 150_000_000, 1, 5)),
+            Ok(())
+        );
+        assert_eq!(cache.price_usdc_e6(1), 150_000_000);
+
+        assert_eq!(
+            cache.apply(&PriceUpdate::new(WSOL, 1, 1, 5)),
+            Err(PriceRejection::Stale {
+                cached_seq: 5,
+                got: 5
+            })
+        );
+        assert_eq!(
+            cache.apply(&PriceUpdate::new(USDC, 0, 1, 1)),
+            Err(PriceRejection::ZeroPrice)
+        );
+        assert_eq!(
+            cache.apply(&PriceUpdate::new(99, 1, 1, 1)),
+            Err(PriceRejection::UnknownToken)
+        );
+        assert_eq!(cache.price_usdc_e6(1), 150_000_000);
+    }
+}
@@ -0,0 +1,289 @@
+//! Private-routing heuristics.
+//!
+//! A victim sent through a private relay never enters the public mempool
+//! order, so a sandwich against it cannot land: it is only worth skipping
+//! early. EVM victims (`DexSwapTxEvm`) are flagged when
+//!
+//! - their sender is a known builder-only sender, an account that only
+//!   ever submits straight to builders;
+//! - their sender is a known router contract that forwards orders over a
+//!   private channel (intent settlement, smart-account bundlers);
+//! - they pay no priority fee, with the zero-tip rule enabled: a public
+//!   transaction without a tip waits, one that is privately routed need not.
+//!
+//! The signature lists start from configuration (`MEV_PRIVATE_BUILDERS`,
+//! `MEV_PRIVATE_ROUTERS`, `MEV_PRIVATE_ZERO_TIP`) and are edited at runtime
+//! from the admin socket. Flagged victims are not evaluated; their nonces
+//! are still tracked, so a replacement can cancel an opportunity emitted
+//! before its sender was listed. Other victim records are never flagged.
+use crate::nonce::Address;
+use crate::payload::DexSwapTxEvm;
+use heapless::FnvIndexSet;
+
+/// Addresses each signature list holds.
+pub const MAX_SIGNATURES: usize = 64;
+
+/// Why a victim is taken to be privately routed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrivateRoute {
+    BuilderSender = 0,
+    Router = 1,
+    ZeroTip = 2,
+}
+
+impl PrivateRoute {
+    pub const COUNT: usize = 3;
+}
+
+/// An editable signature list.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SignatureList {
+    Builders,
+    Routers,
+}
+
+impl SignatureList {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "builder" => Some(Self::Builders),
+            "router" => Some(Self::Routers),
+            _ => None,
+        }
+    }
+}
+
+/// Admin commands on the filter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrivacyCommand {
+    Show,
+    Add(SignatureList, Address),
+    Remove(SignatureList, Address),
+    ZeroTip(bool),
+}
+
+impl PrivacyCommand {
+    /// Parse what follows `privacy` on an admin line: `add|remove
+    /// builder|router <address>` or `zero-tip on|off`.
+    #[cfg(feature = "std")]
+    pub fn parse(args: &str) -> Option<Self> {
+        let entry = |args: &str| {
+            let (list, address) = args.split_once(' ')?;
+            Some((
+                SignatureList::parse(list)?,
+                crate::config::parse_address(address)?,
+            ))
+        };
+        match args.split_once(' ')? {
+            ("add", args) => entry(args).map(|(list, address)| Self::Add(list, address)),
+            ("remove", args) => entry(args).map(|(list, address)| Self::Remove(list, address)),
+            ("zero-tip", "on") => Some(Self::ZeroTip(true)),
+            ("zero-tip", "off") => Some(Self::ZeroTip(false)),
+            _ => None,
+        }
+    }
+
+    /// `Show` only reads.
+    pub const fn is_readonly(self) -> bool {
+        matches!(self, Self::Show)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrivacyConfig {
+    pub builders: heapless::Vec<Address, MAX_SIGNATURES>,
+    pub routers: heapless::Vec<Address, MAX_SIGNATURES>,
+    pub zero_tip: bool,
+}
+
+/// A signature list is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ListFull;
+
+impl core::fmt::Display for ListFull {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "signature list full ({MAX_SIGNATURES} addresses)")
+    }
+}
+
+pub struct PrivacyFilter {
+    builders: FnvIndexSet<Address, MAX_SIGNATURES>,
+    routers: FnvIndexSet<Address, MAX_SIGNATURES>,
+    zero_tip: bool,
+    /// Victims flagged so far, indexed by `PrivateRoute as usize`.
+    pub flagged: [u64; PrivateRoute::COUNT],
+}
+
+impl PrivacyFilter {
+    pub fn new(config: &PrivacyConfig) -> Self {
+        let mut filter = Self {
+            builders: FnvIndexSet::new(),
+            routers: FnvIndexSet::new(),
+            zero_tip: config.zero_tip,
+            flagged: [0; PrivateRoute::COUNT],
+        };
+        // The config lists hold at most `MAX_SIGNATURES` each.
+        for address in &config.builders {
+            let _ = filter.builders.insert(*address);
+        }
+        for address in &config.routers {
+            let _ = filter.routers.insert(*address);
+        }
+        filter
+    }
+
+    fn list(&mut self, list: SignatureList) -> &mut FnvIndexSet<Address, MAX_SIGNATURES> {
+        match list {
+            SignatureList::Builders => &mut self.builders,
+            SignatureList::Routers => &mut self.routers,
+        }
+    }
+
+    /// Add `address` to `list`; `false` if it was already there.
+    pub fn add(&mut self, list: SignatureList, address: Address) -> Result<bool, ListFull> {
+        self.list(list).insert(address).map_err(|_| ListFull)
+    }
+
+    /// Remove `address` from `list`; `false` if it was not there.
+    pub fn remove(&mut self, list: SignatureList, address: &Address) -> bool {
+        self.list(list).remove(address)
+    }
+
+    pub fn set_zero_tip(&mut self, enabled: bool) {
+        self.zero_tip = enabled;
+    }
+
+    /// Apply an admin command; `Ok(false)` for an add or remove that
+    /// changed nothing.
+    pub fn command(&mut self, cmd: PrivacyCommand) -> Result<bool, ListFull> {
+        match cmd {
+            PrivacyCommand::Show => Ok(true),
+            PrivacyCommand::Add(list, address) => self.add(list, address),
+            PrivacyCommand::Remove(list, address) => Ok(self.remove(list, &address)),
+            PrivacyCommand::ZeroTip(enabled) => {
+                self.set_zero_tip(enabled);
+                Ok(true)
+            }
+        }
+    }
+
+    /// How the victim in `tx` is privately routed, if it looks so.
+    #[inline(always)]
+    pub fn check(&self, tx: &DexSwapTxEvm) -> Option<PrivateRoute> {
+        if self.builders.contains(&tx.sender) {
+            Some(PrivateRoute::BuilderSender)
+        } else if self.routers.contains(&tx.sender) {
+            Some(PrivateRoute::Router)
+        } else if self.zero_tip && tx.ext.priority_fee() == 0 {
+            Some(PrivateRoute::ZeroTip)
+        } else {
+            None
+        }
+    }
+
+    /// [`Self::check`], counting the victims flagged.
+    #[inline(always)]
+    pub fn flag(&mut self, tx: &DexSwapTxEvm) -> Option<PrivateRoute> {
+        let route = self.check(tx)?;
+        self.flagged[route as usize] += 1;
+        Some(route)
+    }
+}
+
+/// As JSON: both lists as hex addresses, the zero-tip rule and the counts.
+impl core::fmt::Display for PrivacyFilter {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for (name, list) in [("builders", &self.builders), ("routers", &self.routers)] {
+            let sep = if name == "builders" { "{" } else { "," };
+            write!(f, "{sep}\"{name}\":[")?;
+            for (i, address) in list.iter().enumerate() {
+                f.write_str(if i == 0 { "\"" } else { ",\"" })?;
+                address
+                    .iter()
+                    .try_for_each(|byte| write!(f, "{byte:02x}"))?;
+                f.write_str("\"")?;
+            }
+            f.write_str("]")?;
+        }
+        let [builder, router, zero_tip] = self.flagged;
+        write!(
+            f,
+            ",\"zero_tip\":{},\"flagged\":{{\"builder\":{builder},\"router\":{router},\"zero_tip\":{zero_tip}}}}}",
+            self.zero_tip
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payload::{DexSwapTx, DexSwapTxExt};
+
+    fn victim(sender: u8, priority_fee: u64) -> DexSwapTxEvm {
+        let base = DexSwapTx::from_parts(1, [0xAA; 20], 5_000_000, 1, 0);
+        DexSwapTxEvm::from_parts(
+            DexSwapTxExt::from_parts(base, 0).with_priority_fee(priority_fee),
+            [sender; 20],
+            30,
+        )
+    }
+
+    #[test]
+    fn flags_listed_senders_and_zero_tips() {
+        let builders = heapless::Vec::from_slice(&[[0xB1; 20]]).unwrap();
+        let mut filter = PrivacyFilter::new(&PrivacyConfig {
+            builders,
+            ..PrivacyConfig::default()
+        });
+        assert_eq!(
+            filter.flag(&victim(0xB1, 5)),
+            Some(PrivateRoute::BuilderSender)
+        );
+        assert_eq!(filter.flag(&victim(0xC1, 0)), None, "zero-tip rule is off");
+
+        let router = "0xc1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1";
+        let add = PrivacyCommand::parse(&format!("add router {router}")).unwrap();
+        assert_eq!(add, PrivacyCommand::Add(SignatureList::Routers, [0xC1; 20]));
+        assert_eq!(
+            (filter.command(add), filter.command(add)),
+            (Ok(true), Ok(false))
+        );
+        assert_eq!(
+            filter.command(PrivacyCommand::parse("zero-tip on").unwrap()),
+            Ok(true)
+        );
+        for bad in [
+            "add pool 0xc1",
+            "remove builder",
+            "zero-tip maybe",
+            "add router 0x12",
+        ] {
+            assert_eq!(PrivacyCommand::parse(bad), None, "{bad}");
+        }
+        assert_eq!(filter.flag(&victim(0xC1, 5)), Some(PrivateRoute::Router));
+        assert_eq!(filter.flag(&victim(0xD1, 0)), Some(PrivateRoute::ZeroTip));
+        assert_eq!(filter.flag(&victim(0xD1, 1)), None);
+
+        assert!(filter.remove(SignatureList::Builders, &[0xB1; 20]));
+        assert!(!filter.remove(SignatureList::Builders, &[0xB1; 20]));
+        assert_eq!(filter.flag(&victim(0xB1, 5)), None);
+        assert_eq!(filter.flagged, [1, 1, 1]);
+        for i in 0..MAX_SIGNATURES as u8 {
+            let _ = filter.add(SignatureList::Builders, [i; 20]);
+        }
+        assert_eq!(
+            filter.add(SignatureList::Builders, [0xFF; 20]),
+            Err(ListFull)
+        );
+        let json = filter.to_string();
+        assert!(
+            json.contains(
+                "\"routers\":[\"c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1c1\"],\"zero_tip\":true"
+            ),
+            "{json}"
+        );
+        assert!(
+            json.ends_with("\"flagged\":{\"builder\":1,\"router\":1,\"zero_tip\":1}}"),
+            "{json}"
+        );
+    }
+}
@@ -1,4 +1,20 @@
-use crate::payload::DexSwapTx;
+use crate::anomaly::SuppressedPools;
+use crate::clock::Clock;
+use crate::confidence::{
+    ConfidenceInputs, ConfidenceModel, ConfidenceSignals, FULL_CONFIDENCE_BPS,
+};
+use crate::discovery::UnknownPools;
+use crate::orderbook::{self, OrderBookState, TakerArb};
+use crate::payload::{DexSwapTx, DexSwapTxExt};
+use crate::pool_registry::{PoolAddress, PoolEntry, PoolTable};
+use crate::prices::PriceCache;
+use crate::recip::Reciprocal;
+use crate::runtime::prefetch_read;
+#[cfg(feature = "std")]
+use crate::runtime::unix_time_ms;
+use crate::threshold::AdaptiveThreshold;
+use crate::tokens::{self, ProfitNormalizer, TokenId};
+use core::sync::atomic::{AtomicU8, Ordering};
 
 /// Simulated AMM pool state (pre-allocated, never heap-allocated).
 /// Models a Uniswap v2 / Raydium-style constant-product pool: x * y = k.
@@ -13,9 +29,86 @@ pub struct AmmPoolState {
     pub fee_num: u64,
     /// Fee denominator (e.g. 1000).
     pub fee_den: u64,
+    /// With `recip_math`, reciprocals of the front-run leg's denominators,
+    /// indexed by `!zero_for_one`; see [`crate::recip`]. Checked against the
+    /// divisor before use, so a stale one is only a cache miss.
+    front_run: [Reciprocal; 2],
 }
 
 impl AmmPoolState {
+    pub const fn new(reserve0: u64, reserve1: u64, fee_num: u64, fee_den: u64) -> Self {
+        let state = Self {
+            reserve0,
+            reserve1,
+            fee_num,
+            fee_den,
+            front_run: [Reciprocal::NONE; 2],
+        };
+        state.refreshed()
+    }
+
+    /// This state with its cached reciprocals recomputed for its reserves
+    /// and fee; call after changing either.
+    #[inline(always)]
+    pub const fn refreshed(mut self) -> Self {
+        if cfg!(feature = "recip_math") {
+            self.front_run = [
+                self.front_run_reciprocal(true),
+                self.front_run_reciprocal(false),
+            ];
+        }
+        self
+    }
+
+    const fn front_run_reciprocal(&self, zero_for_one: bool) -> Reciprocal {
+        let reserve_in = if zero_for_one {
+            self.reserve0
+        } else {
+            self.reserve1
+        };
+        let Some(fee_adj) = self.fee_den.checked_sub(self.fee_num) else {
+            return Reciprocal::NONE;
+        };
+        let denominator = reserve_in as u128 * self.fee_den as u128
+            + OUR_FRONT_RUN_AMOUNT as u128 * fee_adj as u128;
+        if denominator > u64::MAX as u128 {
+            return Reciprocal::NONE;
+        }
+        Reciprocal::new(denominator as u64)
+    }
+
+    /// `numerator / denominator` of the output formula, through a cached
+    /// reciprocal when the divisor is the front-run one.
+    #[cfg(feature = "recip_math")]
+    #[inline(always)]
+    fn output_quotient(
+        &self,
+        numerator: u128,
+        denominator: u128,
+        zero_for_one: bool,
+    ) -> Option<u64> {
+        let Ok(divisor) = u64::try_from(denominator) else {
+            return Some((numerator / denominator) as u64);
+        };
+        let cached = self.front_run[!zero_for_one as usize];
+        if cached.divisor() == divisor {
+            cached.div(numerator)
+        } else {
+            Reciprocal::new(divisor).div(numerator)
+        }
+    }
+
+    #[cfg(not(feature = "recip_math"))]
+    #[inline(always)]
+    fn output_quotient(
+        &self,
+        numerator: u128,
+        denominator: u128,
+        _zero_for_one: bool,
+    ) -> Option<u64> {
+        Some((numerator / denominator) as u64)
+    }
+
     /// Constant-product AMM output calculation (no heap, no floats).
     ///
     /// Formula: amount_out = (reserve_out * amount_in_with_fee) / (reserve_in * fee_den + amount_in_with_fee)
@@ -40,8 +133,59 @@ impl AmmPoolState {
         let denominator = (reserve_in as u128)
             .checked_mul(self.fee_den as u128)?
             .checked_add(amount_in_with_fee as u128)?;
-        let out = (numerator / denominator) as u64;
-        if out == 0 { None } else { Some(out) }
+        let out = self.output_quotient(numerator, denominator, zero_for_one)?;
+        if out == 0 {
+            None
+        } else {
+            Some(out)
+        }
+    }
+
+    /// Input needed to receive exactly `amount_out` (inverse of `get_amount_out`).
+    ///
+    /// Returns `None` if reserves are zero or `amount_out` would drain the pool.
+    #[inline(always)]
+    pub fn get_amount_in(&self, amount_out: u64, zero_for_one: bool) -> Option<u64> {
+        let (reserve_in, reserve_out) = if zero_for_one {
+            (self.reserve0, self.reserve1)
+        } else {
+            (self.reserve1, self.reserve0)
+        };
+        if reserve_in == 0 || amount_out == 0 || amount_out >= reserve_out {
+            return None;
+        }
+        let fee_adj = self.fee_den.checked_sub(self.fee_num)?;
+        // amount_in = reserve_in * amount_out * fee_den / ((reserve_out - amount_out) * fee_adj) + 1
+        let numerator =
+            (reserve_in as u128 * amount_out as u128).checked_mul(self.fee_den as u128)?;
+        let denominator = ((reserve_out - amount_out) as u128).checked_mul(fee_adj as u128)?;
+        u64::try_from(numerator.checked_div(denominator)? + 1).ok()
+    }
+
+    /// The pool after a swap of `amount_in`, and the swap's output.
+    #[inline(always)]
+    pub fn after_swap(&self, amount_in: u64, zero_for_one: bool) -> Option<(Self, u64)> {
+        let out = self.get_amount_out(amount_in, zero_for_one)?;
+        let (reserve0, reserve1) = if zero_for_one {
+            (
+                self.reserve0.checked_add(amount_in)?,
+                self.reserve1.checked_sub(out)?,
+            )
+        } else {
+            (
+                self.reserve0.checked_sub(out)?,
+                self.reserve1.checked_add(amount_in)?,
+            )
+        };
+        Some((
+            Self {
+                reserve0,
+                reserve1,
+                ..*self
+            }
+            .refreshed(),
+            out,
+        ))
     }
 
     /// Compute sandwich arbitrage profit (no allocations).
@@ -50,36 +194,50 @@ impl AmmPoolState {
     /// victim executes at worse price, we back-run (sell token1 back).
     /// Returns estimated profit in token0 units, or None if unprofitable.
     #[inline(always)]
-    pub fn sandwich_profit(&self, victim_amount_in: u64, our_amount_in: u64, zero_for_one: bool) -> Option<u64> {
+    pub fn sandwich_profit(
+        &self,
+        victim_amount_in: u64,
+        our_amount_in: u64,
+        zero_for_one: bool,
+    ) -> Option<u64> {
         // Step 1: our front-run buy (we buy token1 with our_amount_in of token0)
         let our_out = self.get_amount_out(our_amount_in, zero_for_one)?;
         // New pool reserves after our front-run
         let (new_reserve0, new_reserve1) = if zero_for_one {
-            (self.reserve0.checked_add(our_amount_in)?, self.reserve1.checked_sub(our_out)?)
+            (
+                self.reserve0.checked_add(our_amount_in)?,
+                self.reserve1.checked_sub(our_out)?,
+            )
         } else {
-            (self.reserve0.checked_sub(our_out)?, self.reserve1.checked_add(our_amount_in)?)
+            (
+                self.reserve0.checked_sub(our_out)?,
+                self.reserve1.checked_add(our_amount_in)?,
+            )
         };
         let pool_after_frontrun = AmmPoolState {
             reserve0: new_reserve0,
             reserve1: new_reserve1,
-            fee_num: self.fee_num,
-            fee_den: self.fee_den,
+            ..*self
         };
         // Step 2: victim swap (victim buys in same direction, moving price further)
-        let _ = pool_after_frontrun.get_amount_out(victim_amount_in, zero_for_one)?;
         let (r0_after_victim, r1_after_victim) = if zero_for_one {
             let victim_out = pool_after_frontrun.get_amount_out(victim_amount_in, zero_for_one)?;
-            (new_reserve0.checked_add(victim_amount_in)?, new_reserve1.checked_sub(victim_out)?)
+            (
+                new_reserve0.checked_add(victim_amount_in)?,
+                new_reserve1.checked_sub(victim_out)?,
+            )
         } else {
             let victim_out = pool_after_frontrun.get_amount_out(victim_amount_in, zero_for_one)?;
-            (new_reserve0.checked_sub(victim_out)?, new_reserve1.checked_add(victim_amount_in)?)
+            (
+                new_reserve0.checked_sub(victim_out)?,
+                new_reserve1.checked_add(victim_amount_in)?,
+            )
         };
         // Step 3: our back-run sell (we sell our_out of token1 back for token0)
         let pool_after_victim = AmmPoolState {
             reserve0: r0_after_victim,
             reserve1: r1_after_victim,
-            fee_num: self.fee_num,
-            fee_den: self.fee_den,
+            ..*self
         };
         let back_run_out = pool_after_victim.get_amount_out(our_out, !zero_for_one)?;
         // Profit = what we get back minus what we put in
@@ -87,49 +245,608 @@ impl AmmPoolState {
     }
 }
 
+/// Equal reserves and fee; the cached reciprocals follow from them.
+impl PartialEq for AmmPoolState {
+    fn eq(&self, other: &Self) -> bool {
+        (self.reserve0, self.reserve1, self.fee_num, self.fee_den)
+            == (other.reserve0, other.reserve1, other.fee_num, other.fee_den)
+    }
+}
+
+impl Eq for AmmPoolState {}
+
 /// Static mock pool state — represents a Uniswap-style pool seeded with liquidity.
-/// In production this would be updated from on-chain state reads.
-static MOCK_POOL: AmmPoolState = AmmPoolState {
-    reserve0: 1_000_000_000_000, // 1,000,000 token0 (e.g., 1M USDC, 6 decimals)
-    reserve1: 500_000_000_000,   // 500,000 token1 (e.g., 500K ETH units)
-    fee_num: 3,
-    fee_den: 1_000,
-};
+/// Used when no pool table is attached, and to seed the table in dev setups.
+/// Kept shallow on purpose: the 50-token0 victim swaps in the tests must move
+/// the price by more than the fee paid on both sandwich legs, which a
+/// million-unit pool does not.
+pub const MOCK_POOL: AmmPoolState = AmmPoolState::new(
+    10_000_000_000, // reserve0: 10,000 token0 (USDC, 6 decimals)
+    5_000_000_000,  // reserve1: 5 token1 (WSOL, 9 decimals)
+    3,
+    1_000,
+);
+
+/// Token pair of `MOCK_POOL`: (token0, token1).
+pub const MOCK_POOL_TOKENS: (TokenId, TokenId) = (tokens::USDC, tokens::WSOL);
+
+/// `MOCK_POOL` as a pool-table entry at `address`.
+pub const fn mock_pool_entry(address: [u8; 20]) -> PoolEntry {
+    PoolEntry::new(address, MOCK_POOL_TOKENS.0, MOCK_POOL_TOKENS.1, MOCK_POOL)
+}
 
-/// Minimum profitable swap size — below this threshold, gas cost exceeds profit.
-const MIN_AMOUNT_IN: u64 = 1_000_000;
+/// Default minimum profitable swap size — below this threshold, gas cost
+/// exceeds profit. Used when no adaptive threshold is attached.
+pub const MIN_AMOUNT_IN: u64 = 1_000_000;
 
 /// Our front-run capital: fixed pre-allocated amount, no dynamic allocation.
 const OUR_FRONT_RUN_AMOUNT: u64 = 10_000_000;
 
-/// The hot-path processing logic: zero heap allocations.
+/// Default minimum profit in raw numéraire units (0.01 USDC).
+pub const DEFAULT_MIN_PROFIT: u64 = 10_000;
+
+/// Profit weight that leaves the expected profit unscaled.
+pub const FULL_WEIGHT_BPS: u32 = 10_000;
+
+/// How many records ahead `process_batch` prefetches. With `pool_prefetch`
+/// records are fetched one iteration earlier, so a record's pool address is
+/// already cached when its pool-slot prefetch is issued.
+const RECORD_PREFETCH_DISTANCE: usize = if cfg!(feature = "pool_prefetch") {
+    2
+} else {
+    1
+};
+
+/// Strategy that produced an opportunity, for per-strategy accounting.
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    Sandwich = 0,
+    BookTakerArb = 1,
+}
+
+impl Strategy {
+    pub const COUNT: usize = 2;
+    pub const ALL: [Strategy; Self::COUNT] = [Strategy::Sandwich, Strategy::BookTakerArb];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Strategy::Sandwich => "sandwich",
+            Strategy::BookTakerArb => "book_taker_arb",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|s| s.name() == name)
+    }
+
+    /// This strategy's bit in a [`StrategyMask`].
+    pub const fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Which strategies are enabled, one bit per [`Strategy`]. Read with a
+/// relaxed load wherever a strategy is about to be evaluated, and flipped
+/// from the admin socket, so one strategy can be switched off during an
+/// incident while every other keeps running.
+pub struct StrategyMask(AtomicU8);
+
+impl StrategyMask {
+    /// Every strategy enabled.
+    pub const ALL: u8 = (1 << Strategy::COUNT) - 1;
+
+    pub const fn new(bits: u8) -> Self {
+        Self(AtomicU8::new(bits & Self::ALL))
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self, strategy: Strategy) -> bool {
+        self.0.load(Ordering::Relaxed) & strategy.bit() != 0
+    }
+
+    /// Enable or disable `strategy`; returns the new mask.
+    pub fn set(&self, strategy: Strategy, enabled: bool) -> u8 {
+        let bit = strategy.bit();
+        if enabled {
+            self.0.fetch_or(bit, Ordering::Relaxed) | bit
+        } else {
+            self.0.fetch_and(!bit, Ordering::Relaxed) & !bit
+        }
+    }
+
+    pub fn bits(&self) -> u8 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for StrategyMask {
+    fn default() -> Self {
+        Self::new(Self::ALL)
+    }
+}
+
+/// As JSON: `{"sandwich":true,"book_taker_arb":false}`.
+impl core::fmt::Display for StrategyMask {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let bits = self.bits();
+        for (i, strategy) in Strategy::ALL.iter().enumerate() {
+            let sep = if i == 0 { "{" } else { "," };
+            write!(
+                f,
+                "{sep}\"{}\":{}",
+                strategy.name(),
+                bits & strategy.bit() != 0
+            )?;
+        }
+        f.write_str("}")
+    }
+}
+
+/// What out-bidding a victim's priority fee costs a sandwich. The
+/// front-run is only ordered ahead of the victim if it pays at least one
+/// unit more per gas, on the gas it burns, so a victim paying a high fee
+/// needs a higher profit to break even.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriorityFees {
+    /// Token fees are paid in; priority fees are in its raw units.
+    pub fee_token: TokenId,
+    /// Gas our front-run pays the priority fee on; 0 ignores victim fees.
+    pub outbid_gas: u64,
+}
+
+impl PriorityFees {
+    pub const OFF: Self = Self {
+        fee_token: tokens::WETH,
+        outbid_gas: 0,
+    };
+
+    /// Raw fee-token cost of out-bidding `priority_fee`; 0 for victims
+    /// that pay none.
+    #[inline(always)]
+    pub const fn outbid_cost(&self, priority_fee: u64) -> u64 {
+        if priority_fee == 0 {
+            return 0;
+        }
+        priority_fee
+            .saturating_add(1)
+            .saturating_mul(self.outbid_gas)
+    }
+}
+
+/// A priced opportunity: what it is worth, and how far to trust that.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Opportunity {
+    /// Expected profit in numéraire units.
+    pub profit: u64,
+    /// See [`crate::confidence`]; full when the evaluator has no signals.
+    pub confidence_bps: u16,
+    /// Token the profit is made in.
+    pub token: TokenId,
+    /// Profit in raw units of `token`, before any sender weighting.
+    pub edge: u64,
+}
+
+impl Opportunity {
+    /// `profit` at full confidence, as scored without signals, made in
+    /// the default numéraire.
+    pub const fn unscored(profit: u64) -> Self {
+        Self {
+            profit,
+            confidence_bps: FULL_CONFIDENCE_BPS,
+            token: tokens::USDC,
+            edge: profit,
+        }
+    }
+}
+
+/// Evaluation parameters applied after the AMM math.
 ///
-/// Receives a raw wire payload, casts it to `DexSwapTx` via bytemuck (zero-copy),
-/// evaluates the sandwich arbitrage opportunity using AMM constant-product math,
-/// and returns the estimated profit in token0 units.
-#[inline(always)]
-pub fn process_packet(data: &[u8]) -> Option<u64> {
-    let wire = data.get(..DexSwapTx::WIRE_SIZE)?;
-    // Zero-copy cast: no allocation, no parsing loop — just a pointer reinterpretation.
-    let tx = bytemuck::try_from_bytes::<DexSwapTx>(wire).ok()?;
+/// Profit is computed in the raw units of the input token, then normalized
+/// into the configured numéraire so thresholds and reported values are
+/// comparable across pools with different decimals.
+#[derive(Clone, Copy)]
+pub struct Evaluator {
+    pub normalizer: ProfitNormalizer,
+    /// Opportunities below this profit (numéraire units) are dropped.
+    pub min_profit: u64,
+    /// Per-strategy overrides of `min_profit`, indexed by `Strategy as usize`.
+    /// A pool's own `PoolEntry::min_profit` takes precedence over both.
+    pub strategy_min_profit: [Option<u64>; Strategy::COUNT],
+    /// Live reference prices; static prices are used when `None` or when a
+    /// slot has not been populated yet.
+    pub prices: Option<&'static PriceCache>,
+    /// Expected time from decision to our bundle landing. Victims whose
+    /// deadline elapses within this window are skipped.
+    pub execution_latency_ms: u64,
+    /// Runtime-adaptive victim size threshold; [`MIN_AMOUNT_IN`] when `None`.
+    pub min_amount_in: Option<&'static AdaptiveThreshold>,
+    /// Live pool table; every swap is priced against `MOCK_POOL` when `None`.
+    pub pools: Option<&'static PoolTable>,
+    pub confidence_model: ConfidenceModel,
+    /// Live confidence inputs; every opportunity scores full when `None`.
+    pub confidence: Option<&'static ConfidenceSignals>,
+    /// Where swaps on pools missing from `pools` are counted.
+    pub unknown_pools: Option<&'static UnknownPools>,
+    /// Pools the anomaly detector flagged; no strategy prices against them.
+    pub suppressed_pools: Option<&'static SuppressedPools>,
+    /// Victim swaps [`Evaluator::infer_reserves`] applies to a pool between
+    /// explicit updates; 0 disables reserve inference.
+    pub infer_max_swaps: u16,
+    /// Sandwich thresholds are raised by the cost of out-bidding the
+    /// victim's priority fee.
+    pub priority_fees: PriorityFees,
+}
+
+impl Evaluator {
+    pub const fn new(normalizer: ProfitNormalizer, min_profit: u64) -> Self {
+        Self {
+            normalizer,
+            min_profit,
+            strategy_min_profit: [None; Strategy::COUNT],
+            prices: None,
+            execution_latency_ms: 0,
+            min_amount_in: None,
+            pools: None,
+            confidence_model: ConfidenceModel::DEFAULT,
+            confidence: None,
+            unknown_pools: None,
+            suppressed_pools: None,
+            infer_max_swaps: 0,
+            priority_fees: PriorityFees::OFF,
+        }
+    }
+
+    /// Look up each swap's pool in `table`; unknown pools are skipped.
+    pub const fn with_pools(mut self, table: &'static PoolTable) -> Self {
+        self.pools = Some(table);
+        self
+    }
+
+    /// Read the victim size threshold from an adaptive controller.
+    pub const fn with_adaptive_threshold(mut self, threshold: &'static AdaptiveThreshold) -> Self {
+        self.min_amount_in = Some(threshold);
+        self
+    }
+
+    /// Count swaps on pools missing from the table in `pools`, for discovery.
+    pub const fn with_discovery(mut self, pools: &'static UnknownPools) -> Self {
+        self.unknown_pools = Some(pools);
+        self
+    }
+
+    /// Skip pools while they are entered in `pools`.
+    pub const fn with_suppressed_pools(mut self, pools: &'static SuppressedPools) -> Self {
+        self.suppressed_pools = Some(pools);
+        self
+    }
+
+    /// Whether `pool` is suppressed for now.
+    #[inline(always)]
+    fn suppressed(&self, pool: &PoolAddress) -> bool {
+        self.suppressed_pools.is_some_and(|s| s.is_suppressed(pool))
+    }
+
+    /// Score opportunities by `model` over the live `signals`.
+    pub const fn with_confidence(
+        mut self,
+        model: ConfidenceModel,
+        signals: &'static ConfidenceSignals,
+    ) -> Self {
+        self.confidence_model = model;
+        self.confidence = Some(signals);
+        self
+    }
+
+    /// Confidence in an opportunity priced against `pool`.
+    #[inline(always)]
+    pub fn confidence_bps(&self, pool: &PoolEntry, now_ms: u64) -> u16 {
+        match self.confidence {
+            Some(signals) => self.confidence_model.score(&ConfidenceInputs {
+                inferred_swaps: pool.inferred as u64,
+                ..signals.inputs(pool.slot, now_ms)
+            }),
+            None => FULL_CONFIDENCE_BPS,
+        }
+    }
+
+    /// Apply the victim swap in `data` to its pool's reserves, as if it had
+    /// landed, so the next victim is priced against the moved pool. See
+    /// [`PoolTable::infer_swap`]; `false` if nothing was applied.
+    pub fn infer_reserves(&self, data: &[u8]) -> bool {
+        let (Some(table), max_swaps @ 1..) = (self.pools, self.infer_max_swaps) else {
+            return false;
+        };
+        let Some(tx) = DexSwapTx::header(data) else {
+            return false;
+        };
+        table.infer_swap(
+            &tx.pool_address,
+            tx.amount_in(),
+            tx.min_amount_out(),
+            tx.token_direction == 0,
+            max_swaps,
+        )
+    }
+
+    /// Profit threshold for `strategy` on `pool`: the pool's override, else
+    /// the strategy's, else the global `min_profit`.
+    #[inline(always)]
+    pub fn min_profit_for(&self, strategy: Strategy, pool: &PoolEntry) -> u64 {
+        match pool.min_profit {
+            Some(min) => min,
+            None => self.strategy_min_profit[strategy as usize].unwrap_or(self.min_profit),
+        }
+    }
+
+    /// Numéraire cost of out-bidding a victim paying `priority_fee`;
+    /// `None` if it cannot be priced.
+    #[inline(always)]
+    fn outbid_cost(&self, priority_fee: u64) -> Option<u64> {
+        match self.priority_fees.outbid_cost(priority_fee) {
+            0 => Some(0),
+            raw => self.normalize(self.priority_fees.fee_token, raw),
+        }
+    }
+
+    #[inline(always)]
+    fn min_amount_in(&self) -> u64 {
+        match self.min_amount_in {
+            Some(t) => t.load(),
+            None => MIN_AMOUNT_IN,
+        }
+    }
+
+    /// Convert profits using live reference prices from `cache`.
+    pub const fn with_prices(mut self, cache: &'static PriceCache) -> Self {
+        self.prices = Some(cache);
+        self
+    }
+
+    /// Hot-path processing of a single wire payload.
+    ///
+    /// Returns the expected profit in numéraire units.
+    ///
+    /// Payloads carrying the `DexSwapTxExt` extension are additionally checked
+    /// against the victim deadline, the clock only read for those, and must
+    /// clear the cost of out-bidding the victim's priority fee.
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    pub fn process_packet(&self, data: &[u8]) -> Option<u64> {
+        self.process_packet_with_clock(data, unix_time_ms, FULL_WEIGHT_BPS)
+            .map(|(o, _)| o.profit)
+    }
+
+    /// [`Evaluator::process_packet`] with an explicit unix time in milliseconds.
+    /// The entry point for `no_std` embedders, which bring their own clock.
+    #[inline(always)]
+    pub fn process_packet_at(&self, data: &[u8], now_ms: u64) -> Option<u64> {
+        self.process_packet_with_clock(data, || now_ms, FULL_WEIGHT_BPS)
+            .map(|(o, _)| o.profit)
+    }
+
+    /// [`Evaluator::process_packet`] at the time of `clock`.
+    #[inline(always)]
+    pub fn process_packet_on(&self, clock: &impl Clock, data: &[u8]) -> Option<u64> {
+        self.process_packet_with_clock(data, || clock.now_unix_ms(), FULL_WEIGHT_BPS)
+            .map(|(o, _)| o.profit)
+    }
+
+    /// [`Evaluator::process_packet`] with the profit scaled by `weight_bps`
+    /// (e.g. the victim sender's hit rate) before the threshold check.
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    pub fn process_packet_weighted(&self, data: &[u8], weight_bps: u32) -> Option<u64> {
+        self.process_packet_with_clock(data, unix_time_ms, weight_bps)
+            .map(|(o, _)| o.profit)
+    }
+
+    /// [`Evaluator::process_packet_weighted`] returning the opportunity's
+    /// confidence and edge along with its profit.
+    #[cfg(feature = "std")]
+    #[inline(always)]
+    pub fn score_packet(&self, data: &[u8], weight_bps: u32) -> Option<Opportunity> {
+        let (opportunity, pool) = self.process_packet_with_clock(data, unix_time_ms, weight_bps)?;
+        Some(Opportunity {
+            confidence_bps: self.confidence_bps(&pool, unix_time_ms()),
+            ..opportunity
+        })
+    }
+
+    /// [`Evaluator::score_packet`] at the time of `clock`: the victim's
+    /// deadline and the staleness of its pool are judged by it, so a replay
+    /// scores as the node did live.
+    #[inline(always)]
+    pub fn score_packet_on(
+        &self,
+        clock: &impl Clock,
+        data: &[u8],
+        weight_bps: u32,
+    ) -> Option<Opportunity> {
+        let (opportunity, pool) =
+            self.process_packet_with_clock(data, || clock.now_unix_ms(), weight_bps)?;
+        Some(Opportunity {
+            confidence_bps: self.confidence_bps(&pool, clock.now_unix_ms()),
+            ..opportunity
+        })
+    }
+
+    /// The unscored opportunity and the pool it was priced against.
+    #[inline(always)]
+    fn process_packet_with_clock(
+        &self,
+        data: &[u8],
+        now_ms: impl FnOnce() -> u64,
+        weight_bps: u32,
+    ) -> Option<(Opportunity, PoolEntry)> {
+        let wire = data.get(..DexSwapTx::WIRE_SIZE)?;
+        // Zero-copy cast: no allocation, no parsing loop — just a pointer reinterpretation.
+        let tx = bytemuck::try_from_bytes::<DexSwapTx>(wire).ok()?;
+        let mut priority_fee = 0;
+        if let Some(ext) = DexSwapTxExt::parse(data) {
+            if !self.meets_deadline(ext.deadline_ms(), now_ms()) {
+                return None;
+            }
+            priority_fee = ext.priority_fee();
+        }
+        self.evaluate_swap(tx, priority_fee, weight_bps)
+    }
 
-    let amount_in = tx.amount_in();
-    if amount_in < MIN_AMOUNT_IN {
-        return None;
+    /// `false` if the victim would expire before our bundle can land.
+    #[inline(always)]
+    pub fn meets_deadline(&self, deadline_ms: u64, now_ms: u64) -> bool {
+        deadline_ms == 0 || deadline_ms >= now_ms.saturating_add(self.execution_latency_ms)
+    }
+
+    /// Batch variant of [`Evaluator::process_packet`] for payloads carrying
+    /// several back-to-back `DexSwapTx` records.
+    ///
+    /// The batch is cast once via `cast_slice`, and the next record is prefetched
+    /// while the current one is evaluated. With the `pool_prefetch` feature the
+    /// next record's pool-table slot is prefetched too, hiding the DRAM miss of
+    /// cold-pool lookups. `on_profit(index, profit)` is invoked for every
+    /// profitable record. Returns the number of records evaluated.
+    #[inline(always)]
+    pub fn process_batch<F: FnMut(usize, u64)>(&self, data: &[u8], mut on_profit: F) -> usize {
+        let batch = DexSwapTx::cast_batch(data);
+        for (i, tx) in batch.iter().enumerate() {
+            if let Some(next) = batch.get(i + RECORD_PREFETCH_DISTANCE) {
+                prefetch_read(next);
+            }
+            // The next record is already in flight; start its pool lookup.
+            #[cfg(feature = "pool_prefetch")]
+            if let (Some(table), Some(next)) = (self.pools, batch.get(i + 1)) {
+                table.prefetch(&next.pool_address);
+            }
+            if let Some((opportunity, _)) = self.evaluate_swap(tx, 0, FULL_WEIGHT_BPS) {
+                on_profit(i, opportunity.profit);
+            }
+        }
+        batch.len()
     }
 
-    // direction: 0 = token0->token1, 1 = token1->token0
-    let zero_for_one = tx.token_direction == 0;
+    /// Evaluate a single decoded swap, whose sender pays `priority_fee`,
+    /// against the pool state. Returns the opportunity, unscored, and the
+    /// pool priced against.
+    #[inline(always)]
+    fn evaluate_swap(
+        &self,
+        tx: &DexSwapTx,
+        priority_fee: u64,
+        weight_bps: u32,
+    ) -> Option<(Opportunity, PoolEntry)> {
+        let amount_in = tx.amount_in();
+        if amount_in < self.min_amount_in() {
+            return None;
+        }
+
+        // direction: 0 = token0->token1, 1 = token1->token0
+        let zero_for_one = tx.token_direction == 0;
+
+        let pool = match self.pools {
+            Some(table) => match table.get(&tx.pool_address) {
+                Some(pool) => pool,
+                None => {
+                    if let Some(unknown) = self.unknown_pools {
+                        unknown.record(&tx.pool_address);
+                    }
+                    return None;
+                }
+            },
+            None => mock_pool_entry(tx.pool_address),
+        };
+        if self.suppressed(&tx.pool_address) {
+            return None;
+        }
+
+        // Check slippage guard: victim's min_amount_out vs actual AMM output
+        let victim_actual_out = pool.state.get_amount_out(amount_in, zero_for_one)?;
+        if victim_actual_out < tx.min_amount_out() {
+            // Victim tx would revert — not a valid sandwich target
+            return None;
+        }
+
+        // Compute sandwich profit using constant-product AMM formula.
+        // Profit is denominated in the token we front-run with (the input token).
+        let raw_profit =
+            pool.state
+                .sandwich_profit(amount_in, OUR_FRONT_RUN_AMOUNT, zero_for_one)?;
+        let profit_token = if zero_for_one {
+            pool.token0
+        } else {
+            pool.token1
+        };
+        let mut profit = self.normalize(profit_token, raw_profit)?;
+        if weight_bps != FULL_WEIGHT_BPS {
+            profit = (profit as u128 * weight_bps as u128 / FULL_WEIGHT_BPS as u128) as u64;
+        }
+        if profit
+            < self
+                .min_profit_for(Strategy::Sandwich, &pool)
+                .saturating_add(self.outbid_cost(priority_fee)?)
+        {
+            return None;
+        }
+        let opportunity = Opportunity {
+            profit,
+            confidence_bps: FULL_CONFIDENCE_BPS,
+            token: profit_token,
+            edge: raw_profit,
+        };
+        Some((opportunity, pool))
+    }
 
-    // Check slippage guard: victim's min_amount_out vs actual AMM output
-    let victim_actual_out = MOCK_POOL.get_amount_out(amount_in, zero_for_one)?;
-    if victim_actual_out < tx.min_amount_out() {
-        // Victim tx would revert — not a valid sandwich target
-        return None;
+    /// Check an order-book market against the AMM pool trading the same pair.
+    ///
+    /// Returns the best taker arbitrage and its profit in numéraire units.
+    pub fn evaluate_book(
+        &self,
+        book: &OrderBookState,
+        pool: &PoolEntry,
+    ) -> Option<(TakerArb, u64)> {
+        if self.suppressed(&pool.address) {
+            return None;
+        }
+        let arb = orderbook::taker_arb(book, &pool.state)?;
+        let profit = self.normalize(pool.token1, arb.quote_profit)?;
+        (profit >= self.min_profit_for(Strategy::BookTakerArb, pool)).then_some((arb, profit))
     }
 
-    // Compute sandwich profit using constant-product AMM formula
-    MOCK_POOL.sandwich_profit(amount_in, OUR_FRONT_RUN_AMOUNT, zero_for_one)
+    #[inline(always)]
+    fn normalize(&self, token: TokenId, raw: u64) -> Option<u64> {
+        match self.prices {
+            Some(cache) => self.normalizer.to_numeraire_live(cache, token, raw),
+            None => self.normalizer.to_numeraire(token, raw),
+        }
+    }
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        DEFAULT_EVALUATOR
+    }
+}
+
+/// Evaluator used by the free-function entry points: USDC-e6 numéraire,
+/// [`DEFAULT_MIN_PROFIT`] threshold.
+pub const DEFAULT_EVALUATOR: Evaluator =
+    Evaluator::new(ProfitNormalizer::USDC_E6, DEFAULT_MIN_PROFIT);
+
+/// The hot-path processing logic: zero heap allocations.
+///
+/// Receives a raw wire payload, casts it to `DexSwapTx` via bytemuck (zero-copy),
+/// evaluates the sandwich arbitrage opportunity using AMM constant-product math,
+/// and returns the estimated profit in USDC-e6 (see [`DEFAULT_EVALUATOR`]).
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn process_packet(data: &[u8]) -> Option<u64> {
+    DEFAULT_EVALUATOR.process_packet(data)
+}
+
+/// Batch entry point using [`DEFAULT_EVALUATOR`]; see [`Evaluator::process_batch`].
+#[inline(always)]
+pub fn process_batch<F: FnMut(usize, u64)>(data: &[u8], on_profit: F) -> usize {
+    DEFAULT_EVALUATOR.process_batch(data, on_profit)
 }
 
 #[cfg(test)]
@@ -138,29 +855,142 @@ mod tests {
     use crate::payload::DexSwapTx;
     use bytemuck::bytes_of;
 
+    #[test]
+    fn strategies_are_toggled_one_at_a_time() {
+        let mask = StrategyMask::default();
+        assert!(Strategy::ALL.iter().all(|&s| mask.is_enabled(s)));
+        assert_eq!(
+            mask.set(Strategy::BookTakerArb, false),
+            Strategy::Sandwich.bit()
+        );
+        assert!(mask.is_enabled(Strategy::Sandwich) && !mask.is_enabled(Strategy::BookTakerArb));
+        assert_eq!(
+            mask.to_string(),
+            "{\"sandwich\":true,\"book_taker_arb\":false}"
+        );
+        assert_eq!(mask.set(Strategy::BookTakerArb, true), StrategyMask::ALL);
+        assert_eq!(
+            Strategy::from_name("book_taker_arb"),
+            Some(Strategy::BookTakerArb)
+        );
+        assert_eq!(Strategy::from_name("arb"), None);
+    }
+
+    #[test]
+    fn sandwich_threshold_covers_the_outbid_cost() {
+        let base = DexSwapTx::from_parts(7, [0xAB; 20], 50_000_000, 1, 0);
+        let profit = process_packet(bytes_of(&base)).unwrap();
+        let evaluator = Evaluator {
+            priority_fees: PriorityFees {
+                fee_token: tokens::USDC,
+                outbid_gas: 1,
+            },
+            ..DEFAULT_EVALUATOR
+        };
+        let victim = |fee| DexSwapTxExt::from_parts(base, 0).with_priority_fee(fee);
+        // Out-bidding costs fee + 1 per gas: the threshold rises to exactly the profit...
+        let breakeven = profit - DEFAULT_MIN_PROFIT - 1;
+        assert_eq!(
+            evaluator.process_packet(bytes_of(&victim(breakeven))),
+            Some(profit)
+        );
+        // ...and one more unit of fee puts the victim out of reach.
+        assert_eq!(
+            evaluator.process_packet(bytes_of(&victim(breakeven + 1))),
+            None
+        );
+        assert_eq!(
+            DEFAULT_EVALUATOR.process_packet(bytes_of(&victim(breakeven + 1))),
+            Some(profit)
+        );
+        assert_eq!(
+            evaluator.process_packet(bytes_of(&victim(0))),
+            Some(profit),
+            "no fee, no outbid"
+        );
+    }
+
     #[test]
     fn amm_get_amount_out_basic() {
-        let pool = AmmPoolState { reserve0: 1_000_000, reserve1: 1_000_000, fee_num: 3, fee_den: 1_000 };
-        let out = pool.get_amount_out(1_000, true).expect("should produce output");
+        let pool = AmmPoolState::new(1_000_000, 1_000_000, 3, 1_000);
+        let out = pool
+            .get_amount_out(1_000, true)
+            .expect("should produce output");
         // With equal reserves and small input, output should be slightly less than input (fee + slippage)
         assert!(out < 1_000);
         assert!(out > 900);
     }
 
+    #[test]
+    fn amm_output_matches_exact_division() {
+        let exact = |pool: &AmmPoolState, amount_in: u64, zero_for_one: bool| {
+            let (reserve_in, reserve_out) = if zero_for_one {
+                (pool.reserve0, pool.reserve1)
+            } else {
+                (pool.reserve1, pool.reserve0)
+            };
+            let with_fee = amount_in as u128 * (pool.fee_den - pool.fee_num) as u128;
+            let out = reserve_out as u128 * with_fee
+                / (reserve_in as u128 * pool.fee_den as u128 + with_fee);
+            Some(out as u64).filter(|&out| out != 0)
+        };
+        let mut x = 0x2545_F491_4F6C_DD1Du64;
+        for _ in 0..10_000 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            let (reserve0, reserve1) = (x >> (x % 40), x.rotate_left(17) >> (x % 23 + 20));
+            let pool = AmmPoolState::new(reserve0.max(1), reserve1.max(1), x % 100, 10_000);
+            // The fixed front-run size, which may hit the cache, and others.
+            for amount_in in [OUR_FRONT_RUN_AMOUNT, x >> 40, x >> 20, 1] {
+                for zero_for_one in [true, false] {
+                    assert_eq!(
+                        pool.get_amount_out(amount_in, zero_for_one),
+                        exact(&pool, amount_in, zero_for_one)
+                    );
+                }
+            }
+        }
+        let cached = MOCK_POOL.front_run[0].divisor();
+        let expected = MOCK_POOL.reserve0 * 1_000 + OUR_FRONT_RUN_AMOUNT * 997;
+        assert_eq!(
+            cached,
+            if cfg!(feature = "recip_math") {
+                expected
+            } else {
+                0
+            }
+        );
+        let (moved, _) = MOCK_POOL.after_swap(OUR_FRONT_RUN_AMOUNT, true).unwrap();
+        assert_eq!(
+            moved.front_run,
+            moved.refreshed().front_run,
+            "swaps refresh the cache"
+        );
+    }
+
     #[test]
     fn amm_rejects_zero_reserves() {
-        let pool = AmmPoolState { reserve0: 0, reserve1: 1_000_000, fee_num: 3, fee_den: 1_000 };
+        let pool = AmmPoolState::new(0, 1_000_000, 3, 1_000);
         assert!(pool.get_amount_out(1_000, true).is_none());
     }
 
+    #[test]
+    fn amm_get_amount_in_inverts_get_amount_out() {
+        let amount_in = MOCK_POOL
+            .get_amount_in(1_000_000, false)
+            .expect("within reserves");
+        assert!(MOCK_POOL.get_amount_out(amount_in, false).unwrap() >= 1_000_000);
+        assert!(MOCK_POOL.get_amount_out(amount_in - 1, false).unwrap() < 1_000_000);
+        assert!(MOCK_POOL.get_amount_in(MOCK_POOL.reserve0, false).is_none());
+    }
+
     #[test]
     fn process_packet_profitable_swap() {
         let tx = DexSwapTx::from_parts(
-            42,
-            [0xAB; 20],
-            50_000_000,  // large victim swap
-            1,           // min_out = 1, so no slippage revert
-            0,           // zero_for_one
+            42, [0xAB; 20], 50_000_000, // large victim swap
+            1,          // min_out = 1, so no slippage revert
+            0,          // zero_for_one
         );
         let raw = bytes_of(&tx);
         let profit = process_packet(raw);
@@ -171,6 +1001,304 @@ mod tests {
     fn process_packet_rejects_small_swap() {
         let tx = DexSwapTx::from_parts(1, [0u8; 20], 500, 1, 0);
         let raw = bytes_of(&tx);
-        assert!(process_packet(raw).is_none(), "below MIN_AMOUNT_IN should return None");
+        assert!(
+            process_packet(raw).is_none(),
+            "below MIN_AMOUNT_IN should return None"
+        );
+    }
+
+    #[test]
+    fn profit_is_normalized_and_thresholded() {
+        let tx = DexSwapTx::from_parts(7, [0xAB; 20], 50_000_000, 1, 0);
+        let usdc = process_packet(bytes_of(&tx)).expect("profitable in USDC");
+
+        // Same opportunity reported in WSOL: 2000 USDC per SOL, SOL has 9 decimals.
+        let in_sol = Evaluator::new(ProfitNormalizer::new(tokens::WSOL).unwrap(), 0);
+        assert_eq!(
+            in_sol.process_packet(bytes_of(&tx)),
+            Some(usdc * 1_000 / 2_000)
+        );
+
+        let strict = Evaluator::new(ProfitNormalizer::USDC_E6, usdc + 1);
+        assert!(strict.process_packet(bytes_of(&tx)).is_none());
+
+        // Weighted profit is reported and thresholded.
+        assert_eq!(
+            DEFAULT_EVALUATOR.process_packet_weighted(bytes_of(&tx), 5_000),
+            Some(usdc / 2)
+        );
+        let halved = Evaluator::new(ProfitNormalizer::USDC_E6, usdc / 2 + 1);
+        assert!(halved
+            .process_packet_weighted(bytes_of(&tx), 5_000)
+            .is_none());
+    }
+
+    #[test]
+    fn drops_victims_expiring_within_execution_latency() {
+        use crate::payload::DexSwapTxExt;
+        let base = DexSwapTx::from_parts(11, [0xAB; 20], 50_000_000, 1, 0);
+        let now = 1_700_000_000_000;
+        let evaluator = Evaluator {
+            execution_latency_ms: 400,
+            ..DEFAULT_EVALUATOR
+        };
+
+        let ok = DexSwapTxExt::from_parts(base, now + 500);
+        assert!(evaluator.process_packet_at(bytes_of(&ok), now).is_some());
+
+        let too_soon = DexSwapTxExt::from_parts(base, now + 300);
+        assert!(evaluator
+            .process_packet_at(bytes_of(&too_soon), now)
+            .is_none());
+
+        let no_deadline = DexSwapTxExt::from_parts(base, 0);
+        assert!(evaluator
+            .process_packet_at(bytes_of(&no_deadline), now)
+            .is_some());
+    }
+
+    #[test]
+    fn adaptive_threshold_is_read_at_evaluation_time() {
+        static THRESHOLD: AdaptiveThreshold = AdaptiveThreshold::new(MIN_AMOUNT_IN);
+        let evaluator = DEFAULT_EVALUATOR.with_adaptive_threshold(&THRESHOLD);
+        let tx = DexSwapTx::from_parts(12, [0xAB; 20], 50_000_000, 1, 0);
+
+        assert!(evaluator.process_packet(bytes_of(&tx)).is_some());
+        THRESHOLD.store(60_000_000);
+        assert!(evaluator.process_packet(bytes_of(&tx)).is_none());
+    }
+
+    #[test]
+    fn pool_table_lookup_uses_per_pool_state() {
+        static TABLE: PoolTable = PoolTable::new();
+        static UNKNOWN: UnknownPools = UnknownPools::new();
+        let evaluator = DEFAULT_EVALUATOR
+            .with_pools(&TABLE)
+            .with_discovery(&UNKNOWN);
+        let tx = DexSwapTx::from_parts(13, [0x11; 20], 50_000_000, 1, 0);
+
+        assert!(
+            evaluator.process_packet(bytes_of(&tx)).is_none(),
+            "unknown pool"
+        );
+        let mut misses = Vec::new();
+        UNKNOWN.for_each(|pool| misses.push((pool.address, pool.hits)));
+        assert_eq!(misses, [([0x11; 20], 1)]);
+        TABLE.upsert(mock_pool_entry([0x11; 20])).unwrap();
+        assert_eq!(
+            evaluator.process_packet(bytes_of(&tx)),
+            process_packet(bytes_of(&tx))
+        );
+
+        // Deep pool: victim impact no longer covers fees.
+        TABLE.update_reserves(&[0x11; 20], 1_000_000_000_000, 500_000_000_000, 2, 2);
+        assert!(evaluator.process_packet(bytes_of(&tx)).is_none());
+    }
+
+    #[test]
+    fn score_discounts_profit_on_a_lagging_pool() {
+        static TABLE: PoolTable = PoolTable::new();
+        static SIGNALS: ConfidenceSignals = ConfidenceSignals::new();
+        let model = ConfidenceModel {
+            feed_stale_ms: 0,
+            pool_stale_slots: 10,
+            inferred_stale_swaps: 0,
+            prior_win_rate_bps: 10_000,
+        };
+        let evaluator = DEFAULT_EVALUATOR
+            .with_pools(&TABLE)
+            .with_confidence(model, &SIGNALS);
+        let tx = DexSwapTx::from_parts(14, [0x12; 20], 50_000_000, 1, 0);
+        TABLE.upsert(mock_pool_entry([0x12; 20])).unwrap();
+        let profit = process_packet(bytes_of(&tx)).unwrap();
+
+        let scored = evaluator
+            .score_packet(bytes_of(&tx), FULL_WEIGHT_BPS)
+            .unwrap();
+        assert_eq!(
+            (scored.profit, scored.confidence_bps),
+            (profit, FULL_CONFIDENCE_BPS)
+        );
+        // The feed has moved on four slots past this pool's last update.
+        SIGNALS.pool_updated(4, 1);
+        assert_eq!(
+            evaluator
+                .score_packet(bytes_of(&tx), FULL_WEIGHT_BPS)
+                .unwrap()
+                .confidence_bps,
+            6_000
+        );
+        assert_eq!(
+            DEFAULT_EVALUATOR
+                .score_packet(bytes_of(&tx), FULL_WEIGHT_BPS)
+                .unwrap()
+                .confidence_bps,
+            FULL_CONFIDENCE_BPS
+        );
+    }
+
+    #[test]
+    fn inferred_victims_move_the_pool_until_the_next_update() {
+        static TABLE: PoolTable = PoolTable::new();
+        static SIGNALS: ConfidenceSignals = ConfidenceSignals::new();
+        let model = ConfidenceModel {
+            feed_stale_ms: 0,
+            pool_stale_slots: 0,
+            inferred_stale_swaps: 4,
+            prior_win_rate_bps: 10_000,
+        };
+        let evaluator = Evaluator {
+            infer_max_swaps: 2,
+            ..DEFAULT_EVALUATOR.with_pools(&TABLE)
+        }
+        .with_confidence(model, &SIGNALS);
+        let tx = DexSwapTx::from_parts(15, [0x13; 20], 50_000_000, 1, 0);
+        TABLE.upsert(mock_pool_entry([0x13; 20])).unwrap();
+        let before = evaluator
+            .score_packet(bytes_of(&tx), FULL_WEIGHT_BPS)
+            .unwrap();
+
+        assert!(evaluator.infer_reserves(bytes_of(&tx)));
+        let moved = TABLE.peek(&[0x13; 20]).unwrap();
+        assert_eq!(
+            (moved.state, moved.inferred),
+            (MOCK_POOL.after_swap(50_000_000, true).unwrap().0, 1)
+        );
+        let after = evaluator
+            .score_packet(bytes_of(&tx), FULL_WEIGHT_BPS)
+            .unwrap();
+        assert_ne!(after.profit, before.profit);
+        assert_eq!(
+            (before.confidence_bps, after.confidence_bps),
+            (FULL_CONFIDENCE_BPS, 7_500)
+        );
+
+        // A reverting victim changes nothing; the cap stops inference.
+        let reverts = DexSwapTx::from_parts(16, [0x13; 20], 50_000_000, u64::MAX, 0);
+        assert!(!evaluator.infer_reserves(bytes_of(&reverts)));
+        assert!(evaluator.infer_reserves(bytes_of(&tx)));
+        assert!(!evaluator.infer_reserves(bytes_of(&tx)));
+        assert!(
+            !DEFAULT_EVALUATOR
+                .with_pools(&TABLE)
+                .infer_reserves(bytes_of(&tx)),
+            "inference off"
+        );
+
+        // The next update resets the pool to the feed's reserves.
+        TABLE.update_reserves(&[0x13; 20], MOCK_POOL.reserve0, MOCK_POOL.reserve1, 1, 1);
+        assert_eq!(TABLE.peek(&[0x13; 20]).unwrap().inferred, 0);
+        assert_eq!(
+            evaluator.score_packet(bytes_of(&tx), FULL_WEIGHT_BPS),
+            Some(before)
+        );
+    }
+
+    #[test]
+    fn live_reference_price_overrides_static_table() {
+        use crate::prices::PriceUpdate;
+        static CACHE: PriceCache = PriceCache::new();
+
+        // token1 -> token0 swap: we front-run with WSOL, profit is in WSOL.
+        let tx = DexSwapTx::from_parts(9, [0xAB; 20], 30_000_000, 1, 1);
+        let in_sol = Evaluator::new(ProfitNormalizer::new(tokens::WSOL).unwrap(), 0);
+        let raw_sol = in_sol.process_packet(bytes_of(&tx)).expect("profitable");
+
+        let live = Evaluator::new(ProfitNormalizer::USDC_E6, 0).with_prices(&CACHE);
+        let static_usdc = live.process_packet(bytes_of(&tx)).expect("static fallback");
+        assert_eq!(static_usdc, raw_sol * 2);
+
+        CACHE
+            .apply(&PriceUpdate::new(tokens::USDC, 1_000_000, 1, 1))
+            .unwrap();
+        CACHE
+            .apply(&PriceUpdate::new(tokens::WSOL, 150_000_000, 1, 1))
+            .unwrap();
+        assert_eq!(
+            live.process_packet(bytes_of(&tx)),
+            Some(raw_sol * 150 / 1_000)
+        );
+    }
+
+    #[test]
+    fn process_batch_matches_per_record_path() {
+        let txs = [
+            DexSwapTx::from_parts(1, [0xAB; 20], 50_000_000, 1, 0),
+            DexSwapTx::from_parts(2, [0xAB; 20], 500, 1, 0),
+            DexSwapTx::from_parts(3, [0xAB; 20], 80_000_000, 1, 0),
+        ];
+        let raw: &[u8] = bytemuck::cast_slice(&txs);
+
+        let mut hits = [None; 3];
+        let evaluated = process_batch(raw, |i, profit| hits[i] = Some(profit));
+
+        assert_eq!(evaluated, 3);
+        for (i, tx) in txs.iter().enumerate() {
+            assert_eq!(hits[i], process_packet(bytes_of(tx)));
+        }
+        assert!(hits[0].is_some() && hits[1].is_none() && hits[2].is_some());
+    }
+
+    #[test]
+    fn process_batch_looks_up_each_record_pool() {
+        static TABLE: PoolTable = PoolTable::new();
+        TABLE.upsert(mock_pool_entry([0x21; 20])).unwrap();
+        TABLE.upsert(mock_pool_entry([0x23; 20])).unwrap();
+        let evaluator = DEFAULT_EVALUATOR.with_pools(&TABLE);
+        let txs: [DexSwapTx; 4] = core::array::from_fn(|i| {
+            DexSwapTx::from_parts(i as u64, [0x21 + i as u8; 20], 50_000_000, 1, 0)
+        });
+
+        let mut hits = [None; 4];
+        assert_eq!(
+            evaluator.process_batch(bytemuck::cast_slice(&txs), |i, p| hits[i] = Some(p)),
+            4
+        );
+        let expected = process_packet(bytes_of(&txs[0]));
+        assert_eq!(hits, [expected, None, expected, None]);
+    }
+
+    #[test]
+    fn pool_threshold_overrides_strategy_and_global() {
+        static TABLE: PoolTable = PoolTable::new();
+        let tx = |pool: u8| DexSwapTx::from_parts(3, [pool; 20], 50_000_000, 1, 0);
+        let profit = process_packet(bytes_of(&tx(0x41))).expect("profitable");
+        TABLE.upsert(mock_pool_entry([0x41; 20])).unwrap();
+        TABLE
+            .upsert(mock_pool_entry([0x42; 20]).with_min_profit(profit))
+            .unwrap();
+
+        let mut evaluator = DEFAULT_EVALUATOR.with_pools(&TABLE);
+        evaluator.strategy_min_profit[Strategy::Sandwich as usize] = Some(profit + 1);
+        assert_eq!(
+            evaluator.process_packet(bytes_of(&tx(0x41))),
+            None,
+            "strategy threshold applies"
+        );
+        assert_eq!(
+            evaluator.process_packet(bytes_of(&tx(0x42))),
+            Some(profit),
+            "pool threshold wins"
+        );
+
+        evaluator.strategy_min_profit = [None; Strategy::COUNT];
+        evaluator.min_profit = u64::MAX;
+        assert_eq!(evaluator.process_packet(bytes_of(&tx(0x42))), Some(profit));
+    }
+
+    #[test]
+    fn book_arb_profit_is_normalized_from_quote_token() {
+        use crate::orderbook::{OrderBookState, PriceLevel};
+        let mut book = OrderBookState::EMPTY;
+        book.bids[0] = PriceLevel::new(550_000_000, 50_000_000);
+        let pool = mock_pool_entry([0x31; 20]);
+        let (arb, profit) = DEFAULT_EVALUATOR
+            .evaluate_book(&book, &pool)
+            .expect("bid 10% above the pool");
+        // Quote is WSOL (9 decimals) at 2000 USDC.
+        assert_eq!(profit, arb.quote_profit * 2_000 / 1_000);
+
+        let strict = Evaluator::new(ProfitNormalizer::USDC_E6, profit + 1);
+        assert!(strict.evaluate_book(&book, &pool).is_none());
     }
 }
@@ -0,0 +1,270 @@
+//! Sampling stage profiler for the TAP main loop.
+//!
+//! On hosts where `perf` is not available, this gives a flat answer to
+//! "where do the cycles go". The loop marks each stage it enters. Every
+//! `sample_every`-th iteration is sampled: each mark reads the TSC and
+//! charges the cycles since the previous one to the stage that was running.
+//! The stages tile the iteration, so their shares add up to the whole
+//! sampled loop time.
+//!
+//! Off (the default), a mark costs one predictable branch. It is switched
+//! on and off at runtime from the admin socket (`profile start`, `profile
+//! stop`); `profile` reports the attribution so far.
+use crate::runtime;
+
+/// Parts of one main-loop iteration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// smoltcp interface poll.
+    Poll = 0,
+    /// UDP victims: parse, evaluate, reply.
+    Victims = 1,
+    /// Pool feed: receive, FEC, reorder, validate and apply.
+    PoolFeed = 2,
+    /// Relayer TCP session.
+    Relay = 3,
+    /// Executor TCP stream.
+    Executor = 4,
+    /// Feed mirror flush.
+    Mirror = 5,
+    /// Timer wheel, the periodic work it fires, and cancellations.
+    Timers = 6,
+    /// Control port: price updates and outcome reports, when split off.
+    Control = 7,
+}
+
+impl Stage {
+    pub const COUNT: usize = 8;
+    pub const ALL: [Stage; Self::COUNT] = [
+        Stage::Poll,
+        Stage::Victims,
+        Stage::Control,
+        Stage::PoolFeed,
+        Stage::Relay,
+        Stage::Executor,
+        Stage::Mirror,
+        Stage::Timers,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Stage::Poll => "poll",
+            Stage::Victims => "victims",
+            Stage::PoolFeed => "pool_feed",
+            Stage::Relay => "relay",
+            Stage::Executor => "executor",
+            Stage::Mirror => "mirror",
+            Stage::Timers => "timers",
+            Stage::Control => "control",
+        }
+    }
+}
+
+pub struct StageProfiler {
+    sample_every: u32,
+    countdown: u32,
+    enabled: bool,
+    /// This iteration is sampled.
+    sampling: bool,
+    running: Option<Stage>,
+    mark: u64,
+    cycles: [u64; Stage::COUNT],
+    samples: u64,
+}
+
+impl StageProfiler {
+    /// A stopped profiler sampling one iteration in `sample_every`.
+    pub fn new(sample_every: u32) -> Self {
+        let sample_every = sample_every.max(1);
+        Self {
+            sample_every,
+            countdown: sample_every,
+            enabled: false,
+            sampling: false,
+            running: None,
+            mark: 0,
+            cycles: [0; Stage::COUNT],
+            samples: 0,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Start sampling afresh.
+    pub fn start(&mut self) {
+        self.enabled = true;
+        self.cycles = [0; Stage::COUNT];
+        self.samples = 0;
+    }
+
+    /// Stop sampling; the attribution so far is kept for [`report`].
+    ///
+    /// [`report`]: Self::report
+    pub fn stop(&mut self) {
+        self.enabled = false;
+        self.sampling = false;
+    }
+
+    /// An iteration starts at cycle `now`.
+    #[inline(always)]
+    pub fn begin(&mut self, now: u64) {
+        if !self.enabled {
+            return;
+        }
+        self.countdown -= 1;
+        self.sampling = self.countdown == 0;
+        if self.sampling {
+            self.countdown = self.sample_every;
+            self.samples += 1;
+            self.running = None;
+            self.mark = now;
+        }
+    }
+
+    /// The iteration moves on to `stage`.
+    #[inline(always)]
+    pub fn enter(&mut self, stage: Stage) {
+        if self.sampling {
+            self.lap(Some(stage), runtime::now_cycles());
+        }
+    }
+
+    /// The iteration is over.
+    #[inline(always)]
+    pub fn end(&mut self) {
+        if self.sampling {
+            self.lap(None, runtime::now_cycles());
+            self.sampling = false;
+        }
+    }
+
+    fn lap(&mut self, next: Option<Stage>, now: u64) {
+        if let Some(stage) = self.running {
+            self.cycles[stage as usize] += now.saturating_sub(self.mark);
+        }
+        self.running = next;
+        self.mark = now;
+    }
+
+    pub fn report(&self) -> ProfileReport {
+        ProfileReport {
+            enabled: self.enabled,
+            samples: self.samples,
+            cycles: self.cycles,
+        }
+    }
+}
+
+/// Flat per-stage attribution of the sampled iterations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProfileReport {
+    pub enabled: bool,
+    /// Iterations sampled.
+    pub samples: u64,
+    /// Cycles charged to each stage, by `Stage` index.
+    pub cycles: [u64; Stage::COUNT],
+}
+
+impl ProfileReport {
+    /// Share of the sampled cycles spent in `stage`, in basis points.
+    pub fn share_bps(&self, stage: Stage) -> u64 {
+        let total: u64 = self.cycles.iter().sum();
+        (self.cycles[stage as usize] * 10_000)
+            .checked_div(total)
+            .unwrap_or(0)
+    }
+
+    /// Mean cycles per sampled iteration spent in `stage`.
+    pub fn mean_cycles(&self, stage: Stage) -> u64 {
+        self.cycles[stage as usize]
+            .checked_div(self.samples)
+            .unwrap_or(0)
+    }
+}
+
+/// One line: `on samples=N poll=41.20%/830cyc victims=...`, stages by
+/// share, mean cycles per sampled iteration.
+impl core::fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} samples={}",
+            if self.enabled { "on" } else { "off" },
+            self.samples
+        )?;
+        let mut stages = Stage::ALL;
+        stages.sort_by_key(|&stage| core::cmp::Reverse(self.cycles[stage as usize]));
+        for stage in stages {
+            let bps = self.share_bps(stage);
+            write!(
+                f,
+                " {}={}.{:02}%/{}cyc",
+                stage.name(),
+                bps / 100,
+                bps % 100,
+                self.mean_cycles(stage)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_are_charged_on_sampled_iterations_only() {
+        let mut profiler = StageProfiler::new(2);
+        profiler.begin(0);
+        profiler.enter(Stage::Poll);
+        profiler.end();
+        assert_eq!(profiler.report().samples, 0);
+
+        profiler.start();
+        for _ in 0..4 {
+            profiler.begin(runtime::now_cycles());
+            profiler.enter(Stage::Poll);
+            profiler.enter(Stage::Victims);
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            profiler.enter(Stage::Timers);
+            profiler.end();
+        }
+        profiler.stop();
+        let report = profiler.report();
+        assert_eq!(report.samples, 2);
+        assert!(report.share_bps(Stage::Victims) > 9_000, "{report}");
+        assert_eq!(report.cycles[Stage::Relay as usize], 0);
+        assert!(report.to_string().starts_with("off samples=2 victims="));
+    }
+
+    #[test]
+    fn shares_and_means_of_a_known_attribution() {
+        let mut cycles = [0; Stage::COUNT];
+        cycles[Stage::Poll as usize] = 250;
+        cycles[Stage::Executor as usize] = 750;
+        let report = ProfileReport {
+            enabled: true,
+            samples: 5,
+            cycles,
+        };
+        assert_eq!(
+            (
+                report.share_bps(Stage::Poll),
+                report.mean_cycles(Stage::Executor)
+            ),
+            (2_500, 150)
+        );
+        assert!(report.to_string().starts_with(
+            "on samples=5 executor=75.00%/150cyc poll=25.00%/50cyc victims=0.00%/0cyc"
+        ));
+        let empty = ProfileReport {
+            enabled: false,
+            samples: 0,
+            cycles: [0; Stage::COUNT],
+        };
+        assert_eq!(empty.share_bps(Stage::Poll), 0);
+    }
+}
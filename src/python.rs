@@ -0,0 +1,500 @@
+//! Python extension module for offline analysis (`python` feature).
+//!
+//! Exposes the production AMM math, wire decoders, journal reader and
+//! backtest to notebooks, so captured traffic is analysed with the same code
+//! the node runs:
+//!
+//! ```python
+//! import mev_zerocopy_node as mev
+//!
+//! pool = mev.AmmPool(10_000_000_000, 5_000_000_000)
+//! pool.sandwich_profit(victim_amount_in=500_000_000, our_amount_in=200_000_000, zero_for_one=True)
+//! for seq, payload in mev.read_records(open("relayer.bin", "rb").read()):
+//!     update = mev.decode_pool_update(payload)
+//!
+//! backtest = mev.Backtest(latencies_ns=[0, 1_000_000])
+//! backtest.add_pool(bytes.fromhex("aa" * 20), pool)
+//! for segment in mev.journal_segments("timeline.bin"):
+//!     verdicts = backtest.play(mev.read_segment(segment))
+//! backtest.finish()
+//! ```
+//!
+//! Every decoder returns a `dict` of the message's fields and raises
+//! `ValueError` for bytes that are not that message.
+use crate::backtest::{
+    Backtest, BacktestConfig, EventKind, ScenarioReport, Timeline, TimelineError,
+};
+use crate::journal;
+use crate::payload::{DexSwapTx, DexSwapTxEvm, DexSwapTxExt, OpportunityCancel, OpportunityReply};
+use crate::pool_registry::{PoolEntry, PoolTable};
+use crate::processor::{AmmPoolState, DEFAULT_EVALUATOR, MOCK_POOL, MOCK_POOL_TOKENS};
+use crate::session::RecordHeader;
+use crate::tokens::TokenId;
+use crate::validator::validate_pool_update;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict};
+use std::path::PathBuf;
+
+/// A constant-product pool; see [`AmmPoolState`]. Held as plain fields:
+/// Python objects cannot carry the state's cache-line alignment.
+#[pyclass(name = "AmmPool", frozen)]
+pub struct PyAmmPool {
+    reserve0: u64,
+    reserve1: u64,
+    fee_num: u64,
+    fee_den: u64,
+}
+
+impl PyAmmPool {
+    fn state(&self) -> AmmPoolState {
+        AmmPoolState::new(self.reserve0, self.reserve1, self.fee_num, self.fee_den)
+    }
+}
+
+#[pymethods]
+impl PyAmmPool {
+    #[new]
+    #[pyo3(signature = (reserve0, reserve1, fee_num = MOCK_POOL.fee_num, fee_den = MOCK_POOL.fee_den))]
+    fn new(reserve0: u64, reserve1: u64, fee_num: u64, fee_den: u64) -> Self {
+        Self {
+            reserve0,
+            reserve1,
+            fee_num,
+            fee_den,
+        }
+    }
+
+    #[getter]
+    fn reserve0(&self) -> u64 {
+        self.reserve0
+    }
+
+    #[getter]
+    fn reserve1(&self) -> u64 {
+        self.reserve1
+    }
+
+    /// Output for `amount_in`, or `None` if the swap yields nothing.
+    fn amount_out(&self, amount_in: u64, zero_for_one: bool) -> Option<u64> {
+        self.state().get_amount_out(amount_in, zero_for_one)
+    }
+
+    /// Input needed for exactly `amount_out`, or `None` if the pool cannot
+    /// provide it.
+    fn amount_in(&self, amount_out: u64, zero_for_one: bool) -> Option<u64> {
+        self.state().get_amount_in(amount_out, zero_for_one)
+    }
+
+    /// Profit of front-running `victim_amount_in` with `our_amount_in`, or
+    /// `None` if the sandwich loses.
+    fn sandwich_profit(
+        &self,
+        victim_amount_in: u64,
+        our_amount_in: u64,
+        zero_for_one: bool,
+    ) -> Option<u64> {
+        self.state()
+            .sandwich_profit(victim_amount_in, our_amount_in, zero_for_one)
+    }
+
+    fn __repr__(&self) -> String {
+        let Self {
+            reserve0,
+            reserve1,
+            fee_num,
+            fee_den,
+        } = self;
+        format!("AmmPool({reserve0}, {reserve1}, fee_num={fee_num}, fee_den={fee_den})")
+    }
+}
+
+fn malformed(what: &str, len: usize) -> PyErr {
+    PyValueError::new_err(format!("{len} bytes are not a {what}"))
+}
+
+/// A `DexSwapTx`, `DexSwapTxExt` or `DexSwapTxEvm`, told apart by length
+/// (exactly 48, 64 or 96 bytes).
+#[pyfunction]
+fn decode_swap<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+    let (base, ext, evm) = match data.len() {
+        DexSwapTx::WIRE_SIZE => (DexSwapTx::header(data), None, None),
+        DexSwapTxExt::WIRE_SIZE => {
+            let ext = DexSwapTxExt::parse(data);
+            (ext.map(|e| &e.base), ext, None)
+        }
+        DexSwapTxEvm::WIRE_SIZE => {
+            let evm = DexSwapTxEvm::parse(data);
+            (evm.map(|e| &e.ext.base), evm.map(|e| &e.ext), evm)
+        }
+        _ => (None, None, None),
+    };
+    let tx = base.ok_or_else(|| malformed("swap", data.len()))?;
+    let out = PyDict::new(py);
+    out.set_item("nonce", tx.nonce())?;
+    out.set_item("pool", PyBytes::new(py, &tx.pool_address))?;
+    out.set_item("amount_in", tx.amount_in())?;
+    out.set_item("min_amount_out", tx.min_amount_out())?;
+    out.set_item("zero_for_one", tx.token_direction == 0)?;
+    if let Some(ext) = ext {
+        out.set_item("deadline_ms", ext.deadline_ms())?;
+        out.set_item("priority_fee", ext.priority_fee())?;
+    }
+    if let Some(evm) = evm {
+        out.set_item("sender", PyBytes::new(py, &evm.sender))?;
+        out.set_item("gas_price", evm.gas_price())?;
+    }
+    Ok(out)
+}
+
+/// A `PoolStateUpdate`, checked as the node checks it; `last_seq` other
+/// than 0 also requires `seq == last_seq + 1`.
+#[pyfunction]
+#[pyo3(signature = (data, last_seq = 0))]
+fn decode_pool_update<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    last_seq: u32,
+) -> PyResult<Bound<'py, PyDict>> {
+    let update = validate_pool_update(data, last_seq)
+        .map_err(|e| PyValueError::new_err(format!("invalid pool update: {e:?}")))?;
+    let out = PyDict::new(py);
+    out.set_item("pool", PyBytes::new(py, &update.pool_address))?;
+    out.set_item("reserve0", update.reserve0())?;
+    out.set_item("reserve1", update.reserve1())?;
+    out.set_item("slot", update.slot())?;
+    out.set_item("seq", update.seq())?;
+    out.set_item("version", update.version())?;
+    if update.version() >= 2 {
+        out.set_item("fee_ppm", update.fee_ppm.get())?;
+    }
+    Ok(out)
+}
+
+/// An `OpportunityReply` (exactly 32 bytes).
+#[pyfunction]
+fn decode_opportunity_reply<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+    let reply = bytemuck::try_from_bytes::<OpportunityReply>(data)
+        .map_err(|_| malformed("opportunity reply", data.len()))?;
+    let out = PyDict::new(py);
+    out.set_item("nonce", reply.nonce())?;
+    out.set_item("profit", reply.profit())?;
+    out.set_item("rx_ts_ns", reply.rx_ts_ns())?;
+    out.set_item("tx_ts_ns", reply.tx_ts_ns())?;
+    out.set_item("confidence_bps", reply.confidence_bps())?;
+    Ok(out)
+}
+
+/// An `OpportunityCancel` (exactly 48 bytes).
+#[pyfunction]
+fn decode_opportunity_cancel<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyDict>> {
+    let cancel = OpportunityCancel::parse(data)
+        .ok_or_else(|| malformed("opportunity cancel", data.len()))?;
+    let out = PyDict::new(py);
+    out.set_item("sender", PyBytes::new(py, &cancel.sender))?;
+    out.set_item("nonce", cancel.nonce())?;
+    out.set_item("reason", cancel.reason)?;
+    out.set_item("tx_ts_ns", cancel.tx_ts_ns())?;
+    Ok(out)
+}
+
+/// Split a captured relayer session stream (the bytes after the welcome)
+/// into `(seq, payload)` pairs; a truncated last record raises.
+#[pyfunction]
+fn read_records<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Vec<(u64, Bound<'py, PyBytes>)>> {
+    let mut records = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let offset = data.len() - rest.len();
+        let truncated = || PyValueError::new_err(format!("truncated record at byte {offset}"));
+        let header: &RecordHeader = rest
+            .get(..RecordHeader::WIRE_SIZE)
+            .map(bytemuck::from_bytes)
+            .ok_or_else(truncated)?;
+        let end = RecordHeader::WIRE_SIZE + header.len();
+        let payload = rest
+            .get(RecordHeader::WIRE_SIZE..end)
+            .ok_or_else(truncated)?;
+        records.push((header.seq(), PyBytes::new(py, payload)));
+        rest = &rest[end..];
+    }
+    Ok(records)
+}
+
+/// The segments of the journal at `path`, oldest first, the active one
+/// last; see [`journal::segments`].
+#[pyfunction]
+fn journal_segments(path: PathBuf) -> PyResult<Vec<PathBuf>> {
+    Ok(journal::segments(path)?)
+}
+
+/// One journal segment's timeline, decompressed if it is zstd.
+#[pyfunction]
+fn read_segment(py: Python<'_>, path: PathBuf) -> PyResult<Bound<'_, PyBytes>> {
+    Ok(PyBytes::new(py, &journal::read_segment(path)?))
+}
+
+fn damaged(e: TimelineError) -> PyErr {
+    PyValueError::new_err(format!("damaged timeline: {e}"))
+}
+
+fn event_name(kind: EventKind) -> &'static str {
+    match kind {
+        EventKind::PoolUpdate => "pool_update",
+        EventKind::Victim => "victim",
+        EventKind::Decision => "decision",
+    }
+}
+
+/// Split a timeline (a journal segment) into `(kind, rx_ts_ns, payload)`
+/// triples, `kind` one of `"pool_update"`, `"victim"` and `"decision"`; a
+/// damaged record raises.
+#[pyfunction]
+fn read_timeline<'py>(
+    py: Python<'py>,
+    data: &[u8],
+) -> PyResult<Vec<(&'static str, u64, Bound<'py, PyBytes>)>> {
+    Timeline::parse(data)
+        .map_err(damaged)?
+        .map(|event| {
+            let event = event.map_err(damaged)?;
+            let payload = PyBytes::new(py, event.payload);
+            Ok((event_name(event.kind), event.rx_ts_ns, payload))
+        })
+        .collect()
+}
+
+/// Timelines played through the production evaluator; see [`Backtest`].
+/// The pool table starts empty: `add_pool` the pools the timeline trades
+/// before playing it.
+#[pyclass(name = "Backtest")]
+pub struct PyBacktest {
+    backtest: Backtest,
+    pools: &'static PoolTable,
+}
+
+fn report<'py>(py: Python<'py>, report: &ScenarioReport) -> PyResult<Bound<'py, PyDict>> {
+    let out = PyDict::new(py);
+    out.set_item("latency_ns", report.latency_ns)?;
+    out.set_item("opportunities", report.opportunities)?;
+    out.set_item("moved", report.moved)?;
+    out.set_item("raced", report.totals.emitted)?;
+    out.set_item("landed", report.totals.landed)?;
+    out.set_item("fill_bps", report.fill_bps())?;
+    out.set_item("pnl", report.totals.pnl)?;
+    Ok(out)
+}
+
+#[pymethods]
+impl PyBacktest {
+    #[new]
+    #[pyo3(signature = (latencies_ns = None, gas_cost = 0))]
+    fn new(latencies_ns: Option<Vec<u64>>, gas_cost: u64) -> Self {
+        let defaults = BacktestConfig::default();
+        let config = BacktestConfig {
+            latencies_ns: latencies_ns.unwrap_or(defaults.latencies_ns),
+            gas_cost,
+            ..defaults
+        };
+        // One table per backtest, for the process's lifetime, as the node
+        // gives one to each replay.
+        let pools: &'static PoolTable = Box::leak(Box::new(PoolTable::new()));
+        let evaluator = DEFAULT_EVALUATOR.with_pools(pools);
+        Self {
+            backtest: Backtest::new(evaluator, pools, &config),
+            pools,
+        }
+    }
+
+    /// Add (or replace) the pool at the 20-byte `address`.
+    #[pyo3(signature = (address, pool, token0 = MOCK_POOL_TOKENS.0, token1 = MOCK_POOL_TOKENS.1))]
+    fn add_pool(
+        &self,
+        address: &[u8],
+        pool: PyRef<'_, PyAmmPool>,
+        token0: TokenId,
+        token1: TokenId,
+    ) -> PyResult<()> {
+        let address = address
+            .try_into()
+            .map_err(|_| malformed("pool address", address.len()))?;
+        let entry = PoolEntry::new(address, token0, token1, pool.state());
+        match self.pools.upsert(entry) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(PyValueError::new_err("the zero address is not a pool")),
+        }
+    }
+
+    /// Play a timeline, continuing the previous one, and return
+    /// `(nonce, profit)` for each victim, `profit` `None` without a reply.
+    /// A damaged record raises; the records before it stay played.
+    fn play(&mut self, data: &[u8]) -> PyResult<Vec<(u64, Option<u64>)>> {
+        let mut verdicts = Vec::new();
+        for event in Timeline::parse(data).map_err(damaged)? {
+            if let Some(verdict) = self.backtest.replay(event.map_err(damaged)?) {
+                verdicts.push((verdict.nonce, verdict.profit));
+            }
+        }
+        Ok(verdicts)
+    }
+
+    /// Settle what is still pending, after the last timeline.
+    fn finish(&mut self) {
+        self.backtest.finish();
+    }
+
+    /// One `dict` per latency scenario, in configured order.
+    fn reports<'py>(&self, py: Python<'py>) -> PyResult<Vec<Bound<'py, PyDict>>> {
+        self.backtest
+            .reports()
+            .iter()
+            .map(|r| report(py, r))
+            .collect()
+    }
+
+    #[getter]
+    fn pool_updates(&self) -> u64 {
+        self.backtest.pool_updates
+    }
+
+    #[getter]
+    fn pool_rejects(&self) -> u64 {
+        self.backtest.pool_rejects
+    }
+
+    #[getter]
+    fn victims(&self) -> u64 {
+        self.backtest.victims
+    }
+
+    #[getter]
+    fn opportunities(&self) -> u64 {
+        self.backtest.opportunities
+    }
+}
+
+#[pymodule]
+fn mev_zerocopy_node(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyAmmPool>()?;
+    m.add_class::<PyBacktest>()?;
+    m.add_function(wrap_pyfunction!(decode_swap, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_pool_update, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_opportunity_reply, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_opportunity_cancel, m)?)?;
+    m.add_function(wrap_pyfunction!(read_records, m)?)?;
+    m.add_function(wrap_pyfunction!(journal_segments, m)?)?;
+    m.add_function(wrap_pyfunction!(read_segment, m)?)?;
+    m.add_function(wrap_pyfunction!(read_timeline, m)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::TimelineWriter;
+    use crate::testvectors;
+
+    fn vector(name: &str, buf: &mut [u8]) -> usize {
+        let vector = testvectors::find(name).unwrap();
+        vector.decode(buf).unwrap().len()
+    }
+
+    fn field<'py, T: FromPyObject<'py>>(dict: &Bound<'py, PyDict>, key: &str) -> T {
+        dict.get_item(key).unwrap().unwrap().extract().unwrap()
+    }
+
+    #[test]
+    fn decodes_the_swap_vector() {
+        pyo3::prepare_freethreaded_python();
+        let mut buf = [0u8; 128];
+        let len = vector("dex_swap_tx", &mut buf);
+        Python::with_gil(|py| {
+            let swap = decode_swap(py, &buf[..len]).unwrap();
+            assert_eq!(field::<u64>(&swap, "nonce"), 0x0102_0304_0506_0708);
+            assert_eq!(
+                field::<Vec<u8>>(&swap, "pool"),
+                (0xa0..=0xb3).collect::<Vec<u8>>()
+            );
+            assert_eq!(
+                (
+                    field::<u64>(&swap, "amount_in"),
+                    field::<u64>(&swap, "min_amount_out")
+                ),
+                (1_500_000_000, 742_000_000)
+            );
+            assert!(!field::<bool>(&swap, "zero_for_one"));
+            assert!(decode_swap(py, &buf[..len - 1]).is_err());
+        });
+    }
+
+    #[test]
+    fn decodes_the_pool_update_vector() {
+        pyo3::prepare_freethreaded_python();
+        let mut buf = [0u8; 128];
+        let len = vector("pool_state_update_v2", &mut buf);
+        Python::with_gil(|py| {
+            let update = decode_pool_update(py, &buf[..len], 0).unwrap();
+            assert_eq!(
+                (
+                    field::<u64>(&update, "reserve0"),
+                    field::<u64>(&update, "reserve1"),
+                    field::<u64>(&update, "slot"),
+                    field::<u32>(&update, "seq")
+                ),
+                (10_000_000_000, 5_000_000_000, 250_000_000, 42)
+            );
+            assert_eq!(field::<u32>(&update, "fee_ppm"), 3_000);
+            // Out of sequence after seq 7.
+            assert!(decode_pool_update(py, &buf[..len], 7).is_err());
+        });
+    }
+
+    #[test]
+    fn replays_a_journal() {
+        const POOL: [u8; 20] = [0xaa; 20];
+        let dir = std::env::temp_dir().join(format!("mev-python-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("timeline.bin");
+        let victim = DexSwapTx::from_parts(1, POOL, 500_000_000, 0, 0);
+        let dust = DexSwapTx::from_parts(2, POOL, 1, 0, 0);
+        // A rotated segment with the victim, the active one with dust.
+        for (segment, tx, ts) in [
+            (dir.join("timeline.bin.0000000000000001"), victim, 1_000),
+            (path.clone(), dust, 2_000),
+        ] {
+            let mut writer = TimelineWriter::new(std::fs::File::create(segment).unwrap()).unwrap();
+            writer
+                .record(EventKind::Victim, ts, bytemuck::bytes_of(&tx))
+                .unwrap();
+            writer.flush().unwrap();
+        }
+
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let mut backtest = PyBacktest::new(Some(vec![0]), 0);
+            let state = MOCK_POOL;
+            let pool = PyAmmPool::new(state.reserve0, state.reserve1, state.fee_num, state.fee_den);
+            let pool = Bound::new(py, pool).unwrap();
+            let (token0, token1) = MOCK_POOL_TOKENS;
+            backtest
+                .add_pool(&POOL, pool.borrow(), token0, token1)
+                .unwrap();
+            let segments = journal_segments(path.clone()).unwrap();
+            assert_eq!(segments.len(), 2);
+            let mut verdicts = Vec::new();
+            for segment in segments {
+                let bytes = read_segment(py, segment).unwrap();
+                let events = read_timeline(py, bytes.as_bytes()).unwrap();
+                assert_eq!(events[0].0, "victim");
+                verdicts.extend(backtest.play(bytes.as_bytes()).unwrap());
+            }
+            backtest.finish();
+            assert!(matches!(verdicts[..], [(1, Some(profit)), (2, None)] if profit > 0));
+            assert_eq!((backtest.victims(), backtest.opportunities()), (2, 1));
+            let reports = backtest.reports(py).unwrap();
+            assert_eq!(field::<u64>(&reports[0], "opportunities"), 1);
+            assert!(backtest.play(b"pcap").is_err());
+        });
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
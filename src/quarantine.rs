@@ -0,0 +1,243 @@
+//! Quarantine of malformed frames, for debugging relayer encoders.
+//!
+//! A frame that fails parsing or validation is normally just counted. The
+//! quarantine also keeps the first [`QUARANTINE_BYTES`] bytes of the most
+//! recent ones, with where they came from and why they were refused. The
+//! admin socket dumps it (`quarantine`), so an encoding bug shows up as the
+//! offending bytes rather than as a rising reject counter.
+//!
+//! Capturing is a copy of at most [`QUARANTINE_BYTES`] bytes into a
+//! pre-allocated ring, and only happens on the reject path.
+use crate::orderbook::BookRejection;
+use crate::prices::PriceRejection;
+use crate::validator::ValidationError;
+
+/// Leading bytes kept of each frame.
+pub const QUARANTINE_BYTES: usize = 128;
+/// Frames kept; older ones are overwritten.
+pub const QUARANTINE_DEPTH: usize = 64;
+
+/// Why a frame was quarantined.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Malformed {
+    /// Victim shorter than a `DexSwapTx`.
+    ShortVictim,
+    /// `PoolStateUpdate` that failed validation or decoding.
+    PoolUpdate(ValidationError),
+    /// `BookUpdate` with unordered levels or a crossed book.
+    BookUpdate,
+    /// `PriceUpdate` for an unknown token or with a zero price.
+    PriceUpdate(PriceRejection),
+    /// IPv4 header or UDP checksum mismatch.
+    Checksum,
+}
+
+impl Malformed {
+    /// Stable numeric code, for tooling that reads dumps.
+    pub const fn code(&self) -> u8 {
+        match self {
+            Self::ShortVictim => 1,
+            Self::PoolUpdate(ValidationError::TooShort) => 10,
+            Self::PoolUpdate(ValidationError::LayoutMismatch) => 11,
+            Self::PoolUpdate(ValidationError::ZeroReserves) => 12,
+            Self::PoolUpdate(ValidationError::SequenceGap { .. }) => 13,
+            Self::PoolUpdate(ValidationError::UnsupportedVersion(_)) => 14,
+            Self::PoolUpdate(ValidationError::FeeOutOfRange(_)) => 15,
+            Self::BookUpdate => 20,
+            Self::PriceUpdate(_) => 30,
+            Self::Checksum => 40,
+        }
+    }
+
+    /// The rejection of a book update, if it means the bytes are bad rather
+    /// than late or without room.
+    pub const fn from_book(rejection: BookRejection) -> Option<Self> {
+        match rejection {
+            BookRejection::Malformed => Some(Self::BookUpdate),
+            BookRejection::Stale { .. } | BookRejection::Full => None,
+        }
+    }
+
+    /// Likewise for a price update.
+    pub const fn from_price(rejection: PriceRejection) -> Option<Self> {
+        match rejection {
+            PriceRejection::UnknownToken | PriceRejection::ZeroPrice => {
+                Some(Self::PriceUpdate(rejection))
+            }
+            PriceRejection::Stale { .. } => None,
+        }
+    }
+}
+
+impl core::fmt::Display for Malformed {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::ShortVictim => write!(f, "short victim"),
+            Self::PoolUpdate(e) => write!(f, "pool update {e:?}"),
+            Self::BookUpdate => write!(f, "malformed book"),
+            Self::PriceUpdate(e) => write!(f, "price update {e:?}"),
+            Self::Checksum => write!(f, "checksum mismatch"),
+        }
+    }
+}
+
+/// One quarantined frame.
+#[derive(Clone, Copy, Debug)]
+pub struct QuarantinedFrame {
+    pub reason: Malformed,
+    pub rx_ts_ns: u64,
+    /// Sender `ip:port`.
+    pub source: ([u8; 4], u16),
+    /// Local port the frame arrived on.
+    pub port: u16,
+    /// Length of the whole frame; only the first `captured` bytes are kept.
+    pub len: usize,
+    pub captured: usize,
+    pub bytes: [u8; QUARANTINE_BYTES],
+}
+
+impl QuarantinedFrame {
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.captured]
+    }
+}
+
+/// Ring of the last [`QUARANTINE_DEPTH`] malformed frames; single writer.
+pub struct Quarantine {
+    frames: [Option<QuarantinedFrame>; QUARANTINE_DEPTH],
+    next: usize,
+    total: u64,
+}
+
+impl Default for Quarantine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Quarantine {
+    pub const fn new() -> Self {
+        Self {
+            frames: [None; QUARANTINE_DEPTH],
+            next: 0,
+            total: 0,
+        }
+    }
+
+    /// Keep the head of `frame`, overwriting the oldest entry when full.
+    pub fn capture(
+        &mut self,
+        reason: Malformed,
+        rx_ts_ns: u64,
+        source: ([u8; 4], u16),
+        port: u16,
+        frame: &[u8],
+    ) {
+        let captured = frame.len().min(QUARANTINE_BYTES);
+        let mut bytes = [0u8; QUARANTINE_BYTES];
+        bytes[..captured].copy_from_slice(&frame[..captured]);
+        self.frames[self.next] = Some(QuarantinedFrame {
+            reason,
+            rx_ts_ns,
+            source,
+            port,
+            len: frame.len(),
+            captured,
+            bytes,
+        });
+        self.next = (self.next + 1) % QUARANTINE_DEPTH;
+        self.total += 1;
+    }
+
+    /// Frames captured since start or the last [`clear`](Self::clear),
+    /// including ones since overwritten.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Kept frames, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &QuarantinedFrame> {
+        let (newer, older) = self.frames.split_at(self.next);
+        older.iter().chain(newer).flatten()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// One line of JSON: `{"total":N,"frames":[{"code":..,"reason":"..",..,"bytes":"<hex>"},..]}`.
+impl core::fmt::Display for Quarantine {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{{\"total\":{},\"frames\":[", self.total)?;
+        for (i, frame) in self.iter().enumerate() {
+            let ([a, b, c, d], port) = frame.source;
+            write!(
+                f,
+                "{}{{\"code\":{},\"reason\":\"{}\",\"rx_ts_ns\":{},\"source\":\"{a}.{b}.{c}.{d}:{port}\",\"port\":{},\"len\":{},\"bytes\":\"",
+                if i > 0 { "," } else { "" },
+                frame.reason.code(),
+                frame.reason,
+                frame.rx_ts_ns,
+                frame.port,
+                frame.len
+            )?;
+            for byte in frame.bytes() {
+                write!(f, "{byte:02x}")?;
+            }
+            write!(f, "\"}}")?;
+        }
+        write!(f, "]}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_keeps_the_latest_heads_oldest_first() {
+        let mut quarantine = Quarantine::new();
+        assert_eq!(quarantine.to_string(), "{\"total\":0,\"frames\":[]}");
+        let long = [0xab; QUARANTINE_BYTES + 40];
+        for i in 0..QUARANTINE_DEPTH + 2 {
+            quarantine.capture(
+                Malformed::ShortVictim,
+                i as u64,
+                ([10, 0, 0, 1], 9000),
+                8080,
+                &[i as u8; 3],
+            );
+        }
+        quarantine.capture(
+            Malformed::PoolUpdate(ValidationError::ZeroReserves),
+            99,
+            ([10, 0, 0, 2], 7000),
+            8081,
+            &long,
+        );
+
+        assert_eq!(quarantine.total(), QUARANTINE_DEPTH as u64 + 3);
+        let kept: Vec<_> = quarantine.iter().collect();
+        assert_eq!(kept.len(), QUARANTINE_DEPTH);
+        assert_eq!((kept[0].rx_ts_ns, kept[0].bytes()), (3, &[3u8; 3][..]));
+        let newest = kept[QUARANTINE_DEPTH - 1];
+        assert_eq!(
+            (newest.reason.code(), newest.len, newest.bytes().len()),
+            (12, QUARANTINE_BYTES + 40, QUARANTINE_BYTES)
+        );
+        let json = quarantine.to_string();
+        assert!(json.ends_with(&format!(
+            "{{\"code\":12,\"reason\":\"pool update ZeroReserves\",\"rx_ts_ns\":99,\"source\":\"10.0.0.2:7000\",\"port\":8081,\"len\":168,\"bytes\":\"{}\"}}]}}",
+            "ab".repeat(QUARANTINE_BYTES)
+        )));
+
+        quarantine.clear();
+        assert_eq!((quarantine.total(), quarantine.iter().count()), (0, 0));
+        assert_eq!(Malformed::from_book(BookRejection::Full), None);
+        assert_eq!(
+            Malformed::from_price(PriceRejection::ZeroPrice).map(|m| m.code()),
+            Some(30)
+        );
+    }
+}
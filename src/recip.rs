@@ -0,0 +1,172 @@
+//! Division by precomputed reciprocals.
+//!
+//! The constant-product output is a 128-by-64-bit division, which compiles
+//! to a `__udivti3` call: tens of cycles, and the largest single cost of a
+//! sandwich evaluation. With the `recip_math` feature the AMM math divides
+//! by a [`Reciprocal`] instead, after Möller and Granlund, "Improved division
+//! by invariant integers" (2011):
+//!
+//! - the reciprocal of a divisor is seeded from a 256-entry table indexed by
+//!   its top bits and refined by Newton iterations, all multiply and shift;
+//! - a quotient is then two multiplies and at most two corrections.
+//!
+//! Computing a reciprocal costs about what it saves on one division, so
+//! they pay off when cached: every pool keeps the reciprocals of its
+//! front-run leg's denominators (the front-run size is fixed), refreshed
+//! when its reserves change. The victim-sized legs compute theirs on the
+//! spot and still avoid the division call. Quotients are exact, not
+//! approximations; divisors above 64 bits fall back to `/`.
+
+/// `floor((2^19 - 3 * 2^8) / d9)` for every 9-bit `d9` with its top bit set:
+/// an 11-bit first approximation of the reciprocal.
+const SEED: [u16; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        table[i] = (((1 << 19) - 3 * (1 << 8)) / (256 + i as u32)) as u16;
+        i += 1;
+    }
+    table
+};
+
+/// `floor((2^128 - 1) / d) - 2^64` for a normalized `d` (top bit set).
+#[inline(always)]
+const fn reciprocal_word(d: u64) -> u64 {
+    let d0 = d & 1;
+    let d40 = (d >> 24) + 1;
+    let d63 = (d >> 1) + d0;
+    let v0 = SEED[(d >> 55) as usize - 256] as u64;
+    let v1 = (v0 << 11) - ((v0 * v0 * d40) >> 40) - 1;
+    let v2 = (v1 << 13) + ((v1 * ((1 << 60) - v1 * d40)) >> 47);
+    let e = ((v2 >> 1) & 0u64.wrapping_sub(d0)).wrapping_sub(v2.wrapping_mul(d63));
+    let v3 = (((v2 as u128 * e as u128) >> 65) as u64).wrapping_add(v2 << 31);
+    let hi = ((v3 as u128 * d as u128 + d as u128) >> 64) as u64;
+    v3.wrapping_sub(hi.wrapping_add(d))
+}
+
+/// A divisor and its reciprocal.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Reciprocal {
+    divisor: u64,
+    v: u64,
+}
+
+impl Reciprocal {
+    /// No divisor; every division by it fails.
+    pub const NONE: Self = Self { divisor: 0, v: 0 };
+
+    pub const fn new(divisor: u64) -> Self {
+        if divisor == 0 {
+            return Self::NONE;
+        }
+        Self {
+            divisor,
+            v: reciprocal_word(divisor << divisor.leading_zeros()),
+        }
+    }
+
+    pub const fn divisor(&self) -> u64 {
+        self.divisor
+    }
+
+    /// `n / divisor`; `None` for a zero divisor or a quotient beyond 64 bits.
+    #[inline(always)]
+    pub const fn div(&self, n: u128) -> Option<u64> {
+        if self.divisor == 0 {
+            return None;
+        }
+        let shift = self.divisor.leading_zeros();
+        if shift != 0 && n >> (128 - shift) != 0 {
+            return None;
+        }
+        let d = self.divisor << shift;
+        let n = n << shift;
+        let (u1, u0) = ((n >> 64) as u64, n as u64);
+        if u1 >= d {
+            return None;
+        }
+        let q = (self.v as u128 * u1 as u128).wrapping_add((((u1 + 1) as u128) << 64) | u0 as u128);
+        let (mut q1, q0) = ((q >> 64) as u64, q as u64);
+        let mut r = u0.wrapping_sub(q1.wrapping_mul(d));
+        if r > q0 {
+            q1 = q1.wrapping_sub(1);
+            r = r.wrapping_add(d);
+        }
+        if r >= d {
+            q1 += 1;
+        }
+        Some(q1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Edge divisors, then xorshift64 ones across the whole range, each
+    /// with a random dividend.
+    fn cases() -> impl Iterator<Item = (u64, u128)> {
+        let mut x = 0x9E37_79B9_7F4A_7C15u64;
+        let mut next = move || {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        };
+        let edges = [
+            1,
+            2,
+            3,
+            1_000,
+            (1 << 32) - 1,
+            1 << 63,
+            (1 << 63) + 1,
+            u64::MAX - 1,
+            u64::MAX,
+        ];
+        (0..20_000).map(move |i| {
+            let divisor = edges
+                .get(i)
+                .copied()
+                .unwrap_or_else(|| (next() >> (next() % 64)).max(1));
+            (divisor, (next() as u128) << (next() % 64))
+        })
+    }
+
+    #[test]
+    fn reciprocal_is_the_floor_of_its_definition() {
+        for (divisor, _) in cases() {
+            let d = divisor << divisor.leading_zeros();
+            assert_eq!(
+                Reciprocal::new(divisor).v as u128 + (1 << 64),
+                u128::MAX / d as u128,
+                "reciprocal of {divisor}"
+            );
+        }
+    }
+
+    #[test]
+    fn matches_exact_division() {
+        for (divisor, random) in cases() {
+            let recip = Reciprocal::new(divisor);
+            let widest = ((divisor as u128) << 64) - 1;
+            for n in [
+                0,
+                divisor as u128 - 1,
+                divisor as u128,
+                widest,
+                widest + 1,
+                random,
+            ] {
+                let exact = n / divisor as u128;
+                assert_eq!(recip.div(n), u64::try_from(exact).ok(), "{n} / {divisor}");
+            }
+        }
+    }
+
+    #[test]
+    fn quotients_past_u64_and_the_none_reciprocal_fail() {
+        assert_eq!(Reciprocal::new(3).div(u128::MAX), None);
+        assert_eq!(Reciprocal::NONE.div(7), None);
+    }
+}
@@ -0,0 +1,243 @@
+//! Sequence reordering window for the pool feed.
+//!
+//! When the same feed arrives over two paths (two relayers, or UDP plus the
+//! relayer TCP session), updates interleave: pool P's seq 8 can land before
+//! seq 7, and every update shows up twice. Fed straight into the validator,
+//! each inversion is a `SequenceGap` and the late update overwrites newer
+//! reserves.
+//!
+//! The window sits in front of the validator. An update that continues its
+//! pool's sequence is released at once, followed by any held successors. One
+//! that skips ahead is held, for at most `max_hold_cycles` or until the
+//! window is full, waiting for the missing update; after that it is released
+//! anyway and the gap goes to the validator as before. Updates at or behind
+//! their pool's sequence — the copy from the slower path — are dropped.
+use crate::pool_registry::PoolAddress;
+use crate::validator::PoolStateUpdate;
+
+/// Largest configurable window depth.
+pub const MAX_REORDER_DEPTH: usize = 64;
+
+#[derive(Clone, Copy, Debug)]
+struct Held {
+    update: PoolStateUpdate,
+    held_at: u64,
+}
+
+/// Counters since startup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ReorderStats {
+    /// Updates held for a predecessor and released in sequence.
+    pub reordered: u64,
+    /// Late or duplicate updates dropped.
+    pub dropped_late: u64,
+    /// Held updates released with their gap still open.
+    pub released_gaps: u64,
+}
+
+/// `N`: capacity. `depth` (at most `N`) and `max_hold_cycles` are runtime
+/// settings; a depth of 0 passes everything through.
+pub struct ReorderWindow<const N: usize> {
+    held: heapless::Vec<Held, N>,
+    depth: usize,
+    max_hold_cycles: u64,
+    pub stats: ReorderStats,
+}
+
+/// Whether `seq` is at or behind `last` (wrapping; 0 means no history).
+#[inline(always)]
+fn is_late(seq: u32, last: u32) -> bool {
+    last != 0 && (seq.wrapping_sub(last) as i32) <= 0
+}
+
+impl<const N: usize> ReorderWindow<N> {
+    pub fn new(depth: usize, max_hold_cycles: u64) -> Self {
+        Self {
+            held: heapless::Vec::new(),
+            depth: depth.min(N),
+            max_hold_cycles,
+            stats: ReorderStats::default(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.held.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+
+    /// Offer one update. `last_seq` is its pool's last applied sequence
+    /// (0 if none); `release` applies an update. `now` is in cycles.
+    pub fn offer(
+        &mut self,
+        update: &PoolStateUpdate,
+        last_seq: u32,
+        now: u64,
+        mut release: impl FnMut(&PoolStateUpdate),
+    ) {
+        let seq = update.seq();
+        if is_late(seq, last_seq) || self.is_held(&update.pool_address, seq) {
+            self.stats.dropped_late += 1;
+            return;
+        }
+        if self.depth == 0 || last_seq == 0 || seq == last_seq.wrapping_add(1) {
+            release(update);
+            self.release_successors(&update.pool_address, seq, &mut release);
+            return;
+        }
+        if self.held.len() >= self.depth {
+            self.release_oldest(&mut release);
+        }
+        let _ = self.held.push(Held {
+            update: *update,
+            held_at: now,
+        });
+    }
+
+    /// Release updates held longer than `max_hold_cycles`, gaps and all.
+    pub fn expire(&mut self, now: u64, mut release: impl FnMut(&PoolStateUpdate)) {
+        while let Some(i) = self
+            .held
+            .iter()
+            .position(|h| now.wrapping_sub(h.held_at) >= self.max_hold_cycles)
+        {
+            self.release_at(i, &mut release);
+        }
+    }
+
+    fn is_held(&self, pool: &PoolAddress, seq: u32) -> bool {
+        self.held
+            .iter()
+            .any(|h| h.update.pool_address == *pool && h.update.seq() == seq)
+    }
+
+    fn release_oldest(&mut self, release: &mut impl FnMut(&PoolStateUpdate)) {
+        let oldest = self
+            .held
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, h)| h.held_at)
+            .map(|(i, _)| i);
+        if let Some(i) = oldest {
+            self.release_at(i, release);
+        }
+    }
+
+    /// Release entry `i` ahead of its gap, then whatever follows it.
+    fn release_at(&mut self, i: usize, release: &mut impl FnMut(&PoolStateUpdate)) {
+        let held = self.held.swap_remove(i);
+        self.stats.released_gaps += 1;
+        release(&held.update);
+        self.release_successors(&held.update.pool_address, held.update.seq(), release);
+    }
+
+    fn release_successors(
+        &mut self,
+        pool: &PoolAddress,
+        mut seq: u32,
+        release: &mut impl FnMut(&PoolStateUpdate),
+    ) {
+        while let Some(i) = self
+            .held
+            .iter()
+            .position(|h| h.update.pool_address == *pool && h.update.seq() == seq.wrapping_add(1))
+        {
+            let held = self.held.swap_remove(i);
+            self.stats.reordered += 1;
+            release(&held.update);
+            seq = held.update.seq();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POOL: PoolAddress = [0xAA; 20];
+
+    fn update(pool: PoolAddress, seq: u32) -> PoolStateUpdate {
+        PoolStateUpdate::new(pool, 1_000, 2_000, seq as u64, seq)
+    }
+
+    /// Offers `seqs` to a window in front of a one-pool "validator".
+    fn run(
+        window: &mut ReorderWindow<8>,
+        seqs: &[(PoolAddress, u32)],
+        last: &mut [u32; 2],
+        out: &mut Vec<u32>,
+    ) {
+        for (i, &(pool, seq)) in seqs.iter().enumerate() {
+            let k = (pool != POOL) as usize;
+            let mut applied = Vec::new();
+            window.offer(&update(pool, seq), last[k], i as u64, |u| {
+                applied.push((u.pool_address, u.seq()))
+            });
+            for (p, s) in applied {
+                last[(p != POOL) as usize] = s;
+                out.push(s);
+            }
+        }
+    }
+
+    #[test]
+    fn interleaved_paths_come_out_in_order_once() {
+        let mut window = ReorderWindow::<8>::new(8, 1_000);
+        let (mut last, mut out) = ([0; 2], Vec::new());
+        // Path A: 1 2 3 4, path B: 1 3 2 4, interleaved and both delivered.
+        let seqs = [1, 1, 3, 2, 2, 3, 4, 4].map(|seq| (POOL, seq));
+        run(&mut window, &seqs, &mut last, &mut out);
+        assert_eq!(out, [1, 2, 3, 4]);
+        assert_eq!(
+            window.stats,
+            ReorderStats {
+                reordered: 1,
+                dropped_late: 4,
+                released_gaps: 0
+            }
+        );
+        assert!(window.is_empty());
+    }
+
+    #[test]
+    fn held_updates_expire_or_spill_with_their_gap() {
+        let other = [0xBB; 20];
+        let mut window = ReorderWindow::<8>::new(2, 100);
+        let (mut last, mut out) = ([5, 9], Vec::new());
+        // 7 and 8 wait for 6; the window is full when pool B's 11 arrives,
+        // so the oldest (7) is released with its gap, pulling 8 after it.
+        run(
+            &mut window,
+            &[(POOL, 7), (POOL, 8), (other, 11)],
+            &mut last,
+            &mut out,
+        );
+        assert_eq!(out, [7, 8]);
+        assert_eq!(window.len(), 1);
+
+        let mut expired = Vec::new();
+        window.expire(50, |u| expired.push(u.seq()));
+        assert!(expired.is_empty());
+        window.expire(102, |u| expired.push(u.seq()));
+        assert_eq!(expired, [11]);
+        assert_eq!(
+            window.stats,
+            ReorderStats {
+                reordered: 1,
+                dropped_late: 0,
+                released_gaps: 2
+            }
+        );
+    }
+
+    #[test]
+    fn zero_depth_passes_through() {
+        let mut window = ReorderWindow::<8>::new(0, 1_000);
+        let (mut last, mut out) = ([3, 0], Vec::new());
+        run(&mut window, &[(POOL, 6), (POOL, 5)], &mut last, &mut out);
+        assert_eq!(out, [6]);
+        assert_eq!(window.stats.dropped_late, 1);
+    }
+}
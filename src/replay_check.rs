@@ -0,0 +1,349 @@
+//! Determinism checks for journal replays.
+//!
+//! A refactor of the evaluator should not change what the node trades. The
+//! `replay-check` subcommand plays a journal through [`Backtest`] and
+//! compares its verdict on every victim with a reference, stopping at the
+//! first [`Divergence`]:
+//! - against a second replay in the same process ([`Lockstep`]), each with
+//!   its own pool table, which catches state leaking between runs and
+//!   anything else nondeterministic;
+//! - against a decision log another binary wrote with [`DecisionLog`], which
+//!   catches behavior changes between versions.
+//!
+//! A decision log is a timeline (see [`crate::backtest`]): every victim,
+//! followed by a decision record if the replay replied to it. [`Verdicts`]
+//! reads one back.
+use crate::backtest::{
+    Backtest, EventKind, Timeline, TimelineError, TimelineEvent, TimelineWriter, Verdict,
+};
+use crate::confidence::FULL_CONFIDENCE_BPS;
+use crate::payload::{DexSwapTx, OpportunityReply};
+use crate::pool_registry::PoolTable;
+use crate::processor::AmmPoolState;
+use std::io::{self, Write};
+
+/// The first victim two replays decided differently.
+#[derive(Clone, Debug)]
+pub struct Divergence {
+    /// Index of the victim among the journal's victims, from 0.
+    pub index: u64,
+    pub rx_ts_ns: u64,
+    pub victim: Option<DexSwapTx>,
+    pub expected: Verdict,
+    pub actual: Verdict,
+    /// The victim's pool in the reference replay's table; `None` if it was
+    /// unknown, or the reference is a decision log.
+    pub expected_pool: Option<AmmPoolState>,
+    /// The victim's pool in the checked replay's table.
+    pub actual_pool: Option<AmmPoolState>,
+}
+
+impl core::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let verdict = |v: &Verdict| match v.profit {
+            Some(profit) => format!("nonce={} reply profit={profit}", v.nonce),
+            None => format!("nonce={} no reply", v.nonce),
+        };
+        writeln!(
+            f,
+            "first divergence at victim #{} (rx_ts_ns={})",
+            self.index, self.rx_ts_ns
+        )?;
+        match &self.victim {
+            Some(tx) => writeln!(
+                f,
+                "  victim:   nonce={} pool={:02x?} amount_in={} min_amount_out={} zero_for_one={}",
+                tx.nonce(),
+                tx.pool_address,
+                tx.amount_in(),
+                tx.min_amount_out(),
+                tx.token_direction == 0
+            )?,
+            None => writeln!(f, "  victim:   malformed")?,
+        }
+        writeln!(f, "  expected: {}", verdict(&self.expected))?;
+        write!(f, "  actual:   {}", verdict(&self.actual))?;
+        for (run, pool) in [
+            ("expected", &self.expected_pool),
+            ("actual", &self.actual_pool),
+        ] {
+            if let Some(s) = pool {
+                let (r0, r1, fee_num, fee_den) = (s.reserve0, s.reserve1, s.fee_num, s.fee_den);
+                write!(
+                    f,
+                    "\n  pool ({run} run): reserve0={r0} reserve1={r1} fee={fee_num}/{fee_den}"
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Divergence {}
+
+/// The victim's pool in `pools`, without touching its eviction state.
+fn pool_state(pools: &PoolTable, victim: Option<&DexSwapTx>) -> Option<AmmPoolState> {
+    pools.peek(&victim?.pool_address).map(|entry| entry.state)
+}
+
+/// Compares a replay with a reference, one victim at a time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Comparison {
+    /// Victims compared so far.
+    pub victims: u64,
+    /// Of those, replied to.
+    pub replies: u64,
+}
+
+impl Comparison {
+    /// Compare the verdicts on the victim `event`. `pools` are the reference
+    /// replay's and the checked replay's tables, for context.
+    pub fn compare(
+        &mut self,
+        event: &TimelineEvent<'_>,
+        expected: Verdict,
+        actual: Verdict,
+        pools: (Option<&PoolTable>, &PoolTable),
+    ) -> Result<(), Box<Divergence>> {
+        let index = self.victims;
+        self.victims += 1;
+        if expected == actual {
+            self.replies += actual.profit.is_some() as u64;
+            return Ok(());
+        }
+        let victim = DexSwapTx::header(event.payload).copied();
+        Err(Box::new(Divergence {
+            index,
+            rx_ts_ns: event.rx_ts_ns,
+            victim,
+            expected,
+            actual,
+            expected_pool: pools.0.and_then(|p| pool_state(p, victim.as_ref())),
+            actual_pool: pool_state(pools.1, victim.as_ref()),
+        }))
+    }
+}
+
+/// Two replays of one journal, played event by event.
+pub struct Lockstep {
+    runs: [(Backtest, &'static PoolTable); 2],
+    pub comparison: Comparison,
+}
+
+impl Lockstep {
+    /// `runs`: two backtests, each reading its own pool table.
+    pub fn new(runs: [(Backtest, &'static PoolTable); 2]) -> Self {
+        Self {
+            runs,
+            comparison: Comparison::default(),
+        }
+    }
+
+    /// Play `event` in both; the verdict both reached on a victim.
+    pub fn replay(&mut self, event: TimelineEvent<'_>) -> Result<Option<Verdict>, Box<Divergence>> {
+        let [(first, first_pools), (second, second_pools)] = &mut self.runs;
+        match (first.replay(event), second.replay(event)) {
+            (Some(expected), Some(actual)) => {
+                self.comparison.compare(
+                    &event,
+                    expected,
+                    actual,
+                    (Some(*first_pools), second_pools),
+                )?;
+                Ok(Some(actual))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+/// Writes a replay's verdicts as a timeline.
+pub struct DecisionLog<W: Write> {
+    out: TimelineWriter<W>,
+}
+
+impl<W: Write> DecisionLog<W> {
+    pub fn new(out: W) -> io::Result<Self> {
+        Ok(Self {
+            out: TimelineWriter::new(out)?,
+        })
+    }
+
+    /// Log the verdict on the victim `event`.
+    pub fn record(&mut self, event: &TimelineEvent<'_>, verdict: Verdict) -> io::Result<()> {
+        self.out
+            .record(EventKind::Victim, event.rx_ts_ns, event.payload)?;
+        let Some(profit) = verdict.profit else {
+            return Ok(());
+        };
+        let reply = OpportunityReply {
+            nonce_le: verdict.nonce.to_le_bytes(),
+            profit_le: profit.to_le_bytes(),
+            rx_ts_ns_le: event.rx_ts_ns.to_le_bytes(),
+            tx_ts_ns_le: event.rx_ts_ns.to_le_bytes(),
+            confidence_bps_le: FULL_CONFIDENCE_BPS.to_le_bytes(),
+            _reserved: [0; 6],
+        };
+        self.out.record(
+            EventKind::Decision,
+            event.rx_ts_ns,
+            bytemuck::bytes_of(&reply),
+        )
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out.into_inner()
+    }
+}
+
+/// The verdicts of a decision log, in order.
+pub struct Verdicts<'a> {
+    timeline: core::iter::Peekable<Timeline<'a>>,
+}
+
+impl<'a> Verdicts<'a> {
+    pub fn new(timeline: Timeline<'a>) -> Self {
+        Self {
+            timeline: timeline.peekable(),
+        }
+    }
+}
+
+impl Iterator for Verdicts<'_> {
+    type Item = Result<Verdict, TimelineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let victim = loop {
+            match self.timeline.next()? {
+                Ok(event) if event.kind == EventKind::Victim => break event,
+                Ok(_) => continue,
+                Err(e) => return Some(Err(e)),
+            }
+        };
+        let nonce = DexSwapTx::header(victim.payload).map_or(0, |tx| tx.nonce());
+        let reply = match self.timeline.peek() {
+            Some(Ok(event)) if event.kind == EventKind::Decision => {
+                bytemuck::try_from_bytes::<OpportunityReply>(event.payload)
+                    .ok()
+                    .copied()
+            }
+            _ => None,
+        };
+        let profit = reply.filter(|r| r.nonce() == nonce).map(|r| r.profit());
+        if profit.is_some() {
+            self.timeline.next();
+        }
+        Some(Ok(Verdict { nonce, profit }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::BacktestConfig;
+    use crate::processor::{self, DEFAULT_EVALUATOR};
+    use crate::validator::PoolStateUpdate;
+    use zerocopy::IntoBytes;
+
+    const POOL: [u8; 20] = [0xaa; 20];
+
+    fn backtest() -> (Backtest, &'static PoolTable) {
+        let pools: &'static PoolTable = Box::leak(Box::new(PoolTable::new()));
+        pools.upsert(processor::mock_pool_entry(POOL)).unwrap();
+        let config = BacktestConfig {
+            latencies_ns: vec![0],
+            ..BacktestConfig::default()
+        };
+        (
+            Backtest::new(DEFAULT_EVALUATOR.with_pools(pools), pools, &config),
+            pools,
+        )
+    }
+
+    #[test]
+    fn replays_agree_with_each_other_and_with_their_decision_log() {
+        let state = processor::MOCK_POOL;
+        let mut journal = TimelineWriter::new(Vec::new()).unwrap();
+        let (big, small) = (
+            DexSwapTx::from_parts(1, POOL, 500_000_000, 0, 0),
+            DexSwapTx::from_parts(2, POOL, 1, 0, 0),
+        );
+        journal
+            .record(EventKind::Victim, 1_000, bytemuck::bytes_of(&big))
+            .unwrap();
+        journal
+            .record(EventKind::Victim, 2_000, bytemuck::bytes_of(&small))
+            .unwrap();
+        let deep = PoolStateUpdate::new(
+            POOL,
+            state.reserve0 * 1_000_000,
+            state.reserve1 * 1_000_000,
+            2,
+            1,
+        );
+        journal
+            .record(EventKind::PoolUpdate, 3_000, deep.as_bytes())
+            .unwrap();
+        journal
+            .record(EventKind::Victim, 4_000, bytemuck::bytes_of(&big))
+            .unwrap();
+        let journal = journal.into_inner();
+
+        // Twice in lockstep, logging the verdicts.
+        let mut lockstep = Lockstep::new([backtest(), backtest()]);
+        let mut log = DecisionLog::new(Vec::new()).unwrap();
+        for event in Timeline::parse(&journal).unwrap() {
+            let event = event.unwrap();
+            if let Some(verdict) = lockstep.replay(event).unwrap() {
+                log.record(&event, verdict).unwrap();
+            }
+        }
+        assert_eq!(
+            (lockstep.comparison.victims, lockstep.comparison.replies),
+            (3, 1)
+        );
+
+        // A replay whose pool never deepened replies to the last victim too.
+        let log = log.into_inner();
+        let expected: Vec<Verdict> = Verdicts::new(Timeline::parse(&log).unwrap())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            expected
+                .iter()
+                .map(|v| v.profit.is_some())
+                .collect::<Vec<_>>(),
+            [true, false, false]
+        );
+        let (mut shallow, pools) = backtest();
+        let mut comparison = Comparison::default();
+        let mut expected = expected.into_iter();
+        let divergence = Timeline::parse(&journal)
+            .unwrap()
+            .map(Result::unwrap)
+            .filter(|event| event.kind != EventKind::PoolUpdate)
+            .find_map(|event| {
+                let actual = shallow.replay(event)?;
+                comparison
+                    .compare(&event, expected.next()?, actual, (None, pools))
+                    .err()
+            })
+            .expect("diverges");
+        assert_eq!((divergence.index, divergence.rx_ts_ns), (2, 4_000));
+        assert!(divergence.expected.profit.is_none() && divergence.actual.profit.is_some());
+        assert_eq!(
+            divergence.actual_pool.map(|s| s.reserve0),
+            Some(state.reserve0)
+        );
+        let report = divergence.to_string();
+        assert!(
+            report.starts_with("first divergence at victim #2 (rx_ts_ns=4000)"),
+            "{report}"
+        );
+        assert!(report.contains("expected: nonce=1 no reply"), "{report}");
+    }
+}
@@ -0,0 +1,309 @@
+//! Reply destination routing.
+//!
+//! By default a reply or cancellation goes back to where the victim came
+//! from. `MEV_REPLY_ROUTES` overrides that per (strategy, pool, message
+//! kind): each rule names the executors its messages go to, several for a
+//! fan-out. Rules are `;`-separated and the first match wins:
+//!
+//! ```text
+//! <strategy|*>/<pool|*>/<reply|cancel|*>=<ip:port>[+<ip:port>...]
+//! ```
+//!
+//! Destinations must be `MEV_EXECUTORS` entries, whose pinned MACs let the
+//! Ethernet/IPv4/UDP headers of every (destination, kind) be built once at
+//! config load; the DPDK path copies them in front of the payload instead
+//! of addressing each frame.
+use crate::dpdk::{build_headers, UdpEndpoint, HEADERS_LEN};
+use crate::payload::{OpportunityCancel, OpportunityReply};
+use crate::pool_registry::PoolAddress;
+use crate::processor::Strategy;
+
+/// Maximum number of routing rules.
+pub const MAX_ROUTES: usize = 16;
+/// Maximum destinations of one rule.
+pub const MAX_FANOUT: usize = 4;
+/// Maximum distinct destinations across all rules.
+pub const MAX_DESTINATIONS: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    Reply = 0,
+    Cancel = 1,
+}
+
+impl MessageKind {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "reply" => Some(Self::Reply),
+            "cancel" => Some(Self::Cancel),
+            _ => None,
+        }
+    }
+
+    pub const fn wire_size(self) -> usize {
+        match self {
+            Self::Reply => OpportunityReply::WIRE_SIZE,
+            Self::Cancel => OpportunityCancel::WIRE_SIZE,
+        }
+    }
+}
+
+/// An executor endpoint with its pre-built headers, one per message kind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReplyDestination {
+    pub endpoint: UdpEndpoint,
+    headers: [[u8; HEADERS_LEN]; 2],
+}
+
+impl ReplyDestination {
+    /// Headers of a `kind` message from the node to this destination.
+    #[inline(always)]
+    pub fn headers(&self, kind: MessageKind) -> &[u8; HEADERS_LEN] {
+        &self.headers[kind as usize]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RouteRule {
+    strategy: Option<Strategy>,
+    pool: Option<PoolAddress>,
+    kind: Option<MessageKind>,
+    to: heapless::Vec<u8, MAX_FANOUT>,
+}
+
+impl RouteRule {
+    fn matches(&self, strategy: Strategy, pool: &PoolAddress, kind: MessageKind) -> bool {
+        self.strategy.is_none_or(|s| s == strategy)
+            && self.pool.as_ref().is_none_or(|p| p == pool)
+            && self.kind.is_none_or(|k| k == kind)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RouteError {
+    /// A rule is not `<strategy>/<pool>/<kind>=<destinations>`.
+    Syntax,
+    UnknownStrategy,
+    InvalidPool,
+    UnknownKind,
+    /// A destination is not `ip:port`.
+    InvalidDestination,
+    /// A destination is not among `MEV_EXECUTORS`, so its MAC is unknown.
+    UnknownExecutor([u8; 4]),
+    /// More than [`MAX_ROUTES`] rules, [`MAX_FANOUT`] destinations in a
+    /// rule or [`MAX_DESTINATIONS`] in total.
+    TooMany,
+}
+
+impl core::fmt::Display for RouteError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Syntax => write!(f, "expected <strategy|*>/<pool|*>/<reply|cancel|*>=<ip:port>[+<ip:port>...]"),
+            Self::UnknownStrategy => write!(f, "unknown strategy"),
+            Self::InvalidPool => write!(f, "pool is not a 20-byte hex address"),
+            Self::UnknownKind => write!(f, "message kind is not reply or cancel"),
+            Self::InvalidDestination => write!(f, "destination is not ip:port"),
+            Self::UnknownExecutor([a, b, c, d]) => write!(f, "{a}.{b}.{c}.{d} is not in MEV_EXECUTORS"),
+            Self::TooMany => write!(
+                f,
+                "at most {MAX_ROUTES} rules, {MAX_FANOUT} destinations per rule and {MAX_DESTINATIONS} in total"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for RouteError {}
+
+/// Parsed `MEV_REPLY_ROUTES`; empty routes every message back to its source.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplyRoutes {
+    rules: heapless::Vec<RouteRule, MAX_ROUTES>,
+    destinations: heapless::Vec<ReplyDestination, MAX_DESTINATIONS>,
+}
+
+impl ReplyRoutes {
+    /// Parse `spec`, building headers from `source` (the node's reply
+    /// address). `executor_mac` resolves a destination address to its MAC.
+    pub fn parse(
+        spec: &str,
+        source: &UdpEndpoint,
+        executor_mac: impl Fn([u8; 4]) -> Option<[u8; 6]>,
+        parse_pool: impl Fn(&str) -> Option<PoolAddress>,
+    ) -> Result<Self, RouteError> {
+        let mut routes = Self::default();
+        for rule in spec.split(';').map(str::trim).filter(|r| !r.is_empty()) {
+            let (selector, targets) = rule.split_once('=').ok_or(RouteError::Syntax)?;
+            let mut fields = selector.split('/').map(str::trim);
+            let (Some(strategy), Some(pool), Some(kind), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(RouteError::Syntax);
+            };
+            let strategy = match strategy {
+                "*" => None,
+                name => Some(
+                    Strategy::ALL
+                        .into_iter()
+                        .find(|s| s.name() == name)
+                        .ok_or(RouteError::UnknownStrategy)?,
+                ),
+            };
+            let pool = match pool {
+                "*" => None,
+                hex => Some(parse_pool(hex).ok_or(RouteError::InvalidPool)?),
+            };
+            let kind = match kind {
+                "*" => None,
+                name => Some(MessageKind::parse(name).ok_or(RouteError::UnknownKind)?),
+            };
+            let mut to = heapless::Vec::new();
+            for target in targets.split('+').map(str::trim) {
+                let addr: std::net::SocketAddrV4 =
+                    target.parse().map_err(|_| RouteError::InvalidDestination)?;
+                let index =
+                    routes.destination(source, addr.ip().octets(), addr.port(), &executor_mac)?;
+                to.push(index).map_err(|_| RouteError::TooMany)?;
+            }
+            routes
+                .rules
+                .push(RouteRule {
+                    strategy,
+                    pool,
+                    kind,
+                    to,
+                })
+                .map_err(|_| RouteError::TooMany)?;
+        }
+        Ok(routes)
+    }
+
+    /// Index of the destination `addr:port`, added with its headers if new.
+    fn destination(
+        &mut self,
+        source: &UdpEndpoint,
+        addr: [u8; 4],
+        port: u16,
+        executor_mac: impl Fn([u8; 4]) -> Option<[u8; 6]>,
+    ) -> Result<u8, RouteError> {
+        if let Some(i) = self
+            .destinations
+            .iter()
+            .position(|d| d.endpoint.addr == addr && d.endpoint.port == port)
+        {
+            return Ok(i as u8);
+        }
+        let mac = executor_mac(addr).ok_or(RouteError::UnknownExecutor(addr))?;
+        let endpoint = UdpEndpoint { mac, addr, port };
+        let headers = [MessageKind::Reply, MessageKind::Cancel].map(|kind| {
+            build_headers(source, &endpoint, kind.wire_size()).expect("message fits a datagram")
+        });
+        self.destinations
+            .push(ReplyDestination { endpoint, headers })
+            .map_err(|_| RouteError::TooMany)?;
+        Ok(self.destinations.len() as u8 - 1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Destinations of a `kind` message about `pool` found by `strategy`;
+    /// none means back to the source.
+    #[inline(always)]
+    pub fn lookup(
+        &self,
+        strategy: Strategy,
+        pool: &PoolAddress,
+        kind: MessageKind,
+    ) -> impl Iterator<Item = &ReplyDestination> + '_ {
+        let rule = self.rules.iter().find(|r| r.matches(strategy, pool, kind));
+        rule.into_iter()
+            .flat_map(move |r| r.to.iter().map(move |&i| &self.destinations[i as usize]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NODE: UdpEndpoint = UdpEndpoint {
+        mac: [0x02, 0, 0, 0, 0, 0x01],
+        addr: [192, 168, 69, 2],
+        port: 8080,
+    };
+
+    fn executor_mac(addr: [u8; 4]) -> Option<[u8; 6]> {
+        (addr[..3] == [10, 0, 0]).then_some([0x02, 0, 0, 0, 0, addr[3]])
+    }
+
+    fn pool(hex: &str) -> Option<PoolAddress> {
+        (hex.len() == 2).then(|| [u8::from_str_radix(hex, 16).unwrap_or(0); 20])
+    }
+
+    fn addrs<'a>(dests: impl Iterator<Item = &'a ReplyDestination>) -> Vec<([u8; 4], u16)> {
+        dests.map(|d| (d.endpoint.addr, d.endpoint.port)).collect()
+    }
+
+    #[test]
+    fn first_matching_rule_picks_the_destinations() {
+        let spec = "sandwich/aa/reply = 10.0.0.5:9000+10.0.0.6:9000; */*/cancel=10.0.0.7:9100; sandwich/*/*=10.0.0.5:9000";
+        let routes = ReplyRoutes::parse(spec, &NODE, executor_mac, pool).unwrap();
+        let (aa, bb) = ([0xaa; 20], [0xbb; 20]);
+        let fanout = [([10, 0, 0, 5], 9000), ([10, 0, 0, 6], 9000)];
+        assert_eq!(
+            addrs(routes.lookup(Strategy::Sandwich, &aa, MessageKind::Reply)),
+            fanout
+        );
+        assert_eq!(
+            addrs(routes.lookup(Strategy::Sandwich, &aa, MessageKind::Cancel)),
+            [([10, 0, 0, 7], 9100)]
+        );
+        assert_eq!(
+            addrs(routes.lookup(Strategy::Sandwich, &bb, MessageKind::Reply)),
+            [([10, 0, 0, 5], 9000)]
+        );
+        assert!(routes
+            .lookup(Strategy::BookTakerArb, &bb, MessageKind::Reply)
+            .next()
+            .is_none());
+        // Shared destinations are resolved once.
+        assert_eq!(routes.destinations.len(), 3);
+
+        let dest = routes
+            .lookup(Strategy::Sandwich, &aa, MessageKind::Cancel)
+            .next()
+            .unwrap();
+        let headers = dest.headers(MessageKind::Cancel);
+        assert_eq!(
+            (&headers[..6], &headers[26..30]),
+            (&[0x02, 0, 0, 0, 0, 7][..], &NODE.addr[..])
+        );
+        let udp_len = u16::from_be_bytes([headers[38], headers[39]]) as usize;
+        assert_eq!(udp_len, 8 + OpportunityCancel::WIRE_SIZE);
+    }
+
+    #[test]
+    fn rejects_malformed_rules() {
+        let parse = |spec| ReplyRoutes::parse(spec, &NODE, executor_mac, pool);
+        assert_eq!(parse("sandwich/*=10.0.0.5:9000"), Err(RouteError::Syntax));
+        assert_eq!(
+            parse("liquidation/*/*=10.0.0.5:9000"),
+            Err(RouteError::UnknownStrategy)
+        );
+        assert_eq!(parse("*/zzz/*=10.0.0.5:9000"), Err(RouteError::InvalidPool));
+        assert_eq!(
+            parse("*/*/alert=10.0.0.5:9000"),
+            Err(RouteError::UnknownKind)
+        );
+        assert_eq!(parse("*/*/*=10.0.0.5"), Err(RouteError::InvalidDestination));
+        assert_eq!(
+            parse("*/*/*=172.16.0.1:9000"),
+            Err(RouteError::UnknownExecutor([172, 16, 0, 1]))
+        );
+        assert_eq!(
+            parse("*/*/*=10.0.0.1:1+10.0.0.1:2+10.0.0.1:3+10.0.0.1:4+10.0.0.1:5"),
+            Err(RouteError::TooMany)
+        );
+        assert!(parse("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,239 @@
+//! Victim sender reputation.
+//!
+//! Executors report what happened to each victim we emitted an opportunity
+//! against (`VictimOutcome`): it landed publicly, it reverted, or it never
+//! showed up in the public mempool order (private routing). Senders whose
+//! victims rarely land make for bundles that rarely land, so their expected
+//! profit is scaled by their hit rate, and senders below a floor are not
+//! evaluated at all.
+//!
+//! The table is direct-mapped and fixed-size; a colliding sender overwrites
+//! the previous one. Counts are halved once a sender's history exceeds
+//! [`HISTORY_WINDOW`], so old behavior fades out.
+use crate::nonce::Address;
+use crate::pool_registry::address_hash;
+use bytemuck::{Pod, Zeroable};
+
+/// Magic prefix identifying a `VictimOutcome` datagram ("MVVO").
+pub const VICTIM_OUTCOME_MAGIC: [u8; 4] = *b"MVVO";
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Mined from the public mempool.
+    Landed = 1,
+    /// Mined but reverted.
+    Reverted = 2,
+    /// Mined via a private route, or never mined.
+    Private = 3,
+}
+
+/// Executor report on one victim transaction.
+///
+/// Layout (40 bytes, little-endian):
+///   [0..4]   magic    "MVVO"
+///   [4..24]  sender   victim account
+///   [24..32] nonce    u64 — victim account nonce
+///   [32]     outcome  `Outcome`
+///   [33..40] _reserved
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct VictimOutcome {
+    pub magic: [u8; 4],
+    pub sender: Address,
+    pub nonce_le: [u8; 8],
+    pub outcome: u8,
+    pub _reserved: [u8; 7],
+}
+
+const _: () = assert!(core::mem::size_of::<VictimOutcome>() == 40);
+
+impl VictimOutcome {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<VictimOutcome>();
+
+    pub fn new(sender: Address, nonce: u64, outcome: Outcome) -> Self {
+        let nonce_le = nonce.to_le_bytes();
+        Self {
+            magic: VICTIM_OUTCOME_MAGIC,
+            sender,
+            nonce_le,
+            outcome: outcome as u8,
+            _reserved: [0; 7],
+        }
+    }
+
+    #[inline(always)]
+    pub fn nonce(&self) -> u64 {
+        u64::from_le_bytes(self.nonce_le)
+    }
+
+    pub fn outcome(&self) -> Option<Outcome> {
+        match self.outcome {
+            1 => Some(Outcome::Landed),
+            2 => Some(Outcome::Reverted),
+            3 => Some(Outcome::Private),
+            _ => None,
+        }
+    }
+
+    /// Zero-copy parse; returns `None` unless the payload is exactly one
+    /// `VictimOutcome` carrying the expected magic.
+    pub fn parse(data: &[u8]) -> Option<&VictimOutcome> {
+        if data.len() != Self::WIRE_SIZE {
+            return None;
+        }
+        let report = bytemuck::try_from_bytes::<VictimOutcome>(data).ok()?;
+        (report.magic == VICTIM_OUTCOME_MAGIC).then_some(report)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ReputationPolicy {
+    /// Outcomes needed before a sender's record counts.
+    pub min_samples: u32,
+    /// Senders whose hit rate is below this (bps) are skipped.
+    pub skip_below_bps: u32,
+}
+
+impl Default for ReputationPolicy {
+    fn default() -> Self {
+        Self {
+            min_samples: 8,
+            skip_below_bps: 1_000,
+        }
+    }
+}
+
+/// Outcomes kept per sender before the counts are halved.
+pub const HISTORY_WINDOW: u32 = 256;
+
+#[derive(Clone, Copy)]
+struct SenderRecord {
+    sender: Address,
+    landed: u32,
+    missed: u32,
+    occupied: bool,
+}
+
+impl SenderRecord {
+    const EMPTY: Self = Self {
+        sender: [0; 20],
+        landed: 0,
+        missed: 0,
+        occupied: false,
+    };
+}
+
+/// Per-sender outcome counts; `N` direct-mapped slots.
+pub struct ReputationTable<const N: usize> {
+    policy: ReputationPolicy,
+    records: [SenderRecord; N],
+}
+
+impl<const N: usize> ReputationTable<N> {
+    const VALID: () = assert!(N.is_power_of_two(), "N must be a power of two");
+
+    pub const fn new(policy: ReputationPolicy) -> Self {
+        let () = Self::VALID;
+        Self {
+            policy,
+            records: [SenderRecord::EMPTY; N],
+        }
+    }
+
+    #[inline(always)]
+    fn slot(sender: &Address) -> usize {
+        (address_hash(sender) >> 32) as usize & (N - 1)
+    }
+
+    /// Book one executor report.
+    pub fn record(&mut self, sender: &Address, outcome: Outcome) {
+        let r = &mut self.records[Self::slot(sender)];
+        if !r.occupied || r.sender != *sender {
+            *r = SenderRecord {
+                sender: *sender,
+                occupied: true,
+                ..SenderRecord::EMPTY
+            };
+        }
+        match outcome {
+            Outcome::Landed => r.landed += 1,
+            Outcome::Reverted | Outcome::Private => r.missed += 1,
+        }
+        if r.landed + r.missed > HISTORY_WINDOW {
+            (r.landed, r.missed) = (r.landed / 2, r.missed / 2);
+        }
+    }
+
+    /// Weight (bps) to apply to opportunities against `sender`'s victims:
+    /// the sender's hit rate, or 10 000 while it has too little history.
+    /// `None` if the sender should be skipped.
+    #[inline(always)]
+    pub fn weight_bps(&self, sender: &Address) -> Option<u32> {
+        let r = &self.records[Self::slot(sender)];
+        let total = r.landed + r.missed;
+        if !r.occupied || r.sender != *sender || total < self.policy.min_samples.max(1) {
+            return Some(10_000);
+        }
+        let rate = (r.landed as u64 * 10_000 / total as u64) as u32;
+        (rate >= self.policy.skip_below_bps).then_some(rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_rate_weights_and_skips_senders() {
+        let mut table: ReputationTable<64> = ReputationTable::new(ReputationPolicy {
+            min_samples: 4,
+            skip_below_bps: 3_000,
+        });
+        let (good, bad) = ([0x60; 20], [0xBA; 20]);
+        for outcome in [
+            Outcome::Landed,
+            Outcome::Landed,
+            Outcome::Landed,
+            Outcome::Reverted,
+        ] {
+            table.record(&good, outcome);
+        }
+        for _ in 0..3 {
+            table.record(&bad, Outcome::Private);
+        }
+        assert_eq!(table.weight_bps(&good), Some(7_500));
+        assert_eq!(
+            table.weight_bps(&bad),
+            Some(10_000),
+            "not enough history yet"
+        );
+        table.record(&bad, Outcome::Reverted);
+        assert_eq!(table.weight_bps(&bad), None);
+        assert_eq!(table.weight_bps(&[0x33; 20]), Some(10_000));
+    }
+
+    #[test]
+    fn old_history_fades() {
+        let mut table: ReputationTable<64> = ReputationTable::new(ReputationPolicy::default());
+        let sender = [0x51; 20];
+        for _ in 0..HISTORY_WINDOW {
+            table.record(&sender, Outcome::Reverted);
+        }
+        assert_eq!(table.weight_bps(&sender), None);
+        for _ in 0..HISTORY_WINDOW {
+            table.record(&sender, Outcome::Landed);
+        }
+        assert!(table.weight_bps(&sender).is_some_and(|w| w > 7_000));
+    }
+
+    #[test]
+    fn outcome_report_round_trips() {
+        let report = VictimOutcome::new([0x11; 20], 42, Outcome::Private);
+        let parsed = VictimOutcome::parse(bytemuck::bytes_of(&report)).expect("valid report");
+        assert_eq!(
+            (parsed.sender, parsed.nonce(), parsed.outcome()),
+            ([0x11; 20], 42, Some(Outcome::Private))
+        );
+    }
+}
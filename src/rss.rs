@@ -0,0 +1,126 @@
+//! Receive-side scaling: the NIC's Toeplitz hash, reproduced in software.
+//!
+//! In sharded run-to-completion mode every worker core owns one NIC queue
+//! and a private pool table, so nothing is shared between cores on the
+//! packet path. That only works if a pool's updates and the swaps against it
+//! arrive on the queue whose table holds the pool. The node programs the NIC
+//! with [`RSS_KEY`] and a round-robin indirection table, which makes the
+//! queue of any IPv4/UDP flow computable off the NIC ([`flow_queue`]), and
+//! assigns each pool to a queue by the same hash over its address
+//! ([`pool_queue`]). Senders steer a pool's traffic to its owner by picking
+//! the UDP source port ([`steer_source_port`]).
+use crate::pool_registry::PoolAddress;
+
+/// The 40-byte Toeplitz key programmed into the NIC (the key from the
+/// Microsoft RSS specification, also the default of most drivers).
+pub const RSS_KEY: [u8; 40] = [
+    0x6d, 0x5a, 0x56, 0xda, 0x25, 0x5b, 0x0e, 0xc2, 0x41, 0x67, 0x25, 0x3d, 0x43, 0xa3, 0x8f, 0xb0,
+    0xd0, 0xca, 0x2b, 0xcb, 0xae, 0x7b, 0x30, 0xb4, 0x77, 0xcb, 0x2d, 0xa3, 0x80, 0x30, 0xf2, 0x0c,
+    0x6a, 0x42, 0xb7, 0x3b, 0xbe, 0xac, 0x01, 0xfa,
+];
+
+/// Toeplitz hash of `input` under `key`. `input` may be at most
+/// `key.len() - 4` bytes.
+pub fn toeplitz(key: &[u8; 40], input: &[u8]) -> u32 {
+    let mut hash = 0u32;
+    let mut window = u32::from_be_bytes([key[0], key[1], key[2], key[3]]);
+    for (i, &byte) in input.iter().enumerate() {
+        let next = key.get(i + 4).copied().unwrap_or(0);
+        for bit in 0..8 {
+            if byte & (0x80 >> bit) != 0 {
+                hash ^= window;
+            }
+            window = (window << 1) | ((next >> (7 - bit)) & 1) as u32;
+        }
+    }
+    hash
+}
+
+/// RSS hash of an IPv4/UDP flow, as the NIC computes it (source address,
+/// destination address, source port, destination port).
+pub fn ipv4_udp_hash(src: [u8; 4], dst: [u8; 4], src_port: u16, dst_port: u16) -> u32 {
+    let mut input = [0u8; 12];
+    input[..4].copy_from_slice(&src);
+    input[4..8].copy_from_slice(&dst);
+    input[8..10].copy_from_slice(&src_port.to_be_bytes());
+    input[10..].copy_from_slice(&dst_port.to_be_bytes());
+    toeplitz(&RSS_KEY, &input)
+}
+
+/// Queue a hash lands on with a `reta_size`-entry indirection table filled
+/// round-robin (`entry i → queue i % queues`).
+#[inline(always)]
+pub fn hash_queue(hash: u32, reta_size: u16, queues: u16) -> u16 {
+    ((hash % reta_size.max(1) as u32) % queues.max(1) as u32) as u16
+}
+
+/// Queue the NIC delivers an IPv4/UDP flow to.
+pub fn flow_queue(
+    src: [u8; 4],
+    dst: [u8; 4],
+    src_port: u16,
+    dst_port: u16,
+    reta_size: u16,
+    queues: u16,
+) -> u16 {
+    hash_queue(
+        ipv4_udp_hash(src, dst, src_port, dst_port),
+        reta_size,
+        queues,
+    )
+}
+
+/// Queue, and so worker, that owns `pool`.
+#[inline(always)]
+pub fn pool_queue(pool: &PoolAddress, reta_size: u16, queues: u16) -> u16 {
+    hash_queue(toeplitz(&RSS_KEY, pool), reta_size, queues)
+}
+
+/// A source port from `ports` whose flow to `dst:dst_port` lands on
+/// `queue`, for senders steering a pool's traffic to its owner.
+pub fn steer_source_port(
+    src: [u8; 4],
+    dst: [u8; 4],
+    dst_port: u16,
+    queue: u16,
+    reta_size: u16,
+    queues: u16,
+    ports: core::ops::Range<u16>,
+) -> Option<u16> {
+    ports
+        .into_iter()
+        .find(|&port| flow_queue(src, dst, port, dst_port, reta_size, queues) == queue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_the_specification_test_vectors() {
+        // "Verifying the RSS Hash Calculation", IPv4 with ports.
+        assert_eq!(
+            ipv4_udp_hash([66, 9, 149, 187], [161, 142, 100, 80], 2794, 1766),
+            0x51cc_c178
+        );
+        assert_eq!(
+            ipv4_udp_hash([199, 92, 111, 2], [65, 69, 140, 83], 14230, 4739),
+            0xc626_b0ea
+        );
+        // Addresses only.
+        let input = [66, 9, 149, 187, 161, 142, 100, 80];
+        assert_eq!(toeplitz(&RSS_KEY, &input), 0x323e_8fc2);
+    }
+
+    #[test]
+    fn steered_ports_reach_the_pool_owner() {
+        let (relayer, node) = ([10, 0, 0, 5], [10, 0, 0, 2]);
+        for tag in 0..16u8 {
+            let owner = pool_queue(&[tag; 20], 128, 4);
+            assert!(owner < 4);
+            let port =
+                steer_source_port(relayer, node, 8081, owner, 128, 4, 40_000..40_100).unwrap();
+            assert_eq!(flow_queue(relayer, node, port, 8081, 128, 4), owner);
+        }
+    }
+}
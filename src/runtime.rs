@@ -1,5 +1,10 @@
-use core::sync::atomic::{AtomicU64, Ordering};
-use minstant::Instant;
+use crate::burst::BatchSizes;
+use crate::clock::Clock;
+use crate::pnl::PnlLedger;
+use crate::processor::Strategy;
+use crate::slo::Slo;
+use crate::source_stats::SourceStats;
+use core::sync::atomic::{AtomicI64, AtomicU64, Ordering};
 
 #[repr(align(64))]
 pub struct CacheAlignedAtomicU64(pub AtomicU64);
@@ -14,16 +19,208 @@ impl CacheAlignedAtomicU64 {
         self.0.fetch_add(1, Ordering::Relaxed);
     }
 
+    #[inline(always)]
+    pub fn add(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
     #[inline(always)]
     pub fn load(&self) -> u64 {
         self.0.load(Ordering::Relaxed)
     }
+
+    #[inline(always)]
+    pub fn store(&self, v: u64) {
+        self.0.store(v, Ordering::Relaxed);
+    }
+}
+
+/// Log2 histogram buckets: bucket `i` counts values in `[2^i, 2^(i+1))`
+/// (bucket 0 also takes 0).
+pub const LOG2_BUCKETS: usize = 64;
+
+/// Log2 histogram of profits or durations, shared by every writer.
+pub struct Log2Histogram {
+    buckets: [AtomicU64; LOG2_BUCKETS],
+}
+
+impl Log2Histogram {
+    pub const fn new() -> Self {
+        Self {
+            buckets: [const { AtomicU64::new(0) }; LOG2_BUCKETS],
+        }
+    }
+
+    #[inline(always)]
+    pub fn record(&self, value: u64) {
+        let bucket = (u64::BITS - 1).saturating_sub(value.leading_zeros()) as usize;
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn snapshot(&self) -> Log2Snapshot {
+        Log2Snapshot {
+            buckets: core::array::from_fn(|i| self.buckets[i].load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Default for Log2Histogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Bucket counts of a [`Log2Histogram`] at one moment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Log2Snapshot {
+    pub buckets: [u64; LOG2_BUCKETS],
+}
+
+impl Log2Snapshot {
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /// Upper bound of the bucket holding the `pct` percentile; 0 without
+    /// samples.
+    pub fn percentile(&self, pct: u64) -> u64 {
+        let rank = (self.count() * pct).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (i, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if count != 0 && seen >= rank {
+                return if i + 1 == LOG2_BUCKETS {
+                    u64::MAX
+                } else {
+                    (2u64 << i) - 1
+                };
+            }
+        }
+        0
+    }
+}
+
+/// How long AF_XDP UMEM frames spend in each leg of their round trip, in
+/// cycles: see `xdp::FrameAges`.
+pub struct FrameDwell {
+    /// Posted to the Fill ring until dequeued from the RX ring: in the
+    /// kernel, waiting for a packet.
+    pub kernel: Log2Histogram,
+    /// Dequeued until processed: in the packet loop.
+    pub processing: Log2Histogram,
+    /// Processed until posted to the Fill ring again.
+    pub refill: Log2Histogram,
+}
+
+impl FrameDwell {
+    pub const fn new() -> Self {
+        Self {
+            kernel: Log2Histogram::new(),
+            processing: Log2Histogram::new(),
+            refill: Log2Histogram::new(),
+        }
+    }
+
+    /// Each leg's name and histogram.
+    pub fn legs(&self) -> [(&'static str, &Log2Histogram); 3] {
+        [
+            ("kernel", &self.kernel),
+            ("processing", &self.processing),
+            ("refill", &self.refill),
+        ]
+    }
+}
+
+impl Default for FrameDwell {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 pub struct NodeStats {
     pub rx_packets: CacheAlignedAtomicU64,
     pub tx_packets: CacheAlignedAtomicU64,
     pub opportunities: CacheAlignedAtomicU64,
+    pub price_updates: CacheAlignedAtomicU64,
+    pub rejected_sources: CacheAlignedAtomicU64,
+    pub pool_updates: CacheAlignedAtomicU64,
+    pub pool_update_rejects: CacheAlignedAtomicU64,
+    pub pool_seq_gaps: CacheAlignedAtomicU64,
+    /// Pool updates skipped for a newer one of the same pool in the batch.
+    pub pool_conflated: CacheAlignedAtomicU64,
+    /// Pool updates applied from schema version 2 messages.
+    pub pool_updates_v2: CacheAlignedAtomicU64,
+    /// Messages whose schema version is outside the accepted window.
+    pub wire_version_rejects: CacheAlignedAtomicU64,
+    pub feed_resumes: CacheAlignedAtomicU64,
+    pub feed_fresh_sessions: CacheAlignedAtomicU64,
+    pub book_updates: CacheAlignedAtomicU64,
+    pub book_update_rejects: CacheAlignedAtomicU64,
+    pub book_arbs: CacheAlignedAtomicU64,
+    pub divergence_alerts: CacheAlignedAtomicU64,
+    /// Pools flagged by the anomaly detector.
+    pub pool_anomalies: CacheAlignedAtomicU64,
+    pub victim_replacements: CacheAlignedAtomicU64,
+    pub victims_skipped: CacheAlignedAtomicU64,
+    pub cancellations: CacheAlignedAtomicU64,
+    pub victim_outcomes: CacheAlignedAtomicU64,
+    pub reputation_skips: CacheAlignedAtomicU64,
+    /// EVM victims taken to be privately routed (`src/privacy.rs`).
+    pub privacy_skips: CacheAlignedAtomicU64,
+    pub duplicate_replies: CacheAlignedAtomicU64,
+    /// Sharded DPDK mode: packets for a pool owned by another queue.
+    pub rss_misroutes: CacheAlignedAtomicU64,
+    /// Victim frames skipped as stale behind a receive backlog.
+    pub freshness_drops: CacheAlignedAtomicU64,
+    /// Victim frames head-dropped while the TX path was backed up.
+    pub backpressure_drops: CacheAlignedAtomicU64,
+    /// Opportunities not replied to while trading is halted.
+    pub halted_replies: CacheAlignedAtomicU64,
+    /// Opportunities not replied to while the peer node leads.
+    pub standby_replies: CacheAlignedAtomicU64,
+    /// Victims and book updates not evaluated: their strategy is disabled.
+    pub strategy_skips: CacheAlignedAtomicU64,
+    /// Datagrams smoltcp dropped because a UDP socket's receive buffer was full.
+    pub udp_rx_drops: CacheAlignedAtomicU64,
+    /// Polls that found a TCP receive buffer full, stalling the peer.
+    pub tcp_rx_full: CacheAlignedAtomicU64,
+    /// Replies not sent for want of TX buffer (or pacing queue) room.
+    pub tx_full: CacheAlignedAtomicU64,
+    /// Frames that failed parsing or validation, captured in the quarantine.
+    pub quarantined: CacheAlignedAtomicU64,
+    /// DPDK or AF_XDP frames whose IPv4 header or UDP checksum did not verify.
+    pub rx_checksum_errors: CacheAlignedAtomicU64,
+    /// Coalesced reply datagrams carrying more than one reply, and the
+    /// replies they carried.
+    pub reply_batches: CacheAlignedAtomicU64,
+    pub coalesced_replies: CacheAlignedAtomicU64,
+    /// Replies executors acknowledged, replies that went unacknowledged past
+    /// the TTL, and those retransmitted to the secondary endpoint
+    /// (`src/ack.rs`).
+    pub replies_acked: CacheAlignedAtomicU64,
+    pub replies_unacked: CacheAlignedAtomicU64,
+    pub reply_retransmits: CacheAlignedAtomicU64,
+    /// Waits between empty polls, and the cycles spent in them
+    /// (`src/idle.rs`).
+    pub idle_waits: CacheAlignedAtomicU64,
+    pub idle_wait_cycles: CacheAlignedAtomicU64,
+    /// Gauges, refreshed once per second by the main loop: compute p99 in
+    /// cycles, wire-to-wire p50/p99 in ns.
+    pub compute_p99_cycles: CacheAlignedAtomicU64,
+    pub wire_p50_ns: CacheAlignedAtomicU64,
+    pub wire_p99_ns: CacheAlignedAtomicU64,
+    /// Firing latency SLO alarms, a mask of [`Slo::bit`].
+    pub slo_alarms: CacheAlignedAtomicU64,
+    /// Victim RX batch size in effect at each poll that received frames.
+    pub rx_batch_sizes: BatchSizes,
+    /// Profit of every opportunity detected, by `Strategy` index.
+    pub profit: [Log2Histogram; Strategy::COUNT],
+    /// UMEM frame dwell times, while the AF_XDP backend runs.
+    pub frame_dwell: FrameDwell,
+    /// Traffic, malformed frames and opportunities by sender address.
+    pub sources: SourceStats,
+    /// Detected and realized edge by token.
+    pub pnl: PnlLedger,
 }
 
 impl NodeStats {
@@ -32,8 +229,127 @@ impl NodeStats {
             rx_packets: CacheAlignedAtomicU64::new(0),
             tx_packets: CacheAlignedAtomicU64::new(0),
             opportunities: CacheAlignedAtomicU64::new(0),
+            price_updates: CacheAlignedAtomicU64::new(0),
+            rejected_sources: CacheAlignedAtomicU64::new(0),
+            pool_updates: CacheAlignedAtomicU64::new(0),
+            pool_update_rejects: CacheAlignedAtomicU64::new(0),
+            pool_seq_gaps: CacheAlignedAtomicU64::new(0),
+            pool_conflated: CacheAlignedAtomicU64::new(0),
+            pool_updates_v2: CacheAlignedAtomicU64::new(0),
+            wire_version_rejects: CacheAlignedAtomicU64::new(0),
+            feed_resumes: CacheAlignedAtomicU64::new(0),
+            feed_fresh_sessions: CacheAlignedAtomicU64::new(0),
+            book_updates: CacheAlignedAtomicU64::new(0),
+            book_update_rejects: CacheAlignedAtomicU64::new(0),
+            book_arbs: CacheAlignedAtomicU64::new(0),
+            divergence_alerts: CacheAlignedAtomicU64::new(0),
+            pool_anomalies: CacheAlignedAtomicU64::new(0),
+            victim_replacements: CacheAlignedAtomicU64::new(0),
+            victims_skipped: CacheAlignedAtomicU64::new(0),
+            cancellations: CacheAlignedAtomicU64::new(0),
+            victim_outcomes: CacheAlignedAtomicU64::new(0),
+            reputation_skips: CacheAlignedAtomicU64::new(0),
+            privacy_skips: CacheAlignedAtomicU64::new(0),
+            duplicate_replies: CacheAlignedAtomicU64::new(0),
+            rss_misroutes: CacheAlignedAtomicU64::new(0),
+            freshness_drops: CacheAlignedAtomicU64::new(0),
+            backpressure_drops: CacheAlignedAtomicU64::new(0),
+            halted_replies: CacheAlignedAtomicU64::new(0),
+            standby_replies: CacheAlignedAtomicU64::new(0),
+            strategy_skips: CacheAlignedAtomicU64::new(0),
+            udp_rx_drops: CacheAlignedAtomicU64::new(0),
+            tcp_rx_full: CacheAlignedAtomicU64::new(0),
+            tx_full: CacheAlignedAtomicU64::new(0),
+            quarantined: CacheAlignedAtomicU64::new(0),
+            rx_checksum_errors: CacheAlignedAtomicU64::new(0),
+            reply_batches: CacheAlignedAtomicU64::new(0),
+            coalesced_replies: CacheAlignedAtomicU64::new(0),
+            replies_acked: CacheAlignedAtomicU64::new(0),
+            replies_unacked: CacheAlignedAtomicU64::new(0),
+            reply_retransmits: CacheAlignedAtomicU64::new(0),
+            idle_waits: CacheAlignedAtomicU64::new(0),
+            idle_wait_cycles: CacheAlignedAtomicU64::new(0),
+            compute_p99_cycles: CacheAlignedAtomicU64::new(0),
+            wire_p50_ns: CacheAlignedAtomicU64::new(0),
+            wire_p99_ns: CacheAlignedAtomicU64::new(0),
+            slo_alarms: CacheAlignedAtomicU64::new(0),
+            rx_batch_sizes: BatchSizes::new(),
+            profit: [const { Log2Histogram::new() }; Strategy::COUNT],
+            frame_dwell: FrameDwell::new(),
+            sources: SourceStats::new(),
+            pnl: PnlLedger::new(),
         }
     }
+
+    /// Count one opportunity of `strategy` and its profit.
+    #[inline(always)]
+    pub fn record_profit(&self, strategy: Strategy, profit: u64) {
+        self.profit[strategy as usize].record(profit);
+    }
+
+    /// Every counter with its field name, for exporters.
+    pub fn counters(&self) -> [(&'static str, u64); 43] {
+        [
+            ("rx_packets", self.rx_packets.load()),
+            ("tx_packets", self.tx_packets.load()),
+            ("opportunities", self.opportunities.load()),
+            ("price_updates", self.price_updates.load()),
+            ("rejected_sources", self.rejected_sources.load()),
+            ("pool_updates", self.pool_updates.load()),
+            ("pool_update_rejects", self.pool_update_rejects.load()),
+            ("pool_seq_gaps", self.pool_seq_gaps.load()),
+            ("pool_conflated", self.pool_conflated.load()),
+            ("pool_updates_v2", self.pool_updates_v2.load()),
+            ("wire_version_rejects", self.wire_version_rejects.load()),
+            ("feed_resumes", self.feed_resumes.load()),
+            ("feed_fresh_sessions", self.feed_fresh_sessions.load()),
+            ("book_updates", self.book_updates.load()),
+            ("book_update_rejects", self.book_update_rejects.load()),
+            ("book_arbs", self.book_arbs.load()),
+            ("divergence_alerts", self.divergence_alerts.load()),
+            ("pool_anomalies", self.pool_anomalies.load()),
+            ("victim_replacements", self.victim_replacements.load()),
+            ("victims_skipped", self.victims_skipped.load()),
+            ("cancellations", self.cancellations.load()),
+            ("victim_outcomes", self.victim_outcomes.load()),
+            ("reputation_skips", self.reputation_skips.load()),
+            ("privacy_skips", self.privacy_skips.load()),
+            ("duplicate_replies", self.duplicate_replies.load()),
+            ("rss_misroutes", self.rss_misroutes.load()),
+            ("freshness_drops", self.freshness_drops.load()),
+            ("backpressure_drops", self.backpressure_drops.load()),
+            ("halted_replies", self.halted_replies.load()),
+            ("standby_replies", self.standby_replies.load()),
+            ("strategy_skips", self.strategy_skips.load()),
+            ("udp_rx_drops", self.udp_rx_drops.load()),
+            ("tcp_rx_full", self.tcp_rx_full.load()),
+            ("tx_full", self.tx_full.load()),
+            ("quarantined", self.quarantined.load()),
+            ("rx_checksum_errors", self.rx_checksum_errors.load()),
+            ("reply_batches", self.reply_batches.load()),
+            ("coalesced_replies", self.coalesced_replies.load()),
+            ("replies_acked", self.replies_acked.load()),
+            ("replies_unacked", self.replies_unacked.load()),
+            ("reply_retransmits", self.reply_retransmits.load()),
+            ("idle_waits", self.idle_waits.load()),
+            ("idle_wait_cycles", self.idle_wait_cycles.load()),
+        ]
+    }
+
+    /// Every SLO's alarm, 1 if firing, with its reason label.
+    pub fn slo_alarms(&self) -> [(&'static str, u64); Slo::COUNT] {
+        let alarms = self.slo_alarms.load();
+        Slo::ALL.map(|slo| (slo.reason(), (alarms & slo.bit() != 0) as u64))
+    }
+
+    /// Every gauge with its field name.
+    pub fn gauges(&self) -> [(&'static str, u64); 3] {
+        [
+            ("compute_p99_cycles", self.compute_p99_cycles.load()),
+            ("wire_p50_ns", self.wire_p50_ns.load()),
+            ("wire_p99_ns", self.wire_p99_ns.load()),
+        ]
+    }
 }
 
 impl Default for NodeStats {
@@ -48,37 +364,337 @@ pub struct LatencySample {
     pub micros: u64,
 }
 
+/// Times one span on a [`Clock`].
 pub struct LatencyClock {
     start_cycles: u64,
-    start_time: Instant,
 }
 
 impl LatencyClock {
     #[inline(always)]
-    pub fn start() -> Self {
+    pub fn start(clock: &impl Clock) -> Self {
+        Self {
+            start_cycles: clock.now_cycles(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn stop(self, clock: &impl Clock) -> LatencySample {
+        let cycles = clock.now_cycles().saturating_sub(self.start_cycles);
+        LatencySample {
+            cycles,
+            micros: cycles / clock.cycles_per_us(),
+        }
+    }
+}
+
+/// Wall-clock unix time in milliseconds (for comparing on-chain deadlines).
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn unix_time_ms() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Wall-clock unix time in nanoseconds (for reply timestamps that downstream
+/// hosts compare against their own clocks).
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn unix_time_ns() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos() as u64)
+}
+
+/// Offset from the system clock to the clock stamps on the wire are
+/// expressed in: 0 (system time) unless a PHC is tracked, see `phc`.
+static WIRE_CLOCK_OFFSET_NS: AtomicI64 = AtomicI64::new(0);
+
+pub fn set_wire_clock_offset_ns(offset_ns: i64) {
+    WIRE_CLOCK_OFFSET_NS.store(offset_ns, Ordering::Relaxed);
+}
+
+pub fn wire_clock_offset_ns() -> i64 {
+    WIRE_CLOCK_OFFSET_NS.load(Ordering::Relaxed)
+}
+
+/// A system-clock stamp (e.g. from `unix_time_ns`) converted to wire time
+/// for writing into an outgoing message.
+#[inline(always)]
+pub fn to_wire_time_ns(system_ns: u64) -> u64 {
+    system_ns.wrapping_add_signed(wire_clock_offset_ns())
+}
+
+/// Current TSC reading, for cycle-denominated deadlines.
+#[cfg(feature = "std")]
+#[inline(always)]
+pub fn now_cycles() -> u64 {
+    rdtsc()
+}
+
+/// The main loop's clock: the TSC is read once per iteration ([`tick`]),
+/// and every timestamp the iteration needs — cycles for timers and pacing,
+/// unix time for RX stamps and deadlines, smoltcp's `Instant` — is derived
+/// from that one reading, so they agree with each other.
+///
+/// Unix time is extrapolated from an anchor with a ns-per-cycle rate
+/// measured against the monotonic clock; [`resync`] re-anchors it to the
+/// system clock and refines the rate over the whole run, so call it every
+/// second or so.
+///
+/// [`tick`]: Self::tick
+/// [`resync`]: Self::resync
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct LoopClock {
+    /// Cycles and monotonic time when the clock was calibrated.
+    origin: (u64, std::time::Instant),
+    /// (cycles, unix ns) of the last resync.
+    anchor: (u64, u64),
+    /// (cycles, ns since calibration) of the last resync, for smoltcp time.
+    steady: (u64, u64),
+    /// Nanoseconds per cycle, 32.32 fixed point.
+    ns_per_cycle_q32: u64,
+    cycles: u64,
+}
+
+#[cfg(feature = "std")]
+impl LoopClock {
+    /// Measure the TSC rate against the system clock over `window`
+    /// (blocking). Without a TSC, "cycles" are nanoseconds.
+    pub fn calibrate(window: std::time::Duration) -> Self {
+        let origin = (rdtsc(), std::time::Instant::now());
+        let anchor = (origin.0, unix_time_ns());
+        std::thread::sleep(window);
+        let mut clock = Self {
+            origin,
+            anchor,
+            steady: (origin.0, 0),
+            ns_per_cycle_q32: 1 << 32,
+            cycles: origin.0,
+        };
+        clock.resync();
+        clock
+    }
+
+    /// Read the TSC; the iteration's timestamps all derive from this.
+    #[inline(always)]
+    pub fn tick(&mut self) -> u64 {
+        self.cycles = rdtsc();
+        self.cycles
+    }
+
+    /// Cycles at the last `tick`.
+    #[inline(always)]
+    pub fn cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    /// Unix time in nanoseconds at the last `tick`.
+    #[inline(always)]
+    pub fn unix_ns(&self) -> u64 {
+        self.anchor.1 + self.cycles_to_ns(self.cycles.saturating_sub(self.anchor.0))
+    }
+
+    #[inline(always)]
+    pub fn unix_ms(&self) -> u64 {
+        self.unix_ns() / 1_000_000
+    }
+
+    /// smoltcp time at the last `tick`: time since calibration, which,
+    /// unlike [`unix_ns`](Self::unix_ns), never steps back at a resync.
+    #[inline(always)]
+    pub fn instant(&self) -> smoltcp::time::Instant {
+        smoltcp::time::Instant::from_micros((self.steady_ns(self.cycles) / 1_000) as i64)
+    }
+
+    #[inline(always)]
+    fn steady_ns(&self, cycles: u64) -> u64 {
+        self.steady.1 + self.cycles_to_ns(cycles.saturating_sub(self.steady.0))
+    }
+
+    /// A smoltcp delay in cycles, such as `Interface::poll_delay`'s.
+    #[inline(always)]
+    pub fn delay_cycles(&self, delay: smoltcp::time::Duration) -> u64 {
+        delay.total_micros().saturating_mul(self.cycles_per_us())
+    }
+
+    #[inline(always)]
+    pub fn cycles_to_ns(&self, cycles: u64) -> u64 {
+        ((cycles as u128 * self.ns_per_cycle_q32 as u128) >> 32) as u64
+    }
+
+    /// Hot-path latency from `start`, a [`now_cycles`] reading, converted
+    /// at the calibrated rate.
+    #[inline(always)]
+    pub fn sample_since(&self, start: u64) -> LatencySample {
+        let cycles = rdtsc().saturating_sub(start);
+        LatencySample {
+            cycles,
+            micros: self.cycles_to_ns(cycles) / 1_000,
+        }
+    }
+
+    /// Whole cycles per microsecond; at least 1.
+    pub fn cycles_per_us(&self) -> u64 {
+        ((1_000u128 << 32) / self.ns_per_cycle_q32.max(1) as u128).max(1) as u64
+    }
+
+    /// Re-anchor unix time to the system clock and refine the rate over
+    /// everything since calibration.
+    pub fn resync(&mut self) {
+        let (cycles, ns) = (rdtsc(), unix_time_ns());
+        let span_cycles = cycles.saturating_sub(self.origin.0);
+        let span_ns = self.origin.1.elapsed().as_nanos() as u64;
+        // Held where the old rate got it if that is ahead.
+        let steady_ns = span_ns.max(self.steady_ns(cycles));
+        if span_cycles > 0 && span_ns > 0 {
+            self.ns_per_cycle_q32 = (((span_ns as u128) << 32) / span_cycles as u128).max(1) as u64;
+        }
+        self.anchor = (cycles, ns);
+        self.steady = (cycles, steady_ns);
+    }
+}
+
+/// The live clock: reads the TSC afresh, where [`LoopClock::cycles`] and
+/// [`LoopClock::unix_ns`] give the iteration's reading.
+#[cfg(feature = "std")]
+impl Clock for LoopClock {
+    #[inline(always)]
+    fn now_cycles(&self) -> u64 {
+        rdtsc()
+    }
+
+    #[inline(always)]
+    fn now_unix_ns(&self) -> u64 {
+        let cycles = rdtsc();
+        self.anchor.1 + self.cycles_to_ns(cycles.saturating_sub(self.anchor.0))
+    }
+
+    fn cycles_per_us(&self) -> u64 {
+        LoopClock::cycles_per_us(self)
+    }
+}
+
+/// The live clock of loops that keep no [`LoopClock`] (the DPDK and AF_XDP
+/// workers): the TSC for cycles, the system clock for unix time.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug)]
+pub struct SystemClock {
+    cycles_per_us: u64,
+}
+
+#[cfg(feature = "std")]
+impl SystemClock {
+    /// For a TSC of `cycles_per_us`, as calibrated by a [`LoopClock`].
+    pub fn new(cycles_per_us: u64) -> Self {
         Self {
-            start_cycles: rdtsc(),
-            start_time: Instant::now(),
+            cycles_per_us: cycles_per_us.max(1),
         }
     }
+}
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    #[inline(always)]
+    fn now_cycles(&self) -> u64 {
+        rdtsc()
+    }
 
     #[inline(always)]
-    pub fn stop(self) -> LatencySample {
-        let cycles = rdtsc().saturating_sub(self.start_cycles);
-        let micros = self.start_time.elapsed().as_micros() as u64;
-        LatencySample { cycles, micros }
+    fn now_unix_ns(&self) -> u64 {
+        unix_time_ns()
+    }
+
+    fn cycles_per_us(&self) -> u64 {
+        self.cycles_per_us
     }
 }
 
+#[cfg(feature = "std")]
 #[inline(always)]
 fn rdtsc() -> u64 {
     #[cfg(target_arch = "x86_64")]
     unsafe {
-        std::arch::x86_64::_rdtsc()
+        core::arch::x86_64::_rdtsc()
     }
 
     #[cfg(not(target_arch = "x86_64"))]
     {
-        0
+        static EPOCH: std::sync::OnceLock<minstant::Instant> = std::sync::OnceLock::new();
+        EPOCH
+            .get_or_init(minstant::Instant::now)
+            .elapsed()
+            .as_nanos() as u64
+    }
+}
+
+/// Hint the CPU to pull the cache line holding `ptr` into L1 ahead of use.
+///
+/// No-op on targets without an explicit prefetch intrinsic.
+#[inline(always)]
+pub fn prefetch_read<T>(ptr: *const T) {
+    #[cfg(target_arch = "x86_64")]
+    unsafe {
+        use core::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(ptr as *const i8);
+    }
+
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        let _ = ptr;
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profit_percentiles_are_bucket_upper_bounds() {
+        let histogram = Log2Histogram::new();
+        assert_eq!(histogram.snapshot().percentile(50), 0);
+        for profit in [0, 3, 900, 1_000, 1_023, 5_000_000] {
+            histogram.record(profit);
+        }
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 6);
+        // 900..1023 share [512, 1024).
+        assert_eq!(snapshot.percentile(50), 1_023);
+        assert_eq!(snapshot.percentile(10), 1);
+        assert_eq!(snapshot.percentile(100), (1 << 23) - 1);
+        histogram.record(u64::MAX);
+        assert_eq!(histogram.snapshot().percentile(100), u64::MAX);
+    }
+
+    #[test]
+    fn loop_clock_tracks_the_system_clock() {
+        let mut clock = LoopClock::calibrate(std::time::Duration::from_millis(5));
+        assert!(clock.cycles_per_us() >= 1);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let start = clock.tick();
+        let derived = clock.unix_ns();
+        let system = unix_time_ns();
+        assert!(
+            derived.abs_diff(system) < 2_000_000,
+            "derived {derived}, system {system}"
+        );
+        assert_eq!(clock.cycles(), start);
+        assert!(clock.sample_since(start).cycles > 0);
+
+        // smoltcp time counts from calibration and goes on across a resync.
+        let instant = clock.instant();
+        assert!(
+            (5_000..2_000_000).contains(&instant.total_micros()),
+            "{instant}"
+        );
+        clock.resync();
+        clock.tick();
+        assert!(clock.instant() >= instant);
+        let delay = smoltcp::time::Duration::from_micros(3);
+        assert_eq!(clock.delay_cycles(delay), 3 * clock.cycles_per_us());
+
+        let system = SystemClock::new(clock.cycles_per_us());
+        assert!(system.now_unix_ns().abs_diff(unix_time_ns()) < 1_000_000);
     }
 }
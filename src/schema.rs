@@ -0,0 +1,102 @@
+//! Wire schema versions, and the window of them a node accepts.
+//!
+//! Messages evolve without lockstep deploys. A new version only uses bytes
+//! that older versions leave zero, so an old decoder still reads what it
+//! knows. Every message says which version it is, and the node decodes each
+//! one by its own version. While relayers roll out, the node accepts both
+//! the old and the new version; once all of them are upgraded, the window is
+//! closed to the new one (`MEV_WIRE_VERSIONS`).
+//!
+//! | Version | Changes |
+//! |---|---|
+//! | 1 | Original layouts; version bytes zero |
+//! | 2 | `PoolStateUpdate` carries the pool's swap fee |
+//! | 3 | `PoolStateUpdate` also carries the relayer's send time |
+//!
+//! On the relayer TCP feed the `SessionHello` also announces the window, and
+//! the `SessionWelcome` names the version the relayer will send. Relayers
+//! that predate negotiation leave it zero and are taken to send version 1.
+//! Victims are not versioned this way: their extensions
+//! (`DexSwapTxExt`, `DexSwapTxEvm`) are told apart by length.
+
+/// Oldest version this build decodes.
+pub const WIRE_VERSION_MIN: u8 = 1;
+/// Newest version this build decodes.
+pub const WIRE_VERSION_MAX: u8 = 3;
+
+/// Version byte as sent: zero is version 1, sent by producers that predate
+/// versioning.
+#[inline(always)]
+pub const fn normalize(version: u8) -> u8 {
+    if version == 0 {
+        1
+    } else {
+        version
+    }
+}
+
+/// Versions accepted, `min..=max`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VersionWindow {
+    pub min: u8,
+    pub max: u8,
+}
+
+impl VersionWindow {
+    /// Every version this build decodes.
+    pub const ALL: Self = Self {
+        min: WIRE_VERSION_MIN,
+        max: WIRE_VERSION_MAX,
+    };
+
+    /// `None` unless `min <= max` and both are versions this build decodes.
+    pub const fn new(min: u8, max: u8) -> Option<Self> {
+        if WIRE_VERSION_MIN <= min && min <= max && max <= WIRE_VERSION_MAX {
+            Some(Self { min, max })
+        } else {
+            None
+        }
+    }
+
+    /// `1-3`, or a single version such as `2`.
+    pub fn parse(s: &str) -> Option<Self> {
+        let (min, max) = s.split_once('-').unwrap_or((s, s));
+        Self::new(min.trim().parse().ok()?, max.trim().parse().ok()?)
+    }
+
+    #[inline(always)]
+    pub const fn accepts(&self, version: u8) -> bool {
+        self.min <= version && version <= self.max
+    }
+}
+
+impl Default for VersionWindow {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl core::fmt::Display for VersionWindow {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}-{}", self.min, self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_parse_and_stay_within_the_build() {
+        assert_eq!(VersionWindow::parse("1-3"), Some(VersionWindow::ALL));
+        assert_eq!(
+            VersionWindow::parse("2"),
+            Some(VersionWindow { min: 2, max: 2 })
+        );
+        assert_eq!(VersionWindow::parse("2-1"), None);
+        assert_eq!(VersionWindow::parse("1-9"), None);
+        assert_eq!(VersionWindow::parse("0"), None);
+        let window = VersionWindow::parse("2").unwrap();
+        assert!(!window.accepts(normalize(0)) && window.accepts(2));
+    }
+}
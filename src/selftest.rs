@@ -0,0 +1,352 @@
+/// On-host latency self-test for the parse + evaluate hot path.
+///
+/// Drives `process_packet` over a fixed set of synthetic swaps, timing each
+/// call with `LatencyClock`, and reports cycle percentiles. Intended as a
+/// deploy gate on new hardware: `mev-zerocopy-node selftest latency` exits
+/// non-zero if p99 exceeds the configured budget.
+///
+/// Samples are collected into a heap buffer up front — this runs before the
+/// hot loop starts and is not part of the no-heap path.
+///
+/// [`calibrate`] times the hot path's stages separately — parse, pool
+/// lookup, AMM math and reply enqueue — and derives per-stage cycle budgets
+/// and a per-poll batch size from what this host actually achieves, so the
+/// defaults need not be guessed per machine.
+use crate::alloc_audit::HotPath;
+use crate::payload::DexSwapTx;
+use crate::pool_registry::PoolTable;
+use crate::processor::{self, Opportunity, MOCK_POOL};
+use crate::runtime::{now_cycles, LatencyClock, LoopClock};
+use crate::threshold::percentile;
+use crate::transport::{Transport, UmemTxFrame};
+
+/// Number of distinct synthetic packets cycled through during the test.
+const SYNTHETIC_PACKETS: usize = 64;
+
+#[derive(Clone, Copy, Debug)]
+pub struct LatencySelftestConfig {
+    /// Number of timed `process_packet` calls.
+    pub iterations: usize,
+    /// Untimed calls made first to warm caches and branch predictors.
+    pub warmup: usize,
+    /// Maximum acceptable p99 in TSC cycles.
+    pub p99_budget_cycles: u64,
+}
+
+impl Default for LatencySelftestConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 100_000,
+            warmup: 10_000,
+            p99_budget_cycles: 5_000,
+        }
+    }
+}
+
+/// Cycle percentiles from one self-test run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LatencyReport {
+    pub samples: usize,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub p999: u64,
+    pub max: u64,
+}
+
+impl LatencyReport {
+    /// Build a report from unsorted cycle samples (sorts in place).
+    pub fn from_samples(samples: &mut [u64]) -> Self {
+        samples.sort_unstable();
+        Self {
+            samples: samples.len(),
+            p50: percentile(samples, 50.0),
+            p90: percentile(samples, 90.0),
+            p99: percentile(samples, 99.0),
+            p999: percentile(samples, 99.9),
+            max: samples.last().copied().unwrap_or(0),
+        }
+    }
+
+    #[inline(always)]
+    pub fn within_budget(&self, p99_budget_cycles: u64) -> bool {
+        self.p99 <= p99_budget_cycles
+    }
+}
+
+/// Mix of profitable, below-threshold, and slippage-reverting swaps in both
+/// directions, so every branch of the evaluator is exercised.
+fn synthetic_packets() -> [DexSwapTx; SYNTHETIC_PACKETS] {
+    let mut out = [DexSwapTx::from_parts(0, [0; 20], 0, 0, 0); SYNTHETIC_PACKETS];
+    for (i, tx) in out.iter_mut().enumerate() {
+        let i = i as u64;
+        let amount_in = match i % 4 {
+            0 => 500,
+            _ => 10_000_000 + i * 1_000_000,
+        };
+        let min_out = if i % 8 == 7 { u64::MAX } else { 1 };
+        *tx = DexSwapTx::from_parts(i, [0xAB; 20], amount_in, min_out, (i & 1) as u8);
+    }
+    out
+}
+
+/// Run the latency self-test and return the measured percentiles.
+pub fn run_latency(cfg: &LatencySelftestConfig) -> LatencyReport {
+    let packets = synthetic_packets();
+    let wire = |i: usize| bytemuck::bytes_of(&packets[i % SYNTHETIC_PACKETS]);
+
+    for i in 0..cfg.warmup {
+        core::hint::black_box(processor::process_packet(core::hint::black_box(wire(i))));
+    }
+
+    let mut samples = vec![0u64; cfg.iterations];
+    let clock = LoopClock::calibrate(std::time::Duration::from_millis(10));
+    let hot = HotPath::enter();
+    for (i, sample) in samples.iter_mut().enumerate() {
+        let latency = LatencyClock::start(&clock);
+        core::hint::black_box(processor::process_packet(core::hint::black_box(wire(i))));
+        *sample = latency.stop(&clock).cycles;
+    }
+    drop(hot);
+    LatencyReport::from_samples(&mut samples)
+}
+
+/// Operations timed together per calibration sample, so the cost of
+/// reading the TSC is spread over several of them.
+const OPS_PER_SAMPLE: usize = 8;
+
+/// Largest derived batch: the default `FrameBatch` capacity.
+const MAX_BATCH_FRAMES: usize = 32;
+
+/// One stage of the per-frame hot path.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    Parse = 0,
+    Lookup = 1,
+    Amm = 2,
+    Enqueue = 3,
+}
+
+impl Stage {
+    pub const COUNT: usize = 4;
+    pub const ALL: [Self; Self::COUNT] = [Self::Parse, Self::Lookup, Self::Amm, Self::Enqueue];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Parse => "parse",
+            Self::Lookup => "lookup",
+            Self::Amm => "amm",
+            Self::Enqueue => "enqueue",
+        }
+    }
+}
+
+/// What the node does with its startup calibration.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CalibrationMode {
+    /// Skip it.
+    Off,
+    /// Measure and log, keep the configured budgets.
+    Report,
+    /// Measure, log, and use the derived frame budget.
+    Apply,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CalibrationConfig {
+    /// Timed samples per stage.
+    pub iterations: usize,
+    /// Untimed samples per stage made first.
+    pub warmup: usize,
+    /// Budget of a stage as a percentage of its measured p99.
+    pub headroom_pct: u64,
+    /// Cycles one poll may spend evaluating frames; sizes the batch.
+    pub poll_budget_cycles: u64,
+}
+
+impl Default for CalibrationConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 20_000,
+            warmup: 2_000,
+            headroom_pct: 200,
+            poll_budget_cycles: 50_000,
+        }
+    }
+}
+
+/// Measured per-stage costs on this host and the budgets derived from them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Calibration {
+    /// Cost of one operation of each [`Stage`], in cycles.
+    pub p50: [u64; Stage::COUNT],
+    pub p99: [u64; Stage::COUNT],
+    /// Per-stage budgets: p99 plus headroom.
+    pub budgets: [u64; Stage::COUNT],
+    /// Whole-frame budget, the sum of the stage budgets.
+    pub frame_budget_cycles: u64,
+    /// Frames whose typical (p50) cost fits one poll's budget.
+    pub batch_frames: usize,
+}
+
+impl Calibration {
+    /// Derive budgets from per-stage `(p50, p99)` costs.
+    pub fn derive(costs: [(u64, u64); Stage::COUNT], cfg: &CalibrationConfig) -> Self {
+        let p50 = costs.map(|(p50, _)| p50);
+        let p99 = costs.map(|(_, p99)| p99.max(1));
+        let budgets = p99.map(|p99| p99.saturating_mul(cfg.headroom_pct.max(100)) / 100);
+        let typical = p50.iter().sum::<u64>().max(1);
+        Self {
+            p50,
+            p99,
+            budgets,
+            frame_budget_cycles: budgets.iter().sum(),
+            batch_frames: ((cfg.poll_budget_cycles / typical) as usize).clamp(1, MAX_BATCH_FRAMES),
+        }
+    }
+}
+
+impl core::fmt::Display for Calibration {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        for stage in Stage::ALL {
+            let i = stage as usize;
+            write!(
+                f,
+                "{}={}/{}/{} ",
+                stage.name(),
+                self.p50[i],
+                self.p99[i],
+                self.budgets[i]
+            )?;
+        }
+        write!(
+            f,
+            "(p50/p99/budget cyc), frame_budget={}cyc, batch={}",
+            self.frame_budget_cycles, self.batch_frames
+        )
+    }
+}
+
+/// Time `op` over `cfg.iterations` samples of [`OPS_PER_SAMPLE`] calls,
+/// net of `overhead` cycles per sample; `(p50, p99)` per call.
+fn time_stage(cfg: &CalibrationConfig, overhead: u64, mut op: impl FnMut(usize)) -> (u64, u64) {
+    for i in 0..cfg.warmup * OPS_PER_SAMPLE {
+        op(i);
+    }
+    let mut samples = vec![0u64; cfg.iterations.max(1)];
+    for (n, sample) in samples.iter_mut().enumerate() {
+        let start = now_cycles();
+        for i in n * OPS_PER_SAMPLE..(n + 1) * OPS_PER_SAMPLE {
+            op(i);
+        }
+        *sample = now_cycles().wrapping_sub(start).saturating_sub(overhead) / OPS_PER_SAMPLE as u64;
+    }
+    samples.sort_unstable();
+    (percentile(&samples, 50.0), percentile(&samples, 99.0))
+}
+
+/// Measure the hot-path stages on this host and derive budgets from them.
+pub fn calibrate(cfg: &CalibrationConfig) -> Calibration {
+    let packets = synthetic_packets();
+    let wire = |i: usize| bytemuck::bytes_of(&packets[i % SYNTHETIC_PACKETS]);
+
+    // Cost of the TSC reads around an empty sample.
+    let mut empty: Vec<u64> = (0..cfg.iterations.max(1))
+        .map(|_| {
+            let start = now_cycles();
+            now_cycles().wrapping_sub(start)
+        })
+        .collect();
+    empty.sort_unstable();
+    let overhead = percentile(&empty, 50.0);
+
+    // A populated table of the production shape, one pool per packet.
+    let table: Box<PoolTable> = Box::default();
+    let pools: [[u8; 20]; SYNTHETIC_PACKETS] = core::array::from_fn(|i| [i as u8 + 1; 20]);
+    for address in pools {
+        let _ = table.upsert(processor::mock_pool_entry(address));
+    }
+    let mut frame = [0u8; 256];
+
+    let hot = HotPath::enter();
+    let costs = Stage::ALL.map(|stage| match stage {
+        Stage::Parse => time_stage(cfg, overhead, |i| {
+            core::hint::black_box(DexSwapTx::header(core::hint::black_box(wire(i))));
+        }),
+        Stage::Lookup => time_stage(cfg, overhead, |i| {
+            core::hint::black_box(table.peek(core::hint::black_box(&pools[i % SYNTHETIC_PACKETS])));
+        }),
+        Stage::Amm => time_stage(cfg, overhead, |i| {
+            let tx = &packets[i % SYNTHETIC_PACKETS];
+            let pool = core::hint::black_box(MOCK_POOL);
+            core::hint::black_box(pool.sandwich_profit(
+                tx.amount_in(),
+                10_000_000,
+                tx.token_direction == 0,
+            ));
+        }),
+        Stage::Enqueue => time_stage(cfg, overhead, |i| {
+            let mut tx = UmemTxFrame::new(&mut frame, 0, 42);
+            core::hint::black_box(tx.send_reply(i as u64, Opportunity::unscored(1), 0));
+        }),
+    });
+    drop(hot);
+    Calibration::derive(costs, cfg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_rank_percentiles() {
+        let mut samples: [u64; 100] = core::array::from_fn(|i| 100 - i as u64);
+        let report = LatencyReport::from_samples(&mut samples);
+        assert_eq!(report.samples, 100);
+        assert_eq!(report.p50, 50);
+        assert_eq!(report.p99, 99);
+        assert_eq!(report.max, 100);
+        assert!(report.within_budget(99));
+        assert!(!report.within_budget(98));
+    }
+
+    #[test]
+    fn run_latency_collects_all_samples() {
+        let cfg = LatencySelftestConfig {
+            iterations: 1_000,
+            warmup: 10,
+            p99_budget_cycles: 0,
+        };
+        let report = run_latency(&cfg);
+        assert_eq!(report.samples, 1_000);
+        assert!(report.p50 <= report.p99 && report.p99 <= report.max);
+    }
+
+    #[test]
+    fn budgets_follow_the_measured_stage_costs() {
+        let cfg = CalibrationConfig {
+            headroom_pct: 150,
+            poll_budget_cycles: 5_000,
+            ..Default::default()
+        };
+        let cal = Calibration::derive([(20, 40), (60, 200), (100, 300), (80, 0)], &cfg);
+        assert_eq!(cal.budgets, [60, 300, 450, 1]);
+        assert_eq!(cal.frame_budget_cycles, 811);
+        assert_eq!(cal.batch_frames, 5_000 / 260);
+        // A slow host still evaluates at least one frame per poll.
+        let slow = Calibration::derive([(50_000, 90_000); Stage::COUNT], &cfg);
+        assert_eq!(slow.batch_frames, 1);
+
+        let measured = calibrate(&CalibrationConfig {
+            iterations: 200,
+            warmup: 10,
+            ..Default::default()
+        });
+        assert!(measured
+            .p50
+            .iter()
+            .zip(&measured.p99)
+            .all(|(p50, p99)| p50 <= p99));
+        assert!(measured.frame_budget_cycles > 0 && measured.batch_frames >= 1);
+    }
+}
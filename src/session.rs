@@ -0,0 +1,662 @@
+//! Session protocol for TCP pool feeds.
+//!
+//! On connect the node sends a `SessionHello` carrying the resume token the
+//! relayer issued last time and the stream sequence of the last record it
+//! received. The relayer answers with a `SessionWelcome`: `Resumed` if it
+//! still holds the records after that sequence (it replays them first), or
+//! `Fresh` with a new token if the token is unknown or its history is gone —
+//! only then do per-pool sequence gaps show up and resync from live updates.
+//!
+//! After the welcome the stream is a sequence of records, each a
+//! `RecordHeader` (stream sequence, payload length) followed by the payload
+//! — a `PoolStateUpdate` or `BookUpdate`, exactly as on the UDP feed.
+//!
+//! While streaming, the node may send `FlowControl` messages: XOFF when its
+//! receive buffer is filling up, asking the relayer to hold further records,
+//! and XON to resume from the record after `last_seq`. They are only sent
+//! when enabled (`MEV_RELAY_XOFF_PCT`), as older relayers do not read them.
+//!
+//! The hello also carries the window of record schema versions the node
+//! accepts (see [`crate::schema`]), and the welcome names the one the
+//! relayer will send. A relayer that leaves it zero sends version 1; one
+//! outside the window is refused.
+use crate::flow_control::FlowAction;
+use crate::schema::{self, VersionWindow};
+use crate::transport::Transport;
+use bytemuck::{Pod, Zeroable};
+
+/// Magic prefix of a `SessionHello` ("MVSH").
+pub const SESSION_HELLO_MAGIC: [u8; 4] = *b"MVSH";
+/// Magic prefix of a `SessionWelcome` ("MVSW").
+pub const SESSION_WELCOME_MAGIC: [u8; 4] = *b"MVSW";
+/// Magic prefix of a `FlowControl` ("MVSF").
+pub const FLOW_CONTROL_MAGIC: [u8; 4] = *b"MVSF";
+pub const SESSION_VERSION: u16 = 1;
+/// Largest record payload accepted.
+pub const MAX_RECORD_LEN: usize = 512;
+
+/// Opaque token identifying a relayer-side session.
+pub type ResumeToken = [u8; 16];
+
+/// Client → relayer, first message on every connection.
+///
+/// Layout (32 bytes, little-endian):
+///   [0..4]   magic     "MVSH"
+///   [4..6]   version   u16
+///   [6]      wire_min  oldest record schema version accepted
+///   [7]      wire_max  newest record schema version accepted
+///   [8..24]  token     zero on first connect
+///   [24..32] last_seq  u64 — stream sequence of the last record received
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SessionHello {
+    pub magic: [u8; 4],
+    pub version_le: [u8; 2],
+    pub wire_min: u8,
+    pub wire_max: u8,
+    pub token: ResumeToken,
+    pub last_seq_le: [u8; 8],
+}
+
+const _: () = assert!(core::mem::size_of::<SessionHello>() == 32);
+
+impl SessionHello {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<SessionHello>();
+
+    #[inline(always)]
+    pub fn last_seq(&self) -> u64 {
+        u64::from_le_bytes(self.last_seq_le)
+    }
+
+    pub fn parse(data: &[u8]) -> Option<&SessionHello> {
+        let hello = bytemuck::try_from_bytes::<SessionHello>(data.get(..Self::WIRE_SIZE)?).ok()?;
+        (hello.magic == SESSION_HELLO_MAGIC).then_some(hello)
+    }
+}
+
+#[repr(u8)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WelcomeStatus {
+    /// Records after `last_seq` follow.
+    Resumed = 1,
+    /// New session: the stream continues from live updates.
+    Fresh = 2,
+}
+
+/// Relayer → client, answer to a `SessionHello`.
+///
+/// Layout (32 bytes, little-endian):
+///   [0..4]   magic     "MVSW"
+///   [4..6]   version   u16
+///   [6]      status    `WelcomeStatus`
+///   [7]      wire_version  record schema version that follows; 0 = 1
+///   [8..24]  token     token to present on the next connect
+///   [24..32] next_seq  u64 — stream sequence of the next record
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct SessionWelcome {
+    pub magic: [u8; 4],
+    pub version_le: [u8; 2],
+    pub status: u8,
+    pub wire_version: u8,
+    pub token: ResumeToken,
+    pub next_seq_le: [u8; 8],
+}
+
+const _: () = assert!(core::mem::size_of::<SessionWelcome>() == 32);
+
+impl SessionWelcome {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<SessionWelcome>();
+
+    pub fn new(status: WelcomeStatus, token: ResumeToken, next_seq: u64) -> Self {
+        Self {
+            magic: SESSION_WELCOME_MAGIC,
+            version_le: SESSION_VERSION.to_le_bytes(),
+            status: status as u8,
+            wire_version: 0,
+            token,
+            next_seq_le: next_seq.to_le_bytes(),
+        }
+    }
+
+    /// Announce the record schema version that follows.
+    pub fn with_wire_version(mut self, version: u8) -> Self {
+        self.wire_version = version;
+        self
+    }
+
+    #[inline(always)]
+    pub fn next_seq(&self) -> u64 {
+        u64::from_le_bytes(self.next_seq_le)
+    }
+
+    pub fn status(&self) -> Option<WelcomeStatus> {
+        match self.status {
+            1 => Some(WelcomeStatus::Resumed),
+            2 => Some(WelcomeStatus::Fresh),
+            _ => None,
+        }
+    }
+}
+
+/// Client → relayer while streaming: pause or resume the record stream.
+///
+/// Layout (16 bytes, little-endian):
+///   [0..4]   magic     "MVSF"
+///   [4]      action    1 = XOFF (hold records), 2 = XON (resume)
+///   [5..8]   _reserved
+///   [8..16]  last_seq  u64 — stream sequence of the last record received
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct FlowControl {
+    pub magic: [u8; 4],
+    pub action: u8,
+    pub _reserved: [u8; 3],
+    pub last_seq_le: [u8; 8],
+}
+
+const _: () = assert!(core::mem::size_of::<FlowControl>() == 16);
+
+impl FlowControl {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<FlowControl>();
+
+    pub fn new(action: FlowAction, last_seq: u64) -> Self {
+        let action = match action {
+            FlowAction::Xoff => 1,
+            FlowAction::Xon => 2,
+        };
+        Self {
+            magic: FLOW_CONTROL_MAGIC,
+            action,
+            _reserved: [0; 3],
+            last_seq_le: last_seq.to_le_bytes(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn last_seq(&self) -> u64 {
+        u64::from_le_bytes(self.last_seq_le)
+    }
+
+    pub fn action(&self) -> Option<FlowAction> {
+        match self.action {
+            1 => Some(FlowAction::Xoff),
+            2 => Some(FlowAction::Xon),
+            _ => None,
+        }
+    }
+
+    pub fn parse(data: &[u8]) -> Option<&FlowControl> {
+        let msg = bytemuck::try_from_bytes::<FlowControl>(data.get(..Self::WIRE_SIZE)?).ok()?;
+        (msg.magic == FLOW_CONTROL_MAGIC).then_some(msg)
+    }
+}
+
+/// Framing of one record after the welcome.
+///
+/// Layout (16 bytes, little-endian):
+///   [0..8]   seq       u64 — stream sequence
+///   [8..10]  len       u16 — payload bytes that follow
+///   [10..16] _reserved
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct RecordHeader {
+    pub seq_le: [u8; 8],
+    pub len_le: [u8; 2],
+    pub _reserved: [u8; 6],
+}
+
+const _: () = assert!(core::mem::size_of::<RecordHeader>() == 16);
+
+impl RecordHeader {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<RecordHeader>();
+
+    pub fn new(seq: u64, len: u16) -> Self {
+        Self {
+            seq_le: seq.to_le_bytes(),
+            len_le: len.to_le_bytes(),
+            _reserved: [0; 6],
+        }
+    }
+
+    #[inline(always)]
+    pub fn seq(&self) -> u64 {
+        u64::from_le_bytes(self.seq_le)
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        u16::from_le_bytes(self.len_le) as usize
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Protocol violations; the connection should be dropped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionError {
+    BadWelcome,
+    UnsupportedVersion(u16),
+    /// The relayer's record schema version is outside the accepted window.
+    UnsupportedWireVersion(u8),
+    RecordTooLarge(usize),
+}
+
+impl core::fmt::Display for SessionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BadWelcome => write!(f, "malformed session welcome"),
+            Self::UnsupportedVersion(v) => write!(f, "unsupported session protocol version {v}"),
+            Self::UnsupportedWireVersion(v) => {
+                write!(f, "record schema version {v} outside the accepted window")
+            }
+            Self::RecordTooLarge(len) => write!(f, "record of {len} bytes exceeds the limit"),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+/// What one `receive` call produced besides records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionEvent {
+    Welcomed(WelcomeStatus),
+    /// Stream sequence jumped from `expected` to `got`.
+    Gap {
+        expected: u64,
+        got: u64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Disconnected,
+    AwaitWelcome,
+    Streaming,
+}
+
+/// Client side of one feed session; survives reconnects.
+pub struct FeedSession {
+    state: State,
+    token: ResumeToken,
+    /// Stream sequence of the last record delivered; 0 before the first.
+    last_seq: u64,
+    /// Partial welcome or record carried over between reads.
+    pending: heapless::Vec<u8, { RecordHeader::WIRE_SIZE + MAX_RECORD_LEN }>,
+    wire_window: VersionWindow,
+    /// Record schema version the relayer named in its last welcome.
+    wire_version: u8,
+}
+
+impl FeedSession {
+    pub fn new() -> Self {
+        Self {
+            state: State::Disconnected,
+            token: [0; 16],
+            last_seq: 0,
+            pending: heapless::Vec::new(),
+            wire_window: VersionWindow::ALL,
+            wire_version: schema::WIRE_VERSION_MIN,
+        }
+    }
+
+    /// Record schema versions to announce and accept.
+    pub fn with_wire_window(mut self, window: VersionWindow) -> Self {
+        self.wire_window = window;
+        self
+    }
+
+    /// Record schema version of the current (or last) connection.
+    pub fn wire_version(&self) -> u8 {
+        self.wire_version
+    }
+
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// Resume token and last record delivered, for a node taking over.
+    pub fn position(&self) -> (ResumeToken, u64) {
+        (self.token, self.last_seq)
+    }
+
+    /// Pick up where another node's session stopped: the first hello
+    /// resumes from `last_seq` under `token`.
+    pub fn resume_from(mut self, token: ResumeToken, last_seq: u64) -> Self {
+        self.token = token;
+        self.last_seq = last_seq;
+        self
+    }
+
+    pub fn is_streaming(&self) -> bool {
+        self.state == State::Streaming
+    }
+
+    pub fn awaits_hello(&self) -> bool {
+        self.state == State::Disconnected
+    }
+
+    /// Send the hello on a fresh connection. `false` if `tx` had no room.
+    pub fn connected(&mut self, tx: &mut impl Transport) -> bool {
+        let Some(buf) = tx.tx_reserve(SessionHello::WIRE_SIZE) else {
+            return false;
+        };
+        buf.copy_from_slice(bytemuck::bytes_of(&SessionHello {
+            magic: SESSION_HELLO_MAGIC,
+            version_le: SESSION_VERSION.to_le_bytes(),
+            wire_min: self.wire_window.min,
+            wire_max: self.wire_window.max,
+            token: self.token,
+            last_seq_le: self.last_seq.to_le_bytes(),
+        }));
+        self.state = State::AwaitWelcome;
+        self.pending.clear();
+        true
+    }
+
+    /// Send XOFF or XON while streaming. `false` if not streaming or `tx`
+    /// had no room.
+    pub fn flow_control(&mut self, tx: &mut impl Transport, action: FlowAction) -> bool {
+        if self.state != State::Streaming {
+            return false;
+        }
+        let Some(buf) = tx.tx_reserve(FlowControl::WIRE_SIZE) else {
+            return false;
+        };
+        buf.copy_from_slice(bytemuck::bytes_of(&FlowControl::new(action, self.last_seq)));
+        true
+    }
+
+    /// The connection dropped; the token and position are kept for resume.
+    pub fn disconnected(&mut self) {
+        self.state = State::Disconnected;
+        self.pending.clear();
+    }
+
+    /// Bytes still needed to complete what `pending` holds.
+    fn wanted(&self) -> Result<usize, SessionError> {
+        if self.state == State::AwaitWelcome {
+            return Ok(SessionWelcome::WIRE_SIZE);
+        }
+        match self.pending.get(..RecordHeader::WIRE_SIZE) {
+            Some(header) => {
+                let len = bytemuck::from_bytes::<RecordHeader>(header).len();
+                if len > MAX_RECORD_LEN {
+                    return Err(SessionError::RecordTooLarge(len));
+                }
+                Ok(RecordHeader::WIRE_SIZE + len)
+            }
+            None => Ok(RecordHeader::WIRE_SIZE),
+        }
+    }
+
+    /// Consume stream bytes. Complete records are passed to `on_record`
+    /// (stream sequence, payload); it returns `false` when it cannot take
+    /// more, and the rest is left for a later call. Records the session
+    /// already delivered before a resume are skipped. Returns the number of
+    /// bytes of `data` consumed and the last event seen.
+    pub fn receive(
+        &mut self,
+        mut data: &[u8],
+        mut on_record: impl FnMut(u64, &[u8]) -> bool,
+    ) -> Result<(usize, Option<SessionEvent>), SessionError> {
+        let total = data.len();
+        let mut event = None;
+        loop {
+            let wanted = self.wanted()?;
+            if self.pending.len() < wanted {
+                let take = (wanted - self.pending.len()).min(data.len());
+                let _ = self.pending.extend_from_slice(&data[..take]);
+                data = &data[take..];
+                if self.pending.len() < wanted {
+                    break;
+                }
+                // A just-completed header says how much payload follows.
+                if self.wanted()? != wanted {
+                    continue;
+                }
+            }
+
+            if self.state == State::AwaitWelcome {
+                let welcome: SessionWelcome = bytemuck::pod_read_unaligned(&self.pending);
+                let version = u16::from_le_bytes(welcome.version_le);
+                if welcome.magic != SESSION_WELCOME_MAGIC {
+                    return Err(SessionError::BadWelcome);
+                }
+                if version != SESSION_VERSION {
+                    return Err(SessionError::UnsupportedVersion(version));
+                }
+                let status = welcome.status().ok_or(SessionError::BadWelcome)?;
+                let wire_version = schema::normalize(welcome.wire_version);
+                if !self.wire_window.accepts(wire_version) {
+                    return Err(SessionError::UnsupportedWireVersion(wire_version));
+                }
+                self.wire_version = wire_version;
+                if status == WelcomeStatus::Fresh {
+                    self.last_seq = welcome.next_seq().saturating_sub(1);
+                }
+                self.token = welcome.token;
+                self.state = State::Streaming;
+                self.pending.clear();
+                event = Some(SessionEvent::Welcomed(status));
+                continue;
+            }
+
+            let header: RecordHeader =
+                bytemuck::pod_read_unaligned(&self.pending[..RecordHeader::WIRE_SIZE]);
+            let seq = header.seq();
+            if seq > self.last_seq || self.last_seq == 0 {
+                if !on_record(seq, &self.pending[RecordHeader::WIRE_SIZE..]) {
+                    break;
+                }
+                if self.last_seq != 0 && seq != self.last_seq + 1 {
+                    event = Some(SessionEvent::Gap {
+                        expected: self.last_seq + 1,
+                        got: seq,
+                    });
+                }
+                self.last_seq = seq;
+            }
+            self.pending.clear();
+        }
+        Ok((total - data.len(), event))
+    }
+}
+
+impl Default for FeedSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Collects what the session writes.
+    struct Sink(Vec<u8>);
+
+    impl Transport for Sink {
+        fn tx_reserve(&mut self, len: usize) -> Option<&mut [u8]> {
+            let start = self.0.len();
+            self.0.resize(start + len, 0);
+            Some(&mut self.0[start..])
+        }
+    }
+
+    fn record(seq: u64, payload: &[u8]) -> Vec<u8> {
+        let mut out = bytemuck::bytes_of(&RecordHeader::new(seq, payload.len() as u16)).to_vec();
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn deliver(
+        session: &mut FeedSession,
+        bytes: &[u8],
+        chunk: usize,
+    ) -> (Vec<u64>, Vec<SessionEvent>) {
+        let (mut seqs, mut events) = (Vec::new(), Vec::new());
+        for piece in bytes.chunks(chunk) {
+            let (used, event) = session
+                .receive(piece, |seq, _| {
+                    seqs.push(seq);
+                    true
+                })
+                .unwrap();
+            assert_eq!(used, piece.len());
+            events.extend(event);
+        }
+        (seqs, events)
+    }
+
+    #[test]
+    fn reconnect_resumes_after_last_record() {
+        let mut session = FeedSession::new();
+        let mut out = Sink(Vec::new());
+        assert!(session.connected(&mut out));
+        assert_eq!(
+            (
+                SessionHello::parse(&out.0).unwrap().last_seq(),
+                SessionHello::parse(&out.0).unwrap().token
+            ),
+            (0, [0; 16])
+        );
+
+        let mut stream =
+            bytemuck::bytes_of(&SessionWelcome::new(WelcomeStatus::Fresh, [7; 16], 100)).to_vec();
+        for seq in 100..103 {
+            stream.extend(record(seq, &[seq as u8; 64]));
+        }
+        // Byte-by-byte delivery exercises every partial-read path.
+        let (seqs, events) = deliver(&mut session, &stream, 1);
+        assert_eq!(seqs, [100, 101, 102]);
+        assert_eq!(events, [SessionEvent::Welcomed(WelcomeStatus::Fresh)]);
+
+        session.disconnected();
+        let mut out = Sink(Vec::new());
+        assert!(session.connected(&mut out));
+        let hello = SessionHello::parse(&out.0).unwrap();
+        assert_eq!((hello.last_seq(), hello.token), (102, [7; 16]));
+
+        // The relayer replays from 102 inclusive; the duplicate is dropped.
+        let mut stream =
+            bytemuck::bytes_of(&SessionWelcome::new(WelcomeStatus::Resumed, [7; 16], 102)).to_vec();
+        stream.extend(record(102, &[0; 8]));
+        stream.extend(record(103, &[0; 8]));
+        stream.extend(record(105, &[0; 8]));
+        let (seqs, events) = deliver(&mut session, &stream, 13);
+        assert_eq!(seqs, [103, 105]);
+        assert_eq!(
+            events,
+            [
+                SessionEvent::Welcomed(WelcomeStatus::Resumed),
+                SessionEvent::Gap {
+                    expected: 104,
+                    got: 105
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn refused_records_stay_pending() {
+        let mut session = FeedSession::new();
+        session.connected(&mut Sink(Vec::new()));
+        let mut stream =
+            bytemuck::bytes_of(&SessionWelcome::new(WelcomeStatus::Fresh, [1; 16], 1)).to_vec();
+        stream.extend(record(1, &[1; 4]));
+        stream.extend(record(2, &[2; 4]));
+
+        let mut taken = Vec::new();
+        let (used, _) = session
+            .receive(&stream, |seq, _| {
+                taken.push(seq);
+                taken.len() < 2
+            })
+            .unwrap();
+        assert_eq!(taken, [1, 2]);
+        assert_eq!(used, stream.len(), "the refused record was buffered");
+        let (used, _) = session
+            .receive(&[], |seq, payload| {
+                taken.push(seq);
+                payload == [2; 4]
+            })
+            .unwrap();
+        assert_eq!(
+            (used, taken.as_slice(), session.last_seq()),
+            (0, &[1, 2, 2][..], 2)
+        );
+    }
+
+    #[test]
+    fn flow_control_is_sent_only_while_streaming() {
+        let mut session = FeedSession::new();
+        let mut out = Sink(Vec::new());
+        assert!(!session.flow_control(&mut out, FlowAction::Xoff));
+        session.connected(&mut out);
+        assert!(
+            !session.flow_control(&mut out, FlowAction::Xoff),
+            "no welcome yet"
+        );
+        let mut stream =
+            bytemuck::bytes_of(&SessionWelcome::new(WelcomeStatus::Fresh, [1; 16], 40)).to_vec();
+        stream.extend(record(40, &[0; 4]));
+        deliver(&mut session, &stream, stream.len());
+
+        out.0.clear();
+        assert!(session.flow_control(&mut out, FlowAction::Xoff));
+        let msg = FlowControl::parse(&out.0).unwrap();
+        assert_eq!((msg.action(), msg.last_seq()), (Some(FlowAction::Xoff), 40));
+    }
+
+    #[test]
+    fn protocol_violations_are_errors() {
+        let mut session = FeedSession::new();
+        session.connected(&mut Sink(Vec::new()));
+        let mut welcome = SessionWelcome::new(WelcomeStatus::Fresh, [0; 16], 1);
+        welcome.version_le = 9u16.to_le_bytes();
+        assert_eq!(
+            session.receive(bytemuck::bytes_of(&welcome), |_, _| true),
+            Err(SessionError::UnsupportedVersion(9))
+        );
+
+        let mut session = FeedSession::new();
+        session.connected(&mut Sink(Vec::new()));
+        let mut stream =
+            bytemuck::bytes_of(&SessionWelcome::new(WelcomeStatus::Fresh, [0; 16], 1)).to_vec();
+        stream.extend_from_slice(bytemuck::bytes_of(&RecordHeader::new(1, 4096)));
+        assert_eq!(
+            session.receive(&stream, |_, _| true),
+            Err(SessionError::RecordTooLarge(4096))
+        );
+    }
+
+    #[test]
+    fn wire_version_is_negotiated_within_the_window() {
+        let mut session = FeedSession::new().with_wire_window(VersionWindow { min: 2, max: 2 });
+        let mut out = Sink(Vec::new());
+        session.connected(&mut out);
+        let hello = SessionHello::parse(&out.0).unwrap();
+        assert_eq!((hello.wire_min, hello.wire_max), (2, 2));
+        // A relayer that predates negotiation sends version 1.
+        let legacy = SessionWelcome::new(WelcomeStatus::Fresh, [0; 16], 1);
+        assert_eq!(
+            session.receive(bytemuck::bytes_of(&legacy), |_, _| true),
+            Err(SessionError::UnsupportedWireVersion(1))
+        );
+
+        let mut session = FeedSession::new();
+        session.connected(&mut Sink(Vec::new()));
+        assert_eq!(session.wire_version(), 1);
+        let welcome = SessionWelcome::new(WelcomeStatus::Fresh, [0; 16], 1).with_wire_version(2);
+        deliver(
+            &mut session,
+            bytemuck::bytes_of(&welcome),
+            SessionWelcome::WIRE_SIZE,
+        );
+        assert_eq!((session.is_streaming(), session.wire_version()), (true, 2));
+    }
+}
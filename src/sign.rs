@@ -0,0 +1,371 @@
+//! Node identity and reply signing.
+//!
+//! On a shared network segment anyone can put an `OpportunityReply` on the
+//! wire with the node's source address. With a key configured
+//! (`MEV_REPLY_KEY`), every reply is followed by a [`ReplySignature`]: the
+//! node's id (`MEV_NODE_ID`) and an HMAC-SHA256 tag, truncated to 128 bits,
+//! over the reply and the id. Executors holding the same key check the tag
+//! and drop replies that fail it.
+//!
+//! The HMAC's inner and outer pads are absorbed once, when the key is set,
+//! so signing a reply costs two SHA-256 compressions. Signed replies are
+//! 64 bytes rather than 40, which also keeps them out of reply batches:
+//! coalescing re-stamps replies at flush, after they would be signed.
+use bytemuck::{Pod, Zeroable};
+
+/// Bytes of a truncated tag.
+pub const TAG_LEN: usize = 16;
+
+/// Longest key accepted; longer keys would be hashed down to 32 bytes.
+pub const MAX_KEY_LEN: usize = 64;
+
+/// Shortest key accepted.
+pub const MIN_KEY_LEN: usize = 16;
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const H0: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// Streaming SHA-256 (FIPS 180-4).
+#[derive(Clone, Copy)]
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    filled: usize,
+    len: u64,
+}
+
+impl Sha256 {
+    pub const fn new() -> Self {
+        Self {
+            state: H0,
+            block: [0; 64],
+            filled: 0,
+            len: 0,
+        }
+    }
+
+    fn compress(state: &mut [u32; 8], block: &[u8; 64]) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            (h, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+        while !data.is_empty() {
+            let take = data.len().min(64 - self.filled);
+            self.block[self.filled..self.filled + take].copy_from_slice(&data[..take]);
+            self.filled += take;
+            data = &data[take..];
+            if self.filled == 64 {
+                Self::compress(&mut self.state, &self.block);
+                self.filled = 0;
+            }
+        }
+    }
+
+    pub fn finalize(mut self) -> [u8; 32] {
+        let bits = self.len.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.filled != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bits.to_be_bytes());
+        let mut out = [0; 32];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        out
+    }
+}
+
+impl Default for Sha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// HMAC-SHA256 keyed once: the hashes with the inner and outer pads
+/// already absorbed.
+#[derive(Clone, Copy)]
+pub struct Hmac {
+    inner: Sha256,
+    outer: Sha256,
+}
+
+impl Hmac {
+    pub fn new(key: &[u8]) -> Self {
+        let mut block = [0u8; 64];
+        if key.len() > 64 {
+            let mut hash = Sha256::new();
+            hash.update(key);
+            block[..32].copy_from_slice(&hash.finalize());
+        } else {
+            block[..key.len()].copy_from_slice(key);
+        }
+        let (mut inner, mut outer) = (Sha256::new(), Sha256::new());
+        inner.update(&block.map(|b| b ^ 0x36));
+        outer.update(&block.map(|b| b ^ 0x5c));
+        Self { inner, outer }
+    }
+
+    /// The full 32-byte tag of `parts`, concatenated.
+    pub fn tag(&self, parts: &[&[u8]]) -> [u8; 32] {
+        let mut inner = self.inner;
+        for part in parts {
+            inner.update(part);
+        }
+        let mut outer = self.outer;
+        outer.update(&inner.finalize());
+        outer.finalize()
+    }
+}
+
+/// Trailer following a signed `OpportunityReply` in the same datagram.
+///
+/// Layout (24 bytes, little-endian):
+///   [0..4]   node_id   u32 — `MEV_NODE_ID` of the sender
+///   [4..8]   _reserved
+///   [8..24]  tag       HMAC-SHA256(key, reply || node_id || _reserved),
+///                      first 16 bytes
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct ReplySignature {
+    pub node_id_le: [u8; 4],
+    pub _reserved: [u8; 4],
+    pub tag: [u8; TAG_LEN],
+}
+
+const _: () = assert!(core::mem::size_of::<ReplySignature>() == 24);
+
+impl ReplySignature {
+    pub const WIRE_SIZE: usize = core::mem::size_of::<ReplySignature>();
+
+    #[inline(always)]
+    pub fn node_id(&self) -> u32 {
+        u32::from_le_bytes(self.node_id_le)
+    }
+
+    /// View a reserved TX buffer as a signature to be filled in.
+    #[inline(always)]
+    pub fn emplace(buf: &mut [u8]) -> Option<&mut Self> {
+        bytemuck::try_from_bytes_mut(buf).ok()
+    }
+}
+
+/// Why a [`ReplySigner::new`] key was refused.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BadKeyLength(pub usize);
+
+impl core::fmt::Display for BadKeyLength {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "reply key is {} bytes, expected {MIN_KEY_LEN} to {MAX_KEY_LEN}",
+            self.0
+        )
+    }
+}
+
+/// This node's identity and key. The key itself is not kept, only the
+/// keyed hash states, and neither is printed by `Debug`.
+#[derive(Clone, Copy)]
+pub struct ReplySigner {
+    node_id: u32,
+    hmac: Hmac,
+}
+
+impl ReplySigner {
+    pub fn new(node_id: u32, key: &[u8]) -> Result<Self, BadKeyLength> {
+        if !(MIN_KEY_LEN..=MAX_KEY_LEN).contains(&key.len()) {
+            return Err(BadKeyLength(key.len()));
+        }
+        Ok(Self {
+            node_id,
+            hmac: Hmac::new(key),
+        })
+    }
+
+    pub fn node_id(&self) -> u32 {
+        self.node_id
+    }
+
+    /// The signature trailer for the encoded reply `reply`.
+    #[inline]
+    pub fn sign(&self, reply: &[u8]) -> ReplySignature {
+        let node_id_le = self.node_id.to_le_bytes();
+        let full = self.hmac.tag(&[reply, &node_id_le, &[0; 4]]);
+        let mut tag = [0; TAG_LEN];
+        tag.copy_from_slice(&full[..TAG_LEN]);
+        ReplySignature {
+            node_id_le,
+            _reserved: [0; 4],
+            tag,
+        }
+    }
+
+    /// Whether `datagram` is a reply followed by a signature from this
+    /// key, of any node id. The tags are compared in constant time.
+    pub fn verify(&self, datagram: &[u8]) -> bool {
+        let Some(split) = datagram.len().checked_sub(ReplySignature::WIRE_SIZE) else {
+            return false;
+        };
+        let (reply, trailer) = datagram.split_at(split);
+        let Ok(signature) = bytemuck::try_from_bytes::<ReplySignature>(trailer) else {
+            return false;
+        };
+        let full = self
+            .hmac
+            .tag(&[reply, &signature.node_id_le, &signature._reserved]);
+        full[..TAG_LEN]
+            .iter()
+            .zip(signature.tag)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+}
+
+impl core::fmt::Debug for ReplySigner {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("ReplySigner")
+            .field("node_id", &self.node_id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Signers compare by node id only: the key is not kept to compare.
+impl PartialEq for ReplySigner {
+    fn eq(&self, other: &Self) -> bool {
+        self.node_id == other.node_id
+    }
+}
+
+impl Eq for ReplySigner {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sha256_matches_known_digests() {
+        // "abc", and a message spanning two blocks.
+        let mut hash = Sha256::new();
+        hash.update(b"abc");
+        assert_eq!(
+            hash.finalize()[..],
+            hex("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        let mut hash = Sha256::new();
+        hash.update(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+        assert_eq!(
+            hash.finalize()[..],
+            hex("248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1")
+        );
+    }
+
+    #[test]
+    fn hmac_matches_rfc_4231() {
+        // Test cases 2 and 6 (a key longer than the block).
+        let tag = Hmac::new(b"Jefe").tag(&[b"what do ya want ", b"for nothing?"]);
+        assert_eq!(
+            tag[..],
+            hex("5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843")
+        );
+        let tag = Hmac::new(&[0xaa; 131])
+            .tag(&[b"Test Using Larger Than Block-Size Key - Hash Key First"]);
+        assert_eq!(
+            tag[..],
+            hex("60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54")
+        );
+    }
+
+    fn signed_reply(signer: &ReplySigner) -> Vec<u8> {
+        let mut datagram = [0x11u8; 40].to_vec();
+        datagram.extend_from_slice(bytemuck::bytes_of(&signer.sign(&datagram)));
+        datagram
+    }
+
+    #[test]
+    fn signed_replies_verify_and_carry_the_node_id() {
+        let signer = ReplySigner::new(7, &[0x42; 32]).unwrap();
+        let datagram = signed_reply(&signer);
+        assert!(signer.verify(&datagram));
+        assert_eq!(
+            bytemuck::from_bytes::<ReplySignature>(&datagram[40..]).node_id(),
+            7
+        );
+    }
+
+    #[test]
+    fn tampered_truncated_or_foreign_replies_fail() {
+        let signer = ReplySigner::new(7, &[0x42; 32]).unwrap();
+        let mut datagram = signed_reply(&signer);
+        datagram[3] ^= 1;
+        assert!(!signer.verify(&datagram), "tampered reply");
+        datagram[3] ^= 1;
+        assert!(
+            !ReplySigner::new(7, &[0x43; 32]).unwrap().verify(&datagram),
+            "other key"
+        );
+        assert!(!signer.verify(&datagram[..20]));
+    }
+
+    #[test]
+    fn short_keys_are_rejected() {
+        assert_eq!(
+            ReplySigner::new(7, &[0; 8]).map(|s| s.node_id()),
+            Err(BadKeyLength(8))
+        );
+    }
+
+    #[test]
+    fn debug_output_leaves_the_key_out() {
+        let signer = ReplySigner::new(7, &[0x42; 32]).unwrap();
+        assert!(!format!("{signer:?}").contains("hmac"));
+    }
+}
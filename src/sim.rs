@@ -0,0 +1,234 @@
+//! Dry-run simulation: synthetic settlement and hypothetical PnL.
+//!
+//! In `MEV_SIM` mode the node does not send replies. Each opportunity it
+//! would have emitted is raced against a competitor under [`RaceModel`]; if
+//! ours is faster and the builder includes the bundle, the opportunity is
+//! counted as landed and its profit (net of gas) is booked to the
+//! `(strategy, pool)` it came from.
+//!
+//! The model is deterministic for a given seed, so runs over the same pcap
+//! are reproducible.
+use crate::pool_registry::PoolAddress;
+use crate::processor::Strategy;
+use heapless::FnvIndexMap;
+
+/// Latency race parameters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RaceModel {
+    /// Fastest competitor, nanoseconds from the victim's RX to their bundle.
+    pub competitor_min_ns: u64,
+    /// Slowest competitor; competitor latency is uniform in `[min, max]`.
+    pub competitor_max_ns: u64,
+    /// Probability a winning bundle is actually included, in basis points.
+    pub inclusion_bps: u32,
+    /// PRNG seed.
+    pub seed: u64,
+}
+
+impl Default for RaceModel {
+    fn default() -> Self {
+        Self {
+            competitor_min_ns: 200_000,
+            competitor_max_ns: 2_000_000,
+            inclusion_bps: 8_000,
+            seed: 0x5EED,
+        }
+    }
+}
+
+/// Hypothetical PnL of one `(strategy, pool)`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PnlEntry {
+    pub emitted: u64,
+    pub landed: u64,
+    /// Sum of landed profits minus gas, numéraire units.
+    pub pnl: i64,
+}
+
+impl PnlEntry {
+    fn book(&mut self, landed: bool, net: i64) {
+        self.emitted += 1;
+        if landed {
+            self.landed += 1;
+            self.pnl = self.pnl.saturating_add(net);
+        }
+    }
+}
+
+/// Distinct `(strategy, pool)` pairs tracked individually; the rest are
+/// folded into [`Simulator::overflow`].
+pub const SIM_LEDGER_CAPACITY: usize = 256;
+
+pub struct Simulator {
+    pub model: RaceModel,
+    rng: u64,
+    ledger: FnvIndexMap<(Strategy, PoolAddress), PnlEntry, SIM_LEDGER_CAPACITY>,
+    /// PnL of pairs that did not fit in the ledger.
+    pub overflow: PnlEntry,
+    /// Totals across every pair.
+    pub totals: PnlEntry,
+}
+
+impl Simulator {
+    pub fn new(model: RaceModel) -> Self {
+        Self {
+            model,
+            rng: model.seed | 1,
+            ledger: FnvIndexMap::new(),
+            overflow: PnlEntry::default(),
+            totals: PnlEntry::default(),
+        }
+    }
+
+    /// xorshift64: cheap, deterministic, good enough for a race model.
+    fn next_u64(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    /// Would a bundle `our_latency_ns` after the victim's RX have landed?
+    pub fn race(&mut self, our_latency_ns: u64) -> bool {
+        let span = self
+            .model
+            .competitor_max_ns
+            .saturating_sub(self.model.competitor_min_ns);
+        let competitor = self.model.competitor_min_ns + self.next_u64() % (span + 1);
+        let included = (self.next_u64() % 10_000) < self.model.inclusion_bps as u64;
+        our_latency_ns < competitor && included
+    }
+
+    /// Race one opportunity and book the outcome. Returns whether it landed.
+    pub fn settle(
+        &mut self,
+        strategy: Strategy,
+        pool: PoolAddress,
+        profit: u64,
+        gas_cost: u64,
+        our_latency_ns: u64,
+    ) -> bool {
+        let landed = self.race(our_latency_ns);
+        let net = (profit as i64).saturating_sub(gas_cost as i64);
+        let entry = match self.ledger.get_mut(&(strategy, pool)) {
+            Some(entry) => entry,
+            None => match self.ledger.insert((strategy, pool), PnlEntry::default()) {
+                Ok(_) => self
+                    .ledger
+                    .get_mut(&(strategy, pool))
+                    .expect("just inserted"),
+                Err(_) => &mut self.overflow,
+            },
+        };
+        entry.book(landed, net);
+        self.totals.book(landed, net);
+        landed
+    }
+
+    /// Per-`(strategy, pool)` results, in first-seen order.
+    pub fn entries(&self) -> impl Iterator<Item = (Strategy, &PoolAddress, &PnlEntry)> {
+        self.ledger
+            .iter()
+            .map(|((strategy, pool), entry)| (*strategy, pool, entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_bundles_land_and_book_net_profit() {
+        let model = RaceModel {
+            competitor_min_ns: 1_000,
+            competitor_max_ns: 2_000,
+            inclusion_bps: 10_000,
+            seed: 7,
+        };
+        let mut sim = Simulator::new(model);
+        assert!(sim.settle(Strategy::Sandwich, [1; 20], 50_000, 10_000, 500));
+        assert!(
+            !sim.settle(Strategy::Sandwich, [1; 20], 50_000, 10_000, 5_000),
+            "slower than every competitor"
+        );
+        assert!(sim.settle(Strategy::Sandwich, [2; 20], 5_000, 10_000, 0));
+
+        let per_pool: Vec<_> = sim.entries().map(|(_, pool, e)| (pool[0], *e)).collect();
+        assert_eq!(
+            per_pool[0],
+            (
+                1,
+                PnlEntry {
+                    emitted: 2,
+                    landed: 1,
+                    pnl: 40_000
+                }
+            )
+        );
+        assert_eq!(
+            per_pool[1],
+            (
+                2,
+                PnlEntry {
+                    emitted: 1,
+                    landed: 1,
+                    pnl: -5_000
+                }
+            )
+        );
+        assert_eq!(
+            sim.totals,
+            PnlEntry {
+                emitted: 3,
+                landed: 2,
+                pnl: 35_000
+            }
+        );
+    }
+
+    #[test]
+    fn inclusion_rate_and_determinism() {
+        let model = RaceModel {
+            competitor_min_ns: u64::MAX,
+            competitor_max_ns: u64::MAX,
+            inclusion_bps: 2_500,
+            seed: 42,
+        };
+        let run = || {
+            let mut sim = Simulator::new(model);
+            (0..10_000).filter(|_| sim.race(0)).count()
+        };
+        let landed = run();
+        assert!(
+            (2_200..2_800).contains(&landed),
+            "landed {landed} of 10000 at 25%"
+        );
+        assert_eq!(run(), landed);
+    }
+
+    #[test]
+    fn ledger_overflow_is_still_counted() {
+        let model = RaceModel {
+            competitor_min_ns: 10,
+            competitor_max_ns: 10,
+            inclusion_bps: 10_000,
+            seed: 1,
+        };
+        let mut sim = Simulator::new(model);
+        for i in 0..=SIM_LEDGER_CAPACITY as u32 {
+            let mut pool = [0u8; 20];
+            pool[..4].copy_from_slice(&i.to_le_bytes());
+            sim.settle(Strategy::Sandwich, pool, 2, 1, 0);
+        }
+        assert_eq!(sim.entries().count(), SIM_LEDGER_CAPACITY);
+        assert_eq!(
+            sim.overflow,
+            PnlEntry {
+                emitted: 1,
+                landed: 1,
+                pnl: 1
+            }
+        );
+        assert_eq!(sim.totals.pnl, SIM_LEDGER_CAPACITY as i64 + 1);
+    }
+}
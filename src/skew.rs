@@ -0,0 +1,158 @@
+//! Clock skew of pool-feed relayers.
+//!
+//! Version 3 pool updates carry the time the relayer sent them, so the age
+//! of the pool state an opportunity is priced against can be measured from
+//! when the update left the relayer rather than from when it happened to be
+//! received. That holds only as far as the relayer's clock agrees with the
+//! node's. A relayer running two seconds behind makes every one of its
+//! updates look two seconds old: its feed looks stale, and confidence in
+//! every opportunity priced against it drops to zero, though its updates
+//! are as fresh as anyone's.
+//!
+//! [`SkewFilter`] follows, per feed, the gap between send and receive
+//! stamps with a proportional-integral filter: the proportional term tracks
+//! the offset between the two clocks, the integral term the rate at which
+//! it drifts. Send stamps are moved onto the node's clock with it before
+//! their age is judged. One-way stamps cannot tell clock offset from
+//! transit time, so the filter follows the floor of the gap: it is fed the
+//! one message of every 100 ms window that arrived soonest after it was
+//! sent, and messages queued on the way do not move it. The path's minimum
+//! delay is absorbed into the offset, so a corrected age is the time since
+//! the update would have arrived over an idle path.
+
+/// Window the soonest-arriving message is picked from.
+const WINDOW_NS: u64 = 100_000_000;
+/// Share of a prediction error taken into the offset: 1/2.
+const KP_SHIFT: u32 = 1;
+/// Share of a prediction error taken into the drift over the window: 1/8.
+const KI_SHIFT: u32 = 3;
+/// Largest drift believed, ±500 ppm; crystal oscillators stay within ±100.
+pub const MAX_DRIFT_PPB: i64 = 500_000;
+
+/// Offset and drift of one sender's clock against ours.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SkewFilter {
+    /// Receive minus send stamp expected at `last_rx_ns`: how far the
+    /// sender's clock is behind ours, plus the path's minimum delay.
+    offset_ns: i64,
+    /// How fast the offset grows, in ns per second.
+    drift_ppb: i64,
+    last_rx_ns: u64,
+    window_start_ns: u64,
+    /// Gap and receive stamp of the soonest arrival in the window.
+    window_best: (i64, u64),
+    /// Stamps observed.
+    pub samples: u64,
+}
+
+impl SkewFilter {
+    pub const fn new() -> Self {
+        Self {
+            offset_ns: 0,
+            drift_ppb: 0,
+            last_rx_ns: 0,
+            window_start_ns: 0,
+            window_best: (0, 0),
+            samples: 0,
+        }
+    }
+
+    /// Receive minus send stamp expected at `rx_ns`.
+    #[inline(always)]
+    fn predict(&self, rx_ns: u64) -> i64 {
+        let elapsed = rx_ns.saturating_sub(self.last_rx_ns) as i128;
+        self.offset_ns + (elapsed * self.drift_ppb as i128 / 1_000_000_000) as i64
+    }
+
+    /// A message the sender stamped `sent_ns` arrived at `rx_ns`.
+    pub fn observe(&mut self, sent_ns: u64, rx_ns: u64) {
+        let gap = rx_ns.wrapping_sub(sent_ns) as i64;
+        self.samples += 1;
+        if self.samples == 1 {
+            // A first estimate, refined once the window closes.
+            self.offset_ns = gap;
+            self.last_rx_ns = rx_ns;
+        } else if rx_ns.saturating_sub(self.window_start_ns) >= WINDOW_NS {
+            self.step(self.window_best);
+        } else {
+            let (best_gap, best_rx) = self.window_best;
+            if gap - self.predict(rx_ns) < best_gap - self.predict(best_rx) {
+                self.window_best = (gap, rx_ns);
+            }
+            return;
+        }
+        self.window_start_ns = rx_ns;
+        self.window_best = (gap, rx_ns);
+    }
+
+    /// Move the estimate toward the gap `gap` seen at `rx_ns`.
+    fn step(&mut self, (gap, rx_ns): (i64, u64)) {
+        let predicted = self.predict(rx_ns);
+        let error = gap - predicted;
+        let interval = rx_ns.saturating_sub(self.last_rx_ns).max(1);
+        self.offset_ns = predicted + (error >> KP_SHIFT);
+        let step = ((error >> KI_SHIFT) as i128 * 1_000_000_000 / interval as i128) as i64;
+        self.drift_ppb = (self.drift_ppb + step).clamp(-MAX_DRIFT_PPB, MAX_DRIFT_PPB);
+        self.last_rx_ns = self.last_rx_ns.max(rx_ns);
+    }
+
+    /// `sent_ns` on our clock, for a message received at `rx_ns`: never
+    /// later than `rx_ns`. Unchanged before the first sample.
+    #[inline(always)]
+    pub fn correct(&self, sent_ns: u64, rx_ns: u64) -> u64 {
+        if self.samples == 0 {
+            return sent_ns;
+        }
+        sent_ns
+            .saturating_add_signed(self.predict(rx_ns))
+            .min(rx_ns)
+    }
+
+    pub fn offset_ns(&self) -> i64 {
+        self.offset_ns
+    }
+
+    pub fn drift_ppb(&self) -> i64 {
+        self.drift_ppb
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_a_slow_drifting_clock_past_queueing_spikes() {
+        // The sender runs 2 s behind and gains 50 us a second (50 ppm); the
+        // path takes 40 us, and every 50th message queues 3 ms on the way.
+        let sent =
+            |rx_ns: u64, delay: u64| (rx_ns - delay) - 2_000_000_000 + (rx_ns - delay) / 20_000;
+        let mut clock = SkewFilter::new();
+        assert_eq!(clock.correct(7, 9), 7, "nothing learned yet");
+        let start = 1_700_000_000_000_000_000u64;
+        for i in 0..20_000u64 {
+            let rx = start + i * 1_000_000;
+            let delay = if i % 50 == 25 { 3_040_000 } else { 40_000 };
+            clock.observe(sent(rx, delay), rx);
+        }
+        assert!(
+            (clock.drift_ppb() + 50_000).abs() < 2_000,
+            "drift {}",
+            clock.drift_ppb()
+        );
+
+        let rx = start + 20_000 * 1_000_000;
+        // A message over the idle path is as fresh as it gets...
+        let idle = clock.correct(sent(rx, 40_000), rx);
+        assert!(rx - idle < 20_000, "idle age {}", rx - idle);
+        // ...one that queued is as old as the queueing made it.
+        let queued = clock.correct(sent(rx, 5_040_000), rx);
+        assert!(
+            (rx - queued).abs_diff(5_000_000) < 20_000,
+            "queued age {}",
+            rx - queued
+        );
+        // Never from the future.
+        assert_eq!(clock.correct(sent(rx, 0) + 1_000_000, rx), rx);
+    }
+}
@@ -0,0 +1,152 @@
+//! Latency SLO alarms.
+//!
+//! The main loop already keeps p99s of the hot path (receive to reply
+//! queued, "parse") and of wire-to-wire latency ("end to end"). Once a
+//! second they are checked against the configured thresholds. An SLO that
+//! stays above its threshold for `window` checks in a row raises its alarm,
+//! and one that stays under for as long clears it, so a single slow second
+//! does not page anyone. Alarms are published in [`NodeStats`] and exported
+//! as the `slo_alarm` gauge labelled with the SLO's reason, so alerting
+//! rules need no percentile math of their own.
+//!
+//! [`NodeStats`]: crate::runtime::NodeStats
+
+/// A latency objective.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Slo {
+    /// Hot-path p99: victim received to reply queued.
+    ParseP99 = 0,
+    /// Wire-to-wire p99: RX stamp to TX stamp.
+    EndToEndP99 = 1,
+}
+
+impl Slo {
+    pub const COUNT: usize = 2;
+    pub const ALL: [Slo; Self::COUNT] = [Slo::ParseP99, Slo::EndToEndP99];
+
+    /// Reason label value.
+    pub const fn reason(self) -> &'static str {
+        match self {
+            Slo::ParseP99 => "parse_p99",
+            Slo::EndToEndP99 => "e2e_p99",
+        }
+    }
+
+    /// Bit of this SLO in an alarm mask.
+    pub const fn bit(self) -> u64 {
+        1 << self as u64
+    }
+}
+
+/// `MEV_SLO_PARSE_P99_NS`, `MEV_SLO_E2E_P99_NS` and `MEV_SLO_WINDOW`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SloPolicy {
+    /// Threshold in ns per SLO, by `Slo` index; `None` is not monitored.
+    pub thresholds_ns: [Option<u64>; Slo::COUNT],
+    /// Consecutive checks to raise or clear an alarm.
+    pub window: u32,
+}
+
+impl Default for SloPolicy {
+    fn default() -> Self {
+        Self {
+            thresholds_ns: [None; Slo::COUNT],
+            window: 10,
+        }
+    }
+}
+
+impl SloPolicy {
+    pub fn is_enabled(&self) -> bool {
+        self.thresholds_ns.iter().any(Option::is_some)
+    }
+}
+
+/// An alarm raised or cleared by [`SloMonitor::check`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SloChange {
+    pub slo: Slo,
+    pub firing: bool,
+    pub p99_ns: u64,
+    pub threshold_ns: u64,
+}
+
+pub struct SloMonitor {
+    policy: SloPolicy,
+    /// Consecutive checks disagreeing with the alarm's current state.
+    streak: [u32; Slo::COUNT],
+    alarms: u64,
+}
+
+impl SloMonitor {
+    pub fn new(policy: SloPolicy) -> Self {
+        Self {
+            policy,
+            streak: [0; Slo::COUNT],
+            alarms: 0,
+        }
+    }
+
+    /// Bit mask of firing alarms ([`Slo::bit`]).
+    pub fn alarms(&self) -> u64 {
+        self.alarms
+    }
+
+    /// Check this period's p99s, by `Slo` index. `on_change` is called for
+    /// every alarm raised or cleared.
+    pub fn check(&mut self, p99_ns: [u64; Slo::COUNT], mut on_change: impl FnMut(SloChange)) {
+        for slo in Slo::ALL {
+            let i = slo as usize;
+            let Some(threshold_ns) = self.policy.thresholds_ns[i] else {
+                continue;
+            };
+            let firing = self.alarms & slo.bit() != 0;
+            if (p99_ns[i] > threshold_ns) == firing {
+                self.streak[i] = 0;
+                continue;
+            }
+            self.streak[i] += 1;
+            if self.streak[i] >= self.policy.window.max(1) {
+                self.streak[i] = 0;
+                self.alarms ^= slo.bit();
+                on_change(SloChange {
+                    slo,
+                    firing: !firing,
+                    p99_ns: p99_ns[i],
+                    threshold_ns,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alarms_need_a_full_window_to_raise_and_clear() {
+        let policy = SloPolicy {
+            thresholds_ns: [Some(1_000), None],
+            window: 3,
+        };
+        let mut monitor = SloMonitor::new(policy);
+        let mut changes = Vec::new();
+        for parse in [2_000, 2_000, 500, 2_000, 2_000, 2_000, 900, 900, 3_000, 900] {
+            monitor.check([parse, u64::MAX], |change| {
+                changes.push((change.slo, change.firing, change.p99_ns))
+            });
+        }
+        // The dip at the third check restarts the count; the end-to-end
+        // SLO has no threshold and never fires.
+        assert_eq!(changes, [(Slo::ParseP99, true, 2_000)]);
+        assert_eq!(monitor.alarms(), Slo::ParseP99.bit());
+        for _ in 0..2 {
+            monitor.check([900, 0], |change| {
+                changes.push((change.slo, change.firing, change.p99_ns))
+            });
+        }
+        assert_eq!(changes[1..], [(Slo::ParseP99, false, 900)]);
+        assert_eq!(monitor.alarms(), 0);
+    }
+}
@@ -0,0 +1,176 @@
+//! Source allow-listing for ingest traffic.
+//!
+//! Only datagrams and connections from configured IPv4 prefixes / port ranges
+//! are allowed to reach the parser, so stray traffic on the interface cannot
+//! inject fake swaps or prices. An empty filter allows everything.
+//!
+//! Rules are held in a fixed-capacity `heapless::Vec`; matching is a linear
+//! scan over at most [`MAX_SOURCE_RULES`] entries with no allocation.
+use heapless::Vec;
+
+pub const MAX_SOURCE_RULES: usize = 16;
+
+/// One allowed source: IPv4 prefix plus inclusive port range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceRule {
+    network: u32,
+    mask: u32,
+    port_lo: u16,
+    port_hi: u16,
+}
+
+impl SourceRule {
+    pub fn new(addr: [u8; 4], prefix_len: u8, port_lo: u16, port_hi: u16) -> Self {
+        let mask = match prefix_len {
+            0 => 0,
+            n => u32::MAX << (32 - n.min(32) as u32),
+        };
+        Self {
+            network: u32::from_be_bytes(addr) & mask,
+            mask,
+            port_lo,
+            port_hi,
+        }
+    }
+
+    #[inline(always)]
+    pub fn matches(&self, addr: [u8; 4], port: u16) -> bool {
+        u32::from_be_bytes(addr) & self.mask == self.network
+            && (self.port_lo..=self.port_hi).contains(&port)
+    }
+
+    /// Parse `a.b.c.d[/len][:port[-port]]`.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (ip_part, ports) = match spec.split_once(':') {
+            Some((ip, ports)) => (ip, Some(ports)),
+            None => (spec, None),
+        };
+        let (ip, prefix_len) = match ip_part.split_once('/') {
+            Some((ip, len)) => (ip, len.parse::<u8>().ok().filter(|&l| l <= 32)?),
+            None => (ip_part, 32),
+        };
+        let addr: core::net::Ipv4Addr = ip.parse().ok()?;
+        let (port_lo, port_hi) = match ports {
+            None => (0, u16::MAX),
+            Some(p) => match p.split_once('-') {
+                Some((lo, hi)) => (lo.parse().ok()?, hi.parse().ok()?),
+                None => {
+                    let port = p.parse().ok()?;
+                    (port, port)
+                }
+            },
+        };
+        (port_lo <= port_hi).then(|| Self::new(addr.octets(), prefix_len, port_lo, port_hi))
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SourceFilter {
+    rules: Vec<SourceRule, MAX_SOURCE_RULES>,
+}
+
+impl SourceFilter {
+    /// Filter that allows every source.
+    pub const fn allow_all() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Parse a comma-separated list of [`SourceRule`] specs.
+    ///
+    /// Returns `None` if any rule is malformed or there are more than
+    /// [`MAX_SOURCE_RULES`] rules.
+    pub fn parse(list: &str) -> Option<Self> {
+        let mut filter = Self::allow_all();
+        for spec in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            filter.push(SourceRule::parse(spec)?).ok()?;
+        }
+        Some(filter)
+    }
+
+    pub fn push(&mut self, rule: SourceRule) -> Result<(), SourceRule> {
+        self.rules.push(rule)
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn allows(&self, addr: [u8; 4], port: u16) -> bool {
+        self.rules.is_empty() || self.rules.iter().any(|r| r.matches(addr, port))
+    }
+
+    /// Check a raw Ethernet/IPv4/UDP-or-TCP frame before any payload parsing
+    /// (AF_XDP / raw backends). Non-IPv4 and truncated frames are rejected
+    /// when the filter is non-empty.
+    #[inline(always)]
+    pub fn allows_frame(&self, frame: &[u8]) -> bool {
+        if self.rules.is_empty() {
+            return true;
+        }
+        match frame_source(frame) {
+            Some((addr, port)) => self.allows(addr, port),
+            None => false,
+        }
+    }
+}
+
+/// Extract (source IPv4, source port) from an Ethernet II frame.
+#[inline(always)]
+pub fn frame_source(frame: &[u8]) -> Option<([u8; 4], u16)> {
+    const ETH_HDR: usize = 14;
+    if frame.get(12..14)? != [0x08, 0x00] {
+        return None;
+    }
+    let ip = frame.get(ETH_HDR..)?;
+    let ihl = ((*ip.first()? & 0x0F) as usize) * 4;
+    let proto = *ip.get(9)?;
+    if ihl < 20 || !(proto == 6 || proto == 17) {
+        return None;
+    }
+    let addr: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+    let l4 = ip.get(ihl..ihl + 2)?;
+    Some((addr, u16::from_be_bytes([l4[0], l4[1]])))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn udp_frame(src: [u8; 4], sport: u16) -> [u8; 42] {
+        let mut f = [0u8; 42];
+        f[12] = 0x08;
+        f[14] = 0x45;
+        f[23] = 17;
+        f[26..30].copy_from_slice(&src);
+        f[34..36].copy_from_slice(&sport.to_be_bytes());
+        f
+    }
+
+    #[test]
+    fn parses_prefixes_and_port_ranges() {
+        let f = SourceFilter::parse("192.168.69.0/24, 10.0.0.1:9000-9100").expect("valid list");
+        assert!(f.allows([192, 168, 69, 7], 1234));
+        assert!(!f.allows([192, 168, 70, 7], 1234));
+        assert!(f.allows([10, 0, 0, 1], 9050));
+        assert!(!f.allows([10, 0, 0, 1], 8999));
+        assert!(SourceFilter::parse("300.1.1.1").is_none());
+        assert!(SourceFilter::parse("1.2.3.4:90-10").is_none());
+    }
+
+    #[test]
+    fn empty_filter_allows_everything() {
+        let f = SourceFilter::allow_all();
+        assert!(f.allows([1, 2, 3, 4], 1));
+        assert!(f.allows_frame(&[]));
+    }
+
+    #[test]
+    fn checks_raw_frames_before_parse() {
+        let f = SourceFilter::parse("192.168.69.1:54321").unwrap();
+        assert!(f.allows_frame(&udp_frame([192, 168, 69, 1], 54321)));
+        assert!(!f.allows_frame(&udp_frame([192, 168, 69, 9], 54321)));
+        assert!(!f.allows_frame(&udp_frame([192, 168, 69, 1], 54321)[..30]));
+    }
+}
@@ -0,0 +1,229 @@
+//! Traffic and edge broken down by ingest source.
+//!
+//! The node-wide counters say how much came in and how many opportunities
+//! it turned into; [`SourceStats`] says from whom. Each sender IPv4 address
+//! (a relayer, a feed, an executor connection) gets a slot in a small table
+//! the first time it is seen, counting its frames, the malformed ones among
+//! them, and the opportunities and profit found in its victims. Senders past
+//! [`MAX_SOURCES`] share one overflow slot.
+//!
+//! Slots are claimed with a compare-and-swap and counted with relaxed
+//! atomics, so the table can sit in [`NodeStats`] and be read by exporters
+//! while the packet loop writes it.
+//!
+//! [`NodeStats`]: crate::runtime::NodeStats
+use core::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// Sources tracked individually.
+pub const MAX_SOURCES: usize = 16;
+
+/// Counters of one source.
+pub struct SourceCounters {
+    /// IPv4 address, big-endian; 0 while the slot is free.
+    addr: AtomicU32,
+    pub rx_packets: AtomicU64,
+    /// Frames that failed parsing or validation.
+    pub malformed: AtomicU64,
+    pub opportunities: AtomicU64,
+    /// Sum of the profit of those opportunities, in numéraire units.
+    pub profit: AtomicU64,
+}
+
+impl SourceCounters {
+    const fn new() -> Self {
+        Self {
+            addr: AtomicU32::new(0),
+            rx_packets: AtomicU64::new(0),
+            malformed: AtomicU64::new(0),
+            opportunities: AtomicU64::new(0),
+            profit: AtomicU64::new(0),
+        }
+    }
+
+    #[inline(always)]
+    pub fn count_rx(&self) {
+        self.rx_packets.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn count_malformed(&self) {
+        self.malformed.fetch_add(1, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn count_opportunity(&self, profit: u64) {
+        self.opportunities.fetch_add(1, Ordering::Relaxed);
+        self.profit.fetch_add(profit, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self, addr: Option<[u8; 4]>) -> SourceSnapshot {
+        SourceSnapshot {
+            addr,
+            rx_packets: self.rx_packets.load(Ordering::Relaxed),
+            malformed: self.malformed.load(Ordering::Relaxed),
+            opportunities: self.opportunities.load(Ordering::Relaxed),
+            profit: self.profit.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// A source's counters at one point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceSnapshot {
+    /// `None` for the overflow slot.
+    pub addr: Option<[u8; 4]>,
+    pub rx_packets: u64,
+    pub malformed: u64,
+    pub opportunities: u64,
+    pub profit: u64,
+}
+
+impl SourceSnapshot {
+    /// `10.0.0.9`, or `other` for the overflow slot.
+    pub fn label(&self) -> SourceLabel {
+        SourceLabel(self.addr)
+    }
+}
+
+/// Display form of a source address.
+pub struct SourceLabel(Option<[u8; 4]>);
+
+impl core::fmt::Display for SourceLabel {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0 {
+            Some([a, b, c, d]) => write!(f, "{a}.{b}.{c}.{d}"),
+            None => write!(f, "other"),
+        }
+    }
+}
+
+pub struct SourceStats {
+    slots: [SourceCounters; MAX_SOURCES],
+    /// Sources that found the table full, and 0.0.0.0.
+    other: SourceCounters,
+}
+
+impl Default for SourceStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SourceStats {
+    pub const fn new() -> Self {
+        Self {
+            slots: [const { SourceCounters::new() }; MAX_SOURCES],
+            other: SourceCounters::new(),
+        }
+    }
+
+    /// Counters of `addr`, claiming a slot on first sight.
+    #[inline]
+    pub fn source(&self, addr: [u8; 4]) -> &SourceCounters {
+        let key = u32::from_be_bytes(addr);
+        if key == 0 {
+            return &self.other;
+        }
+        for slot in &self.slots {
+            match slot.addr.load(Ordering::Relaxed) {
+                k if k == key => return slot,
+                0 => match slot
+                    .addr
+                    .compare_exchange(0, key, Ordering::Relaxed, Ordering::Relaxed)
+                {
+                    Ok(_) => return slot,
+                    // Another writer claimed it meanwhile, maybe for `addr`.
+                    Err(k) if k == key => return slot,
+                    Err(_) => {}
+                },
+                _ => {}
+            }
+        }
+        &self.other
+    }
+
+    /// Every source seen so far, in order of first sight, then the overflow
+    /// slot if anything landed there.
+    pub fn for_each(&self, mut f: impl FnMut(SourceSnapshot)) {
+        for slot in &self.slots {
+            let key = slot.addr.load(Ordering::Relaxed);
+            if key == 0 {
+                break;
+            }
+            f(slot.snapshot(Some(key.to_be_bytes())));
+        }
+        let other = self.other.snapshot(None);
+        if other.rx_packets + other.malformed + other.opportunities != 0 {
+            f(other);
+        }
+    }
+}
+
+/// One line of JSON: `{"sources":[{"source":"10.0.0.9","rx_packets":..,..},..]}`.
+impl core::fmt::Display for SourceStats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{{\"sources\":[")?;
+        let mut result = Ok(());
+        let mut first = true;
+        self.for_each(|s| {
+            let sep = if core::mem::take(&mut first) { "" } else { "," };
+            result = result.and_then(|()| {
+                write!(
+                    f,
+                    "{sep}{{\"source\":\"{}\",\"rx_packets\":{},\"malformed\":{},\"opportunities\":{},\"profit\":{}}}",
+                    s.label(),
+                    s.rx_packets,
+                    s.malformed,
+                    s.opportunities,
+                    s.profit
+                )
+            });
+        });
+        result?;
+        write!(f, "]}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sources_get_a_slot_until_the_table_is_full() {
+        let stats = SourceStats::new();
+        stats.source([10, 0, 0, 9]).count_rx();
+        stats.source([10, 0, 0, 9]).count_opportunity(500);
+        stats.source([10, 0, 0, 7]).count_malformed();
+        for i in 0..MAX_SOURCES as u8 {
+            stats.source([192, 168, 0, i + 1]).count_rx();
+        }
+        stats.source([0; 4]).count_rx();
+
+        let mut seen = Vec::new();
+        stats.for_each(|s| seen.push(s));
+        assert_eq!(seen.len(), MAX_SOURCES + 1);
+        assert_eq!(
+            seen[0],
+            SourceSnapshot {
+                addr: Some([10, 0, 0, 9]),
+                rx_packets: 1,
+                malformed: 0,
+                opportunities: 1,
+                profit: 500
+            }
+        );
+        assert_eq!(
+            (seen[1].label().to_string(), seen[1].malformed),
+            ("10.0.0.7".to_string(), 1)
+        );
+        // The last 192.168.0.x sources and 0.0.0.0 overflowed.
+        let other = seen[MAX_SOURCES];
+        assert_eq!(
+            (other.label().to_string(), other.rx_packets),
+            ("other".to_string(), 3)
+        );
+        assert!(stats.to_string().starts_with(
+            "{\"sources\":[{\"source\":\"10.0.0.9\",\"rx_packets\":1,\"malformed\":0,\"opportunities\":1,\"profit\":500},"
+        ));
+    }
+}
@@ -0,0 +1,387 @@
+//! Pool-state sources, and which one wins when they disagree.
+//!
+//! Pool reserves can reach the node from more than one place: the wire feed
+//! (UDP and the relayer session), a Geyser plugin stream, JSON-RPC polling,
+//! or a snapshot file written by `dump-pools --bin`. Everything but the wire
+//! feed is a [`StateSource`]: polled from the main loop, it hands over
+//! `PoolStateUpdate` records, which are validated and applied like the
+//! feed's own. Only snapshot files are implemented here; Geyser and
+//! JSON-RPC need clients the node does not link, and plug in through the
+//! same trait.
+//!
+//! Every pool-table entry remembers which source set it. Before an update
+//! is applied, [`SourceArbiter::admit`] compares the two:
+//! - an update from the source that set the entry is applied, so a single
+//!   source behaves exactly as the wire feed alone always has;
+//! - one from another source is applied for a later slot, and for the same
+//!   slot if it ranks at least as high (lower `priority`) or the entry's
+//!   source has gone stale: delivered nothing for `stale_after_ms`;
+//! - anything else is counted as stale (an older slot) or outranked.
+//!
+//! Per source the arbiter counts applied, stale and outranked updates and
+//! keeps the newest slot and time of the last update, served as JSON on
+//! the admin `pool sources` command.
+use crate::pool_registry::PoolEntry;
+use crate::validator::PoolStateUpdate;
+use std::io;
+
+/// Index of a registered source; stored in each pool-table entry.
+pub type SourceId = u8;
+
+/// The wire feed, registered first by [`SourceArbiter::new`].
+pub const WIRE_FEED: SourceId = 0;
+
+/// Sources the arbiter tracks, the wire feed included.
+pub const MAX_SOURCES: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SourceKind {
+    WireFeed = 0,
+    Geyser = 1,
+    RpcPoll = 2,
+    Snapshot = 3,
+}
+
+impl SourceKind {
+    pub const COUNT: usize = 4;
+    pub const ALL: [SourceKind; Self::COUNT] = [
+        SourceKind::WireFeed,
+        SourceKind::Geyser,
+        SourceKind::RpcPoll,
+        SourceKind::Snapshot,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            SourceKind::WireFeed => "wire",
+            SourceKind::Geyser => "geyser",
+            SourceKind::RpcPoll => "rpc",
+            SourceKind::Snapshot => "snapshot",
+        }
+    }
+
+    /// Priority unless configured otherwise: the wire feed first, a
+    /// snapshot last.
+    pub const fn default_priority(self) -> u8 {
+        self as u8
+    }
+}
+
+/// Which sources to run and how to rank them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StateSourceConfig {
+    /// Snapshot file to apply at startup.
+    pub snapshot: Option<String>,
+    /// Priority per [`SourceKind`], indexed by `kind as usize`.
+    pub priority: [u8; SourceKind::COUNT],
+    /// Silence after which a source no longer outranks others.
+    pub stale_after_ms: u64,
+}
+
+impl StateSourceConfig {
+    pub fn priority(&self, kind: SourceKind) -> u8 {
+        self.priority[kind as usize]
+    }
+
+    /// The arbiter for these settings, with only the wire feed registered.
+    pub fn arbiter(&self) -> SourceArbiter {
+        SourceArbiter::new(self.priority(SourceKind::WireFeed), self.stale_after_ms)
+    }
+}
+
+impl Default for StateSourceConfig {
+    fn default() -> Self {
+        Self {
+            snapshot: None,
+            priority: SourceKind::ALL.map(SourceKind::default_priority),
+            stale_after_ms: 2_000,
+        }
+    }
+}
+
+/// A pool-state source other than the wire feed.
+pub trait StateSource {
+    fn kind(&self) -> SourceKind;
+
+    /// Where the updates come from, for logs.
+    fn name(&self) -> &str;
+
+    /// Hand every update available now to `apply`, one `PoolStateUpdate`
+    /// record at a time, without blocking. `Ok(false)` once the source has
+    /// nothing more to give and can be dropped.
+    fn poll(&mut self, apply: &mut dyn FnMut(&[u8])) -> io::Result<bool>;
+}
+
+/// A snapshot file of back-to-back `PoolStateUpdate` records, applied once.
+pub struct SnapshotSource {
+    path: String,
+}
+
+impl SnapshotSource {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl StateSource for SnapshotSource {
+    fn kind(&self) -> SourceKind {
+        SourceKind::Snapshot
+    }
+
+    fn name(&self) -> &str {
+        &self.path
+    }
+
+    fn poll(&mut self, apply: &mut dyn FnMut(&[u8])) -> io::Result<bool> {
+        let snapshot = std::fs::read(&self.path)?;
+        if snapshot.len() % PoolStateUpdate::WIRE_SIZE != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a whole number of pool records",
+            ));
+        }
+        snapshot
+            .chunks_exact(PoolStateUpdate::WIRE_SIZE)
+            .for_each(apply);
+        Ok(false)
+    }
+}
+
+/// Whether an update may overwrite the pool-table entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    Apply,
+    /// For a slot older than the entry's, set by another source.
+    Stale,
+    /// For the entry's slot, from a source ranked below the fresh one that
+    /// set it.
+    Outranked,
+}
+
+/// Freshness and arbitration counts of one source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceHealth {
+    pub kind: SourceKind,
+    /// Lower wins a same-slot disagreement.
+    pub priority: u8,
+    /// Silence after which the source no longer outranks others.
+    pub stale_after_ms: u64,
+    pub applied: u64,
+    pub stale: u64,
+    pub outranked: u64,
+    /// Newest slot the source delivered.
+    pub newest_slot: u64,
+    /// Unix time of its last update, 0 before the first.
+    pub last_update_ms: u64,
+}
+
+impl SourceHealth {
+    const fn new(kind: SourceKind, priority: u8, stale_after_ms: u64) -> Self {
+        Self {
+            kind,
+            priority,
+            stale_after_ms,
+            applied: 0,
+            stale: 0,
+            outranked: 0,
+            newest_slot: 0,
+            last_update_ms: 0,
+        }
+    }
+
+    pub fn is_fresh(&self, now_ms: u64) -> bool {
+        self.last_update_ms != 0
+            && now_ms.saturating_sub(self.last_update_ms) <= self.stale_after_ms
+    }
+}
+
+pub struct SourceArbiter {
+    sources: heapless::Vec<SourceHealth, MAX_SOURCES>,
+}
+
+impl SourceArbiter {
+    /// An arbiter with the wire feed registered as [`WIRE_FEED`].
+    pub fn new(wire_priority: u8, stale_after_ms: u64) -> Self {
+        let mut sources = heapless::Vec::new();
+        let _ = sources.push(SourceHealth::new(
+            SourceKind::WireFeed,
+            wire_priority,
+            stale_after_ms,
+        ));
+        Self { sources }
+    }
+
+    /// Track another source; `None` if [`MAX_SOURCES`] are registered.
+    pub fn register(
+        &mut self,
+        kind: SourceKind,
+        priority: u8,
+        stale_after_ms: u64,
+    ) -> Option<SourceId> {
+        let id = self.sources.len() as SourceId;
+        self.sources
+            .push(SourceHealth::new(kind, priority, stale_after_ms))
+            .ok()?;
+        Some(id)
+    }
+
+    pub fn sources(&self) -> &[SourceHealth] {
+        &self.sources
+    }
+
+    /// Decide on an update for `slot` from `source` against the pool's
+    /// current `entry`, and count the outcome. `now_ms` is unix time.
+    pub fn admit(
+        &mut self,
+        source: SourceId,
+        entry: &PoolEntry,
+        slot: u64,
+        now_ms: u64,
+    ) -> Verdict {
+        let verdict = match self.sources.get(entry.source as usize) {
+            _ if entry.source == source || slot > entry.slot => Verdict::Apply,
+            _ if slot < entry.slot => Verdict::Stale,
+            Some(owner) if owner.is_fresh(now_ms) && owner.priority < self.priority(source) => {
+                Verdict::Outranked
+            }
+            _ => Verdict::Apply,
+        };
+        let Some(health) = self.sources.get_mut(source as usize) else {
+            return verdict;
+        };
+        match verdict {
+            Verdict::Apply => health.applied += 1,
+            Verdict::Stale => health.stale += 1,
+            Verdict::Outranked => health.outranked += 1,
+        }
+        health.newest_slot = health.newest_slot.max(slot);
+        health.last_update_ms = now_ms;
+        verdict
+    }
+
+    fn priority(&self, source: SourceId) -> u8 {
+        self.sources
+            .get(source as usize)
+            .map_or(u8::MAX, |s| s.priority)
+    }
+
+    /// Per-source counts as JSON, freshness judged at `now_ms`.
+    pub fn to_json(&self, now_ms: u64) -> String {
+        let mut out = String::from("{\"sources\":[");
+        for (id, s) in self.sources.iter().enumerate() {
+            if id != 0 {
+                out.push(',');
+            }
+            out.push_str(&format!(
+                "{{\"id\":{id},\"kind\":\"{}\",\"priority\":{},\"fresh\":{},\"applied\":{},\"stale\":{},\
+                 \"outranked\":{},\"newest_slot\":{},\"last_update_ms\":{}}}",
+                s.kind.name(),
+                s.priority,
+                s.is_fresh(now_ms),
+                s.applied,
+                s.stale,
+                s.outranked,
+                s.newest_slot,
+                s.last_update_ms
+            ));
+        }
+        out.push_str("]}");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::processor::mock_pool_entry;
+
+    #[test]
+    fn disagreements_go_to_the_newer_slot_then_the_fresh_higher_priority_source() {
+        let mut arbiter = SourceArbiter::new(0, 1_000);
+        let rpc = arbiter.register(SourceKind::RpcPoll, 2, 1_000).unwrap();
+        let entry = |slot, source| PoolEntry {
+            slot,
+            source,
+            ..mock_pool_entry([0xAB; 20])
+        };
+
+        // The wire feed set slot 100 at t=5000.
+        assert_eq!(
+            arbiter.admit(WIRE_FEED, &entry(99, WIRE_FEED), 100, 5_000),
+            Verdict::Apply
+        );
+        assert_eq!(
+            arbiter.admit(rpc, &entry(100, WIRE_FEED), 99, 5_100),
+            Verdict::Stale
+        );
+        assert_eq!(
+            arbiter.admit(rpc, &entry(100, WIRE_FEED), 100, 5_200),
+            Verdict::Outranked
+        );
+        assert_eq!(
+            arbiter.admit(rpc, &entry(100, WIRE_FEED), 101, 5_300),
+            Verdict::Apply
+        );
+        // Its own entries are never second-guessed; a silent feed no longer
+        // outranks anyone.
+        assert_eq!(
+            arbiter.admit(rpc, &entry(101, rpc), 90, 5_400),
+            Verdict::Apply
+        );
+        assert_eq!(
+            arbiter.admit(rpc, &entry(100, WIRE_FEED), 100, 6_001),
+            Verdict::Apply
+        );
+        assert_eq!(
+            arbiter.admit(WIRE_FEED, &entry(101, rpc), 101, 6_100),
+            Verdict::Apply
+        );
+
+        let wire = arbiter.sources()[WIRE_FEED as usize];
+        assert_eq!(
+            (
+                wire.applied,
+                wire.newest_slot,
+                wire.is_fresh(6_500),
+                wire.is_fresh(7_101)
+            ),
+            (2, 101, true, false)
+        );
+        let rpc = arbiter.sources()[rpc as usize];
+        assert_eq!(
+            (rpc.applied, rpc.stale, rpc.outranked, rpc.newest_slot),
+            (3, 1, 1, 101)
+        );
+    }
+
+    #[test]
+    fn snapshot_source_delivers_its_records_once() {
+        let path = std::env::temp_dir().join(format!("mev-snapshot-{}.bin", std::process::id()));
+        let records = [
+            PoolStateUpdate::new([0x01; 20], 10, 20, 7, 1),
+            PoolStateUpdate::new([0x02; 20], 30, 40, 8, 2),
+        ];
+        std::fs::write(
+            &path,
+            records
+                .iter()
+                .flat_map(|r| zerocopy::IntoBytes::as_bytes(r).to_vec())
+                .collect::<Vec<_>>(),
+        )
+        .unwrap();
+        let mut source = SnapshotSource::new(path.to_str().unwrap());
+        let mut seen = Vec::new();
+        assert!(!source.poll(&mut |record| seen.push(record[0])).unwrap());
+        assert_eq!(
+            (source.kind(), seen),
+            (SourceKind::Snapshot, vec![0x01, 0x02])
+        );
+
+        std::fs::write(&path, [0u8; 10]).unwrap();
+        assert_eq!(
+            source.poll(&mut |_| {}).unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,244 @@
+//! Push exporter: node counters to a statsd collector over UDP.
+//!
+//! A background thread wakes every `interval_ms`, reads [`NodeStats`] and
+//! sends each counter's increase since the last push as a statsd counter
+//! (`<prefix>.<name>:<delta>|c`) and each gauge as is
+//! (`<prefix>.<name>:<value>|g`), followed by the p50/p90/p99 of each
+//! strategy's profit histogram (`<prefix>.profit.<strategy>.p50`, upper
+//! bucket bounds in numéraire units) and of the victim RX batch size
+//! (`<prefix>.rx_batch.p50`, frames), and the p50/p99 of each AF_XDP frame
+//! dwell leg (`<prefix>.frame_dwell.kernel.p99`, cycles). Latency SLO alarms go out as one
+//! `slo_alarm` gauge per SLO, 1 while firing, labelled with a DogStatsD tag
+//! (`<prefix>.slo_alarm:1|g|#reason:parse_p99`). The per-source breakdown
+//! goes out the same way, as running totals tagged with the sender
+//! (`<prefix>.source.rx_packets:1520|g|#source:10.0.0.9`), and so does the
+//! PnL ledger, valued in the numéraire at the prices of each push
+//! (`<prefix>.pnl.edge_value:250000000|g|#token:WSOL`, `pnl.est_costs`,
+//! `pnl.paid_costs`; see [`crate::pnl`]). Lines are
+//! packed into datagrams of at most [`MAX_DATAGRAM`] bytes. The same wire
+//! format feeds OpenTelemetry collectors through their statsd receiver.
+//!
+//! The hot loop only ever touches the atomics it already increments; the
+//! exporter reads them with relaxed loads, off the pinned core.
+use crate::prices::PriceCache;
+use crate::processor::Strategy;
+use crate::runtime::NodeStats;
+use crate::slo::Slo;
+use crate::tokens::{self, ProfitNormalizer};
+use std::io;
+use std::net::{SocketAddrV4, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Largest datagram sent; fits a 1500-byte MTU with IP and UDP headers.
+pub const MAX_DATAGRAM: usize = 1432;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatsdConfig {
+    /// Collector address.
+    pub target: SocketAddrV4,
+    pub interval_ms: u64,
+    /// Prepended to every metric name, followed by a dot.
+    pub prefix: String,
+}
+
+/// Counter values at the last push, to turn totals into deltas.
+pub struct StatsdEncoder<const N: usize> {
+    prefix: String,
+    last: [u64; N],
+}
+
+impl<const N: usize> StatsdEncoder<N> {
+    pub fn new(prefix: &str) -> Self {
+        Self {
+            prefix: prefix.to_owned(),
+            last: [0; N],
+        }
+    }
+
+    /// Encode one push into `datagrams` (cleared first): counters as the
+    /// increase since the previous call, gauges as their value, and
+    /// `tagged` gauges `(name, tag, value)` with their `key:value` tag.
+    pub fn encode(
+        &mut self,
+        counters: &[(&str, u64); N],
+        gauges: &[(&str, u64)],
+        tagged: &[(&str, &str, u64)],
+        datagrams: &mut Vec<String>,
+    ) {
+        datagrams.clear();
+        let mut current = String::new();
+        let mut emit = |line: &str, current: &mut String| {
+            if !current.is_empty() && current.len() + 1 + line.len() > MAX_DATAGRAM {
+                datagrams.push(std::mem::take(current));
+            }
+            if !current.is_empty() {
+                current.push('\n');
+            }
+            current.push_str(line);
+        };
+        for ((name, value), last) in counters.iter().zip(self.last.iter_mut()) {
+            let delta = value.wrapping_sub(*last);
+            *last = *value;
+            emit(&format!("{}.{name}:{delta}|c", self.prefix), &mut current);
+        }
+        for (name, value) in gauges {
+            emit(&format!("{}.{name}:{value}|g", self.prefix), &mut current);
+        }
+        for (name, tag, value) in tagged {
+            emit(
+                &format!("{}.{name}:{value}|g|#{tag}", self.prefix),
+                &mut current,
+            );
+        }
+        if !current.is_empty() {
+            datagrams.push(current);
+        }
+    }
+}
+
+/// `slo_alarm` gauges, tagged with `tags` (`reason:<slo>`, by `Slo` index).
+fn slo_alarms<'a>(
+    stats: &NodeStats,
+    tags: &'a [String; Slo::COUNT],
+) -> [(&'static str, &'a str, u64); Slo::COUNT] {
+    let alarms = stats.slo_alarms();
+    core::array::from_fn(|i| ("slo_alarm", tags[i].as_str(), alarms[i].1))
+}
+
+/// Start the exporter thread; it pushes every `interval_ms` until `stop`
+/// is set, valuing PnL with `normalizer` at `prices`. The thread runs on
+/// `core`.
+pub fn spawn(
+    config: StatsdConfig,
+    stats: &'static NodeStats,
+    (normalizer, prices): (ProfitNormalizer, &'static PriceCache),
+    stop: &'static AtomicBool,
+    core: crate::affinity::CoreSlot,
+) -> io::Result<JoinHandle<()>> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect(config.target)?;
+    std::thread::Builder::new()
+        .name("statsd".into())
+        .spawn(move || {
+            crate::affinity::pin_current_thread(core);
+            let mut encoder = StatsdEncoder::new(&config.prefix);
+            let mut datagrams = Vec::new();
+            let profit_names: Vec<[String; 3]> = Strategy::ALL
+                .iter()
+                .map(|s| ["p50", "p90", "p99"].map(|p| format!("profit.{}.{p}", s.name())))
+                .collect();
+            let dwell_names = stats
+                .frame_dwell
+                .legs()
+                .map(|(leg, _)| ["p50", "p99"].map(|p| format!("frame_dwell.{leg}.{p}")));
+            let slo_tags = Slo::ALL.map(|slo| format!("reason:{}", slo.reason()));
+            let token_tags = tokens::TOKENS.map(|t| format!("token:{}", t.symbol_str()));
+            let interval = Duration::from_millis(config.interval_ms);
+            while !stop.load(Ordering::Relaxed) {
+                std::thread::sleep(interval);
+                let pnl = stats.pnl.snapshot(&normalizer, Some(prices));
+                let mut gauges = stats.gauges().to_vec();
+                gauges.extend([
+                    ("pnl.est_costs", pnl.est_costs),
+                    ("pnl.paid_costs", pnl.paid_costs),
+                ]);
+                for (name, pct) in [
+                    ("rx_batch.p50", 50),
+                    ("rx_batch.p90", 90),
+                    ("rx_batch.p99", 99),
+                ] {
+                    gauges.push((name, stats.rx_batch_sizes.percentile(pct) as u64));
+                }
+                for (strategy, names) in Strategy::ALL.iter().zip(&profit_names) {
+                    let profit = stats.profit[*strategy as usize].snapshot();
+                    for (name, pct) in names.iter().zip([50, 90, 99]) {
+                        gauges.push((name.as_str(), profit.percentile(pct)));
+                    }
+                }
+                for ((_, dwell), names) in stats.frame_dwell.legs().iter().zip(&dwell_names) {
+                    let dwell = dwell.snapshot();
+                    for (name, pct) in names.iter().zip([50, 99]) {
+                        gauges.push((name.as_str(), dwell.percentile(pct)));
+                    }
+                }
+                let mut sources = Vec::new();
+                stats.sources.for_each(|source| {
+                    sources.push((format!("source:{}", source.label()), source))
+                });
+                let mut tagged = slo_alarms(stats, &slo_tags).to_vec();
+                for (tag, source) in &sources {
+                    tagged.extend([
+                        ("source.rx_packets", tag.as_str(), source.rx_packets),
+                        ("source.malformed", tag.as_str(), source.malformed),
+                        ("source.opportunities", tag.as_str(), source.opportunities),
+                        ("source.profit", tag.as_str(), source.profit),
+                    ]);
+                }
+                for (tag, token) in token_tags.iter().zip(&pnl.tokens) {
+                    tagged.extend([
+                        ("pnl.opportunities", tag.as_str(), token.opportunities),
+                        ("pnl.edge_value", tag.as_str(), token.edge_value),
+                        ("pnl.landed", tag.as_str(), token.landed),
+                        ("pnl.realized_value", tag.as_str(), token.realized_value),
+                    ]);
+                }
+                encoder.encode(&stats.counters(), &gauges, &tagged, &mut datagrams);
+                for datagram in &datagrams {
+                    if let Err(e) = socket.send(datagram.as_bytes()) {
+                        log::debug!("statsd push to {} failed: {e}", config.target);
+                    }
+                }
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_are_pushed_as_deltas_and_gauges_as_values() {
+        let mut encoder = StatsdEncoder::<2>::new("mev");
+        let mut out = Vec::new();
+        encoder.encode(
+            &[("rx_packets", 10), ("tx_packets", 4)],
+            &[("wire_p99_ns", 900)],
+            &[],
+            &mut out,
+        );
+        assert_eq!(
+            out,
+            ["mev.rx_packets:10|c\nmev.tx_packets:4|c\nmev.wire_p99_ns:900|g"]
+        );
+        encoder.encode(&[("rx_packets", 25), ("tx_packets", 4)], &[], &[], &mut out);
+        assert_eq!(out, ["mev.rx_packets:15|c\nmev.tx_packets:0|c"]);
+    }
+
+    #[test]
+    fn slo_alarms_carry_their_reason() {
+        let stats = NodeStats::new();
+        stats.slo_alarms.store(Slo::EndToEndP99.bit());
+        let tags = Slo::ALL.map(|slo| format!("reason:{}", slo.reason()));
+        let mut out = Vec::new();
+        StatsdEncoder::<0>::new("mev").encode(&[], &[], &slo_alarms(&stats, &tags), &mut out);
+        assert_eq!(
+            out,
+            ["mev.slo_alarm:0|g|#reason:parse_p99\nmev.slo_alarm:1|g|#reason:e2e_p99"]
+        );
+    }
+
+    #[test]
+    fn lines_are_split_across_datagrams() {
+        let names: Vec<String> = (0..100)
+            .map(|i| format!("counter_with_a_long_name_{i:03}"))
+            .collect();
+        let counters: [(&str, u64); 100] = core::array::from_fn(|i| (names[i].as_str(), 1));
+        let mut out = Vec::new();
+        StatsdEncoder::<100>::new("mev").encode(&counters, &[], &[], &mut out);
+        assert!(out.len() > 1);
+        assert!(out.iter().all(|d| d.len() <= MAX_DATAGRAM));
+        assert_eq!(out.iter().map(|d| d.lines().count()).sum::<usize>(), 100);
+    }
+}
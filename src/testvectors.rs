@@ -0,0 +1,532 @@
+//! Golden byte-level encodings of every wire message.
+//!
+//! Each message has a fixture in `testvectors/<name>.hex`: the canonical
+//! encoding of one instance, 16 bytes per line, with `#` comment lines
+//! giving the field values it was built from. The tests below rebuild every
+//! message from those values and require the exact bytes, then parse the
+//! fixture back and check each field, so a layout change cannot slip
+//! through unnoticed. Relayer and executor implementations in other
+//! languages can validate their encoders and decoders against the same
+//! files.
+
+/// One golden encoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TestVector {
+    /// Fixture name, `testvectors/<name>.hex`.
+    pub name: &'static str,
+    /// Encoded length in bytes.
+    pub wire_size: usize,
+    /// Fixture contents: hex with `#` comment lines.
+    pub hex: &'static str,
+}
+
+macro_rules! vector {
+    ($name:literal, $ty:ty) => {
+        TestVector {
+            name: $name,
+            wire_size: core::mem::size_of::<$ty>(),
+            hex: include_str!(concat!("../testvectors/", $name, ".hex")),
+        }
+    };
+}
+
+/// Every wire message, in the order of the fixture files.
+pub const VECTORS: [TestVector; 22] = [
+    vector!("dex_swap_tx", crate::payload::DexSwapTx),
+    vector!("dex_swap_tx_ext", crate::payload::DexSwapTxExt),
+    vector!("dex_swap_tx_evm", crate::payload::DexSwapTxEvm),
+    vector!("opportunity_reply", crate::payload::OpportunityReply),
+    vector!("reply_signature", crate::sign::ReplySignature),
+    vector!("opportunity_cancel", crate::payload::OpportunityCancel),
+    vector!("reply_batch_header", crate::coalesce::ReplyBatchHeader),
+    vector!("pool_state_update", crate::validator::PoolStateUpdate),
+    vector!("pool_state_update_v2", crate::validator::PoolStateUpdate),
+    vector!("pool_state_update_v3", crate::validator::PoolStateUpdate),
+    vector!("price_update", crate::prices::PriceUpdate),
+    vector!("book_update", crate::orderbook::BookUpdate),
+    vector!("victim_outcome", crate::reputation::VictimOutcome),
+    vector!("reply_ack", crate::ack::ReplyAck),
+    vector!("divergence_alert", crate::divergence::DivergenceAlert),
+    vector!(
+        "pool_discovery_request",
+        crate::discovery::PoolDiscoveryRequest
+    ),
+    vector!("session_hello", crate::session::SessionHello),
+    vector!("session_welcome", crate::session::SessionWelcome),
+    vector!("record_header", crate::session::RecordHeader),
+    vector!("flow_control", crate::session::FlowControl),
+    vector!("fec_shard_header", crate::fec::FecShardHeader),
+    vector!("handover_header", crate::handover::HandoverHeader),
+];
+
+/// The vector named `name`.
+pub fn find(name: &str) -> Option<&'static TestVector> {
+    VECTORS.iter().find(|v| v.name == name)
+}
+
+/// Decode fixture `hex` into `out`, skipping whitespace and `#` comment
+/// lines. Returns the byte count; `None` on a stray character, an odd
+/// number of digits or more bytes than `out` holds.
+pub fn decode_hex(hex: &str, out: &mut [u8]) -> Option<usize> {
+    let mut len = 0;
+    let mut high: Option<u8> = None;
+    for line in hex.lines().filter(|l| !l.trim_start().starts_with('#')) {
+        for c in line.chars().filter(|c| !c.is_whitespace()) {
+            let nibble = c.to_digit(16)? as u8;
+            match high.take() {
+                None => high = Some(nibble),
+                Some(h) => {
+                    *out.get_mut(len)? = (h << 4) | nibble;
+                    len += 1;
+                }
+            }
+        }
+    }
+    high.is_none().then_some(len)
+}
+
+impl TestVector {
+    /// The fixture's bytes, into `out`.
+    pub fn decode<'a>(&self, out: &'a mut [u8]) -> Option<&'a [u8]> {
+        let len = decode_hex(self.hex, out)?;
+        (len == self.wire_size).then(|| &out[..len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ack::ReplyAck;
+    use crate::coalesce::{ReplyBatchHeader, REPLY_BATCH_MAGIC};
+    use crate::discovery::PoolDiscoveryRequest;
+    use crate::divergence::{DivergenceAlert, DIVERGENCE_ALERT_MAGIC};
+    use crate::fec::FecShardHeader;
+    use crate::flow_control::FlowAction;
+    use crate::handover::{FdRole, FeedPosition, HandoverHeader, HandoverOffer, HANDOVER_MAGIC};
+    use crate::orderbook::{BookUpdate, OrderBookState, PriceLevel, BOOK_DEPTH};
+    use crate::payload::{
+        CancelReason, DexSwapTx, DexSwapTxEvm, DexSwapTxExt, OpportunityCancel, OpportunityReply,
+        OPPORTUNITY_CANCEL_MAGIC,
+    };
+    use crate::prices::PriceUpdate;
+    use crate::reputation::{Outcome, VictimOutcome};
+    use crate::schema::VersionWindow;
+    use crate::session::{
+        FlowControl, RecordHeader, SessionHello, SessionWelcome, WelcomeStatus,
+        SESSION_HELLO_MAGIC, SESSION_VERSION,
+    };
+    use crate::sign::{ReplySignature, ReplySigner};
+    use crate::tokens;
+    use crate::validator::{self, PoolStateUpdate};
+    use bytemuck::bytes_of;
+    use zerocopy::{FromBytes, IntoBytes};
+
+    // Field values of the fixtures; the `#` lines of each file repeat them.
+    const NONCE: u64 = 0x0102_0304_0506_0708;
+    const POOL: [u8; 20] = bytes(0xa0);
+    const MARKET: [u8; 20] = bytes(0xe0);
+    const SENDER: [u8; 20] = bytes(0xc0);
+    const TOKEN: [u8; 16] = [
+        0x10, 0x32, 0x54, 0x76, 0x98, 0xba, 0xdc, 0xfe, 0xef, 0xcd, 0xab, 0x89, 0x67, 0x45, 0x23,
+        0x01,
+    ];
+    const RX_TS_NS: u64 = 1_700_000_000_000_000_000;
+    const SLOT: u64 = 250_000_000;
+
+    /// 20 consecutive byte values from `first`.
+    const fn bytes(first: u8) -> [u8; 20] {
+        let mut out = [0; 20];
+        let mut i = 0;
+        while i < 20 {
+            out[i] = first + i as u8;
+            i += 1;
+        }
+        out
+    }
+
+    /// Node 7, key bytes 0x00..=0x1f.
+    fn signer() -> ReplySigner {
+        ReplySigner::new(7, &core::array::from_fn::<u8, 32, _>(|i| i as u8)).unwrap()
+    }
+
+    fn swap() -> DexSwapTx {
+        DexSwapTx::from_parts(NONCE, POOL, 1_500_000_000, 742_000_000, 1)
+    }
+
+    fn swap_ext() -> DexSwapTxExt {
+        DexSwapTxExt::from_parts(swap(), 1_700_000_000_123).with_priority_fee(2_000_000_000)
+    }
+
+    fn book() -> OrderBookState {
+        let mut book = OrderBookState {
+            bids: [PriceLevel::EMPTY; BOOK_DEPTH],
+            asks: [PriceLevel::EMPTY; BOOK_DEPTH],
+        };
+        book.bids[0] = PriceLevel::new(1_999_000_000, 3_000_000);
+        book.bids[1] = PriceLevel::new(1_998_000_000, 7_000_000);
+        book.asks[0] = PriceLevel::new(2_001_000_000, 2_000_000);
+        book.asks[1] = PriceLevel::new(2_003_000_000, 9_000_000);
+        book
+    }
+
+    /// Every message built from the fixture values, by vector name.
+    fn canonical() -> Vec<(&'static str, Vec<u8>)> {
+        let mut reply = [0u8; OpportunityReply::WIRE_SIZE];
+        let r = OpportunityReply::emplace(&mut reply).unwrap();
+        r.nonce_le = NONCE.to_le_bytes();
+        r.profit_le = 123_456u64.to_le_bytes();
+        r.rx_ts_ns_le = RX_TS_NS.to_le_bytes();
+        r.tx_ts_ns_le = (RX_TS_NS + 2_500).to_le_bytes();
+        r.confidence_bps_le = 7_250u16.to_le_bytes();
+        let cancel = OpportunityCancel {
+            magic: OPPORTUNITY_CANCEL_MAGIC,
+            sender: SENDER,
+            nonce_le: NONCE.to_le_bytes(),
+            reason: CancelReason::PoolMoved as u8,
+            _reserved: [0; 7],
+            tx_ts_ns_le: (RX_TS_NS + 9_000).to_le_bytes(),
+        };
+        let alert = DivergenceAlert {
+            magic: DIVERGENCE_ALERT_MAGIC,
+            market: MARKET,
+            pool: POOL,
+            divergence_bps_le: (-125i32).to_le_bytes(),
+            updates_le: 4u32.to_le_bytes(),
+            book_mid_le: 2_000_000_000u64.to_le_bytes(),
+            pool_price_le: 2_025_000_000u64.to_le_bytes(),
+            slot_le: SLOT.to_le_bytes(),
+            _reserved: [0; 4],
+        };
+        let hello = SessionHello {
+            magic: SESSION_HELLO_MAGIC,
+            version_le: SESSION_VERSION.to_le_bytes(),
+            wire_min: 1,
+            wire_max: 2,
+            token: TOKEN,
+            last_seq_le: 90_001u64.to_le_bytes(),
+        };
+        vec![
+            ("dex_swap_tx", bytes_of(&swap()).to_vec()),
+            ("dex_swap_tx_ext", bytes_of(&swap_ext()).to_vec()),
+            (
+                "dex_swap_tx_evm",
+                bytes_of(&DexSwapTxEvm::from_parts(
+                    swap_ext(),
+                    SENDER,
+                    30_000_000_000,
+                ))
+                .to_vec(),
+            ),
+            ("opportunity_reply", reply.to_vec()),
+            ("reply_signature", bytes_of(&signer().sign(&reply)).to_vec()),
+            ("opportunity_cancel", bytes_of(&cancel).to_vec()),
+            (
+                "reply_batch_header",
+                bytes_of(&ReplyBatchHeader::new(3)).to_vec(),
+            ),
+            (
+                "pool_state_update",
+                PoolStateUpdate::new(POOL, 10_000_000_000, 5_000_000_000, SLOT, 42)
+                    .as_bytes()
+                    .to_vec(),
+            ),
+            (
+                "pool_state_update_v2",
+                PoolStateUpdate::new(POOL, 10_000_000_000, 5_000_000_000, SLOT, 42)
+                    .with_fee_ppm(3_000)
+                    .as_bytes()
+                    .to_vec(),
+            ),
+            (
+                "pool_state_update_v3",
+                PoolStateUpdate::new(POOL, 10_000_000_000, 5_000_000_000, SLOT, 42)
+                    .with_fee_ppm(3_000)
+                    .with_sent_ns(RX_TS_NS)
+                    .as_bytes()
+                    .to_vec(),
+            ),
+            (
+                "price_update",
+                bytes_of(&PriceUpdate::new(tokens::WSOL, 150_250_000, SLOT, 7)).to_vec(),
+            ),
+            (
+                "book_update",
+                bytes_of(&BookUpdate::new(MARKET, POOL, 9, SLOT, book())).to_vec(),
+            ),
+            (
+                "victim_outcome",
+                bytes_of(&VictimOutcome::new(SENDER, NONCE, Outcome::Landed)).to_vec(),
+            ),
+            ("reply_ack", bytes_of(&ReplyAck::new(NONCE)).to_vec()),
+            ("divergence_alert", bytes_of(&alert).to_vec()),
+            (
+                "pool_discovery_request",
+                bytes_of(&PoolDiscoveryRequest::new(POOL, 5, 2)).to_vec(),
+            ),
+            ("session_hello", bytes_of(&hello).to_vec()),
+            (
+                "session_welcome",
+                bytes_of(
+                    &SessionWelcome::new(WelcomeStatus::Resumed, TOKEN, 90_002)
+                        .with_wire_version(2),
+                )
+                .to_vec(),
+            ),
+            (
+                "record_header",
+                bytes_of(&RecordHeader::new(
+                    90_002,
+                    PoolStateUpdate::WIRE_SIZE as u16,
+                ))
+                .to_vec(),
+            ),
+            (
+                "flow_control",
+                bytes_of(&FlowControl::new(FlowAction::Xoff, 90_003)).to_vec(),
+            ),
+            (
+                "fec_shard_header",
+                bytes_of(&FecShardHeader::new(7, 1, 4, 64, 312)).to_vec(),
+            ),
+            ("handover_header", bytes_of(&handover().header()).to_vec()),
+        ]
+    }
+
+    /// A handover of every role; only the header is encoded, so stdin
+    /// stands in for each descriptor.
+    fn handover() -> HandoverOffer<'static> {
+        // SAFETY: the fd is never used, only counted.
+        let fd = unsafe { std::os::fd::BorrowedFd::borrow_raw(libc::STDIN_FILENO) };
+        [
+            FdRole::Tap,
+            FdRole::StateFile,
+            FdRole::AdminListener,
+            FdRole::HandoverListener,
+        ]
+        .into_iter()
+        .fold(HandoverOffer::new(), |offer, role| offer.with_fd(role, fd))
+        .with_tap_frame(1514)
+        .with_feed(FeedPosition {
+            token: TOKEN,
+            last_seq: 90_001,
+        })
+    }
+
+    /// Bytes of fixture `name`.
+    fn golden(name: &str) -> Vec<u8> {
+        let mut buf = [0u8; 512];
+        find(name).unwrap().decode(&mut buf).unwrap().to_vec()
+    }
+
+    #[test]
+    fn encoders_produce_the_golden_bytes() {
+        let built = canonical();
+        assert_eq!(built.len(), VECTORS.len());
+        for (name, bytes) in built {
+            assert_eq!(bytes, golden(name), "{name} encoding changed");
+        }
+    }
+
+    #[test]
+    fn wire_encoder_matches_the_golden_bytes() {
+        use crate::wire::encoder::{Direction, PoolUpdateEncoder, SwapEncoder};
+        let swap = SwapEncoder::new(NONCE, POOL, Direction::OneForZero)
+            .amount_in(1_500_000_000)
+            .min_amount_out(742_000_000);
+        let ext = swap
+            .deadline_ms(1_700_000_000_123)
+            .priority_fee(2_000_000_000);
+        let update = PoolUpdateEncoder::new(POOL, 42)
+            .reserves(10_000_000_000, 5_000_000_000)
+            .slot(SLOT);
+        let encoded = |encode: &dyn Fn(&mut [u8]) -> Result<usize, _>| {
+            let mut buf = [0u8; 96];
+            let len = encode(&mut buf).unwrap();
+            buf[..len].to_vec()
+        };
+        assert_eq!(encoded(&|b| swap.encode(b)), golden("dex_swap_tx"));
+        assert_eq!(encoded(&|b| ext.encode(b)), golden("dex_swap_tx_ext"));
+        assert_eq!(
+            encoded(&|b| ext.evm(SENDER, 30_000_000_000).encode(b)),
+            golden("dex_swap_tx_evm")
+        );
+        assert_eq!(encoded(&|b| update.encode(b)), golden("pool_state_update"));
+        assert_eq!(
+            encoded(&|b| update.fee_ppm(3_000).encode(b)),
+            golden("pool_state_update_v2")
+        );
+        assert_eq!(
+            encoded(&|b| update.fee_ppm(3_000).sent_ns(RX_TS_NS).encode(b)),
+            golden("pool_state_update_v3")
+        );
+    }
+
+    #[test]
+    fn golden_bytes_parse_back_to_their_fields() {
+        let swap = golden("dex_swap_tx");
+        let tx = DexSwapTx::header(&swap).unwrap();
+        assert_eq!(
+            (
+                tx.nonce(),
+                tx.pool_address,
+                tx.amount_in(),
+                tx.min_amount_out()
+            ),
+            (NONCE, POOL, 1_500_000_000, 742_000_000)
+        );
+        assert_eq!(tx.token_direction, 1);
+        let ext = golden("dex_swap_tx_ext");
+        let ext = DexSwapTxExt::parse(&ext).unwrap();
+        assert_eq!(
+            (ext.deadline_ms(), ext.priority_fee()),
+            (1_700_000_000_123, 2_000_000_000)
+        );
+        let evm = golden("dex_swap_tx_evm");
+        let evm = DexSwapTxEvm::parse(&evm).unwrap();
+        assert_eq!(
+            (evm.nonce(), evm.sender, evm.gas_price()),
+            (NONCE, SENDER, 30_000_000_000)
+        );
+
+        let mut reply = golden("opportunity_reply");
+        let reply = OpportunityReply::emplace(&mut reply).unwrap();
+        assert_eq!(
+            (reply.nonce(), reply.profit(), reply.wire_latency_ns()),
+            (NONCE, 123_456, 2_500)
+        );
+        assert_eq!(reply.confidence_bps(), 7_250);
+        let signed = [bytemuck::bytes_of(reply), &golden("reply_signature")].concat();
+        assert!(signer().verify(&signed));
+        assert_eq!(
+            bytemuck::from_bytes::<ReplySignature>(&signed[40..]).node_id(),
+            7
+        );
+        let cancel = golden("opportunity_cancel");
+        let cancel = OpportunityCancel::parse(&cancel).unwrap();
+        assert_eq!(
+            (cancel.sender, cancel.nonce(), cancel.reason()),
+            (SENDER, NONCE, Some(CancelReason::PoolMoved))
+        );
+        let batch = golden("reply_batch_header");
+        let batch: &ReplyBatchHeader = bytemuck::from_bytes(&batch);
+        assert_eq!((batch.magic, batch.count), (REPLY_BATCH_MAGIC, 3));
+
+        let update = PoolStateUpdate::read_from_bytes(&golden("pool_state_update")).unwrap();
+        assert_eq!(
+            (
+                update.reserve0(),
+                update.reserve1(),
+                update.slot(),
+                update.seq()
+            ),
+            (10_000_000_000, 5_000_000_000, SLOT, 42)
+        );
+        let v2 = PoolStateUpdate::read_from_bytes(&golden("pool_state_update_v2")).unwrap();
+        let v2 = validator::decode_pool_update(&v2, VersionWindow::ALL).unwrap();
+        assert_eq!(
+            (v2.version, v2.reserve0, v2.fee),
+            (2, 10_000_000_000, Some((3_000, 1_000_000)))
+        );
+        let v3 = PoolStateUpdate::read_from_bytes(&golden("pool_state_update_v3")).unwrap();
+        let v3 = validator::decode_pool_update(&v3, VersionWindow::ALL).unwrap();
+        assert_eq!(
+            (v3.version, v3.fee, v3.sent_ns),
+            (3, Some((3_000, 1_000_000)), Some(RX_TS_NS))
+        );
+        let price = golden("price_update");
+        let price = PriceUpdate::parse(&price).unwrap();
+        assert_eq!(
+            (price.token_id(), price.price_usdc_e6(), price.seq()),
+            (tokens::WSOL, 150_250_000, 7)
+        );
+        let update = golden("book_update");
+        let update = BookUpdate::parse(&update).unwrap();
+        assert_eq!(
+            (update.market, update.ref_pool, update.seq(), update.book),
+            (MARKET, POOL, 9, book())
+        );
+        let outcome = golden("victim_outcome");
+        assert_eq!(
+            VictimOutcome::parse(&outcome).unwrap().outcome(),
+            Some(Outcome::Landed)
+        );
+        let ack = golden("reply_ack");
+        assert_eq!(ReplyAck::parse(&ack).unwrap().nonce(), NONCE);
+        let alert = golden("divergence_alert");
+        let alert = DivergenceAlert::parse(&alert).unwrap();
+        assert_eq!(
+            (alert.divergence_bps(), alert.updates(), alert.pool_price()),
+            (-125, 4, 2_025_000_000)
+        );
+        let request = golden("pool_discovery_request");
+        let request = PoolDiscoveryRequest::parse(&request).unwrap();
+        assert_eq!(
+            (request.pool, request.hits(), request.attempt()),
+            (POOL, 5, 2)
+        );
+
+        let hello = golden("session_hello");
+        let hello = SessionHello::parse(&hello).unwrap();
+        assert_eq!(
+            (hello.last_seq(), hello.wire_min, hello.wire_max),
+            (90_001, 1, 2)
+        );
+        let welcome = golden("session_welcome");
+        let welcome: &SessionWelcome = bytemuck::from_bytes(&welcome);
+        assert_eq!(
+            (welcome.status(), welcome.token, welcome.next_seq()),
+            (Some(WelcomeStatus::Resumed), TOKEN, 90_002)
+        );
+        assert_eq!(welcome.wire_version, 2);
+        let header = golden("record_header");
+        let header: &RecordHeader = bytemuck::from_bytes(&header);
+        assert_eq!(
+            (header.seq(), header.len()),
+            (90_002, PoolStateUpdate::WIRE_SIZE)
+        );
+        let flow = golden("flow_control");
+        let flow = FlowControl::parse(&flow).unwrap();
+        assert_eq!(
+            (flow.action(), flow.last_seq()),
+            (Some(FlowAction::Xoff), 90_003)
+        );
+        let fec = golden("fec_shard_header");
+        let fec: &FecShardHeader = bytemuck::from_bytes(&fec);
+        assert_eq!(
+            (
+                fec.group(),
+                fec.index,
+                fec.k,
+                fec.message_len(),
+                fec.shard_len()
+            ),
+            (7, 1, 4, 64, 312)
+        );
+        let handover = golden("handover_header");
+        let handover: &HandoverHeader = bytemuck::from_bytes(&handover);
+        assert_eq!(
+            (handover.magic, handover.fd_count, handover.roles),
+            (HANDOVER_MAGIC, 4, [1, 2, 3, 4])
+        );
+        assert_eq!(
+            (
+                handover.feed_token,
+                u64::from_le_bytes(handover.feed_last_seq_le)
+            ),
+            (TOKEN, 90_001)
+        );
+    }
+
+    #[test]
+    fn hex_decoding_skips_comments_and_rejects_garbage() {
+        let mut out = [0u8; 4];
+        assert_eq!(
+            decode_hex("# a comment\nde ad\n  # another\nbeef\n", &mut out),
+            Some(4)
+        );
+        assert_eq!(out, [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(decode_hex("abc", &mut out), None);
+        assert_eq!(decode_hex("zz", &mut out), None);
+        assert_eq!(decode_hex("0011223344", &mut out), None);
+    }
+}
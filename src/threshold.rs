@@ -0,0 +1,254 @@
+//! Runtime-adaptive `min_amount_in` threshold.
+//!
+//! The hot path reads the current threshold with a single relaxed atomic load.
+//! A controller recomputes it periodically, off the per-packet path, from:
+//! - the current gas cost of landing a bundle (in numéraire units),
+//! - the realized win rate of emitted opportunities,
+//! - recent hot-path latency percentiles (slower → fewer races won).
+//!
+//! The result is clamped to configured bounds so a bad input can never open
+//! the floodgates or silence the node entirely.
+use crate::runtime::CacheAlignedAtomicU64;
+
+/// Shared threshold cell: written by the controller, read by evaluators.
+pub struct AdaptiveThreshold {
+    value: CacheAlignedAtomicU64,
+}
+
+impl AdaptiveThreshold {
+    pub const fn new(initial: u64) -> Self {
+        Self {
+            value: CacheAlignedAtomicU64::new(initial),
+        }
+    }
+
+    #[inline(always)]
+    pub fn load(&self) -> u64 {
+        self.value.load()
+    }
+
+    #[inline(always)]
+    pub fn store(&self, v: u64) {
+        self.value.store(v);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThresholdConfig {
+    /// Lower bound for `min_amount_in`.
+    pub floor: u64,
+    /// Upper bound for `min_amount_in`.
+    pub ceiling: u64,
+    /// Victim size needed per numéraire unit of gas at a 100% win rate.
+    pub amount_per_gas_unit: u64,
+    /// Win rate assumed until enough outcomes have been observed (bps).
+    pub prior_win_rate_bps: u32,
+    /// p99 hot-path latency above which the threshold is scaled up (cycles).
+    pub latency_budget_cycles: u64,
+}
+
+impl Default for ThresholdConfig {
+    fn default() -> Self {
+        Self {
+            floor: 1_000_000,
+            ceiling: 1_000_000_000,
+            amount_per_gas_unit: 100,
+            prior_win_rate_bps: 5_000,
+            latency_budget_cycles: 5_000,
+        }
+    }
+}
+
+/// Inputs sampled by the controller on each recompute.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ThresholdInputs {
+    /// Gas cost of one bundle, in numéraire units.
+    pub gas_cost: u64,
+    /// Realized win rate in basis points, `None` if not enough samples.
+    pub win_rate_bps: Option<u32>,
+    /// Recent p99 hot-path latency in cycles (0 = unknown).
+    pub p99_cycles: u64,
+}
+
+impl ThresholdConfig {
+    /// Compute the threshold for the given inputs:
+    ///
+    ///   base      = gas_cost * amount_per_gas_unit / win_rate
+    ///   threshold = base * max(1, p99 / latency_budget), clamped to bounds
+    pub fn compute(&self, inputs: &ThresholdInputs) -> u64 {
+        let win_bps = inputs
+            .win_rate_bps
+            .unwrap_or(self.prior_win_rate_bps)
+            .clamp(100, 10_000) as u128;
+        let mut value =
+            (inputs.gas_cost as u128 * self.amount_per_gas_unit as u128 * 10_000) / win_bps;
+        if self.latency_budget_cycles != 0 && inputs.p99_cycles > self.latency_budget_cycles {
+            value = value.saturating_mul(inputs.p99_cycles as u128)
+                / self.latency_budget_cycles as u128;
+        }
+        (value.min(u64::MAX as u128) as u64).clamp(self.floor, self.ceiling)
+    }
+}
+
+/// Sliding window of emitted-opportunity outcomes (landed / lost).
+pub struct WinRateWindow<const N: usize> {
+    outcomes: [bool; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> WinRateWindow<N> {
+    /// Minimum outcomes before the realized rate replaces the prior.
+    pub const MIN_SAMPLES: usize = if N < 32 { N } else { 32 };
+
+    pub const fn new() -> Self {
+        Self {
+            outcomes: [false; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn record(&mut self, landed: bool) {
+        self.outcomes[self.head] = landed;
+        self.head = (self.head + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    pub fn win_rate_bps(&self) -> Option<u32> {
+        if self.len < Self::MIN_SAMPLES || self.len == 0 {
+            return None;
+        }
+        let wins = self.outcomes[..self.len].iter().filter(|&&w| w).count();
+        Some((wins * 10_000 / self.len) as u32)
+    }
+}
+
+impl<const N: usize> Default for WinRateWindow<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Fixed window of recent latency samples (cycles or nanoseconds).
+///
+/// `record` is a single store; percentiles are computed on a stack copy by
+/// the controller.
+pub struct LatencyWindow<const N: usize> {
+    samples: [u64; N],
+    head: usize,
+    len: usize,
+}
+
+impl<const N: usize> LatencyWindow<N> {
+    pub const fn new() -> Self {
+        Self {
+            samples: [0; N],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    #[inline(always)]
+    pub fn record(&mut self, sample: u64) {
+        self.samples[self.head] = sample;
+        self.head = (self.head + 1) % N;
+        self.len = (self.len + 1).min(N);
+    }
+
+    pub fn p99(&self) -> u64 {
+        self.percentile(99.0)
+    }
+
+    /// Nearest-rank percentile of the samples in the window; 0 when empty.
+    pub fn percentile(&self, pct: f64) -> u64 {
+        let mut sorted = self.samples;
+        let window = &mut sorted[..self.len];
+        window.sort_unstable();
+        percentile(window, pct)
+    }
+}
+
+impl<const N: usize> Default for LatencyWindow<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Nearest-rank percentile over an ascending slice.
+pub fn percentile(sorted: &[u64], pct: f64) -> u64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    // ceil() without std: round the rank up by hand.
+    let exact = (pct / 100.0) * sorted.len() as f64;
+    let rank = exact as usize + ((exact as usize as f64) < exact) as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn threshold_tracks_gas_win_rate_and_latency() {
+        let cfg = ThresholdConfig::default();
+        let base = ThresholdInputs {
+            gas_cost: 20_000,
+            win_rate_bps: Some(10_000),
+            p99_cycles: 0,
+        };
+        assert_eq!(cfg.compute(&base), 2_000_000);
+
+        let losing = ThresholdInputs {
+            win_rate_bps: Some(2_500),
+            ..base
+        };
+        assert_eq!(cfg.compute(&losing), 8_000_000);
+
+        let slow = ThresholdInputs {
+            p99_cycles: 10_000,
+            ..base
+        };
+        assert_eq!(cfg.compute(&slow), 4_000_000);
+
+        let prior = ThresholdInputs {
+            win_rate_bps: None,
+            ..base
+        };
+        assert_eq!(cfg.compute(&prior), 4_000_000);
+    }
+
+    #[test]
+    fn threshold_is_clamped_to_bounds() {
+        let cfg = ThresholdConfig {
+            floor: 1_000,
+            ceiling: 5_000,
+            ..ThresholdConfig::default()
+        };
+        assert_eq!(cfg.compute(&ThresholdInputs::default()), 1_000);
+        let huge = ThresholdInputs {
+            gas_cost: u64::MAX,
+            win_rate_bps: Some(0),
+            p99_cycles: u64::MAX,
+        };
+        assert_eq!(cfg.compute(&huge), 5_000);
+    }
+
+    #[test]
+    fn windows_report_rates_and_percentiles() {
+        let mut wins: WinRateWindow<64> = WinRateWindow::new();
+        assert_eq!(wins.win_rate_bps(), None);
+        for i in 0..64 {
+            wins.record(i % 4 == 0);
+        }
+        assert_eq!(wins.win_rate_bps(), Some(2_500));
+
+        let mut lat: LatencyWindow<100> = LatencyWindow::new();
+        for c in 1..=200 {
+            lat.record(c);
+        }
+        assert_eq!(lat.p99(), 199);
+        assert_eq!(lat.percentile(50.0), 150);
+    }
+}
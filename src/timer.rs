@@ -0,0 +1,291 @@
+//! Hashed timing wheel for the main loop's periodic work.
+//!
+//! Expiry sweeps, state syncs, threshold updates, stats and pacing releases
+//! each used to keep their own `next_*` deadline and compare it against a
+//! clock read. The wheel holds them all in fixed arrays: the loop reads the
+//! TSC once per iteration and [`TimerWheel::advance`] fires whatever came
+//! due since the last call.
+//!
+//! Time is kept in ticks of one microsecond, converted from cycles with the
+//! rate of the wheel's [`Clock`] (live, measured at startup by
+//! [`crate::runtime::LoopClock`]). A
+//! timer sits in bucket `deadline % SLOTS` of an intrusive list threaded
+//! through the timer array, so scheduling and cancelling never allocate.
+//! Deadlines further out than one revolution share buckets with nearer ones
+//! and are skipped until their own deadline passes.
+use crate::clock::Clock;
+
+const NIL: u16 = u16::MAX;
+
+/// Handle for cancelling a timer; stale once the timer fired (one-shot) or
+/// was cancelled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerId {
+    index: u16,
+    generation: u16,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimerError {
+    /// All `CAP` timers are scheduled.
+    Full,
+}
+
+impl core::fmt::Display for TimerError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Full => f.write_str("timer wheel is full"),
+        }
+    }
+}
+
+impl core::error::Error for TimerError {}
+
+#[derive(Clone, Copy)]
+struct Entry<T> {
+    /// `None` while the entry is on the free list.
+    event: Option<T>,
+    /// Tick at which the timer fires.
+    deadline: u64,
+    /// Ticks between firings; 0 for a one-shot timer.
+    period: u64,
+    next: u16,
+    generation: u16,
+}
+
+/// `SLOTS`: buckets, i.e. microseconds per revolution. `CAP`: timers that
+/// can be scheduled at once (below `u16::MAX`).
+pub struct TimerWheel<T: Copy, const SLOTS: usize, const CAP: usize> {
+    cycles_per_tick: u64,
+    /// Last tick [`advance`](Self::advance) processed.
+    tick: u64,
+    heads: [u16; SLOTS],
+    entries: [Entry<T>; CAP],
+    free: u16,
+    len: usize,
+}
+
+impl<T: Copy, const SLOTS: usize, const CAP: usize> TimerWheel<T, SLOTS, CAP> {
+    /// A wheel whose clock starts at cycle `now`.
+    pub fn new(cycles_per_us: u64, now: u64) -> Self {
+        assert!(
+            SLOTS > 0 && CAP < NIL as usize,
+            "timer wheel dimensions out of range"
+        );
+        let cycles_per_tick = cycles_per_us.max(1);
+        let mut entries = [Entry {
+            event: None,
+            deadline: 0,
+            period: 0,
+            next: NIL,
+            generation: 0,
+        }; CAP];
+        for (i, entry) in entries.iter_mut().enumerate() {
+            entry.next = if i + 1 < CAP { (i + 1) as u16 } else { NIL };
+        }
+        Self {
+            cycles_per_tick,
+            tick: now / cycles_per_tick,
+            heads: [NIL; SLOTS],
+            entries,
+            free: if CAP > 0 { 0 } else { NIL },
+            len: 0,
+        }
+    }
+
+    /// A wheel running on `clock`, starting now.
+    pub fn on(clock: &impl Clock) -> Self {
+        Self::new(clock.cycles_per_us(), clock.now_cycles())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Fire `event` once, `after_us` (at least 1) after the last advance.
+    pub fn schedule(&mut self, event: T, after_us: u64) -> Result<TimerId, TimerError> {
+        self.insert(event, self.tick + after_us.max(1), 0)
+    }
+
+    /// Fire `event` every `period_us` (at least 1), starting one period
+    /// after the last advance.
+    pub fn every(&mut self, event: T, period_us: u64) -> Result<TimerId, TimerError> {
+        let period = period_us.max(1);
+        self.insert(event, self.tick + period, period)
+    }
+
+    /// Remove a scheduled timer; false if it already fired or was cancelled.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        let Some(entry) = self.entries.get(id.index as usize) else {
+            return false;
+        };
+        if entry.event.is_none() || entry.generation != id.generation {
+            return false;
+        }
+        let bucket = self.bucket(entry.deadline);
+        let mut prev = NIL;
+        let mut cur = self.heads[bucket];
+        while cur != id.index {
+            prev = cur;
+            cur = self.entries[cur as usize].next;
+        }
+        self.unlink(bucket, prev, cur);
+        self.release(cur);
+        true
+    }
+
+    /// Move the clock to cycle `now` and pass every timer that came due to
+    /// `fire`, in deadline order unless more than a revolution has passed.
+    /// A periodic timer fires once per call however many periods it missed,
+    /// and is rescheduled on its original phase.
+    pub fn advance(&mut self, now: u64, mut fire: impl FnMut(T)) {
+        let target = now / self.cycles_per_tick;
+        if target <= self.tick {
+            return;
+        }
+        let steps = (target - self.tick).min(SLOTS as u64);
+        // Periodic timers that fired, relinked once the walk is done so they
+        // cannot be visited twice.
+        let mut requeue = NIL;
+        for tick in self.tick + 1..=self.tick + steps {
+            let bucket = self.bucket(tick);
+            let mut prev = NIL;
+            let mut cur = self.heads[bucket];
+            while cur != NIL {
+                let entry = self.entries[cur as usize];
+                if entry.deadline > target {
+                    prev = cur;
+                    cur = entry.next;
+                    continue;
+                }
+                self.unlink(bucket, prev, cur);
+                if let Some(event) = entry.event {
+                    fire(event);
+                }
+                if let Some(missed) = (target - entry.deadline).checked_div(entry.period) {
+                    let requeued = &mut self.entries[cur as usize];
+                    requeued.deadline = entry.deadline + (missed + 1) * entry.period;
+                    requeued.next = requeue;
+                    requeue = cur;
+                } else {
+                    self.release(cur);
+                }
+                cur = entry.next;
+            }
+        }
+        self.tick = target;
+        while requeue != NIL {
+            let next = self.entries[requeue as usize].next;
+            self.link(requeue);
+            requeue = next;
+        }
+    }
+
+    #[inline(always)]
+    fn bucket(&self, tick: u64) -> usize {
+        (tick % SLOTS as u64) as usize
+    }
+
+    fn insert(&mut self, event: T, deadline: u64, period: u64) -> Result<TimerId, TimerError> {
+        if self.free == NIL {
+            return Err(TimerError::Full);
+        }
+        let index = self.free;
+        let entry = &mut self.entries[index as usize];
+        self.free = entry.next;
+        entry.event = Some(event);
+        entry.deadline = deadline;
+        entry.period = period;
+        let generation = entry.generation;
+        self.link(index);
+        self.len += 1;
+        Ok(TimerId { index, generation })
+    }
+
+    fn link(&mut self, index: u16) {
+        let bucket = self.bucket(self.entries[index as usize].deadline);
+        self.entries[index as usize].next = self.heads[bucket];
+        self.heads[bucket] = index;
+    }
+
+    fn unlink(&mut self, bucket: usize, prev: u16, index: u16) {
+        let next = self.entries[index as usize].next;
+        if prev == NIL {
+            self.heads[bucket] = next;
+        } else {
+            self.entries[prev as usize].next = next;
+        }
+    }
+
+    fn release(&mut self, index: u16) {
+        let entry = &mut self.entries[index as usize];
+        entry.event = None;
+        entry.generation = entry.generation.wrapping_add(1);
+        entry.next = self.free;
+        self.free = index;
+        self.len -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 1 cycle per microsecond, so cycles read as microseconds.
+    fn wheel() -> TimerWheel<u8, 8, 4> {
+        TimerWheel::new(1, 0)
+    }
+
+    fn fired(wheel: &mut TimerWheel<u8, 8, 4>, now: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        wheel.advance(now, |event| out.push(event));
+        out
+    }
+
+    #[test]
+    fn one_shot_timers_fire_once_in_deadline_order() {
+        let mut wheel = wheel();
+        wheel.schedule(2, 5).unwrap();
+        wheel.schedule(1, 3).unwrap();
+        // Beyond one revolution: shares bucket 3 but is not due yet.
+        wheel.schedule(3, 11).unwrap();
+        assert!(fired(&mut wheel, 2).is_empty());
+        assert_eq!(fired(&mut wheel, 6), [1, 2]);
+        assert!(fired(&mut wheel, 10).is_empty());
+        assert_eq!(fired(&mut wheel, 11), [3]);
+        assert!(wheel.is_empty());
+    }
+
+    #[test]
+    fn periodic_timers_keep_their_phase_across_a_stall() {
+        let mut wheel = wheel();
+        wheel.every(7, 3).unwrap();
+        assert_eq!(fired(&mut wheel, 3), [7]);
+        assert!(fired(&mut wheel, 5).is_empty());
+        assert_eq!(fired(&mut wheel, 6), [7]);
+        // Several revolutions later: one firing, then back on the grid of 3.
+        assert_eq!(fired(&mut wheel, 40), [7]);
+        assert!(fired(&mut wheel, 41).is_empty());
+        assert_eq!(fired(&mut wheel, 42), [7]);
+        assert_eq!(wheel.len(), 1);
+    }
+
+    #[test]
+    fn cancelled_timers_do_not_fire_and_free_their_place() {
+        let mut wheel = wheel();
+        let ids: Vec<_> = (0..4).map(|i| wheel.schedule(i, 2).unwrap()).collect();
+        assert_eq!(wheel.schedule(9, 2), Err(TimerError::Full));
+        assert!(wheel.cancel(ids[1]));
+        assert!(!wheel.cancel(ids[1]));
+        let reused = wheel.every(9, 2).unwrap();
+        assert_eq!(fired(&mut wheel, 2), [9, 3, 2, 0]);
+        // Fired one-shots are gone; the periodic one is still cancellable.
+        assert!(!wheel.cancel(ids[0]));
+        assert!(wheel.cancel(reused));
+        assert!(wheel.is_empty());
+    }
+}
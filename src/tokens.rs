@@ -0,0 +1,224 @@
+//! Static token metadata and profit normalization into a single numéraire.
+//!
+//! Raw on-chain amounts are integers scaled by each token's `decimals`
+//! (USDC = 6, SOL = 9, ...). Comparing or summing profits across pools
+//! therefore requires rescaling to one reference unit — the numéraire —
+//! before any threshold is applied or a value is reported.
+//!
+//! Conversion is integer-only:
+//!   value = amount_raw * price / 10^decimals
+//! where `price` is the value of one whole token in raw numéraire units,
+//! looked up through the token's reference-price slot. Live prices from the
+//! reference feed (`prices::PriceCache`) take precedence over the static table.
+use crate::prices::PriceCache;
+
+/// Index into [`TOKENS`].
+pub type TokenId = u16;
+
+/// Number of reference-price slots.
+pub const PRICE_SLOTS: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenMeta {
+    /// Short ASCII symbol, zero-padded (for logs and reports only).
+    pub symbol: [u8; 8],
+    /// Number of decimals in the raw on-chain amount.
+    pub decimals: u8,
+    /// Slot in the reference-price table holding this token's price.
+    pub price_slot: u8,
+}
+
+impl TokenMeta {
+    const fn new(symbol: &[u8], decimals: u8, price_slot: u8) -> Self {
+        let mut padded = [0u8; 8];
+        let mut i = 0;
+        while i < symbol.len() && i < 8 {
+            padded[i] = symbol[i];
+            i += 1;
+        }
+        Self {
+            symbol: padded,
+            decimals,
+            price_slot,
+        }
+    }
+
+    /// Symbol as a string slice, without trailing padding.
+    pub fn symbol_str(&self) -> &str {
+        let len = self.symbol.iter().position(|&b| b == 0).unwrap_or(8);
+        core::str::from_utf8(&self.symbol[..len]).unwrap_or("?")
+    }
+}
+
+pub const USDC: TokenId = 0;
+pub const WSOL: TokenId = 1;
+pub const WETH: TokenId = 2;
+pub const WBTC: TokenId = 3;
+
+/// Number of known tokens.
+pub const TOKEN_COUNT: usize = 4;
+
+/// Token metadata table, indexed by `TokenId`.
+pub static TOKENS: [TokenMeta; TOKEN_COUNT] = [
+    TokenMeta::new(b"USDC", 6, 0),
+    TokenMeta::new(b"WSOL", 9, 1),
+    TokenMeta::new(b"WETH", 18, 2),
+    TokenMeta::new(b"WBTC", 8, 3),
+];
+
+/// Look up a token's metadata.
+#[inline(always)]
+pub fn token(id: TokenId) -> Option<&'static TokenMeta> {
+    TOKENS.get(id as usize)
+}
+
+/// Find a token by symbol (case-insensitive). Used when parsing config.
+pub fn token_by_symbol(symbol: &str) -> Option<TokenId> {
+    TOKENS
+        .iter()
+        .position(|t| t.symbol_str().eq_ignore_ascii_case(symbol))
+        .map(|i| i as TokenId)
+}
+
+/// Static reference prices: value of one whole token in USDC-e6.
+pub const STATIC_PRICES_USDC_E6: [u64; PRICE_SLOTS] = [
+    1_000_000,      // USDC
+    2_000_000_000,  // WSOL (mock pool price)
+    3_000_000_000,  // WETH
+    60_000_000_000, // WBTC
+    0,
+    0,
+    0,
+    0,
+];
+
+/// Rescale `amount` from `from_decimals` to `to_decimals` (truncating).
+#[inline(always)]
+pub fn rescale(amount: u64, from_decimals: u8, to_decimals: u8) -> Option<u64> {
+    if from_decimals <= to_decimals {
+        let factor = 10u64.checked_pow((to_decimals - from_decimals) as u32)?;
+        amount.checked_mul(factor)
+    } else {
+        let factor = 10u128.checked_pow((from_decimals - to_decimals) as u32)?;
+        Some((amount as u128 / factor) as u64)
+    }
+}
+
+/// Converts raw token amounts into a configured numéraire.
+///
+/// Prices are stored per slot as "raw numéraire units per whole token", so
+/// the numéraire itself always has price `10^numeraire.decimals`.
+#[derive(Clone, Copy, Debug)]
+pub struct ProfitNormalizer {
+    numeraire: TokenId,
+    prices: [u64; PRICE_SLOTS],
+}
+
+impl ProfitNormalizer {
+    /// Normalizer using the static USDC-e6 price table.
+    pub const USDC_E6: Self = Self {
+        numeraire: USDC,
+        prices: STATIC_PRICES_USDC_E6,
+    };
+
+    /// Build a normalizer for `numeraire`, re-basing the static USDC prices.
+    ///
+    /// Returns `None` for unknown tokens or a numéraire without a price.
+    pub fn new(numeraire: TokenId) -> Option<Self> {
+        let meta = token(numeraire)?;
+        let base = STATIC_PRICES_USDC_E6[meta.price_slot as usize];
+        if base == 0 {
+            return None;
+        }
+        let unit = 10u128.pow(meta.decimals as u32);
+        let mut prices = [0u64; PRICE_SLOTS];
+        for (dst, &usdc) in prices.iter_mut().zip(STATIC_PRICES_USDC_E6.iter()) {
+            *dst = u64::try_from(usdc as u128 * unit / base as u128).ok()?;
+        }
+        Some(Self { numeraire, prices })
+    }
+
+    #[inline(always)]
+    pub fn numeraire(&self) -> TokenId {
+        self.numeraire
+    }
+
+    /// Convert a raw `amount` of `token` into raw numéraire units.
+    #[inline(always)]
+    pub fn to_numeraire(&self, token_id: TokenId, amount: u64) -> Option<u64> {
+        if token_id == self.numeraire {
+            return Some(amount);
+        }
+        let meta = token(token_id)?;
+        let price = *self.prices.get(meta.price_slot as usize)?;
+        if price == 0 {
+            return None;
+        }
+        let value = amount as u128 * price as u128 / 10u128.pow(meta.decimals as u32);
+        u64::try_from(value).ok()
+    }
+
+    /// Like [`ProfitNormalizer::to_numeraire`], but prefers live reference
+    /// prices from `cache` when both the token and the numéraire have one.
+    #[inline(always)]
+    pub fn to_numeraire_live(
+        &self,
+        cache: &PriceCache,
+        token_id: TokenId,
+        amount: u64,
+    ) -> Option<u64> {
+        if token_id == self.numeraire {
+            return Some(amount);
+        }
+        let (Some(meta), Some(num)) = (token(token_id), token(self.numeraire)) else {
+            return None;
+        };
+        let token_px = cache.price_usdc_e6(meta.price_slot);
+        let num_px = cache.price_usdc_e6(num.price_slot);
+        if token_px == 0 || num_px == 0 {
+            return self.to_numeraire(token_id, amount);
+        }
+        // amount * (token USDC / token unit) / (numéraire USDC / numéraire unit)
+        let value = amount as u128 * token_px as u128 * 10u128.pow(num.decimals as u32)
+            / (10u128.pow(meta.decimals as u32) * num_px as u128);
+        u64::try_from(value).ok()
+    }
+}
+
+impl Default for ProfitNormalizer {
+    fn default() -> Self {
+        Self::USDC_E6
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rescale_between_decimals() {
+        assert_eq!(rescale(1_500_000, 6, 9), Some(1_500_000_000));
+        assert_eq!(rescale(1_500_000_000, 9, 6), Some(1_500_000));
+        assert_eq!(rescale(u64::MAX, 0, 18), None);
+    }
+
+    #[test]
+    fn normalizes_into_usdc_e6() {
+        let n = ProfitNormalizer::USDC_E6;
+        assert_eq!(n.to_numeraire(USDC, 42), Some(42));
+        // 0.5 SOL at 2000 USDC = 1000 USDC
+        assert_eq!(n.to_numeraire(WSOL, 500_000_000), Some(1_000_000_000));
+        // 0.001 ETH at 3000 USDC = 3 USDC
+        assert_eq!(n.to_numeraire(WETH, 1_000_000_000_000_000), Some(3_000_000));
+    }
+
+    #[test]
+    fn rebased_numeraire() {
+        let n = ProfitNormalizer::new(WSOL).expect("WSOL has a price");
+        assert_eq!(n.numeraire(), WSOL);
+        // 2000 USDC = 1 SOL = 1e9 lamports
+        assert_eq!(n.to_numeraire(USDC, 2_000_000_000), Some(1_000_000_000));
+        assert_eq!(token_by_symbol("wsol"), Some(WSOL));
+        assert_eq!(token(WBTC).map(|t| t.symbol_str()), Some("WBTC"));
+    }
+}
@@ -0,0 +1,879 @@
+//! Zero-copy TX path: replies are written directly into the transport's
+//! outgoing buffer instead of being built as owned arrays and copied.
+//!
+//! [`Transport::tx_reserve`] hands out `len` contiguous bytes of the TX
+//! buffer — a smoltcp socket buffer or a UMEM frame — and the caller writes
+//! the message (e.g. an [`OpportunityReply`]) once, in place.
+//!
+//! On the receive side, [`Transport::rx_batch`] drains everything the
+//! transport has queued into a [`FrameBatch`] in one call. Each frame keeps
+//! its own RX timestamp, source and a sequence number that orders frames
+//! across batches, so a capture of the batches replays in arrival order.
+pub mod tap;
+#[cfg(target_os = "linux")]
+pub mod xdp;
+
+use crate::coalesce::{Flushed, ReplyCoalescer};
+use crate::pacing::{Destination, Pacer};
+use crate::payload::{CancelReason, OpportunityCancel, OpportunityReply, OPPORTUNITY_CANCEL_MAGIC};
+use crate::pool_registry::PoolAddress;
+use crate::processor::{Opportunity, Strategy};
+use crate::reply_routes::{MessageKind, ReplyRoutes};
+use crate::runtime::{now_cycles, to_wire_time_ns, unix_time_ns};
+use crate::sign::{ReplySignature, ReplySigner};
+use crate::xdp::XdpRingDescriptor;
+use smoltcp::socket::{tcp, udp};
+use smoltcp::wire::{IpAddress, IpEndpoint};
+
+/// Arrival metadata of one received frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameMeta {
+    /// Arrival order, monotonic across batches.
+    pub seq: u64,
+    /// Software RX stamp (unix ns), taken when the frame was dequeued.
+    pub rx_ts_ns: u64,
+    /// TSC reading taken with `rx_ts_ns`, for ageing the frame in cycles.
+    pub rx_cycles: u64,
+    /// Sender IPv4 address and port.
+    pub source: ([u8; 4], u16),
+}
+
+/// Received frames, copied back to back into a fixed arena.
+pub struct FrameBatch<const FRAMES: usize = 32, const BYTES: usize = { 16 * 1024 }> {
+    data: [u8; BYTES],
+    frames: heapless::Vec<(FrameMeta, usize, usize), FRAMES>,
+    used: usize,
+    next_seq: u64,
+    /// Frames taken per fill, at most `FRAMES`.
+    limit: usize,
+}
+
+impl<const FRAMES: usize, const BYTES: usize> FrameBatch<FRAMES, BYTES> {
+    pub fn new() -> Self {
+        Self {
+            data: [0; BYTES],
+            frames: heapless::Vec::new(),
+            used: 0,
+            next_seq: 0,
+            limit: FRAMES,
+        }
+    }
+
+    /// Take at most `frames` frames per fill (1 to `FRAMES`); see
+    /// [`crate::burst`].
+    #[inline(always)]
+    pub fn set_limit(&mut self, frames: usize) {
+        self.limit = frames.clamp(1, FRAMES);
+    }
+
+    /// Drop the frames; sequence numbers continue where they left off.
+    pub fn clear(&mut self) {
+        self.frames.clear();
+        self.used = 0;
+    }
+
+    /// Whether a further frame of `len` bytes fits.
+    #[inline(always)]
+    pub fn fits(&self, len: usize) -> bool {
+        self.frames.len() < self.limit && self.used + len <= BYTES
+    }
+
+    /// Append one frame, assigning its sequence number. `false` if full.
+    #[inline(always)]
+    pub fn push(&mut self, payload: &[u8], rx_ts_ns: u64, source: ([u8; 4], u16)) -> bool {
+        if !self.fits(payload.len()) {
+            return false;
+        }
+        let (start, end) = (self.used, self.used + payload.len());
+        self.data[start..end].copy_from_slice(payload);
+        let meta = FrameMeta {
+            seq: self.next_seq,
+            rx_ts_ns,
+            rx_cycles: now_cycles(),
+            source,
+        };
+        let _ = self.frames.push((meta, start, payload.len()));
+        self.used = end;
+        self.next_seq += 1;
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Frames in arrival order.
+    pub fn iter(&self) -> impl Iterator<Item = (&FrameMeta, &[u8])> {
+        self.frames
+            .iter()
+            .map(|(meta, start, len)| (meta, &self.data[*start..*start + *len]))
+    }
+}
+
+impl<const FRAMES: usize, const BYTES: usize> Default for FrameBatch<FRAMES, BYTES> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub trait Transport {
+    /// Reserve `len` contiguous bytes for one outgoing message and return
+    /// them for writing. `None` if the TX buffer has no contiguous room.
+    ///
+    /// The reservation is committed as soon as it is handed out: whatever
+    /// the caller leaves in the slice is what gets sent.
+    fn tx_reserve(&mut self, len: usize) -> Option<&mut [u8]>;
+
+    /// Reserve room for an [`OpportunityReply`] carrying `opportunity`
+    /// against victim `nonce` and fill it in place, stamping the TX enqueue
+    /// time. Both stamps are
+    /// written in wire time (PHC-corrected when tracked). With a
+    /// [`reply_signer`], the reply is followed by its [`ReplySignature`].
+    /// Returns the reply's
+    /// wire-to-wire latency in nanoseconds, or `None` if the TX buffer is full.
+    #[inline(always)]
+    fn send_reply(&mut self, nonce: u64, opportunity: Opportunity, rx_ts_ns: u64) -> Option<u64> {
+        let signer = reply_signer();
+        let len = OpportunityReply::WIRE_SIZE + signer.map_or(0, |_| ReplySignature::WIRE_SIZE);
+        fill_reply(self.tx_reserve(len)?, signer, nonce, opportunity, rx_ts_ns)
+    }
+
+    /// Reserve room for an [`OpportunityCancel`] withdrawing the opportunity
+    /// against victim `(sender, nonce)`. `false` if the TX buffer is full.
+    #[inline(always)]
+    fn send_cancel(&mut self, sender: [u8; 20], nonce: u64, reason: CancelReason) -> bool {
+        let Some(buf) = self.tx_reserve(OpportunityCancel::WIRE_SIZE) else {
+            return false;
+        };
+        let Some(cancel) = OpportunityCancel::emplace(buf) else {
+            return false;
+        };
+        *cancel = OpportunityCancel {
+            magic: OPPORTUNITY_CANCEL_MAGIC,
+            sender,
+            nonce_le: nonce.to_le_bytes(),
+            reason: reason as u8,
+            _reserved: [0; 7],
+            tx_ts_ns_le: to_wire_time_ns(unix_time_ns()).to_le_bytes(),
+        };
+        true
+    }
+
+    /// Clear `batch` and fill it with the frames queued for receive, each
+    /// stamped at dequeue. Returns the number of frames; 0 for TX-only
+    /// transports. Frames that do not fit stay queued for the next call.
+    fn rx_batch<const FRAMES: usize, const BYTES: usize>(
+        &mut self,
+        batch: &mut FrameBatch<FRAMES, BYTES>,
+    ) -> usize {
+        batch.clear();
+        0
+    }
+}
+
+/// Key every reply is signed with, if any (`src/sign.rs`).
+static REPLY_SIGNER: std::sync::OnceLock<ReplySigner> = std::sync::OnceLock::new();
+
+/// Sign every reply sent from now on with `signer`. Set once, at startup;
+/// `false` if a signer was already set.
+pub fn set_reply_signer(signer: ReplySigner) -> bool {
+    REPLY_SIGNER.set(signer).is_ok()
+}
+
+#[inline(always)]
+pub fn reply_signer() -> Option<&'static ReplySigner> {
+    REPLY_SIGNER.get()
+}
+
+/// Write a reply into `buf`, reserved by [`Transport::send_reply`], and its
+/// signature after it when `signer` is given.
+#[inline(always)]
+pub fn fill_reply(
+    buf: &mut [u8],
+    signer: Option<&ReplySigner>,
+    nonce: u64,
+    opportunity: Opportunity,
+    rx_ts_ns: u64,
+) -> Option<u64> {
+    let (head, trailer) = buf.split_at_mut_checked(OpportunityReply::WIRE_SIZE)?;
+    let reply = OpportunityReply::emplace(head)?;
+    reply.nonce_le = nonce.to_le_bytes();
+    reply.profit_le = opportunity.profit.to_le_bytes();
+    reply.rx_ts_ns_le = to_wire_time_ns(rx_ts_ns).to_le_bytes();
+    reply.tx_ts_ns_le = to_wire_time_ns(unix_time_ns()).to_le_bytes();
+    reply.confidence_bps_le = opportunity.confidence_bps.to_le_bytes();
+    reply._reserved = [0; 6];
+    let latency_ns = reply.wire_latency_ns();
+    if let Some(signer) = signer {
+        *ReplySignature::emplace(trailer)? = signer.sign(bytemuck::bytes_of(reply));
+    }
+    Some(latency_ns)
+}
+
+/// A UDP socket addressed to one remote endpoint; each reservation becomes
+/// one datagram in the socket's TX ring.
+pub struct UdpReply<'s, 'b> {
+    socket: &'s mut udp::Socket<'b>,
+    remote: IpEndpoint,
+}
+
+impl<'s, 'b> UdpReply<'s, 'b> {
+    pub fn new(socket: &'s mut udp::Socket<'b>, remote: IpEndpoint) -> Self {
+        Self { socket, remote }
+    }
+}
+
+impl Transport for UdpReply<'_, '_> {
+    #[inline(always)]
+    fn tx_reserve(&mut self, len: usize) -> Option<&mut [u8]> {
+        self.socket.send(len, self.remote).ok()
+    }
+}
+
+/// IPv4 endpoint as a pacing [`Destination`]; `None` for other families.
+pub fn destination(endpoint: IpEndpoint) -> Option<Destination> {
+    #[allow(irrefutable_let_patterns)]
+    if let IpAddress::Ipv4(addr) = endpoint.addr {
+        return Some((addr.0, endpoint.port));
+    }
+    None
+}
+
+/// A transport whose messages to `to` are paced: a reservation made without
+/// a token from the [`Pacer`] is written into its delay queue instead, and
+/// sent from there by [`Pacer::release`]. Without a pacer it passes through.
+pub struct Paced<'p, T> {
+    inner: T,
+    pacer: Option<&'p mut Pacer>,
+    to: Destination,
+    now: u64,
+}
+
+impl<'p, T: Transport> Paced<'p, T> {
+    /// Pace `inner`, sending to `to`, at cycle `now`.
+    pub fn new(inner: T, pacer: Option<&'p mut Pacer>, to: Destination, now: u64) -> Self {
+        Self {
+            inner,
+            pacer,
+            to,
+            now,
+        }
+    }
+}
+
+impl<T: Transport> Transport for Paced<'_, T> {
+    #[inline(always)]
+    fn tx_reserve(&mut self, len: usize) -> Option<&mut [u8]> {
+        if let Some(pacer) = self.pacer.as_deref_mut() {
+            if !pacer.admit(self.to, self.now) {
+                return pacer.delay(self.to, len);
+            }
+        }
+        self.inner.tx_reserve(len)
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for &mut T {
+    #[inline(always)]
+    fn tx_reserve(&mut self, len: usize) -> Option<&mut [u8]> {
+        (**self).tx_reserve(len)
+    }
+
+    fn rx_batch<const FRAMES: usize, const BYTES: usize>(
+        &mut self,
+        batch: &mut FrameBatch<FRAMES, BYTES>,
+    ) -> usize {
+        (**self).rx_batch(batch)
+    }
+}
+
+/// A transport whose `OpportunityReply`-sized reservations for `to` are
+/// held in a [`ReplyCoalescer`], when one is attached and has room, to leave
+/// with the others for `to` at the next [`flush_coalesced`]. Everything else
+/// goes to `inner`.
+pub struct Coalesced<'c, T> {
+    inner: T,
+    coalescer: Option<&'c mut ReplyCoalescer>,
+    to: Destination,
+}
+
+impl<T: Transport> Transport for Coalesced<'_, T> {
+    #[inline(always)]
+    fn tx_reserve(&mut self, len: usize) -> Option<&mut [u8]> {
+        if len == OpportunityReply::WIRE_SIZE {
+            if let Some(slot) = self
+                .coalescer
+                .as_deref_mut()
+                .and_then(|c| c.reserve(self.to))
+            {
+                return Some(slot);
+            }
+        }
+        self.inner.tx_reserve(len)
+    }
+}
+
+/// Send the replies `coalescer` holds from `socket`, one datagram per
+/// destination, TX-stamped now and paced through `pacer` at cycle `now`.
+pub fn flush_coalesced(
+    coalescer: &mut ReplyCoalescer,
+    socket: &mut udp::Socket<'_>,
+    mut pacer: Option<&mut Pacer>,
+    now: u64,
+) -> Flushed {
+    coalescer.flush(to_wire_time_ns(unix_time_ns()), |to, datagram| {
+        let ([a, b, c, d], port) = to;
+        let tx = UdpReply::new(socket, IpEndpoint::new(IpAddress::v4(a, b, c, d), port));
+        let mut tx = Paced::new(tx, pacer.as_deref_mut(), to, now);
+        tx.tx_reserve(datagram.len())
+            .map(|buf| buf.copy_from_slice(datagram))
+            .is_some()
+    })
+}
+
+/// A UDP socket sending each reply and cancellation to the destinations
+/// [`ReplyRoutes`] gives for it — all of them, for a fan-out — or back to
+/// `source` when no rule matches, each destination paced separately when a
+/// [`Pacer`] is attached and replies coalesced when a [`ReplyCoalescer`] is.
+/// Raw reservations go to `source`, unpaced.
+pub struct RoutedUdpReply<'s, 'b> {
+    socket: &'s mut udp::Socket<'b>,
+    source: IpEndpoint,
+    routes: &'s ReplyRoutes,
+    strategy: Strategy,
+    pool: PoolAddress,
+    pacer: Option<&'s mut Pacer>,
+    coalescer: Option<&'s mut ReplyCoalescer>,
+    now: u64,
+}
+
+impl<'s, 'b> RoutedUdpReply<'s, 'b> {
+    pub fn new(
+        socket: &'s mut udp::Socket<'b>,
+        source: IpEndpoint,
+        routes: &'s ReplyRoutes,
+        strategy: Strategy,
+        pool: PoolAddress,
+    ) -> Self {
+        Self {
+            socket,
+            source,
+            routes,
+            strategy,
+            pool,
+            pacer: None,
+            coalescer: None,
+            now: 0,
+        }
+    }
+
+    /// Pace every destination through `pacer`, at cycle `now`.
+    pub fn paced(mut self, pacer: &'s mut Pacer, now: u64) -> Self {
+        self.pacer = Some(pacer);
+        self.now = now;
+        self
+    }
+
+    /// Hold replies in `coalescer` until the caller's [`flush_coalesced`].
+    pub fn coalesced(mut self, coalescer: &'s mut ReplyCoalescer) -> Self {
+        self.coalescer = Some(coalescer);
+        self
+    }
+
+    /// Run `send` against every routed destination of `kind`, or the source.
+    /// Returns the last successful result.
+    fn fan_out<T>(
+        &mut self,
+        kind: MessageKind,
+        mut send: impl FnMut(&mut Coalesced<'_, Paced<'_, UdpReply<'_, 'b>>>) -> Option<T>,
+    ) -> Option<T> {
+        let mut routed = false;
+        let mut sent = None;
+        for dest in self.routes.lookup(self.strategy, &self.pool, kind) {
+            routed = true;
+            let [a, b, c, d] = dest.endpoint.addr;
+            let remote = IpEndpoint::new(IpAddress::v4(a, b, c, d), dest.endpoint.port);
+            let to = (dest.endpoint.addr, dest.endpoint.port);
+            let tx = Paced::new(
+                UdpReply::new(self.socket, remote),
+                self.pacer.as_deref_mut(),
+                to,
+                self.now,
+            );
+            sent = send(&mut Coalesced {
+                inner: tx,
+                coalescer: self.coalescer.as_deref_mut(),
+                to,
+            })
+            .or(sent);
+        }
+        if !routed {
+            let to = destination(self.source).unwrap_or(([0; 4], 0));
+            let tx = Paced::new(
+                UdpReply::new(self.socket, self.source),
+                self.pacer.as_deref_mut(),
+                to,
+                self.now,
+            );
+            sent = send(&mut Coalesced {
+                inner: tx,
+                coalescer: self.coalescer.as_deref_mut(),
+                to,
+            });
+        }
+        sent
+    }
+}
+
+impl Transport for RoutedUdpReply<'_, '_> {
+    #[inline(always)]
+    fn tx_reserve(&mut self, len: usize) -> Option<&mut [u8]> {
+        self.socket.send(len, self.source).ok()
+    }
+
+    fn send_reply(&mut self, nonce: u64, opportunity: Opportunity, rx_ts_ns: u64) -> Option<u64> {
+        self.fan_out(MessageKind::Reply, |tx| {
+            tx.send_reply(nonce, opportunity, rx_ts_ns)
+        })
+    }
+
+    fn send_cancel(&mut self, sender: [u8; 20], nonce: u64, reason: CancelReason) -> bool {
+        // The reply being withdrawn may still be held: it leaves first.
+        if let Some(coalescer) = self.coalescer.as_deref_mut().filter(|c| !c.is_empty()) {
+            flush_coalesced(coalescer, self.socket, self.pacer.as_deref_mut(), self.now);
+        }
+        self.fan_out(MessageKind::Cancel, |tx| {
+            tx.send_cancel(sender, nonce, reason).then_some(())
+        })
+        .is_some()
+    }
+}
+
+/// The bound socket itself receives; it has no destination to send to, so
+/// replies go through [`UdpReply`].
+impl Transport for udp::Socket<'_> {
+    fn tx_reserve(&mut self, _len: usize) -> Option<&mut [u8]> {
+        None
+    }
+
+    fn rx_batch<const FRAMES: usize, const BYTES: usize>(
+        &mut self,
+        batch: &mut FrameBatch<FRAMES, BYTES>,
+    ) -> usize {
+        batch.clear();
+        while let Ok((payload, meta)) = self.peek() {
+            if !batch.fits(payload.len()) {
+                break;
+            }
+            // The node only has an IPv4 address; anything else is dropped.
+            #[allow(irrefutable_let_patterns)]
+            if let IpAddress::Ipv4(addr) = meta.endpoint.addr {
+                batch.push(payload, unix_time_ns(), (addr.0, meta.endpoint.port));
+            }
+            let _ = self.recv();
+        }
+        batch.len()
+    }
+}
+
+/// TCP replies are appended to the stream. A reservation that would wrap
+/// around the end of the ring buffer is refused rather than split.
+impl Transport for tcp::Socket<'_> {
+    #[inline(always)]
+    fn tx_reserve(&mut self, len: usize) -> Option<&mut [u8]> {
+        if !self.can_send() {
+            return None;
+        }
+        self.send(|free| {
+            if free.len() >= len {
+                (len, Some(&mut free[..len]))
+            } else {
+                (0, None)
+            }
+        })
+        .ok()
+        .flatten()
+    }
+}
+
+/// One UMEM frame being prepared for the AF_XDP TX ring.
+///
+/// The first `headroom` bytes are left for the Ethernet/IPv4/UDP headers
+/// ([`headers_mut`](Self::headers_mut)); the reservation follows directly,
+/// so the reply is DMA'd from where it was written.
+pub struct UmemTxFrame<'a> {
+    frame: &'a mut [u8],
+    addr: u64,
+    headroom: usize,
+    len: usize,
+}
+
+impl<'a> UmemTxFrame<'a> {
+    /// Wrap a UMEM frame (e.g. from `XdpUmem::frame_mut`) at UMEM offset `addr`.
+    pub fn new(frame: &'a mut [u8], addr: u64, headroom: usize) -> Self {
+        Self {
+            frame,
+            addr,
+            headroom,
+            len: 0,
+        }
+    }
+
+    pub fn headers_mut(&mut self) -> &mut [u8] {
+        &mut self.frame[..self.headroom]
+    }
+
+    /// Length of the reserved payload; 0 until [`Transport::tx_reserve`] succeeds.
+    pub fn payload_len(&self) -> usize {
+        self.len
+    }
+
+    /// TX ring descriptor covering headers plus the reserved payload.
+    pub fn descriptor(&self) -> XdpRingDescriptor {
+        XdpRingDescriptor::new(self.addr, (self.headroom + self.len) as u32)
+    }
+}
+
+/// A frame carries exactly one message; a second reservation is refused.
+impl Transport for UmemTxFrame<'_> {
+    #[inline(always)]
+    fn tx_reserve(&mut self, len: usize) -> Option<&mut [u8]> {
+        let end = self.headroom.checked_add(len)?;
+        if self.len != 0 || end > self.frame.len() {
+            return None;
+        }
+        self.len = len;
+        Some(&mut self.frame[self.headroom..end])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use smoltcp::wire::IpAddress;
+
+    #[test]
+    fn udp_reply_is_queued_in_socket_buffer() {
+        let mut rx_meta = [udp::PacketMetadata::EMPTY; 1];
+        let mut tx_meta = [udp::PacketMetadata::EMPTY; 2];
+        let (mut rx, mut tx) = ([0u8; 64], [0u8; 2 * OpportunityReply::WIRE_SIZE]);
+        let mut socket = udp::Socket::new(
+            udp::PacketBuffer::new(&mut rx_meta[..], &mut rx[..]),
+            udp::PacketBuffer::new(&mut tx_meta[..], &mut tx[..]),
+        );
+        socket.bind(8080).unwrap();
+
+        let remote = IpEndpoint::new(IpAddress::v4(10, 0, 0, 1), 9000);
+        let mut reply = UdpReply::new(&mut socket, remote);
+        assert!(reply.send_reply(1, Opportunity::unscored(42), 0).is_some());
+        assert!(reply.send_reply(2, Opportunity::unscored(43), 0).is_some());
+        assert!(
+            reply.send_reply(3, Opportunity::unscored(44), 0).is_none(),
+            "TX buffer holds two replies"
+        );
+        assert!(!socket.can_send());
+    }
+
+    #[test]
+    fn signed_reply_is_followed_by_its_signature() {
+        let signer = ReplySigner::new(3, b"0123456789abcdef").unwrap();
+        let mut buf = [0u8; OpportunityReply::WIRE_SIZE + ReplySignature::WIRE_SIZE];
+        assert!(fill_reply(&mut buf, Some(&signer), 7, Opportunity::unscored(42), 0).is_some());
+        assert!(signer.verify(&buf));
+        assert_eq!(
+            bytemuck::from_bytes::<OpportunityReply>(&buf[..40]).nonce(),
+            7
+        );
+        assert!(
+            fill_reply(
+                &mut buf[..40],
+                Some(&signer),
+                7,
+                Opportunity::unscored(42),
+                0
+            )
+            .is_none(),
+            "no room"
+        );
+        assert!(fill_reply(&mut buf[..40], None, 7, Opportunity::unscored(42), 0).is_some());
+    }
+
+    #[test]
+    fn routed_reply_fans_out_and_falls_back_to_source() {
+        use crate::dpdk::UdpEndpoint;
+        use crate::payload::CancelReason;
+
+        let node = UdpEndpoint {
+            mac: [0x02, 0, 0, 0, 0, 1],
+            addr: [192, 168, 69, 2],
+            port: 8080,
+        };
+        let spec = "sandwich/*/reply=10.0.0.5:9000+10.0.0.6:9000";
+        let routes =
+            ReplyRoutes::parse(spec, &node, |a| Some([0x02, 0, 0, 0, 0, a[3]]), |_| None).unwrap();
+        let mut rx_meta = [udp::PacketMetadata::EMPTY; 1];
+        let mut tx_meta = [udp::PacketMetadata::EMPTY; 3];
+        let (mut rx, mut tx) = ([0u8; 64], [0u8; 256]);
+        let mut socket = udp::Socket::new(
+            udp::PacketBuffer::new(&mut rx_meta[..], &mut rx[..]),
+            udp::PacketBuffer::new(&mut tx_meta[..], &mut tx[..]),
+        );
+        socket.bind(8080).unwrap();
+
+        let source = IpEndpoint::new(IpAddress::v4(10, 0, 0, 1), 9000);
+        let mut tx =
+            RoutedUdpReply::new(&mut socket, source, &routes, Strategy::Sandwich, [0xaa; 20]);
+        // Two datagrams for the reply, one for the unrouted cancellation.
+        assert!(tx.send_reply(1, Opportunity::unscored(42), 0).is_some());
+        assert!(tx.send_cancel([7; 20], 1, CancelReason::Replaced));
+        assert!(!socket.can_send());
+    }
+
+    #[test]
+    fn coalesced_replies_leave_in_one_datagram_per_destination() {
+        use crate::coalesce::ReplyBatchHeader;
+        use crate::dpdk::UdpEndpoint;
+        use smoltcp::iface::{Config, Interface, SocketSet, SocketStorage};
+        use smoltcp::phy::{Loopback, Medium};
+        use smoltcp::time::Instant;
+        use smoltcp::wire::{EthernetAddress, IpCidr};
+
+        let mut device = Loopback::new(Medium::Ethernet);
+        let config = Config::new(EthernetAddress([0x02, 0, 0, 0, 0, 1]).into());
+        let mut iface = Interface::new(config, &mut device, Instant::ZERO);
+        iface.update_ip_addrs(|addrs| {
+            addrs
+                .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
+                .unwrap()
+        });
+        let (mut meta_a, mut meta_b) = (
+            [udp::PacketMetadata::EMPTY; 8],
+            [udp::PacketMetadata::EMPTY; 8],
+        );
+        let (mut buf_a, mut buf_b) = ([0u8; 512], [0u8; 512]);
+        let mut storage = [SocketStorage::EMPTY; 2];
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        let node = sockets.add(udp_socket(&mut meta_a, &mut buf_a));
+        let executor = sockets.add(udp_socket(&mut meta_b, &mut buf_b));
+        sockets.get_mut::<udp::Socket>(node).bind(8080).unwrap();
+        sockets.get_mut::<udp::Socket>(executor).bind(9000).unwrap();
+
+        let local = UdpEndpoint {
+            mac: [0x02, 0, 0, 0, 0, 1],
+            addr: [127, 0, 0, 1],
+            port: 8080,
+        };
+        let routes = ReplyRoutes::parse(
+            "sandwich/*/reply=127.0.0.1:9000",
+            &local,
+            |_| Some([0x02; 6]),
+            |_| None,
+        )
+        .unwrap();
+        let source = IpEndpoint::new(IpAddress::v4(127, 0, 0, 2), 7000);
+        let mut coalescer = ReplyCoalescer::new();
+        for nonce in 1..=3 {
+            let socket = sockets.get_mut::<udp::Socket>(node);
+            let mut tx =
+                RoutedUdpReply::new(socket, source, &routes, Strategy::Sandwich, [0xaa; 20])
+                    .coalesced(&mut coalescer);
+            assert!(tx.send_reply(nonce, Opportunity::unscored(42), 0).is_some());
+        }
+        assert!(!coalescer.is_empty());
+        let flushed = flush_coalesced(
+            &mut coalescer,
+            sockets.get_mut::<udp::Socket>(node),
+            None,
+            0,
+        );
+        assert_eq!(
+            flushed,
+            Flushed {
+                datagrams: 1,
+                batches: 1,
+                coalesced: 3,
+                dropped: 0
+            }
+        );
+
+        for _ in 0..4 {
+            iface.poll(Instant::ZERO, &mut device, &mut sockets);
+        }
+        let executor = sockets.get_mut::<udp::Socket>(executor);
+        let (datagram, _) = executor.recv().unwrap();
+        let replies = ReplyBatchHeader::replies(datagram).unwrap();
+        assert_eq!(
+            replies
+                .iter()
+                .map(OpportunityReply::nonce)
+                .collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert!(
+            executor.recv().is_err(),
+            "one datagram for the three replies"
+        );
+    }
+
+    #[test]
+    fn paced_reply_is_delayed_then_released() {
+        use crate::pacing::PacingPolicy;
+
+        let mut pacer = Pacer::new(PacingPolicy {
+            interval_cycles: 1_000,
+            burst: 1,
+        });
+        let (mut frame_a, mut frame_b) = ([0u8; 128], [0u8; 128]);
+        let to = ([10, 0, 0, 5], 9000);
+        let mut tx = Paced::new(
+            UmemTxFrame::new(&mut frame_a, 0, 42),
+            Some(&mut pacer),
+            to,
+            0,
+        );
+        assert!(tx.send_reply(1, Opportunity::unscored(42), 0).is_some());
+        let mut tx = Paced::new(
+            UmemTxFrame::new(&mut frame_b, 0, 42),
+            Some(&mut pacer),
+            to,
+            10,
+        );
+        assert!(tx.send_reply(2, Opportunity::unscored(42), 0).is_some());
+        assert_eq!(tx.inner.payload_len(), 0);
+        assert_eq!((pacer.delayed, pacer.queued()), (1, 1));
+
+        let mut released = Vec::new();
+        let sent = pacer.release(1_000, |_, msg| {
+            released.extend_from_slice(msg);
+            true
+        });
+        assert_eq!(sent, 1);
+        let reply = OpportunityReply::emplace(&mut released).unwrap();
+        assert_eq!(reply.nonce(), 2);
+    }
+
+    fn udp_socket<'a>(
+        meta: &'a mut [udp::PacketMetadata],
+        payload: &'a mut [u8],
+    ) -> udp::Socket<'a> {
+        let (rx_meta, tx_meta) = meta.split_at_mut(meta.len() / 2);
+        let (rx, tx) = payload.split_at_mut(payload.len() / 2);
+        udp::Socket::new(
+            udp::PacketBuffer::new(rx_meta, rx),
+            udp::PacketBuffer::new(tx_meta, tx),
+        )
+    }
+
+    #[test]
+    fn rx_batch_stamps_and_orders_frames_across_calls() {
+        use smoltcp::iface::{Config, Interface, SocketSet, SocketStorage};
+        use smoltcp::phy::{Loopback, Medium};
+        use smoltcp::time::Instant;
+        use smoltcp::wire::{EthernetAddress, IpCidr};
+
+        let mut device = Loopback::new(Medium::Ethernet);
+        let config = Config::new(EthernetAddress([0x02, 0, 0, 0, 0, 1]).into());
+        let mut iface = Interface::new(config, &mut device, Instant::ZERO);
+        iface.update_ip_addrs(|addrs| {
+            addrs
+                .push(IpCidr::new(IpAddress::v4(127, 0, 0, 1), 8))
+                .unwrap()
+        });
+
+        let (mut meta_a, mut meta_b) = (
+            [udp::PacketMetadata::EMPTY; 8],
+            [udp::PacketMetadata::EMPTY; 8],
+        );
+        let (mut buf_a, mut buf_b) = ([0u8; 512], [0u8; 512]);
+        let mut storage = [SocketStorage::EMPTY; 2];
+        let mut sockets = SocketSet::new(&mut storage[..]);
+        let rx = sockets.add(udp_socket(&mut meta_a, &mut buf_a));
+        let tx = sockets.add(udp_socket(&mut meta_b, &mut buf_b));
+        sockets.get_mut::<udp::Socket>(rx).bind(8080).unwrap();
+        let sender = sockets.get_mut::<udp::Socket>(tx);
+        sender.bind(9000).unwrap();
+        for len in [10, 20, 30] {
+            sender
+                .send_slice(&vec![len as u8; len], (IpAddress::v4(127, 0, 0, 1), 8080))
+                .unwrap();
+        }
+        for _ in 0..4 {
+            iface.poll(Instant::ZERO, &mut device, &mut sockets);
+        }
+
+        // Limited to two frames: the third stays queued.
+        let socket = sockets.get_mut::<udp::Socket>(rx);
+        let mut batch: FrameBatch<4, 64> = FrameBatch::new();
+        batch.set_limit(2);
+        assert_eq!(socket.rx_batch(&mut batch), 2);
+        let frames: Vec<_> = batch
+            .iter()
+            .map(|(m, p)| (m.seq, m.source, p.len(), p[0]))
+            .collect();
+        assert_eq!(
+            frames,
+            [
+                (0, ([127, 0, 0, 1], 9000), 10, 10),
+                (1, ([127, 0, 0, 1], 9000), 20, 20)
+            ]
+        );
+        let stamps: Vec<_> = batch.iter().map(|(m, _)| m.rx_ts_ns).collect();
+        assert!(stamps[0] > 0 && stamps[0] <= stamps[1]);
+
+        assert_eq!(socket.rx_batch(&mut batch), 1);
+        assert_eq!(
+            batch
+                .iter()
+                .map(|(m, p)| (m.seq, p.len()))
+                .collect::<Vec<_>>(),
+            [(2, 30)]
+        );
+        assert_eq!(socket.rx_batch(&mut batch), 0);
+        assert!(batch.is_empty());
+    }
+
+    #[test]
+    fn umem_frame_reserves_after_headroom_once() {
+        let mut frame = [0u8; 96];
+        let mut tx = UmemTxFrame::new(&mut frame, 4096, 42);
+        assert!(tx.tx_reserve(56).is_none(), "does not fit after headroom");
+        let rx_ts = unix_time_ns();
+        let opportunity = Opportunity {
+            confidence_bps: 6_500,
+            ..Opportunity::unscored(7)
+        };
+        let wire_ns = tx
+            .send_reply(5, opportunity, rx_ts)
+            .expect("frame has room");
+        assert!(
+            tx.send_reply(6, Opportunity::unscored(8), rx_ts).is_none(),
+            "one message per frame"
+        );
+        let desc = tx.descriptor();
+        assert_eq!((desc.addr, desc.len), (4096, 82));
+
+        let reply: &OpportunityReply = bytemuck::from_bytes(&frame[42..82]);
+        assert_eq!(
+            (reply.nonce(), reply.profit(), reply.rx_ts_ns()),
+            (5, 7, rx_ts)
+        );
+        assert_eq!(reply.confidence_bps(), 6_500);
+        assert_eq!(reply.wire_latency_ns(), wire_ns);
+    }
+
+    #[test]
+    fn cancellation_is_written_in_place() {
+        let mut frame = [0u8; 42 + OpportunityCancel::WIRE_SIZE];
+        let mut tx = UmemTxFrame::new(&mut frame, 0, 42);
+        assert!(tx.send_cancel([0xA1; 20], 9, CancelReason::Replaced));
+        let cancel = OpportunityCancel::parse(&frame[42..]).expect("magic and size");
+        assert_eq!(
+            (cancel.sender, cancel.nonce(), cancel.reason()),
+            ([0xA1; 20], 9, Some(CancelReason::Replaced))
+        );
+    }
+}
@@ -0,0 +1,480 @@
+//! In-process TAP setup, replacing `scripts/setup_tap.sh`.
+//!
+//! [`TapDevice::ensure`] reuses an existing device as is. Otherwise it needs
+//! `CAP_NET_ADMIN`: it creates a persistent TAP device through
+//! `/dev/net/tun`, assigns the host-side peer address and brings the link up
+//! over netlink. A device created here is deleted again when the
+//! [`TapDevice`] is dropped; one that was already there is left alone.
+//!
+//! The device is made persistent and the setup fd closed, so smoltcp's
+//! `TunTapInterface` can attach to it by name afterwards; it reads the MTU
+//! set here, jumbo frames included.
+//!
+//! smoltcp drops a datagram silently when the receiving socket's buffer is
+//! full. [`CountingDevice`] counts the datagrams the device delivers per UDP
+//! port, and [`RxDropCounter`] compares them with what was drained to
+//! surface those drops.
+use crate::netlink::{self, Netlink, NetlinkError};
+use smoltcp::phy::{Device, DeviceCapabilities, RxToken};
+use smoltcp::time::Instant;
+
+/// `CAP_NET_ADMIN` capability bit.
+const CAP_NET_ADMIN: u32 = 12;
+/// `IFNAMSIZ`, including the NUL terminator.
+const IFNAMSIZ: usize = 16;
+
+#[derive(Clone, Copy, Debug)]
+pub struct TapConfig {
+    pub name: &'static str,
+    /// Host-side address of the link; the node's smoltcp address lives in
+    /// the same subnet.
+    pub peer_addr: [u8; 4],
+    pub prefix_len: u8,
+    /// Link MTU to set, e.g. 9000 for jumbo frames; `None` keeps the
+    /// device's (1500 for a new one).
+    pub mtu: Option<u32>,
+}
+
+impl Default for TapConfig {
+    fn default() -> Self {
+        Self {
+            name: "tap0",
+            peer_addr: [192, 168, 69, 1],
+            prefix_len: 24,
+            mtu: None,
+        }
+    }
+}
+
+/// Sizes of the smoltcp socket buffers on the TAP path, allocated once at
+/// startup. Byte sizes are payload; packet counts are UDP metadata slots.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SocketBuffers {
+    /// Executor TCP stream, each direction.
+    pub tcp_rx_bytes: usize,
+    pub tcp_tx_bytes: usize,
+    /// Victim UDP socket.
+    pub udp_rx_bytes: usize,
+    pub udp_rx_packets: usize,
+    pub udp_tx_bytes: usize,
+    pub udp_tx_packets: usize,
+    /// Pool feed UDP socket, receive side.
+    pub pool_rx_bytes: usize,
+    pub pool_rx_packets: usize,
+    /// Control UDP socket, when control messages have a port of their own.
+    pub control_rx_bytes: usize,
+    pub control_rx_packets: usize,
+    /// Relayer TCP session, receive side: the room a catch-up replay has.
+    pub relay_rx_bytes: usize,
+}
+
+impl Default for SocketBuffers {
+    fn default() -> Self {
+        Self {
+            tcp_rx_bytes: 65_535,
+            tcp_tx_bytes: 65_535,
+            udp_rx_bytes: 16 * 1024,
+            udp_rx_packets: 64,
+            udp_tx_bytes: 16 * 1024,
+            udp_tx_packets: 64,
+            pool_rx_bytes: 16 * 1024,
+            pool_rx_packets: 64,
+            control_rx_bytes: 8 * 1024,
+            control_rx_packets: 32,
+            relay_rx_bytes: 16 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum TapError {
+    /// The device does not exist and we may not create it.
+    MissingCapability,
+    NameTooLong,
+    TunOpen(i32),
+    /// `TUNSETIFF` or `TUNSETPERSIST` failed.
+    TunIoctl(i32),
+    Netlink(NetlinkError),
+}
+
+impl core::fmt::Display for TapError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::MissingCapability => write!(f, "creating a TAP device needs CAP_NET_ADMIN"),
+            Self::NameTooLong => write!(f, "TAP device name longer than {} bytes", IFNAMSIZ - 1),
+            Self::TunOpen(e) => write!(f, "/dev/net/tun open failed (errno={})", e),
+            Self::TunIoctl(e) => write!(f, "TAP device ioctl failed (errno={})", e),
+            Self::Netlink(e) => write!(f, "TAP link setup failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for TapError {}
+
+impl From<NetlinkError> for TapError {
+    fn from(e: NetlinkError) -> Self {
+        Self::Netlink(e)
+    }
+}
+
+/// Whether the `CapEff:` line of a `/proc/<pid>/status` grants `cap`.
+pub fn has_capability(status: &str, cap: u32) -> bool {
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("CapEff:"))
+        .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok())
+        .is_some_and(|mask| mask & (1 << cap) != 0)
+}
+
+/// Whether this process holds `CAP_NET_ADMIN`.
+pub fn has_net_admin() -> bool {
+    std::fs::read_to_string("/proc/self/status")
+        .is_ok_and(|status| has_capability(&status, CAP_NET_ADMIN))
+}
+
+fn errno() -> i32 {
+    unsafe { *libc::__errno_location() }
+}
+
+/// A TAP device the node runs on.
+pub struct TapDevice {
+    config: TapConfig,
+    /// Created by us, so deleted on drop.
+    owned: bool,
+}
+
+impl TapDevice {
+    /// Use the device named in `config`, creating and configuring it first
+    /// if it does not exist.
+    pub fn ensure(config: TapConfig) -> Result<Self, TapError> {
+        if config.name.len() >= IFNAMSIZ {
+            return Err(TapError::NameTooLong);
+        }
+        if let Ok(index) = netlink::if_index(config.name) {
+            log::info!("Using existing TAP device {}", config.name);
+            if let Some(mtu) = config.mtu {
+                // Without CAP_NET_ADMIN the device keeps its MTU; smoltcp
+                // follows whatever it is.
+                match Netlink::open().and_then(|mut nl| nl.set_mtu(index, mtu)) {
+                    Ok(()) => log::info!("Set {} MTU to {mtu}", config.name),
+                    Err(e) => log::warn!("{} MTU not set to {mtu}: {e}", config.name),
+                }
+            }
+            return Ok(Self {
+                config,
+                owned: false,
+            });
+        }
+        if !has_net_admin() {
+            return Err(TapError::MissingCapability);
+        }
+        create_persistent(config.name)?;
+        // From here on a failure still removes the half-configured device.
+        let device = Self {
+            config,
+            owned: true,
+        };
+        let index = netlink::if_index(config.name)?;
+        let mut nl = Netlink::open()?;
+        nl.add_address(index, config.peer_addr, config.prefix_len)?;
+        if let Some(mtu) = config.mtu {
+            nl.set_mtu(index, mtu)?;
+        }
+        nl.set_link_up(index, true)?;
+        let [a, b, c, d] = config.peer_addr;
+        log::info!(
+            "Created TAP device {} with peer {a}.{b}.{c}.{d}/{}",
+            config.name,
+            config.prefix_len
+        );
+        Ok(device)
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.config.name
+    }
+
+    /// Keep the device on drop even if created here: it now belongs to the
+    /// node it was handed to.
+    pub fn release(&mut self) {
+        self.owned = false;
+    }
+
+    /// Whether the device is deleted when this is dropped.
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+}
+
+impl Drop for TapDevice {
+    fn drop(&mut self) {
+        if !self.owned {
+            return;
+        }
+        let removed = netlink::if_index(self.config.name)
+            .and_then(|index| Netlink::open()?.delete_link(index));
+        match removed {
+            Ok(()) => log::info!("Removed TAP device {}", self.config.name),
+            Err(e) => log::warn!("TAP device {} not removed: {e}", self.config.name),
+        }
+    }
+}
+
+/// UDP ports a [`CountingDevice`] can count.
+pub const MAX_COUNTED_PORTS: usize = 4;
+
+/// A smoltcp device counting the UDP datagrams it delivers per destination
+/// port (IPv4, first fragments only).
+pub struct CountingDevice<D> {
+    inner: D,
+    /// (port, datagrams delivered); port 0 marks an unused slot.
+    ports: [(u16, u64); MAX_COUNTED_PORTS],
+}
+
+impl<D> CountingDevice<D> {
+    /// Count datagrams to `ports` (at most [`MAX_COUNTED_PORTS`]).
+    pub fn new(inner: D, ports: &[u16]) -> Self {
+        let mut counted = [(0, 0); MAX_COUNTED_PORTS];
+        for (slot, &port) in counted.iter_mut().zip(ports) {
+            slot.0 = port;
+        }
+        Self {
+            inner,
+            ports: counted,
+        }
+    }
+
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Datagrams to `port` delivered so far.
+    pub fn delivered(&self, port: u16) -> u64 {
+        self.ports
+            .iter()
+            .find(|(p, _)| *p == port && port != 0)
+            .map_or(0, |(_, n)| *n)
+    }
+}
+
+/// Destination port of an Ethernet frame carrying a UDP datagram's first
+/// (or only) IPv4 fragment.
+fn udp_dst_port(frame: &[u8]) -> Option<u16> {
+    const ETH_HEADER: usize = 14;
+    if frame.get(12..14)? != [0x08, 0x00] {
+        return None;
+    }
+    let ip = frame.get(ETH_HEADER..)?;
+    let header_len = usize::from(ip.first()? & 0x0f) * 4;
+    let fragment_offset = u16::from_be_bytes([*ip.get(6)?, *ip.get(7)?]) & 0x1fff;
+    if *ip.get(9)? != 17 || fragment_offset != 0 {
+        return None;
+    }
+    let udp = ip.get(header_len..header_len + 4)?;
+    Some(u16::from_be_bytes([udp[2], udp[3]]))
+}
+
+pub struct CountingRxToken<'a, T> {
+    inner: T,
+    ports: &'a mut [(u16, u64); MAX_COUNTED_PORTS],
+}
+
+impl<T: RxToken> RxToken for CountingRxToken<'_, T> {
+    fn consume<R, F>(self, f: F) -> R
+    where
+        F: FnOnce(&mut [u8]) -> R,
+    {
+        let ports = self.ports;
+        self.inner.consume(|frame| {
+            if let Some(port) = udp_dst_port(frame) {
+                if let Some(slot) = ports.iter_mut().find(|(p, _)| *p == port && port != 0) {
+                    slot.1 += 1;
+                }
+            }
+            f(frame)
+        })
+    }
+
+    fn meta(&self) -> smoltcp::phy::PacketMeta {
+        self.inner.meta()
+    }
+}
+
+impl<D: Device> Device for CountingDevice<D> {
+    type RxToken<'a>
+        = CountingRxToken<'a, D::RxToken<'a>>
+    where
+        Self: 'a;
+    type TxToken<'a>
+        = D::TxToken<'a>
+    where
+        Self: 'a;
+
+    fn receive(&mut self, timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        let (rx, tx) = self.inner.receive(timestamp)?;
+        Some((
+            CountingRxToken {
+                inner: rx,
+                ports: &mut self.ports,
+            },
+            tx,
+        ))
+    }
+
+    fn transmit(&mut self, timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        self.inner.transmit(timestamp)
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.inner.capabilities()
+    }
+}
+
+/// A device that only transmits: polling an interface over it sends what
+/// the sockets have queued and leaves received frames in the device, for
+/// whoever reads it next (a node taking over, on a handover).
+pub struct EgressOnly<'d, D>(pub &'d mut D);
+
+impl<D: Device> Device for EgressOnly<'_, D> {
+    type RxToken<'a>
+        = D::RxToken<'a>
+    where
+        Self: 'a;
+    type TxToken<'a>
+        = D::TxToken<'a>
+    where
+        Self: 'a;
+
+    fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
+        None
+    }
+
+    fn transmit(&mut self, timestamp: Instant) -> Option<Self::TxToken<'_>> {
+        self.0.transmit(timestamp)
+    }
+
+    fn capabilities(&self) -> DeviceCapabilities {
+        self.0.capabilities()
+    }
+}
+
+/// Datagrams a UDP socket dropped for want of buffer space: those the
+/// device delivered to its port that were never drained. Known only once
+/// the socket has been drained empty; until then they may still be queued.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RxDropCounter {
+    seen: u64,
+    queued: u64,
+}
+
+impl RxDropCounter {
+    /// Account one drain: `delivered` is the device's running count for the
+    /// port, `drained` the datagrams taken, `empty` whether the socket has
+    /// nothing left. Returns the drops found.
+    pub fn update(&mut self, delivered: u64, drained: usize, empty: bool) -> u64 {
+        self.queued += delivered.saturating_sub(self.seen);
+        self.seen = self.seen.max(delivered);
+        self.queued = self.queued.saturating_sub(drained as u64);
+        if empty {
+            core::mem::take(&mut self.queued)
+        } else {
+            0
+        }
+    }
+}
+
+/// `TUNSETIFF` + `TUNSETPERSIST` so the device outlives the setup fd.
+fn create_persistent(name: &str) -> Result<(), TapError> {
+    #[repr(C)]
+    struct IfReq {
+        name: [u8; IFNAMSIZ],
+        flags: libc::c_short,
+        _pad: [u8; 22],
+    }
+    let mut req = IfReq {
+        name: [0; IFNAMSIZ],
+        flags: (libc::IFF_TAP | libc::IFF_NO_PI) as libc::c_short,
+        _pad: [0; 22],
+    };
+    req.name[..name.len()].copy_from_slice(name.as_bytes());
+
+    let fd = unsafe { libc::open(c"/dev/net/tun".as_ptr(), libc::O_RDWR | libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(TapError::TunOpen(errno()));
+    }
+    let rc = unsafe {
+        if libc::ioctl(fd, libc::TUNSETIFF, &mut req as *mut IfReq) < 0 {
+            -1
+        } else {
+            libc::ioctl(fd, libc::TUNSETPERSIST, 1 as libc::c_ulong)
+        }
+    };
+    let e = errno();
+    unsafe { libc::close(fd) };
+    if rc < 0 {
+        return Err(TapError::TunIoctl(e));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capability_mask_is_read_from_cap_eff() {
+        let status = "Name:\tmev\nCapInh:\t0000000000000000\nCapPrm:\t0000000000001000\nCapEff:\t0000000000001000\n";
+        assert!(has_capability(status, CAP_NET_ADMIN));
+        assert!(!has_capability(status, 21));
+        assert!(!has_capability(
+            "CapPrm:\t0000003fffffffff\nCapEff:\t0000000000000000\n",
+            CAP_NET_ADMIN
+        ));
+        assert!(!has_capability("Name:\tmev\n", CAP_NET_ADMIN));
+    }
+
+    /// Ethernet + IPv4 (20-byte header) + UDP header to `port`.
+    fn udp_frame(port: u16, fragment: u16) -> [u8; 42] {
+        let mut frame = [0u8; 42];
+        frame[12..14].copy_from_slice(&[0x08, 0x00]);
+        frame[14] = 0x45;
+        frame[20..22].copy_from_slice(&fragment.to_be_bytes());
+        frame[23] = 17;
+        frame[36..38].copy_from_slice(&port.to_be_bytes());
+        frame
+    }
+
+    #[test]
+    fn udp_destination_ports_are_read_from_first_fragments() {
+        assert_eq!(udp_dst_port(&udp_frame(8081, 0)), Some(8081));
+        // "Don't fragment" set, offset 0: still a whole datagram.
+        assert_eq!(udp_dst_port(&udp_frame(8081, 0x4000)), Some(8081));
+        assert_eq!(udp_dst_port(&udp_frame(8081, 185)), None);
+        let mut tcp = udp_frame(8080, 0);
+        tcp[23] = 6;
+        assert_eq!(udp_dst_port(&tcp), None);
+        assert_eq!(udp_dst_port(&udp_frame(8080, 0)[..30]), None);
+    }
+
+    #[test]
+    fn drops_are_counted_once_the_socket_is_drained() {
+        let mut counter = RxDropCounter::default();
+        // 10 arrived, 4 drained, more still queued: nothing known yet.
+        assert_eq!(counter.update(10, 4, false), 0);
+        // 2 more arrived; 5 drained and the socket is empty: 3 never made it.
+        assert_eq!(counter.update(12, 5, true), 3);
+        assert_eq!(counter.update(15, 3, true), 0);
+    }
+
+    #[test]
+    fn oversized_names_are_refused_before_any_syscall() {
+        let config = TapConfig {
+            name: "tap-name-far-too-long",
+            ..TapConfig::default()
+        };
+        assert!(matches!(
+            TapDevice::ensure(config),
+            Err(TapError::NameTooLong)
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+//! AF_XDP transport: the packet loop's side of [`XdpSocket`]'s rings.
+//!
+//! Every UMEM frame is in exactly one place: posted to the Fill ring, in
+//! the kernel, in the current RX burst, queued for TX, or on the free list.
+//! [`XdpTransport::rx_burst`] first takes back what the Completion ring
+//! returned and tops the Fill ring up from the free list, then takes a
+//! burst off the RX ring. The loop reads each frame in place
+//! ([`received`](XdpTransport::received)); to answer one, it turns the
+//! frame around and writes the reply over the payload
+//! ([`reply_in_place`](XdpTransport::reply_in_place)), and hands it back
+//! to [`release`](XdpTransport::release) for TX. Frames of the burst it does
+//! not take are recycled by [`finish_burst`](XdpTransport::finish_burst),
+//! which also wakes the driver for what was queued.
+use crate::burst::MAX_RX_BATCH;
+use crate::transport::UmemTxFrame;
+use crate::xdp::{
+    FrameAges, FrameStamp, UmemConfig, XdpConfig, XdpError, XdpRingDescriptor, XdpSocket, XdpUmem,
+};
+
+/// Ethernet + IPv4 (no options) + UDP header bytes left in front of a
+/// reply written in place.
+const HEADERS_LEN: usize = crate::dpdk::HEADERS_LEN;
+
+pub struct XdpTransport {
+    // Dropped first: the socket unmaps its rings before the UMEM goes away.
+    socket: XdpSocket,
+    umem: XdpUmem,
+    ages: FrameAges,
+    /// Frames neither posted to the kernel nor in use, by UMEM offset.
+    free: Vec<u64>,
+    burst: [XdpRingDescriptor; MAX_RX_BATCH],
+    burst_len: usize,
+    /// Burst frames handed out by `reply_in_place`, one bit each.
+    taken: u64,
+    /// Frames queued for TX since the last `finish_burst`.
+    queued: usize,
+}
+
+impl XdpTransport {
+    /// Allocate the UMEM, bind the socket and post as many frames to the
+    /// Fill ring as it holds. `ages` must be sized for `umem`.
+    pub fn open(config: XdpConfig, umem: UmemConfig, ages: FrameAges) -> Result<Self, XdpError> {
+        let umem = XdpUmem::allocate(umem)?;
+        let socket = XdpSocket::open(config, &umem)?;
+        let frame_size = umem.config.frame_size as u64;
+        let free = (0..umem.config.frame_count as u64)
+            .rev()
+            .map(|frame| frame * frame_size)
+            .collect();
+        let mut transport = Self {
+            socket,
+            umem,
+            ages,
+            free,
+            burst: [XdpRingDescriptor::default(); MAX_RX_BATCH],
+            burst_len: 0,
+            taken: 0,
+            queued: 0,
+        };
+        transport.refill(crate::runtime::now_cycles());
+        Ok(transport)
+    }
+
+    /// The socket, for inserting into an `XskMap`.
+    pub fn fd(&self) -> i32 {
+        self.socket.fd
+    }
+
+    /// Frames rejected as stale so far.
+    pub fn stale(&self) -> u64 {
+        self.ages.stale
+    }
+
+    /// Take back sent frames, top up the Fill ring, and take up to `max`
+    /// received frames (at most [`MAX_RX_BATCH`]) as the new burst,
+    /// stamped at cycle `now`. Returns the burst's size.
+    #[inline(always)]
+    pub fn rx_burst(&mut self, max: usize, now: u64) -> usize {
+        let mut done = [0u64; MAX_RX_BATCH];
+        loop {
+            let n = self.socket.completed(&mut done);
+            for &addr in &done[..n] {
+                self.free.push(self.frame_start(addr));
+            }
+            if n < done.len() {
+                break;
+            }
+        }
+        self.refill(now);
+        let max = max.min(MAX_RX_BATCH);
+        self.burst_len = self
+            .socket
+            .poll_rx_stamped(&mut self.burst[..max], &mut self.ages, now);
+        self.taken = 0;
+        self.burst_len
+    }
+
+    /// Frame `i` of the burst as received, with its stamps.
+    #[inline(always)]
+    pub fn received(&self, i: usize) -> (&[u8], FrameStamp) {
+        let desc = &self.burst[i];
+        let stamp = self.ages.stamp(desc.addr).unwrap_or_default();
+        (self.umem.packet(desc), stamp)
+    }
+
+    /// Whether frame `i` of the burst is too old to evaluate at `now`;
+    /// see [`FrameAges::reject`].
+    #[inline(always)]
+    pub fn too_old(&mut self, i: usize, now: u64) -> bool {
+        let Some(stamp) = self.ages.stamp(self.burst[i].addr) else {
+            return false;
+        };
+        self.ages.reject(stamp, now)
+    }
+
+    /// Frame `i` of the burst, received headers in front, for writing a
+    /// reply over its payload. Its descriptor must come back through
+    /// [`release`](Self::release).
+    #[inline(always)]
+    pub fn reply_in_place(&mut self, i: usize) -> UmemTxFrame<'_> {
+        let addr = self.burst[i].addr;
+        self.taken |= 1 << i;
+        // SAFETY: a burst frame belongs to userspace until it is released.
+        UmemTxFrame::new(unsafe { self.umem.packet_mut(addr) }, addr, HEADERS_LEN)
+    }
+
+    /// A free frame, for a further copy of a reply; `None` if none is left.
+    /// Its descriptor must come back through [`release`](Self::release).
+    #[inline(always)]
+    pub fn spare(&mut self) -> Option<UmemTxFrame<'_>> {
+        let addr = self.free.pop()?;
+        // SAFETY: a free frame is owned by no one else.
+        Some(UmemTxFrame::new(
+            unsafe { self.umem.packet_mut(addr) },
+            addr,
+            HEADERS_LEN,
+        ))
+    }
+
+    /// Hand back the frame `desc` describes (see
+    /// [`UmemTxFrame::descriptor`]) at cycle `now`: queued for TX if `send`,
+    /// recycled if not or if the TX ring is full. Returns whether it was
+    /// queued.
+    #[inline(always)]
+    pub fn release(&mut self, desc: XdpRingDescriptor, send: bool, now: u64) -> bool {
+        self.ages.processed(desc.addr, now);
+        if send && self.socket.send(&[desc]) == 1 {
+            self.queued += 1;
+            return true;
+        }
+        self.free.push(self.frame_start(desc.addr));
+        false
+    }
+
+    /// Done with the burst at cycle `now`: recycle the frames not handed
+    /// out and wake the driver for the replies queued.
+    #[inline(always)]
+    pub fn finish_burst(&mut self, now: u64) {
+        for i in 0..self.burst_len {
+            if self.taken & (1 << i) == 0 {
+                let addr = self.burst[i].addr;
+                self.ages.processed(addr, now);
+                self.free.push(self.frame_start(addr));
+            }
+        }
+        self.burst_len = 0;
+        if core::mem::take(&mut self.queued) != 0 {
+            self.socket.kick_tx();
+        }
+    }
+
+    /// UMEM offset of the frame holding `addr`, which may point past its
+    /// headroom.
+    #[inline(always)]
+    fn frame_start(&self, addr: u64) -> u64 {
+        addr - addr % self.umem.config.frame_size as u64
+    }
+
+    /// Post free frames to the Fill ring, stamping them at `now`.
+    fn refill(&mut self, now: u64) {
+        let start = self.free.len() - self.socket.fill_room().min(self.free.len());
+        let posted = self.socket.fill(&self.free[start..]);
+        for &addr in &self.free[start..start + posted] {
+            self.ages.recycled(addr, now);
+        }
+        self.free.drain(start..start + posted);
+    }
+}
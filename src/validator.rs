@@ -5,58 +5,111 @@
 /// field invariants at cast time. This module shows the complementary usage:
 /// - `bytemuck` for maximum throughput in the hot loop (one pointer cast, no checks)
 /// - `zerocopy` for the outer validation layer (field range checks, endianness markers)
-use zerocopy::{AsBytes, FromBytes, FromZeroes};
+use crate::schema::{self, VersionWindow, WIRE_VERSION_MAX};
+use zerocopy::byteorder::little_endian::{U32, U64};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
 
 /// A validated pool state update broadcast from on-chain relayers.
 ///
-/// `FromBytes` + `AsBytes` from `zerocopy` guarantee that:
+/// `FromBytes` + `IntoBytes` from `zerocopy` guarantee that:
 /// 1. The type has no padding bytes (safe to reinterpret from wire data).
 /// 2. Any bit pattern is a valid instance (no enum discriminant traps).
 ///
+/// `Unaligned` (every field is a byte array or a `byteorder` wrapper) means
+/// the cast never fails on alignment, wherever the payload sits in the frame.
+///
 /// This mirrors how Solana program accounts are deserialized in
 /// high-throughput indexers (OpenBook, Phoenix) — via `bytemuck` / `zerocopy`
 /// rather than Anchor's serde-style `AccountDeserialize`.
 #[repr(C)]
-#[derive(Debug, Clone, Copy, FromBytes, AsBytes, FromZeroes)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, FromBytes, IntoBytes, Immutable, KnownLayout, Unaligned,
+)]
 pub struct PoolStateUpdate {
     /// Pool address (20 bytes, Ethereum-style or Solana truncated).
     pub pool_address: [u8; 20],
-    /// Current reserve of token0 (little-endian u64).
-    pub reserve0_le: [u8; 8],
-    /// Current reserve of token1 (little-endian u64).
-    pub reserve1_le: [u8; 8],
-    /// Block/slot number of this update (little-endian u64).
-    pub slot_le: [u8; 8],
-    /// Sequence number for detecting missed updates (little-endian u32).
-    pub seq_le: [u8; 4],
-    /// Padding to reach 64-byte cache-line alignment.
-    pub _pad: [u8; 16],
+    /// Current reserve of token0.
+    pub reserve0: U64,
+    /// Current reserve of token1.
+    pub reserve1: U64,
+    /// Block/slot number of this update.
+    pub slot: U64,
+    /// Sequence number for detecting missed updates.
+    pub seq: U32,
+    /// Schema version ([`crate::schema`]); zero from version 1 producers.
+    pub version: u8,
+    pub _reserved: [u8; 3],
+    /// Version 2: the pool's swap fee in parts per million.
+    pub fee_ppm: U32,
+    /// Version 3: when the relayer sent the update, unix nanoseconds by
+    /// its clock ([`crate::skew`]).
+    pub sent_ns: U64,
 }
 
-// Total: 20 + 8 + 8 + 8 + 4 + 16 = 64 bytes — exactly one cache line.
+// Total: 20 + 8 + 8 + 8 + 4 + 1 + 3 + 4 + 8 = 64 bytes — exactly one cache line.
 const _: () = assert!(core::mem::size_of::<PoolStateUpdate>() == 64);
 
 impl PoolStateUpdate {
     pub const WIRE_SIZE: usize = core::mem::size_of::<PoolStateUpdate>();
 
+    pub fn new(pool_address: [u8; 20], reserve0: u64, reserve1: u64, slot: u64, seq: u32) -> Self {
+        Self {
+            pool_address,
+            reserve0: U64::new(reserve0),
+            reserve1: U64::new(reserve1),
+            slot: U64::new(slot),
+            seq: U32::new(seq),
+            version: 0,
+            _reserved: [0; 3],
+            fee_ppm: U32::new(0),
+            sent_ns: U64::new(0),
+        }
+    }
+
+    /// The version 2 form, carrying the pool's fee.
+    pub fn with_fee_ppm(mut self, fee_ppm: u32) -> Self {
+        self.version = self.version.max(2);
+        self.fee_ppm = U32::new(fee_ppm);
+        self
+    }
+
+    /// The version 3 form, carrying the relayer's send time as well as the
+    /// fee (set it with [`with_fee_ppm`](Self::with_fee_ppm)).
+    pub fn with_sent_ns(mut self, sent_ns: u64) -> Self {
+        self.version = 3;
+        self.sent_ns = U64::new(sent_ns);
+        self
+    }
+
+    /// Schema version, 1 for producers that predate versioning.
+    #[inline(always)]
+    pub fn version(&self) -> u8 {
+        schema::normalize(self.version)
+    }
+
     #[inline(always)]
     pub fn reserve0(&self) -> u64 {
-        u64::from_le_bytes(self.reserve0_le)
+        self.reserve0.get()
     }
 
     #[inline(always)]
     pub fn reserve1(&self) -> u64 {
-        u64::from_le_bytes(self.reserve1_le)
+        self.reserve1.get()
     }
 
     #[inline(always)]
     pub fn slot(&self) -> u64 {
-        u64::from_le_bytes(self.slot_le)
+        self.slot.get()
     }
 
     #[inline(always)]
     pub fn seq(&self) -> u32 {
-        u32::from_le_bytes(self.seq_le)
+        self.seq.get()
+    }
+
+    #[inline(always)]
+    pub fn sent_ns(&self) -> u64 {
+        self.sent_ns.get()
     }
 }
 
@@ -65,37 +118,79 @@ impl PoolStateUpdate {
 pub enum ValidationError {
     /// Slice too short to contain a full `PoolStateUpdate`.
     TooShort,
-    /// `zerocopy` layout check failed (misaligned or wrong size).
+    /// `zerocopy` layout check failed (wrong size).
     LayoutMismatch,
     /// Both reserves are zero — indicates an uninitialized or invalid pool.
     ZeroReserves,
     /// Sequence number gap detected (missed update).
     SequenceGap { expected: u32, got: u32 },
+    /// Schema version this build does not decode, or outside the accepted
+    /// window.
+    UnsupportedVersion(u8),
+    /// Version 2 fee of 100% or more.
+    FeeOutOfRange(u32),
+}
+
+impl core::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::TooShort => write!(f, "shorter than a PoolStateUpdate"),
+            Self::LayoutMismatch => write!(f, "not a PoolStateUpdate layout"),
+            Self::ZeroReserves => write!(f, "both reserves are zero"),
+            Self::SequenceGap { expected, got } => {
+                write!(f, "sequence gap: expected {expected}, got {got}")
+            }
+            Self::UnsupportedVersion(v) => write!(f, "unsupported schema version {v}"),
+            Self::FeeOutOfRange(ppm) => write!(f, "fee of {ppm} ppm is 100% or more"),
+        }
+    }
 }
 
+impl core::error::Error for ValidationError {}
+
+/// A pool update as the node applies it, whatever its wire version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolUpdate {
+    pub version: u8,
+    pub reserve0: u64,
+    pub reserve1: u64,
+    pub slot: u64,
+    pub seq: u32,
+    /// Fee as `(numerator, denominator)`, if the update carries one.
+    pub fee: Option<(u64, u64)>,
+    /// Send time, unix nanoseconds, if the update carries one.
+    pub sent_ns: Option<u64>,
+}
+
+/// Parts per million, the denominator of a version 2 fee.
+pub const FEE_PPM_DENOMINATOR: u64 = 1_000_000;
+
 /// Validate and zero-copy cast a raw byte slice to a `PoolStateUpdate`.
 ///
-/// Uses `zerocopy::FromBytes::ref_from` — this is a guaranteed-safe
-/// pointer cast that also checks alignment and size at runtime.
+/// Uses `zerocopy::FromBytes::ref_from_bytes` — this is a guaranteed-safe
+/// pointer cast that also checks size at runtime.
 /// No copy, no allocation.
 ///
 /// Returns `Err(ValidationError)` if the slice is malformed or the pool
 /// state fails sanity checks.
 #[inline(always)]
-pub fn validate_pool_update<'a>(
-    data: &'a [u8],
+pub fn validate_pool_update(
+    data: &[u8],
     last_seq: u32,
-) -> Result<&'a PoolStateUpdate, ValidationError> {
+) -> Result<&PoolStateUpdate, ValidationError> {
     if data.len() < PoolStateUpdate::WIRE_SIZE {
         return Err(ValidationError::TooShort);
     }
-    // zerocopy::FromBytes::ref_from: zero-copy cast with layout validation.
-    let update = PoolStateUpdate::ref_from(&data[..PoolStateUpdate::WIRE_SIZE])
-        .ok_or(ValidationError::LayoutMismatch)?;
+    // zerocopy::FromBytes::ref_from_bytes: zero-copy cast with layout validation.
+    let update = PoolStateUpdate::ref_from_bytes(&data[..PoolStateUpdate::WIRE_SIZE])
+        .map_err(|_| ValidationError::LayoutMismatch)?;
 
     if update.reserve0() == 0 && update.reserve1() == 0 {
         return Err(ValidationError::ZeroReserves);
     }
+    if update.version() > WIRE_VERSION_MAX {
+        return Err(ValidationError::UnsupportedVersion(update.version()));
+    }
 
     // Sequence continuity check (wrapping arithmetic for rollover safety)
     let expected = last_seq.wrapping_add(1);
@@ -109,20 +204,60 @@ pub fn validate_pool_update<'a>(
     Ok(update)
 }
 
+/// Decode a validated update by its own version, if `window` accepts it.
+#[inline(always)]
+pub fn decode_pool_update(
+    update: &PoolStateUpdate,
+    window: VersionWindow,
+) -> Result<PoolUpdate, ValidationError> {
+    match update.version() {
+        v if !window.accepts(v) => Err(ValidationError::UnsupportedVersion(v)),
+        1 => Ok(decode_v1(update)),
+        2 => decode_v2(update),
+        3 => decode_v3(update),
+        v => Err(ValidationError::UnsupportedVersion(v)),
+    }
+}
+
+fn decode_v1(update: &PoolStateUpdate) -> PoolUpdate {
+    PoolUpdate {
+        version: 1,
+        reserve0: update.reserve0(),
+        reserve1: update.reserve1(),
+        slot: update.slot(),
+        seq: update.seq(),
+        fee: None,
+        sent_ns: None,
+    }
+}
+
+fn decode_v2(update: &PoolStateUpdate) -> Result<PoolUpdate, ValidationError> {
+    let fee_ppm = update.fee_ppm.get();
+    if fee_ppm as u64 >= FEE_PPM_DENOMINATOR {
+        return Err(ValidationError::FeeOutOfRange(fee_ppm));
+    }
+    Ok(PoolUpdate {
+        version: 2,
+        fee: Some((fee_ppm as u64, FEE_PPM_DENOMINATOR)),
+        ..decode_v1(update)
+    })
+}
+
+fn decode_v3(update: &PoolStateUpdate) -> Result<PoolUpdate, ValidationError> {
+    let sent_ns = Some(update.sent_ns()).filter(|&ns| ns != 0);
+    Ok(PoolUpdate {
+        version: 3,
+        sent_ns,
+        ..decode_v2(update)?
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use zerocopy::AsBytes;
 
     fn make_update(reserve0: u64, reserve1: u64, slot: u64, seq: u32) -> [u8; 64] {
-        let update = PoolStateUpdate {
-            pool_address: [0xCA; 20],
-            reserve0_le: reserve0.to_le_bytes(),
-            reserve1_le: reserve1.to_le_bytes(),
-            slot_le: slot.to_le_bytes(),
-            seq_le: seq.to_le_bytes(),
-            _pad: [0u8; 16],
-        };
+        let update = PoolStateUpdate::new([0xCA; 20], reserve0, reserve1, slot, seq);
         let mut buf = [0u8; 64];
         buf.copy_from_slice(update.as_bytes());
         buf
@@ -141,20 +276,32 @@ mod tests {
     #[test]
     fn zerocopy_rejects_zero_reserves() {
         let buf = make_update(0, 0, 1, 1);
-        assert_eq!(validate_pool_update(&buf, 0), Err(ValidationError::ZeroReserves));
+        assert_eq!(
+            validate_pool_update(&buf, 0),
+            Err(ValidationError::ZeroReserves)
+        );
     }
 
     #[test]
     fn zerocopy_detects_sequence_gap() {
         let buf = make_update(1_000, 2_000, 1, 5);
         let result = validate_pool_update(&buf, 3); // expected seq=4, got seq=5
-        assert_eq!(result, Err(ValidationError::SequenceGap { expected: 4, got: 5 }));
+        assert_eq!(
+            result,
+            Err(ValidationError::SequenceGap {
+                expected: 4,
+                got: 5
+            })
+        );
     }
 
     #[test]
     fn zerocopy_rejects_short_slice() {
         let short = [0u8; 10];
-        assert_eq!(validate_pool_update(&short, 0), Err(ValidationError::TooShort));
+        assert_eq!(
+            validate_pool_update(&short, 0),
+            Err(ValidationError::TooShort)
+        );
     }
 
     #[test]
@@ -167,4 +314,53 @@ mod tests {
         let update_ptr = update as *const _ as usize;
         assert_eq!(update_ptr, buf_ptr, "zerocopy must alias original buffer");
     }
+
+    #[test]
+    fn each_version_decodes_within_the_window() {
+        let v1 = make_update(1_000, 2_000, 7, 1);
+        let v2 = PoolStateUpdate::new([0xCA; 20], 1_000, 2_000, 7, 1).with_fee_ppm(3_000);
+        let v1 = validate_pool_update(&v1, 0).unwrap();
+        let v2 = validate_pool_update(v2.as_bytes(), 0).unwrap();
+        let both = VersionWindow::ALL;
+        assert_eq!(
+            decode_pool_update(v1, both).map(|u| (u.version, u.fee)),
+            Ok((1, None))
+        );
+        assert_eq!(
+            decode_pool_update(v2, both).map(|u| (u.version, u.reserve1, u.fee)),
+            Ok((2, 2_000, Some((3_000, 1_000_000))))
+        );
+        let v2_only = VersionWindow::parse("2").unwrap();
+        assert_eq!(
+            decode_pool_update(v1, v2_only),
+            Err(ValidationError::UnsupportedVersion(1))
+        );
+
+        let v3 = PoolStateUpdate::new([0xCA; 20], 1_000, 2_000, 7, 1)
+            .with_sent_ns(9)
+            .with_fee_ppm(500);
+        let decoded = decode_pool_update(&v3, both).map(|u| (u.version, u.fee, u.sent_ns));
+        assert_eq!(decoded, Ok((3, Some((500, 1_000_000)), Some(9))));
+        let mut future = PoolStateUpdate::new([0xCA; 20], 1_000, 2_000, 7, 1);
+        future.version = 4;
+        assert_eq!(
+            validate_pool_update(future.as_bytes(), 0),
+            Err(ValidationError::UnsupportedVersion(4))
+        );
+        let greedy = PoolStateUpdate::new([0xCA; 20], 1_000, 2_000, 7, 1).with_fee_ppm(1_000_000);
+        assert_eq!(
+            decode_pool_update(&greedy, both),
+            Err(ValidationError::FeeOutOfRange(1_000_000))
+        );
+    }
+
+    #[test]
+    fn unaligned_payload_casts_in_place() {
+        // Payloads start at odd offsets inside Ethernet frames.
+        let mut frame = [0u8; 65];
+        frame[1..].copy_from_slice(&make_update(7, 9, 3, 1));
+        let update = validate_pool_update(&frame[1..], 0).expect("no alignment requirement");
+        assert_eq!((update.reserve0(), update.reserve1()), (7, 9));
+        assert_eq!(update as *const _ as usize, frame[1..].as_ptr() as usize);
+    }
 }
@@ -0,0 +1,219 @@
+//! Startup warmup: pay cold-start costs before the first real packet.
+//!
+//! Memory the node sets up at startup is mostly not backed yet. The pool
+//! table and counters live in `.bss`, rings and windows are fresh heap or
+//! stack, and the state file is a new mapping. The first write to each page
+//! takes a page fault, and the first access takes a TLB miss. Branch
+//! predictors and caches have not seen a victim either. Left alone, all of
+//! that lands on the first packets of the session.
+//!
+//! [`Warmup`] moves the cost to startup:
+//!
+//! - [`Warmup::region`] backs every page of a structure with
+//!   `MADV_POPULATE_WRITE`. Contents are left as they are, so it is safe on
+//!   live state. Read-only memory, and kernels older than 5.14 which lack
+//!   the advice, get a read of each page instead. Either way each page is then read once, so its TLB
+//!   entry is resolved.
+//! - [`Warmup::prime`] feeds synthetic victims through an evaluation
+//!   closure, training the predictors and pulling the evaluator's code and
+//!   data into cache. The closure must not touch trading state.
+//! - [`Warmup::lock_memory`] (`MEV_MLOCK`) locks everything mapped so far
+//!   with `mlockall(MCL_CURRENT)`. This also faults in what `region` cannot
+//!   name, such as smoltcp's socket buffers. Later mappings stay unlocked,
+//!   so a tight `RLIMIT_MEMLOCK` cannot make future allocations fail.
+//!
+//! AF_XDP UMEM is already mapped with `MAP_POPULATE` and locked by
+//! [`crate::xdp`].
+use crate::payload::{DexSwapTx, DexSwapTxExt};
+use crate::runtime;
+use core::mem::MaybeUninit;
+
+/// Granularity of faults and TLB entries assumed for regions.
+pub const PAGE_SIZE: usize = 4096;
+
+/// `MEV_WARMUP`, `MEV_WARMUP_PACKETS` and `MEV_MLOCK`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WarmupConfig {
+    pub enabled: bool,
+    /// Synthetic victims evaluated.
+    pub packets: usize,
+    /// Lock all current mappings into RAM.
+    pub lock_memory: bool,
+}
+
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            packets: 4096,
+            lock_memory: false,
+        }
+    }
+}
+
+/// What the warmup did.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WarmupReport {
+    pub regions: usize,
+    /// Pages spanned by the regions.
+    pub pages: usize,
+    /// Of those, pages backed by `MADV_POPULATE_WRITE`; the rest were only
+    /// read.
+    pub populated: usize,
+    pub packets: usize,
+    /// `mlockall` result, if asked for: `Err(errno)` on failure.
+    pub locked: Option<Result<(), i32>>,
+    pub cycles: u64,
+}
+
+impl core::fmt::Display for WarmupReport {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} regions, {}/{} pages populated, {} packets, {} cycles",
+            self.regions, self.populated, self.pages, self.packets, self.cycles
+        )?;
+        match self.locked {
+            Some(Ok(())) => write!(f, ", memory locked"),
+            Some(Err(e)) => write!(f, ", mlockall failed (errno={e})"),
+            None => Ok(()),
+        }
+    }
+}
+
+pub struct Warmup {
+    started: u64,
+    report: WarmupReport,
+}
+
+impl Default for Warmup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Warmup {
+    pub fn new() -> Self {
+        Self {
+            started: runtime::now_cycles(),
+            report: WarmupReport::default(),
+        }
+    }
+
+    /// Back and touch every page `value` spans.
+    pub fn region<T: ?Sized>(&mut self, value: &T) {
+        let len = core::mem::size_of_val(value);
+        if len == 0 {
+            return;
+        }
+        let start = value as *const T as *const u8 as usize;
+        let first = start & !(PAGE_SIZE - 1);
+        let pages = (start + len - first).div_ceil(PAGE_SIZE);
+        self.report.regions += 1;
+        self.report.pages += pages;
+        // Advice works on whole pages. It maps pages without writing to
+        // them, so the neighbours sharing the first and last page are safe.
+        let advised = unsafe {
+            libc::madvise(
+                first as *mut libc::c_void,
+                pages * PAGE_SIZE,
+                libc::MADV_POPULATE_WRITE,
+            )
+        } == 0;
+        if advised {
+            self.report.populated += pages;
+        }
+        // One read per page, within `value`, for the TLB. Padding may be
+        // uninitialized, hence `MaybeUninit`.
+        let base = start as *const MaybeUninit<u8>;
+        let mut offset = 0;
+        while offset < len {
+            // SAFETY: `offset < len`, inside `value`.
+            unsafe { core::hint::black_box(base.add(offset).read_volatile()) };
+            offset = (start + offset + PAGE_SIZE) / PAGE_SIZE * PAGE_SIZE - start;
+        }
+    }
+
+    /// Evaluate `packets` synthetic victims against `pools` with `evaluate`.
+    ///
+    /// The mix covers both directions, base and deadline-carrying records,
+    /// dust, profitable swaps and ones whose slippage guard reverts, so
+    /// every branch of the evaluator is taken.
+    pub fn prime(&mut self, packets: usize, pools: &[[u8; 20]], mut evaluate: impl FnMut(&[u8])) {
+        if pools.is_empty() {
+            return;
+        }
+        for i in 0..packets {
+            let n = i as u64;
+            let amount_in = if i % 4 == 0 {
+                500
+            } else {
+                10_000_000 + (n % 64) * 1_000_000
+            };
+            let min_out = if i % 8 == 7 { u64::MAX } else { 1 };
+            let tx =
+                DexSwapTx::from_parts(n, pools[i % pools.len()], amount_in, min_out, (i & 1) as u8);
+            if i % 3 == 0 {
+                evaluate(bytemuck::bytes_of(&DexSwapTxExt::from_parts(tx, 0)));
+            } else {
+                evaluate(bytemuck::bytes_of(&tx));
+            }
+        }
+        self.report.packets += packets;
+    }
+
+    /// `mlockall(MCL_CURRENT)`; needs `CAP_IPC_LOCK` or enough
+    /// `RLIMIT_MEMLOCK`.
+    pub fn lock_memory(&mut self) {
+        let locked = if unsafe { libc::mlockall(libc::MCL_CURRENT) } == 0 {
+            Ok(())
+        } else {
+            Err(unsafe { *libc::__errno_location() })
+        };
+        self.report.locked = Some(locked);
+    }
+
+    pub fn finish(mut self) -> WarmupReport {
+        self.report.cycles = runtime::now_cycles().saturating_sub(self.started);
+        self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_count_spanned_pages_and_keep_their_contents() {
+        let table = vec![0xa5u8; 3 * PAGE_SIZE + 100];
+        let mut warmup = Warmup::new();
+        warmup.region(table.as_slice());
+        warmup.region(&[0u8; 0][..]);
+        let counter = 7u64;
+        warmup.region(&counter);
+        let mut seen = Vec::new();
+        warmup.prime(10, &[[1; 20], [2; 20]], |payload| {
+            seen.push((payload.len(), payload[8]))
+        });
+        warmup.prime(10, &[], |_| unreachable!());
+        let report = warmup.finish();
+
+        assert!(table.iter().all(|&b| b == 0xa5));
+        assert_eq!(report.regions, 2);
+        // Four pages of bytes, plus one if they straddle a fifth; one for the u64.
+        let straddles =
+            (table.as_ptr() as usize % PAGE_SIZE + table.len()).div_ceil(PAGE_SIZE) == 5;
+        assert_eq!(report.pages, 4 + straddles as usize + 1);
+        assert!(report.populated == 0 || report.populated == report.pages);
+        assert_eq!(report.packets, 10);
+        assert_eq!(
+            seen[..3],
+            [
+                (DexSwapTxExt::WIRE_SIZE, 1),
+                (DexSwapTx::WIRE_SIZE, 2),
+                (DexSwapTx::WIRE_SIZE, 1)
+            ]
+        );
+        assert!(report.to_string().starts_with("2 regions, "));
+    }
+}
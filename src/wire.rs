@@ -0,0 +1,6 @@
+//! Producing the node's wire format from other Rust code.
+//!
+//! The message types themselves live with the code that consumes them
+//! ([`crate::payload`], [`crate::validator`], ...); this module is the
+//! supported way for feed producers to build them.
+pub mod encoder;
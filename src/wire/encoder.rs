@@ -0,0 +1,422 @@
+//! Builders that encode node messages into caller-provided buffers.
+//!
+//! A relayer written in Rust depends on this crate and encodes through
+//! these builders instead of laying out bytes itself, so producer and
+//! consumer always agree on the format. Nothing allocates: every `encode`
+//! writes into the slice it is given and returns the byte count.
+//!
+//! ```
+//! use mev_zerocopy_node::wire::encoder::{Direction, PoolUpdateEncoder, SwapEncoder, TxBatchEncoder};
+//!
+//! let mut datagram = [0u8; 96];
+//! let len = SwapEncoder::new(7, [0xaa; 20], Direction::ZeroForOne)
+//!     .amount_in(50_000_000)
+//!     .min_amount_out(1)
+//!     .deadline_ms(1_700_000_000_000)
+//!     .encode(&mut datagram)?;
+//! assert_eq!(len, 64);
+//!
+//! let mut frame = [0u8; 1024];
+//! let mut batch = TxBatchEncoder::new(&mut frame);
+//! for nonce in 0..4 {
+//!     batch.push(&SwapEncoder::new(nonce, [0xaa; 20], Direction::OneForZero).amount_in(1_000_000))?;
+//! }
+//! assert_eq!(batch.bytes().len(), 4 * 48);
+//!
+//! let update = PoolUpdateEncoder::new([0xaa; 20], 42).reserves(10_000, 5_000).slot(9);
+//! assert_eq!(update.encode(&mut datagram)?, 64);
+//! # Ok::<(), mev_zerocopy_node::wire::encoder::EncodeError>(())
+//! ```
+use crate::payload::{DexSwapTx, DexSwapTxEvm, DexSwapTxExt};
+use crate::pool_registry::PoolAddress;
+use crate::validator::PoolStateUpdate;
+use zerocopy::IntoBytes;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodeError {
+    /// The message needs `needed` bytes; the buffer has `available`.
+    BufferTooSmall { needed: usize, available: usize },
+    /// The all-zero pool address marks empty pool-table slots.
+    ZeroPoolAddress,
+    /// A swap with no input amount.
+    ZeroAmount,
+    /// Both reserves zero: the node rejects the update.
+    ZeroReserves,
+    /// Batches carry base `DexSwapTx` records only; deadline, priority fee
+    /// and EVM fields do not fit them.
+    ExtendedInBatch,
+    /// A version 3 pool update (send time) carries the fee as well.
+    SentWithoutFee,
+}
+
+impl core::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::BufferTooSmall { needed, available } => {
+                write!(f, "message needs {needed} bytes, buffer has {available}")
+            }
+            Self::ZeroPoolAddress => write!(f, "pool address is all zero"),
+            Self::ZeroAmount => write!(f, "swap amount_in is zero"),
+            Self::ZeroReserves => write!(f, "both pool reserves are zero"),
+            Self::ExtendedInBatch => write!(
+                f,
+                "batch records cannot carry deadline, priority fee or EVM fields"
+            ),
+            Self::SentWithoutFee => write!(f, "a pool update with a send time needs a fee"),
+        }
+    }
+}
+
+impl core::error::Error for EncodeError {}
+
+/// Copy `bytes` to the start of `out`.
+fn write(bytes: &[u8], out: &mut [u8]) -> Result<usize, EncodeError> {
+    let available = out.len();
+    let dst = out
+        .get_mut(..bytes.len())
+        .ok_or(EncodeError::BufferTooSmall {
+            needed: bytes.len(),
+            available,
+        })?;
+    dst.copy_from_slice(bytes);
+    Ok(bytes.len())
+}
+
+/// Which way a swap trades.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Token0 in, token1 out.
+    ZeroForOne = 0,
+    /// Token1 in, token0 out.
+    OneForZero = 1,
+}
+
+/// A victim swap. Encodes as a 48-byte `DexSwapTx`; with
+/// [`deadline_ms`](Self::deadline_ms) or
+/// [`priority_fee`](Self::priority_fee) as a 64-byte `DexSwapTxExt`, and
+/// with [`evm`](Self::evm) as a 96-byte `DexSwapTxEvm`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapEncoder {
+    nonce: u64,
+    pool: PoolAddress,
+    direction: Direction,
+    amount_in: u64,
+    min_amount_out: u64,
+    deadline_ms: Option<u64>,
+    priority_fee: Option<u64>,
+    evm: Option<([u8; 20], u64)>,
+}
+
+impl SwapEncoder {
+    pub const fn new(nonce: u64, pool: PoolAddress, direction: Direction) -> Self {
+        Self {
+            nonce,
+            pool,
+            direction,
+            amount_in: 0,
+            min_amount_out: 0,
+            deadline_ms: None,
+            priority_fee: None,
+            evm: None,
+        }
+    }
+
+    pub const fn amount_in(mut self, amount_in: u64) -> Self {
+        self.amount_in = amount_in;
+        self
+    }
+
+    /// Slippage bound: the victim reverts below this output.
+    pub const fn min_amount_out(mut self, min_amount_out: u64) -> Self {
+        self.min_amount_out = min_amount_out;
+        self
+    }
+
+    /// On-chain deadline, unix milliseconds.
+    pub const fn deadline_ms(mut self, deadline_ms: u64) -> Self {
+        self.deadline_ms = Some(deadline_ms);
+        self
+    }
+
+    /// Priority fee per gas unit, in raw units of the chain's fee token.
+    pub const fn priority_fee(mut self, priority_fee: u64) -> Self {
+        self.priority_fee = Some(priority_fee);
+        self
+    }
+
+    /// Whether the swap needs the `DexSwapTxExt` fields.
+    const fn is_extended(&self) -> bool {
+        self.deadline_ms.is_some() || self.priority_fee.is_some()
+    }
+
+    /// EVM sender and effective gas price (wei); the nonce is then the
+    /// sender's account nonce.
+    pub const fn evm(mut self, sender: [u8; 20], gas_price: u64) -> Self {
+        self.evm = Some((sender, gas_price));
+        self
+    }
+
+    /// Encoded length: 48, 64 or 96 bytes.
+    pub const fn wire_size(&self) -> usize {
+        match (self.evm, self.is_extended()) {
+            (Some(_), _) => DexSwapTxEvm::WIRE_SIZE,
+            (None, true) => DexSwapTxExt::WIRE_SIZE,
+            (None, false) => DexSwapTx::WIRE_SIZE,
+        }
+    }
+
+    fn base(&self) -> Result<DexSwapTx, EncodeError> {
+        if self.pool == [0; 20] {
+            return Err(EncodeError::ZeroPoolAddress);
+        }
+        if self.amount_in == 0 {
+            return Err(EncodeError::ZeroAmount);
+        }
+        let direction = self.direction as u8;
+        Ok(DexSwapTx::from_parts(
+            self.nonce,
+            self.pool,
+            self.amount_in,
+            self.min_amount_out,
+            direction,
+        ))
+    }
+
+    /// Write the swap to the start of `out`; returns its length.
+    pub fn encode(&self, out: &mut [u8]) -> Result<usize, EncodeError> {
+        let base = self.base()?;
+        let ext = DexSwapTxExt::from_parts(base, self.deadline_ms.unwrap_or(0))
+            .with_priority_fee(self.priority_fee.unwrap_or(0));
+        match (self.evm, self.is_extended()) {
+            (Some((sender, gas_price)), _) => write(
+                bytemuck::bytes_of(&DexSwapTxEvm::from_parts(ext, sender, gas_price)),
+                out,
+            ),
+            (None, true) => write(bytemuck::bytes_of(&ext), out),
+            (None, false) => write(bytemuck::bytes_of(&base), out),
+        }
+    }
+}
+
+/// Back-to-back `DexSwapTx` records in one frame, as the node's batch
+/// path (`DexSwapTx::cast_batch`) reads them.
+pub struct TxBatchEncoder<'a> {
+    out: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> TxBatchEncoder<'a> {
+    pub fn new(out: &'a mut [u8]) -> Self {
+        Self { out, len: 0 }
+    }
+
+    /// Append one record; the batch is unchanged on error.
+    pub fn push(&mut self, swap: &SwapEncoder) -> Result<(), EncodeError> {
+        if swap.is_extended() || swap.evm.is_some() {
+            return Err(EncodeError::ExtendedInBatch);
+        }
+        self.len += write(bytemuck::bytes_of(&swap.base()?), &mut self.out[self.len..])?;
+        Ok(())
+    }
+
+    /// Records appended so far.
+    pub fn records(&self) -> usize {
+        self.len / DexSwapTx::WIRE_SIZE
+    }
+
+    /// Records that still fit.
+    pub fn remaining(&self) -> usize {
+        (self.out.len() - self.len) / DexSwapTx::WIRE_SIZE
+    }
+
+    /// The encoded batch.
+    pub fn bytes(&self) -> &[u8] {
+        &self.out[..self.len]
+    }
+}
+
+/// A pool reserve update (`PoolStateUpdate`, 64 bytes).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolUpdateEncoder {
+    pool: PoolAddress,
+    seq: u32,
+    reserve0: u64,
+    reserve1: u64,
+    slot: u64,
+    fee_ppm: Option<u32>,
+    sent_ns: Option<u64>,
+}
+
+impl PoolUpdateEncoder {
+    /// Update `seq` of `pool`; the node expects each pool's sequence to
+    /// advance by one per update.
+    pub const fn new(pool: PoolAddress, seq: u32) -> Self {
+        Self {
+            pool,
+            seq,
+            reserve0: 0,
+            reserve1: 0,
+            slot: 0,
+            fee_ppm: None,
+            sent_ns: None,
+        }
+    }
+
+    pub const fn reserves(mut self, reserve0: u64, reserve1: u64) -> Self {
+        self.reserve0 = reserve0;
+        self.reserve1 = reserve1;
+        self
+    }
+
+    /// Block/slot the reserves were observed at.
+    pub const fn slot(mut self, slot: u64) -> Self {
+        self.slot = slot;
+        self
+    }
+
+    /// Swap fee in parts per million; makes the update version 2, which
+    /// nodes built before fees were carried reject.
+    pub const fn fee_ppm(mut self, fee_ppm: u32) -> Self {
+        self.fee_ppm = Some(fee_ppm);
+        self
+    }
+
+    /// When the update is sent, unix nanoseconds by the relayer's clock;
+    /// makes the update version 3, which needs a fee as well.
+    pub const fn sent_ns(mut self, sent_ns: u64) -> Self {
+        self.sent_ns = Some(sent_ns);
+        self
+    }
+
+    /// Write the update to the start of `out`; returns its length.
+    pub fn encode(&self, out: &mut [u8]) -> Result<usize, EncodeError> {
+        if self.pool == [0; 20] {
+            return Err(EncodeError::ZeroPoolAddress);
+        }
+        if self.reserve0 == 0 && self.reserve1 == 0 {
+            return Err(EncodeError::ZeroReserves);
+        }
+        let mut update =
+            PoolStateUpdate::new(self.pool, self.reserve0, self.reserve1, self.slot, self.seq);
+        if let Some(fee_ppm) = self.fee_ppm {
+            update = update.with_fee_ppm(fee_ppm);
+        }
+        match (self.sent_ns, self.fee_ppm) {
+            (Some(_), None) => return Err(EncodeError::SentWithoutFee),
+            (Some(sent_ns), Some(_)) => update = update.with_sent_ns(sent_ns),
+            (None, _) => {}
+        }
+        write(update.as_bytes(), out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validator::validate_pool_update;
+
+    const POOL: PoolAddress = [0xaa; 20];
+
+    #[test]
+    fn swaps_encode_at_each_size_and_parse_back() {
+        let swap = SwapEncoder::new(7, POOL, Direction::OneForZero)
+            .amount_in(5_000)
+            .min_amount_out(4_900);
+        let mut buf = [0u8; 96];
+        assert_eq!(swap.encode(&mut buf), Ok(48));
+        let tx = DexSwapTx::header(&buf).unwrap();
+        assert_eq!(
+            (
+                tx.nonce(),
+                tx.amount_in(),
+                tx.min_amount_out(),
+                tx.token_direction
+            ),
+            (7, 5_000, 4_900, 1)
+        );
+
+        assert_eq!(swap.deadline_ms(1_234).encode(&mut buf), Ok(64));
+        assert_eq!(DexSwapTxExt::parse(&buf).unwrap().deadline_ms(), 1_234);
+        assert_eq!(swap.priority_fee(3).encode(&mut buf), Ok(64));
+        assert_eq!(
+            DexSwapTxExt::parse(&buf).map(|e| (e.deadline_ms(), e.priority_fee())),
+            Some((0, 3))
+        );
+        let evm = swap.evm([0xcc; 20], 30);
+        assert_eq!((evm.wire_size(), evm.encode(&mut buf)), (96, Ok(96)));
+        let parsed = DexSwapTxEvm::parse(&buf).unwrap();
+        assert_eq!(
+            (parsed.sender, parsed.gas_price(), parsed.ext.deadline_ms()),
+            ([0xcc; 20], 30, 0)
+        );
+
+        assert_eq!(
+            evm.encode(&mut buf[..64]),
+            Err(EncodeError::BufferTooSmall {
+                needed: 96,
+                available: 64
+            })
+        );
+        assert_eq!(
+            SwapEncoder::new(1, [0; 20], Direction::ZeroForOne)
+                .amount_in(1)
+                .encode(&mut buf),
+            Err(EncodeError::ZeroPoolAddress)
+        );
+        assert_eq!(
+            SwapEncoder::new(1, POOL, Direction::ZeroForOne).encode(&mut buf),
+            Err(EncodeError::ZeroAmount)
+        );
+    }
+
+    #[test]
+    fn batch_holds_base_records_until_full() {
+        let mut frame = [0u8; 100];
+        let mut batch = TxBatchEncoder::new(&mut frame);
+        let swap = SwapEncoder::new(1, POOL, Direction::ZeroForOne).amount_in(10);
+        batch.push(&swap).unwrap();
+        assert_eq!(
+            batch.push(&swap.deadline_ms(5)),
+            Err(EncodeError::ExtendedInBatch)
+        );
+        batch.push(&SwapEncoder { nonce: 2, ..swap }).unwrap();
+        assert_eq!(
+            batch.push(&swap),
+            Err(EncodeError::BufferTooSmall {
+                needed: 48,
+                available: 4
+            })
+        );
+        assert_eq!((batch.records(), batch.remaining()), (2, 0));
+        let nonces: [u64; 2] =
+            core::array::from_fn(|i| DexSwapTx::cast_batch(batch.bytes())[i].nonce());
+        assert_eq!(nonces, [1, 2]);
+    }
+
+    #[test]
+    fn pool_update_passes_the_validator() {
+        let mut buf = [0u8; 64];
+        let update = PoolUpdateEncoder::new(POOL, 4)
+            .reserves(1_000, 2_000)
+            .slot(77);
+        assert_eq!(update.encode(&mut buf), Ok(64));
+        let parsed = validate_pool_update(&buf, 3).unwrap();
+        assert_eq!(
+            (
+                parsed.pool_address,
+                parsed.reserve1(),
+                parsed.slot(),
+                parsed.seq()
+            ),
+            (POOL, 2_000, 77, 4)
+        );
+        assert_eq!(
+            PoolUpdateEncoder::new(POOL, 5).encode(&mut buf),
+            Err(EncodeError::ZeroReserves)
+        );
+        assert_eq!(
+            update.sent_ns(9).encode(&mut buf),
+            Err(EncodeError::SentWithoutFee)
+        );
+    }
+}
@@ -12,6 +12,7 @@
 ///
 /// This module provides:
 /// - `XdpConfig` and `XdpMode` — configuration types
+/// - `InterfaceName` — interface name stored inline, NUL-terminated
 /// - `UmemConfig` — UMEM memory region parameters
 /// - `XdpRingDescriptor` — the actual ring buffer entry (POD, cache-aligned)
 /// - `XdpUmem` — UMEM descriptor (real mmap on Linux, stub elsewhere)
@@ -28,10 +29,75 @@ pub enum XdpMode {
     Generic,
 }
 
+/// Longest interface name the kernel accepts (`IFNAMSIZ` less the NUL).
+pub const MAX_INTERFACE_NAME: usize = 15;
+
+/// A network interface name, stored inline with its NUL terminator so it
+/// can be built at runtime and handed to `if_nametoindex` as is.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceName {
+    /// The name, then NUL padding; at least the last byte is NUL.
+    bytes: [u8; MAX_INTERFACE_NAME + 1],
+}
+
+impl InterfaceName {
+    /// `veth0`, the default.
+    pub const DEFAULT: Self = match Self::new("veth0") {
+        Some(name) => name,
+        None => panic!("valid interface name"),
+    };
+
+    /// `None` if `name` is empty, longer than [`MAX_INTERFACE_NAME`] bytes,
+    /// or contains a NUL.
+    pub const fn new(name: &str) -> Option<Self> {
+        let name = name.as_bytes();
+        if name.is_empty() || name.len() > MAX_INTERFACE_NAME {
+            return None;
+        }
+        let mut bytes = [0u8; MAX_INTERFACE_NAME + 1];
+        let mut i = 0;
+        while i < name.len() {
+            if name[i] == 0 {
+                return None;
+            }
+            bytes[i] = name[i];
+            i += 1;
+        }
+        Some(Self { bytes })
+    }
+
+    pub fn as_str(&self) -> &str {
+        let len = self
+            .bytes
+            .iter()
+            .position(|&b| b == 0)
+            .unwrap_or(MAX_INTERFACE_NAME);
+        // Copied whole from a `&str` without NULs: valid UTF-8 up to the first NUL.
+        core::str::from_utf8(&self.bytes[..len]).unwrap_or_default()
+    }
+
+    /// The name as a C string, for `if_nametoindex` and friends.
+    pub fn as_c_str(&self) -> &core::ffi::CStr {
+        core::ffi::CStr::from_bytes_until_nul(&self.bytes).unwrap_or_default()
+    }
+}
+
+impl core::fmt::Display for InterfaceName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl core::fmt::Debug for InterfaceName {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct XdpConfig {
     /// Network interface name (e.g., "eth0", "veth-bot0").
-    pub interface: &'static str,
+    pub interface: InterfaceName,
     /// Hardware queue index to bind to (0 for first queue).
     pub queue_id: u32,
     /// XDP loading mode.
@@ -41,7 +107,7 @@ pub struct XdpConfig {
 impl Default for XdpConfig {
     fn default() -> Self {
         Self {
-            interface: "veth0",
+            interface: InterfaceName::DEFAULT,
             queue_id: 0,
             mode: XdpMode::Native,
         }
@@ -90,29 +156,181 @@ impl UmemConfig {
 /// RX descriptors (addr + len) into the RX ring; userspace reads them,
 /// processes the frame at UMEM[addr..addr+len], then recycles via Fill ring.
 ///
-/// `#[repr(C, align(64))]` ensures:
-/// - Correct ABI layout expected by the Linux kernel.
-/// - Each descriptor occupies its own cache line (no false sharing).
-#[repr(C, align(64))]
-#[derive(Clone, Copy, Debug, Default)]
+/// `#[repr(C)]` with the layout of the kernel's `struct xdp_desc`, the
+/// entry of both the RX and the TX ring.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
 pub struct XdpRingDescriptor {
-    /// Byte offset of the frame within the UMEM region.
+    /// Byte offset of the packet within the UMEM region.
     pub addr: u64,
     /// Number of valid bytes in the frame.
     pub len: u32,
     /// Kernel-internal options field (must be zero for userspace).
     pub options: u32,
-    /// Padding to fill cache line (64 - 8 - 4 - 4 = 48 bytes).
-    _pad: [u8; 48],
 }
 
-const _: () = assert!(core::mem::size_of::<XdpRingDescriptor>() == 64);
+const _: () = assert!(core::mem::size_of::<XdpRingDescriptor>() == 16);
+
+impl Default for XdpRingDescriptor {
+    fn default() -> Self {
+        Self::new(0, 0)
+    }
+}
 
 impl XdpRingDescriptor {
     /// Create a new descriptor pointing to a UMEM frame.
     #[inline(always)]
     pub fn new(addr: u64, len: u32) -> Self {
-        Self { addr, len, options: 0, _pad: [0u8; 48] }
+        Self {
+            addr,
+            len,
+            options: 0,
+        }
+    }
+}
+
+use crate::runtime::FrameDwell;
+
+/// TSC stamps of one received frame.
+///
+/// The frame was empty when it was posted to the Fill ring, so the packet
+/// in it arrived somewhere between `recycled_cycles` and `rx_cycles`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStamp {
+    /// When the frame was last posted to the Fill ring.
+    pub recycled_cycles: u64,
+    /// When its descriptor was dequeued from the RX ring.
+    pub rx_cycles: u64,
+}
+
+impl FrameStamp {
+    /// Age at `now` the frame is known to have reached.
+    #[inline(always)]
+    pub fn min_age(&self, now: u64) -> u64 {
+        now.wrapping_sub(self.rx_cycles)
+    }
+
+    /// Age at `now` the frame cannot have exceeded.
+    #[inline(always)]
+    pub fn max_age(&self, now: u64) -> u64 {
+        now.wrapping_sub(self.recycled_cycles)
+    }
+}
+
+/// Per-UMEM-frame age tracking for stale-frame rejection.
+///
+/// Frames are stamped when recycled to the Fill ring and again when their
+/// descriptor comes off the RX ring. A frame whose minimum age exceeds
+/// `max_age_cycles` is rejected before evaluation: a victim that old has
+/// most likely been included already, and bidding on it only burns gas.
+/// Frames of uncertain age (recycled long ago, received just now) are kept.
+///
+/// With [`with_dwell`](Self::with_dwell), the time a frame spends in each
+/// leg of its round trip is also recorded — in the kernel (Fill to RX), in
+/// the packet loop (RX to [`processed`](Self::processed)) and waiting to be
+/// refilled — to size the UMEM and pace Fill-ring replenishment by. A leg
+/// whose start was not stamped is not recorded.
+pub struct FrameAges {
+    frame_size: u64,
+    /// 0 disables rejection; stamps are still recorded.
+    max_age_cycles: u64,
+    recycled: Box<[u64]>,
+    received: Box<[u64]>,
+    /// When each frame was processed; 0 once it is refilled.
+    processed: Box<[u64]>,
+    dwell: Option<&'static FrameDwell>,
+    /// Frames rejected as stale.
+    pub stale: u64,
+}
+
+impl FrameAges {
+    pub fn new(config: &UmemConfig, max_age_cycles: u64) -> Self {
+        let frames = config.frame_count as usize;
+        Self {
+            frame_size: config.frame_size.max(1) as u64,
+            max_age_cycles,
+            recycled: vec![0; frames].into_boxed_slice(),
+            received: vec![0; frames].into_boxed_slice(),
+            processed: vec![0; frames].into_boxed_slice(),
+            dwell: None,
+            stale: 0,
+        }
+    }
+
+    /// Record dwell times into `dwell`.
+    pub fn with_dwell(mut self, dwell: &'static FrameDwell) -> Self {
+        self.dwell = Some(dwell);
+        self
+    }
+
+    /// Record that the frame at UMEM offset `addr` was posted to the Fill
+    /// ring at cycle `now`.
+    #[inline(always)]
+    pub fn recycled(&mut self, addr: u64, now: u64) {
+        let index = (addr / self.frame_size) as usize;
+        if let Some(stamp) = self.recycled.get_mut(index) {
+            *stamp = now;
+        }
+        if let (Some(dwell), Some(processed)) = (self.dwell, self.processed.get_mut(index)) {
+            if *processed != 0 {
+                dwell
+                    .refill
+                    .record(now.wrapping_sub(core::mem::take(processed)));
+            }
+        }
+    }
+
+    /// Record that `desc` was dequeued from the RX ring at cycle `now` and
+    /// return the frame's stamps.
+    #[inline(always)]
+    pub fn received(&mut self, desc: &XdpRingDescriptor, now: u64) -> FrameStamp {
+        let index = (desc.addr / self.frame_size) as usize;
+        if let Some(stamp) = self.received.get_mut(index) {
+            *stamp = now;
+        }
+        let recycled = self.recycled.get(index).copied();
+        if let Some((dwell, recycled @ 1..)) = self.dwell.zip(recycled) {
+            dwell.kernel.record(now.wrapping_sub(recycled));
+        }
+        FrameStamp {
+            recycled_cycles: recycled.unwrap_or(now),
+            rx_cycles: now,
+        }
+    }
+
+    /// Record that the packet loop was done with the frame at `addr` at
+    /// cycle `now`; it waits to be refilled from here.
+    #[inline(always)]
+    pub fn processed(&mut self, addr: u64, now: u64) {
+        let Some(dwell) = self.dwell else { return };
+        let index = (addr / self.frame_size) as usize;
+        let (Some(&received), Some(processed)) =
+            (self.received.get(index), self.processed.get_mut(index))
+        else {
+            return;
+        };
+        if received != 0 {
+            dwell.processing.record(now.wrapping_sub(received));
+        }
+        *processed = now.max(1);
+    }
+
+    /// Stamps of the frame at `addr` as last recorded.
+    pub fn stamp(&self, addr: u64) -> Option<FrameStamp> {
+        let index = (addr / self.frame_size) as usize;
+        Some(FrameStamp {
+            recycled_cycles: *self.recycled.get(index)?,
+            rx_cycles: *self.received.get(index)?,
+        })
+    }
+
+    /// Whether a frame stamped `stamp` is too old to evaluate at `now`.
+    /// Rejections are counted in [`stale`](Self::stale).
+    #[inline(always)]
+    pub fn reject(&mut self, stamp: FrameStamp, now: u64) -> bool {
+        let stale = self.max_age_cycles > 0 && stamp.min_age(now) > self.max_age_cycles;
+        self.stale += stale as u64;
+        stale
     }
 }
 
@@ -123,17 +341,24 @@ pub use linux_impl::*;
 
 #[cfg(target_os = "linux")]
 mod linux_impl {
-    use super::{UmemConfig, XdpConfig, XdpRingDescriptor};
+    use super::{FrameAges, UmemConfig, XdpConfig, XdpRingDescriptor};
+    use core::sync::atomic::{AtomicU32, Ordering};
 
     // Linux kernel constants for AF_XDP
     const AF_XDP: i32 = 44;
     const SOL_XDP: i32 = 283;
-    const XDP_UMEM_REG: i32 = 5;
-    const XDP_UMEM_FILL_RING: i32 = 6;
-    const XDP_UMEM_COMPLETION_RING: i32 = 7;
-    const XDP_RX_RING: i32 = 1;
-    const XDP_TX_RING: i32 = 2;
-    const XDP_MMAP_OFFSETS: i32 = 3;
+    const XDP_MMAP_OFFSETS: i32 = 1;
+    const XDP_RX_RING: i32 = 2;
+    const XDP_TX_RING: i32 = 3;
+    const XDP_UMEM_REG: i32 = 4;
+    const XDP_UMEM_FILL_RING: i32 = 5;
+    const XDP_UMEM_COMPLETION_RING: i32 = 6;
+    const XDP_USE_NEED_WAKEUP: u16 = 1 << 3;
+    const XDP_RING_NEED_WAKEUP: u32 = 1 << 0;
+    const XDP_PGOFF_RX_RING: libc::off_t = 0;
+    const XDP_PGOFF_TX_RING: libc::off_t = 0x8000_0000;
+    const XDP_UMEM_PGOFF_FILL_RING: libc::off_t = 0x1_0000_0000;
+    const XDP_UMEM_PGOFF_COMPLETION_RING: libc::off_t = 0x1_8000_0000;
 
     /// Registered UMEM region — mmap'd memory shared with the kernel.
     ///
@@ -201,6 +426,7 @@ mod linux_impl {
                 chunk_size: u32,
                 headroom: u32,
                 flags: u32,
+                tx_metadata_len: u32,
             }
             let reg = XdpUmemReg {
                 addr: ptr as u64,
@@ -208,6 +434,7 @@ mod linux_impl {
                 chunk_size: config.frame_size,
                 headroom: 0,
                 flags: 0,
+                tx_metadata_len: 0,
             };
             let rc = unsafe {
                 libc::setsockopt(
@@ -249,19 +476,48 @@ mod linux_impl {
                 )
             };
 
-            Ok(Self { ptr: ptr as *mut u8, size, config, fd })
+            Ok(Self {
+                ptr: ptr as *mut u8,
+                size,
+                config,
+                fd,
+            })
         }
 
         /// Return a mutable slice for the frame at `frame_index`.
         /// Panics if the index is out of bounds.
+        ///
+        /// # Safety
+        /// The caller must ensure the kernel does not own the frame (i.e. it is
+        /// not currently posted on the Fill or TX ring).
         #[inline(always)]
         pub unsafe fn frame_mut(&mut self, frame_index: u32) -> &mut [u8] {
             assert!((frame_index as usize) < self.config.frame_count as usize);
             let offset = frame_index as usize * self.config.frame_size as usize;
-            core::slice::from_raw_parts_mut(
-                self.ptr.add(offset),
-                self.config.frame_size as usize,
-            )
+            core::slice::from_raw_parts_mut(self.ptr.add(offset), self.config.frame_size as usize)
+        }
+
+        /// The packet `desc` describes, borrowed from the UMEM; empty if the
+        /// descriptor points outside it.
+        #[inline(always)]
+        pub fn packet(&self, desc: &XdpRingDescriptor) -> &[u8] {
+            let (start, len) = (desc.addr as usize, desc.len as usize);
+            if start.checked_add(len).is_none_or(|end| end > self.size) {
+                return &[];
+            }
+            // SAFETY: in bounds; the kernel handed the frame to userspace.
+            unsafe { core::slice::from_raw_parts(self.ptr.add(start), len) }
+        }
+
+        /// From `addr` to the end of its frame, for writing a packet in place.
+        ///
+        /// # Safety
+        /// As for [`frame_mut`](Self::frame_mut).
+        #[inline(always)]
+        pub unsafe fn packet_mut(&mut self, addr: u64) -> &mut [u8] {
+            let frame_size = self.config.frame_size as u64;
+            let frame = self.frame_mut((addr / frame_size) as u32);
+            &mut frame[(addr % frame_size) as usize..]
         }
     }
 
@@ -277,50 +533,193 @@ mod linux_impl {
     // Safety: XdpUmem is only used from a single pinned thread.
     unsafe impl Send for XdpUmem {}
 
-    /// High-level AF_XDP socket with bound UMEM and sized rings.
+    /// One of the four rings shared with the kernel, `mmap`'d from the
+    /// socket at the offsets `getsockopt(XDP_MMAP_OFFSETS)` reports.
     ///
-    /// After calling `bind()`, packets arrive in the RX ring. Each descriptor
+    /// The Fill and TX rings are produced by userspace ([`push`](Self::push)),
+    /// the RX and Completion rings by the kernel ([`pop`](Self::pop)). Either
+    /// side only ever moves its own index: entries are written before the
+    /// producer index is published with `Release`, and read after it is
+    /// loaded with `Acquire`.
+    struct Ring<T> {
+        producer: *const AtomicU32,
+        consumer: *const AtomicU32,
+        flags: *const AtomicU32,
+        entries: *mut T,
+        mask: u32,
+        map: *mut libc::c_void,
+        map_len: usize,
+    }
+
+    impl<T: Copy> Ring<T> {
+        /// Map the ring of `size` entries at page offset `pgoff` of `fd`.
+        fn map(
+            fd: i32,
+            offsets: &XdpRingOffset,
+            size: u32,
+            pgoff: libc::off_t,
+        ) -> Result<Self, XdpError> {
+            let map_len = offsets.desc as usize + size as usize * core::mem::size_of::<T>();
+            let map = unsafe {
+                libc::mmap(
+                    core::ptr::null_mut(),
+                    map_len,
+                    libc::PROT_READ | libc::PROT_WRITE,
+                    libc::MAP_SHARED | libc::MAP_POPULATE,
+                    fd,
+                    pgoff,
+                )
+            };
+            if map == libc::MAP_FAILED {
+                return Err(XdpError::RingMmap(unsafe { *libc::__errno_location() }));
+            }
+            let at = |offset: u64| unsafe { (map as *mut u8).add(offset as usize) };
+            Ok(Self {
+                producer: at(offsets.producer) as *const AtomicU32,
+                consumer: at(offsets.consumer) as *const AtomicU32,
+                flags: at(offsets.flags) as *const AtomicU32,
+                entries: at(offsets.desc) as *mut T,
+                mask: size - 1,
+                map,
+                map_len,
+            })
+        }
+
+        #[inline(always)]
+        fn index(&self, ptr: *const AtomicU32) -> &AtomicU32 {
+            // SAFETY: points into the mapping, which lives as long as self.
+            unsafe { &*ptr }
+        }
+
+        /// Free entries of a userspace-produced ring.
+        #[inline(always)]
+        fn room(&self) -> usize {
+            let produced = self.index(self.producer).load(Ordering::Relaxed);
+            let consumed = self.index(self.consumer).load(Ordering::Acquire);
+            (self.mask + 1 - produced.wrapping_sub(consumed)) as usize
+        }
+
+        /// Produce as many of `items` as fit; returns how many.
+        #[inline(always)]
+        fn push(&mut self, items: &[T]) -> usize {
+            let produced = self.index(self.producer).load(Ordering::Relaxed);
+            let n = items.len().min(self.room());
+            for (k, item) in items[..n].iter().enumerate() {
+                let slot = produced.wrapping_add(k as u32) & self.mask;
+                // SAFETY: `slot` is within the ring; the kernel does not read
+                // it until the producer index below covers it.
+                unsafe { self.entries.add(slot as usize).write(*item) };
+            }
+            self.index(self.producer)
+                .store(produced.wrapping_add(n as u32), Ordering::Release);
+            n
+        }
+
+        /// Consume up to `out.len()` entries of a kernel-produced ring.
+        #[inline(always)]
+        fn pop(&mut self, out: &mut [T]) -> usize {
+            let consumed = self.index(self.consumer).load(Ordering::Relaxed);
+            let ready = self
+                .index(self.producer)
+                .load(Ordering::Acquire)
+                .wrapping_sub(consumed);
+            let n = out.len().min(ready as usize);
+            for (k, entry) in out[..n].iter_mut().enumerate() {
+                let slot = consumed.wrapping_add(k as u32) & self.mask;
+                // SAFETY: published by the kernel and not reused until the
+                // consumer index below passes it.
+                *entry = unsafe { self.entries.add(slot as usize).read() };
+            }
+            self.index(self.consumer)
+                .store(consumed.wrapping_add(n as u32), Ordering::Release);
+            n
+        }
+
+        /// Whether the kernel asks for a syscall to process this ring
+        /// (`XDP_USE_NEED_WAKEUP`).
+        #[inline(always)]
+        fn needs_wakeup(&self) -> bool {
+            self.index(self.flags).load(Ordering::Relaxed) & XDP_RING_NEED_WAKEUP != 0
+        }
+    }
+
+    impl<T> Drop for Ring<T> {
+        fn drop(&mut self) {
+            unsafe { libc::munmap(self.map, self.map_len) };
+        }
+    }
+
+    /// `struct xdp_ring_offset`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct XdpRingOffset {
+        producer: u64,
+        consumer: u64,
+        desc: u64,
+        flags: u64,
+    }
+
+    /// `struct xdp_mmap_offsets`.
+    #[repr(C)]
+    #[derive(Default)]
+    struct XdpMmapOffsets {
+        rx: XdpRingOffset,
+        tx: XdpRingOffset,
+        fill: XdpRingOffset,
+        completion: XdpRingOffset,
+    }
+
+    /// High-level AF_XDP socket with bound UMEM and mapped rings.
+    ///
+    /// After `open`, packets arrive in the RX ring. Each descriptor
     /// contains `(addr, len)` pointing into the UMEM. The caller reads
-    /// `umem.frame_at(addr)` — zero-copy — and recycles the frame via Fill ring.
+    /// `umem.packet(&desc)` — zero-copy — then either queues the frame for
+    /// TX or recycles it to the Fill ring; frames sent come back on the
+    /// Completion ring.
     pub struct XdpSocket {
         pub fd: i32,
         pub config: XdpConfig,
+        rx: Ring<XdpRingDescriptor>,
+        tx: Ring<XdpRingDescriptor>,
+        fill: Ring<u64>,
+        completion: Ring<u64>,
     }
 
     impl XdpSocket {
-        /// Create a new AF_XDP socket, configure RX/TX rings, and bind to
-        /// the given interface queue.
+        /// Bind the UMEM's socket to the given interface queue, with RX/TX
+        /// rings, and map all four rings.
         ///
         /// Requires `CAP_NET_ADMIN` (or `CAP_BPF` on newer kernels).
         pub fn open(cfg: XdpConfig, umem: &XdpUmem) -> Result<Self, XdpError> {
-            // Open a new socket for the XSK (separate from UMEM's socket)
-            let fd = unsafe { libc::socket(AF_XDP, libc::SOCK_RAW, 0) };
+            // The UMEM is registered on its own socket, which becomes the XSK.
+            // A duplicate descriptor keeps it open while either owner lives.
+            let fd = unsafe { libc::dup(umem.fd) };
             if fd < 0 {
                 return Err(XdpError::SocketOpen(unsafe { *libc::__errno_location() }));
             }
+            match Self::bind(fd, cfg, umem) {
+                Ok(socket) => Ok(socket),
+                Err(e) => {
+                    unsafe { libc::close(fd) };
+                    Err(e)
+                }
+            }
+        }
 
-            // Size the RX ring
+        fn bind(fd: i32, cfg: XdpConfig, umem: &XdpUmem) -> Result<Self, XdpError> {
+            // Size the RX and TX rings
             let ring_size = umem.config.rx_tx_ring_size as i32;
-            let _ = unsafe {
-                libc::setsockopt(
-                    fd,
-                    SOL_XDP,
-                    XDP_RX_RING,
-                    &ring_size as *const _ as *const libc::c_void,
-                    core::mem::size_of::<i32>() as libc::socklen_t,
-                )
-            };
-
-            // Size the TX ring
-            let _ = unsafe {
-                libc::setsockopt(
-                    fd,
-                    SOL_XDP,
-                    XDP_TX_RING,
-                    &ring_size as *const _ as *const libc::c_void,
-                    core::mem::size_of::<i32>() as libc::socklen_t,
-                )
-            };
+            for ring in [XDP_RX_RING, XDP_TX_RING] {
+                let _ = unsafe {
+                    libc::setsockopt(
+                        fd,
+                        SOL_XDP,
+                        ring,
+                        &ring_size as *const _ as *const libc::c_void,
+                        core::mem::size_of::<i32>() as libc::socklen_t,
+                    )
+                };
+            }
 
             // Bind the socket to the interface + queue
             #[repr(C)]
@@ -331,27 +730,26 @@ mod linux_impl {
                 sxdp_queue_id: u32,
                 sxdp_shared_umem_fd: u32,
             }
-            let ifindex = unsafe {
-                libc::if_nametoindex(
-                    cfg.interface.as_ptr() as *const libc::c_char,
-                )
-            };
+            let ifindex = unsafe { libc::if_nametoindex(cfg.interface.as_c_str().as_ptr()) };
             if ifindex == 0 {
-                unsafe { libc::close(fd) };
                 return Err(XdpError::IfNotFound);
             }
 
-            // XDP_USE_NEED_WAKEUP = 8; XDP_COPY (generic) = 2; XDP_ZEROCOPY (native) = 4
-            let flags: u16 = match cfg.mode {
-                super::XdpMode::Native => 4,  // XDP_ZEROCOPY
-                super::XdpMode::Generic => 2, // XDP_COPY (fallback)
-            };
+            // XDP_USE_NEED_WAKEUP = 8; XDP_COPY (generic) = 2. Native mode
+            // leaves the choice to the kernel: zero-copy where the driver
+            // supports it (XDP_ZEROCOPY would fail elsewhere, e.g. on veth),
+            // copy otherwise.
+            let flags: u16 = XDP_USE_NEED_WAKEUP
+                | match cfg.mode {
+                    super::XdpMode::Native => 0,
+                    super::XdpMode::Generic => 2, // XDP_COPY (fallback)
+                };
             let sa = SockaddrXdp {
                 sxdp_family: AF_XDP as u16,
                 sxdp_flags: flags,
                 sxdp_ifindex: ifindex,
                 sxdp_queue_id: cfg.queue_id,
-                sxdp_shared_umem_fd: umem.fd as u32,
+                sxdp_shared_umem_fd: 0,
             };
             let rc = unsafe {
                 libc::bind(
@@ -361,39 +759,126 @@ mod linux_impl {
                 )
             };
             if rc != 0 {
-                unsafe { libc::close(fd) };
                 return Err(XdpError::BindFailed(unsafe { *libc::__errno_location() }));
             }
 
+            let mut offsets = XdpMmapOffsets::default();
+            let mut len = core::mem::size_of::<XdpMmapOffsets>() as libc::socklen_t;
+            let rc = unsafe {
+                libc::getsockopt(
+                    fd,
+                    SOL_XDP,
+                    XDP_MMAP_OFFSETS,
+                    &mut offsets as *mut _ as *mut libc::c_void,
+                    &mut len,
+                )
+            };
+            if rc != 0 {
+                return Err(XdpError::RingMmap(unsafe { *libc::__errno_location() }));
+            }
+            let (rx_tx, fill_completion) =
+                (umem.config.rx_tx_ring_size, umem.config.fill_ring_size);
+            let socket = Self {
+                fd,
+                config: cfg,
+                rx: Ring::map(fd, &offsets.rx, rx_tx, XDP_PGOFF_RX_RING)?,
+                tx: Ring::map(fd, &offsets.tx, rx_tx, XDP_PGOFF_TX_RING)?,
+                fill: Ring::map(fd, &offsets.fill, fill_completion, XDP_UMEM_PGOFF_FILL_RING)?,
+                completion: Ring::map(
+                    fd,
+                    &offsets.completion,
+                    fill_completion,
+                    XDP_UMEM_PGOFF_COMPLETION_RING,
+                )?,
+            };
+
             log::info!(
                 "AF_XDP socket bound: iface={} queue={} mode={:?} fd={}",
-                cfg.interface, cfg.queue_id, cfg.mode, fd
+                cfg.interface,
+                cfg.queue_id,
+                cfg.mode,
+                fd
             );
-            Ok(Self { fd, config: cfg })
+            Ok(socket)
         }
 
-        /// Poll the RX ring for a received frame descriptor (non-blocking).
+        /// Take up to `out.len()` received descriptors off the RX ring
+        /// (non-blocking); returns how many.
         ///
-        /// Returns `Some(desc)` if a frame is available in the ring, `None` if empty.
+        /// The caller reads `umem.packet(&desc)` and then hands the frame back:
+        /// to the Fill ring, or to the TX ring with a reply written in place.
         /// In production, this is called in a tight loop on a pinned CPU core.
-        ///
-        /// The caller must read `umem.frame_at(desc.addr)[..desc.len]` and then
-        /// recycle the frame by writing `desc.addr` back to the Fill ring.
         #[inline(always)]
-        pub fn poll_rx(&self, rx_ring_ptr: *mut XdpRingDescriptor, rx_idx: &mut u32, ring_size: u32) -> Option<XdpRingDescriptor> {
-            // In real AF_XDP usage the ring pointers are mmap'd from the kernel
-            // via getsockopt(XDP_MMAP_OFFSETS) + mmap(fd, offset=XDP_PGOFF_RX_RING).
-            // Here we read from the pre-mapped ring pointer.
-            let mask = ring_size - 1;
-            let desc = unsafe {
-                let slot = rx_ring_ptr.add((*rx_idx & mask) as usize);
-                *slot
-            };
-            if desc.len == 0 {
-                return None; // ring empty
+        pub fn poll_rx(&mut self, out: &mut [XdpRingDescriptor]) -> usize {
+            let n = self.rx.pop(out);
+            if n == 0 && self.fill.needs_wakeup() {
+                // The driver waits for a syscall before it takes Fill entries.
+                let null = core::ptr::null_mut();
+                unsafe {
+                    libc::recvfrom(self.fd, null, 0, libc::MSG_DONTWAIT, null as _, null as _)
+                };
+            }
+            n
+        }
+
+        /// [`poll_rx`](Self::poll_rx), stamping each descriptor in `ages` at
+        /// cycle `now`.
+        #[inline(always)]
+        pub fn poll_rx_stamped(
+            &mut self,
+            out: &mut [XdpRingDescriptor],
+            ages: &mut FrameAges,
+            now: u64,
+        ) -> usize {
+            let n = self.poll_rx(out);
+            for desc in &out[..n] {
+                ages.received(desc, now);
+            }
+            n
+        }
+
+        /// Free entries of the Fill ring.
+        #[inline(always)]
+        pub fn fill_room(&self) -> usize {
+            self.fill.room()
+        }
+
+        /// Post empty frames (UMEM offsets of their start) to the Fill ring;
+        /// returns how many fit.
+        #[inline(always)]
+        pub fn fill(&mut self, addrs: &[u64]) -> usize {
+            self.fill.push(addrs)
+        }
+
+        /// Queue frames for transmission; returns how many fit. They are sent
+        /// once [`kick_tx`](Self::kick_tx) runs, or the driver next polls.
+        #[inline(always)]
+        pub fn send(&mut self, descs: &[XdpRingDescriptor]) -> usize {
+            self.tx.push(descs)
+        }
+
+        /// Wake the driver for queued TX descriptors, if it asks for it.
+        #[inline(always)]
+        pub fn kick_tx(&self) {
+            if self.tx.needs_wakeup() {
+                unsafe {
+                    libc::sendto(
+                        self.fd,
+                        core::ptr::null(),
+                        0,
+                        libc::MSG_DONTWAIT,
+                        core::ptr::null(),
+                        0,
+                    )
+                };
             }
-            *rx_idx = rx_idx.wrapping_add(1);
-            Some(desc)
+        }
+
+        /// Take up to `out.len()` sent frames off the Completion ring;
+        /// returns how many. Each is free to reuse.
+        #[inline(always)]
+        pub fn completed(&mut self, out: &mut [u64]) -> usize {
+            self.completion.pop(out)
         }
     }
 
@@ -412,6 +897,8 @@ mod linux_impl {
         UmemReg(i32),
         IfNotFound,
         BindFailed(i32),
+        /// `XDP_MMAP_OFFSETS` or a ring `mmap` failed.
+        RingMmap(i32),
     }
 
     impl core::fmt::Display for XdpError {
@@ -423,10 +910,13 @@ mod linux_impl {
                 Self::UmemReg(e) => write!(f, "XDP_UMEM_REG setsockopt failed (errno={})", e),
                 Self::IfNotFound => write!(f, "network interface not found"),
                 Self::BindFailed(e) => write!(f, "AF_XDP bind failed (errno={})", e),
+                Self::RingMmap(e) => write!(f, "AF_XDP ring mmap failed (errno={})", e),
             }
         }
     }
 
+    impl std::error::Error for XdpError {}
+
     /// Probe whether the running kernel supports AF_XDP sockets.
     ///
     /// Opens and immediately closes an AF_XDP socket. Does not allocate UMEM
@@ -439,6 +929,40 @@ mod linux_impl {
         let _ = unsafe { libc::close(fd) };
         true
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::os::fd::AsRawFd;
+
+        #[test]
+        fn rings_hand_entries_across_and_wrap() {
+            // Shared zeroed memory stands in for the kernel's mapping; the
+            // ring is its own consumer.
+            let zero = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open("/dev/zero")
+                .unwrap();
+            let offsets = XdpRingOffset {
+                producer: 0,
+                consumer: 64,
+                desc: 128,
+                flags: 192,
+            };
+            let mut ring: Ring<u64> = Ring::map(zero.as_raw_fd(), &offsets, 4, 0).unwrap();
+            assert_eq!(ring.push(&[1, 2, 3]), 3);
+            let mut out = [0; 4];
+            assert_eq!(ring.pop(&mut out[..2]), 2);
+            assert_eq!(&out[..2], [1, 2]);
+            assert_eq!(ring.room(), 3);
+            assert_eq!(ring.push(&[4, 5, 6, 7]), 3, "one slot still holds 3");
+            assert_eq!(ring.pop(&mut out), 4);
+            assert_eq!(out, [3, 4, 5, 6]);
+            assert_eq!(ring.pop(&mut out), 0);
+            assert!(!ring.needs_wakeup());
+        }
+    }
 }
 
 // ─── Non-Linux stub ───────────────────────────────────────────────────────────
@@ -447,3 +971,79 @@ mod linux_impl {
 pub fn probe_af_xdp_socket() -> bool {
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interface_names_are_stored_nul_terminated() {
+        let name = InterfaceName::new("enp59s0f0np0").unwrap();
+        assert_eq!(name.as_str(), "enp59s0f0np0");
+        assert_eq!(name.as_c_str().to_bytes_with_nul(), b"enp59s0f0np0\0");
+        assert_eq!(InterfaceName::DEFAULT.to_string(), "veth0");
+        let longest = "a".repeat(MAX_INTERFACE_NAME);
+        assert_eq!(
+            InterfaceName::new(&longest).map(|n| n.as_c_str().to_bytes().len()),
+            Some(MAX_INTERFACE_NAME)
+        );
+        assert!(InterfaceName::new(&format!("{longest}a")).is_none());
+        assert!(InterfaceName::new("").is_none() && InterfaceName::new("eth\0x").is_none());
+    }
+
+    #[test]
+    fn rejects_frames_known_to_be_too_old() {
+        let umem = UmemConfig {
+            frame_count: 4,
+            frame_size: 4096,
+            ..UmemConfig::default()
+        };
+        let mut ages = FrameAges::new(&umem, 1_000);
+        ages.recycled(4096, 100);
+        // Offsets inside the frame (headroom) map to the same frame.
+        let stamp = ages.received(&XdpRingDescriptor::new(4096 + 256, 64), 5_000);
+        assert_eq!(
+            stamp,
+            FrameStamp {
+                recycled_cycles: 100,
+                rx_cycles: 5_000
+            }
+        );
+        assert_eq!(ages.stamp(4096), Some(stamp));
+        // Possibly up to 5,400 cycles old, but only known to be 400: kept.
+        assert!(!ages.reject(stamp, 5_400));
+        assert!(ages.reject(stamp, 6_001));
+        assert_eq!(ages.stale, 1);
+        assert!(!FrameAges::new(&umem, 0).reject(stamp, u64::MAX));
+        assert_eq!(ages.stamp(4 * 4096), None);
+    }
+
+    #[test]
+    fn records_how_long_frames_dwell_in_each_leg() {
+        let dwell: &'static FrameDwell = Box::leak(Box::new(FrameDwell::new()));
+        let umem = UmemConfig {
+            frame_count: 2,
+            frame_size: 4096,
+            ..UmemConfig::default()
+        };
+        let mut ages = FrameAges::new(&umem, 0).with_dwell(dwell);
+        // First posted at startup: the refill leg starts with the first RX.
+        ages.recycled(0, 1_000);
+        ages.received(&XdpRingDescriptor::new(0, 64), 1_500);
+        ages.processed(0, 1_600);
+        ages.recycled(0, 1_900);
+        // Never stamped: its kernel leg is unknown.
+        ages.received(&XdpRingDescriptor::new(4096, 64), 2_000);
+        let legs = dwell
+            .legs()
+            .map(|(name, h)| (name, h.snapshot().count(), h.snapshot().percentile(100)));
+        assert_eq!(
+            legs,
+            [
+                ("kernel", 1, 511),
+                ("processing", 1, 127),
+                ("refill", 1, 511)
+            ]
+        );
+    }
+}